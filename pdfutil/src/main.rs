@@ -81,9 +81,7 @@ fn main() {
 				"extract_pages" => {
 					if let Some(pages) = args.value_of("pages") {
 						let page_numbers = compute_page_numbers(pages);
-						let total = *doc.get_pages().keys().max().unwrap_or(&0);
-						let page_numbers = complement_page_numbers(&page_numbers, total);
-						doc.delete_pages(&page_numbers);
+						doc = doc.extract_pages(page_numbers).unwrap();
 					}
 				}
 				"delete_pages" => {
@@ -182,14 +180,4 @@ fn main() {
 		}
 		page_numbers
 	}
-
-	fn complement_page_numbers(pages: &[u32], total: u32) -> Vec<u32> {
-		let mut page_numbers = vec![];
-		for page in 1..(total + 1) {
-			if !pages.contains(&page) {
-				page_numbers.push(page);
-			}
-		}
-		page_numbers
-	}
 }
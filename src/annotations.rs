@@ -0,0 +1,745 @@
+use crate::content::ContentBuilder;
+use crate::encodings::{decode_text_string, encode_text_string};
+use crate::forms::{text_width, DefaultAppearance};
+use crate::{xobject, Dictionary, Document, Matrix, Object, ObjectId, Rectangle, Result, StringFormat};
+
+/// Annotation of a page, with the entries common to all annotation types.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    /// Id of the annotation dictionary, if it's an indirect object.
+    pub id: Option<ObjectId>,
+    pub kind: AnnotationKind,
+    pub rect: Rectangle,
+    /// Text displayed for the annotation, or its alternate description.
+    pub contents: Option<String>,
+    /// Color components, with one, three or four components for gray, RGB or CMYK.
+    pub color: Option<Vec<f64>>,
+    /// Author of the annotation, given by its /T entry.
+    pub author: Option<String>,
+    pub border: Option<Border>,
+    pub flags: u32,
+}
+
+/// Type of an annotation, with the entries specific to it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnnotationKind {
+    /// Sticky note, shown as an icon.
+    Text {
+        open: bool,
+        icon: Option<String>,
+    },
+    /// Text displayed directly on the page, using a default appearance string.
+    FreeText {
+        default_appearance: String,
+    },
+    Square {
+        interior_color: Option<Vec<f64>>,
+    },
+    Circle {
+        interior_color: Option<Vec<f64>>,
+    },
+    /// Highlighted text, covering the quadrilaterals given by eight coordinates each.
+    Highlight {
+        quad_points: Vec<f64>,
+    },
+    /// Freehand drawing, as a list of strokes of x, y coordinate pairs.
+    Ink {
+        ink_list: Vec<Vec<f64>>,
+    },
+    /// Rubber stamp, shown as a predefined icon such as Approved or Draft.
+    Stamp {
+        name: String,
+    },
+    /// Area which opens a URI or goes to a destination when clicked.
+    Link {
+        target: Option<LinkTarget>,
+    },
+    /// Annotation of any other type, given by its subtype.
+    Other(String),
+}
+
+/// What happens when a link is clicked.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinkTarget {
+    /// URI action, opening a web page or another resource.
+    Uri(String),
+    /// Explicit destination array, such as `[page /Fit]`, or name of a named destination, given by /Dest or by
+    /// the /D entry of a GoTo action.
+    Destination(Object),
+    /// Any other action dictionary.
+    Action(Dictionary),
+}
+
+/// Border of an annotation, given by a /BS border style dictionary or else a /Border array.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Border {
+    /// Width in points, where zero means no border.
+    pub width: f64,
+    pub style: BorderStyle,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BorderStyle {
+    Solid,
+    /// Dashes and gaps of the given lengths.
+    Dashed(Vec<f64>),
+    Beveled,
+    Inset,
+    /// Single line along the bottom of the rectangle.
+    Underline,
+}
+
+impl Border {
+    pub fn new(width: f64, style: BorderStyle) -> Border {
+        Border { width, style }
+    }
+
+    /// Create a border which isn't drawn.
+    pub fn none() -> Border {
+        Border::new(0.0, BorderStyle::Solid)
+    }
+
+    fn from_dictionary(dict: &Dictionary) -> Option<Border> {
+        if let Ok(style) = dict.get(b"BS").and_then(Object::as_dict) {
+            let dashes = style.get(b"D").and_then(numbers).unwrap_or_else(|_| vec![3.0]);
+            let style_name = style.get(b"S").and_then(Object::as_name_str).unwrap_or("S");
+            return Some(Border {
+                width: style.get(b"W").and_then(Object::as_float).unwrap_or(1.0),
+                style: match style_name {
+                    "D" => BorderStyle::Dashed(dashes),
+                    "B" => BorderStyle::Beveled,
+                    "I" => BorderStyle::Inset,
+                    "U" => BorderStyle::Underline,
+                    _ => BorderStyle::Solid,
+                },
+            });
+        }
+        // Border array of horizontal and vertical corner radii, width and an optional dash array.
+        let border = dict.get(b"Border").and_then(Object::as_array).ok()?;
+        let width = border.get(2).and_then(|width| width.as_float().ok()).unwrap_or(1.0);
+        let style = match border.get(3).map(numbers) {
+            Some(Ok(dashes)) => BorderStyle::Dashed(dashes),
+            _ => BorderStyle::Solid,
+        };
+        Some(Border { width, style })
+    }
+
+    fn to_dictionary(&self) -> Dictionary {
+        let style = match self.style {
+            BorderStyle::Solid => "S",
+            BorderStyle::Dashed(_) => "D",
+            BorderStyle::Beveled => "B",
+            BorderStyle::Inset => "I",
+            BorderStyle::Underline => "U",
+        };
+        let mut dict = dictionary! {
+            "Type" => "Border",
+            "W" => self.width,
+            "S" => style,
+        };
+        if let BorderStyle::Dashed(ref dashes) = self.style {
+            dict.set("D", reals(dashes));
+        }
+        dict
+    }
+}
+
+impl Annotation {
+    pub const INVISIBLE: u32 = 1;
+    pub const HIDDEN: u32 = 1 << 1;
+    pub const PRINT: u32 = 1 << 2;
+    pub const NO_ZOOM: u32 = 1 << 3;
+    pub const NO_ROTATE: u32 = 1 << 4;
+    pub const NO_VIEW: u32 = 1 << 5;
+    pub const READ_ONLY: u32 = 1 << 6;
+    pub const LOCKED: u32 = 1 << 7;
+
+    /// Create a printable annotation of the given kind.
+    pub fn new(kind: AnnotationKind, rect: Rectangle) -> Annotation {
+        Annotation {
+            id: None,
+            kind,
+            rect,
+            contents: None,
+            color: None,
+            author: None,
+            border: None,
+            flags: Annotation::PRINT,
+        }
+    }
+
+    /// Create a sticky note with the given text.
+    pub fn text(rect: Rectangle, contents: &str) -> Annotation {
+        Annotation::new(
+            AnnotationKind::Text {
+                open: false,
+                icon: None,
+            },
+            rect,
+        )
+        .with_contents(contents)
+    }
+
+    /// Create a free text annotation showing the text in Helvetica of the given size.
+    pub fn free_text(rect: Rectangle, contents: &str, font_size: f64) -> Annotation {
+        let default_appearance = format!("/Helv {} Tf 0 g", font_size);
+        Annotation::new(AnnotationKind::FreeText { default_appearance }, rect).with_contents(contents)
+    }
+
+    pub fn square(rect: Rectangle) -> Annotation {
+        Annotation::new(AnnotationKind::Square { interior_color: None }, rect)
+    }
+
+    pub fn circle(rect: Rectangle) -> Annotation {
+        Annotation::new(AnnotationKind::Circle { interior_color: None }, rect)
+    }
+
+    /// Create a highlight covering the given rectangles, such as the bounding boxes of lines of text.
+    pub fn highlight(rects: &[Rectangle]) -> Annotation {
+        let quad_points = rects
+            .iter()
+            .flat_map(|rect| {
+                vec![
+                    rect.llx, rect.ury, rect.urx, rect.ury, rect.llx, rect.lly, rect.urx, rect.lly,
+                ]
+            })
+            .collect();
+        let bounds = rects.iter().fold(None, |bounds: Option<Rectangle>, rect| {
+            Some(match bounds {
+                Some(bounds) => Rectangle::new(
+                    bounds.llx.min(rect.llx),
+                    bounds.lly.min(rect.lly),
+                    bounds.urx.max(rect.urx),
+                    bounds.ury.max(rect.ury),
+                ),
+                None => *rect,
+            })
+        });
+        Annotation::new(AnnotationKind::Highlight { quad_points }, bounds.unwrap_or_default())
+    }
+
+    /// Create a freehand drawing from strokes of points.
+    pub fn ink(strokes: &[Vec<(f64, f64)>]) -> Annotation {
+        let points = strokes.iter().flatten();
+        let rect = Rectangle::new(
+            points.clone().map(|point| point.0).fold(f64::INFINITY, f64::min),
+            points.clone().map(|point| point.1).fold(f64::INFINITY, f64::min),
+            points.clone().map(|point| point.0).fold(f64::NEG_INFINITY, f64::max),
+            points.map(|point| point.1).fold(f64::NEG_INFINITY, f64::max),
+        );
+        let rect = if strokes.iter().all(Vec::is_empty) {
+            Rectangle::default()
+        } else {
+            // Leave room for the default line width of one.
+            Rectangle::new(rect.llx - 1.0, rect.lly - 1.0, rect.urx + 1.0, rect.ury + 1.0)
+        };
+        let ink_list = strokes
+            .iter()
+            .map(|stroke| stroke.iter().flat_map(|&(x, y)| vec![x, y]).collect())
+            .collect();
+        Annotation::new(AnnotationKind::Ink { ink_list }, rect)
+    }
+
+    /// Create a rubber stamp showing one of the standard icons, such as Approved, Draft or Confidential.
+    pub fn stamp(rect: Rectangle, name: &str) -> Annotation {
+        Annotation::new(AnnotationKind::Stamp { name: name.to_owned() }, rect)
+    }
+
+    /// Create a link without a border, which opens a URI or goes to a destination.
+    pub fn link(rect: Rectangle, target: LinkTarget) -> Annotation {
+        Annotation::new(AnnotationKind::Link { target: Some(target) }, rect).with_border(Border::none())
+    }
+
+    pub fn with_contents(mut self, contents: &str) -> Annotation {
+        self.contents = Some(contents.to_owned());
+        self
+    }
+
+    pub fn with_color(mut self, color: Vec<f64>) -> Annotation {
+        self.color = Some(color);
+        self
+    }
+
+    pub fn with_author(mut self, author: &str) -> Annotation {
+        self.author = Some(author.to_owned());
+        self
+    }
+
+    pub fn with_border(mut self, border: Border) -> Annotation {
+        self.border = Some(border);
+        self
+    }
+
+    /// Get the subtype of the annotation.
+    pub fn subtype(&self) -> &str {
+        match self.kind {
+            AnnotationKind::Text { .. } => "Text",
+            AnnotationKind::FreeText { .. } => "FreeText",
+            AnnotationKind::Square { .. } => "Square",
+            AnnotationKind::Circle { .. } => "Circle",
+            AnnotationKind::Highlight { .. } => "Highlight",
+            AnnotationKind::Ink { .. } => "Ink",
+            AnnotationKind::Stamp { .. } => "Stamp",
+            AnnotationKind::Link { .. } => "Link",
+            AnnotationKind::Other(ref subtype) => subtype,
+        }
+    }
+
+    /// Parse an annotation dictionary.
+    pub fn from_dictionary(dict: &Dictionary) -> Result<Annotation> {
+        let subtype = dict.get(b"Subtype").and_then(Object::as_name_str)?;
+        let name = |key: &[u8]| dict.get(key).and_then(Object::as_name_str).map(str::to_owned).ok();
+        let text = |key: &[u8]| dict.get(key).and_then(Object::as_str).map(decode_text_string).ok();
+        let number_array = |key: &[u8]| dict.get(key).ok().and_then(|array| numbers(array).ok());
+
+        let kind = match subtype {
+            "Text" => AnnotationKind::Text {
+                open: matches!(dict.get(b"Open"), Ok(Object::Boolean(true))),
+                icon: name(b"Name"),
+            },
+            "FreeText" => AnnotationKind::FreeText {
+                default_appearance: text(b"DA").unwrap_or_default(),
+            },
+            "Square" => AnnotationKind::Square {
+                interior_color: number_array(b"IC"),
+            },
+            "Circle" => AnnotationKind::Circle {
+                interior_color: number_array(b"IC"),
+            },
+            "Highlight" => AnnotationKind::Highlight {
+                quad_points: number_array(b"QuadPoints").unwrap_or_default(),
+            },
+            "Ink" => AnnotationKind::Ink {
+                ink_list: dict
+                    .get(b"InkList")
+                    .and_then(Object::as_array)
+                    .map(|strokes| strokes.iter().filter_map(|stroke| numbers(stroke).ok()).collect())
+                    .unwrap_or_default(),
+            },
+            "Stamp" => AnnotationKind::Stamp {
+                name: name(b"Name").unwrap_or_else(|| "Draft".to_owned()),
+            },
+            "Link" => {
+                let action = dict.get(b"A").and_then(Object::as_dict).ok();
+                let target = match (dict.get(b"Dest"), action) {
+                    (Ok(destination), _) => Some(LinkTarget::Destination(destination.clone())),
+                    (_, Some(action)) => match action.get(b"S").and_then(Object::as_name_str) {
+                        Ok("URI") => action
+                            .get(b"URI")
+                            .and_then(Object::as_str)
+                            .map(|uri| LinkTarget::Uri(String::from_utf8_lossy(uri).into_owned()))
+                            .ok(),
+                        Ok("GoTo") => action.get(b"D").cloned().map(LinkTarget::Destination).ok(),
+                        _ => Some(LinkTarget::Action(action.clone())),
+                    },
+                    _ => None,
+                };
+                AnnotationKind::Link { target }
+            }
+            _ => AnnotationKind::Other(subtype.to_owned()),
+        };
+
+        Ok(Annotation {
+            id: None,
+            kind,
+            rect: dict.get(b"Rect").and_then(Rectangle::from_object)?,
+            contents: text(b"Contents"),
+            color: number_array(b"C"),
+            author: text(b"T"),
+            border: Border::from_dictionary(dict),
+            flags: dict.get(b"F").and_then(Object::as_i64).unwrap_or(0) as u32,
+        })
+    }
+
+    /// Create the annotation dictionary.
+    pub fn to_dictionary(&self) -> Dictionary {
+        let mut dict = dictionary! {
+            "Type" => "Annot",
+            "Subtype" => self.subtype(),
+            "Rect" => self.rect,
+            "F" => self.flags as i64,
+        };
+        let text = |text: &str| Object::String(encode_text_string(text), StringFormat::Literal);
+        if let Some(ref contents) = self.contents {
+            dict.set("Contents", text(contents));
+        }
+        if let Some(ref color) = self.color {
+            dict.set("C", reals(color));
+        }
+        if let Some(ref author) = self.author {
+            dict.set("T", text(author));
+        }
+        if let Some(ref border) = self.border {
+            dict.set("BS", border.to_dictionary());
+        }
+
+        match self.kind {
+            AnnotationKind::Text { open, ref icon } => {
+                dict.set("Open", open);
+                if let Some(icon) = icon {
+                    dict.set("Name", Object::Name(icon.as_bytes().to_vec()));
+                }
+            }
+            AnnotationKind::FreeText { ref default_appearance } => {
+                dict.set("DA", text(default_appearance));
+            }
+            AnnotationKind::Square { ref interior_color } | AnnotationKind::Circle { ref interior_color } => {
+                if let Some(interior_color) = interior_color {
+                    dict.set("IC", reals(interior_color));
+                }
+            }
+            AnnotationKind::Highlight { ref quad_points } => {
+                dict.set("QuadPoints", reals(quad_points));
+            }
+            AnnotationKind::Ink { ref ink_list } => {
+                dict.set(
+                    "InkList",
+                    ink_list.iter().map(|stroke| reals(stroke)).collect::<Vec<_>>(),
+                );
+            }
+            AnnotationKind::Stamp { ref name } => {
+                dict.set("Name", Object::Name(name.as_bytes().to_vec()));
+            }
+            AnnotationKind::Link { ref target } => match target {
+                Some(LinkTarget::Uri(uri)) => dict.set(
+                    "A",
+                    dictionary! {
+                        "S" => "URI",
+                        "URI" => Object::string_literal(uri.as_bytes()),
+                    },
+                ),
+                Some(LinkTarget::Destination(destination)) => dict.set(
+                    "A",
+                    dictionary! {
+                        "S" => "GoTo",
+                        "D" => destination.clone(),
+                    },
+                ),
+                Some(LinkTarget::Action(action)) => dict.set("A", action.clone()),
+                None => {}
+            },
+            AnnotationKind::Other(_) => {}
+        }
+        dict
+    }
+}
+
+/// Add an ellipse inscribed in a rectangle to the path, approximated by four Bézier curves.
+fn ellipse(content: ContentBuilder, rect: &Rectangle) -> ContentBuilder {
+    // Distance of the control points from the ends of quarter circles of radius one.
+    const KAPPA: f64 = 0.552_284_75;
+    let (cx, cy) = ((rect.llx + rect.urx) / 2.0, (rect.lly + rect.ury) / 2.0);
+    let (rx, ry) = (rect.width() / 2.0, rect.height() / 2.0);
+    let (kx, ky) = (rx * KAPPA, ry * KAPPA);
+    content
+        .move_to(cx + rx, cy)
+        .curve_to(cx + rx, cy + ky, cx + kx, cy + ry, cx, cy + ry)
+        .curve_to(cx - kx, cy + ry, cx - rx, cy + ky, cx - rx, cy)
+        .curve_to(cx - rx, cy - ky, cx - kx, cy - ry, cx, cy - ry)
+        .curve_to(cx + kx, cy - ry, cx + rx, cy - ky, cx + rx, cy)
+        .close_path()
+}
+
+fn numbers(object: &Object) -> Result<Vec<f64>> {
+    object.as_array()?.iter().map(Object::as_float).collect()
+}
+
+fn reals(numbers: &[f64]) -> Object {
+    Object::Array(numbers.iter().map(|&number| Object::Real(number)).collect())
+}
+
+impl Document {
+    /// Get the annotations of a page.
+    pub fn get_page_annotations(&self, page_id: ObjectId) -> Result<Vec<Annotation>> {
+        let page = self.get_dictionary(page_id)?;
+        let annotations = match page.get(b"Annots") {
+            Ok(annotations) => self.dereference(annotations)?.1.as_array()?,
+            Err(_) => return Ok(Vec::new()),
+        };
+        Ok(annotations
+            .iter()
+            .filter_map(|annotation| {
+                let (id, annotation) = self.dereference(annotation).ok()?;
+                let mut annotation = Annotation::from_dictionary(annotation.as_dict().ok()?).ok()?;
+                annotation.id = id;
+                Some(annotation)
+            })
+            .collect())
+    }
+
+    /// Add an annotation to a page, returning the id of the new annotation dictionary.
+    pub fn add_annotation(&mut self, page_id: ObjectId, annotation: &Annotation) -> Result<ObjectId> {
+        let mut dict = annotation.to_dictionary();
+        dict.set("P", page_id);
+        let annotation_id = self.add_object(dict);
+        self.append_annotation(page_id, annotation_id)?;
+        Ok(annotation_id)
+    }
+
+    /// Append an annotation dictionary to the annotations of a page, creating them if missing.
+    pub(crate) fn append_annotation(&mut self, page_id: ObjectId, annotation_id: ObjectId) -> Result<()> {
+        let page = self.get_object_mut(page_id).and_then(Object::as_dict_mut)?;
+        let annotations = match page.get(b"Annots").and_then(Object::as_reference) {
+            Ok(annotations_id) => self.get_object_mut(annotations_id)?,
+            Err(_) => {
+                if !page.has(b"Annots") {
+                    page.set("Annots", Vec::new());
+                }
+                page.get_mut(b"Annots")?
+            }
+        };
+        annotations.as_array_mut()?.push(annotation_id.into());
+        Ok(())
+    }
+
+    /// Generate the normal appearance stream of a square, circle, highlight, ink, free text, stamp or text
+    /// annotation from its entries, replacing the existing appearance. Other annotations are left unchanged.
+    ///
+    /// The appearance is drawn in default user space, with the annotation rectangle as its bounding box.
+    pub fn generate_annotation_appearance(&mut self, annotation_id: ObjectId) -> Result<()> {
+        let annotation = Annotation::from_dictionary(self.get_dictionary(annotation_id)?)?;
+        let rect = annotation.rect.normalized();
+        let width = annotation.border.as_ref().map_or(1.0, |border| border.width);
+        let color = annotation.color.clone();
+        let stroke_color = color.clone().unwrap_or_else(|| vec![0.0]);
+        let mut resources = Dictionary::new();
+        let mut content = ContentBuilder::new().save_state();
+
+        match annotation.kind {
+            AnnotationKind::Square { ref interior_color } | AnnotationKind::Circle { ref interior_color } => {
+                let inset = Rectangle::new(
+                    rect.llx + width / 2.0,
+                    rect.lly + width / 2.0,
+                    rect.urx - width / 2.0,
+                    rect.ury - width / 2.0,
+                );
+                content = content.set_line_width(width).set_stroke_color(&stroke_color);
+                if let Some(interior_color) = interior_color {
+                    content = content.set_fill_color(interior_color);
+                }
+                content = if let AnnotationKind::Circle { .. } = annotation.kind {
+                    ellipse(content, &inset)
+                } else {
+                    content.rectangle(inset.llx, inset.lly, inset.width(), inset.height())
+                };
+                content = match (interior_color.is_some(), width > 0.0) {
+                    (true, true) => content.fill_and_stroke(),
+                    (true, false) => content.fill(),
+                    (false, true) => content.stroke(),
+                    (false, false) => content.end_path(),
+                };
+            }
+            AnnotationKind::Highlight { ref quad_points } => {
+                // Highlights darken the text under them instead of covering it.
+                resources.set(
+                    "ExtGState",
+                    dictionary! { "GS1" => dictionary! { "Type" => "ExtGState", "BM" => "Multiply" } },
+                );
+                content = content
+                    .set_graphics_state("GS1")
+                    .set_fill_color(color.as_deref().unwrap_or(&[1.0, 1.0, 0.0]));
+                for quad in quad_points.chunks_exact(8) {
+                    content = content
+                        .move_to(quad[4], quad[5])
+                        .line_to(quad[6], quad[7])
+                        .line_to(quad[2], quad[3])
+                        .line_to(quad[0], quad[1])
+                        .close_path();
+                }
+                content = content.fill();
+            }
+            AnnotationKind::Ink { ref ink_list } => {
+                content = content
+                    .set_line_width(width)
+                    .set_stroke_color(&stroke_color)
+                    // Round caps and joins.
+                    .operation("J", vec![1.into()])
+                    .operation("j", vec![1.into()]);
+                for stroke in ink_list {
+                    let mut points = stroke.chunks_exact(2);
+                    if let Some(first) = points.next() {
+                        content = content.move_to(first[0], first[1]);
+                        // Single points are drawn as dots.
+                        content = content.line_to(first[0], first[1]);
+                    }
+                    for point in points {
+                        content = content.line_to(point[0], point[1]);
+                    }
+                }
+                content = content.stroke();
+            }
+            AnnotationKind::FreeText { ref default_appearance } => {
+                let appearance = DefaultAppearance::parse(default_appearance);
+                let font_name = String::from_utf8_lossy(&appearance.font).into_owned();
+                let size = if appearance.size > 0.0 { appearance.size } else { 12.0 };
+                let font = dictionary! {
+                    "Type" => "Font",
+                    "Subtype" => "Type1",
+                    "BaseFont" => "Helvetica",
+                    "Encoding" => "WinAnsiEncoding",
+                };
+                let mut fonts = Dictionary::new();
+                fonts.set(appearance.font.clone(), font);
+                resources.set("Font", fonts);
+                content = content
+                    .rectangle(rect.llx, rect.lly, rect.width(), rect.height())
+                    .clip()
+                    .end_path()
+                    .begin_text()
+                    .set_font(&font_name, size)
+                    .set_fill_color(&[0.0]);
+                // The color operators of the default appearance override the default black.
+                for operation in appearance.operations {
+                    content = content.operation(&operation.operator, operation.operands);
+                }
+                let contents = annotation.contents.as_deref().unwrap_or("");
+                let leading = size * 1.15;
+                for (index, line) in contents.lines().enumerate() {
+                    let y = rect.ury - 2.0 - leading * (index + 1) as f64 + (leading - size) / 2.0 + 0.22 * size;
+                    content = content
+                        .set_text_matrix(Matrix::translate(rect.llx + 2.0, y))
+                        .show_text(Document::encode_text(Some("WinAnsiEncoding"), line));
+                }
+                content = content.end_text();
+            }
+            AnnotationKind::Stamp { ref name } => {
+                let stamp_color = color.clone().unwrap_or_else(|| vec![0.8, 0.1, 0.1]);
+                let font = dictionary! {
+                    "Type" => "Font",
+                    "Subtype" => "Type1",
+                    "BaseFont" => "Helvetica-Bold",
+                    "Encoding" => "WinAnsiEncoding",
+                };
+                let text = Document::encode_text(Some("WinAnsiEncoding"), &name.to_uppercase());
+                let text_width = text_width(&font, &text);
+                let size = (rect.height() * 0.5).min((rect.width() - 8.0) / text_width.max(0.1));
+                resources.set("Font", dictionary! { "F1" => font });
+                content = content
+                    .set_line_width(2.0)
+                    .set_stroke_color(&stamp_color)
+                    .rectangle(rect.llx + 1.0, rect.lly + 1.0, rect.width() - 2.0, rect.height() - 2.0)
+                    .stroke()
+                    .begin_text()
+                    .set_font("F1", size)
+                    .set_fill_color(&stamp_color)
+                    .set_text_matrix(Matrix::translate(
+                        rect.llx + (rect.width() - text_width * size) / 2.0,
+                        rect.lly + (rect.height() - 0.7 * size) / 2.0,
+                    ))
+                    .show_text(text)
+                    .end_text();
+            }
+            AnnotationKind::Text { .. } => {
+                // A note with lines of text, in the color of the annotation.
+                let note = color.clone().unwrap_or_else(|| vec![1.0, 0.9, 0.3]);
+                let side = rect.width().min(rect.height());
+                content = content
+                    .set_line_width(1.0)
+                    .set_fill_color(&note)
+                    .set_stroke_color(&[0.0])
+                    .rectangle(rect.llx + 0.5, rect.ury - side + 0.5, side - 1.0, side - 1.0)
+                    .fill_and_stroke();
+                for line in 1..4 {
+                    let y = rect.ury - side * line as f64 / 4.0;
+                    content = content
+                        .move_to(rect.llx + side * 0.2, y)
+                        .line_to(rect.llx + side * 0.8, y);
+                }
+                content = content.stroke();
+            }
+            _ => return Ok(()),
+        }
+
+        let content = content.restore_state().encode()?;
+        let mut form = xobject::form(
+            vec![rect.llx, rect.lly, rect.urx, rect.ury],
+            vec![1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+            content,
+        );
+        form.dict.set("Resources", resources);
+        let appearance_id = self.add_object(form);
+        let annotation = self.get_object_mut(annotation_id).and_then(Object::as_dict_mut)?;
+        annotation.set("AP", dictionary! { "N" => appearance_id });
+        annotation.remove(b"AS");
+        Ok(())
+    }
+
+    /// Add a link without a border over an area of a page, returning the id of the annotation dictionary.
+    pub fn add_link(&mut self, page_id: ObjectId, rect: Rectangle, target: LinkTarget) -> Result<ObjectId> {
+        self.add_annotation(page_id, &Annotation::link(rect, target))
+    }
+}
+
+#[test]
+fn add_and_read_annotations() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    let note = Annotation::text(Rectangle::new(50.0, 700.0, 70.0, 720.0), "Check this")
+        .with_author("Jane")
+        .with_color(vec![1.0, 1.0, 0.0]);
+    let note_id = doc.add_annotation(page_id, &note).unwrap();
+    let highlight = Annotation::highlight(&[
+        Rectangle::new(100.0, 600.0, 200.0, 612.0),
+        Rectangle::new(100.0, 586.0, 150.0, 598.0),
+    ]);
+    doc.add_annotation(page_id, &highlight).unwrap();
+    let ink = Annotation::ink(&[vec![(10.0, 10.0), (20.0, 30.0)], vec![(15.0, 5.0)]]);
+    doc.add_annotation(page_id, &ink).unwrap();
+    doc.add_annotation(
+        page_id,
+        &Annotation::stamp(Rectangle::new(300.0, 300.0, 400.0, 350.0), "Approved"),
+    )
+    .unwrap();
+
+    let annotations = doc.get_page_annotations(page_id).unwrap();
+    assert_eq!(annotations.len(), 4);
+    assert_eq!(annotations[0].id, Some(note_id));
+    assert_eq!(annotations[0].contents.as_deref(), Some("Check this"));
+    assert_eq!(annotations[0].author.as_deref(), Some("Jane"));
+    assert_eq!(annotations[0].kind, note.kind);
+    assert_eq!(annotations[1].rect, Rectangle::new(100.0, 586.0, 200.0, 612.0));
+    assert_eq!(annotations[1].kind, highlight.kind);
+    assert_eq!(annotations[2].rect, Rectangle::new(9.0, 4.0, 21.0, 31.0));
+    assert_eq!(annotations[2].kind, ink.kind);
+    assert_eq!(annotations[3].subtype(), "Stamp");
+    assert_eq!(annotations[3].flags, Annotation::PRINT);
+}
+
+#[test]
+fn add_and_read_links() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    let uri = LinkTarget::Uri("https://example.com/?a=1&b=2".to_owned());
+    doc.add_link(page_id, Rectangle::new(100.0, 700.0, 200.0, 712.0), uri.clone())
+        .unwrap();
+    let destination = LinkTarget::Destination(vec![page_id.into(), "Fit".into()].into());
+    let dashed = Border::new(2.0, BorderStyle::Dashed(vec![3.0, 1.0]));
+    let link = Annotation::link(Rectangle::new(100.0, 680.0, 200.0, 692.0), destination.clone()).with_border(dashed);
+    doc.add_annotation(page_id, &link).unwrap();
+    let named = LinkTarget::Destination(Object::string_literal("Chapter1"));
+    doc.add_link(page_id, Rectangle::new(100.0, 660.0, 200.0, 672.0), named.clone())
+        .unwrap();
+
+    let annotations = doc.get_page_annotations(page_id).unwrap();
+    assert_eq!(annotations.len(), 3);
+    assert_eq!(annotations[0].kind, AnnotationKind::Link { target: Some(uri) });
+    assert_eq!(annotations[0].border, Some(Border::none()));
+    assert_eq!(annotations[1].kind, link.kind);
+    assert_eq!(annotations[1].border, link.border);
+    assert_eq!(annotations[2].kind, AnnotationKind::Link { target: Some(named) });
+
+    // Links of other writers may only have a /Border array and a /Dest entry.
+    let mut dict = annotations[2].to_dictionary();
+    dict.remove(b"A");
+    dict.remove(b"BS");
+    dict.set("Dest", Object::Name(b"Chapter2".to_vec()));
+    dict.set("Border", vec![0.into(), 0.into(), 1.into()]);
+    let annotation = Annotation::from_dictionary(&dict).unwrap();
+    assert_eq!(annotation.border, Some(Border::new(1.0, BorderStyle::Solid)));
+    let target = LinkTarget::Destination(Object::Name(b"Chapter2".to_vec()));
+    assert_eq!(annotation.kind, AnnotationKind::Link { target: Some(target) });
+}
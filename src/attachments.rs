@@ -0,0 +1,472 @@
+use crate::encodings::{decode_text_string, encode_text_string};
+use crate::{Dictionary, Document, Error, Object, ObjectId, PdfDate, Result, Stream, StringFormat};
+
+/// Relationship of an associated file to the document, page or object it's associated with, as used by PDF/A-3
+/// and PDF 2.0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileRelationship {
+    /// Original source material of the content, such as the spreadsheet of a chart.
+    Source,
+    /// Data the content represents, such as the XML of an e-invoice.
+    Data,
+    /// Alternative representation of the content, such as audio.
+    Alternative,
+    /// Supplemental representation of the content, such as a MathML version of a formula.
+    Supplement,
+    EncryptedPayload,
+    FormData,
+    Schema,
+    Unspecified,
+}
+
+impl FileRelationship {
+    fn from_name(name: &[u8]) -> Option<FileRelationship> {
+        match name {
+            b"Source" => Some(FileRelationship::Source),
+            b"Data" => Some(FileRelationship::Data),
+            b"Alternative" => Some(FileRelationship::Alternative),
+            b"Supplement" => Some(FileRelationship::Supplement),
+            b"EncryptedPayload" => Some(FileRelationship::EncryptedPayload),
+            b"FormData" => Some(FileRelationship::FormData),
+            b"Schema" => Some(FileRelationship::Schema),
+            b"Unspecified" => Some(FileRelationship::Unspecified),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            FileRelationship::Source => "Source",
+            FileRelationship::Data => "Data",
+            FileRelationship::Alternative => "Alternative",
+            FileRelationship::Supplement => "Supplement",
+            FileRelationship::EncryptedPayload => "EncryptedPayload",
+            FileRelationship::FormData => "FormData",
+            FileRelationship::Schema => "Schema",
+            FileRelationship::Unspecified => "Unspecified",
+        }
+    }
+}
+
+/// File embedded in a document, listed in the /EmbeddedFiles name tree of the catalog.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attachment {
+    /// Name of the entry in the name tree.
+    pub name: String,
+    /// File name of the file specification, from /UF or /F.
+    pub file_name: String,
+    pub description: Option<String>,
+    /// MIME type of the embedded file stream, such as text/xml.
+    pub mime_type: Option<String>,
+    pub creation_date: Option<PdfDate>,
+    pub mod_date: Option<PdfDate>,
+    /// Size of the file as given by the embedded file stream.
+    pub size: Option<u64>,
+    /// Relationship of the file when it's an associated file.
+    pub relationship: Option<FileRelationship>,
+    /// Id of the file specification dictionary, unless it's a direct object of the name tree.
+    pub filespec_id: Option<ObjectId>,
+    /// Id of the embedded file stream.
+    pub file_id: ObjectId,
+}
+
+impl Attachment {
+    /// Get the decoded contents of the file.
+    pub fn data(&self, doc: &Document) -> Result<Vec<u8>> {
+        let stream = doc.get_object(self.file_id).and_then(Object::as_stream)?;
+        if stream.filters().is_err() {
+            return Ok(stream.content.clone());
+        }
+        stream.decompressed_content()
+    }
+}
+
+/// Options of `Document::add_attachment`.
+#[derive(Debug, Clone, Default)]
+pub struct AttachmentOptions {
+    pub description: Option<String>,
+    /// MIME type of the file, such as application/pdf.
+    pub mime_type: Option<String>,
+    pub creation_date: Option<PdfDate>,
+    pub mod_date: Option<PdfDate>,
+    /// Relationship of the file, which should be set for associated files.
+    pub relationship: Option<FileRelationship>,
+}
+
+fn text_string(text: &str) -> Object {
+    Object::String(encode_text_string(text), StringFormat::Literal)
+}
+
+impl Document {
+    /// Get the files embedded in the document, in the order of their names.
+    ///
+    /// Entries whose file specification has no embedded file stream are skipped.
+    pub fn attachments(&self) -> Result<Vec<Attachment>> {
+        let entries = self.get_name_tree(b"EmbeddedFiles")?;
+        Ok(entries
+            .into_iter()
+            .filter_map(|(name, filespec)| self.attachment(&name, &filespec))
+            .collect())
+    }
+
+    fn attachment(&self, name: &[u8], filespec: &Object) -> Option<Attachment> {
+        let (filespec_id, filespec) = self.dereference(filespec).ok()?;
+        let filespec = filespec.as_dict().ok()?;
+        let embedded_files = self.dereference(filespec.get(b"EF").ok()?).ok()?.1.as_dict().ok()?;
+        let file_id = [&b"UF"[..], b"F"]
+            .iter()
+            .find_map(|key| embedded_files.get(key).and_then(Object::as_reference).ok())?;
+        let stream = self.get_object(file_id).and_then(Object::as_stream).ok()?;
+        let params = stream
+            .dict
+            .get(b"Params")
+            .and_then(|params| self.dereference(params))
+            .and_then(|(_, params)| params.as_dict())
+            .ok();
+        let param = |key: &[u8]| params.and_then(|params| params.get(key).ok());
+        let text = |dict: &Dictionary, key: &[u8]| dict.get(key).and_then(Object::as_str).map(decode_text_string).ok();
+
+        Some(Attachment {
+            name: decode_text_string(name),
+            file_name: text(filespec, b"UF")
+                .or_else(|| text(filespec, b"F"))
+                .unwrap_or_default(),
+            description: text(filespec, b"Desc"),
+            mime_type: stream
+                .dict
+                .get(b"Subtype")
+                .and_then(Object::as_name_str)
+                .map(str::to_owned)
+                .ok(),
+            creation_date: param(b"CreationDate").and_then(|date| PdfDate::from_object(date).ok()),
+            mod_date: param(b"ModDate").and_then(|date| PdfDate::from_object(date).ok()),
+            size: param(b"Size")
+                .and_then(|size| size.as_i64().ok())
+                .map(|size| size.max(0) as u64),
+            relationship: filespec
+                .get(b"AFRelationship")
+                .and_then(Object::as_name)
+                .ok()
+                .and_then(FileRelationship::from_name),
+            filespec_id,
+            file_id,
+        })
+    }
+
+    /// Embed a file under a name, which is also its file name, replacing the entry of the same name if any.
+    /// Returns the id of the file specification.
+    pub fn add_attachment(&mut self, name: &str, data: Vec<u8>, options: &AttachmentOptions) -> Result<ObjectId> {
+        let mut params = dictionary! { "Size" => data.len() as i64 };
+        if let Some(date) = options.creation_date {
+            params.set("CreationDate", date);
+        }
+        if let Some(date) = options.mod_date {
+            params.set("ModDate", date);
+        }
+        let mut file = Stream::new(
+            dictionary! {
+                "Type" => "EmbeddedFile",
+                "Params" => params,
+            },
+            data,
+        );
+        if let Some(ref mime_type) = options.mime_type {
+            file.dict.set("Subtype", Object::Name(mime_type.as_bytes().to_vec()));
+        }
+        file.compress()?;
+        let file_id = self.add_object(file);
+
+        let mut filespec = dictionary! {
+            "Type" => "Filespec",
+            "F" => text_string(name),
+            "UF" => text_string(name),
+            "EF" => dictionary! { "F" => file_id, "UF" => file_id },
+        };
+        if let Some(ref description) = options.description {
+            filespec.set("Desc", text_string(description));
+        }
+        if let Some(relationship) = options.relationship {
+            filespec.set("AFRelationship", relationship.name());
+        }
+        let filespec_id = self.add_object(filespec);
+
+        if self.get_attachment(name).is_ok() {
+            self.remove_attachment(name)?;
+        }
+        let mut entries = self.get_name_tree(b"EmbeddedFiles")?;
+        entries.push((encode_text_string(name), Object::Reference(filespec_id)));
+        self.set_name_tree(b"EmbeddedFiles", entries)?;
+        Ok(filespec_id)
+    }
+
+    /// Remove the attachment of a name from the /EmbeddedFiles name tree, which is rebuilt without it, and delete
+    /// its file specification and file streams unless other objects, such as file attachment annotations, still
+    /// refer to them.
+    pub fn remove_attachment(&mut self, name: &str) -> Result<()> {
+        let attachment = self.get_attachment(name)?;
+        let mut entries = self.get_name_tree(b"EmbeddedFiles")?;
+        let mut file_ids = Vec::new();
+        for (_, filespec) in entries.iter().filter(|(entry, _)| decode_text_string(entry) == name) {
+            let filespec = self.dereference(filespec).and_then(|(_, filespec)| filespec.as_dict());
+            if let Ok(embedded_files) = filespec
+                .and_then(|filespec| filespec.get(b"EF"))
+                .and_then(Object::as_dict)
+            {
+                file_ids.extend(embedded_files.iter().filter_map(|(_, file)| file.as_reference().ok()));
+            }
+        }
+        entries.retain(|(entry, _)| decode_text_string(entry) != name);
+        self.set_name_tree(b"EmbeddedFiles", entries)?;
+        if let Some(filespec_id) = attachment.filespec_id {
+            self.remove_associated_file(filespec_id);
+        }
+
+        if let Some(filespec_id) = attachment.filespec_id {
+            if self.reference_count(filespec_id) == 0 {
+                self.objects.remove(&filespec_id);
+            }
+        }
+        for file_id in file_ids {
+            if self.reference_count(file_id) == 0 {
+                self.objects.remove(&file_id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace the contents of the attachment of a name, keeping its file specification and description.
+    pub fn replace_attachment(&mut self, name: &str, data: Vec<u8>) -> Result<()> {
+        let attachment = self.get_attachment(name)?;
+        let file = self
+            .get_object_mut(attachment.file_id)
+            .and_then(Object::as_stream_mut)?;
+        let size = data.len() as i64;
+        file.dict.remove(b"Filter");
+        file.dict.remove(b"DecodeParms");
+        file.set_content(data);
+        file.compress()?;
+        match file.dict.get_mut(b"Params") {
+            Ok(Object::Dictionary(params)) => {
+                // The checksum is of the previous contents.
+                params.remove(b"CheckSum");
+                params.set("Size", size);
+            }
+            _ => file.dict.set("Params", dictionary! { "Size" => size }),
+        }
+        Ok(())
+    }
+
+    /// Embed a file and associate it with the document, or with a page or another object such as an image,
+    /// as the /AF entries of PDF/A-3 and PDF 2.0 do. Returns the id of the file specification.
+    ///
+    /// Like for ZUGFeRD and Factur-X invoices, the file is also listed in the /EmbeddedFiles name tree.
+    pub fn add_associated_file(
+        &mut self, target: Option<ObjectId>, name: &str, data: Vec<u8>, options: &AttachmentOptions,
+    ) -> Result<ObjectId> {
+        let mut options = options.clone();
+        options.relationship.get_or_insert(FileRelationship::Unspecified);
+        let filespec_id = self.add_attachment(name, data, &options)?;
+        self.associate_file(target, filespec_id)?;
+        Ok(filespec_id)
+    }
+
+    /// Associate a file specification with the document, or with a page or another object, unless it already is.
+    pub fn associate_file(&mut self, target: Option<ObjectId>, filespec_id: ObjectId) -> Result<()> {
+        let target_id = match target {
+            Some(id) => id,
+            None => self.trailer.get(b"Root").and_then(Object::as_reference)?,
+        };
+        let dict = match self.get_object_mut(target_id)? {
+            Object::Dictionary(dict) => dict,
+            Object::Stream(stream) => &mut stream.dict,
+            _ => return Err(Error::Type),
+        };
+        let files_id = match dict.get_mut(b"AF") {
+            Ok(Object::Array(files)) => {
+                if !files.contains(&Object::Reference(filespec_id)) {
+                    files.push(Object::Reference(filespec_id));
+                }
+                return Ok(());
+            }
+            Ok(&mut Object::Reference(id)) => id,
+            _ => {
+                dict.set("AF", vec![Object::Reference(filespec_id)]);
+                return Ok(());
+            }
+        };
+        let files = self.get_object_mut(files_id).and_then(Object::as_array_mut)?;
+        if !files.contains(&Object::Reference(filespec_id)) {
+            files.push(Object::Reference(filespec_id));
+        }
+        Ok(())
+    }
+
+    /// Get the ids of the file specifications associated with the document, or with a page or another object.
+    pub fn associated_files(&self, target: Option<ObjectId>) -> Result<Vec<ObjectId>> {
+        let dict = match target {
+            Some(id) => match self.get_object(id)? {
+                Object::Dictionary(dict) => dict,
+                Object::Stream(stream) => &stream.dict,
+                _ => return Err(Error::Type),
+            },
+            None => self.catalog()?,
+        };
+        Ok(match dict.get(b"AF").and_then(|files| self.dereference(files)) {
+            Ok((_, Object::Array(files))) => files.iter().filter_map(|file| file.as_reference().ok()).collect(),
+            _ => vec![],
+        })
+    }
+
+    /// Remove a file specification from the /AF arrays of all objects.
+    fn remove_associated_file(&mut self, filespec_id: ObjectId) {
+        let files_ids: Vec<ObjectId> = self
+            .objects
+            .values()
+            .filter_map(|object| match object {
+                Object::Dictionary(dict) => dict.get(b"AF").and_then(Object::as_reference).ok(),
+                Object::Stream(stream) => stream.dict.get(b"AF").and_then(Object::as_reference).ok(),
+                _ => None,
+            })
+            .collect();
+        for (id, object) in self.objects.iter_mut() {
+            let files = match object {
+                Object::Array(files) if files_ids.contains(id) => files,
+                Object::Dictionary(dict) => match dict.get_mut(b"AF") {
+                    Ok(Object::Array(files)) => files,
+                    _ => continue,
+                },
+                Object::Stream(stream) => match stream.dict.get_mut(b"AF") {
+                    Ok(Object::Array(files)) => files,
+                    _ => continue,
+                },
+                _ => continue,
+            };
+            files.retain(|file| *file != Object::Reference(filespec_id));
+        }
+    }
+
+    /// Get the attachment of a name.
+    pub fn get_attachment(&self, name: &str) -> Result<Attachment> {
+        self.attachments()?
+            .into_iter()
+            .find(|attachment| attachment.name == name)
+            .ok_or_else(|| Error::Syntax(format!("no attachment named {}", name)))
+    }
+}
+
+#[test]
+fn add_and_read_attachments() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    assert!(doc.attachments().unwrap().is_empty());
+    let options = AttachmentOptions {
+        description: Some("Invoice data".to_owned()),
+        mime_type: Some("text/xml".to_owned()),
+        creation_date: Some(PdfDate::new(2020, 1, 2, 3, 4, 5)),
+        ..AttachmentOptions::default()
+    };
+    let xml = b"<invoice>".repeat(20);
+    doc.add_attachment("invoice.xml", xml.clone(), &options).unwrap();
+    doc.add_attachment("notes.txt", b"Notes".to_vec(), &AttachmentOptions::default())
+        .unwrap();
+
+    let attachments = doc.attachments().unwrap();
+    assert_eq!(attachments.len(), 2);
+    let invoice = &attachments[0];
+    assert_eq!(invoice.name, "invoice.xml");
+    assert_eq!(invoice.file_name, "invoice.xml");
+    assert_eq!(invoice.description.as_deref(), Some("Invoice data"));
+    assert_eq!(invoice.mime_type.as_deref(), Some("text/xml"));
+    assert_eq!(invoice.creation_date, Some(PdfDate::new(2020, 1, 2, 3, 4, 5)));
+    assert_eq!(invoice.mod_date, None);
+    assert_eq!(invoice.size, Some(180));
+    assert_eq!(invoice.data(&doc).unwrap(), xml);
+    assert_eq!(attachments[1].data(&doc).unwrap(), b"Notes");
+
+    // Adding a file of the same name replaces the entry.
+    doc.add_attachment("notes.txt", b"New notes".to_vec(), &AttachmentOptions::default())
+        .unwrap();
+    let notes = doc.get_attachment("notes.txt").unwrap();
+    assert_eq!(notes.data(&doc).unwrap(), b"New notes");
+    assert_eq!(doc.attachments().unwrap().len(), 2);
+    assert!(doc.get_attachment("missing.txt").is_err());
+}
+
+#[test]
+fn remove_and_replace_attachments() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    // Enough files for the name tree to have intermediate nodes.
+    for index in 0..40 {
+        let name = format!("file{:02}.txt", index);
+        doc.add_attachment(&name, name.clone().into_bytes(), &AttachmentOptions::default())
+            .unwrap();
+    }
+    let object_count = doc.objects.len();
+    let removed = doc.get_attachment("file07.txt").unwrap();
+    doc.remove_attachment("file07.txt").unwrap();
+    assert!(doc.get_attachment("file07.txt").is_err());
+    assert!(doc.remove_attachment("file07.txt").is_err());
+    assert!(!doc.objects.contains_key(&removed.filespec_id.unwrap()));
+    assert!(!doc.objects.contains_key(&removed.file_id));
+    assert_eq!(doc.objects.len(), object_count - 2);
+    let names: Vec<String> = doc
+        .attachments()
+        .unwrap()
+        .into_iter()
+        .map(|attachment| attachment.name)
+        .collect();
+    assert_eq!(names.len(), 39);
+    assert_eq!(names[7], "file08.txt");
+
+    // File specifications which annotations still refer to are kept.
+    let kept = doc.get_attachment("file08.txt").unwrap();
+    doc.add_object(dictionary! { "Subtype" => "FileAttachment", "FS" => kept.filespec_id.unwrap() });
+    doc.remove_attachment("file08.txt").unwrap();
+    assert!(doc.objects.contains_key(&kept.filespec_id.unwrap()));
+    assert!(doc.objects.contains_key(&kept.file_id));
+
+    doc.replace_attachment("file09.txt", b"Replaced".repeat(10)).unwrap();
+    let replaced = doc.get_attachment("file09.txt").unwrap();
+    assert_eq!(replaced.size, Some(80));
+    assert_eq!(replaced.data(&doc).unwrap(), b"Replaced".repeat(10));
+    assert!(doc.replace_attachment("missing.txt", vec![]).is_err());
+}
+
+#[test]
+fn add_associated_files() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    let options = AttachmentOptions {
+        mime_type: Some("text/xml".to_owned()),
+        relationship: Some(FileRelationship::Data),
+        ..AttachmentOptions::default()
+    };
+    let invoice_id = doc
+        .add_associated_file(None, "factur-x.xml", b"<Invoice/>".to_vec(), &options)
+        .unwrap();
+    let source_id = doc
+        .add_associated_file(
+            Some(page_id),
+            "chart.csv",
+            b"1,2".to_vec(),
+            &AttachmentOptions::default(),
+        )
+        .unwrap();
+    doc.associate_file(None, invoice_id).unwrap();
+
+    assert_eq!(doc.associated_files(None).unwrap(), vec![invoice_id]);
+    assert_eq!(doc.associated_files(Some(page_id)).unwrap(), vec![source_id]);
+    let attachments = doc.attachments().unwrap();
+    assert_eq!(attachments[0].relationship, Some(FileRelationship::Unspecified));
+    assert_eq!(attachments[1].relationship, Some(FileRelationship::Data));
+
+    // Removing an attachment removes it from the associated files too.
+    doc.remove_attachment("factur-x.xml").unwrap();
+    assert!(doc.associated_files(None).unwrap().is_empty());
+    assert!(!doc.objects.contains_key(&invoice_id));
+}
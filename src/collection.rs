@@ -0,0 +1,373 @@
+use crate::encodings::{decode_text_string, encode_text_string};
+use crate::{Dictionary, Document, Error, Object, PdfDate, Result, StringFormat};
+use std::collections::BTreeMap;
+
+/// Data shown by a field of the schema of a portfolio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollectionFieldType {
+    /// Text of the collection items of the files.
+    Text,
+    /// Date of the collection items of the files.
+    Date,
+    /// Number of the collection items of the files.
+    Number,
+    FileName,
+    Description,
+    ModDate,
+    CreationDate,
+    Size,
+    /// Compressed size of the embedded file streams.
+    CompressedSize,
+}
+
+impl CollectionFieldType {
+    fn from_name(name: &[u8]) -> Option<CollectionFieldType> {
+        match name {
+            b"S" => Some(CollectionFieldType::Text),
+            b"D" => Some(CollectionFieldType::Date),
+            b"N" => Some(CollectionFieldType::Number),
+            b"F" => Some(CollectionFieldType::FileName),
+            b"Desc" => Some(CollectionFieldType::Description),
+            b"ModDate" => Some(CollectionFieldType::ModDate),
+            b"CreationDate" => Some(CollectionFieldType::CreationDate),
+            b"Size" => Some(CollectionFieldType::Size),
+            b"CompressedSize" => Some(CollectionFieldType::CompressedSize),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            CollectionFieldType::Text => "S",
+            CollectionFieldType::Date => "D",
+            CollectionFieldType::Number => "N",
+            CollectionFieldType::FileName => "F",
+            CollectionFieldType::Description => "Desc",
+            CollectionFieldType::ModDate => "ModDate",
+            CollectionFieldType::CreationDate => "CreationDate",
+            CollectionFieldType::Size => "Size",
+            CollectionFieldType::CompressedSize => "CompressedSize",
+        }
+    }
+}
+
+/// Field of the schema of a portfolio, which is a column of its details view.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CollectionField {
+    pub field_type: CollectionFieldType,
+    /// Name shown to the user.
+    pub name: String,
+    /// Position of the field relative to the other fields.
+    pub order: Option<i64>,
+    pub visible: bool,
+    pub editable: bool,
+}
+
+impl CollectionField {
+    pub fn new(field_type: CollectionFieldType, name: &str) -> CollectionField {
+        CollectionField {
+            field_type,
+            name: name.to_owned(),
+            order: None,
+            visible: true,
+            editable: false,
+        }
+    }
+
+    fn from_dictionary(dict: &Dictionary) -> Option<CollectionField> {
+        let boolean = |key: &[u8], default: bool| match dict.get(key) {
+            Ok(Object::Boolean(value)) => *value,
+            _ => default,
+        };
+        Some(CollectionField {
+            field_type: CollectionFieldType::from_name(dict.get(b"Subtype").and_then(Object::as_name).ok()?)?,
+            name: dict
+                .get(b"N")
+                .and_then(Object::as_str)
+                .map(decode_text_string)
+                .unwrap_or_default(),
+            order: dict.get(b"O").and_then(Object::as_i64).ok(),
+            visible: boolean(b"V", true),
+            editable: boolean(b"E", false),
+        })
+    }
+
+    fn to_dictionary(&self) -> Dictionary {
+        let mut dict = dictionary! {
+            "Type" => "CollectionField",
+            "Subtype" => self.field_type.name(),
+            "N" => Object::String(encode_text_string(&self.name), StringFormat::Literal),
+        };
+        if let Some(order) = self.order {
+            dict.set("O", order);
+        }
+        if !self.visible {
+            dict.set("V", false);
+        }
+        if self.editable {
+            dict.set("E", true);
+        }
+        dict
+    }
+}
+
+/// Initial presentation of the files of a portfolio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollectionView {
+    /// Files listed with the fields of the schema.
+    Details,
+    /// Files shown as tiles with small icons.
+    Tile,
+    /// Files hidden at first, showing the initial document.
+    Hidden,
+}
+
+/// Portfolio, which presents the embedded files of a document as its contents, described by the /Collection
+/// dictionary of the catalog.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Collection {
+    /// Fields of the schema by their keys, which are the keys of the collection items of the files.
+    pub schema: BTreeMap<String, CollectionField>,
+    /// Name of the embedded file shown at first.
+    pub initial_document: Option<String>,
+    pub view: CollectionView,
+    /// Keys of the fields the files are sorted by, with whether they're sorted in ascending order.
+    pub sort: Vec<(String, bool)>,
+}
+
+impl Default for Collection {
+    fn default() -> Collection {
+        Collection {
+            schema: BTreeMap::new(),
+            initial_document: None,
+            view: CollectionView::Details,
+            sort: Vec::new(),
+        }
+    }
+}
+
+/// Value of a collection item, which gives data of a file for the fields of the schema.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CollectionValue {
+    Text(String),
+    Date(PdfDate),
+    Number(f64),
+}
+
+impl CollectionValue {
+    fn to_object(&self) -> Object {
+        match self {
+            CollectionValue::Text(text) => Object::String(encode_text_string(text), StringFormat::Literal),
+            CollectionValue::Date(date) => Object::from(*date),
+            CollectionValue::Number(number) => Object::Real(*number),
+        }
+    }
+}
+
+impl Document {
+    /// Get the portfolio of the document, if it is one.
+    pub fn collection(&self) -> Result<Option<Collection>> {
+        let collection = match self.catalog()?.get(b"Collection") {
+            Ok(collection) => self.dereference(collection)?.1.as_dict()?,
+            Err(_) => return Ok(None),
+        };
+        let dictionary = |key: &[u8]| {
+            collection
+                .get(key)
+                .and_then(|object| self.dereference(object))
+                .and_then(|(_, object)| object.as_dict())
+                .ok()
+        };
+
+        let mut schema = BTreeMap::new();
+        for (key, field) in dictionary(b"Schema").into_iter().flat_map(Dictionary::iter) {
+            let field = self.dereference(field).and_then(|(_, field)| field.as_dict());
+            if let Some(field) = field.ok().and_then(CollectionField::from_dictionary) {
+                schema.insert(String::from_utf8_lossy(key).into_owned(), field);
+            }
+        }
+        let view = match collection.get(b"View").and_then(Object::as_name) {
+            Ok(b"T") => CollectionView::Tile,
+            Ok(b"H") => CollectionView::Hidden,
+            _ => CollectionView::Details,
+        };
+        let mut sort = Vec::new();
+        if let Some(sort_dict) = dictionary(b"Sort") {
+            let keys = match sort_dict.get(b"S") {
+                Ok(Object::Array(keys)) => keys.iter().filter_map(|key| key.as_name_str().ok()).collect(),
+                Ok(key) => key.as_name_str().into_iter().collect(),
+                Err(_) => vec![],
+            };
+            let boolean = |value: &Object| match *value {
+                Object::Boolean(value) => Some(value),
+                _ => None,
+            };
+            let ascending = match sort_dict.get(b"A") {
+                Ok(Object::Array(ascending)) => ascending.iter().map(boolean).collect(),
+                Ok(value) => vec![boolean(value)],
+                Err(_) => vec![],
+            };
+            for (index, key) in keys.into_iter().enumerate() {
+                // A single value applies to the first key only, and other keys are sorted in ascending order.
+                let ascending = ascending.get(index).copied().flatten().unwrap_or(true);
+                sort.push((key.to_owned(), ascending));
+            }
+        }
+
+        Ok(Some(Collection {
+            schema,
+            initial_document: collection
+                .get(b"D")
+                .and_then(Object::as_str)
+                .map(decode_text_string)
+                .ok(),
+            view,
+            sort,
+        }))
+    }
+
+    /// Make the document a portfolio, replacing its /Collection dictionary. The files of the portfolio are the
+    /// attachments of the document.
+    pub fn set_collection(&mut self, collection: &Collection) -> Result<()> {
+        let mut schema = Dictionary::new();
+        for (key, field) in &collection.schema {
+            schema.set(key.as_bytes().to_vec(), field.to_dictionary());
+        }
+        let view = match collection.view {
+            CollectionView::Details => "D",
+            CollectionView::Tile => "T",
+            CollectionView::Hidden => "H",
+        };
+        let mut dict = dictionary! {
+            "Type" => "Collection",
+            "Schema" => schema,
+            "View" => view,
+        };
+        if let Some(ref name) = collection.initial_document {
+            dict.set("D", Object::String(encode_text_string(name), StringFormat::Literal));
+        }
+        if !collection.sort.is_empty() {
+            let keys: Vec<Object> = collection.sort.iter().map(|(key, _)| key.as_str().into()).collect();
+            let ascending: Vec<Object> = collection
+                .sort
+                .iter()
+                .map(|&(_, ascending)| Object::Boolean(ascending))
+                .collect();
+            dict.set(
+                "Sort",
+                dictionary! { "Type" => "CollectionSort", "S" => keys, "A" => ascending },
+            );
+        }
+
+        let catalog_id = self.trailer.get(b"Root").and_then(Object::as_reference)?;
+        self.get_object_mut(catalog_id)
+            .and_then(Object::as_dict_mut)?
+            .set("Collection", dict);
+        Ok(())
+    }
+
+    /// Get the collection item of an attachment, with its values by the keys of the fields of the schema.
+    /// Strings are read as dates for date fields of the schema.
+    pub fn collection_item(&self, name: &str) -> Result<BTreeMap<String, CollectionValue>> {
+        let attachment = self.get_attachment(name)?;
+        let filespec_id = attachment.filespec_id.ok_or(Error::Type)?;
+        let item = match self.get_dictionary(filespec_id)?.get(b"CI") {
+            Ok(item) => self.dereference(item)?.1.as_dict()?,
+            Err(_) => return Ok(BTreeMap::new()),
+        };
+        let schema = self
+            .collection()?
+            .map(|collection| collection.schema)
+            .unwrap_or_default();
+
+        let mut values = BTreeMap::new();
+        for (key, value) in item.iter() {
+            let key = String::from_utf8_lossy(key).into_owned();
+            // Values may be given with a prefix, which isn't used for sorting.
+            let value = match value {
+                Object::Dictionary(value) => match value.get(b"D") {
+                    Ok(value) => value,
+                    Err(_) => continue,
+                },
+                value => value,
+            };
+            let is_date = schema
+                .get(&key)
+                .filter(|field| field.field_type == CollectionFieldType::Date)
+                .is_some();
+            let value = match value {
+                Object::Integer(_) | Object::Real(_) => CollectionValue::Number(value.as_float()?),
+                Object::String(text, _) if is_date => match PdfDate::parse(text) {
+                    Ok(date) => CollectionValue::Date(date),
+                    Err(_) => CollectionValue::Text(decode_text_string(text)),
+                },
+                Object::String(text, _) => CollectionValue::Text(decode_text_string(text)),
+                _ => continue,
+            };
+            if key != "Type" {
+                values.insert(key, value);
+            }
+        }
+        Ok(values)
+    }
+
+    /// Set the collection item of an attachment, replacing its previous values.
+    pub fn set_collection_item(&mut self, name: &str, values: &BTreeMap<String, CollectionValue>) -> Result<()> {
+        let attachment = self.get_attachment(name)?;
+        let filespec_id = attachment.filespec_id.ok_or(Error::Type)?;
+        let mut item = dictionary! { "Type" => "CollectionItem" };
+        for (key, value) in values {
+            item.set(key.as_bytes().to_vec(), value.to_object());
+        }
+        self.get_object_mut(filespec_id)
+            .and_then(Object::as_dict_mut)?
+            .set("CI", item);
+        Ok(())
+    }
+}
+
+#[test]
+fn read_and_write_collections() {
+    use crate::attachments::AttachmentOptions;
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    assert_eq!(doc.collection().unwrap(), None);
+    for name in ["report.pdf", "mail.eml"].iter() {
+        doc.add_attachment(name, b"data".to_vec(), &AttachmentOptions::default())
+            .unwrap();
+    }
+
+    let mut collection = Collection {
+        initial_document: Some("report.pdf".to_owned()),
+        view: CollectionView::Tile,
+        sort: vec![("received".to_owned(), false), ("file".to_owned(), true)],
+        ..Collection::default()
+    };
+    let mut received = CollectionField::new(CollectionFieldType::Date, "Received");
+    received.order = Some(1);
+    received.editable = true;
+    collection.schema.insert("received".to_owned(), received);
+    collection.schema.insert(
+        "file".to_owned(),
+        CollectionField::new(CollectionFieldType::FileName, "File name"),
+    );
+    collection.schema.insert(
+        "custodian".to_owned(),
+        CollectionField::new(CollectionFieldType::Text, "Custodian"),
+    );
+    doc.set_collection(&collection).unwrap();
+    assert_eq!(doc.collection().unwrap(), Some(collection));
+
+    let mut values = BTreeMap::new();
+    values.insert(
+        "received".to_owned(),
+        CollectionValue::Date(PdfDate::new(2021, 3, 4, 5, 6, 7)),
+    );
+    values.insert("custodian".to_owned(), CollectionValue::Text("Legal".to_owned()));
+    values.insert("pages".to_owned(), CollectionValue::Number(12.0));
+    doc.set_collection_item("mail.eml", &values).unwrap();
+    assert_eq!(doc.collection_item("mail.eml").unwrap(), values);
+    assert!(doc.collection_item("report.pdf").unwrap().is_empty());
+    assert!(doc.set_collection_item("missing.txt", &values).is_err());
+}
@@ -0,0 +1,404 @@
+use crate::{Dictionary, Document, Error, Object, Result};
+
+/// Color space of colors and image samples.
+///
+/// CalGray and CalRGB are treated as DeviceGray and DeviceRGB. Conversion to RGB is meant for extraction and
+/// previews, and doesn't use ICC profiles.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColorSpace {
+    DeviceGray,
+    DeviceRGB,
+    DeviceCMYK,
+    /// Colors given by an index into a table of colors in the base color space.
+    Indexed {
+        base: Box<ColorSpace>,
+        /// Highest valid index.
+        hival: usize,
+        /// Components of the base color space for each index, one byte per component.
+        lookup: Vec<u8>,
+    },
+    /// Colors with the components of an ICC profile, which are converted with the alternate color space.
+    ICCBased {
+        components: usize,
+        alternate: Box<ColorSpace>,
+    },
+    /// Colorant given by a single tint value.
+    Separation {
+        name: Vec<u8>,
+        alternate: Box<ColorSpace>,
+        tint_transform: Option<TintTransform>,
+    },
+    /// Several colorants, each given by a tint value.
+    DeviceN {
+        names: Vec<Vec<u8>>,
+        alternate: Box<ColorSpace>,
+        tint_transform: Option<TintTransform>,
+    },
+    /// CIE L*a*b* colors relative to a white point.
+    Lab {
+        white_point: [f64; 3],
+        /// Ranges of the a* and b* components.
+        range: [f64; 4],
+    },
+    Pattern,
+}
+
+/// Function mapping tints to the components of an alternate color space. Other kinds of functions, such as
+/// PostScript calculator functions, aren't supported, and tints are then shown as shades of gray.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TintTransform {
+    /// Exponential interpolation function (type 2) of one tint.
+    Exponential { c0: Vec<f64>, c1: Vec<f64>, exponent: f64 },
+    /// Sampled function (type 0) of one tint, with its decoded samples over the domain, which are linearly
+    /// interpolated.
+    Sampled { samples: Vec<Vec<f64>> },
+}
+
+impl TintTransform {
+    fn from_object(doc: &Document, object: &Object) -> Option<TintTransform> {
+        let (_, function) = doc.dereference(object).ok()?;
+        let dict = match function {
+            Object::Dictionary(dict) => dict,
+            Object::Stream(stream) => &stream.dict,
+            _ => return None,
+        };
+        let numbers = |key: &[u8]| -> Option<Vec<f64>> {
+            let (_, array) = doc.dereference(dict.get(key).ok()?).ok()?;
+            array.as_array().ok()?.iter().map(|n| n.as_float().ok()).collect()
+        };
+        match dict.get(b"FunctionType").and_then(Object::as_i64).ok()? {
+            2 => Some(TintTransform::Exponential {
+                c0: numbers(b"C0").unwrap_or_else(|| vec![0.0]),
+                c1: numbers(b"C1").unwrap_or_else(|| vec![1.0]),
+                exponent: dict.get(b"N").and_then(Object::as_float).ok()?,
+            }),
+            0 => {
+                let stream = function.as_stream().ok()?;
+                let size = numbers(b"Size")?;
+                let range = numbers(b"Range")?;
+                let decode = numbers(b"Decode").unwrap_or_else(|| range.clone());
+                let bits = dict.get(b"BitsPerSample").and_then(Object::as_i64).ok()?;
+                if size.len() != 1 || size[0] < 1.0 || ![8, 16].contains(&bits) || decode.len() < range.len() {
+                    return None;
+                }
+                let data = stream.decompressed_content().unwrap_or_else(|_| stream.content.clone());
+                let outputs = range.len() / 2;
+                let bytes = bits as usize / 8;
+                let max = ((1u32 << bits) - 1) as f64;
+                let values: Vec<f64> = data
+                    .chunks_exact(bytes)
+                    .map(|sample| sample.iter().fold(0u32, |value, &byte| value << 8 | u32::from(byte)) as f64)
+                    .collect();
+                let samples: Vec<Vec<f64>> = values
+                    .chunks_exact(outputs)
+                    .take(size[0] as usize)
+                    .map(|sample| {
+                        sample
+                            .iter()
+                            .enumerate()
+                            .map(|(output, &value)| {
+                                let (min, max_value) = (decode[2 * output], decode[2 * output + 1]);
+                                min + value * (max_value - min) / max
+                            })
+                            .collect()
+                    })
+                    .collect();
+                if samples.is_empty() {
+                    return None;
+                }
+                Some(TintTransform::Sampled { samples })
+            }
+            _ => None,
+        }
+    }
+
+    /// Get the components of the alternate color space for a tint between 0 and 1.
+    pub fn evaluate(&self, tint: f64) -> Vec<f64> {
+        let tint = tint.clamp(0.0, 1.0);
+        match self {
+            TintTransform::Exponential { c0, c1, exponent } => {
+                let factor = tint.powf(*exponent);
+                c0.iter().zip(c1).map(|(c0, c1)| c0 + factor * (c1 - c0)).collect()
+            }
+            TintTransform::Sampled { samples } => {
+                let position = tint * (samples.len() - 1) as f64;
+                let index = (position.floor() as usize).min(samples.len() - 1);
+                let next = (index + 1).min(samples.len() - 1);
+                let fraction = position - index as f64;
+                samples[index]
+                    .iter()
+                    .zip(&samples[next])
+                    .map(|(a, b)| a + fraction * (b - a))
+                    .collect()
+            }
+        }
+    }
+}
+
+impl ColorSpace {
+    /// Get a color space from its name or array, as used in /ColorSpace entries of resources and images.
+    /// Names of device color spaces may be abbreviated as in inline images.
+    pub fn from_object(doc: &Document, object: &Object) -> Result<ColorSpace> {
+        let (_, object) = doc.dereference(object)?;
+        let (family, parameters): (&[u8], &[Object]) = match object {
+            Object::Name(name) => (name, &[]),
+            Object::Array(array) if !array.is_empty() => (array[0].as_name()?, &array[1..]),
+            _ => return Err(Error::Type),
+        };
+        let parameter = |index: usize| {
+            parameters
+                .get(index)
+                .ok_or(Error::Type)
+                .and_then(|parameter| doc.dereference(parameter))
+                .map(|(_, parameter)| parameter)
+        };
+        let numbers = |index: usize, key: &[u8]| -> Option<Vec<f64>> {
+            let dict = parameter(index).and_then(Object::as_dict).ok()?;
+            let (_, array) = doc.dereference(dict.get(key).ok()?).ok()?;
+            array.as_array().ok()?.iter().map(|n| n.as_float().ok()).collect()
+        };
+        Ok(match family {
+            b"DeviceGray" | b"CalGray" | b"G" => ColorSpace::DeviceGray,
+            b"DeviceRGB" | b"CalRGB" | b"RGB" => ColorSpace::DeviceRGB,
+            b"DeviceCMYK" | b"CMYK" => ColorSpace::DeviceCMYK,
+            b"Pattern" => ColorSpace::Pattern,
+            b"Indexed" | b"I" => {
+                let base = ColorSpace::from_object(doc, parameter(0)?)?;
+                if let ColorSpace::Indexed { .. } | ColorSpace::Pattern = base {
+                    return Err(Error::Type);
+                }
+                let hival = parameter(1)?.as_i64()?.clamp(0, 255) as usize;
+                let lookup = match *parameter(2)? {
+                    Object::String(ref lookup, _) => lookup.clone(),
+                    Object::Stream(ref lookup) => {
+                        lookup.decompressed_content().unwrap_or_else(|_| lookup.content.clone())
+                    }
+                    _ => return Err(Error::Type),
+                };
+                ColorSpace::Indexed {
+                    base: Box::new(base),
+                    hival,
+                    lookup,
+                }
+            }
+            b"ICCBased" => {
+                let profile = parameter(0)?.as_stream()?;
+                let components = profile.dict.get(b"N")?.as_i64()?.max(0) as usize;
+                let alternate = match profile.dict.get(b"Alternate") {
+                    Ok(alternate) => ColorSpace::from_object(doc, alternate)?,
+                    Err(_) => match components {
+                        1 => ColorSpace::DeviceGray,
+                        3 => ColorSpace::DeviceRGB,
+                        4 => ColorSpace::DeviceCMYK,
+                        _ => return Err(Error::Type),
+                    },
+                };
+                ColorSpace::ICCBased {
+                    components,
+                    alternate: Box::new(alternate),
+                }
+            }
+            b"Separation" => ColorSpace::Separation {
+                name: parameter(0)?.as_name()?.to_vec(),
+                alternate: Box::new(ColorSpace::from_object(doc, parameter(1)?)?),
+                tint_transform: parameter(2)
+                    .ok()
+                    .and_then(|function| TintTransform::from_object(doc, function)),
+            },
+            b"DeviceN" => ColorSpace::DeviceN {
+                names: parameter(0)?
+                    .as_array()?
+                    .iter()
+                    .map(|name| name.as_name().map(<[u8]>::to_vec))
+                    .collect::<Result<_>>()?,
+                alternate: Box::new(ColorSpace::from_object(doc, parameter(1)?)?),
+                tint_transform: parameter(2)
+                    .ok()
+                    .and_then(|function| TintTransform::from_object(doc, function)),
+            },
+            b"Lab" => {
+                let white_point = numbers(0, b"WhitePoint").unwrap_or_default();
+                let range = numbers(0, b"Range").unwrap_or_default();
+                ColorSpace::Lab {
+                    white_point: match white_point.as_slice() {
+                        &[x, y, z] => [x, y, z],
+                        _ => return Err(Error::Type),
+                    },
+                    range: match range.as_slice() {
+                        &[a_min, a_max, b_min, b_max] => [a_min, a_max, b_min, b_max],
+                        _ => [-100.0, 100.0, -100.0, 100.0],
+                    },
+                }
+            }
+            _ => return Err(Error::Type),
+        })
+    }
+
+    /// Number of color components, which is zero for patterns.
+    pub fn components(&self) -> usize {
+        match self {
+            ColorSpace::DeviceGray | ColorSpace::Indexed { .. } | ColorSpace::Separation { .. } => 1,
+            ColorSpace::DeviceRGB | ColorSpace::Lab { .. } => 3,
+            ColorSpace::DeviceCMYK => 4,
+            ColorSpace::ICCBased { components, .. } => *components,
+            ColorSpace::DeviceN { names, .. } => names.len(),
+            ColorSpace::Pattern => 0,
+        }
+    }
+
+    /// Convert color components to RGB components between 0 and 1. Missing components are taken as 0,
+    /// and patterns are black.
+    pub fn to_rgb(&self, components: &[f64]) -> [f64; 3] {
+        let component = |index: usize| components.get(index).copied().unwrap_or(0.0);
+        let gray = |value: f64| {
+            let value = value.clamp(0.0, 1.0);
+            [value, value, value]
+        };
+        match self {
+            ColorSpace::DeviceGray => gray(component(0)),
+            ColorSpace::DeviceRGB => [
+                component(0).clamp(0.0, 1.0),
+                component(1).clamp(0.0, 1.0),
+                component(2).clamp(0.0, 1.0),
+            ],
+            ColorSpace::DeviceCMYK => {
+                let black = 1.0 - component(3).clamp(0.0, 1.0);
+                [
+                    (1.0 - component(0).clamp(0.0, 1.0)) * black,
+                    (1.0 - component(1).clamp(0.0, 1.0)) * black,
+                    (1.0 - component(2).clamp(0.0, 1.0)) * black,
+                ]
+            }
+            ColorSpace::Indexed { base, hival, lookup } => {
+                let index = (component(0).round().max(0.0) as usize).min(*hival);
+                let count = base.components();
+                let base_components: Vec<f64> = (0..count)
+                    .map(|offset| f64::from(lookup.get(index * count + offset).copied().unwrap_or(0)) / 255.0)
+                    .collect();
+                base.to_rgb(&base_components)
+            }
+            ColorSpace::ICCBased { alternate, .. } => alternate.to_rgb(components),
+            ColorSpace::Separation {
+                alternate,
+                tint_transform,
+                ..
+            } => match tint_transform {
+                Some(tint_transform) => alternate.to_rgb(&tint_transform.evaluate(component(0))),
+                None => gray(1.0 - component(0)),
+            },
+            ColorSpace::DeviceN {
+                names,
+                alternate,
+                tint_transform,
+            } => match tint_transform {
+                Some(tint_transform) if names.len() == 1 => alternate.to_rgb(&tint_transform.evaluate(component(0))),
+                _ => {
+                    let total: f64 = components.iter().map(|tint| tint.clamp(0.0, 1.0)).sum();
+                    gray(1.0 - total.min(1.0))
+                }
+            },
+            ColorSpace::Lab { range, .. } => {
+                let lightness = component(0).clamp(0.0, 100.0);
+                let a = component(1).clamp(range[0], range[1]);
+                let b = component(2).clamp(range[2], range[3]);
+                lab_to_rgb(lightness, a, b)
+            }
+            ColorSpace::Pattern => [0.0, 0.0, 0.0],
+        }
+    }
+}
+
+/// Convert a CIE L*a*b* color to sRGB. XYZ values are scaled from the white point of the color space to the D65
+/// white point, so that the white point itself doesn't matter.
+fn lab_to_rgb(lightness: f64, a: f64, b: f64) -> [f64; 3] {
+    let inverse = |t: f64| {
+        if t > 6.0 / 29.0 {
+            t.powi(3)
+        } else {
+            3.0 * (6.0f64 / 29.0).powi(2) * (t - 4.0 / 29.0)
+        }
+    };
+    let fy = (lightness + 16.0) / 116.0;
+    let x = 0.9505 * inverse(fy + a / 500.0);
+    let y = inverse(fy);
+    let z = 1.089 * inverse(fy - b / 200.0);
+    let linear = [
+        3.2406 * x - 1.5372 * y - 0.4986 * z,
+        -0.9689 * x + 1.8758 * y + 0.0415 * z,
+        0.0557 * x - 0.2040 * y + 1.0570 * z,
+    ];
+    let mut rgb = [0.0; 3];
+    for (channel, value) in rgb.iter_mut().zip(linear.iter()) {
+        let value = value.clamp(0.0, 1.0);
+        *channel = if value <= 0.003_130_8 {
+            12.92 * value
+        } else {
+            1.055 * value.powf(1.0 / 2.4) - 0.055
+        };
+    }
+    rgb
+}
+
+impl Document {
+    /// Get a color space by the name used by the `cs` and `CS` operators, which is either the name of a device
+    /// color space or of an entry in the /ColorSpace dictionary of the resources.
+    pub fn get_color_space(&self, name: &[u8], resources: Option<&Dictionary>) -> Result<ColorSpace> {
+        let resource = resources
+            .and_then(|resources| resources.get(b"ColorSpace").ok())
+            .and_then(|color_spaces| self.dereference(color_spaces).ok())
+            .and_then(|(_, color_spaces)| color_spaces.as_dict().ok())
+            .and_then(|color_spaces| color_spaces.get(name).ok());
+        match resource {
+            Some(color_space) => ColorSpace::from_object(self, color_space),
+            None => ColorSpace::from_object(self, &Object::Name(name.to_vec())),
+        }
+    }
+}
+
+#[test]
+fn resolve_and_convert_color_spaces() {
+    use crate::test_support::create_document;
+    use crate::{Stream, StringFormat};
+
+    let mut doc = create_document();
+    let profile_id = doc.add_object(Stream::new(dictionary! { "N" => 3 }, vec![]));
+    let resources = dictionary! {
+        "ColorSpace" => dictionary! {
+            "CS0" => vec!["ICCBased".into(), profile_id.into()],
+            "CS1" => vec![
+                "Indexed".into(),
+                "DeviceRGB".into(),
+                1.into(),
+                Object::String(vec![255, 0, 0, 0, 0, 255], StringFormat::Hexadecimal),
+            ],
+            "CS2" => vec![
+                "Separation".into(),
+                "Spot".into(),
+                "DeviceCMYK".into(),
+                Object::Dictionary(dictionary! {
+                    "FunctionType" => 2,
+                    "Domain" => vec![0.into(), 1.into()],
+                    "C0" => vec![0.into(), 0.into(), 0.into(), 0.into()],
+                    "C1" => vec![0.into(), 1.into(), 1.into(), 0.into()],
+                    "N" => 1,
+                }),
+            ],
+            "CS3" => vec!["Lab".into(), Object::Dictionary(dictionary! {
+                "WhitePoint" => vec![Object::Real(0.9505), 1.into(), Object::Real(1.089)],
+            })],
+        },
+    };
+    let get = |name: &str| doc.get_color_space(name.as_bytes(), Some(&resources)).unwrap();
+
+    assert_eq!(get("DeviceCMYK").to_rgb(&[0.0, 0.0, 0.0, 1.0]), [0.0, 0.0, 0.0]);
+    let icc = get("CS0");
+    assert_eq!(icc.components(), 3);
+    assert_eq!(icc.to_rgb(&[0.2, 0.4, 0.6]), [0.2, 0.4, 0.6]);
+    assert_eq!(get("CS1").to_rgb(&[1.0]), [0.0, 0.0, 1.0]);
+    assert_eq!(get("CS2").to_rgb(&[1.0]), [1.0, 0.0, 0.0]);
+    assert_eq!(get("CS2").to_rgb(&[0.0]), [1.0, 1.0, 1.0]);
+    let white = get("CS3").to_rgb(&[100.0, 0.0, 0.0]);
+    assert!(white.iter().all(|channel| (channel - 1.0).abs() < 0.01));
+    assert_eq!(get("Pattern").components(), 0);
+    assert!(doc.get_color_space(b"CS9", Some(&resources)).is_err());
+}
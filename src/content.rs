@@ -1,6 +1,6 @@
 use super::Object;
 use crate::writer::Writer;
-use crate::Result;
+use crate::{Dictionary, Matrix, Result, Stream};
 use std::io::Write;
 
 #[derive(Debug, Clone)]
@@ -16,6 +16,119 @@ impl Operation {
             operands,
         }
     }
+
+    /// Create the `BI` operation of an inline image, with its parameters as they are written, using abbreviated
+    /// keys and names such as /W and /DCT.
+    pub fn inline_image(parameters: Dictionary, data: Vec<u8>) -> Operation {
+        Operation::new(
+            "BI",
+            vec![Object::Stream(Stream {
+                dict: parameters,
+                content: data,
+                allows_compression: false,
+                start_position: None,
+            })],
+        )
+    }
+
+    /// Get the parameters and data of an inline image, which the content parser reads from
+    /// `BI` ... `ID` ... `EI` as one `BI` operation with a stream operand.
+    pub fn as_inline_image(&self) -> Option<&Stream> {
+        match (self.operator.as_str(), self.operands.as_slice()) {
+            ("BI", [Object::Stream(image)]) => Some(image),
+            _ => None,
+        }
+    }
+
+    /// Get an inline image as an image XObject, with the abbreviated keys and names of its parameters expanded,
+    /// so that it can be decoded like other images.
+    pub fn inline_image_xobject(&self) -> Option<Stream> {
+        let image = self.as_inline_image()?;
+        let mut dict = dictionary! { "Type" => "XObject", "Subtype" => "Image" };
+        for (key, value) in image.dict.iter() {
+            let key = INLINE_IMAGE_KEYS
+                .iter()
+                .find(|(abbreviation, _)| abbreviation.as_bytes() == key.as_slice())
+                .map_or(key.as_slice(), |(_, full)| full.as_bytes());
+            let value = match key {
+                b"ColorSpace" | b"Filter" => expand_inline_image_names(value),
+                _ => value.clone(),
+            };
+            dict.set(key.to_vec(), value);
+        }
+        Some(Stream::new(dict, image.content.clone()))
+    }
+}
+
+const INLINE_IMAGE_KEYS: [(&str, &str); 10] = [
+    ("BPC", "BitsPerComponent"),
+    ("CS", "ColorSpace"),
+    ("D", "Decode"),
+    ("DP", "DecodeParms"),
+    ("F", "Filter"),
+    ("H", "Height"),
+    ("IM", "ImageMask"),
+    ("I", "Interpolate"),
+    ("L", "Length"),
+    ("W", "Width"),
+];
+
+const INLINE_IMAGE_NAMES: [(&str, &str); 11] = [
+    ("G", "DeviceGray"),
+    ("RGB", "DeviceRGB"),
+    ("CMYK", "DeviceCMYK"),
+    ("I", "Indexed"),
+    ("AHx", "ASCIIHexDecode"),
+    ("A85", "ASCII85Decode"),
+    ("LZW", "LZWDecode"),
+    ("Fl", "FlateDecode"),
+    ("RL", "RunLengthDecode"),
+    ("CCF", "CCITTFaxDecode"),
+    ("DCT", "DCTDecode"),
+];
+
+/// Expand the abbreviated color space and filter names of an inline image, including those in arrays.
+fn expand_inline_image_names(value: &Object) -> Object {
+    match value {
+        Object::Name(name) => Object::Name(
+            INLINE_IMAGE_NAMES
+                .iter()
+                .find(|(abbreviation, _)| abbreviation.as_bytes() == name.as_slice())
+                .map_or_else(|| name.clone(), |(_, full)| full.as_bytes().to_vec()),
+        ),
+        Object::Array(array) => Object::Array(array.iter().map(expand_inline_image_names).collect()),
+        _ => value.clone(),
+    }
+}
+
+/// Find the end of the data of an inline image, which starts after the whitespace following `ID`, returning
+/// the length of the data and the length up to the end of `EI`.
+///
+/// The data is as long as the /L or /Length parameter if there is one, and otherwise ends before the first
+/// `EI` which is surrounded by whitespace.
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+pub(crate) fn inline_image_end(input: &[u8], parameters: &Dictionary) -> Option<(usize, usize)> {
+    let is_whitespace = |byte: &u8| b" \t\r\n\x0C\0".contains(byte);
+    let length = parameters
+        .get(b"L")
+        .or_else(|_| parameters.get(b"Length"))
+        .and_then(Object::as_i64)
+        .ok();
+    if let Some(length) = length.filter(|&length| length >= 0 && length as usize <= input.len()) {
+        let length = length as usize;
+        let end = length + input[length..].iter().take_while(|byte| is_whitespace(byte)).count();
+        if input[end..].starts_with(b"EI") {
+            return Some((length, end + 2));
+        }
+    }
+    (0..input.len()).find_map(|index| {
+        let terminated = input.get(index + 3).filter(|byte| !is_whitespace(byte)).is_none();
+        if is_whitespace(&input[index]) && input[index + 1..].starts_with(b"EI") && terminated {
+            Some((index, index + 3))
+        } else {
+            None
+        }
+    })
 }
 
 #[derive(Debug, Clone)]
@@ -28,6 +141,19 @@ impl<Operations: AsRef<[Operation]>> Content<Operations> {
     pub fn encode(&self) -> Result<Vec<u8>> {
         let mut buffer = Vec::new();
         for operation in self.operations.as_ref() {
+            if let Some(image) = operation.as_inline_image() {
+                buffer.write_all(b"BI")?;
+                for (key, value) in image.dict.iter() {
+                    buffer.write_all(b" ")?;
+                    Writer::write_object(&mut buffer, &Object::Name(key.clone()))?;
+                    buffer.write_all(b" ")?;
+                    Writer::write_object(&mut buffer, value)?;
+                }
+                buffer.write_all(b" ID ")?;
+                buffer.write_all(&image.content)?;
+                buffer.write_all(b"\nEI\n")?;
+                continue;
+            }
             for operand in &operation.operands {
                 Writer::write_object(&mut buffer, operand)?;
                 buffer.write_all(b" ")?;
@@ -38,3 +164,275 @@ impl<Operations: AsRef<[Operation]>> Content<Operations> {
         Ok(buffer)
     }
 }
+
+/// Builder of content streams with typed operations, which keeps graphics states and text objects balanced.
+///
+/// ```
+/// use lopdf::content::ContentBuilder;
+///
+/// let content = ContentBuilder::new()
+///     .set_fill_color(&[1.0, 0.0, 0.0])
+///     .rectangle(100.0, 100.0, 200.0, 50.0)
+///     .fill()
+///     .show_text_with_font("F1", 24.0, 100.0, 600.0, "Hello World!")
+///     .build();
+/// assert_eq!(content.operations.len(), 8);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ContentBuilder {
+    operations: Vec<Operation>,
+    state_depth: usize,
+    in_text: bool,
+}
+
+impl ContentBuilder {
+    pub fn new() -> ContentBuilder {
+        ContentBuilder::default()
+    }
+
+    /// Add an operation which has no typed method.
+    pub fn operation(mut self, operator: &str, operands: Vec<Object>) -> ContentBuilder {
+        self.operations.push(Operation::new(operator, operands));
+        self
+    }
+
+    fn numbers(self, operator: &str, numbers: &[f64]) -> ContentBuilder {
+        self.operation(operator, numbers.iter().map(|&number| Object::Real(number)).collect())
+    }
+
+    /// Number of graphics states saved and not yet restored.
+    pub fn state_depth(&self) -> usize {
+        self.state_depth
+    }
+
+    pub fn save_state(mut self) -> ContentBuilder {
+        self.state_depth += 1;
+        self.operation("q", vec![])
+    }
+
+    /// Restore the last saved graphics state, or do nothing if there is none.
+    pub fn restore_state(mut self) -> ContentBuilder {
+        if self.state_depth == 0 {
+            return self;
+        }
+        self.state_depth -= 1;
+        self.operation("Q", vec![])
+    }
+
+    /// Concatenate a matrix to the current transformation matrix.
+    pub fn transform(self, matrix: Matrix) -> ContentBuilder {
+        self.numbers("cm", &matrix.to_array())
+    }
+
+    pub fn set_line_width(self, width: f64) -> ContentBuilder {
+        self.numbers("w", &[width])
+    }
+
+    /// Set the fill color, in the gray, RGB or CMYK color space for one, three or four components,
+    /// or in the current color space otherwise.
+    pub fn set_fill_color(self, components: &[f64]) -> ContentBuilder {
+        let operator = match components.len() {
+            1 => "g",
+            3 => "rg",
+            4 => "k",
+            _ => "sc",
+        };
+        self.numbers(operator, components)
+    }
+
+    /// Set the stroke color, in the same color spaces as `set_fill_color`.
+    pub fn set_stroke_color(self, components: &[f64]) -> ContentBuilder {
+        let operator = match components.len() {
+            1 => "G",
+            3 => "RG",
+            4 => "K",
+            _ => "SC",
+        };
+        self.numbers(operator, components)
+    }
+
+    /// Set the graphics state parameters of an /ExtGState resource.
+    pub fn set_graphics_state(self, name: &str) -> ContentBuilder {
+        self.operation("gs", vec![Object::Name(name.as_bytes().to_vec())])
+    }
+
+    pub fn move_to(self, x: f64, y: f64) -> ContentBuilder {
+        self.numbers("m", &[x, y])
+    }
+
+    pub fn line_to(self, x: f64, y: f64) -> ContentBuilder {
+        self.numbers("l", &[x, y])
+    }
+
+    /// Append a cubic Bézier curve with two control points.
+    pub fn curve_to(self, x1: f64, y1: f64, x2: f64, y2: f64, x3: f64, y3: f64) -> ContentBuilder {
+        self.numbers("c", &[x1, y1, x2, y2, x3, y3])
+    }
+
+    pub fn rectangle(self, x: f64, y: f64, width: f64, height: f64) -> ContentBuilder {
+        self.numbers("re", &[x, y, width, height])
+    }
+
+    pub fn close_path(self) -> ContentBuilder {
+        self.operation("h", vec![])
+    }
+
+    pub fn stroke(self) -> ContentBuilder {
+        self.operation("S", vec![])
+    }
+
+    /// Fill the path with the nonzero winding number rule.
+    pub fn fill(self) -> ContentBuilder {
+        self.operation("f", vec![])
+    }
+
+    pub fn fill_and_stroke(self) -> ContentBuilder {
+        self.operation("B", vec![])
+    }
+
+    /// End the path without painting it, such as after setting it as clipping path.
+    pub fn end_path(self) -> ContentBuilder {
+        self.operation("n", vec![])
+    }
+
+    /// Intersect the clipping path with the current path, which still needs to be painted or ended.
+    pub fn clip(self) -> ContentBuilder {
+        self.operation("W", vec![])
+    }
+
+    /// Begin a text object, unless one is already open.
+    pub fn begin_text(mut self) -> ContentBuilder {
+        if self.in_text {
+            return self;
+        }
+        self.in_text = true;
+        self.operation("BT", vec![])
+    }
+
+    /// End the open text object, if any.
+    pub fn end_text(mut self) -> ContentBuilder {
+        if !self.in_text {
+            return self;
+        }
+        self.in_text = false;
+        self.operation("ET", vec![])
+    }
+
+    /// Set the font of a /Font resource and its size.
+    pub fn set_font(self, name: &str, size: f64) -> ContentBuilder {
+        self.operation("Tf", vec![Object::Name(name.as_bytes().to_vec()), Object::Real(size)])
+    }
+
+    /// Move to the start of the next line, offset from the start of the current one.
+    pub fn move_text(self, tx: f64, ty: f64) -> ContentBuilder {
+        self.numbers("Td", &[tx, ty])
+    }
+
+    pub fn set_text_matrix(self, matrix: Matrix) -> ContentBuilder {
+        self.numbers("Tm", &matrix.to_array())
+    }
+
+    /// Show text, given in the encoding of the current font.
+    pub fn show_text<T: Into<Vec<u8>>>(self, text: T) -> ContentBuilder {
+        self.operation("Tj", vec![Object::string_literal(text)])
+    }
+
+    /// Show text in a text object of its own, with the given font and size, starting at a position.
+    pub fn show_text_with_font<T: Into<Vec<u8>>>(
+        self, font: &str, size: f64, x: f64, y: f64, text: T,
+    ) -> ContentBuilder {
+        self.begin_text()
+            .set_font(font, size)
+            .move_text(x, y)
+            .show_text(text)
+            .end_text()
+    }
+
+    /// Paint an /XObject resource, such as an image or form.
+    pub fn draw_xobject(self, name: &str) -> ContentBuilder {
+        self.operation("Do", vec![Object::Name(name.as_bytes().to_vec())])
+    }
+
+    /// Get the content, with the open text object ended and the saved graphics states restored.
+    pub fn build(mut self) -> Content {
+        self = self.end_text();
+        while self.state_depth > 0 {
+            self = self.restore_state();
+        }
+        Content {
+            operations: self.operations,
+        }
+    }
+
+    /// Encode the content, as built by `build`.
+    pub fn encode(self) -> Result<Vec<u8>> {
+        self.build().encode()
+    }
+}
+
+#[test]
+fn build_content() {
+    let builder = ContentBuilder::new()
+        .save_state()
+        .transform(Matrix::translate(10.0, 20.0))
+        .set_stroke_color(&[0.5])
+        .move_to(0.0, 0.0)
+        .line_to(100.0, 0.0)
+        .stroke()
+        .restore_state()
+        .restore_state()
+        .save_state()
+        .begin_text()
+        .begin_text()
+        .set_font("F1", 12.0)
+        .show_text("Hi");
+    assert_eq!(builder.state_depth(), 1);
+    let content = builder.build();
+    let operators: Vec<&str> = content
+        .operations
+        .iter()
+        .map(|operation| operation.operator.as_str())
+        .collect();
+    assert_eq!(
+        operators,
+        ["q", "cm", "G", "m", "l", "S", "Q", "q", "BT", "Tf", "Tj", "ET", "Q"]
+    );
+    assert_eq!(
+        String::from_utf8(content.encode().unwrap()).unwrap().lines().nth(1),
+        Some("1.0 0.0 0.0 1.0 10.0 20.0 cm")
+    );
+}
+
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+#[test]
+fn parse_and_write_inline_images() {
+    let data = b"q 2 0 0 1 0 0 cm\nBI /W 2 /H 1 /CS /G /BPC 8 /F [/AHx]\nID 00FF>\nEI Q\nBI /W 3 /H 1 /CS /G /BPC 8 /L 3 ID E\nI\nEI\n0 g";
+    let content = Content::decode(data).unwrap();
+    let operators: Vec<&str> = content
+        .operations
+        .iter()
+        .map(|operation| operation.operator.as_str())
+        .collect();
+    assert_eq!(operators, ["q", "cm", "BI", "Q", "BI", "g"]);
+    let image = content.operations[2].as_inline_image().unwrap();
+    assert_eq!(image.dict.get(b"CS").and_then(Object::as_name_str).unwrap(), "G");
+    assert_eq!(image.content, b"00FF>");
+    assert_eq!(content.operations[4].as_inline_image().unwrap().content, b"E\nI");
+
+    let xobject = content.operations[2].inline_image_xobject().unwrap();
+    assert_eq!(xobject.dict.get(b"Width").and_then(Object::as_i64).unwrap(), 2);
+    assert_eq!(
+        xobject.dict.get(b"ColorSpace").and_then(Object::as_name_str).unwrap(),
+        "DeviceGray"
+    );
+    assert_eq!(
+        xobject.dict.get(b"Filter").unwrap(),
+        &Object::Array(vec![Object::Name(b"ASCIIHexDecode".to_vec())])
+    );
+
+    let encoded = content.encode().unwrap();
+    let decoded = Content::decode(&encoded).unwrap();
+    assert_eq!(decoded.operations.len(), 6);
+    assert_eq!(decoded.operations[2].as_inline_image(), Some(image));
+    assert_eq!(decoded.encode().unwrap(), encoded);
+}
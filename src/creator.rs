@@ -58,12 +58,24 @@ impl Document {
 
     pub fn get_or_create_resources(&mut self, page_id: ObjectId) -> Result<&mut Object> {
         let mut resources_id = None;
+        let mut inherited = None;
         {
             let page = self.get_object(page_id).and_then(Object::as_dict)?;
             if page.has(b"Resources") {
                 resources_id = page.get(b"Resources").and_then(Object::as_reference).ok();
+            } else {
+                // Copy inherited resources, which would otherwise be hidden by the new ones.
+                inherited = self
+                    .get_page_attribute(page_id, b"Resources")
+                    .and_then(|resources| resources.as_dict().ok())
+                    .cloned();
             }
         }
+        if let Some(resources) = inherited {
+            self.get_object_mut(page_id)
+                .and_then(Object::as_dict_mut)?
+                .set("Resources", resources);
+        }
         match resources_id {
             Some(res_id) => self.get_object_mut(res_id),
             None => self.get_or_create_resources_mut(page_id),
@@ -115,55 +127,7 @@ impl Document {
 
 #[test]
 fn create_document() {
-    use super::content::*;
-    use super::Stream;
-
-    let mut doc = Document::with_version("1.5");
-    let info_id = doc.add_object(dictionary! {
-        "Title" => Object::string_literal("Create PDF document example"),
-        "Creator" => Object::string_literal("https://crates.io/crates/lopdf"),
-        "CreationDate" => time::OffsetDateTime::now_utc(),
-    });
-    let pages_id = doc.new_object_id();
-    let font_id = doc.add_object(dictionary! {
-        "Type" => "Font",
-        "Subtype" => "Type1",
-        "BaseFont" => "Courier",
-    });
-    let resources_id = doc.add_object(dictionary! {
-        "Font" => dictionary! {
-            "F1" => font_id,
-        },
-    });
-    let content = Content {
-        operations: vec![
-            Operation::new("BT", vec![]),
-            Operation::new("Tf", vec!["F1".into(), 48.into()]),
-            Operation::new("Td", vec![100.into(), 600.into()]),
-            Operation::new("Tj", vec![Object::string_literal("Hello World!")]),
-            Operation::new("ET", vec![]),
-        ],
-    };
-    let content_id = doc.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
-    let page_id = doc.add_object(dictionary! {
-        "Type" => "Page",
-        "Parent" => pages_id,
-        "Contents" => content_id,
-    });
-    let pages = dictionary! {
-        "Type" => "Pages",
-        "Kids" => vec![page_id.into()],
-        "Count" => 1,
-        "Resources" => resources_id,
-        "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
-    };
-    doc.objects.insert(pages_id, Object::Dictionary(pages));
-    let catalog_id = doc.add_object(dictionary! {
-        "Type" => "Catalog",
-        "Pages" => pages_id,
-    });
-    doc.trailer.set("Root", catalog_id);
-    doc.trailer.set("Info", info_id);
+    let mut doc = crate::test_support::create_document();
     doc.compress();
 
     doc.save("test_1_create.pdf").unwrap();
@@ -1,6 +1,8 @@
 use super::Object;
+use crate::{Dictionary, Document, Error, Result};
 #[cfg(feature = "chrono_time")]
 use chrono::prelude::*;
+use std::fmt;
 
 use time::OffsetDateTime;
 use time::Time;
@@ -97,6 +99,264 @@ impl Object {
     }
 }
 
+/// Date in the `D:YYYYMMDDHHmmSSOHH'mm'` format of PDF strings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PdfDate {
+    pub year: u16,
+    /// Month from 1 to 12.
+    pub month: u8,
+    /// Day of the month from 1 to 31.
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    /// Offset of local time from UT in minutes, or none if the relationship to UT is unknown.
+    pub utc_offset: Option<i16>,
+}
+
+impl PdfDate {
+    pub fn new(year: u16, month: u8, day: u8, hour: u8, minute: u8, second: u8) -> PdfDate {
+        PdfDate {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+            utc_offset: None,
+        }
+    }
+
+    pub fn with_utc_offset(mut self, minutes: i16) -> PdfDate {
+        self.utc_offset = Some(minutes);
+        self
+    }
+
+    /// Parse a date, of which all fields but the year are optional, with or without the `D:` prefix.
+    pub fn parse(text: &[u8]) -> Result<PdfDate> {
+        let invalid = || Error::Syntax(format!("invalid date {:?}", String::from_utf8_lossy(text)));
+        let mut rest = text.strip_prefix(b"D:").unwrap_or(text);
+        let mut field = |digits: usize, default: u16, min: u16, max: u16| -> Result<u16> {
+            match rest.get(..digits) {
+                Some(field) if field.iter().all(u8::is_ascii_digit) => {
+                    rest = &rest[digits..];
+                    let value = field
+                        .iter()
+                        .fold(0, |value, digit| value * 10 + u16::from(digit - b'0'));
+                    if value < min || value > max {
+                        return Err(invalid());
+                    }
+                    Ok(value)
+                }
+                _ if digits == 4 => Err(invalid()),
+                _ => Ok(default),
+            }
+        };
+        let year = field(4, 0, 0, 9999)?;
+        let mut date = PdfDate::new(
+            year,
+            field(2, 1, 1, 12)? as u8,
+            field(2, 1, 1, 31)? as u8,
+            field(2, 0, 0, 23)? as u8,
+            field(2, 0, 0, 59)? as u8,
+            field(2, 0, 0, 59)? as u8,
+        );
+        let sign = match rest.first() {
+            None => return Ok(date),
+            Some(b'Z') => 0,
+            Some(b'+') => 1,
+            Some(b'-') => -1,
+            Some(_) => return Err(invalid()),
+        };
+        rest = &rest[1..];
+        let mut offset = |max: u16| -> Result<i16> {
+            match rest.get(..2) {
+                Some(field) if field.iter().all(u8::is_ascii_digit) => {
+                    let value = u16::from(field[0] - b'0') * 10 + u16::from(field[1] - b'0');
+                    rest = rest[2..].strip_prefix(b"'").unwrap_or(&rest[2..]);
+                    if value > max {
+                        return Err(invalid());
+                    }
+                    Ok(value as i16)
+                }
+                _ => Ok(0),
+            }
+        };
+        let hours = offset(23)?;
+        let minutes = offset(59)?;
+        if !rest.is_empty() {
+            return Err(invalid());
+        }
+        date.utc_offset = Some(sign * (hours * 60 + minutes));
+        Ok(date)
+    }
+
+    /// Get a date from a string object.
+    pub fn from_object(object: &Object) -> Result<PdfDate> {
+        PdfDate::parse(object.as_str()?)
+    }
+
+    /// Convert the date to a chrono date, taking dates without offset as UT.
+    #[cfg(feature = "chrono_time")]
+    pub fn to_chrono(&self) -> Option<DateTime<FixedOffset>> {
+        FixedOffset::east_opt(i32::from(self.utc_offset.unwrap_or(0)) * 60)?
+            .with_ymd_and_hms(
+                i32::from(self.year),
+                u32::from(self.month),
+                u32::from(self.day),
+                u32::from(self.hour),
+                u32::from(self.minute),
+                u32::from(self.second),
+            )
+            .single()
+    }
+
+    /// Convert the date to a time date, taking dates without offset as UT.
+    pub fn to_offset_date_time(&self) -> Option<OffsetDateTime> {
+        let date = time::Date::try_from_ymd(i32::from(self.year), self.month, self.day).ok()?;
+        let time = Time::try_from_hms(self.hour, self.minute, self.second).ok()?;
+        let offset = time::UtcOffset::minutes(self.utc_offset.unwrap_or(0));
+        Some(time::PrimitiveDateTime::new(date, time).assume_offset(offset))
+    }
+}
+
+impl fmt::Display for PdfDate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "D:{:04}{:02}{:02}{:02}{:02}{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )?;
+        match self.utc_offset {
+            None => Ok(()),
+            Some(0) => write!(f, "Z"),
+            Some(offset) => {
+                let sign = if offset < 0 { '-' } else { '+' };
+                let offset = offset.unsigned_abs();
+                write!(f, "{}{:02}'{:02}'", sign, offset / 60, offset % 60)
+            }
+        }
+    }
+}
+
+impl From<PdfDate> for Object {
+    fn from(date: PdfDate) -> Self {
+        Object::string_literal(date.to_string())
+    }
+}
+
+#[cfg(feature = "chrono_time")]
+impl<Tz: TimeZone> From<DateTime<Tz>> for PdfDate {
+    fn from(date: DateTime<Tz>) -> Self {
+        let offset = date.offset().fix().local_minus_utc() / 60;
+        PdfDate::new(
+            date.year() as u16,
+            date.month() as u8,
+            date.day() as u8,
+            date.hour() as u8,
+            date.minute() as u8,
+            date.second() as u8,
+        )
+        .with_utc_offset(offset as i16)
+    }
+}
+
+impl From<OffsetDateTime> for PdfDate {
+    fn from(date: OffsetDateTime) -> Self {
+        PdfDate::new(
+            date.year() as u16,
+            date.month(),
+            date.day(),
+            date.hour(),
+            date.minute(),
+            date.second(),
+        )
+        .with_utc_offset(date.offset().as_minutes())
+    }
+}
+
+impl Document {
+    /// Get the creation date of the document information dictionary.
+    pub fn creation_date(&self) -> Result<PdfDate> {
+        self.info_date(b"CreationDate")
+    }
+
+    /// Get the modification date of the document information dictionary.
+    pub fn mod_date(&self) -> Result<PdfDate> {
+        self.info_date(b"ModDate")
+    }
+
+    /// Set the creation date of the document information dictionary, creating it if needed.
+    pub fn set_creation_date<D: Into<PdfDate>>(&mut self, date: D) {
+        self.info_mut().set("CreationDate", date.into());
+    }
+
+    /// Set the modification date of the document information dictionary, creating it if needed.
+    pub fn set_mod_date<D: Into<PdfDate>>(&mut self, date: D) {
+        self.info_mut().set("ModDate", date.into());
+    }
+
+    fn info_date(&self, key: &[u8]) -> Result<PdfDate> {
+        let info = self.trailer.get_deref(b"Info", self)?.as_dict()?;
+        PdfDate::from_object(info.get_deref(key, self)?)
+    }
+
+    fn info_mut(&mut self) -> &mut Dictionary {
+        let info_id = match self.trailer.get(b"Info") {
+            Ok(Object::Reference(id)) if self.get_dictionary(*id).is_ok() => Some(*id),
+            Ok(Object::Dictionary(_)) => None,
+            _ => {
+                let info_id = self.add_object(Dictionary::new());
+                self.trailer.set("Info", info_id);
+                Some(info_id)
+            }
+        };
+        let info = match info_id {
+            Some(id) => self.get_object_mut(id),
+            None => self.trailer.get_mut(b"Info"),
+        };
+        info.and_then(Object::as_dict_mut)
+            .expect("information dictionary was checked or created")
+    }
+}
+
+#[test]
+fn format_and_parse_pdf_dates() {
+    let date = PdfDate::parse(b"D:199812231952-08'00'").unwrap();
+    assert_eq!(date, PdfDate::new(1998, 12, 23, 19, 52, 0).with_utc_offset(-480));
+    assert_eq!(date.to_string(), "D:19981223195200-08'00'");
+    assert_eq!(PdfDate::parse(b"D:2004").unwrap(), PdfDate::new(2004, 1, 1, 0, 0, 0));
+    assert_eq!(PdfDate::parse(b"20040229103000+05'30").unwrap().utc_offset, Some(330));
+    assert_eq!(
+        PdfDate::parse(b"D:20040229103000Z").unwrap().to_string(),
+        "D:20040229103000Z"
+    );
+    assert!(PdfDate::parse(b"D:20041329").is_err());
+    assert!(PdfDate::parse(b"D:04").is_err());
+    assert!(PdfDate::parse(b"D:20040229X").is_err());
+
+    let time = date.to_offset_date_time().unwrap();
+    assert_eq!(time.offset().as_minutes(), -480);
+    assert_eq!(PdfDate::from(time), date);
+    #[cfg(feature = "chrono_time")]
+    {
+        let chrono = date.to_chrono().unwrap();
+        assert_eq!(chrono.to_rfc3339(), "1998-12-23T19:52:00-08:00");
+        assert_eq!(PdfDate::from(chrono), date);
+    }
+
+    let mut doc = Document::new();
+    assert!(doc.creation_date().is_err());
+    doc.set_creation_date(date);
+    doc.set_mod_date(PdfDate::new(2020, 1, 2, 3, 4, 5).with_utc_offset(0));
+    assert_eq!(doc.creation_date().unwrap(), date);
+    let info = doc.trailer.get_deref(b"Info", &doc).and_then(Object::as_dict).unwrap();
+    assert_eq!(
+        info.get(b"ModDate").and_then(Object::as_str).unwrap(),
+        b"D:20200102030405Z"
+    );
+}
+
 #[cfg(feature = "chrono_time")]
 #[test]
 fn parse_datetime_local() {
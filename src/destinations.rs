@@ -0,0 +1,111 @@
+use crate::{Document, Object, Result};
+use std::collections::BTreeMap;
+
+impl Document {
+    /// Get the named destinations of the /Dests dictionary of the catalog and of the /Dests name tree, sorted by
+    /// name. Names of the name tree take precedence.
+    ///
+    /// Destinations are explicit destination arrays, such as `[page /Fit]`, also for destinations given as
+    /// dictionaries with a /D entry.
+    pub fn named_destinations(&self) -> Result<impl Iterator<Item = (Vec<u8>, Object)>> {
+        let mut destinations = BTreeMap::new();
+        if let Ok(dests) = self.catalog()?.get(b"Dests") {
+            if let Ok(dests) = self.dereference(dests).and_then(|(_, dests)| dests.as_dict()) {
+                destinations.extend(dests.iter().map(|(name, dest)| (name.clone(), dest.clone())));
+            }
+        }
+        destinations.extend(self.get_name_tree(b"Dests")?);
+
+        let destination_array = |dest: &Object| {
+            let dest = match self.dereference(dest).ok()?.1 {
+                Object::Dictionary(dict) => self.dereference(dict.get(b"D").ok()?).ok()?.1,
+                dest => dest,
+            };
+            dest.as_array().ok().map(|array| Object::Array(array.clone()))
+        };
+        let destinations: Vec<(Vec<u8>, Object)> = destinations
+            .into_iter()
+            .filter_map(|(name, dest)| Some((name, destination_array(&dest)?)))
+            .collect();
+        Ok(destinations.into_iter())
+    }
+
+    /// Add a named destination to the /Dests name tree, replacing a destination with the same name.
+    ///
+    /// The name tree is rebuilt balanced, and the name is removed from the /Dests dictionary of the catalog.
+    pub fn add_named_destination<N: Into<Vec<u8>>>(&mut self, name: N, destination: Object) -> Result<()> {
+        let name = name.into();
+        let catalog_id = self.trailer.get(b"Root").and_then(Object::as_reference)?;
+        let dests = match self.catalog()?.get(b"Dests") {
+            Ok(&Object::Reference(id)) => self.get_object_mut(id).and_then(Object::as_dict_mut).ok(),
+            Ok(&Object::Dictionary(_)) => self
+                .get_object_mut(catalog_id)
+                .and_then(Object::as_dict_mut)?
+                .get_mut(b"Dests")
+                .and_then(Object::as_dict_mut)
+                .ok(),
+            _ => None,
+        };
+        if let Some(dests) = dests {
+            dests.remove(&name);
+        }
+
+        let mut entries = self.get_name_tree(b"Dests")?;
+        entries.retain(|(other_name, _)| *other_name != name);
+        entries.push((name, destination));
+        self.set_name_tree(b"Dests", entries)
+    }
+}
+
+#[test]
+fn add_and_read_named_destinations() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    let catalog_id = doc.trailer.get(b"Root").and_then(Object::as_reference).unwrap();
+    let fit = |top: i64| Object::Array(vec![page_id.into(), "FitH".into(), top.into()]);
+    let action_id = doc.add_object(dictionary! { "D" => fit(1) });
+    doc.get_object_mut(catalog_id)
+        .and_then(Object::as_dict_mut)
+        .unwrap()
+        .set(
+            "Dests",
+            dictionary! { "Old" => fit(2), "Replaced" => fit(3), "Action" => action_id },
+        );
+
+    for index in 0..100 {
+        doc.add_named_destination(format!("Name{:03}", index), fit(index))
+            .unwrap();
+    }
+    doc.add_named_destination("Replaced", fit(4)).unwrap();
+    doc.add_named_destination("Name050", fit(5)).unwrap();
+
+    let destinations: Vec<(Vec<u8>, Object)> = doc.named_destinations().unwrap().collect();
+    assert_eq!(destinations.len(), 103);
+    let top = |name: &[u8]| {
+        let (_, dest) = destinations.iter().find(|(other_name, _)| other_name == name).unwrap();
+        dest.as_array().unwrap()[2].as_i64().unwrap()
+    };
+    assert_eq!(top(b"Action"), 1);
+    assert_eq!(top(b"Old"), 2);
+    assert_eq!(top(b"Replaced"), 4);
+    assert_eq!(top(b"Name050"), 5);
+    assert_eq!(top(b"Name099"), 99);
+
+    // 101 names of the tree are split into four leaves of at most 32 names, with their limits.
+    let names = doc.catalog().unwrap().get(b"Names").and_then(Object::as_dict).unwrap();
+    let root = doc
+        .dereference(names.get(b"Dests").unwrap())
+        .unwrap()
+        .1
+        .as_dict()
+        .unwrap();
+    assert!(!root.has(b"Limits"));
+    let kids = root.get(b"Kids").and_then(Object::as_array).unwrap();
+    assert_eq!(kids.len(), 4);
+    let last = doc.get_dictionary(kids[3].as_reference().unwrap()).unwrap();
+    let limits = last.get(b"Limits").and_then(Object::as_array).unwrap();
+    assert_eq!(limits[0].as_str().unwrap(), b"Name096");
+    assert_eq!(limits[1].as_str().unwrap(), b"Replaced");
+}
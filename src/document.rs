@@ -1,7 +1,8 @@
 use super::encodings::{self, bytes_to_string, string_to_bytes};
 use super::{Dictionary, Object, ObjectId};
+use crate::incremental::{LoadedState, Revision};
 use crate::xref::Xref;
-use crate::{Error, Result};
+use crate::{Error, LoadWarning, Result};
 use encoding::all::UTF_16BE;
 use encoding::types::{DecoderTrap, EncoderTrap, Encoding};
 use log::info;
@@ -27,6 +28,22 @@ pub struct Document {
 
     /// Current maximum object id within the document.
     pub max_id: u32,
+
+    /// Byte offset of the last cross-reference section of the file the document was loaded from.
+    pub xref_start: usize,
+
+    /// State of the document when it was loaded, used to find changed objects for incremental updates.
+    pub(crate) loaded_state: Option<LoadedState>,
+
+    /// Revisions of the file the document was loaded from.
+    pub(crate) revisions: Vec<Revision>,
+
+    /// Malformed constructs which were tolerated while loading the document.
+    pub(crate) load_warnings: Vec<LoadWarning>,
+
+    /// Encryption dictionary and file identifier of the document before it was decrypted.
+    #[cfg(feature = "crypto")]
+    pub(crate) original_encryption: Option<(Dictionary, Vec<u8>)>,
 }
 
 impl Document {
@@ -38,9 +55,25 @@ impl Document {
             reference_table: Xref::new(0),
             objects: BTreeMap::new(),
             max_id: 0,
+            xref_start: 0,
+            loaded_state: None,
+            revisions: Vec::new(),
+            load_warnings: Vec::new(),
+            #[cfg(feature = "crypto")]
+            original_encryption: None,
         }
     }
 
+    /// Check whether the document is encrypted.
+    pub fn is_encrypted(&self) -> bool {
+        self.trailer.has(b"Encrypt")
+    }
+
+    /// Get the malformed constructs which were tolerated while the document was loaded, in file order.
+    pub fn load_warnings(&self) -> &[LoadWarning] {
+        &self.load_warnings
+    }
+
     const DEREF_LIMIT: usize = 128;
 
     /// Follow references if the supplied object is a reference.
@@ -263,7 +296,7 @@ impl Document {
                 "MacRomanEncoding" => bytes_to_string(encodings::MAC_ROMAN_ENCODING, bytes),
                 "MacExpertEncoding" => bytes_to_string(encodings::MAC_EXPERT_ENCODING, bytes),
                 "WinAnsiEncoding" => bytes_to_string(encodings::WIN_ANSI_ENCODING, bytes),
-                "UniGB-UCS2-H" | "UniGB−UTF16−H" => UTF_16BE.decode(bytes, DecoderTrap::Ignore).unwrap(),
+                "UniGB-UCS2-H" | "UniGB-UTF16-H" => UTF_16BE.decode(bytes, DecoderTrap::Ignore).unwrap(),
                 "Identity-H" => "?Identity-H Unimplemented?".to_string(), // Unimplemented
                 _ => String::from_utf8_lossy(bytes).to_string(),
             }
@@ -279,7 +312,7 @@ impl Document {
                 "MacRomanEncoding" => string_to_bytes(encodings::MAC_ROMAN_ENCODING, text),
                 "MacExpertEncoding" => string_to_bytes(encodings::MAC_EXPERT_ENCODING, text),
                 "WinAnsiEncoding" => string_to_bytes(encodings::WIN_ANSI_ENCODING, text),
-                "UniGB-UCS2-H" | "UniGB−UTF16−H" => UTF_16BE.encode(text, EncoderTrap::Ignore).unwrap(),
+                "UniGB-UCS2-H" | "UniGB-UTF16-H" => UTF_16BE.encode(text, EncoderTrap::Ignore).unwrap(),
                 "Identity-H" => vec![], // Unimplemented
                 _ => text.as_bytes().to_vec(),
             }
@@ -17,3 +17,30 @@ pub fn string_to_bytes(encoding: [Option<u16>; 256], text: &str) -> Vec<u8> {
         .map(|byte| byte as u8)
         .collect()
 }
+
+/// Decode a text string, which is either UTF-16BE or UTF-8 with a byte order mark, or PDFDocEncoding.
+pub fn decode_text_string(bytes: &[u8]) -> String {
+    if bytes.starts_with(&[0xFE, 0xFF]) {
+        let code_units = bytes[2..]
+            .chunks(2)
+            .map(|pair| u16::from_be_bytes([pair[0], *pair.get(1).unwrap_or(&0)]))
+            .collect::<Vec<u16>>();
+        String::from_utf16_lossy(&code_units)
+    } else if bytes.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        String::from_utf8_lossy(&bytes[3..]).into_owned()
+    } else {
+        // PDFDocEncoding matches Latin-1 apart from a few rarely used characters.
+        bytes.iter().map(|&byte| char::from(byte)).collect()
+    }
+}
+
+/// Encode a text string, as PDFDocEncoding if it's ASCII or as UTF-16BE with a byte order mark otherwise.
+pub fn encode_text_string(text: &str) -> Vec<u8> {
+    if text.is_ascii() {
+        text.as_bytes().to_vec()
+    } else {
+        let mut bytes = vec![0xFE, 0xFF];
+        bytes.extend(text.encode_utf16().flat_map(u16::to_be_bytes));
+        bytes
+    }
+}
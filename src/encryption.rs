@@ -0,0 +1,987 @@
+use crate::error::{DecryptionError, ErrorContext};
+use crate::{Dictionary, Document, Error, Object, ObjectId, Result, Stream};
+use aes::cipher::block_padding::{NoPadding, Pkcs7};
+use aes::cipher::generic_array::GenericArray;
+use aes::cipher::{BlockDecrypt, BlockDecryptMut, BlockEncrypt, BlockEncryptMut, KeyInit, KeyIvInit};
+use aes::{Aes128, Aes256};
+use log::warn;
+use md5::{Digest, Md5};
+use sha2::{Sha256, Sha384, Sha512};
+use std::collections::BTreeMap;
+use std::ops::BitOr;
+
+/// Padding string used to pad passwords to 32 bytes.
+const PADDING: [u8; 32] = [
+    0x28, 0xBF, 0x4E, 0x5E, 0x4E, 0x75, 0x8A, 0x41, 0x64, 0x00, 0x4E, 0x56, 0xFF, 0xFA, 0x01, 0x08, 0x2E, 0x2E, 0x00,
+    0xB6, 0xD0, 0x68, 0x3E, 0x80, 0x2F, 0x0C, 0xA9, 0xFE, 0x64, 0x53, 0x69, 0x7A,
+];
+
+impl Document {
+    /// Decrypt the document with the user or the owner password.
+    ///
+    /// Strings and streams are decrypted in place and the encryption dictionary is removed, so the document
+    /// is saved unencrypted, unless it's saved with `save_with_original_encryption`. Encrypted documents are
+    /// decrypted with an empty password while loading, so this is only needed for documents which have a user
+    /// password.
+    pub fn decrypt(&mut self, password: &str) -> Result<()> {
+        self.decrypt_with(|encrypt, file_id| SecurityHandler::new(encrypt, file_id, password.as_bytes()))
+    }
+
+    /// Decrypt the document with the security handler created from its encryption dictionary and the first part
+    /// of its file identifier.
+    pub(crate) fn decrypt_with<F>(&mut self, security_handler: F) -> Result<()>
+    where
+        F: FnOnce(&Dictionary, &[u8]) -> Result<SecurityHandler>,
+    {
+        let (encrypt_id, encrypt) = match self.trailer.get(b"Encrypt") {
+            Ok(Object::Reference(id)) => (Some(*id), self.get_dictionary(*id)?.clone()),
+            Ok(Object::Dictionary(dict)) => (None, dict.clone()),
+            Ok(_) => return Err(Error::Decryption(DecryptionError::InvalidEncryptDictionary)),
+            Err(_) => return Ok(()),
+        };
+        let file_id = self.file_id().unwrap_or(&[]);
+        let handler = security_handler(&encrypt, file_id).map_err(|e| match encrypt_id {
+            Some(id) => e.with_context(ErrorContext::in_object(id)),
+            None => e.with_context(ErrorContext::default().with_key(b"Encrypt")),
+        })?;
+
+        self.original_encryption = Some((encrypt, file_id.to_vec()));
+        if let Some(id) = encrypt_id {
+            self.objects.remove(&id);
+        }
+        self.trailer.remove(b"Encrypt");
+        for (&id, object) in self.objects.iter_mut() {
+            handler.decrypt_object(id, object);
+        }
+        // Objects replaced by incremental updates are kept to roll back to earlier revisions.
+        for revision in self.revisions.iter_mut() {
+            for (&id, object) in revision.replaced.iter_mut() {
+                handler.decrypt_object(id, object);
+            }
+        }
+
+        // Objects in object streams could only be read now.
+        #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+        {
+            let mut objects = BTreeMap::new();
+            for object in self.objects.values_mut() {
+                if let Object::Stream(ref mut stream) = *object {
+                    if stream.dict.type_is(b"ObjStm") {
+                        if let Ok(object_stream) = crate::object_stream::ObjectStream::new(stream) {
+                            objects.extend(object_stream.objects);
+                        }
+                    }
+                }
+            }
+            self.objects.extend(objects);
+        }
+        Ok(())
+    }
+
+    /// Get the encryption dictionary the document had before it was decrypted.
+    pub fn original_encryption(&self) -> Option<&Dictionary> {
+        self.original_encryption.as_ref().map(|(encrypt, _)| encrypt)
+    }
+
+    /// Get a security handler for the encryption the document had before it was decrypted, with the encryption
+    /// dictionary. The first part of the file identifier, which is an input to the key, is restored if changed.
+    pub(crate) fn original_security_handler(&mut self, password: &str) -> Result<(SecurityHandler, Dictionary)> {
+        let (encrypt, file_id) = self
+            .original_encryption
+            .clone()
+            .ok_or(Error::Decryption(DecryptionError::NotEncrypted))?;
+        let handler = SecurityHandler::new(&encrypt, &file_id, password.as_bytes())?;
+        if self.file_id() != Some(&file_id[..]) {
+            let id = Object::String(file_id, crate::StringFormat::Hexadecimal);
+            self.trailer.set("ID", vec![id.clone(), id]);
+        }
+        Ok((handler, encrypt))
+    }
+
+    /// Get the first part of the file identifier, which is an input to the encryption key.
+    pub(crate) fn file_id(&self) -> Option<&[u8]> {
+        self.trailer
+            .get(b"ID")
+            .and_then(Object::as_array)
+            .ok()
+            .and_then(|id| id.first())
+            .and_then(|id| id.as_str().ok())
+    }
+}
+
+/// Encryption algorithm used when saving an encrypted document.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EncryptionAlgorithm {
+    /// RC4 with a 128-bit key (PDF 1.4).
+    Rc4,
+    /// AES with a 128-bit key (PDF 1.6).
+    Aes128,
+    /// AES with a 256-bit key (PDF 2.0).
+    Aes256,
+}
+
+/// Permissions granted when a document is opened with the user password.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Permissions(u32);
+
+impl Permissions {
+    pub const PRINT: Permissions = Permissions(1 << 2);
+    pub const MODIFY: Permissions = Permissions(1 << 3);
+    pub const COPY: Permissions = Permissions(1 << 4);
+    pub const ANNOTATE: Permissions = Permissions(1 << 5);
+    pub const FILL_FORMS: Permissions = Permissions(1 << 8);
+    pub const EXTRACT_FOR_ACCESSIBILITY: Permissions = Permissions(1 << 9);
+    pub const ASSEMBLE: Permissions = Permissions(1 << 10);
+    pub const PRINT_HIGH_QUALITY: Permissions = Permissions(1 << 11);
+
+    pub fn empty() -> Permissions {
+        Permissions(0)
+    }
+
+    pub fn all() -> Permissions {
+        Permissions(0b1111_0011_1100)
+    }
+
+    pub fn contains(self, other: Permissions) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Get the value of the P entry, with the reserved bits set as required.
+    pub fn to_p_value(self) -> i32 {
+        ((self.0 & Permissions::all().0) | 0xFFFF_F0C0) as i32
+    }
+}
+
+impl Default for Permissions {
+    fn default() -> Self {
+        Permissions::all()
+    }
+}
+
+impl BitOr for Permissions {
+    type Output = Permissions;
+
+    fn bitor(self, other: Permissions) -> Permissions {
+        Permissions(self.0 | other.0)
+    }
+}
+
+/// Options for encrypting a document with the standard security handler when saving.
+#[derive(Debug, Clone)]
+pub struct EncryptionOptions {
+    /// Password needed to open the document, may be empty.
+    pub user_password: String,
+    /// Password granting all permissions, the user password is used when it's empty.
+    pub owner_password: String,
+    pub permissions: Permissions,
+    pub algorithm: EncryptionAlgorithm,
+}
+
+/// Method used by a crypt filter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum CryptMethod {
+    Identity,
+    Rc4,
+    Aes128,
+    Aes256,
+}
+
+/// Methods of the crypt filters of an encryption dictionary by name.
+type CryptFilters = BTreeMap<Vec<u8>, CryptMethod>;
+
+/// Security handler of an encrypted document, holding the file encryption key.
+#[derive(Debug, Clone)]
+pub(crate) struct SecurityHandler {
+    key: Vec<u8>,
+    string_method: CryptMethod,
+    stream_method: CryptMethod,
+    filters: CryptFilters,
+    encrypt_metadata: bool,
+}
+
+impl SecurityHandler {
+    /// Get the file encryption key from the encryption dictionary with the user or the owner password.
+    pub fn new(encrypt: &Dictionary, file_id: &[u8], password: &[u8]) -> Result<SecurityHandler> {
+        let invalid = || Error::Decryption(DecryptionError::InvalidEncryptDictionary);
+        if encrypt
+            .get(b"Filter")
+            .and_then(Object::as_name)
+            .map_err(|_| invalid())?
+            != b"Standard"
+        {
+            return Err(Error::Decryption(DecryptionError::UnsupportedEncryption));
+        }
+        let revision = encrypt.get(b"R").and_then(Object::as_i64).map_err(|_| invalid())?;
+        let owner = encrypt.get(b"O").and_then(Object::as_str).map_err(|_| invalid())?;
+        let user = encrypt.get(b"U").and_then(Object::as_str).map_err(|_| invalid())?;
+        let permissions = encrypt.get(b"P").and_then(Object::as_i64).map_err(|_| invalid())? as i32;
+        let encrypt_metadata = match encrypt.get(b"EncryptMetadata") {
+            Ok(Object::Boolean(value)) => *value,
+            _ => true,
+        };
+
+        let (key_length, string_method, stream_method, filters) = crypt_methods(encrypt)?;
+        let key = match revision {
+            2..=4 => {
+                let key_length = if revision == 2 { 5 } else { key_length.min(16) };
+                let key = |password: &[u8]| {
+                    file_key(
+                        password,
+                        owner,
+                        permissions,
+                        file_id,
+                        revision,
+                        key_length,
+                        encrypt_metadata,
+                    )
+                };
+                let user_key = key(password);
+                if check_user_key(&user_key, user, file_id, revision) {
+                    user_key
+                } else {
+                    let user_password = user_password_from_owner(password, owner, revision, key_length);
+                    let owner_key = key(&user_password);
+                    if !check_user_key(&owner_key, user, file_id, revision) {
+                        return Err(Error::Decryption(DecryptionError::IncorrectPassword));
+                    }
+                    owner_key
+                }
+            }
+            5 | 6 => {
+                let owner_encrypted = encrypt.get(b"OE").and_then(Object::as_str).map_err(|_| invalid())?;
+                let user_encrypted = encrypt.get(b"UE").and_then(Object::as_str).map_err(|_| invalid())?;
+                let key = file_key_aes256(password, owner, user, owner_encrypted, user_encrypted, revision)?;
+                if let Ok(perms) = encrypt.get(b"Perms").and_then(Object::as_str) {
+                    check_permissions(&key, perms, permissions)?;
+                }
+                key
+            }
+            _ => return Err(Error::Decryption(DecryptionError::UnsupportedEncryption)),
+        };
+
+        Ok(SecurityHandler {
+            key,
+            string_method,
+            stream_method,
+            filters,
+            encrypt_metadata,
+        })
+    }
+
+    /// Create a security handler for an encryption dictionary with a file encryption key derived by another
+    /// security handler, which is given the length of the key in bytes.
+    #[cfg(feature = "pubsec")]
+    pub fn from_key<F>(encrypt: &Dictionary, encrypt_metadata: bool, key: F) -> Result<SecurityHandler>
+    where
+        F: FnOnce(usize) -> Vec<u8>,
+    {
+        let (key_length, string_method, stream_method, filters) = crypt_methods(encrypt)?;
+        Ok(SecurityHandler {
+            key: key(key_length),
+            string_method,
+            stream_method,
+            filters,
+            encrypt_metadata,
+        })
+    }
+
+    /// Create a security handler with a new file encryption key, together with the encryption dictionary.
+    pub fn for_encryption(options: &EncryptionOptions, file_id: &[u8]) -> (SecurityHandler, Dictionary) {
+        let user_password = options.user_password.as_bytes();
+        let owner_password = if options.owner_password.is_empty() {
+            user_password
+        } else {
+            options.owner_password.as_bytes()
+        };
+        let permissions = options.permissions.to_p_value();
+
+        let (key, method, mut encrypt) = match options.algorithm {
+            EncryptionAlgorithm::Rc4 | EncryptionAlgorithm::Aes128 => {
+                let revision = if options.algorithm == EncryptionAlgorithm::Rc4 {
+                    3
+                } else {
+                    4
+                };
+                let owner = owner_hash(owner_password, user_password, revision, 16);
+                let key = file_key(user_password, &owner, permissions, file_id, revision, 16, true);
+                let mut user = user_hash(&key, file_id, revision);
+                user.resize(32, 0);
+                let mut encrypt = dictionary! {
+                    "V" => if revision == 3 { 2 } else { 4 },
+                    "R" => revision,
+                    "O" => Object::string_literal(owner),
+                    "U" => Object::string_literal(user),
+                };
+                if options.algorithm == EncryptionAlgorithm::Rc4 {
+                    (key, CryptMethod::Rc4, encrypt)
+                } else {
+                    encrypt.set(
+                        "CF",
+                        dictionary! {
+                            "StdCF" => dictionary! { "CFM" => "AESV2", "AuthEvent" => "DocOpen", "Length" => 16 },
+                        },
+                    );
+                    (key, CryptMethod::Aes128, encrypt)
+                }
+            }
+            EncryptionAlgorithm::Aes256 => {
+                let user_password = &user_password[..user_password.len().min(127)];
+                let owner_password = &owner_password[..owner_password.len().min(127)];
+                let key: [u8; 32] = rand::random();
+                let salts: [u8; 32] = rand::random();
+                let iv = [0; 16];
+                let encrypt_key = |intermediate_key: &[u8]| {
+                    cbc::Encryptor::<Aes256>::new_from_slices(intermediate_key, &iv)
+                        .expect("key and iv have a valid length")
+                        .encrypt_padded_vec_mut::<NoPadding>(&key)
+                };
+
+                let mut user = hash_aes256(user_password, &salts[..8], &[], 6);
+                user.extend_from_slice(&salts[..16]);
+                let user_encrypted = encrypt_key(&hash_aes256(user_password, &salts[8..16], &[], 6));
+                let mut owner = hash_aes256(owner_password, &salts[16..24], &user, 6);
+                owner.extend_from_slice(&salts[16..32]);
+                let owner_encrypted = encrypt_key(&hash_aes256(owner_password, &salts[24..32], &user, 6));
+                let mut perms = permissions.to_le_bytes().to_vec();
+                perms.extend_from_slice(b"\xFF\xFF\xFF\xFFTadb");
+                perms.extend_from_slice(&salts[..4]);
+
+                let encrypt = dictionary! {
+                    "V" => 5,
+                    "R" => 6,
+                    "O" => Object::string_literal(owner),
+                    "U" => Object::string_literal(user),
+                    "OE" => Object::string_literal(owner_encrypted),
+                    "UE" => Object::string_literal(user_encrypted),
+                    "Perms" => Object::string_literal(aes256_encrypt_block(&key, &perms)),
+                    "CF" => dictionary! {
+                        "StdCF" => dictionary! { "CFM" => "AESV3", "AuthEvent" => "DocOpen", "Length" => 32 },
+                    },
+                };
+                (key.to_vec(), CryptMethod::Aes256, encrypt)
+            }
+        };
+        encrypt.set("Filter", "Standard");
+        encrypt.set("Length", key.len() as i64 * 8);
+        encrypt.set("P", i64::from(permissions));
+        let mut filters = BTreeMap::new();
+        if method != CryptMethod::Rc4 {
+            encrypt.set("StmF", "StdCF");
+            encrypt.set("StrF", "StdCF");
+            filters.insert(b"StdCF".to_vec(), method);
+        }
+
+        let handler = SecurityHandler {
+            key,
+            string_method: method,
+            stream_method: method,
+            filters,
+            encrypt_metadata: true,
+        };
+        (handler, encrypt)
+    }
+
+    /// Decrypt strings and stream content of an indirect object in place.
+    pub fn decrypt_object(&self, id: ObjectId, object: &mut Object) {
+        self.crypt_object(id, object, false)
+    }
+
+    /// Encrypt strings and stream content of an indirect object in place.
+    pub fn encrypt_object(&self, id: ObjectId, object: &mut Object) {
+        self.crypt_object(id, object, true)
+    }
+
+    fn crypt_object(&self, id: ObjectId, object: &mut Object, encrypt: bool) {
+        match *object {
+            Object::String(ref mut bytes, _) => {
+                if let Some(result) = self.crypt(id, bytes, self.string_method, encrypt) {
+                    *bytes = result;
+                }
+            }
+            Object::Array(ref mut array) => {
+                for item in array.iter_mut() {
+                    self.crypt_object(id, item, encrypt);
+                }
+            }
+            Object::Dictionary(ref mut dict) => self.crypt_dictionary(id, dict, encrypt),
+            Object::Stream(ref mut stream) => {
+                // Cross-reference streams aren't encrypted.
+                if stream.dict.type_is(b"XRef") {
+                    return;
+                }
+                self.crypt_dictionary(id, &mut stream.dict, encrypt);
+                let method = if !encrypt {
+                    self.stream_crypt_method(stream)
+                } else if stream.dict.type_is(b"Metadata") && !self.encrypt_metadata {
+                    CryptMethod::Identity
+                } else {
+                    // Streams keep their crypt filter, so they're decrypted with the same method.
+                    self.crypt_filter_method(stream).unwrap_or(self.stream_method)
+                };
+                if let Some(result) = self.crypt(id, &stream.content, method, encrypt) {
+                    stream.set_content(result);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn crypt_dictionary(&self, id: ObjectId, dict: &mut Dictionary, encrypt: bool) {
+        // The contents of signatures aren't encrypted.
+        let is_signature = dict.has(b"ByteRange");
+        for (key, value) in dict.iter_mut() {
+            if !(is_signature && key == b"Contents") {
+                self.crypt_object(id, value, encrypt);
+            }
+        }
+    }
+
+    /// Get the method for a stream to decrypt, removing a crypt filter from its filters.
+    fn stream_crypt_method(&self, stream: &mut Stream) -> CryptMethod {
+        if stream.dict.type_is(b"Metadata") && !self.encrypt_metadata {
+            return CryptMethod::Identity;
+        }
+        let method = match self.crypt_filter_method(stream) {
+            Some(method) => method,
+            None => return self.stream_method,
+        };
+        for key in [&b"Filter"[..], &b"DecodeParms"[..]].iter() {
+            let remove = match stream.dict.get_mut(key) {
+                Ok(Object::Array(array)) => {
+                    if !array.is_empty() {
+                        array.remove(0);
+                    }
+                    array.is_empty()
+                }
+                _ => true,
+            };
+            if remove {
+                stream.dict.remove(key);
+            }
+        }
+        method
+    }
+
+    /// Get the method of the crypt filter of a stream, which is the first of its filters if any.
+    /// The Identity filter and filters which aren't in the encryption dictionary leave data as it is.
+    fn crypt_filter_method(&self, stream: &Stream) -> Option<CryptMethod> {
+        let has_crypt_filter = stream
+            .filters()
+            .map(|filters| filters.first().map(String::as_str) == Some("Crypt"))
+            .unwrap_or(false);
+        if !has_crypt_filter {
+            return None;
+        }
+        let params = match stream.dict.get(b"DecodeParms") {
+            Ok(Object::Array(params)) => params.first().and_then(|params| params.as_dict().ok()),
+            Ok(Object::Dictionary(params)) => Some(params),
+            _ => None,
+        };
+        let method = match params.and_then(|params| params.get(b"Name").and_then(Object::as_name).ok()) {
+            Some(name) => self.filters.get(name).cloned().unwrap_or(CryptMethod::Identity),
+            None => CryptMethod::Identity,
+        };
+        Some(method)
+    }
+
+    fn crypt(&self, id: ObjectId, data: &[u8], method: CryptMethod, encrypt: bool) -> Option<Vec<u8>> {
+        let result = match (method, encrypt) {
+            (CryptMethod::Identity, _) => return None,
+            (CryptMethod::Rc4, _) => Some(rc4(&self.object_key(id, false), data)),
+            (CryptMethod::Aes128, false) => aes_cbc_decrypt(&self.object_key(id, true), data),
+            (CryptMethod::Aes256, false) => aes_cbc_decrypt(&self.key, data),
+            (CryptMethod::Aes128, true) => Some(aes_cbc_encrypt(&self.object_key(id, true), data)),
+            (CryptMethod::Aes256, true) => Some(aes_cbc_encrypt(&self.key, data)),
+        };
+        if result.is_none() {
+            warn!("Could not decrypt data of object {} {}.", id.0, id.1);
+        }
+        result
+    }
+
+    /// Get the key for an object, the file encryption key is used as is by AES-256.
+    fn object_key(&self, id: ObjectId, aes: bool) -> Vec<u8> {
+        let mut hasher = Md5::new();
+        hasher.update(&self.key);
+        hasher.update(&id.0.to_le_bytes()[..3]);
+        hasher.update(id.1.to_le_bytes());
+        if aes {
+            hasher.update(b"sAlT");
+        }
+        let hash = hasher.finalize();
+        hash[..(self.key.len() + 5).min(16)].to_vec()
+    }
+}
+
+/// Get the length of the file encryption key in bytes, and the crypt methods for strings and streams and of the
+/// crypt filters, from an encryption dictionary.
+fn crypt_methods(encrypt: &Dictionary) -> Result<(usize, CryptMethod, CryptMethod, CryptFilters)> {
+    let version = encrypt.get(b"V").and_then(Object::as_i64).unwrap_or(0);
+    let length = encrypt.get(b"Length").and_then(Object::as_i64).ok();
+    let mut filters = BTreeMap::new();
+    let (key_length, string_method, stream_method) = match version {
+        1 => (5, CryptMethod::Rc4, CryptMethod::Rc4),
+        2 => (length.unwrap_or(40) as usize / 8, CryptMethod::Rc4, CryptMethod::Rc4),
+        4 | 5 => {
+            if let Ok(crypt_filters) = encrypt.get(b"CF").and_then(Object::as_dict) {
+                for (name, filter) in crypt_filters.iter() {
+                    let method = match filter.as_dict().and_then(|filter| filter.get(b"CFM")) {
+                        Ok(Object::Name(method)) if method == b"V2" => CryptMethod::Rc4,
+                        Ok(Object::Name(method)) if method == b"AESV2" => CryptMethod::Aes128,
+                        Ok(Object::Name(method)) if method == b"AESV3" => CryptMethod::Aes256,
+                        _ => CryptMethod::Identity,
+                    };
+                    filters.insert(name.clone(), method);
+                }
+            }
+            let method = |key: &[u8]| match encrypt.get(key).and_then(Object::as_name) {
+                Ok(name) => filters.get(name).cloned().unwrap_or(CryptMethod::Identity),
+                Err(_) => CryptMethod::Identity,
+            };
+            let key_length = if version == 5 {
+                32
+            } else {
+                length.unwrap_or(128) as usize / 8
+            };
+            (key_length, method(b"StrF"), method(b"StmF"))
+        }
+        _ => return Err(Error::Decryption(DecryptionError::UnsupportedEncryption)),
+    };
+    let key_length = key_length.clamp(5, 32);
+    Ok((key_length, string_method, stream_method, filters))
+}
+
+fn pad_password(password: &[u8]) -> Vec<u8> {
+    let password = &password[..password.len().min(32)];
+    password.iter().chain(PADDING.iter()).take(32).cloned().collect()
+}
+
+/// Compute the file encryption key for revisions 2 to 4 (algorithm 2).
+fn file_key(
+    password: &[u8], owner: &[u8], permissions: i32, file_id: &[u8], revision: i64, key_length: usize,
+    encrypt_metadata: bool,
+) -> Vec<u8> {
+    let mut hasher = Md5::new();
+    hasher.update(pad_password(password));
+    hasher.update(&owner[..owner.len().min(32)]);
+    hasher.update(permissions.to_le_bytes());
+    hasher.update(file_id);
+    if revision >= 4 && !encrypt_metadata {
+        hasher.update([0xFF; 4]);
+    }
+    let mut hash = hasher.finalize().to_vec();
+    if revision >= 3 {
+        for _ in 0..50 {
+            hash = Md5::digest(&hash[..key_length]).to_vec();
+        }
+    }
+    hash.truncate(key_length);
+    hash
+}
+
+/// Compute the U entry, or its first 16 bytes as of revision 3, from the file encryption key (algorithms 4 and 5).
+fn user_hash(key: &[u8], file_id: &[u8], revision: i64) -> Vec<u8> {
+    if revision == 2 {
+        return rc4(key, &PADDING);
+    }
+    let mut hasher = Md5::new();
+    hasher.update(PADDING);
+    hasher.update(file_id);
+    let mut data = rc4(key, &hasher.finalize());
+    for round in 1..=19 {
+        let round_key: Vec<u8> = key.iter().map(|byte| byte ^ round).collect();
+        data = rc4(&round_key, &data);
+    }
+    data
+}
+
+/// Check a file encryption key against the U entry (algorithm 6).
+fn check_user_key(key: &[u8], user: &[u8], file_id: &[u8], revision: i64) -> bool {
+    let hash = user_hash(key, file_id, revision);
+    user.len() >= hash.len() && hash[..] == user[..hash.len()]
+}
+
+/// Compute the RC4 key used to encrypt the user password in the O entry.
+fn owner_key(owner_password: &[u8], revision: i64, key_length: usize) -> Vec<u8> {
+    let mut hash = Md5::digest(pad_password(owner_password)).to_vec();
+    if revision >= 3 {
+        for _ in 0..50 {
+            hash = Md5::digest(&hash).to_vec();
+        }
+    }
+    hash.truncate(key_length);
+    hash
+}
+
+/// Compute the O entry from the owner and user passwords (algorithm 3).
+fn owner_hash(owner_password: &[u8], user_password: &[u8], revision: i64, key_length: usize) -> Vec<u8> {
+    let key = owner_key(owner_password, revision, key_length);
+    let mut data = rc4(&key, &pad_password(user_password));
+    if revision >= 3 {
+        for round in 1..=19 {
+            let round_key: Vec<u8> = key.iter().map(|byte| byte ^ round).collect();
+            data = rc4(&round_key, &data);
+        }
+    }
+    data
+}
+
+/// Recover the user password from the O entry with the owner password (algorithm 7).
+fn user_password_from_owner(password: &[u8], owner: &[u8], revision: i64, key_length: usize) -> Vec<u8> {
+    let key = owner_key(password, revision, key_length);
+    if revision == 2 {
+        return rc4(&key, owner);
+    }
+    let mut data = owner.to_vec();
+    for round in (0..=19).rev() {
+        let round_key: Vec<u8> = key.iter().map(|byte| byte ^ round).collect();
+        data = rc4(&round_key, &data);
+    }
+    data
+}
+
+/// Compute the file encryption key for revisions 5 and 6 (algorithm 2.A).
+fn file_key_aes256(
+    password: &[u8], owner: &[u8], user: &[u8], owner_encrypted: &[u8], user_encrypted: &[u8], revision: i64,
+) -> Result<Vec<u8>> {
+    if owner.len() < 48 || user.len() < 48 || owner_encrypted.len() < 32 || user_encrypted.len() < 32 {
+        return Err(Error::Decryption(DecryptionError::InvalidEncryptDictionary));
+    }
+    let password = &password[..password.len().min(127)];
+    let (intermediate_key, encrypted_key) =
+        if hash_aes256(password, &owner[32..40], &user[..48], revision) == owner[..32] {
+            (
+                hash_aes256(password, &owner[40..48], &user[..48], revision),
+                owner_encrypted,
+            )
+        } else if hash_aes256(password, &user[32..40], &[], revision) == user[..32] {
+            (hash_aes256(password, &user[40..48], &[], revision), user_encrypted)
+        } else {
+            return Err(Error::Decryption(DecryptionError::IncorrectPassword));
+        };
+
+    let iv = [0; 16];
+    cbc::Decryptor::<Aes256>::new_from_slices(&intermediate_key, &iv)
+        .ok()
+        .and_then(|cipher| cipher.decrypt_padded_vec_mut::<NoPadding>(&encrypted_key[..32]).ok())
+        .ok_or(Error::Decryption(DecryptionError::InvalidEncryptDictionary))
+}
+
+/// Compute the password hash for revisions 5 and 6 (algorithm 2.B).
+pub(crate) fn hash_aes256(password: &[u8], salt: &[u8], user: &[u8], revision: i64) -> Vec<u8> {
+    let mut hash = Sha256::new()
+        .chain_update(password)
+        .chain_update(salt)
+        .chain_update(user)
+        .finalize()
+        .to_vec();
+    if revision == 5 {
+        return hash;
+    }
+
+    let mut round = 0;
+    let mut last_byte = 0;
+    while round < 64 || u32::from(last_byte) > round - 32 {
+        let mut data = Vec::with_capacity(64 * (password.len() + hash.len() + user.len()));
+        for _ in 0..64 {
+            data.extend_from_slice(password);
+            data.extend_from_slice(&hash);
+            data.extend_from_slice(user);
+        }
+        let encrypted = cbc::Encryptor::<Aes128>::new_from_slices(&hash[..16], &hash[16..32])
+            .expect("key and iv have a valid length")
+            .encrypt_padded_vec_mut::<NoPadding>(&data);
+        let remainder = encrypted[..16].iter().map(|byte| u32::from(*byte)).sum::<u32>() % 3;
+        hash = match remainder {
+            0 => Sha256::digest(&encrypted).to_vec(),
+            1 => Sha384::digest(&encrypted).to_vec(),
+            _ => Sha512::digest(&encrypted).to_vec(),
+        };
+        last_byte = encrypted[encrypted.len() - 1];
+        round += 1;
+    }
+    hash.truncate(32);
+    hash
+}
+
+/// Validate the Perms entry, which holds the permissions encrypted with the file encryption key.
+fn check_permissions(key: &[u8], perms: &[u8], permissions: i32) -> Result<()> {
+    if perms.len() < 16 {
+        return Err(Error::Decryption(DecryptionError::InvalidPermissions));
+    }
+    let cipher = Aes256::new_from_slice(key).map_err(|_| Error::Decryption(DecryptionError::InvalidPermissions))?;
+    let mut block = GenericArray::clone_from_slice(&perms[..16]);
+    cipher.decrypt_block(&mut block);
+    if &block[9..12] != b"adb" || block[..4] != permissions.to_le_bytes() {
+        return Err(Error::Decryption(DecryptionError::InvalidPermissions));
+    }
+    Ok(())
+}
+
+pub(crate) fn rc4(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut state: Vec<u8> = (0..=255).collect();
+    let mut j: u8 = 0;
+    for i in 0..256 {
+        j = j.wrapping_add(state[i]).wrapping_add(key[i % key.len()]);
+        state.swap(i, j as usize);
+    }
+
+    let mut i: u8 = 0;
+    let mut j: u8 = 0;
+    data.iter()
+        .map(|byte| {
+            i = i.wrapping_add(1);
+            j = j.wrapping_add(state[i as usize]);
+            state.swap(i as usize, j as usize);
+            byte ^ state[state[i as usize].wrapping_add(state[j as usize]) as usize]
+        })
+        .collect()
+}
+
+/// Decrypt data prefixed with the initialization vector.
+fn aes_cbc_decrypt(key: &[u8], data: &[u8]) -> Option<Vec<u8>> {
+    if data.len() < 16 {
+        return None;
+    }
+    let (iv, data) = data.split_at(16);
+    match key.len() {
+        16 => cbc::Decryptor::<Aes128>::new_from_slices(key, iv)
+            .ok()?
+            .decrypt_padded_vec_mut::<Pkcs7>(data)
+            .ok(),
+        _ => cbc::Decryptor::<Aes256>::new_from_slices(key, iv)
+            .ok()?
+            .decrypt_padded_vec_mut::<Pkcs7>(data)
+            .ok(),
+    }
+}
+
+/// Encrypt data with a random initialization vector, which prefixes the result.
+fn aes_cbc_encrypt(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let iv: [u8; 16] = rand::random();
+    let encrypted = match key.len() {
+        16 => cbc::Encryptor::<Aes128>::new_from_slices(key, &iv)
+            .expect("key and iv have a valid length")
+            .encrypt_padded_vec_mut::<Pkcs7>(data),
+        _ => cbc::Encryptor::<Aes256>::new_from_slices(key, &iv)
+            .expect("key and iv have a valid length")
+            .encrypt_padded_vec_mut::<Pkcs7>(data),
+    };
+    iv.iter().cloned().chain(encrypted).collect()
+}
+
+/// Encrypt a single block of a 256-bit key without chaining.
+fn aes256_encrypt_block(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let cipher = Aes256::new_from_slice(key).expect("key has a valid length");
+    let mut block = GenericArray::clone_from_slice(data);
+    cipher.encrypt_block(&mut block);
+    block.to_vec()
+}
+
+#[test]
+fn rc4_test_vector() {
+    assert_eq!(
+        rc4(b"Key", b"Plaintext"),
+        [0xBB, 0xF3, 0x16, 0xE8, 0xD9, 0x40, 0xAF, 0x0A, 0xD3]
+    );
+}
+
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+#[test]
+fn decrypt_aes256() {
+    use crate::test_support::create_document;
+
+    let password = b"secret";
+    let file_key = [7; 32];
+    let permissions: i32 = -4;
+    let encrypt_key = |key: &[u8], data: &[u8]| {
+        cbc::Encryptor::<Aes256>::new_from_slices(key, &[0; 16])
+            .unwrap()
+            .encrypt_padded_vec_mut::<NoPadding>(data)
+    };
+    let mut user = hash_aes256(password, b"uservals", &[], 6);
+    user.extend_from_slice(b"uservalsuserkeys");
+    let user_encrypted = encrypt_key(&hash_aes256(password, b"userkeys", &[], 6), &file_key);
+    let mut owner = hash_aes256(b"owner", b"ownvalid", &user, 6);
+    owner.extend_from_slice(b"ownvalidownerkey");
+    let owner_encrypted = encrypt_key(&hash_aes256(b"owner", b"ownerkey", &user, 6), &file_key);
+    let mut perms = permissions.to_le_bytes().to_vec();
+    perms.extend_from_slice(b"\xFF\xFF\xFF\xFFTadbrand");
+    let mut perms = GenericArray::clone_from_slice(&perms);
+    Aes256::new_from_slice(&file_key).unwrap().encrypt_block(&mut perms);
+
+    let mut doc = create_document();
+    for object in doc.objects.values_mut() {
+        if let Object::Stream(ref mut stream) = *object {
+            let iv = [3; 16];
+            let mut content = iv.to_vec();
+            content.extend(
+                cbc::Encryptor::<Aes256>::new_from_slices(&file_key, &iv)
+                    .unwrap()
+                    .encrypt_padded_vec_mut::<Pkcs7>(&stream.content),
+            );
+            stream.set_content(content);
+        }
+    }
+    let encrypt_id = doc.add_object(dictionary! {
+        "Filter" => "Standard",
+        "V" => 5,
+        "R" => 6,
+        "Length" => 256,
+        "CF" => dictionary! {
+            "StdCF" => dictionary! { "CFM" => "AESV3", "Length" => 32 },
+        },
+        "StmF" => "StdCF",
+        "StrF" => "StdCF",
+        "O" => Object::string_literal(owner),
+        "U" => Object::string_literal(user),
+        "OE" => Object::string_literal(owner_encrypted),
+        "UE" => Object::string_literal(user_encrypted),
+        "P" => i64::from(permissions),
+        "Perms" => Object::string_literal(perms.to_vec()),
+    });
+    doc.trailer.set("Encrypt", encrypt_id);
+    assert!(doc.is_encrypted());
+
+    let mut wrong = doc.clone();
+    assert!(wrong.decrypt("wrong").is_err());
+    let mut with_owner_password = doc.clone();
+    with_owner_password.decrypt("owner").unwrap();
+    doc.decrypt("secret").unwrap();
+    assert!(!doc.is_encrypted());
+    assert_eq!(doc.extract_text(&[1]).unwrap(), "Hello World!\n");
+    assert_eq!(with_owner_password.extract_text(&[1]).unwrap(), "Hello World!\n");
+}
+
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+#[test]
+fn save_encrypted() {
+    use crate::test_support::create_document;
+    use crate::SaveOptions;
+
+    for &algorithm in [
+        EncryptionAlgorithm::Rc4,
+        EncryptionAlgorithm::Aes128,
+        EncryptionAlgorithm::Aes256,
+    ]
+    .iter()
+    {
+        let mut doc = create_document();
+        let options = SaveOptions {
+            use_object_streams: algorithm == EncryptionAlgorithm::Aes256,
+            encryption: Some(EncryptionOptions {
+                user_password: "user".to_string(),
+                owner_password: "owner".to_string(),
+                permissions: Permissions::PRINT | Permissions::COPY,
+                algorithm,
+            }),
+            ..SaveOptions::default()
+        };
+        let mut buffer = Vec::new();
+        doc.save_to_with_options(&mut buffer, &options).unwrap();
+        assert!(!doc.is_encrypted());
+        assert!(!buffer.windows(12).any(|window| window == b"Hello World!"));
+
+        let mut encrypted = Document::load_mem(&buffer).unwrap();
+        assert!(encrypted.is_encrypted());
+        assert!(encrypted.clone().decrypt("wrong").is_err());
+        encrypted.clone().decrypt("owner").unwrap();
+        encrypted.decrypt("user").unwrap();
+        assert_eq!(encrypted.extract_text(&[1]).unwrap(), "Hello World!\n");
+    }
+}
+
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+#[test]
+fn save_with_crypt_filters() {
+    use crate::test_support::create_document;
+    use crate::SaveOptions;
+
+    let mut doc = create_document();
+    let plain_id = doc.add_object(Stream::new(
+        dictionary! { "Filter" => "Crypt" },
+        b"Identity crypt filter".to_vec(),
+    ));
+    let named_id = doc.add_object(Stream::new(
+        dictionary! {
+            "Filter" => vec!["Crypt".into()],
+            "DecodeParms" => vec![dictionary! { "Name" => "StdCF" }.into()],
+        },
+        b"Named crypt filter".to_vec(),
+    ));
+    assert_eq!(
+        doc.get_object(plain_id)
+            .and_then(Object::as_stream)
+            .unwrap()
+            .decompressed_content()
+            .unwrap(),
+        b"Identity crypt filter"
+    );
+
+    let options = SaveOptions {
+        encryption: Some(EncryptionOptions {
+            user_password: "user".to_string(),
+            owner_password: String::new(),
+            permissions: Permissions::all(),
+            algorithm: EncryptionAlgorithm::Aes128,
+        }),
+        ..SaveOptions::default()
+    };
+    let mut buffer = Vec::new();
+    doc.save_to_with_options(&mut buffer, &options).unwrap();
+    assert!(buffer.windows(21).any(|window| window == b"Identity crypt filter"));
+    assert!(!buffer.windows(18).any(|window| window == b"Named crypt filter"));
+
+    let mut encrypted = Document::load_mem(&buffer).unwrap();
+    encrypted.decrypt("user").unwrap();
+    for &(id, content) in [
+        (plain_id, &b"Identity crypt filter"[..]),
+        (named_id, &b"Named crypt filter"[..]),
+    ]
+    .iter()
+    {
+        let stream = encrypted.get_object(id).and_then(Object::as_stream).unwrap();
+        assert_eq!(stream.content, content);
+        assert!(!stream.dict.has(b"Filter"));
+    }
+}
+
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+#[test]
+fn save_with_original_encryption() {
+    use crate::test_support::create_document;
+    use crate::SaveOptions;
+
+    let mut doc = create_document();
+    assert!(matches!(
+        doc.save_to_with_original_encryption(&mut Vec::new(), "owner"),
+        Err(Error::Decryption(DecryptionError::NotEncrypted))
+    ));
+    let options = SaveOptions {
+        encryption: Some(EncryptionOptions {
+            user_password: "user".to_string(),
+            owner_password: "owner".to_string(),
+            permissions: Permissions::PRINT,
+            algorithm: EncryptionAlgorithm::Aes128,
+        }),
+        ..SaveOptions::default()
+    };
+    let mut buffer = Vec::new();
+    doc.save_to_with_options(&mut buffer, &options).unwrap();
+
+    let mut doc = Document::load_mem(&buffer).unwrap();
+    doc.decrypt("owner").unwrap();
+    let original = doc.original_encryption().unwrap().clone();
+    doc.replace_text(1, "Hello World!", "Edited text!").unwrap();
+    assert!(doc.save_to_with_original_encryption(&mut Vec::new(), "wrong").is_err());
+    let mut output = Vec::new();
+    doc.save_to_with_original_encryption(&mut output, "owner").unwrap();
+    assert!(!doc.is_encrypted());
+    assert!(!output.windows(12).any(|window| window == b"Edited text!"));
+
+    let mut reloaded = Document::load_mem(&output).unwrap();
+    assert!(reloaded.is_encrypted());
+    reloaded.decrypt("user").unwrap();
+    assert_eq!(reloaded.extract_text(&[1]).unwrap(), "Edited text!\n");
+    assert_eq!(reloaded.original_encryption(), Some(&original));
+}
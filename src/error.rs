@@ -1,20 +1,36 @@
+use crate::references::{Path, PathStep};
+use crate::ObjectId;
 use std::fmt;
 
 #[derive(Debug)]
 pub enum Error {
+    CharacterEncoding,
     ContentDecode,
+    /// Error which occurred while processing a part of a file, with the location of the part.
+    Context {
+        error: Box<Error>,
+        context: ErrorContext,
+    },
+    Decryption(DecryptionError),
     DictKey,
     Header,
     IO(std::io::Error),
+    Limit(LimitError),
     ObjectIdMismatch,
     ObjectNotFound,
     Offset(usize),
     PageNumberNotFound(u32),
+    PageBoxOutsideMediaBox,
+    NoRevision,
     Parse {
         offset: usize,
     },
     ReferenceLimit,
     BracketLimit,
+    /// A certification signature is added to a document which is signed already.
+    Certification,
+    /// A signature is larger than the space reserved for it, in bytes.
+    SignatureSize(usize),
     Trailer,
     Type,
     UTF8,
@@ -22,22 +38,36 @@ pub enum Error {
     Xref(XrefError),
     #[cfg(feature = "embed_image")]
     Image(image::ImageError),
+    #[cfg(feature = "serde")]
+    Json(serde_json::Error),
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
+            Error::CharacterEncoding => write!(f, "A character could not be encoded with the font"),
             Error::ContentDecode => write!(f, "Could not decode content"),
+            Error::Context { error, context } => write!(f, "{} ({})", error, context),
+            Error::Decryption(e) => write!(f, "Could not decrypt the document ({})", e),
             Error::DictKey => write!(f, "A required dictionary key was not found"),
             Error::Header => write!(f, "Invalid file header"),
             Error::IO(e) => e.fmt(f),
+            Error::Limit(e) => write!(f, "The document exceeds a limit of the load options ({})", e),
             Error::ObjectIdMismatch => write!(f, "The object id found did not match the requested object"),
             Error::ObjectNotFound => write!(f, "A required object was not found"),
             Error::Offset(o) => write!(f, "Invalid file offset: {}", o),
             Error::PageNumberNotFound(p) => write!(f, "Page number {} could not be found", p),
+            Error::PageBoxOutsideMediaBox => write!(f, "A page box is not contained in the media box"),
+            Error::NoRevision => write!(f, "The document was not loaded from a file which could be updated"),
             Error::Parse { offset, .. } => write!(f, "Invalid object at byte {}", offset),
             Error::ReferenceLimit => write!(f, "Could not dereference an object; possible reference loop"),
             Error::BracketLimit => write!(f, "Too deep embedding of ()'s."),
+            Error::Certification => write!(f, "A certification signature must be the first signature of a document"),
+            Error::SignatureSize(size) => write!(
+                f,
+                "The signature of {} bytes is larger than the space reserved for it",
+                size
+            ),
             Error::Trailer => write!(f, "Invalid file trailer"),
             Error::Type => write!(f, "An object does not have the expected type"),
             Error::UTF8 => write!(f, "UTF-8 error"),
@@ -45,11 +75,127 @@ impl fmt::Display for Error {
             Error::Xref(e) => write!(f, "Invalid cross-reference table ({})", e),
             #[cfg(feature = "embed_image")]
             Error::Image(e) => e.fmt(f),
+            #[cfg(feature = "serde")]
+            Error::Json(e) => write!(f, "Invalid JSON ({})", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Context { error, .. } => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl Error {
+    /// Get the location of the part of the file which was processed when the error occurred, if known.
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            Error::Context { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+
+    /// Get the error without its context, to match on its kind.
+    pub fn without_context(&self) -> &Error {
+        match self {
+            Error::Context { error, .. } => error,
+            _ => self,
+        }
+    }
+
+    /// Add the location of the part of the file being processed, keeping the more precise locations the error
+    /// already has. The path is prepended to the path of the error, unless the error concerns another object.
+    #[cfg(any(feature = "crypto", feature = "pom_parser", feature = "nom_parser"))]
+    pub(crate) fn with_context(self, outer: ErrorContext) -> Error {
+        if outer == ErrorContext::default() {
+            return self;
+        }
+        match self {
+            Error::Context { error, mut context } => {
+                if context.object_id.is_none() || context.object_id == outer.object_id {
+                    let mut path = outer.path;
+                    path.extend(context.path);
+                    context.path = path;
+                }
+                context.object_id = context.object_id.or(outer.object_id);
+                context.offset = context.offset.or(outer.offset);
+                Error::Context { error, context }
+            }
+            error => Error::Context {
+                error: Box::new(error),
+                context: outer,
+            },
+        }
+    }
+}
+
+/// Location of the part of a file which was processed when an error occurred.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ErrorContext {
+    /// Byte offset in the file, such as of the object or cross-reference section.
+    pub offset: Option<usize>,
+    pub object_id: Option<ObjectId>,
+    /// Keys and indices leading from the object, or from the trailer if there's no object, to the entry concerned.
+    pub path: Path,
+}
+
+impl ErrorContext {
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    pub(crate) fn at_offset(offset: usize) -> ErrorContext {
+        ErrorContext {
+            offset: Some(offset),
+            ..ErrorContext::default()
+        }
+    }
+
+    #[cfg(any(feature = "crypto", feature = "pom_parser", feature = "nom_parser"))]
+    pub(crate) fn in_object(object_id: ObjectId) -> ErrorContext {
+        ErrorContext {
+            object_id: Some(object_id),
+            ..ErrorContext::default()
         }
     }
+
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    pub(crate) fn with_offset(mut self, offset: usize) -> ErrorContext {
+        self.offset = Some(offset);
+        self
+    }
+
+    #[cfg(any(feature = "crypto", feature = "pom_parser", feature = "nom_parser"))]
+    pub(crate) fn with_key(mut self, key: &[u8]) -> ErrorContext {
+        self.path.push(PathStep::Key(key.to_vec()));
+        self
+    }
 }
 
-impl std::error::Error for Error {}
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some((number, generation)) = self.object_id {
+            parts.push(format!("in object {} {}", number, generation));
+        }
+        if let Some(offset) = self.offset {
+            parts.push(format!("at byte {}", offset));
+        }
+        if !self.path.is_empty() {
+            let path: String = self
+                .path
+                .iter()
+                .map(|step| match step {
+                    PathStep::Key(key) => format!("/{}", String::from_utf8_lossy(key)),
+                    PathStep::Index(index) => format!("[{}]", index),
+                })
+                .collect();
+            parts.push(format!("at {}", path));
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
 
 #[derive(Debug)]
 pub enum XrefError {
@@ -72,6 +218,100 @@ impl fmt::Display for XrefError {
 
 impl std::error::Error for XrefError {}
 
+/// Limit of `LoadOptions` which a document exceeds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitError {
+    Depth,
+    ObjectCount,
+    StreamLength,
+    XrefChain,
+}
+
+impl fmt::Display for LimitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LimitError::Depth => write!(f, "arrays and dictionaries are nested too deeply"),
+            LimitError::ObjectCount => write!(f, "too many objects"),
+            LimitError::StreamLength => write!(f, "stream too long"),
+            LimitError::XrefChain => write!(f, "too many cross-reference sections"),
+        }
+    }
+}
+
+impl std::error::Error for LimitError {}
+
+#[derive(Debug)]
+pub enum DecryptionError {
+    IncorrectPassword,
+    InvalidEncryptDictionary,
+    InvalidPermissions,
+    UnsupportedEncryption,
+    /// The document wasn't encrypted when it was loaded.
+    NotEncrypted,
+    /// A certificate or private key couldn't be parsed.
+    InvalidCertificate,
+    /// The document isn't encrypted for the given certificate.
+    NotARecipient,
+}
+
+impl fmt::Display for DecryptionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecryptionError::IncorrectPassword => write!(f, "incorrect password"),
+            DecryptionError::InvalidEncryptDictionary => write!(f, "invalid encryption dictionary"),
+            DecryptionError::InvalidPermissions => write!(f, "permissions don't match the encrypted ones"),
+            DecryptionError::UnsupportedEncryption => write!(f, "unsupported security handler or algorithm"),
+            DecryptionError::NotEncrypted => write!(f, "document wasn't encrypted"),
+            DecryptionError::InvalidCertificate => write!(f, "invalid certificate or private key"),
+            DecryptionError::NotARecipient => write!(f, "document isn't encrypted for the certificate"),
+        }
+    }
+}
+
+impl std::error::Error for DecryptionError {}
+
+/// Kind of malformed construct which was tolerated while loading a document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadWarningKind {
+    /// The /Size entry of the trailer doesn't match the highest object number of the cross-reference table.
+    XrefSize,
+    /// An object listed in the cross-reference table couldn't be parsed, so it's missing.
+    UnreadableObject,
+    /// The object found at the offset of a cross-reference entry has another object number or generation.
+    ObjectIdMismatch,
+    /// An object stream couldn't be parsed, so it and its objects are missing.
+    UnreadableObjectStream,
+    /// The /Length of a stream is missing or wrong, so its data is empty.
+    StreamLength,
+    /// The cross-reference table or the trailer is broken, or objects are missing, so the document was recovered
+    /// by scanning the whole file.
+    Recovered,
+}
+
+/// Malformed construct which was tolerated while loading a document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadWarning {
+    pub kind: LoadWarningKind,
+    /// Byte offset in the file of the object or cross-reference section concerned.
+    pub offset: Option<usize>,
+    pub object_id: Option<ObjectId>,
+    /// Details, such as the error which occurred.
+    pub message: String,
+}
+
+impl fmt::Display for LoadWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.kind)?;
+        if let Some((number, generation)) = self.object_id {
+            write!(f, " in object {} {}", number, generation)?;
+        }
+        if let Some(offset) = self.offset {
+            write!(f, " at byte {}", offset)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
 pub type Result<T> = std::result::Result<T, Error>;
 
 impl From<std::io::Error> for Error {
@@ -98,3 +338,10 @@ impl From<image::ImageError> for Error {
         Error::Image(err)
     }
 }
+
+#[cfg(feature = "serde")]
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Json(err)
+    }
+}
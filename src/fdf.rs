@@ -0,0 +1,225 @@
+//! Forms Data Format, the PDF based format for exchanging the values of form fields between documents.
+
+use crate::forms::{Field, FieldType};
+use crate::writer::Writer;
+use crate::{Dictionary, Document, Object, Result};
+use std::io::Write;
+
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+use crate::encodings::decode_text_string;
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+use crate::{Error, ObjectId};
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+use std::collections::BTreeMap;
+
+impl Document {
+    /// Write the values of the form fields as an FDF file, which can be imported into another copy of the form.
+    ///
+    /// Fields keep the hierarchy of the form, and terminal fields are exported with or without a value. Push buttons
+    /// and signature fields have no value to export.
+    pub fn export_fdf<W: Write>(&self, target: &mut W) -> Result<()> {
+        let form = self.get_form()?;
+        let fields: Vec<Object> = form
+            .fields
+            .iter()
+            .filter_map(|field| self.fdf_field(field))
+            .map(Object::Dictionary)
+            .collect();
+        let root = dictionary! { "FDF" => dictionary! { "Fields" => fields } };
+
+        target.write_all(b"%FDF-1.2\n%\xE2\xE3\xCF\xD3\n1 0 obj\n")?;
+        Writer::write_object(target, &Object::Dictionary(root))?;
+        target.write_all(b"\nendobj\ntrailer\n<</Root 1 0 R>>\n%%EOF\n")?;
+        Ok(())
+    }
+
+    fn fdf_field(&self, field: &Field) -> Option<Dictionary> {
+        let partial_name = self.get_dictionary(field.id).ok()?.get(b"T").ok()?.clone();
+        let mut dict = dictionary! { "T" => partial_name };
+        match field.field_type {
+            FieldType::PushButton | FieldType::Signature => return None,
+            FieldType::NonTerminal => {
+                let kids: Vec<Object> = field
+                    .kids
+                    .iter()
+                    .filter_map(|kid| self.fdf_field(kid))
+                    .map(Object::Dictionary)
+                    .collect();
+                if kids.is_empty() {
+                    return None;
+                }
+                dict.set("Kids", kids);
+            }
+            _ => {
+                if let Some(ref value) = field.value {
+                    dict.set("V", value.clone());
+                }
+            }
+        }
+        Some(dict)
+    }
+
+    /// Fill in the form fields with the values of an FDF file, generating the appearances of the fields as setting
+    /// their values does. Returns the fully qualified names of the fields of the FDF file which the form doesn't
+    /// have.
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    pub fn import_fdf(&mut self, fdf: &[u8]) -> Result<Vec<String>> {
+        // FDF files often have no cross-reference table, so their objects are found by scanning.
+        let objects: BTreeMap<ObjectId, Object> = Document::scan_objects(fdf)?
+            .into_iter()
+            .map(|scanned| (scanned.id, scanned.object))
+            .collect();
+        let resolve = |object: &Object| -> Object {
+            match *object {
+                Object::Reference(id) => objects.get(&id).cloned().unwrap_or(Object::Null),
+                _ => object.clone(),
+            }
+        };
+        let fdf_dict = objects
+            .values()
+            .filter_map(|object| object.as_dict().ok())
+            .find_map(|dict| dict.get(b"FDF").ok())
+            .map(&resolve)
+            .ok_or(Error::DictKey)?;
+        let fields = fdf_dict
+            .as_dict()?
+            .get(b"Fields")
+            .map(&resolve)
+            .unwrap_or_else(|_| Object::Array(Vec::new()));
+
+        // Collect the values by fully qualified name.
+        let mut values = Vec::new();
+        let mut pending: Vec<(String, Object)> = fields
+            .as_array()?
+            .iter()
+            .rev()
+            .map(|field| (String::new(), resolve(field)))
+            .collect();
+        while let Some((parent, field)) = pending.pop() {
+            let field = match field.as_dict() {
+                Ok(field) => field,
+                Err(_) => continue,
+            };
+            let partial_name = field.get(b"T").and_then(Object::as_str).map(decode_text_string);
+            let name = match partial_name {
+                Ok(partial_name) if !parent.is_empty() => format!("{}.{}", parent, partial_name),
+                Ok(partial_name) => partial_name,
+                Err(_) => parent,
+            };
+            if let Ok(value) = field.get(b"V") {
+                values.push((name.clone(), resolve(value)));
+            }
+            if let Ok(kids) = field.get(b"Kids").map(&resolve) {
+                for kid in kids.as_array()?.iter().rev() {
+                    pending.push((name.clone(), resolve(kid)));
+                }
+            }
+        }
+
+        let mut texts = Vec::new();
+        for (name, value) in values {
+            let text = match value {
+                Object::String(ref text, _) => decode_text_string(text),
+                Object::Name(ref name) => String::from_utf8_lossy(name).into_owned(),
+                // Only the first of several selected options is set.
+                Object::Array(ref options) => match options.first() {
+                    Some(Object::String(text, _)) => decode_text_string(text),
+                    _ => continue,
+                },
+                _ => continue,
+            };
+            texts.push((name, text));
+        }
+        self.set_field_texts(texts)
+    }
+
+    /// Set the values of fields given by their fully qualified names as text, which are the states of buttons or the
+    /// selected options of choice fields. Returns the names of the fields which the form doesn't have.
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser", feature = "xmp"))]
+    pub(crate) fn set_field_texts(&mut self, values: Vec<(String, String)>) -> Result<Vec<String>> {
+        let mut form = self.get_form()?;
+        let mut missing = Vec::new();
+        for (name, text) in values {
+            let field_type = match form.get_field(&name) {
+                Some(field) => field.field_type,
+                None => {
+                    missing.push(name);
+                    continue;
+                }
+            };
+            match field_type {
+                FieldType::Text => form.set_text_value(self, &name, &text)?,
+                FieldType::CheckBox => form.set_checkbox(self, &name, text != "Off")?,
+                FieldType::RadioButton | FieldType::ComboBox | FieldType::ListBox => {
+                    form.set_choice(self, &name, &text)?
+                }
+                _ => {}
+            }
+        }
+        Ok(missing)
+    }
+}
+
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+#[test]
+fn export_and_import_fdf() {
+    use crate::forms::FieldBuilder;
+    use crate::test_support::create_document;
+    use crate::Rectangle;
+
+    let mut filled = create_document();
+    let page_id = filled.page_iter().next().unwrap();
+    let rect = |y: f64| Rectangle::new(100.0, y, 300.0, y + 20.0);
+    let parent_id = filled.add_object(dictionary! {
+        "T" => Object::string_literal("address"),
+        "Kids" => Vec::<Object>::new(),
+    });
+    filled.append_root_field(parent_id).unwrap();
+    filled
+        .add_field(&FieldBuilder::text("name", page_id, rect(700.0)))
+        .unwrap();
+    filled
+        .add_field(&FieldBuilder::checkbox("agree", page_id, rect(670.0)))
+        .unwrap();
+    filled
+        .add_field(&FieldBuilder::combo_box(
+            "color",
+            page_id,
+            rect(640.0),
+            &["Red", "Green"],
+        ))
+        .unwrap();
+    filled
+        .add_field(&FieldBuilder::push_button("submit", page_id, rect(610.0), "Submit"))
+        .unwrap();
+    let mut blank = filled.clone();
+    let city_id = filled.add_object(dictionary! {
+        "FT" => "Tx",
+        "T" => Object::string_literal("city"),
+        "Parent" => parent_id,
+        "V" => Object::string_literal("Berlin"),
+    });
+    filled
+        .get_object_mut(parent_id)
+        .and_then(Object::as_dict_mut)
+        .unwrap()
+        .set("Kids", vec![city_id.into()]);
+
+    let mut form = filled.get_form().unwrap();
+    form.set_text_value(&mut filled, "name", "Jane Doe").unwrap();
+    form.set_checkbox(&mut filled, "agree", true).unwrap();
+    form.set_choice(&mut filled, "color", "Green").unwrap();
+    let mut fdf = Vec::new();
+    filled.export_fdf(&mut fdf).unwrap();
+    assert!(fdf.starts_with(b"%FDF-1.2\n"));
+    assert!(!fdf.windows(6).any(|window| window == b"submit"));
+
+    let missing = blank.import_fdf(&fdf).unwrap();
+    assert_eq!(missing, vec!["address.city"]);
+    let form = blank.get_form().unwrap();
+    assert_eq!(form.get_field("name").unwrap().text_value().unwrap(), "Jane Doe");
+    assert_eq!(form.get_field("agree").unwrap().text_value().unwrap(), "Yes");
+    assert_eq!(form.get_field("color").unwrap().text_value().unwrap(), "Green");
+    let widget_id = form.get_field("name").unwrap().widgets[0].id;
+    assert!(blank.get_dictionary(widget_id).unwrap().has(b"AP"));
+}
@@ -0,0 +1,103 @@
+//! Base-85 encoding as used by the ASCII85Decode filter, where `~>` marks the end of the data.
+//!
+//! Every four bytes are written as five characters from `!` to `u`, and four zero bytes may be abbreviated as `z`.
+
+use log::warn;
+
+const LINE_LENGTH: usize = 75;
+
+/// Decode base-85 data, ignoring white-space and stopping at the end-of-data marker or at the first invalid
+/// character.
+pub fn decode(input: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(input.len() * 4 / 5);
+    let mut group = [0u8; 5];
+    let mut group_length = 0;
+    let mut bytes = input.iter();
+    // An optional `<~` prefix is left by some PostScript-era tools.
+    if input.starts_with(b"<~") {
+        bytes.nth(1);
+    }
+    for &byte in bytes {
+        match byte {
+            b'!'..=b'u' => {
+                group[group_length] = byte - b'!';
+                group_length += 1;
+                if group_length == 5 {
+                    output.extend_from_slice(&group_value(&group).to_be_bytes());
+                    group_length = 0;
+                }
+            }
+            b'z' if group_length == 0 => output.extend_from_slice(&[0; 4]),
+            b'~' => break,
+            b' ' | b'\t' | b'\r' | b'\n' | b'\x0C' | b'\0' => continue,
+            _ => {
+                warn!("invalid character {:?} in base-85 data", char::from(byte));
+                break;
+            }
+        }
+    }
+    // A final partial group is padded with the highest digit.
+    if group_length > 1 {
+        for digit in group.iter_mut().skip(group_length) {
+            *digit = 84;
+        }
+        output.extend_from_slice(&group_value(&group).to_be_bytes()[..group_length - 1]);
+    } else if group_length == 1 {
+        warn!("base-85 data ends with a single character");
+    }
+    output
+}
+
+fn group_value(group: &[u8; 5]) -> u32 {
+    group.iter().fold(0u32, |value, &digit| {
+        value.wrapping_mul(85).wrapping_add(u32::from(digit))
+    })
+}
+
+/// Encode data as lines of base-85 characters, ending with an end-of-data marker.
+pub fn encode(input: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(input.len() * 5 / 4 + input.len() / 60 + 3);
+    let mut line_length = 0;
+    for chunk in input.chunks(4) {
+        let mut bytes = [0u8; 4];
+        bytes[..chunk.len()].copy_from_slice(chunk);
+        let mut value = u32::from_be_bytes(bytes);
+        let characters = if chunk.len() == 4 && value == 0 {
+            vec![b'z']
+        } else {
+            let mut digits = [0u8; 5];
+            for digit in digits.iter_mut().rev() {
+                *digit = (value % 85) as u8 + b'!';
+                value /= 85;
+            }
+            digits[..chunk.len() + 1].to_vec()
+        };
+        if line_length + characters.len() > LINE_LENGTH {
+            output.push(b'\n');
+            line_length = 0;
+        }
+        line_length += characters.len();
+        output.extend(characters);
+    }
+    output.extend_from_slice(b"~>");
+    output
+}
+
+#[test]
+fn encode_and_decode_ascii85() {
+    assert_eq!(decode(b"87cURD_*#TDfTZ)~>"), b"Hello, world");
+    assert_eq!(decode(b"<~87cUR D_*#T\nDfTZ)~>"), b"Hello, world");
+    assert_eq!(decode(b"87cURDZ~>"), b"Hello");
+    assert_eq!(decode(b"z!!~>"), [0, 0, 0, 0, 0]);
+    assert_eq!(encode(b"Hello, world"), b"87cURD_*#TDfTZ)~>");
+    assert_eq!(encode(&[0, 0, 0, 0, 0]), b"z!!~>");
+
+    let data: Vec<u8> = (0..1000u32).map(|n| (n * 37 % 256) as u8).chain(vec![0; 9]).collect();
+    for length in &[data.len(), data.len() - 1, data.len() - 2, data.len() - 3] {
+        let encoded = encode(&data[..*length]);
+        assert!(encoded
+            .split(|&byte| byte == b'\n')
+            .all(|line| line.len() <= LINE_LENGTH + 2));
+        assert_eq!(decode(&encoded), &data[..*length]);
+    }
+}
@@ -0,0 +1,63 @@
+//! Hexadecimal encoding as used by the ASCIIHexDecode filter, where `>` marks the end of the data.
+
+use log::warn;
+
+const LINE_LENGTH: usize = 64;
+
+/// Decode hexadecimal data, ignoring white-space and stopping at the end-of-data marker or at the first
+/// invalid character. A final odd digit is completed with a zero.
+pub fn decode(input: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(input.len() / 2);
+    let mut high = None;
+    for &byte in input {
+        let digit = match byte {
+            b'0'..=b'9' => byte - b'0',
+            b'a'..=b'f' => byte - b'a' + 10,
+            b'A'..=b'F' => byte - b'A' + 10,
+            b'>' => break,
+            b' ' | b'\t' | b'\r' | b'\n' | b'\x0C' | b'\0' => continue,
+            _ => {
+                warn!("invalid character {:?} in hexadecimal data", char::from(byte));
+                break;
+            }
+        };
+        high = match high {
+            Some(high) => {
+                output.push(high << 4 | digit);
+                None
+            }
+            None => Some(digit),
+        };
+    }
+    if let Some(high) = high {
+        output.push(high << 4);
+    }
+    output
+}
+
+/// Encode data as lines of hexadecimal digits, ending with an end-of-data marker.
+pub fn encode(input: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(input.len() * 2 + input.len() / (LINE_LENGTH / 2) + 1);
+    for (index, byte) in input.iter().enumerate() {
+        if index > 0 && index % (LINE_LENGTH / 2) == 0 {
+            output.push(b'\n');
+        }
+        output.extend_from_slice(format!("{:02X}", byte).as_bytes());
+    }
+    output.push(b'>');
+    output
+}
+
+#[test]
+fn encode_and_decode_ascii_hex() {
+    assert_eq!(decode(b"48 65 6c\n6C6F>ignored"), b"Hello");
+    assert_eq!(decode(b"7"), [0x70]);
+    assert_eq!(encode(b"Hello"), b"48656C6C6F>");
+
+    let data: Vec<u8> = (0..=255).collect();
+    let encoded = encode(&data);
+    assert!(encoded
+        .split(|&byte| byte == b'\n')
+        .all(|line| line.len() <= LINE_LENGTH + 1));
+    assert_eq!(decode(&encoded), data);
+}
@@ -0,0 +1,401 @@
+#![cfg(feature = "ccitt")]
+
+//! CCITT fax compression as used by the CCITTFaxDecode filter, for bilevel images.
+//!
+//! Group 3 data is coded one-dimensionally when `K` is 0 and mixes one- and two-dimensionally coded rows when
+//! `K` is positive, and Group 4 data, with a negative `K`, is coded two-dimensionally. Decoded rows have one bit
+//! per pixel and start at byte boundaries.
+
+use crate::{Dictionary, Error, Object, Result};
+use fax::maps::{black, mode, white, Mode};
+use fax::{BitReader, Color};
+use log::warn;
+use std::convert::TryFrom;
+
+/// Parameters of the CCITTFaxDecode filter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CcittParams {
+    pub k: i64,
+    pub columns: usize,
+    /// Number of rows, if known, after which decoding stops.
+    pub rows: Option<usize>,
+    pub black_is_1: bool,
+    /// Whether each row of Group 4 data, or each end-of-line of Group 3 data, is aligned to a byte boundary.
+    pub encoded_byte_align: bool,
+}
+
+impl Default for CcittParams {
+    fn default() -> CcittParams {
+        CcittParams {
+            k: 0,
+            columns: 1728,
+            rows: None,
+            black_is_1: false,
+            encoded_byte_align: false,
+        }
+    }
+}
+
+impl CcittParams {
+    /// Read the decode parameters of a stream, with the number of rows defaulting to the height of the image.
+    pub fn new(params: Option<&Dictionary>, height: Option<usize>) -> CcittParams {
+        let param = |key: &[u8]| params.and_then(|params| params.get(key).ok());
+        let integer = |key: &[u8]| param(key).and_then(|value| value.as_i64().ok());
+        let boolean = |key: &[u8]| matches!(param(key), Some(Object::Boolean(true)));
+        let default = CcittParams::default();
+        CcittParams {
+            k: integer(b"K").unwrap_or(default.k),
+            columns: integer(b"Columns")
+                .and_then(|columns| usize::try_from(columns).ok())
+                .unwrap_or(default.columns),
+            rows: integer(b"Rows").and_then(|rows| usize::try_from(rows).ok()).or(height),
+            black_is_1: boolean(b"BlackIs1"),
+            encoded_byte_align: boolean(b"EncodedByteAlign"),
+        }
+    }
+}
+
+/// Largest size of decoded data, as /Rows and /Columns come from the stream and may be bogus.
+const MAX_OUTPUT: usize = 1 << 28;
+
+fn too_large() -> Error {
+    Error::Syntax("CCITT fax image is too large".to_string())
+}
+
+struct SliceReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> SliceReader<'a> {
+    fn at_end(&self) -> bool {
+        self.position >= self.data.len() * 8
+    }
+
+    fn align(&mut self) {
+        self.position = self.position.next_multiple_of(8);
+    }
+}
+
+impl<'a> BitReader for SliceReader<'a> {
+    type Error = ();
+
+    /// Bits past the end of the data read as zeros, as long as some data remains.
+    fn peek(&self, bits: u8) -> Option<u16> {
+        if self.at_end() {
+            return None;
+        }
+        let mut value = 0u16;
+        for offset in 0..usize::from(bits) {
+            let position = self.position + offset;
+            let bit = self
+                .data
+                .get(position / 8)
+                .map_or(0, |byte| byte >> (7 - position % 8) & 1);
+            value = value << 1 | u16::from(bit);
+        }
+        Some(value)
+    }
+
+    fn consume(&mut self, bits: u8) -> std::result::Result<(), ()> {
+        self.position = (self.position + usize::from(bits)).min(self.data.len() * 8);
+        Ok(())
+    }
+
+    fn bits_to_byte_boundary(&self) -> u8 {
+        ((8 - self.position % 8) % 8) as u8
+    }
+}
+
+const END_OF_LINE: u16 = 1;
+
+/// Read a run length, made of make-up codes followed by a terminating code.
+fn run_length(reader: &mut SliceReader, color: Color) -> Option<usize> {
+    let mut length = 0;
+    loop {
+        let code = match color {
+            Color::White => white::decode(reader)?,
+            Color::Black => black::decode(reader)?,
+        };
+        length += usize::from(code);
+        if code < 64 {
+            return Some(length);
+        }
+    }
+}
+
+/// Get the first changing element of the reference row after `a0` whose color is the opposite of `color`,
+/// and the next changing element.
+fn reference_changes(reference: &[usize], a0: Option<usize>, color: Color, columns: usize) -> (usize, usize) {
+    let mut index = match a0 {
+        Some(a0) => reference
+            .iter()
+            .position(|&change| change > a0)
+            .unwrap_or(reference.len()),
+        None => 0,
+    };
+    // Changes at even indices are to black.
+    if (index % 2 == 0) != (color == Color::White) {
+        index += 1;
+    }
+    let change = |index: usize| reference.get(index).copied().unwrap_or(columns).min(columns);
+    (change(index), change(index + 1))
+}
+
+fn decode_1d_row(reader: &mut SliceReader, columns: usize) -> Option<Vec<usize>> {
+    let mut changes = Vec::new();
+    let mut a0 = 0;
+    let mut color = Color::White;
+    while a0 < columns {
+        a0 += run_length(reader, color)?;
+        changes.push(a0);
+        color = !color;
+    }
+    Some(changes)
+}
+
+fn decode_2d_row(reader: &mut SliceReader, reference: &[usize], columns: usize) -> Option<Vec<usize>> {
+    let mut changes = Vec::new();
+    let mut a0 = None;
+    let mut color = Color::White;
+    while a0.filter(|&a0| a0 >= columns).is_none() {
+        let (b1, b2) = reference_changes(reference, a0, color, columns);
+        let start = a0.unwrap_or(0);
+        match mode::decode(reader)? {
+            Mode::Pass => a0 = Some(b2),
+            Mode::Horizontal => {
+                let a1 = start + run_length(reader, color)?;
+                let a2 = a1 + run_length(reader, !color)?;
+                changes.push(a1);
+                changes.push(a2);
+                a0 = Some(a2);
+            }
+            Mode::Vertical(delta) => {
+                let a1 = (b1 as isize + isize::from(delta)).max(start as isize) as usize;
+                changes.push(a1);
+                a0 = Some(a1);
+                color = !color;
+            }
+            Mode::Extension | Mode::EOF => return None,
+        }
+    }
+    Some(changes)
+}
+
+/// Keep the changes inside the row, dropping pairs of changes at the same position.
+fn normalize_changes(changes: Vec<usize>, columns: usize) -> Vec<usize> {
+    let mut normalized: Vec<usize> = Vec::with_capacity(changes.len());
+    for change in changes.into_iter().take_while(|&change| change < columns) {
+        if normalized.last() == Some(&change) {
+            normalized.pop();
+        } else {
+            normalized.push(change);
+        }
+    }
+    normalized
+}
+
+/// Decode CCITT fax data, stopping after the given number of rows, at the end-of-block marker or at the first
+/// invalid row. Missing rows are white.
+pub fn decode(input: &[u8], params: &CcittParams) -> Result<Vec<u8>> {
+    let columns = params.columns;
+    if columns == 0 {
+        return Err(Error::Type);
+    }
+    let row_bytes = columns.div_ceil(8);
+    if row_bytes > MAX_OUTPUT {
+        return Err(too_large());
+    }
+    let mut output = Vec::new();
+    let mut reader = SliceReader {
+        data: input,
+        position: 0,
+    };
+    let mut reference = Vec::new();
+    let mut rows = 0;
+
+    while params.rows.filter(|&total| rows >= total).is_none() {
+        if params.k < 0 && params.encoded_byte_align {
+            reader.align();
+        }
+        // Skip end-of-line codes with their fill bits, two of which in a row end the data.
+        let mut end_of_lines = 0;
+        loop {
+            match reader.peek(12) {
+                Some(END_OF_LINE) => {
+                    reader.consume(12).ok();
+                    end_of_lines += 1;
+                    // A tag bit follows each end-of-line code when rows are coded both ways.
+                    if params.k > 0 && reader.peek(13) == Some(1 << 12 | END_OF_LINE) {
+                        reader.consume(1).ok();
+                    }
+                }
+                Some(0) => reader.consume(1).ok().unwrap_or(()),
+                _ => break,
+            }
+        }
+        if end_of_lines >= 2 || reader.at_end() {
+            break;
+        }
+        let two_dimensional = match params.k {
+            k if k < 0 => true,
+            0 => false,
+            _ => {
+                let tag = reader.peek(1);
+                reader.consume(1).ok();
+                tag == Some(0)
+            }
+        };
+        let changes = if two_dimensional {
+            decode_2d_row(&mut reader, &reference, columns)
+        } else {
+            decode_1d_row(&mut reader, columns)
+        };
+        let changes = match changes {
+            Some(changes) => normalize_changes(changes, columns),
+            None if rows > 0 => {
+                warn!("invalid CCITT fax data after {} rows", rows);
+                break;
+            }
+            None => return Err(Error::Syntax("invalid CCITT fax data".to_string())),
+        };
+
+        if output.len() + row_bytes > MAX_OUTPUT {
+            return Err(too_large());
+        }
+        output.extend(render_row(&changes, params));
+        reference = changes;
+        rows += 1;
+    }
+
+    if let Some(total) = params.rows.filter(|&total| total > rows) {
+        let size = row_bytes
+            .checked_mul(total)
+            .filter(|&size| size <= MAX_OUTPUT)
+            .ok_or_else(too_large)?;
+        let white_row = render_row(&[], params);
+        output.extend(white_row.iter().cycle().take(size - output.len()));
+    }
+    Ok(output)
+}
+
+/// Get the bits of a row from the positions where its color changes, starting with white.
+fn render_row(changes: &[usize], params: &CcittParams) -> Vec<u8> {
+    let mut row = vec![0u8; params.columns.div_ceil(8)];
+    let mut color = Color::White;
+    let mut changes = changes.iter().peekable();
+    for column in 0..params.columns {
+        while changes.peek() == Some(&&column) {
+            changes.next();
+            color = !color;
+        }
+        if (color == Color::Black) == params.black_is_1 {
+            row[column / 8] |= 0x80 >> (column % 8);
+        }
+    }
+    row
+}
+
+/// Encode rows of one bit per pixel as Group 4 data, ending with an end-of-block marker.
+pub fn encode(input: &[u8], params: &CcittParams) -> Result<Vec<u8>> {
+    use fax::encoder::Encoder;
+    use fax::VecWriter;
+
+    let width = u16::try_from(params.columns).map_err(|_| Error::Type)?;
+    if width == 0 {
+        return Err(Error::Type);
+    }
+    let row_bytes = params.columns.div_ceil(8);
+    let mut encoder = Encoder::new(VecWriter::new());
+    for row in input.chunks(row_bytes).take(params.rows.unwrap_or(usize::MAX)) {
+        let pels = (0..params.columns).map(|column| {
+            let bit = row.get(column / 8).map_or(0, |byte| byte >> (7 - column % 8) & 1);
+            if (bit == 1) == params.black_is_1 {
+                Color::Black
+            } else {
+                Color::White
+            }
+        });
+        encoder.encode_line(pels, width).ok();
+    }
+    Ok(encoder.finish().map(VecWriter::finish).unwrap_or_default())
+}
+
+#[test]
+fn encode_and_decode_ccitt_fax() {
+    use fax::{BitWriter, Bits, VecWriter};
+
+    let params = CcittParams {
+        k: -1,
+        columns: 10,
+        rows: Some(4),
+        ..CcittParams::default()
+    };
+    let rows = [0b1110_0000, 0b1100_0000, 0, 0, 0xFF, 0xC0, 0b1010_1010, 0b1000_0000];
+    let encoded = encode(&rows, &params).unwrap();
+    assert_eq!(decode(&encoded, &params).unwrap(), rows);
+    let inverted = CcittParams {
+        black_is_1: true,
+        ..params
+    };
+    let decoded = decode(&encoded, &inverted).unwrap();
+    assert!(decoded.iter().zip(&rows).all(|(a, b)| a & 0xC0 == !b & 0xC0));
+    assert_eq!(
+        decode(
+            &encoded,
+            &CcittParams {
+                rows: Some(6),
+                ..params
+            }
+        )
+        .unwrap()[8..],
+        [0xFF, 0xC0, 0xFF, 0xC0]
+    );
+
+    // Group 3 rows of 3 white, 5 black and 2 white pixels, all coded one-dimensionally, or the second one
+    // coded two-dimensionally as three vertical mode codes.
+    let write_1d_row = |writer: &mut VecWriter| {
+        writer.write(white::encode(3).unwrap()).unwrap();
+        writer.write(black::encode(5).unwrap()).unwrap();
+        writer.write(white::encode(2).unwrap()).unwrap();
+    };
+    let mut writer = VecWriter::new();
+    write_1d_row(&mut writer);
+    write_1d_row(&mut writer);
+    let one_dimensional = writer.finish();
+    let mut writer = VecWriter::new();
+    writer.write(Bits { data: 1, len: 1 }).unwrap();
+    write_1d_row(&mut writer);
+    writer.write(Bits { data: 0b0111, len: 4 }).unwrap();
+    let mixed = writer.finish();
+
+    let params = CcittParams {
+        columns: 10,
+        rows: Some(2),
+        ..CcittParams::default()
+    };
+    let expected = [0b1110_0000, 0b1100_0000, 0b1110_0000, 0b1100_0000];
+    assert_eq!(decode(&one_dimensional, &params).unwrap(), expected);
+    assert_eq!(decode(&mixed, &CcittParams { k: 1, ..params }).unwrap(), expected);
+    assert!(decode(&[0x02, 0x00], &CcittParams { k: -1, ..params }).is_err());
+
+    let params = CcittParams::new(
+        Some(&dictionary! { "K" => -1, "Columns" => 8, "BlackIs1" => true }),
+        Some(3),
+    );
+    assert_eq!(
+        (params.k, params.columns, params.rows, params.black_is_1),
+        (-1, 8, Some(3), true)
+    );
+
+    // Sizes from the stream that would not fit in memory.
+    let hostile = |columns, rows| CcittParams {
+        k: -1,
+        columns,
+        rows: Some(rows),
+        ..CcittParams::default()
+    };
+    assert!(decode(&encoded, &hostile(10, usize::MAX)).is_err());
+    assert!(decode(&encoded, &hostile(10, 1 << 40)).is_err());
+    assert!(decode(&encoded, &hostile(usize::MAX, 4)).is_err());
+}
@@ -0,0 +1,111 @@
+//! JPEG data as used by the DCTDecode filter.
+//!
+//! Decoding the pixels needs the `images` feature. The output is the one of the PDF filter, so the components
+//! of CMYK files written by Adobe applications stay inverted and images with such data need a `Decode` array.
+
+use crate::{Error, Result};
+
+/// Information from the header of a JPEG file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JpegInfo {
+    pub width: u16,
+    pub height: u16,
+    pub components: u8,
+    pub bits_per_component: u8,
+    /// Color transform of the Adobe APP14 marker segment, if any: 0 for none, 1 for YCbCr and 2 for YCCK.
+    pub adobe_transform: Option<u8>,
+}
+
+/// Read the header of a JPEG file up to the start of its frame.
+pub fn info(data: &[u8]) -> Result<JpegInfo> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return Err(Error::Syntax("missing JPEG start of image".to_owned()));
+    }
+    let mut adobe_transform = None;
+    let mut offset = 2;
+    while offset + 4 <= data.len() && data[offset] == 0xFF {
+        let marker = data[offset + 1];
+        // Markers may be padded with fill bytes.
+        if marker == 0xFF {
+            offset += 1;
+            continue;
+        }
+        let length = usize::from(data[offset + 2]) << 8 | usize::from(data[offset + 3]);
+        let segment = &data[offset + 4..(offset + 2 + length).clamp(offset + 4, data.len())];
+        if marker == 0xEE && segment.starts_with(b"Adobe") && segment.len() >= 12 {
+            adobe_transform = Some(segment[11]);
+        }
+        if (0xC0..=0xCF).contains(&marker) && ![0xC4, 0xC8, 0xCC].contains(&marker) {
+            if segment.len() < 6 {
+                break;
+            }
+            return Ok(JpegInfo {
+                width: u16::from(segment[3]) << 8 | u16::from(segment[4]),
+                height: u16::from(segment[1]) << 8 | u16::from(segment[2]),
+                components: segment[5],
+                bits_per_component: segment[0],
+                adobe_transform,
+            });
+        }
+        offset += 2 + length;
+    }
+    Err(Error::Syntax("missing JPEG frame header".to_owned()))
+}
+
+/// Decode the pixels of a JPEG file, with 8 bits per component.
+#[cfg(feature = "images")]
+pub fn decode(data: &[u8]) -> Result<Vec<u8>> {
+    let mut decoder = jpeg_decoder::Decoder::new(data);
+    let mut pixels = decoder.decode().map_err(|error| Error::Syntax(error.to_string()))?;
+    if let Ok(JpegInfo {
+        components: 4,
+        adobe_transform: Some(transform),
+        ..
+    }) = info(data)
+    {
+        restore_adobe_cmyk(&mut pixels, transform);
+    }
+    Ok(pixels)
+}
+
+/// Undo the inversion of Adobe CMYK data by the JPEG decoder, which PDF leaves to the `Decode` array of the image.
+#[cfg(feature = "images")]
+fn restore_adobe_cmyk(pixels: &mut [u8], transform: u8) {
+    for pixel in pixels.chunks_exact_mut(4) {
+        // With the YCCK transform, only the black component was inverted.
+        let inverted = if transform == 0 {
+            &mut pixel[..]
+        } else {
+            &mut pixel[3..]
+        };
+        for component in inverted {
+            *component = 255 - *component;
+        }
+    }
+}
+
+#[test]
+fn read_jpeg_info() {
+    let jpeg = std::fs::read("assets/pdf_icon.jpg").unwrap();
+    let info = info(&jpeg).unwrap();
+    assert_eq!((info.width, info.height, info.components), (800, 450, 3));
+    assert_eq!(info.bits_per_component, 8);
+    assert!(self::info(b"GIF89a").is_err());
+
+    let mut adobe = vec![0xFF, 0xD8, 0xFF, 0xEE, 0x00, 0x0E];
+    adobe.extend_from_slice(b"Adobe\x00\x64\x00\x00\x00\x00\x02");
+    adobe.extend_from_slice(&[0xFF, 0xC0, 0x00, 0x11, 0x08, 0x00, 0x02, 0x00, 0x03, 0x04]);
+    let info = self::info(&adobe).unwrap();
+    assert_eq!((info.width, info.height, info.components), (3, 2, 4));
+    assert_eq!(info.adobe_transform, Some(2));
+
+    #[cfg(feature = "images")]
+    {
+        let mut pixels = [10, 20, 30, 40, 50, 60, 70, 80];
+        restore_adobe_cmyk(&mut pixels, 0);
+        assert_eq!(pixels, [245, 235, 225, 215, 205, 195, 185, 175]);
+        restore_adobe_cmyk(&mut pixels, 2);
+        assert_eq!(pixels, [245, 235, 225, 40, 205, 195, 185, 80]);
+        assert_eq!(decode(&jpeg).unwrap().len(), 800 * 450 * 3);
+    }
+}
@@ -0,0 +1,1172 @@
+#![cfg(feature = "jbig2")]
+
+//! JBIG2 data as used by the JBIG2Decode filter, in the embedded organization of PDF where the segments of a
+//! page follow those of the optional globals stream.
+//!
+//! Generic regions, coded with MMR or with the arithmetic coder, symbol dictionaries and the text regions which
+//! use them are supported when they are coded with the arithmetic coder and without refinement. Other region
+//! segments are skipped with a warning, and Huffman coded or refined symbols are an error.
+
+use crate::filters::ccitt::{self, CcittParams};
+use crate::{Error, Result};
+use log::warn;
+use std::collections::HashMap;
+
+/// Probability estimates of the arithmetic coder, as the Qe value and the next indices after a more or less
+/// probable symbol, and whether a less probable symbol switches the sense of the more probable one.
+const QE_TABLE: [(u16, u8, u8, bool); 47] = [
+    (0x5601, 1, 1, true),
+    (0x3401, 2, 6, false),
+    (0x1801, 3, 9, false),
+    (0x0AC1, 4, 12, false),
+    (0x0521, 5, 29, false),
+    (0x0221, 38, 33, false),
+    (0x5601, 7, 6, true),
+    (0x5401, 8, 14, false),
+    (0x4801, 9, 14, false),
+    (0x3801, 10, 14, false),
+    (0x3001, 11, 17, false),
+    (0x2401, 12, 18, false),
+    (0x1C01, 13, 20, false),
+    (0x1601, 29, 21, false),
+    (0x5601, 15, 14, true),
+    (0x5401, 16, 14, false),
+    (0x5101, 17, 15, false),
+    (0x4801, 18, 16, false),
+    (0x3801, 19, 17, false),
+    (0x3401, 20, 18, false),
+    (0x3001, 21, 19, false),
+    (0x2801, 22, 19, false),
+    (0x2401, 23, 20, false),
+    (0x2201, 24, 21, false),
+    (0x1C01, 25, 22, false),
+    (0x1801, 26, 23, false),
+    (0x1601, 27, 24, false),
+    (0x1401, 28, 25, false),
+    (0x1201, 29, 26, false),
+    (0x1101, 30, 27, false),
+    (0x0AC1, 31, 28, false),
+    (0x09C1, 32, 29, false),
+    (0x08A1, 33, 30, false),
+    (0x0521, 34, 31, false),
+    (0x0441, 35, 32, false),
+    (0x02A1, 36, 33, false),
+    (0x0221, 37, 34, false),
+    (0x0141, 38, 35, false),
+    (0x0111, 39, 36, false),
+    (0x0085, 40, 37, false),
+    (0x0049, 41, 38, false),
+    (0x0025, 42, 39, false),
+    (0x0015, 43, 40, false),
+    (0x0009, 44, 41, false),
+    (0x0005, 45, 42, false),
+    (0x0001, 45, 43, false),
+    (0x5601, 46, 46, false),
+];
+
+/// Contexts of generic regions which decode whether a row is the same as the previous one, for each template.
+const TYPICAL_PREDICTION_CONTEXTS: [usize; 4] = [0x9B25, 0x0795, 0x00E5, 0x0195];
+
+/// Pixels around the current one which form the context of generic regions, for each template, before the
+/// adaptive pixels.
+const GENERIC_TEMPLATES: [&[(i8, i8)]; 4] = [
+    &[
+        (-1, -2),
+        (0, -2),
+        (1, -2),
+        (-2, -1),
+        (-1, -1),
+        (0, -1),
+        (1, -1),
+        (2, -1),
+        (-4, 0),
+        (-3, 0),
+        (-2, 0),
+        (-1, 0),
+    ],
+    &[
+        (-1, -2),
+        (0, -2),
+        (1, -2),
+        (2, -2),
+        (-2, -1),
+        (-1, -1),
+        (0, -1),
+        (1, -1),
+        (2, -1),
+        (-3, 0),
+        (-2, 0),
+        (-1, 0),
+    ],
+    &[
+        (-1, -2),
+        (0, -2),
+        (1, -2),
+        (-2, -1),
+        (-1, -1),
+        (0, -1),
+        (1, -1),
+        (-2, 0),
+        (-1, 0),
+    ],
+    &[
+        (-3, -1),
+        (-2, -1),
+        (-1, -1),
+        (0, -1),
+        (1, -1),
+        (-4, 0),
+        (-3, 0),
+        (-2, 0),
+        (-1, 0),
+    ],
+];
+
+/// Largest number of pixels of a bitmap, which are stored as a byte each.
+const MAX_PIXELS: usize = 1 << 28;
+
+fn truncated() -> Error {
+    Error::Syntax("truncated JBIG2 data".to_string())
+}
+
+/// Decoder of the MQ arithmetic coder. Each context is stored as its index in the probability table and the
+/// value of its more probable symbol in the lowest bit.
+struct ArithmeticDecoder<'a> {
+    data: &'a [u8],
+    position: usize,
+    c_high: u32,
+    c_low: u32,
+    a: u32,
+    count: u32,
+    /// Number of bytes of ones read at the end of the data, which only a few renormalizations need.
+    padding: usize,
+}
+
+impl<'a> ArithmeticDecoder<'a> {
+    fn new(data: &'a [u8]) -> ArithmeticDecoder<'a> {
+        let mut decoder = ArithmeticDecoder {
+            data,
+            position: 0,
+            c_high: u32::from(data.first().copied().unwrap_or(0xFF)),
+            c_low: 0,
+            a: 0x8000,
+            count: 0,
+            padding: 0,
+        };
+        decoder.byte_in();
+        decoder.c_high = ((decoder.c_high << 7) & 0xFFFF) | ((decoder.c_low >> 9) & 0x7F);
+        decoder.c_low = (decoder.c_low << 7) & 0xFFFF;
+        decoder.count -= 7;
+        decoder
+    }
+
+    /// Read the next byte, or ones past the end of the data or a marker.
+    fn byte_in(&mut self) {
+        let data = self.data;
+        let byte = |position: usize| data.get(position).copied().unwrap_or(0xFF);
+        if byte(self.position) == 0xFF {
+            if byte(self.position + 1) > 0x8F {
+                self.c_low += 0xFF00;
+                self.count = 8;
+                self.padding += 1;
+            } else {
+                self.position += 1;
+                self.c_low += u32::from(byte(self.position)) << 9;
+                self.count = 7;
+            }
+        } else {
+            self.position += 1;
+            self.c_low += u32::from(byte(self.position)) << 8;
+            self.count = 8;
+        }
+        if self.c_low > 0xFFFF {
+            self.c_high += self.c_low >> 16;
+            self.c_low &= 0xFFFF;
+        }
+    }
+
+    /// Fail when far past the end of the data, where hostile counts would otherwise keep decoding.
+    fn check_end(&self) -> Result<()> {
+        if self.padding > 64 {
+            Err(truncated())
+        } else {
+            Ok(())
+        }
+    }
+
+    fn decode(&mut self, contexts: &mut [u8], context: usize) -> u8 {
+        let (index, mut more_probable) = (contexts[context] >> 1, contexts[context] & 1);
+        let (qe, next_more, next_less, switch) = QE_TABLE[usize::from(index)];
+        let qe = u32::from(qe);
+        self.a -= qe;
+        let (bit, next) = if self.c_high < qe {
+            if self.a < qe {
+                self.a = qe;
+                (more_probable, next_more)
+            } else {
+                self.a = qe;
+                let bit = 1 - more_probable;
+                if switch {
+                    more_probable = bit;
+                }
+                (bit, next_less)
+            }
+        } else {
+            self.c_high -= qe;
+            if self.a & 0x8000 != 0 {
+                return more_probable;
+            }
+            if self.a < qe {
+                let bit = 1 - more_probable;
+                if switch {
+                    more_probable = bit;
+                }
+                (bit, next_less)
+            } else {
+                (more_probable, next_more)
+            }
+        };
+        while self.a & 0x8000 == 0 {
+            if self.count == 0 {
+                self.byte_in();
+            }
+            self.a <<= 1;
+            self.c_high = ((self.c_high << 1) & 0xFFFF) | ((self.c_low >> 15) & 1);
+            self.c_low = (self.c_low << 1) & 0xFFFF;
+            self.count -= 1;
+        }
+        contexts[context] = next << 1 | more_probable;
+        bit
+    }
+}
+
+/// Contexts of an integer decoding procedure of the arithmetic coder.
+struct IntegerContexts(Vec<u8>);
+
+impl IntegerContexts {
+    fn new() -> IntegerContexts {
+        IntegerContexts(vec![0; 512])
+    }
+
+    /// Decode an integer, or `None` for the out-of-band value.
+    fn decode(&mut self, decoder: &mut ArithmeticDecoder) -> Option<i64> {
+        let mut previous = 1usize;
+        let mut read_bits = |length: u32| {
+            let mut value = 0i64;
+            for _ in 0..length {
+                let bit = decoder.decode(&mut self.0, previous);
+                previous = if previous < 256 {
+                    previous << 1 | usize::from(bit)
+                } else {
+                    ((previous << 1 | usize::from(bit)) & 511) | 256
+                };
+                value = value << 1 | i64::from(bit);
+            }
+            value
+        };
+        let sign = read_bits(1);
+        let value = if read_bits(1) == 0 {
+            read_bits(2)
+        } else if read_bits(1) == 0 {
+            read_bits(4) + 4
+        } else if read_bits(1) == 0 {
+            read_bits(6) + 20
+        } else if read_bits(1) == 0 {
+            read_bits(8) + 84
+        } else if read_bits(1) == 0 {
+            read_bits(12) + 340
+        } else {
+            read_bits(32) + 4436
+        };
+        match (sign, value) {
+            (0, value) => Some(value),
+            (_, 0) => None,
+            (_, value) => Some(-value),
+        }
+    }
+}
+
+/// Decode a symbol id of the given length with the arithmetic coder.
+fn decode_symbol_id(decoder: &mut ArithmeticDecoder, contexts: &mut [u8], length: u32) -> usize {
+    let mut previous = 1usize;
+    for _ in 0..length {
+        previous = previous << 1 | usize::from(decoder.decode(contexts, previous));
+    }
+    previous - (1 << length)
+}
+
+/// Bilevel image with a byte per pixel, where 1 is black.
+#[derive(Debug, Clone, PartialEq)]
+struct Bitmap {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Bitmap {
+    fn new(width: usize, height: usize, value: u8) -> Result<Bitmap> {
+        Ok(Bitmap {
+            width,
+            height,
+            pixels: vec![value; pixel_count(width, height)?],
+        })
+    }
+
+    fn get(&self, x: isize, y: isize) -> u8 {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            0
+        } else {
+            self.pixels[y as usize * self.width + x as usize]
+        }
+    }
+
+    /// Combine a bitmap with this one at the given position, with an operator of region segments:
+    /// 0 for or, 1 for and, 2 for exclusive or, 3 for exclusive nor and 4 for replacing.
+    fn compose(&mut self, other: &Bitmap, x: isize, y: isize, operator: u8) {
+        for row in 0..other.height {
+            let target_y = y + row as isize;
+            if target_y < 0 || target_y as usize >= self.height {
+                continue;
+            }
+            for column in 0..other.width {
+                let target_x = x + column as isize;
+                if target_x < 0 || target_x as usize >= self.width {
+                    continue;
+                }
+                let source = other.pixels[row * other.width + column];
+                let target = &mut self.pixels[target_y as usize * self.width + target_x as usize];
+                *target = match operator {
+                    0 => *target | source,
+                    1 => *target & source,
+                    2 => *target ^ source,
+                    3 => 1 - (*target ^ source),
+                    _ => source,
+                };
+            }
+        }
+    }
+
+    /// Grow or shrink the bitmap to a number of rows, filling new ones with a value.
+    fn resize(&mut self, height: usize, value: u8) -> Result<()> {
+        self.pixels.resize(pixel_count(self.width, height)?, value);
+        self.height = height;
+        Ok(())
+    }
+}
+
+fn pixel_count(width: usize, height: usize) -> Result<usize> {
+    width
+        .checked_mul(height)
+        .filter(|&count| count <= MAX_PIXELS)
+        .ok_or_else(|| Error::Syntax(format!("JBIG2 bitmap of {}x{} pixels is too large", width, height)))
+}
+
+/// Decode a generic region coded with the arithmetic coder.
+fn decode_generic_region(
+    decoder: &mut ArithmeticDecoder, contexts: &mut [u8], width: usize, height: usize, template: u8,
+    typical_prediction: bool, adaptive_pixels: &[(i8, i8)],
+) -> Result<Bitmap> {
+    let mut pixels: Vec<(isize, isize)> = GENERIC_TEMPLATES[usize::from(template)]
+        .iter()
+        .chain(adaptive_pixels)
+        .map(|&(x, y)| (isize::from(x), isize::from(y)))
+        .collect();
+    // Context bits are in raster order, which the contexts of typical prediction rely on.
+    pixels.sort_by_key(|&(x, y)| (y, x));
+
+    let mut bitmap = Bitmap::new(width, height, 0)?;
+    let mut typical = false;
+    for y in 0..height {
+        if typical_prediction {
+            typical ^= decoder.decode(contexts, TYPICAL_PREDICTION_CONTEXTS[usize::from(template)]) == 1;
+            if typical {
+                if y > 0 {
+                    bitmap.pixels.copy_within((y - 1) * width..y * width, y * width);
+                }
+                continue;
+            }
+        }
+        for x in 0..width {
+            let context = pixels.iter().fold(0usize, |context, &(dx, dy)| {
+                context << 1 | usize::from(bitmap.get(x as isize + dx, y as isize + dy))
+            });
+            bitmap.pixels[y * width + x] = decoder.decode(contexts, context);
+        }
+    }
+    Ok(bitmap)
+}
+
+/// Decode a generic region coded with MMR, which is Group 4 fax coding with black as 1.
+fn decode_mmr_region(data: &[u8], width: usize, height: usize) -> Result<Bitmap> {
+    let mut bitmap = Bitmap::new(width, height, 0)?;
+    let params = CcittParams {
+        k: -1,
+        columns: width,
+        rows: Some(height),
+        black_is_1: true,
+        encoded_byte_align: false,
+    };
+    let packed = ccitt::decode(data, &params)?;
+    let row_bytes = width.div_ceil(8);
+    for y in 0..height {
+        for x in 0..width {
+            bitmap.pixels[y * width + x] = packed[y * row_bytes + x / 8] >> (7 - x % 8) & 1;
+        }
+    }
+    Ok(bitmap)
+}
+
+/// Big-endian reader of segment headers and data.
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, length: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.position..self.position.checked_add(length).ok_or_else(truncated)?)
+            .ok_or_else(truncated)?;
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn i8(&mut self) -> Result<i8> {
+        Ok(self.u8()? as i8)
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from(bytes[0]) << 8 | u16::from(bytes[1]))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let bytes = self.bytes(4)?;
+        Ok(bytes.iter().fold(0, |value, &byte| value << 8 | u32::from(byte)))
+    }
+
+    fn remaining(&self) -> &'a [u8] {
+        &self.data[self.position.min(self.data.len())..]
+    }
+}
+
+struct Segment<'a> {
+    number: u32,
+    kind: u8,
+    referred: Vec<u32>,
+    data: &'a [u8],
+}
+
+fn read_segment<'a>(reader: &mut Reader<'a>) -> Result<Segment<'a>> {
+    let number = reader.u32()?;
+    let flags = reader.u8()?;
+    let first = reader.u8()?;
+    let referred_count = if first >> 5 == 7 {
+        reader.position -= 1;
+        let count = (reader.u32()? & 0x1FFF_FFFF) as usize;
+        reader.bytes((count + 8) / 8)?;
+        count
+    } else {
+        usize::from(first >> 5)
+    };
+    // Each referred segment number takes at least a byte.
+    let mut referred = Vec::with_capacity(referred_count.min(reader.remaining().len()));
+    for _ in 0..referred_count {
+        referred.push(match number {
+            0..=256 => u32::from(reader.u8()?),
+            257..=65536 => u32::from(reader.u16()?),
+            _ => reader.u32()?,
+        });
+    }
+    if flags & 0x40 != 0 {
+        reader.u32()?;
+    } else {
+        reader.u8()?;
+    }
+    let length = reader.u32()?;
+    if length == u32::MAX {
+        return Err(Error::Syntax(
+            "JBIG2 segments of unknown length are not supported".to_string(),
+        ));
+    }
+    Ok(Segment {
+        number,
+        kind: flags & 0x3F,
+        referred,
+        data: reader.bytes(length as usize)?,
+    })
+}
+
+/// Position, size and combination operator of a region segment.
+struct RegionInfo {
+    width: usize,
+    height: usize,
+    x: isize,
+    y: isize,
+    operator: u8,
+}
+
+fn read_region_info(reader: &mut Reader) -> Result<RegionInfo> {
+    Ok(RegionInfo {
+        width: reader.u32()? as usize,
+        height: reader.u32()? as usize,
+        x: reader.u32()? as i32 as isize,
+        y: reader.u32()? as i32 as isize,
+        operator: reader.u8()? & 0x07,
+    })
+}
+
+fn read_adaptive_pixels(reader: &mut Reader, template: u8) -> Result<Vec<(i8, i8)>> {
+    let count = if template == 0 { 4 } else { 1 };
+    (0..count).map(|_| Ok((reader.i8()?, reader.i8()?))).collect()
+}
+
+fn symbol_code_length(count: usize) -> u32 {
+    let mut length = 0;
+    while (1usize << length) < count {
+        length += 1;
+    }
+    length
+}
+
+#[derive(Default)]
+struct Decoder {
+    /// Symbols exported by symbol dictionary segments.
+    symbols: HashMap<u32, Vec<Bitmap>>,
+    page: Option<Bitmap>,
+    /// Whether the height of the page is unknown and grows with its stripes.
+    striped: bool,
+    default_pixel: u8,
+}
+
+impl Decoder {
+    fn referred_symbols(&self, segment: &Segment) -> Vec<Bitmap> {
+        segment
+            .referred
+            .iter()
+            .filter_map(|number| self.symbols.get(number))
+            .flatten()
+            .cloned()
+            .collect()
+    }
+
+    fn process(&mut self, segment: &Segment) -> Result<()> {
+        let mut reader = Reader {
+            data: segment.data,
+            position: 0,
+        };
+        match segment.kind {
+            0 => {
+                let symbols = self.decode_symbol_dictionary(segment, &mut reader)?;
+                self.symbols.insert(segment.number, symbols);
+            }
+            4 | 6 | 7 => {
+                let info = read_region_info(&mut reader)?;
+                let region = self.decode_text_region(segment, &info, &mut reader)?;
+                self.place_region(&region, &info, segment.kind != 4)?;
+            }
+            36 | 38 | 39 => {
+                let info = read_region_info(&mut reader)?;
+                let flags = reader.u8()?;
+                let template = (flags >> 1) & 0x03;
+                let region = if flags & 0x01 != 0 {
+                    decode_mmr_region(reader.remaining(), info.width, info.height)?
+                } else {
+                    let adaptive_pixels = read_adaptive_pixels(&mut reader, template)?;
+                    let mut decoder = ArithmeticDecoder::new(reader.remaining());
+                    let mut contexts = vec![0u8; 1 << 16];
+                    decode_generic_region(
+                        &mut decoder,
+                        &mut contexts,
+                        info.width,
+                        info.height,
+                        template,
+                        flags & 0x08 != 0,
+                        &adaptive_pixels,
+                    )?
+                };
+                self.place_region(&region, &info, segment.kind != 36)?;
+            }
+            48 => {
+                let width = reader.u32()?;
+                let height = reader.u32()?;
+                reader.u32()?;
+                reader.u32()?;
+                let flags = reader.u8()?;
+                self.default_pixel = (flags >> 2) & 1;
+                self.striped = height == u32::MAX;
+                let height = if self.striped { 0 } else { height as usize };
+                self.page = Some(Bitmap::new(width as usize, height, self.default_pixel)?);
+            }
+            50 => {
+                let end_row = reader.u32()? as usize;
+                if let (Some(page), true) = (self.page.as_mut(), self.striped) {
+                    page.resize(end_row.saturating_add(1), self.default_pixel)?;
+                }
+            }
+            // End of page or of file, profiles, tables and extensions.
+            49 | 51 | 52 | 53 | 62 => {}
+            kind => warn!("JBIG2 segments of type {} are not supported", kind),
+        }
+        Ok(())
+    }
+
+    /// Combine an immediate region with the page. Intermediate regions are only used by refinement regions,
+    /// which aren't supported.
+    fn place_region(&mut self, region: &Bitmap, info: &RegionInfo, immediate: bool) -> Result<()> {
+        if let (Some(page), true) = (self.page.as_mut(), immediate) {
+            let bottom = (info.y.max(0) as usize).saturating_add(region.height);
+            if self.striped && bottom > page.height {
+                page.resize(bottom, self.default_pixel)?;
+            }
+            page.compose(region, info.x, info.y, info.operator);
+        }
+        Ok(())
+    }
+
+    fn decode_symbol_dictionary(&self, segment: &Segment, reader: &mut Reader) -> Result<Vec<Bitmap>> {
+        let flags = reader.u16()?;
+        if flags & 0x03 != 0 {
+            return Err(Error::Syntax(
+                "JBIG2 symbol dictionaries with Huffman coding or refinement are not supported".to_string(),
+            ));
+        }
+        let template = ((flags >> 10) & 0x03) as u8;
+        let adaptive_pixels = read_adaptive_pixels(reader, template)?;
+        let exported_count = reader.u32()? as usize;
+        let new_count = reader.u32()? as usize;
+        let input_symbols = self.referred_symbols(segment);
+        let symbol_count = input_symbols.len() + new_count;
+        let invalid = || Error::Syntax("invalid JBIG2 symbol dictionary".to_string());
+        if exported_count > symbol_count {
+            return Err(invalid());
+        }
+
+        let mut decoder = ArithmeticDecoder::new(reader.remaining());
+        let mut generic_contexts = vec![0u8; 1 << 16];
+        let mut height_contexts = IntegerContexts::new();
+        let mut width_contexts = IntegerContexts::new();
+        let mut export_contexts = IntegerContexts::new();
+        // The counts come from the segment, so don't reserve more symbols than there are bytes to decode.
+        let mut new_symbols = Vec::with_capacity(new_count.min(reader.remaining().len()));
+        let mut height = 0i64;
+        let mut pixels = 0usize;
+        while new_symbols.len() < new_count {
+            decoder.check_end()?;
+            height += height_contexts.decode(&mut decoder).ok_or_else(truncated)?;
+            let mut width = 0i64;
+            let class_start = new_symbols.len();
+            while let Some(delta) = width_contexts.decode(&mut decoder) {
+                decoder.check_end()?;
+                width += delta;
+                if new_symbols.len() == new_count || width < 0 || height < 0 {
+                    return Err(invalid());
+                }
+                let symbol = decode_generic_region(
+                    &mut decoder,
+                    &mut generic_contexts,
+                    width as usize,
+                    height as usize,
+                    template,
+                    false,
+                    &adaptive_pixels,
+                )?;
+                pixels += symbol.pixels.len();
+                if pixels > MAX_PIXELS {
+                    return Err(Error::Syntax("JBIG2 symbol dictionary is too large".to_string()));
+                }
+                new_symbols.push(symbol);
+            }
+            if new_symbols.len() == class_start {
+                return Err(invalid());
+            }
+        }
+
+        let mut exported = Vec::with_capacity(exported_count);
+        let mut index = 0;
+        let mut export = false;
+        let mut previous_run = None;
+        let all_symbols: Vec<&Bitmap> = input_symbols.iter().chain(&new_symbols).collect();
+        while index < symbol_count {
+            decoder.check_end()?;
+            let run = export_contexts.decode(&mut decoder).ok_or_else(truncated)?.max(0) as usize;
+            if run == 0 && previous_run == Some(0) {
+                return Err(invalid());
+            }
+            if export {
+                exported.extend(all_symbols.iter().skip(index).take(run).map(|&symbol| symbol.clone()));
+            }
+            index += run;
+            export = !export;
+            previous_run = Some(run);
+        }
+        Ok(exported)
+    }
+
+    fn decode_text_region(&self, segment: &Segment, info: &RegionInfo, reader: &mut Reader) -> Result<Bitmap> {
+        let flags = reader.u16()?;
+        if flags & 0x03 != 0 {
+            return Err(Error::Syntax(
+                "JBIG2 text regions with Huffman coding or refinement are not supported".to_string(),
+            ));
+        }
+        let strip_size = 1i64 << ((flags >> 2) & 0x03);
+        let reference_corner = (flags >> 4) & 0x03;
+        let transposed = flags & 0x40 != 0;
+        let operator = ((flags >> 7) & 0x03) as u8;
+        let default_pixel = ((flags >> 9) & 1) as u8;
+        // Signed 5 bit offset.
+        let offset = i64::from(((flags >> 10) & 0x1F) as i8) - if flags & 0x4000 != 0 { 32 } else { 0 };
+        let instances = reader.u32()? as usize;
+        let symbols = self.referred_symbols(segment);
+        let code_length = symbol_code_length(symbols.len());
+
+        let mut decoder = ArithmeticDecoder::new(reader.remaining());
+        let mut strip_contexts = IntegerContexts::new();
+        let mut first_contexts = IntegerContexts::new();
+        let mut separation_contexts = IntegerContexts::new();
+        let mut strip_offset_contexts = IntegerContexts::new();
+        let mut id_contexts = vec![0u8; 1 << (code_length + 1)];
+        let mut region = Bitmap::new(info.width, info.height, default_pixel)?;
+
+        let mut strip = -strip_contexts.decode(&mut decoder).ok_or_else(truncated)? * strip_size;
+        let mut first_s = 0;
+        let mut placed = 0;
+        while placed < instances {
+            decoder.check_end()?;
+            strip += strip_contexts.decode(&mut decoder).ok_or_else(truncated)? * strip_size;
+            first_s += first_contexts.decode(&mut decoder).ok_or_else(truncated)?;
+            let mut s = first_s;
+            loop {
+                let t = strip
+                    + if strip_size > 1 {
+                        strip_offset_contexts.decode(&mut decoder).ok_or_else(truncated)?
+                    } else {
+                        0
+                    };
+                let id = decode_symbol_id(&mut decoder, &mut id_contexts, code_length);
+                let symbol = symbols
+                    .get(id)
+                    .ok_or_else(|| Error::Syntax("invalid JBIG2 symbol id".to_string()))?;
+                let (width, height) = (symbol.width as i64, symbol.height as i64);
+                // Size of the symbol along the direction of S.
+                let extent = if transposed { height } else { width };
+                let right = reference_corner & 2 != 0;
+                let bottom = reference_corner & 1 == 0;
+                let advances_first = if transposed { bottom } else { right };
+                if advances_first {
+                    s += extent - 1;
+                }
+                let (x, y) = if transposed { (t, s) } else { (s, t) };
+                let x = x - if right { width - 1 } else { 0 };
+                let y = y - if bottom { height - 1 } else { 0 };
+                region.compose(symbol, x as isize, y as isize, operator);
+                if !advances_first {
+                    s += extent - 1;
+                }
+
+                placed += 1;
+                decoder.check_end()?;
+                match separation_contexts.decode(&mut decoder) {
+                    Some(separation) if placed < instances => s += separation + offset,
+                    _ => break,
+                }
+            }
+        }
+        Ok(region)
+    }
+}
+
+/// Decode the JBIG2 data of an image, after the segments of the globals stream if any, into rows of one bit
+/// per pixel where 0 is black, as PDF images expect.
+pub fn decode(input: &[u8], globals: Option<&[u8]>) -> Result<Vec<u8>> {
+    let mut decoder = Decoder::default();
+    for data in globals.into_iter().chain(Some(input)) {
+        let mut reader = Reader { data, position: 0 };
+        while reader.position < data.len() {
+            let segment = read_segment(&mut reader)?;
+            decoder.process(&segment)?;
+            if segment.kind == 51 {
+                break;
+            }
+        }
+    }
+
+    let page = decoder
+        .page
+        .ok_or_else(|| Error::Syntax("missing JBIG2 page information".to_string()))?;
+    let row_bytes = page.width.div_ceil(8);
+    let mut output = vec![0xFF; row_bytes * page.height];
+    for y in 0..page.height {
+        for x in 0..page.width {
+            if page.pixels[y * page.width + x] == 1 {
+                output[y * row_bytes + x / 8] &= !(0x80 >> (x % 8));
+            }
+        }
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encoder of the MQ arithmetic coder.
+    struct ArithmeticEncoder {
+        output: Vec<u8>,
+        c: u32,
+        a: u32,
+        count: u32,
+    }
+
+    impl ArithmeticEncoder {
+        fn new() -> ArithmeticEncoder {
+            ArithmeticEncoder {
+                // The byte before the data, which isn't output.
+                output: vec![0],
+                c: 0,
+                a: 0x8000,
+                count: 12,
+            }
+        }
+
+        fn encode(&mut self, contexts: &mut [u8], context: usize, bit: u8) {
+            let (index, mut more_probable) = (contexts[context] >> 1, contexts[context] & 1);
+            let (qe, next_more, next_less, switch) = QE_TABLE[usize::from(index)];
+            let qe = u32::from(qe);
+            self.a -= qe;
+            let next = if bit == more_probable {
+                if self.a & 0x8000 != 0 {
+                    self.c += qe;
+                    return;
+                }
+                if self.a < qe {
+                    self.a = qe;
+                } else {
+                    self.c += qe;
+                }
+                next_more
+            } else {
+                if self.a < qe {
+                    self.c += qe;
+                } else {
+                    self.a = qe;
+                }
+                if switch {
+                    more_probable = 1 - more_probable;
+                }
+                next_less
+            };
+            contexts[context] = next << 1 | more_probable;
+            while self.a & 0x8000 == 0 {
+                self.a <<= 1;
+                self.c <<= 1;
+                self.count -= 1;
+                if self.count == 0 {
+                    self.byte_out();
+                }
+            }
+        }
+
+        fn byte_out(&mut self) {
+            let last = *self.output.last().unwrap();
+            if last == 0xFF {
+                self.output.push((self.c >> 20) as u8);
+                self.c &= 0xF_FFFF;
+                self.count = 7;
+            } else if self.c < 0x800_0000 {
+                self.output.push((self.c >> 19) as u8);
+                self.c &= 0x7_FFFF;
+                self.count = 8;
+            } else {
+                *self.output.last_mut().unwrap() += 1;
+                if last + 1 == 0xFF {
+                    self.c &= 0x7FF_FFFF;
+                    self.output.push((self.c >> 20) as u8);
+                    self.c &= 0xF_FFFF;
+                    self.count = 7;
+                } else {
+                    self.output.push((self.c >> 19) as u8);
+                    self.c &= 0x7_FFFF;
+                    self.count = 8;
+                }
+            }
+        }
+
+        fn finish(mut self) -> Vec<u8> {
+            let sum = self.c + self.a;
+            self.c |= 0xFFFF;
+            if self.c >= sum {
+                self.c -= 0x8000;
+            }
+            self.c <<= self.count;
+            self.byte_out();
+            self.c <<= self.count;
+            self.byte_out();
+            if *self.output.last().unwrap() != 0xFF {
+                self.output.push(0xFF);
+            }
+            self.output.push(0xAC);
+            self.output.remove(0);
+            self.output
+        }
+
+        fn encode_integer(&mut self, contexts: &mut [u8], value: Option<i64>) {
+            let mut previous = 1usize;
+            let mut write_bits = |encoder: &mut ArithmeticEncoder, value: i64, length: u32| {
+                for shift in (0..length).rev() {
+                    let bit = (value >> shift & 1) as u8;
+                    encoder.encode(contexts, previous, bit);
+                    previous = if previous < 256 {
+                        previous << 1 | usize::from(bit)
+                    } else {
+                        ((previous << 1 | usize::from(bit)) & 511) | 256
+                    };
+                }
+            };
+            let (sign, magnitude) = match value {
+                Some(value) => (i64::from(value < 0), value.abs()),
+                None => (1, 0),
+            };
+            write_bits(self, sign, 1);
+            let ranges = [(0, 2, 0b0, 1), (4, 4, 0b10, 2), (20, 6, 0b110, 3), (84, 8, 0b1110, 4)];
+            let &(start, bits, prefix, prefix_length) = ranges
+                .iter()
+                .find(|&&(start, bits, _, _)| magnitude < start + (1 << bits))
+                .unwrap();
+            write_bits(self, prefix, prefix_length);
+            write_bits(self, magnitude - start, bits);
+        }
+
+        fn encode_generic_region(
+            &mut self, contexts: &mut [u8], bitmap: &Bitmap, template: u8, typical_prediction: bool, at: &[(i8, i8)],
+        ) {
+            let mut pixels: Vec<(isize, isize)> = GENERIC_TEMPLATES[usize::from(template)]
+                .iter()
+                .chain(at)
+                .map(|&(x, y)| (isize::from(x), isize::from(y)))
+                .collect();
+            pixels.sort_by_key(|&(x, y)| (y, x));
+            let mut previous_typical = false;
+            for y in 0..bitmap.height {
+                if typical_prediction {
+                    let row = |y: usize| &bitmap.pixels[y * bitmap.width..(y + 1) * bitmap.width];
+                    let typical = if y == 0 {
+                        row(0).iter().all(|&pixel| pixel == 0)
+                    } else {
+                        row(y) == row(y - 1)
+                    };
+                    let context = TYPICAL_PREDICTION_CONTEXTS[usize::from(template)];
+                    self.encode(contexts, context, u8::from(typical != previous_typical));
+                    previous_typical = typical;
+                    if typical {
+                        continue;
+                    }
+                }
+                for x in 0..bitmap.width {
+                    let context = pixels.iter().fold(0usize, |context, &(dx, dy)| {
+                        context << 1 | usize::from(bitmap.get(x as isize + dx, y as isize + dy))
+                    });
+                    self.encode(contexts, context, bitmap.pixels[y * bitmap.width + x]);
+                }
+            }
+        }
+    }
+
+    fn bitmap(rows: &[&str]) -> Bitmap {
+        Bitmap {
+            width: rows[0].len(),
+            height: rows.len(),
+            pixels: rows
+                .iter()
+                .flat_map(|row| row.bytes().map(|byte| u8::from(byte == b'#')))
+                .collect(),
+        }
+    }
+
+    fn segment(number: u8, kind: u8, referred: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut segment = vec![0, 0, 0, number, kind, (referred.len() as u8) << 5];
+        segment.extend_from_slice(referred);
+        segment.push(1);
+        segment.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        segment.extend_from_slice(data);
+        segment
+    }
+
+    fn region_info(width: u32, height: u32, x: u32, y: u32) -> Vec<u8> {
+        [width, height, x, y]
+            .iter()
+            .flat_map(|value| value.to_be_bytes().to_vec())
+            .chain(Some(0))
+            .collect()
+    }
+
+    const PAGE_INFO: [u8; 19] = [0, 0, 0, 12, 0, 0, 0, 6, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
+    #[test]
+    fn arithmetic_coder_test_sequence() {
+        // Test sequence of the JBIG2 specification, coded with a single context.
+        let data = [
+            0x00, 0x02, 0x00, 0x51, 0x00, 0x00, 0x00, 0xC0, 0x03, 0x52, 0x87, 0x2A, 0xAA, 0xAA, 0xAA, 0xAA, 0x82, 0xC0,
+            0x20, 0x00, 0xFC, 0xD7, 0x9E, 0xF6, 0xBF, 0x7F, 0xED, 0x90, 0x4F, 0x46, 0xA3, 0xBF,
+        ];
+        let encoded = [
+            0x84, 0xC7, 0x3B, 0xFC, 0xE1, 0xA1, 0x43, 0x04, 0x02, 0x20, 0x00, 0x00, 0x41, 0x0D, 0xBB, 0x86, 0xF4, 0x31,
+            0x7F, 0xFF, 0x88, 0xFF, 0x37, 0x47, 0x1A, 0xDB, 0x6A, 0xDF, 0xFF, 0xAC,
+        ];
+        let mut encoder = ArithmeticEncoder::new();
+        let mut contexts = [0u8];
+        for byte in &data {
+            for shift in (0..8).rev() {
+                encoder.encode(&mut contexts, 0, byte >> shift & 1);
+            }
+        }
+        assert_eq!(encoder.finish(), encoded);
+
+        let mut decoder = ArithmeticDecoder::new(&encoded);
+        let mut contexts = [0u8];
+        let decoded: Vec<u8> = (0..data.len())
+            .map(|_| (0..8).fold(0, |byte, _| byte << 1 | decoder.decode(&mut contexts, 0)))
+            .collect();
+        assert_eq!(decoded, data);
+    }
+
+    #[test]
+    fn decode_generic_region_variants() {
+        let image = bitmap(&["........", "##..##..", "##..##..", "...##...", "...##...", "........"]);
+        for template in 0..4 {
+            let at = [(3, -1), (-3, -1), (2, -2), (-2, -2)];
+            let at = if template == 0 { &at[..] } else { &at[..1] };
+            let mut encoder = ArithmeticEncoder::new();
+            encoder.encode_generic_region(&mut vec![0u8; 1 << 16], &image, template, true, at);
+            let data = encoder.finish();
+            let mut decoder = ArithmeticDecoder::new(&data);
+            let mut contexts = vec![0u8; 1 << 16];
+            assert_eq!(
+                decode_generic_region(&mut decoder, &mut contexts, 8, 6, template, true, at).unwrap(),
+                image
+            );
+        }
+
+        let params = CcittParams {
+            k: -1,
+            columns: 8,
+            rows: Some(6),
+            black_is_1: true,
+            encoded_byte_align: false,
+        };
+        let packed: Vec<u8> = image
+            .pixels
+            .chunks(8)
+            .map(|row| row.iter().fold(0, |byte, &pixel| byte << 1 | pixel))
+            .collect();
+        let data = ccitt::encode(&packed, &params).unwrap();
+        assert_eq!(decode_mmr_region(&data, 8, 6).unwrap(), image);
+    }
+
+    #[test]
+    fn decode_generic_and_text_regions() {
+        let letter = bitmap(&[".##.", "#..#", "####", "#..#"]);
+        let bar = bitmap(&["###"]);
+
+        // Page with a generic region in its top left corner.
+        let mut encoder = ArithmeticEncoder::new();
+        let mut contexts = vec![0u8; 1 << 16];
+        let adaptive_pixels = [(3, -1), (-3, -1), (2, -2), (-2, -2)];
+        encoder.encode_generic_region(&mut contexts, &letter, 0, false, &adaptive_pixels);
+        let mut generic = region_info(4, 4, 1, 1);
+        generic.push(0);
+        generic.extend(adaptive_pixels.iter().flat_map(|&(x, y)| vec![x as u8, y as u8]));
+        generic.extend(encoder.finish());
+
+        // Symbol dictionary exporting both symbols, in the globals, and a text region placing them with their
+        // bottom left corners on the last row of the page.
+        let mut encoder = ArithmeticEncoder::new();
+        let (mut height, mut width, mut export) = (vec![0; 512], vec![0; 512], vec![0; 512]);
+        let mut contexts = vec![0u8; 1 << 16];
+        encoder.encode_integer(&mut height, Some(1));
+        encoder.encode_integer(&mut width, Some(3));
+        encoder.encode_generic_region(&mut contexts, &bar, 2, false, &[(2, -1)]);
+        encoder.encode_integer(&mut width, None);
+        encoder.encode_integer(&mut height, Some(3));
+        encoder.encode_integer(&mut width, Some(4));
+        encoder.encode_generic_region(&mut contexts, &letter, 2, false, &[(2, -1)]);
+        encoder.encode_integer(&mut width, None);
+        encoder.encode_integer(&mut export, Some(0));
+        encoder.encode_integer(&mut export, Some(2));
+        let mut dictionary = vec![0x08, 0x00, 2, 0xFF, 0, 0, 0, 2, 0, 0, 0, 2];
+        dictionary.extend(encoder.finish());
+        let globals = segment(1, 0, &[], &dictionary);
+
+        let mut encoder = ArithmeticEncoder::new();
+        let (mut strip, mut first, mut separation) = (vec![0; 512], vec![0; 512], vec![0; 512]);
+        let mut ids = vec![0u8; 4];
+        encoder.encode_integer(&mut strip, Some(0));
+        encoder.encode_integer(&mut strip, Some(5));
+        encoder.encode_integer(&mut first, Some(6));
+        for &(id, gap) in &[(1, Some(1)), (0, None)] {
+            encoder.encode(&mut ids, 1, id);
+            encoder.encode_integer(&mut separation, gap);
+        }
+        let mut text = region_info(12, 6, 0, 0);
+        // Bottom left reference corner and exclusive or combination.
+        text.extend_from_slice(&[0x01, 0x00, 0, 0, 0, 2]);
+        text.extend(encoder.finish());
+
+        let mut data = segment(2, 48, &[], &PAGE_INFO);
+        data.extend(segment(3, 38, &[], &generic));
+        data.extend(segment(4, 6, &[1], &text));
+        data.extend(segment(5, 49, &[], &[]));
+        let decoded = decode(&data, Some(&globals)).unwrap();
+        let rows: Vec<String> = decoded
+            .chunks(2)
+            .map(|row| {
+                (0..12)
+                    .map(|x| if row[x / 8] >> (7 - x % 8) & 1 == 0 { '#' } else { '.' })
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                "............",
+                "..##........",
+                ".#..#..##...",
+                ".####.#..#..",
+                ".#..#.####..",
+                "......#..###",
+            ]
+        );
+        assert!(decode(&data, None).is_err());
+    }
+
+    #[test]
+    fn reject_hostile_sizes() {
+        // Symbol dictionary announcing u32::MAX new symbols.
+        let mut dictionary = vec![0x08, 0x00, 2, 0xFF, 0, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF];
+        dictionary.extend_from_slice(&[0x12, 0x34, 0x56, 0x78]);
+        let mut data = segment(1, 48, &[], &PAGE_INFO);
+        data.extend(segment(2, 0, &[], &dictionary));
+        assert!(decode(&data, None).is_err());
+
+        // Page and region of 0xFFFFFFF0 by 0xFFFFFFF0 pixels.
+        let mut page_info = PAGE_INFO;
+        page_info[..8].copy_from_slice(&[0xFF, 0xFF, 0xFF, 0xF0, 0xFF, 0xFF, 0xFF, 0xF0]);
+        assert!(decode(&segment(1, 48, &[], &page_info), None).is_err());
+        let mut generic = region_info(0xFFFF_FFF0, 0xFFFF_FFF0, 0, 0);
+        generic.extend_from_slice(&[0x01, 0x00]);
+        let mut data = segment(1, 48, &[], &PAGE_INFO);
+        data.extend(segment(2, 38, &[], &generic));
+        assert!(decode(&data, None).is_err());
+
+        // Segment referring to 0x1FFFFFFF others with only a few bytes left.
+        let mut data = vec![0, 0, 0, 1, 0, 0xFF, 0xFF, 0xFF, 0xFF];
+        data.extend_from_slice(&[0; 8]);
+        assert!(decode(&data, None).is_err());
+    }
+}
@@ -0,0 +1,209 @@
+//! JPEG 2000 data as used by the JPXDecode filter, either a JP2 file or a bare codestream.
+//!
+//! lopdf reads the headers of the data, which give its size, bit depth and color space, but decoding the pixels
+//! needs a decoder to be registered with `set_decoder`, such as one wrapping the OpenJPEG library.
+
+use crate::color::ColorSpace;
+use crate::{Error, Result};
+use std::sync::RwLock;
+
+/// Information from the headers of JPEG 2000 data.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JpxInfo {
+    pub width: u32,
+    pub height: u32,
+    pub components: u16,
+    pub bits_per_component: u8,
+    /// Color space given by the JP2 header, if any. Bare codestreams don't have one.
+    pub color_space: Option<ColorSpace>,
+}
+
+/// Pixels decoded from JPEG 2000 data.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JpxImage {
+    pub width: u32,
+    pub height: u32,
+    pub components: usize,
+    /// Components of 8 bits, interleaved row by row from the top, after applying palettes and converting YCC
+    /// data to RGB.
+    pub pixels: Vec<u8>,
+}
+
+/// Decoder of JPEG 2000 data.
+pub trait JpxDecoder: Send + Sync {
+    fn decode(&self, data: &[u8]) -> Result<JpxImage>;
+}
+
+impl<F> JpxDecoder for F
+where
+    F: Fn(&[u8]) -> Result<JpxImage> + Send + Sync,
+{
+    fn decode(&self, data: &[u8]) -> Result<JpxImage> {
+        self(data)
+    }
+}
+
+static DECODER: RwLock<Option<Box<dyn JpxDecoder>>> = RwLock::new(None);
+
+/// Register the decoder used for the pixels of JPXDecode images, replacing the previous one.
+pub fn set_decoder<D: JpxDecoder + 'static>(decoder: D) {
+    *DECODER.write().unwrap_or_else(|error| error.into_inner()) = Some(Box::new(decoder));
+}
+
+/// Remove the registered decoder.
+pub fn clear_decoder() {
+    *DECODER.write().unwrap_or_else(|error| error.into_inner()) = None;
+}
+
+/// Decode JPEG 2000 data with the registered decoder.
+pub fn decode(data: &[u8]) -> Result<JpxImage> {
+    let decoder = DECODER.read().unwrap_or_else(|error| error.into_inner());
+    match decoder.as_ref() {
+        Some(decoder) => decoder.decode(data),
+        None => Err(Error::Syntax("no JPEG 2000 decoder is registered".to_string())),
+    }
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from(bytes[0]) << 8 | u16::from(bytes[1]))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(bytes.iter().fold(0, |value, &byte| value << 8 | u32::from(byte)))
+}
+
+/// Iterate over the boxes of a JP2 file or superbox, as their type and contents.
+fn boxes(mut data: &[u8]) -> impl Iterator<Item = (&[u8], &[u8])> {
+    std::iter::from_fn(move || {
+        let length = u32_at(data, 0)? as usize;
+        let kind = data.get(4..8)?;
+        let (header, length) = match length {
+            0 => (8, data.len()),
+            1 => {
+                let high = u32_at(data, 8)? as usize;
+                let low = u32_at(data, 12)? as usize;
+                (16, high.checked_shl(32)? | low)
+            }
+            length => (8, length),
+        };
+        let contents = data.get(header..length)?;
+        data = &data[length..];
+        Some((kind, contents))
+    })
+}
+
+/// Read the size and bit depth of a codestream from its SIZ marker segment.
+fn codestream_info(data: &[u8]) -> Option<JpxInfo> {
+    if !data.starts_with(&[0xFF, 0x4F, 0xFF, 0x51]) {
+        return None;
+    }
+    let siz = &data[6..];
+    let width = u32_at(siz, 2)?.checked_sub(u32_at(siz, 10)?)?;
+    let height = u32_at(siz, 6)?.checked_sub(u32_at(siz, 14)?)?;
+    Some(JpxInfo {
+        width,
+        height,
+        components: u16_at(siz, 34)?,
+        bits_per_component: (siz.get(36)? & 0x7F) + 1,
+        color_space: None,
+    })
+}
+
+/// Read the headers of JPEG 2000 data.
+pub fn info(data: &[u8]) -> Result<JpxInfo> {
+    let invalid = || Error::Syntax("invalid JPEG 2000 data".to_string());
+    if data.starts_with(&[0xFF, 0x4F]) {
+        return codestream_info(data).ok_or_else(invalid);
+    }
+
+    let mut info = None;
+    let mut color_space = None;
+    let mut palette_components = None;
+    for (kind, contents) in boxes(data) {
+        match kind {
+            b"jp2h" => {
+                for (kind, contents) in boxes(contents) {
+                    match kind {
+                        b"ihdr" => {
+                            info = Some(JpxInfo {
+                                height: u32_at(contents, 0).ok_or_else(invalid)?,
+                                width: u32_at(contents, 4).ok_or_else(invalid)?,
+                                components: u16_at(contents, 8).ok_or_else(invalid)?,
+                                bits_per_component: (contents.get(10).ok_or_else(invalid)? & 0x7F) + 1,
+                                color_space: None,
+                            })
+                        }
+                        // Only the first color specification is used.
+                        b"colr" if color_space.is_none() => color_space = Some(contents),
+                        b"pclr" => palette_components = contents.get(2).map(|&count| u16::from(count)),
+                        _ => {}
+                    }
+                }
+            }
+            b"jp2c" if info.is_none() => info = codestream_info(contents),
+            _ => {}
+        }
+    }
+
+    let mut info = info.ok_or_else(invalid)?;
+    // Palettes map a single component to several ones.
+    let components = palette_components.unwrap_or(info.components);
+    info.color_space = color_space.and_then(|color| match color.first()? {
+        1 => match u32_at(color, 3)? {
+            12 => Some(ColorSpace::DeviceCMYK),
+            16 | 18 => Some(ColorSpace::DeviceRGB),
+            17 => Some(ColorSpace::DeviceGray),
+            _ => None,
+        },
+        // ICC profiles, whose components are converted with the device color space of the same number.
+        2 | 3 => {
+            let alternate = match components {
+                1 => ColorSpace::DeviceGray,
+                4 => ColorSpace::DeviceCMYK,
+                _ => ColorSpace::DeviceRGB,
+            };
+            Some(ColorSpace::ICCBased {
+                components: usize::from(components),
+                alternate: Box::new(alternate),
+            })
+        }
+        _ => None,
+    });
+    Ok(info)
+}
+
+#[test]
+fn read_jpx_info() {
+    let mut codestream = vec![0xFF, 0x4F, 0xFF, 0x51, 0x00, 0x2F, 0x00, 0x00];
+    for value in &[40u32, 30, 0, 0, 40, 30, 0, 0] {
+        codestream.extend_from_slice(&value.to_be_bytes());
+    }
+    codestream.extend_from_slice(&[0x00, 0x03, 0x07, 0x01, 0x01, 0x07, 0x01, 0x01, 0x07, 0x01, 0x01]);
+    let info = self::info(&codestream).unwrap();
+    assert_eq!(
+        (info.width, info.height, info.components, info.bits_per_component),
+        (40, 30, 3, 8)
+    );
+    assert_eq!(info.color_space, None);
+
+    let jp2_box = |kind: &[u8], contents: &[u8]| {
+        let mut jp2_box = (contents.len() as u32 + 8).to_be_bytes().to_vec();
+        jp2_box.extend_from_slice(kind);
+        jp2_box.extend_from_slice(contents);
+        jp2_box
+    };
+    let ihdr = [0, 0, 0, 30, 0, 0, 0, 40, 0, 1, 7, 7, 0, 0];
+    let header = [jp2_box(b"ihdr", &ihdr), jp2_box(b"colr", &[1, 0, 0, 0, 0, 0, 17])].concat();
+    let jp2 = [
+        jp2_box(b"jP  ", &[0x0D, 0x0A, 0x87, 0x0A]),
+        jp2_box(b"jp2h", &header),
+        jp2_box(b"jp2c", &codestream),
+    ]
+    .concat();
+    let info = self::info(&jp2).unwrap();
+    assert_eq!((info.width, info.height, info.components), (40, 30, 1));
+    assert_eq!(info.color_space, Some(ColorSpace::DeviceGray));
+    assert!(self::info(b"GIF89a").is_err());
+}
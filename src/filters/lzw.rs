@@ -0,0 +1,174 @@
+//! LZW compression as used by the LZWDecode filter, with codes of 9 to 12 bits written most significant bit first.
+//!
+//! With early change, which is the default in PDF and the behavior of TIFF encoders, the code length grows one
+//! code early.
+
+use log::warn;
+use std::collections::HashMap;
+
+const CLEAR_TABLE: u16 = 256;
+const END_OF_DATA: u16 = 257;
+const FIRST_CODE: u16 = 258;
+const MAX_CODES: u16 = 4096;
+
+fn code_length(table_size: u16, early_change: bool) -> u32 {
+    match table_size + u16::from(early_change) {
+        0..=511 => 9,
+        512..=1023 => 10,
+        1024..=2047 => 11,
+        _ => 12,
+    }
+}
+
+/// Decode LZW data, stopping at the end-of-data code or at the first invalid code.
+pub fn decode(input: &[u8], early_change: bool) -> Vec<u8> {
+    let mut output = Vec::with_capacity(input.len() * 2);
+    // Entries are stored as their prefix code, last byte, first byte and length.
+    let mut prefixes = vec![0u16; MAX_CODES as usize];
+    let mut suffixes: Vec<u8> = (0..=255).chain(vec![0; MAX_CODES as usize - 256]).collect();
+    let mut firsts = suffixes.clone();
+    let mut lengths = vec![1u16; MAX_CODES as usize];
+    let mut table_size = FIRST_CODE;
+    let mut previous: Option<u16> = None;
+
+    let mut buffer = 0u32;
+    let mut buffered_bits = 0;
+    let mut bytes = input.iter();
+    loop {
+        let length = code_length(table_size, early_change);
+        while buffered_bits < length {
+            match bytes.next() {
+                Some(&byte) => {
+                    buffer = buffer << 8 | u32::from(byte);
+                    buffered_bits += 8;
+                }
+                None => return output,
+            }
+        }
+        buffered_bits -= length;
+        let code = (buffer >> buffered_bits) as u16 & ((1 << length) - 1);
+        buffer &= (1 << buffered_bits) - 1;
+
+        match code {
+            CLEAR_TABLE => {
+                table_size = FIRST_CODE;
+                previous = None;
+                continue;
+            }
+            END_OF_DATA => break,
+            _ => {}
+        }
+        let previous_code = match previous {
+            Some(previous_code) => previous_code,
+            None if code < CLEAR_TABLE => {
+                output.push(code as u8);
+                previous = Some(code);
+                continue;
+            }
+            None => {
+                warn!("LZW code {} without previous code", code);
+                break;
+            }
+        };
+        if code > table_size || (code == table_size && table_size == MAX_CODES) {
+            warn!("invalid LZW code {}", code);
+            break;
+        }
+        // A code which isn't in the table yet is the previous entry followed by its own first byte.
+        let first = if code < table_size {
+            firsts[code as usize]
+        } else {
+            firsts[previous_code as usize]
+        };
+        if table_size < MAX_CODES {
+            let index = table_size as usize;
+            prefixes[index] = previous_code;
+            suffixes[index] = first;
+            firsts[index] = firsts[previous_code as usize];
+            lengths[index] = lengths[previous_code as usize] + 1;
+            table_size += 1;
+        }
+
+        let start = output.len();
+        output.resize(start + lengths[code as usize] as usize, 0);
+        let mut entry = code;
+        for position in (start..output.len()).rev() {
+            output[position] = suffixes[entry as usize];
+            entry = prefixes[entry as usize];
+        }
+        previous = Some(code);
+    }
+    output
+}
+
+/// Encode data with LZW, starting with a clear-table code and ending with an end-of-data code.
+pub fn encode(input: &[u8], early_change: bool) -> Vec<u8> {
+    let mut output = Vec::with_capacity(input.len() / 2);
+    let mut buffer = 0u32;
+    let mut buffered_bits = 0;
+    let mut write = |code: u16, length: u32| {
+        buffer = buffer << length | u32::from(code);
+        buffered_bits += length;
+        while buffered_bits >= 8 {
+            buffered_bits -= 8;
+            output.push((buffer >> buffered_bits) as u8);
+        }
+        buffer &= (1 << buffered_bits) - 1;
+    };
+
+    let mut table = HashMap::new();
+    let mut table_size = FIRST_CODE;
+    // The decoder adds an entry for a code when it reads the next one, so its table is one entry smaller.
+    write(CLEAR_TABLE, code_length(table_size - 1, early_change));
+    let mut current: Option<u16> = None;
+    for &byte in input {
+        let prefix = match current {
+            Some(prefix) => prefix,
+            None => {
+                current = Some(u16::from(byte));
+                continue;
+            }
+        };
+        if let Some(&code) = table.get(&(prefix, byte)) {
+            current = Some(code);
+            continue;
+        }
+        write(prefix, code_length(table_size - 1, early_change));
+        table.insert((prefix, byte), table_size);
+        table_size += 1;
+        if table_size >= MAX_CODES - 2 {
+            write(CLEAR_TABLE, code_length(table_size - 1, early_change));
+            table.clear();
+            table_size = FIRST_CODE;
+        }
+        current = Some(u16::from(byte));
+    }
+    if let Some(code) = current {
+        write(code, code_length(table_size - 1, early_change));
+    }
+    write(END_OF_DATA, code_length(table_size, early_change));
+    write(0, 7);
+    output
+}
+
+#[test]
+fn encode_and_decode_lzw() {
+    // Example of the PDF specification, with early change.
+    let encoded = [0x80, 0x0B, 0x60, 0x50, 0x22, 0x0C, 0x0C, 0x85, 0x01];
+    let text = b"-----A---B";
+    assert_eq!(decode(&encoded, true), text);
+    assert_eq!(encode(text, true), encoded);
+
+    // Long enough for the table to be cleared.
+    let data: Vec<u8> = (0..8000)
+        .map(|n| format!("{} ", n * 7))
+        .collect::<String>()
+        .into_bytes();
+    for &early_change in &[true, false] {
+        let encoded = encode(&data, early_change);
+        assert!(encoded.len() < data.len());
+        assert_eq!(decode(&encoded, early_change), data);
+    }
+    assert_eq!(decode(&encode(b"", true), true), b"");
+    assert_ne!(decode(&encode(&data, false), true), data);
+}
@@ -1 +1,10 @@
+pub mod ascii85;
+pub mod ascii_hex;
+pub mod ccitt;
+pub mod dct;
+pub mod jbig2;
+pub mod jpx;
+pub mod lzw;
 pub mod png;
+pub mod run_length;
+pub mod tiff;
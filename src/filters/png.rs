@@ -1,5 +1,5 @@
 use std::convert::{TryFrom, TryInto};
-use std::io::{Error, ErrorKind, Read, Result, Write};
+use std::io::{Error, ErrorKind, Result, Write};
 use std::mem;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -84,27 +84,23 @@ pub fn decode_row(filter: FilterType, bpp: usize, previous: &[u8], current: &mut
     }
 }
 
-pub fn decode_frame(content: &[u8], bytes_per_pixel: usize, pixels_per_row: usize) -> Result<Vec<u8>> {
-    let bytes_per_row = bytes_per_pixel * pixels_per_row;
+/// Decode rows of data with a PNG filter type before each row. A partial last row is decoded as far as it goes.
+pub fn decode_frame(content: &[u8], bytes_per_pixel: usize, bytes_per_row: usize) -> Result<Vec<u8>> {
+    // No row is longer than the content, whatever the decode parameters say.
+    let bytes_per_row = bytes_per_row.min(content.len());
     let mut previous = vec![0_u8; bytes_per_row];
     let mut current = vec![0_u8; bytes_per_row];
-    let mut decoded = Vec::new();
-    let mut pos = 0;
-    while pos < content.len() {
-        if let Ok(filter) = content[pos].try_into() {
-            pos += 1;
-            (&content[pos..]).read_exact(current.as_mut_slice())?;
-            pos += bytes_per_row;
-
-            decode_row(filter, bytes_per_pixel, previous.as_slice(), current.as_mut_slice());
-            decoded.write_all(current.as_slice())?;
-            mem::swap(&mut previous, &mut current);
-        } else {
-            return Err(Error::new(
-                ErrorKind::InvalidData,
-                format!("invalid PNG filter type ({})", content[pos]),
-            ));
-        }
+    let mut decoded = Vec::with_capacity(content.len());
+    for row in content.chunks(bytes_per_row + 1) {
+        let filter = row[0]
+            .try_into()
+            .map_err(|_| Error::new(ErrorKind::InvalidData, format!("invalid PNG filter type ({})", row[0])))?;
+        let length = row.len() - 1;
+        current[..length].copy_from_slice(&row[1..]);
+
+        decode_row(filter, bytes_per_pixel, &previous[..length], &mut current[..length]);
+        decoded.write_all(&current[..length])?;
+        mem::swap(&mut previous, &mut current);
     }
     Ok(decoded)
 }
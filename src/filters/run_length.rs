@@ -0,0 +1,93 @@
+//! Run-length compression as used by the RunLengthDecode filter.
+//!
+//! A length byte from 0 to 127 is followed by that many bytes plus one, which are copied, and a length byte
+//! from 129 to 255 is followed by a single byte repeated 257 minus the length times. The length 128 marks the
+//! end of the data.
+
+use log::warn;
+
+const END_OF_DATA: u8 = 128;
+const MAX_RUN: usize = 128;
+
+/// Decode run-length data, stopping at the end-of-data marker or at the end of the input.
+pub fn decode(input: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(input.len() * 2);
+    let mut position = 0;
+    while let Some(&length) = input.get(position) {
+        position += 1;
+        match length {
+            0..=127 => {
+                let end = position + usize::from(length) + 1;
+                if end > input.len() {
+                    warn!("run-length data ends in a literal run");
+                    output.extend_from_slice(&input[position..]);
+                    break;
+                }
+                output.extend_from_slice(&input[position..end]);
+                position = end;
+            }
+            END_OF_DATA => break,
+            _ => match input.get(position) {
+                Some(&byte) => {
+                    output.resize(output.len() + 257 - usize::from(length), byte);
+                    position += 1;
+                }
+                None => {
+                    warn!("run-length data ends in a repeated run");
+                    break;
+                }
+            },
+        }
+    }
+    output
+}
+
+/// Encode data with runs of at most 128 bytes, ending with an end-of-data marker.
+pub fn encode(input: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(input.len() + input.len() / MAX_RUN + 2);
+    let mut literal_start = 0;
+    let mut position = 0;
+    while position < input.len() {
+        let byte = input[position];
+        let run = input[position..]
+            .iter()
+            .take(MAX_RUN)
+            .take_while(|&&b| b == byte)
+            .count();
+        // Repeating two bytes takes as much room as copying them, but doesn't break a literal run.
+        if run > 2 || (run == 2 && literal_start == position) {
+            write_literal(&mut output, &input[literal_start..position]);
+            output.push((257 - run) as u8);
+            output.push(byte);
+            position += run;
+            literal_start = position;
+        } else {
+            position += 1;
+        }
+    }
+    write_literal(&mut output, &input[literal_start..]);
+    output.push(END_OF_DATA);
+    output
+}
+
+fn write_literal(output: &mut Vec<u8>, bytes: &[u8]) {
+    for chunk in bytes.chunks(MAX_RUN) {
+        output.push((chunk.len() - 1) as u8);
+        output.extend_from_slice(chunk);
+    }
+}
+
+#[test]
+fn encode_and_decode_run_length() {
+    assert_eq!(decode(&[2, b'a', b'b', b'c', 253, b'x', 128, 0, b'z']), b"abcxxxx");
+    assert_eq!(encode(b"abcxxxx"), [2, b'a', b'b', b'c', 253, b'x', 128]);
+    assert_eq!(encode(b""), [128]);
+    assert_eq!(decode(&[1, b'a']), b"a");
+
+    let mut data = vec![0u8; 300];
+    data.extend((0..=255).chain(0..=255));
+    data.extend(b"aabbcc".iter().cycle().take(200));
+    let encoded = encode(&data);
+    assert!(encoded.len() < data.len());
+    assert_eq!(decode(&encoded), data);
+}
@@ -0,0 +1,68 @@
+//! TIFF predictor 2, which stores each component of a row as its difference with the same component of the
+//! previous pixel.
+
+use crate::{Error, Result};
+
+/// Undo the TIFF predictor in place, for rows of `columns` pixels with `colors` components of `bits` bits.
+///
+/// Rows start on byte boundaries, and a partial last row is decoded as far as it goes.
+pub fn decode(data: &mut [u8], colors: usize, bits: usize, columns: usize) -> Result<()> {
+    let bytes_per_row = colors
+        .checked_mul(bits)
+        .and_then(|bits_per_pixel| bits_per_pixel.checked_mul(columns))
+        .ok_or(Error::Type)?
+        .div_ceil(8);
+    if bytes_per_row == 0 {
+        return Ok(());
+    }
+    for row in data.chunks_mut(bytes_per_row) {
+        match bits {
+            8 => {
+                for index in colors..row.len() {
+                    row[index] = row[index].wrapping_add(row[index - colors]);
+                }
+            }
+            16 => {
+                for index in (2 * colors..row.len() - row.len() % 2).step_by(2) {
+                    let previous = u16::from_be_bytes([row[index - 2 * colors], row[index - 2 * colors + 1]]);
+                    let value = u16::from_be_bytes([row[index], row[index + 1]]).wrapping_add(previous);
+                    row[index..index + 2].copy_from_slice(&value.to_be_bytes());
+                }
+            }
+            1 | 2 | 4 => {
+                let mask = (1u8 << bits) - 1;
+                let samples = row.len() * 8 / bits;
+                let get = |row: &[u8], sample: usize| {
+                    let shift = 8 - bits - sample * bits % 8;
+                    row[sample * bits / 8] >> shift & mask
+                };
+                for sample in colors..samples {
+                    let value = get(row, sample).wrapping_add(get(row, sample - colors)) & mask;
+                    let shift = 8 - bits - sample * bits % 8;
+                    let byte = &mut row[sample * bits / 8];
+                    *byte = *byte & !(mask << shift) | value << shift;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn decode_tiff_predictor() {
+    let mut data = vec![10, 20, 1, 2, 1, 2, 5, 5, 0, 1, 0, 1];
+    decode(&mut data, 2, 8, 3).unwrap();
+    assert_eq!(data, [10, 20, 11, 22, 12, 24, 5, 5, 5, 6, 5, 7]);
+
+    let mut data = vec![0x00, 0xFF, 0x00, 0x02];
+    decode(&mut data, 1, 16, 2).unwrap();
+    assert_eq!(data, [0x00, 0xFF, 0x01, 0x01]);
+
+    // Rows of four 2-bit samples, which wrap around.
+    let mut data = vec![0b0101_0101, 0b1101_0101];
+    decode(&mut data, 1, 2, 4).unwrap();
+    assert_eq!(data, [0b0110_1100, 0b1100_0110]);
+
+    assert!(decode(&mut data, usize::MAX, 8, 2).is_err());
+}
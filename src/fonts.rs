@@ -0,0 +1,512 @@
+use crate::{Dictionary, Document, Error, Object, ObjectId, Result, Stream, StringFormat};
+use std::collections::BTreeMap;
+use ttf_parser::{name_id, Face, GlyphId, Tag};
+
+/// TrueType font, or OpenType font with TrueType outlines, embedded as a composite font with the Identity-H
+/// encoding.
+///
+/// Text shown with the font is encoded by `encode`, which records the glyphs it uses, and `embed` then
+/// adds the font to the document, subset to the used glyphs numbered in the order of their first use.
+/// OpenType fonts with CFF outlines can't be subset and are rejected rather than embedded whole.
+/// Characters are mapped to glyphs one to one, without shaping.
+#[derive(Debug, Clone)]
+pub struct EmbeddedFont {
+    id: ObjectId,
+    data: Vec<u8>,
+    units_per_em: f64,
+    /// Glyph of each Unicode code point.
+    cmap: BTreeMap<char, u16>,
+    /// Advance widths by glyph, in font units.
+    advances: Vec<u16>,
+    /// Original glyph and text of each character id used.
+    used: BTreeMap<u16, (u16, String)>,
+    /// Character id of each glyph used.
+    cids: BTreeMap<u16, u16>,
+}
+
+impl EmbeddedFont {
+    /// Parse a font file, reserving the object id of the font in the document.
+    ///
+    /// Fonts without TrueType outlines are errors, including OpenType fonts with CFF outlines.
+    pub fn new(document: &mut Document, data: Vec<u8>) -> Result<EmbeddedFont> {
+        let face = Face::parse(&data, 0).map_err(|error| Error::Syntax(error.to_string()))?;
+        let tables = face.tables();
+        if tables.glyf.is_none() {
+            if tables.cff.is_some() || tables.cff2.is_some() {
+                return Err(Error::Syntax(
+                    "OpenType fonts with CFF outlines can't be subset".to_string(),
+                ));
+            }
+            return Err(Error::Type);
+        }
+        let mut cmap = BTreeMap::new();
+        for subtable in tables.cmap.iter().flat_map(|cmap| cmap.subtables) {
+            if subtable.is_unicode() {
+                subtable.codepoints(|code_point| {
+                    if let (Some(character), Some(glyph)) =
+                        (char::from_u32(code_point), subtable.glyph_index(code_point))
+                    {
+                        cmap.entry(character).or_insert(glyph.0);
+                    }
+                });
+            }
+        }
+        let advances = (0..face.number_of_glyphs())
+            .map(|glyph| face.glyph_hor_advance(GlyphId(glyph)).unwrap_or(0))
+            .collect();
+        let units_per_em = f64::from(face.units_per_em());
+
+        let mut font = EmbeddedFont {
+            id: document.new_object_id(),
+            data,
+            units_per_em,
+            cmap,
+            advances,
+            used: BTreeMap::new(),
+            cids: BTreeMap::new(),
+        };
+        // The character id 0 of subsets is always the .notdef glyph.
+        font.used.insert(0, (0, String::new()));
+        font.cids.insert(0, 0);
+        Ok(font)
+    }
+
+    /// Get the object id of the Type0 font dictionary, to refer to in resource dictionaries.
+    pub fn id(&self) -> ObjectId {
+        self.id
+    }
+
+    /// Encode text as two byte character codes, using the .notdef glyph for characters missing from the font.
+    pub fn encode(&mut self, text: &str) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(text.len() * 2);
+        for character in text.chars() {
+            let glyph = self.cmap.get(&character).copied().unwrap_or(0);
+            let cid = match self.cids.get(&glyph) {
+                Some(&cid) => cid,
+                None => {
+                    let cid = self.cids.len() as u16;
+                    self.cids.insert(glyph, cid);
+                    let text = if glyph == 0 {
+                        String::new()
+                    } else {
+                        character.to_string()
+                    };
+                    self.used.insert(cid, (glyph, text));
+                    cid
+                }
+            };
+            bytes.extend_from_slice(&cid.to_be_bytes());
+        }
+        bytes
+    }
+
+    /// Encode text as a hexadecimal string object, as shown by the Tj operator.
+    pub fn encode_string(&mut self, text: &str) -> Object {
+        Object::String(self.encode(text), StringFormat::Hexadecimal)
+    }
+
+    /// Get the width of text in thousandths of the font size.
+    pub fn width(&self, text: &str) -> f64 {
+        text.chars()
+            .map(|character| self.glyph_width(self.cmap.get(&character).copied().unwrap_or(0)))
+            .sum()
+    }
+
+    fn glyph_width(&self, glyph: u16) -> f64 {
+        let advance = self.advances.get(usize::from(glyph)).copied().unwrap_or(0);
+        f64::from(advance) * 1000.0 / self.units_per_em
+    }
+
+    /// Add the font to the document with the glyphs used so far, returning the id of its Type0 font dictionary.
+    pub fn embed(self, document: &mut Document) -> Result<ObjectId> {
+        let face = Face::parse(&self.data, 0).map_err(|error| Error::Syntax(error.to_string()))?;
+        let scale = |value: f64| (value * 1000.0 / self.units_per_em).round();
+        let name = face
+            .names()
+            .into_iter()
+            .filter(|name| name.name_id == name_id::POST_SCRIPT_NAME)
+            .find_map(|name| name.to_string())
+            .unwrap_or_else(|| "Font".to_owned());
+
+        let glyphs: Vec<u16> = self.used.values().map(|&(glyph, _)| glyph).collect();
+        let data = subset_truetype(&face, &glyphs)?;
+        let mut font_file = Stream::new(dictionary! { "Length1" => data.len() as i64 }, data);
+        // Ignore any compression error.
+        let _ = font_file.compress();
+        let base_font = format!("{}+{}", subset_tag(&glyphs), name);
+
+        let bbox = face.global_bounding_box();
+        let mut flags = 4;
+        if face.is_monospaced() {
+            flags |= 1;
+        }
+        if face.is_italic() {
+            flags |= 64;
+        }
+        let font_file_id = document.add_object(font_file);
+        let descriptor_id = document.add_object(dictionary! {
+            "Type" => "FontDescriptor",
+            "FontName" => Object::Name(base_font.clone().into_bytes()),
+            "Flags" => flags,
+            "FontBBox" => vec![
+                scale(f64::from(bbox.x_min)).into(),
+                scale(f64::from(bbox.y_min)).into(),
+                scale(f64::from(bbox.x_max)).into(),
+                scale(f64::from(bbox.y_max)).into(),
+            ],
+            "ItalicAngle" => f64::from(face.italic_angle()),
+            "Ascent" => scale(f64::from(face.ascender())),
+            "Descent" => scale(f64::from(face.descender())),
+            "CapHeight" => scale(f64::from(face.capital_height().unwrap_or_else(|| face.ascender()))),
+            "StemV" => 80,
+            "FontFile2" => font_file_id,
+        });
+
+        let cid_font = dictionary! {
+            "Type" => "Font",
+            "Subtype" => "CIDFontType2",
+            "BaseFont" => Object::Name(base_font.clone().into_bytes()),
+            "CIDSystemInfo" => dictionary! {
+                "Registry" => Object::string_literal("Adobe"),
+                "Ordering" => Object::string_literal("Identity"),
+                "Supplement" => 0,
+            },
+            "FontDescriptor" => descriptor_id,
+            "W" => self.widths(),
+            "CIDToGIDMap" => "Identity",
+        };
+        let cid_font_id = document.add_object(cid_font);
+        let mut to_unicode = Stream::new(Dictionary::new(), self.to_unicode());
+        let _ = to_unicode.compress();
+        let to_unicode_id = document.add_object(to_unicode);
+
+        document.objects.insert(
+            self.id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Font",
+                "Subtype" => "Type0",
+                "BaseFont" => Object::Name(base_font.into_bytes()),
+                "Encoding" => "Identity-H",
+                "DescendantFonts" => vec![cid_font_id.into()],
+                "ToUnicode" => to_unicode_id,
+            }),
+        );
+        Ok(self.id)
+    }
+
+    /// Get the W array of the used character ids, grouping consecutive ones.
+    fn widths(&self) -> Vec<Object> {
+        let mut widths = Vec::new();
+        let mut group: Option<(u16, Vec<Object>)> = None;
+        for (&cid, &(glyph, _)) in &self.used {
+            let width = self.glyph_width(glyph).round().into();
+            group = match group {
+                Some((first, mut group)) if usize::from(first) + group.len() == usize::from(cid) => {
+                    group.push(width);
+                    Some((first, group))
+                }
+                previous => {
+                    if let Some((first, group)) = previous {
+                        widths.push(i64::from(first).into());
+                        widths.push(group.into());
+                    }
+                    Some((cid, vec![width]))
+                }
+            };
+        }
+        if let Some((first, group)) = group {
+            widths.push(i64::from(first).into());
+            widths.push(group.into());
+        }
+        widths
+    }
+
+    /// Create a ToUnicode CMap mapping the used character ids to their text.
+    fn to_unicode(&self) -> Vec<u8> {
+        let hex = |units: &[u16]| units.iter().map(|unit| format!("{:04X}", unit)).collect::<String>();
+        let mappings: Vec<String> = self
+            .used
+            .iter()
+            .filter(|(_, (_, text))| !text.is_empty())
+            .map(|(&cid, (_, text))| format!("<{:04X}> <{}>", cid, hex(&text.encode_utf16().collect::<Vec<_>>())))
+            .collect();
+        let mut cmap = String::from(
+            "/CIDInit /ProcSet findresource begin\n12 dict begin\nbegincmap\n\
+             /CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n\
+             /CMapName /Adobe-Identity-UCS def\n/CMapType 2 def\n\
+             1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange\n",
+        );
+        // CMap sections are limited to 100 entries.
+        for chunk in mappings.chunks(100) {
+            cmap.push_str(&format!(
+                "{} beginbfchar\n{}\nendbfchar\n",
+                chunk.len(),
+                chunk.join("\n")
+            ));
+        }
+        cmap.push_str("endcmap\nCMapName currentdict /CMap defineresource pop\nend\nend\n");
+        cmap.into_bytes()
+    }
+}
+
+/// Tag of six uppercase letters prefixed to the names of subset fonts, derived from their glyphs.
+fn subset_tag(glyphs: &[u16]) -> String {
+    let mut hash = glyphs.iter().fold(0x811C_9DC5u32, |hash, &glyph| {
+        (hash ^ u32::from(glyph)).wrapping_mul(0x0100_0193)
+    });
+    (0..6)
+        .map(|_| {
+            let letter = (b'A' + (hash % 26) as u8) as char;
+            hash /= 26;
+            letter
+        })
+        .collect()
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16> {
+    data.get(offset..offset + 2)
+        .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+        .ok_or(Error::Type)
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32> {
+    data.get(offset..offset + 4)
+        .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .ok_or(Error::Type)
+}
+
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+/// Create a TrueType font with the given glyphs, in that order, followed by the components of composite glyphs.
+///
+/// Only the tables needed to render the font from a PDF file are kept.
+fn subset_truetype(face: &Face, glyphs: &[u16]) -> Result<Vec<u8>> {
+    const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+    const WE_HAVE_A_SCALE: u16 = 0x0008;
+    const MORE_COMPONENTS: u16 = 0x0020;
+    const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+    const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+
+    let table = |tag: &[u8; 4]| face.raw_face().table(Tag::from_bytes(tag));
+    let required = |tag: &[u8; 4]| table(tag).ok_or(Error::Type);
+    let mut head = required(b"head")?.to_vec();
+    let mut hhea = required(b"hhea")?.to_vec();
+    let mut maxp = required(b"maxp")?.to_vec();
+    let (loca, glyf, hmtx) = (required(b"loca")?, required(b"glyf")?, required(b"hmtx")?);
+    let long_offsets = read_u16(&head, 50)? != 0;
+    let glyph_data = |glyph: u16| -> Result<&[u8]> {
+        let index = usize::from(glyph);
+        let (start, end) = if long_offsets {
+            (
+                read_u32(loca, index * 4)? as usize,
+                read_u32(loca, index * 4 + 4)? as usize,
+            )
+        } else {
+            (
+                usize::from(read_u16(loca, index * 2)?) * 2,
+                usize::from(read_u16(loca, index * 2 + 2)?) * 2,
+            )
+        };
+        glyf.get(start..end.max(start)).ok_or(Error::Type)
+    };
+    // Offsets of the glyph indices of the components of a composite glyph.
+    let components = |data: &[u8]| -> Result<Vec<usize>> {
+        let mut offsets = Vec::new();
+        if data.len() < 10 || read_u16(data, 0)? & 0x8000 == 0 {
+            return Ok(offsets);
+        }
+        let mut offset = 10;
+        loop {
+            let flags = read_u16(data, offset)?;
+            offsets.push(offset + 2);
+            offset += 4 + if flags & ARG_1_AND_2_ARE_WORDS != 0 { 4 } else { 2 };
+            offset += if flags & WE_HAVE_A_SCALE != 0 {
+                2
+            } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+                4
+            } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+                8
+            } else {
+                0
+            };
+            if flags & MORE_COMPONENTS == 0 {
+                return Ok(offsets);
+            }
+        }
+    };
+
+    let mut order = glyphs.to_vec();
+    let mut index = 0;
+    while index < order.len() {
+        let data = glyph_data(order[index])?;
+        for offset in components(data)? {
+            let component = read_u16(data, offset)?;
+            if !order.contains(&component) {
+                order.push(component);
+            }
+        }
+        index += 1;
+    }
+    let new_ids: BTreeMap<u16, u16> = order
+        .iter()
+        .enumerate()
+        .map(|(id, &glyph)| (glyph, id as u16))
+        .collect();
+
+    let metrics_count = usize::from(read_u16(&hhea, 34)?);
+    let mut new_glyf = Vec::new();
+    let mut new_loca = Vec::new();
+    let mut new_hmtx = Vec::new();
+    for &glyph in &order {
+        let mut data = glyph_data(glyph)?.to_vec();
+        for offset in components(&data)? {
+            let component = new_ids[&read_u16(&data, offset)?];
+            data[offset..offset + 2].copy_from_slice(&component.to_be_bytes());
+        }
+        new_loca.extend_from_slice(&(new_glyf.len() as u32).to_be_bytes());
+        new_glyf.extend(data);
+        while new_glyf.len() % 4 != 0 {
+            new_glyf.push(0);
+        }
+        // Glyphs past the long metrics share the last advance width.
+        let index = usize::from(glyph);
+        let (advance, bearing) = if index < metrics_count {
+            (read_u16(hmtx, index * 4)?, read_u16(hmtx, index * 4 + 2)?)
+        } else {
+            let bearing = read_u16(hmtx, metrics_count * 4 + (index - metrics_count) * 2)?;
+            (read_u16(hmtx, (metrics_count - 1) * 4)?, bearing)
+        };
+        new_hmtx.extend_from_slice(&advance.to_be_bytes());
+        new_hmtx.extend_from_slice(&bearing.to_be_bytes());
+    }
+    new_loca.extend_from_slice(&(new_glyf.len() as u32).to_be_bytes());
+
+    let glyph_count = (order.len() as u16).to_be_bytes();
+    head.get_mut(8..12).ok_or(Error::Type)?.copy_from_slice(&[0; 4]);
+    head.get_mut(50..52)
+        .ok_or(Error::Type)?
+        .copy_from_slice(&1u16.to_be_bytes());
+    hhea.get_mut(34..36).ok_or(Error::Type)?.copy_from_slice(&glyph_count);
+    maxp.get_mut(4..6).ok_or(Error::Type)?.copy_from_slice(&glyph_count);
+
+    let mut tables: Vec<(&[u8; 4], Vec<u8>)> = vec![
+        (b"glyf", new_glyf),
+        (b"head", head),
+        (b"hhea", hhea),
+        (b"hmtx", new_hmtx),
+        (b"loca", new_loca),
+        (b"maxp", maxp),
+    ];
+    for tag in &[b"cvt ", b"fpgm", b"prep"] {
+        if let Some(data) = table(tag) {
+            tables.push((tag, data.to_vec()));
+        }
+    }
+    tables.sort_by_key(|&(tag, _)| *tag);
+
+    let count = tables.len() as u16;
+    let selector = 15 - count.leading_zeros() as u16;
+    let search_range = 16u16 << selector;
+    let mut font = Vec::new();
+    font.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+    for value in &[count, search_range, selector, count * 16 - search_range] {
+        font.extend_from_slice(&value.to_be_bytes());
+    }
+    let mut offset = 12 + tables.len() * 16;
+    let mut head_offset = 0;
+    for (tag, data) in &tables {
+        if *tag == b"head" {
+            head_offset = offset;
+        }
+        font.extend_from_slice(*tag);
+        font.extend_from_slice(&checksum(data).to_be_bytes());
+        font.extend_from_slice(&(offset as u32).to_be_bytes());
+        font.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        offset += data.len().div_ceil(4) * 4;
+    }
+    for (_, data) in &tables {
+        font.extend_from_slice(data);
+        font.resize(font.len().div_ceil(4) * 4, 0);
+    }
+    let adjustment = 0xB1B0_AFBAu32.wrapping_sub(checksum(&font));
+    font[head_offset + 8..head_offset + 12].copy_from_slice(&adjustment.to_be_bytes());
+    Ok(font)
+}
+
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+#[test]
+fn embed_subset_font() {
+    use crate::content::{Content, Operation};
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    let data = std::fs::read("assets/DejaVuSans-ASCII.ttf").unwrap();
+    let mut font = EmbeddedFont::new(&mut doc, data.clone()).unwrap();
+    let font_id = font.id();
+    let resources = doc.get_or_create_resources(page_id).unwrap().as_dict_mut().unwrap();
+    resources
+        .get_mut(b"Font")
+        .and_then(Object::as_dict_mut)
+        .unwrap()
+        .set("F2", font_id);
+    let content = Content {
+        operations: vec![
+            Operation::new("BT", vec![]),
+            Operation::new("Tf", vec!["F2".into(), 20.into()]),
+            Operation::new("Td", vec![100.into(), 400.into()]),
+            Operation::new("Tj", vec![font.encode_string("Hello €")]),
+            Operation::new("ET", vec![]),
+        ],
+    };
+    assert_eq!(font.encode("Hello €"), [0, 1, 0, 2, 0, 3, 0, 3, 0, 4, 0, 5, 0, 0]);
+    let width = font.width("Hello");
+    doc.append_page_content(page_id, content.encode().unwrap()).unwrap();
+    assert_eq!(font.embed(&mut doc).unwrap(), font_id);
+
+    let cid_font_id = doc
+        .get_dictionary(font_id)
+        .and_then(|font| font.get(b"DescendantFonts"))
+        .and_then(Object::as_array)
+        .and_then(|fonts| fonts[0].as_reference())
+        .unwrap();
+    let cid_font = doc.get_dictionary(cid_font_id).unwrap();
+    let descriptor = cid_font
+        .get(b"FontDescriptor")
+        .and_then(Object::as_reference)
+        .and_then(|id| doc.get_dictionary(id))
+        .unwrap();
+    assert!(descriptor
+        .get(b"FontName")
+        .and_then(Object::as_name_str)
+        .unwrap()
+        .ends_with("+DejaVuSans"));
+    let font_file = descriptor
+        .get(b"FontFile2")
+        .and_then(Object::as_reference)
+        .and_then(|id| doc.get_object(id))
+        .and_then(Object::as_stream)
+        .unwrap();
+    let subset = font_file.decompressed_content().unwrap();
+    assert!(subset.len() < data.len() / 4);
+    let face = Face::parse(&subset, 0).unwrap();
+    assert_eq!(face.number_of_glyphs(), 6);
+    assert_eq!(
+        face.glyph_hor_advance(GlyphId(1)),
+        Face::parse(&data, 0).unwrap().glyph_hor_advance(GlyphId(41))
+    );
+
+    let fragments = doc.extract_text_fragments(1).unwrap();
+    let fragment = fragments.last().unwrap();
+    assert_eq!(fragment.text, "Hello \u{FFFD}");
+    assert_eq!(
+        fragment.glyphs[5].bbox.llx.round(),
+        (100.0 + width / 1000.0 * 20.0).round()
+    );
+}
@@ -0,0 +1,1538 @@
+use crate::content::{Content, Operation};
+use crate::encodings::{decode_text_string, encode_text_string};
+use crate::{xobject, Dictionary, Document, Error, Matrix, Object, ObjectId, Rectangle, Result, StringFormat};
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+
+/// Interactive form of a document.
+#[derive(Debug, Clone)]
+pub struct Form {
+    /// Id of the interactive form dictionary, if it's an indirect object.
+    pub id: Option<ObjectId>,
+    /// Root fields, which contain their descendant fields as kids.
+    pub fields: Vec<Field>,
+    /// Whether viewers are asked to construct appearances of all fields.
+    pub need_appearances: bool,
+    /// Default appearance for fields which don't specify one.
+    pub default_appearance: Option<String>,
+}
+
+impl Form {
+    /// Iterate over all fields depth-first, parents before their kids.
+    pub fn iter(&self) -> impl Iterator<Item = &Field> {
+        let mut stack: Vec<&Field> = self.fields.iter().rev().collect();
+        std::iter::from_fn(move || {
+            let field = stack.pop()?;
+            stack.extend(field.kids.iter().rev());
+            Some(field)
+        })
+    }
+
+    /// Get a field by its fully qualified name.
+    pub fn get_field(&self, name: &str) -> Option<&Field> {
+        self.iter().find(|field| field.name == name)
+    }
+
+    fn get_field_mut(&mut self, name: &str) -> Result<&mut Field> {
+        find_field_mut(&mut self.fields, name).ok_or(Error::ObjectNotFound)
+    }
+
+    /// Set the value of a text field and regenerate the appearance streams of its widgets.
+    pub fn set_text_value(&mut self, document: &mut Document, name: &str, value: &str) -> Result<()> {
+        let field = self.get_field_mut(name)?;
+        if field.field_type != FieldType::Text {
+            return Err(Error::Type);
+        }
+        field.set_value(
+            document,
+            Object::String(encode_text_string(value), StringFormat::Literal),
+        )?;
+
+        let shown = if field.flags & Field::PASSWORD != 0 {
+            "*".repeat(value.chars().count())
+        } else {
+            value.to_owned()
+        };
+        let lines = if field.flags & Field::MULTILINE != 0 {
+            shown.lines().map(str::to_owned).collect()
+        } else {
+            vec![shown.lines().collect::<Vec<_>>().join(" ")]
+        };
+        for widget in &field.widgets {
+            document.generate_text_appearance(field, widget, &lines, None)?;
+        }
+        Ok(())
+    }
+
+    /// Check or uncheck a check box, generating the appearance streams of its widgets if they have none.
+    pub fn set_checkbox(&mut self, document: &mut Document, name: &str, checked: bool) -> Result<()> {
+        let field = self.get_field_mut(name)?;
+        if field.field_type != FieldType::CheckBox {
+            return Err(Error::Type);
+        }
+
+        let mut value = if checked { b"Yes".to_vec() } else { b"Off".to_vec() };
+        for (index, widget) in field.widgets.iter().enumerate() {
+            let on_state = document.generate_checkbox_appearance(field, widget)?;
+            if checked && index == 0 {
+                value = on_state.clone();
+            }
+            let state = if checked { on_state } else { b"Off".to_vec() };
+            document
+                .get_object_mut(widget.id)
+                .and_then(Object::as_dict_mut)?
+                .set("AS", Object::Name(state));
+        }
+        field.set_value(document, Object::Name(value))
+    }
+
+    /// Select an option of a combo box or list box, or the state of a radio button,
+    /// and regenerate the appearance streams of choice field widgets.
+    pub fn set_choice(&mut self, document: &mut Document, name: &str, value: &str) -> Result<()> {
+        let field = self.get_field_mut(name)?;
+        match field.field_type {
+            FieldType::RadioButton => {
+                let states: Vec<_> = field
+                    .widgets
+                    .iter()
+                    .map(|widget| document.appearance_states(widget.id))
+                    .collect();
+                if !states.iter().flatten().any(|state| state == value.as_bytes()) {
+                    return Err(Error::DictKey);
+                }
+                for (widget, states) in field.widgets.iter().zip(states) {
+                    let state = if states.iter().any(|state| state == value.as_bytes()) {
+                        value.as_bytes()
+                    } else {
+                        b"Off"
+                    };
+                    document
+                        .get_object_mut(widget.id)
+                        .and_then(Object::as_dict_mut)?
+                        .set("AS", Object::Name(state.to_vec()));
+                }
+                field.set_value(document, Object::Name(value.as_bytes().to_vec()))
+            }
+            FieldType::ComboBox | FieldType::ListBox => {
+                let index = field.options.iter().position(|option| option == value);
+                let editable = field.field_type == FieldType::ComboBox && field.flags & Field::EDIT != 0;
+                if index.is_none() && !editable {
+                    return Err(Error::Type);
+                }
+                field.set_value(
+                    document,
+                    Object::String(encode_text_string(value), StringFormat::Literal),
+                )?;
+
+                let field_dict = document.get_object_mut(field.id).and_then(Object::as_dict_mut)?;
+                match index {
+                    Some(index) if field.field_type == FieldType::ListBox => {
+                        field_dict.set("I", vec![Object::Integer(index as i64)])
+                    }
+                    _ => {
+                        field_dict.remove(b"I");
+                    }
+                }
+
+                let (lines, selected) = if field.field_type == FieldType::ListBox {
+                    (field.options.clone(), index)
+                } else {
+                    (vec![value.to_owned()], None)
+                };
+                for widget in &field.widgets {
+                    document.generate_text_appearance(field, widget, &lines, selected)?;
+                }
+                Ok(())
+            }
+            _ => Err(Error::Type),
+        }
+    }
+}
+
+fn find_field_mut<'a>(fields: &'a mut [Field], name: &str) -> Option<&'a mut Field> {
+    for field in fields {
+        if field.name == name {
+            return Some(field);
+        }
+        if let Some(kid) = find_field_mut(&mut field.kids, name) {
+            return Some(kid);
+        }
+    }
+    None
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldType {
+    PushButton,
+    CheckBox,
+    RadioButton,
+    Text,
+    ComboBox,
+    ListBox,
+    Signature,
+    /// Field without a field type, which only groups its kids.
+    NonTerminal,
+}
+
+/// Form field, with inherited attributes resolved.
+#[derive(Debug, Clone)]
+pub struct Field {
+    pub id: ObjectId,
+    /// Fully qualified name, the partial names of the field and its ancestors joined by periods.
+    pub name: String,
+    pub field_type: FieldType,
+    pub flags: u32,
+    pub value: Option<Object>,
+    pub default_value: Option<Object>,
+    pub default_appearance: Option<String>,
+    /// Export values of the options of a choice field.
+    pub options: Vec<String>,
+    pub kids: Vec<Field>,
+    pub widgets: Vec<Widget>,
+}
+
+impl Field {
+    pub const READ_ONLY: u32 = 1;
+    pub const REQUIRED: u32 = 1 << 1;
+    pub const NO_EXPORT: u32 = 1 << 2;
+    pub const MULTILINE: u32 = 1 << 12;
+    pub const PASSWORD: u32 = 1 << 13;
+    pub const NO_TOGGLE_TO_OFF: u32 = 1 << 14;
+    pub const RADIO: u32 = 1 << 15;
+    pub const PUSH_BUTTON: u32 = 1 << 16;
+    pub const COMBO: u32 = 1 << 17;
+    pub const EDIT: u32 = 1 << 18;
+    pub const MULTI_SELECT: u32 = 1 << 21;
+    pub const COMB: u32 = 1 << 24;
+
+    /// Get the value as text, for text and choice fields or the state name of buttons.
+    pub fn text_value(&self) -> Option<String> {
+        match self.value {
+            Some(Object::String(ref text, _)) => Some(decode_text_string(text)),
+            Some(Object::Name(ref name)) => Some(String::from_utf8_lossy(name).into_owned()),
+            _ => None,
+        }
+    }
+
+    fn set_value(&mut self, document: &mut Document, value: Object) -> Result<()> {
+        document
+            .get_object_mut(self.id)
+            .and_then(Object::as_dict_mut)?
+            .set("V", value.clone());
+        self.value = Some(value);
+        Ok(())
+    }
+}
+
+/// Widget annotation showing a field on a page.
+#[derive(Debug, Clone)]
+pub struct Widget {
+    pub id: ObjectId,
+    pub rect: Option<Rectangle>,
+    pub page: Option<ObjectId>,
+}
+
+/// New root field of the interactive form with its widget annotations, added by `Document::add_field`.
+#[derive(Debug, Clone)]
+pub struct FieldBuilder {
+    /// Partial name, which is also the fully qualified name of a root field.
+    pub name: String,
+    pub field_type: FieldType,
+    pub flags: u32,
+    /// Pages and rectangles of the widget annotations, one per button of radio groups.
+    pub widgets: Vec<(ObjectId, Rectangle)>,
+    /// Text of text fields, selected option of choice fields, export value of the selected button of radio groups,
+    /// `Yes` for checked check boxes, or the caption of push buttons.
+    pub value: Option<String>,
+    /// Options of choice fields, or export values of the buttons of radio groups.
+    pub options: Vec<String>,
+    /// Default appearance string, such as `/Helv 0 Tf 0 g` for auto sized black Helvetica.
+    pub default_appearance: Option<String>,
+    /// Text shown as tool tip, and used by screen readers.
+    pub tooltip: Option<String>,
+    /// Maximum number of characters of text fields.
+    pub max_length: Option<u32>,
+}
+
+impl FieldBuilder {
+    pub fn new(name: &str, field_type: FieldType) -> FieldBuilder {
+        FieldBuilder {
+            name: name.to_owned(),
+            field_type,
+            flags: 0,
+            widgets: Vec::new(),
+            value: None,
+            options: Vec::new(),
+            default_appearance: None,
+            tooltip: None,
+            max_length: None,
+        }
+    }
+
+    pub fn text(name: &str, page_id: ObjectId, rect: Rectangle) -> FieldBuilder {
+        FieldBuilder::new(name, FieldType::Text).with_widget(page_id, rect)
+    }
+
+    pub fn checkbox(name: &str, page_id: ObjectId, rect: Rectangle) -> FieldBuilder {
+        FieldBuilder::new(name, FieldType::CheckBox).with_widget(page_id, rect)
+    }
+
+    /// Create a group of radio buttons, given by their pages, rectangles and export values, of which at most one is
+    /// selected.
+    pub fn radio_group(name: &str, buttons: &[(ObjectId, Rectangle, &str)]) -> FieldBuilder {
+        let mut field = FieldBuilder::new(name, FieldType::RadioButton);
+        field.flags = Field::RADIO | Field::NO_TOGGLE_TO_OFF;
+        for &(page_id, rect, value) in buttons {
+            field.widgets.push((page_id, rect));
+            field.options.push(value.to_owned());
+        }
+        field
+    }
+
+    pub fn combo_box(name: &str, page_id: ObjectId, rect: Rectangle, options: &[&str]) -> FieldBuilder {
+        let mut field = FieldBuilder::new(name, FieldType::ComboBox).with_widget(page_id, rect);
+        field.flags = Field::COMBO;
+        field.options = options.iter().map(|&option| option.to_owned()).collect();
+        field
+    }
+
+    pub fn list_box(name: &str, page_id: ObjectId, rect: Rectangle, options: &[&str]) -> FieldBuilder {
+        let mut field = FieldBuilder::new(name, FieldType::ListBox).with_widget(page_id, rect);
+        field.options = options.iter().map(|&option| option.to_owned()).collect();
+        field
+    }
+
+    pub fn push_button(name: &str, page_id: ObjectId, rect: Rectangle, caption: &str) -> FieldBuilder {
+        let mut field = FieldBuilder::new(name, FieldType::PushButton).with_widget(page_id, rect);
+        field.flags = Field::PUSH_BUTTON;
+        field.value = Some(caption.to_owned());
+        field
+    }
+
+    pub fn with_widget(mut self, page_id: ObjectId, rect: Rectangle) -> FieldBuilder {
+        self.widgets.push((page_id, rect));
+        self
+    }
+
+    pub fn with_value(mut self, value: &str) -> FieldBuilder {
+        self.value = Some(value.to_owned());
+        self
+    }
+
+    /// Add field flags, such as `Field::MULTILINE` or `Field::REQUIRED`.
+    pub fn with_flags(mut self, flags: u32) -> FieldBuilder {
+        self.flags |= flags;
+        self
+    }
+
+    pub fn with_default_appearance(mut self, default_appearance: &str) -> FieldBuilder {
+        self.default_appearance = Some(default_appearance.to_owned());
+        self
+    }
+
+    pub fn with_tooltip(mut self, tooltip: &str) -> FieldBuilder {
+        self.tooltip = Some(tooltip.to_owned());
+        self
+    }
+
+    pub fn with_max_length(mut self, max_length: u32) -> FieldBuilder {
+        self.max_length = Some(max_length);
+        self
+    }
+}
+
+/// Attributes a field inherits from its parent.
+#[derive(Default)]
+struct Inherited {
+    name: String,
+    field_type: Option<Vec<u8>>,
+    flags: u32,
+    value: Option<Object>,
+    default_value: Option<Object>,
+    default_appearance: Option<String>,
+}
+
+impl Document {
+    /// Get the interactive form of the document.
+    pub fn get_form(&self) -> Result<Form> {
+        let acro_form = self.catalog()?.get(b"AcroForm")?;
+        let (id, acro_form) = self.dereference(acro_form)?;
+        let acro_form = acro_form.as_dict()?;
+
+        let inherited = Inherited {
+            default_appearance: acro_form
+                .get(b"DA")
+                .and_then(Object::as_str)
+                .map(decode_text_string)
+                .ok(),
+            ..Inherited::default()
+        };
+        let mut visited = BTreeSet::new();
+        let mut fields = Vec::new();
+        if let Ok(roots) = acro_form.get(b"Fields").and_then(Object::as_array) {
+            for root in roots {
+                if let Ok(root_id) = root.as_reference() {
+                    fields.extend(self.read_field(root_id, &inherited, &mut visited));
+                }
+            }
+        }
+
+        Ok(Form {
+            id,
+            fields,
+            need_appearances: matches!(acro_form.get(b"NeedAppearances"), Ok(Object::Boolean(true))),
+            default_appearance: inherited.default_appearance,
+        })
+    }
+
+    /// Add a root field to the interactive form, creating the form if missing, and add its widget annotations to
+    /// their pages. Returns the id of the field dictionary.
+    ///
+    /// Fields with one widget are merged with it into a single dictionary. Appearance streams are generated for
+    /// the widgets, and the fonts of default appearances are added to the default resources of the form.
+    pub fn add_field(&mut self, builder: &FieldBuilder) -> Result<ObjectId> {
+        if self
+            .get_form()
+            .is_ok_and(|form| form.get_field(&builder.name).is_some())
+        {
+            return Err(Error::DictKey);
+        }
+        if builder.widgets.is_empty()
+            || (builder.field_type == FieldType::RadioButton && builder.options.len() != builder.widgets.len())
+        {
+            return Err(Error::Type);
+        }
+        let field_type = match builder.field_type {
+            FieldType::PushButton | FieldType::CheckBox | FieldType::RadioButton => "Btn",
+            FieldType::Text => "Tx",
+            FieldType::ComboBox | FieldType::ListBox => "Ch",
+            FieldType::Signature | FieldType::NonTerminal => return Err(Error::Type),
+        };
+        let is_button = field_type == "Btn";
+        let default_appearance = match builder.default_appearance {
+            Some(ref default_appearance) => default_appearance.as_str(),
+            None if is_button && builder.field_type != FieldType::PushButton => "/ZaDb 0 Tf 0 g",
+            None => "/Helv 0 Tf 0 g",
+        };
+
+        let mut field = dictionary! {
+            "FT" => field_type,
+            "T" => Object::String(encode_text_string(&builder.name), StringFormat::Literal),
+            "DA" => Object::String(encode_text_string(default_appearance), StringFormat::Literal),
+        };
+        let flags = match builder.field_type {
+            FieldType::PushButton => builder.flags | Field::PUSH_BUTTON,
+            FieldType::RadioButton => builder.flags | Field::RADIO,
+            FieldType::ComboBox => builder.flags | Field::COMBO,
+            _ => builder.flags,
+        };
+        if flags != 0 {
+            field.set("Ff", flags as i64);
+        }
+        if let Some(ref tooltip) = builder.tooltip {
+            field.set("TU", Object::String(encode_text_string(tooltip), StringFormat::Literal));
+        }
+        if let Some(max_length) = builder.max_length {
+            field.set("MaxLen", max_length as i64);
+        }
+        if !is_button && !builder.options.is_empty() {
+            let options = builder
+                .options
+                .iter()
+                .map(|option| Object::String(encode_text_string(option), StringFormat::Literal))
+                .collect::<Vec<_>>();
+            field.set("Opt", options);
+        }
+        match builder.field_type {
+            FieldType::PushButton => {
+                // Centered caption.
+                field.set("Q", 1);
+            }
+            FieldType::RadioButton => {
+                let value = builder.value.as_deref().unwrap_or("Off");
+                field.set("V", Object::Name(value.as_bytes().to_vec()));
+            }
+            _ => {}
+        }
+
+        let widget_dict = |page_id: ObjectId, rect: Rectangle| {
+            let mut widget = dictionary! {
+                "Type" => "Annot",
+                "Subtype" => "Widget",
+                "Rect" => vec![rect.llx.into(), rect.lly.into(), rect.urx.into(), rect.ury.into()],
+                "F" => crate::annotations::Annotation::PRINT as i64,
+                "P" => page_id,
+            };
+            if builder.field_type == FieldType::PushButton {
+                let caption = builder.value.as_deref().unwrap_or("");
+                widget.set(
+                    "MK",
+                    dictionary! { "CA" => Object::String(encode_text_string(caption), StringFormat::Literal) },
+                );
+            }
+            widget
+        };
+        let field_id = if builder.widgets.len() == 1 {
+            let (page_id, rect) = builder.widgets[0];
+            for (key, value) in widget_dict(page_id, rect).iter() {
+                field.set(key.clone(), value.clone());
+            }
+            let field_id = self.add_object(field);
+            self.append_annotation(page_id, field_id)?;
+            field_id
+        } else {
+            let field_id = self.add_object(field);
+            let mut kids = Vec::new();
+            for &(page_id, rect) in &builder.widgets {
+                let mut widget = widget_dict(page_id, rect);
+                widget.set("Parent", field_id);
+                let widget_id = self.add_object(widget);
+                self.append_annotation(page_id, widget_id)?;
+                kids.push(Object::Reference(widget_id));
+            }
+            self.get_object_mut(field_id)
+                .and_then(Object::as_dict_mut)?
+                .set("Kids", kids);
+            field_id
+        };
+        self.append_root_field(field_id)?;
+
+        // Generate the appearances as if the value was set, which also sets the value.
+        let mut form = self.get_form()?;
+        let field = form.get_field(&builder.name).cloned().ok_or(Error::ObjectNotFound)?;
+        match builder.field_type {
+            FieldType::Text => match builder.value {
+                Some(ref value) => form.set_text_value(self, &builder.name, value)?,
+                None => {
+                    for widget in &field.widgets {
+                        self.generate_text_appearance(&field, widget, &[], None)?;
+                    }
+                }
+            },
+            FieldType::CheckBox => {
+                let checked = builder.value.as_ref().is_some_and(|value| value != "Off");
+                form.set_checkbox(self, &builder.name, checked)?;
+            }
+            FieldType::RadioButton => {
+                for (widget, value) in field.widgets.iter().zip(&builder.options) {
+                    self.generate_radio_appearance(&field, widget, value.as_bytes())?;
+                    let state = if builder.value.as_ref() == Some(value) {
+                        value.as_bytes()
+                    } else {
+                        b"Off"
+                    };
+                    self.get_object_mut(widget.id)
+                        .and_then(Object::as_dict_mut)?
+                        .set("AS", Object::Name(state.to_vec()));
+                }
+            }
+            FieldType::ComboBox | FieldType::ListBox => match builder.value {
+                Some(ref value) => form.set_choice(self, &builder.name, value)?,
+                None => {
+                    let lines = if builder.field_type == FieldType::ListBox {
+                        builder.options.clone()
+                    } else {
+                        Vec::new()
+                    };
+                    for widget in &field.widgets {
+                        self.generate_text_appearance(&field, widget, &lines, None)?;
+                    }
+                }
+            },
+            FieldType::PushButton => {
+                let lines = builder.value.iter().cloned().collect::<Vec<_>>();
+                for widget in &field.widgets {
+                    self.generate_text_appearance(&field, widget, &lines, None)?;
+                }
+            }
+            FieldType::Signature | FieldType::NonTerminal => {}
+        }
+        Ok(field_id)
+    }
+
+    /// Whether the interactive form contains an XML Forms Architecture form.
+    pub fn has_xfa(&self) -> bool {
+        self.catalog()
+            .and_then(|catalog| catalog.get(b"AcroForm"))
+            .and_then(|acro_form| self.dereference(acro_form))
+            .and_then(|(_, acro_form)| acro_form.as_dict())
+            .is_ok_and(|acro_form| acro_form.has(b"XFA"))
+    }
+
+    /// Get the packets of the XFA form, as their names and XML data. A form in a single stream is returned as one
+    /// packet named `xdp`.
+    pub fn get_xfa_packets(&self) -> Result<Vec<(String, Vec<u8>)>> {
+        let (_, acro_form) = self.dereference(self.catalog()?.get(b"AcroForm")?)?;
+        let (_, xfa) = self.dereference(acro_form.as_dict()?.get(b"XFA")?)?;
+        let content = |object: &Object| -> Result<Vec<u8>> {
+            let stream = self.dereference(object)?.1.as_stream()?;
+            Ok(stream.decompressed_content().unwrap_or_else(|_| stream.content.clone()))
+        };
+        match *xfa {
+            Object::Stream(_) => Ok(vec![("xdp".to_owned(), content(xfa)?)]),
+            // Packets alternate between their names and their streams.
+            Object::Array(ref packets) => packets
+                .chunks_exact(2)
+                .map(|packet| {
+                    let name = packet[0]
+                        .as_str()
+                        .map(|name| String::from_utf8_lossy(name).into_owned())?;
+                    Ok((name, content(&packet[1])?))
+                })
+                .collect(),
+            _ => Err(Error::Type),
+        }
+    }
+
+    /// Get the XML data of the XFA form, with its packets assembled in order.
+    pub fn get_xfa(&self) -> Result<Vec<u8>> {
+        Ok(self.get_xfa_packets()?.into_iter().flat_map(|(_, data)| data).collect())
+    }
+
+    /// Remove the XFA form, so that viewers use the AcroForm fields instead, which are usually a static version of
+    /// the same form. Returns whether there was an XFA form.
+    pub fn remove_xfa(&mut self) -> Result<bool> {
+        if !self.has_xfa() {
+            return Ok(false);
+        }
+        let xfa = self.acro_form_mut()?.remove(b"XFA").ok_or(Error::DictKey)?;
+        let mut removed = Vec::new();
+        if let Ok((id, object)) = self.dereference(&xfa) {
+            removed.extend(id);
+            if let Ok(packets) = object.as_array() {
+                removed.extend(packets.iter().filter_map(|packet| packet.as_reference().ok()));
+            }
+        }
+        for id in removed {
+            self.objects.remove(&id);
+        }
+        // NeedsRendering asks viewers to render the XFA form, which no longer exists.
+        let catalog_id = self.trailer.get(b"Root").and_then(Object::as_reference)?;
+        self.get_object_mut(catalog_id)
+            .and_then(Object::as_dict_mut)?
+            .remove(b"NeedsRendering");
+        Ok(true)
+    }
+
+    /// Draw the appearance of every field widget into the content of its page and remove the interactive form,
+    /// so that the filled values can no longer be edited.
+    pub fn flatten_forms(&mut self) -> Result<()> {
+        let form = self.get_form()?;
+        let page_ids: Vec<ObjectId> = self.page_iter().collect();
+        let mut removed = BTreeSet::new();
+        let mut page_operations: BTreeMap<ObjectId, Vec<Operation>> = BTreeMap::new();
+
+        for field in form.iter() {
+            removed.insert(field.id);
+            for widget in &field.widgets {
+                removed.insert(widget.id);
+                let page_id = widget
+                    .page
+                    .filter(|page_id| page_ids.contains(page_id))
+                    .or_else(|| self.find_annotation_page(&page_ids, widget.id));
+                let (page_id, rect) = match (page_id, widget.rect) {
+                    (Some(page_id), Some(rect)) => (page_id, rect),
+                    _ => continue,
+                };
+                let flags = self
+                    .get_dictionary(widget.id)
+                    .and_then(|widget| widget.get(b"F"))
+                    .and_then(Object::as_i64)
+                    .unwrap_or(0);
+                if flags & ANNOTATION_HIDDEN != 0 {
+                    continue;
+                }
+                let (appearance_id, matrix) = match self.get_normal_appearance(widget.id, rect) {
+                    Some(appearance) => appearance,
+                    None => continue,
+                };
+                let name = format!("Fm{}", appearance_id.0);
+                self.add_xobject(page_id, name.as_bytes(), appearance_id)?;
+                let operations = page_operations.entry(page_id).or_default();
+                operations.push(Operation::new("q", vec![]));
+                operations.push(Operation::new("cm", matrix.to_operands()));
+                operations.push(Operation::new("Do", vec![Object::Name(name.into_bytes())]));
+                operations.push(Operation::new("Q", vec![]));
+            }
+        }
+
+        for (page_id, operations) in page_operations {
+            let content = Content { operations }.encode()?;
+            self.append_page_content(page_id, content)?;
+        }
+        for &page_id in &page_ids {
+            let array_id = self
+                .get_dictionary(page_id)
+                .and_then(|page| page.get(b"Annots"))
+                .and_then(Object::as_reference);
+            let annotations = match array_id {
+                Ok(array_id) => self.get_object_mut(array_id),
+                Err(_) => self
+                    .get_object_mut(page_id)
+                    .and_then(Object::as_dict_mut)
+                    .and_then(|page| page.get_mut(b"Annots")),
+            };
+            if let Ok(annotations) = annotations.and_then(Object::as_array_mut) {
+                annotations.retain(|annotation| match annotation.as_reference() {
+                    Ok(id) => !removed.contains(&id),
+                    Err(_) => true,
+                });
+            }
+        }
+        for id in removed.into_iter().chain(form.id) {
+            self.objects.remove(&id);
+        }
+        let catalog_id = self.trailer.get(b"Root").and_then(Object::as_reference)?;
+        self.get_object_mut(catalog_id)
+            .and_then(Object::as_dict_mut)?
+            .remove(b"AcroForm");
+        Ok(())
+    }
+
+    fn find_annotation_page(&self, page_ids: &[ObjectId], annotation_id: ObjectId) -> Option<ObjectId> {
+        page_ids.iter().copied().find(|&page_id| {
+            self.get_dictionary(page_id)
+                .and_then(|page| page.get(b"Annots"))
+                .and_then(|annotations| self.dereference(annotations))
+                .and_then(|(_, annotations)| annotations.as_array())
+                .map(|annotations| {
+                    annotations
+                        .iter()
+                        .any(|annotation| annotation.as_reference().ok() == Some(annotation_id))
+                })
+                .unwrap_or(false)
+        })
+    }
+
+    /// Get the normal appearance stream of an annotation in its current state,
+    /// with the matrix which maps the stream onto the annotation rectangle.
+    fn get_normal_appearance(&self, annotation_id: ObjectId, rect: Rectangle) -> Option<(ObjectId, Matrix)> {
+        let annotation = self.get_dictionary(annotation_id).ok()?;
+        let (_, appearances) = self.dereference(annotation.get(b"AP").ok()?).ok()?;
+        let normal = appearances.as_dict().ok()?.get(b"N").ok()?;
+        let appearance_id = match self.dereference(normal).ok()? {
+            (Some(id), Object::Stream(_)) => id,
+            (_, Object::Dictionary(states)) => {
+                let state = annotation.get(b"AS").and_then(Object::as_name).ok()?;
+                states.get(state).and_then(Object::as_reference).ok()?
+            }
+            _ => return None,
+        };
+        let stream = self.get_object(appearance_id).and_then(Object::as_stream).ok()?;
+
+        // Map the bounding box, transformed by the form matrix, onto the annotation rectangle.
+        let bbox = stream.dict.get(b"BBox").and_then(Rectangle::from_object).ok()?;
+        let matrix = stream
+            .dict
+            .get(b"Matrix")
+            .and_then(Matrix::from_object)
+            .unwrap_or(Matrix::IDENTITY);
+        let transformed = matrix.transform_rect(&bbox);
+        if transformed.width() <= 0.0 || transformed.height() <= 0.0 {
+            return None;
+        }
+        let scale_x = rect.width() / transformed.width();
+        let scale_y = rect.height() / transformed.height();
+        Some((
+            appearance_id,
+            Matrix::new(
+                scale_x,
+                0.0,
+                0.0,
+                scale_y,
+                rect.llx - transformed.llx * scale_x,
+                rect.lly - transformed.lly * scale_y,
+            ),
+        ))
+    }
+
+    fn read_field(&self, id: ObjectId, parent: &Inherited, visited: &mut BTreeSet<ObjectId>) -> Option<Field> {
+        if !visited.insert(id) {
+            return None;
+        }
+        let dict = self.get_dictionary(id).ok()?;
+
+        let partial_name = dict.get(b"T").and_then(Object::as_str).map(decode_text_string).ok();
+        let name = match partial_name {
+            Some(partial_name) if !parent.name.is_empty() => format!("{}.{}", parent.name, partial_name),
+            Some(partial_name) => partial_name,
+            None => parent.name.clone(),
+        };
+        let inherited = Inherited {
+            name,
+            field_type: dict
+                .get(b"FT")
+                .and_then(Object::as_name)
+                .map(<[u8]>::to_vec)
+                .ok()
+                .or_else(|| parent.field_type.clone()),
+            flags: dict
+                .get(b"Ff")
+                .and_then(Object::as_i64)
+                .map(|flags| flags as u32)
+                .unwrap_or(parent.flags),
+            value: self.get_inheritable(dict, b"V").or_else(|| parent.value.clone()),
+            default_value: self
+                .get_inheritable(dict, b"DV")
+                .or_else(|| parent.default_value.clone()),
+            default_appearance: dict
+                .get(b"DA")
+                .and_then(Object::as_str)
+                .map(decode_text_string)
+                .ok()
+                .or_else(|| parent.default_appearance.clone()),
+        };
+
+        let mut kids = Vec::new();
+        let mut widgets = Vec::new();
+        if dict.has(b"Rect") || dict.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"Widget") {
+            widgets.push(read_widget(id, dict));
+        }
+        if let Ok(kid_references) = dict.get(b"Kids").and_then(Object::as_array) {
+            for kid_id in kid_references.iter().filter_map(|kid| kid.as_reference().ok()) {
+                let kid = match self.get_dictionary(kid_id) {
+                    Ok(kid) => kid,
+                    Err(_) => continue,
+                };
+                // Kids without a partial name are the widgets of this field.
+                if kid.has(b"T") {
+                    kids.extend(self.read_field(kid_id, &inherited, visited));
+                } else if visited.insert(kid_id) {
+                    widgets.push(read_widget(kid_id, kid));
+                }
+            }
+        }
+
+        let options = match dict.get(b"Opt").and_then(|options| self.dereference(options)) {
+            Ok((_, Object::Array(options))) => options
+                .iter()
+                .filter_map(|option| match *option {
+                    Object::String(ref text, _) => Some(decode_text_string(text)),
+                    Object::Array(ref pair) => pair.first()?.as_str().map(decode_text_string).ok(),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        let flags = inherited.flags;
+        let field_type = match inherited.field_type.as_deref() {
+            Some(b"Btn") if flags & Field::PUSH_BUTTON != 0 => FieldType::PushButton,
+            Some(b"Btn") if flags & Field::RADIO != 0 => FieldType::RadioButton,
+            Some(b"Btn") => FieldType::CheckBox,
+            Some(b"Tx") => FieldType::Text,
+            Some(b"Ch") if flags & Field::COMBO != 0 => FieldType::ComboBox,
+            Some(b"Ch") => FieldType::ListBox,
+            Some(b"Sig") => FieldType::Signature,
+            _ => FieldType::NonTerminal,
+        };
+
+        Some(Field {
+            id,
+            name: inherited.name,
+            field_type,
+            flags,
+            value: inherited.value,
+            default_value: inherited.default_value,
+            default_appearance: inherited.default_appearance,
+            options,
+            kids,
+            widgets,
+        })
+    }
+
+    fn get_inheritable(&self, dict: &Dictionary, key: &[u8]) -> Option<Object> {
+        let value = dict.get(key).ok()?;
+        self.dereference(value).map(|(_, value)| value.clone()).ok()
+    }
+
+    pub(crate) fn acro_form_mut(&mut self) -> Result<&mut Dictionary> {
+        let catalog_id = self.trailer.get(b"Root").and_then(Object::as_reference)?;
+        match self.catalog()?.get(b"AcroForm")?.as_reference() {
+            Ok(id) => self.get_object_mut(id).and_then(Object::as_dict_mut),
+            Err(_) => self
+                .get_object_mut(catalog_id)
+                .and_then(Object::as_dict_mut)?
+                .get_mut(b"AcroForm")
+                .and_then(Object::as_dict_mut),
+        }
+    }
+
+    /// Get the interactive form dictionary, adding an empty interactive form if the document has none.
+    pub(crate) fn get_or_create_acro_form(&mut self) -> Result<&mut Dictionary> {
+        if self.catalog()?.get(b"AcroForm").is_err() {
+            let catalog_id = self.trailer.get(b"Root").and_then(Object::as_reference)?;
+            let acro_form_id = self.add_object(dictionary! {
+                "Fields" => Vec::<Object>::new(),
+                "DA" => Object::string_literal("/Helv 0 Tf 0 g"),
+            });
+            self.get_object_mut(catalog_id)
+                .and_then(Object::as_dict_mut)?
+                .set("AcroForm", acro_form_id);
+        }
+        self.acro_form_mut()
+    }
+
+    /// Append a field dictionary to the root fields of the interactive form, creating the form if missing.
+    pub(crate) fn append_root_field(&mut self, field_id: ObjectId) -> Result<()> {
+        let acro_form = self.get_or_create_acro_form()?;
+        match acro_form.get(b"Fields").and_then(Object::as_reference) {
+            Ok(fields_id) => self.get_object_mut(fields_id)?,
+            Err(_) => {
+                if !acro_form.has(b"Fields") {
+                    acro_form.set("Fields", Vec::new());
+                }
+                acro_form.get_mut(b"Fields")?
+            }
+        }
+        .as_array_mut()?
+        .push(field_id.into());
+        Ok(())
+    }
+
+    /// Get a font of the default resources of the form, adding a standard font if it's missing.
+    fn get_form_font(&mut self, name: &[u8]) -> Result<(Object, Dictionary)> {
+        if let Ok((font, font_dict)) = self.find_form_font(name) {
+            return Ok((font.clone(), font_dict.clone()));
+        }
+
+        let font = if name == b"ZaDb" {
+            dictionary! {
+                "Type" => "Font",
+                "Subtype" => "Type1",
+                "BaseFont" => "ZapfDingbats",
+            }
+        } else {
+            dictionary! {
+                "Type" => "Font",
+                "Subtype" => "Type1",
+                "BaseFont" => "Helvetica",
+                "Encoding" => "WinAnsiEncoding",
+            }
+        };
+        let font_id = self.add_object(font.clone());
+
+        let acro_form = self.acro_form_mut()?;
+        let resources_id = acro_form.get(b"DR").and_then(Object::as_reference).ok();
+        if !acro_form.has(b"DR") {
+            acro_form.set("DR", Dictionary::new());
+        }
+        let resources = match resources_id {
+            Some(id) => self.get_object_mut(id),
+            None => self.acro_form_mut()?.get_mut(b"DR"),
+        }
+        .and_then(Object::as_dict_mut)?;
+        let fonts_id = resources.get(b"Font").and_then(Object::as_reference).ok();
+        if !resources.has(b"Font") {
+            resources.set("Font", Dictionary::new());
+        }
+        let fonts = match fonts_id {
+            Some(id) => self.get_object_mut(id).and_then(Object::as_dict_mut)?,
+            None => resources.get_mut(b"Font").and_then(Object::as_dict_mut)?,
+        };
+        fonts.set(name.to_vec(), font_id);
+        Ok((font_id.into(), font))
+    }
+
+    fn find_form_font(&self, name: &[u8]) -> Result<(&Object, &Dictionary)> {
+        let (_, acro_form) = self.dereference(self.catalog()?.get(b"AcroForm")?)?;
+        let (_, resources) = self.dereference(acro_form.as_dict()?.get(b"DR")?)?;
+        let (_, fonts) = self.dereference(resources.as_dict()?.get(b"Font")?)?;
+        let font = fonts.as_dict()?.get(name)?;
+        Ok((font, self.dereference(font)?.1.as_dict()?))
+    }
+
+    /// Get the names of the normal appearance states of a widget.
+    fn appearance_states(&self, widget_id: ObjectId) -> Vec<Vec<u8>> {
+        let states = self
+            .get_dictionary(widget_id)
+            .and_then(|widget| widget.get(b"AP"))
+            .and_then(|appearances| self.dereference(appearances))
+            .and_then(|(_, appearances)| appearances.as_dict())
+            .and_then(|appearances| appearances.get(b"N"))
+            .and_then(|normal| self.dereference(normal))
+            .and_then(|(_, normal)| normal.as_dict());
+        match states {
+            Ok(states) => states.iter().map(|(state, _)| state.clone()).collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    fn get_quadding(&self, field: &Field) -> i64 {
+        let field_quadding = self
+            .get_dictionary(field.id)
+            .and_then(|dict| dict.get(b"Q"))
+            .and_then(Object::as_i64);
+        let form_quadding = || {
+            self.catalog()
+                .and_then(|catalog| catalog.get(b"AcroForm"))
+                .and_then(|acro_form| self.dereference(acro_form))
+                .and_then(|(_, acro_form)| acro_form.as_dict())
+                .and_then(|acro_form| acro_form.get(b"Q"))
+                .and_then(Object::as_i64)
+        };
+        field_quadding.or_else(|_| form_quadding()).unwrap_or(0)
+    }
+
+    /// Generate the normal appearance of a text or choice field widget showing the given lines.
+    fn generate_text_appearance(
+        &mut self, field: &Field, widget: &Widget, lines: &[String], selected: Option<usize>,
+    ) -> Result<()> {
+        let rect = widget.rect.unwrap_or_else(|| Rectangle::new(0.0, 0.0, 0.0, 0.0));
+        let (width, height) = (rect.width(), rect.height());
+        let appearance = DefaultAppearance::parse(field.default_appearance.as_deref().unwrap_or("/Helv 0 Tf 0 g"));
+        let (font, font_dict) = self.get_form_font(&appearance.font)?;
+        let encoding = font_dict.get_font_encoding().to_owned();
+        let texts: Vec<Vec<u8>> = lines
+            .iter()
+            .map(|line| Document::encode_text(Some(&encoding), line))
+            .collect();
+        let multiline =
+            lines.len() > 1 || field.flags & Field::MULTILINE != 0 || field.field_type == FieldType::ListBox;
+
+        let mut size = appearance.size;
+        if size <= 0.0 {
+            // Auto sized text fills the height of single line fields and shrinks to fit their width.
+            size = if multiline {
+                12.0
+            } else {
+                ((height - 2.0 * PADDING) / LINE_HEIGHT).min(12.0)
+            };
+            let widest = texts
+                .iter()
+                .map(|text| text_width(&font_dict, text))
+                .fold(0.0, f64::max);
+            if widest * size > width - 2.0 * PADDING && widest > 0.0 {
+                size = (width - 2.0 * PADDING) / widest;
+            }
+            size = size.max(MIN_FONT_SIZE);
+        }
+        let leading = size * LINE_HEIGHT;
+        let quadding = self.get_quadding(field);
+
+        let mut operations = vec![
+            Operation::new("BMC", vec!["Tx".into()]),
+            Operation::new("q", vec![]),
+            Operation::new("re", reals(&[1.0, 1.0, width - 2.0, height - 2.0])),
+            Operation::new("W", vec![]),
+            Operation::new("n", vec![]),
+        ];
+        if let Some(index) = selected {
+            let bottom = height - PADDING - leading * (index + 1) as f64;
+            operations.push(Operation::new("rg", reals(&HIGHLIGHT_COLOR)));
+            operations.push(Operation::new("re", reals(&[1.0, bottom, width - 2.0, leading])));
+            operations.push(Operation::new("f", vec![]));
+        }
+        operations.push(Operation::new("BT", vec![]));
+        operations.extend(appearance.operations);
+        operations.push(Operation::new(
+            "Tf",
+            vec![Object::Name(appearance.font.clone()), size.into()],
+        ));
+        for (index, text) in texts.into_iter().enumerate() {
+            let text_width = text_width(&font_dict, &text) * size;
+            let x = match quadding {
+                1 => (width - text_width) / 2.0,
+                2 => width - PADDING - text_width,
+                _ => PADDING,
+            };
+            let y = if multiline {
+                let bottom = height - PADDING - leading * (index + 1) as f64;
+                bottom + (leading - size) / 2.0 + DESCENT * size
+            } else {
+                (height - size) / 2.0 + DESCENT * size
+            };
+            operations.push(Operation::new("Tm", reals(&[1.0, 0.0, 0.0, 1.0, x, y])));
+            operations.push(Operation::new("Tj", vec![Object::String(text, StringFormat::Literal)]));
+        }
+        operations.push(Operation::new("ET", vec![]));
+        operations.push(Operation::new("Q", vec![]));
+        operations.push(Operation::new("EMC", vec![]));
+
+        let content = Content { operations }.encode()?;
+        let stream_id = self.add_appearance_stream(width, height, &appearance.font, font, content);
+        self.get_object_mut(widget.id)
+            .and_then(Object::as_dict_mut)?
+            .set("AP", dictionary! { "N" => stream_id });
+        Ok(())
+    }
+
+    /// Get the on state of a check box widget, generating a check mark appearance if it has none.
+    fn generate_checkbox_appearance(&mut self, field: &Field, widget: &Widget) -> Result<Vec<u8>> {
+        let states = self.appearance_states(widget.id);
+        if let Some(on_state) = states.into_iter().find(|state| state != b"Off") {
+            return Ok(on_state);
+        }
+
+        let rect = widget.rect.unwrap_or_else(|| Rectangle::new(0.0, 0.0, 0.0, 0.0));
+        let (width, height) = (rect.width(), rect.height());
+        let appearance = DefaultAppearance::parse(field.default_appearance.as_deref().unwrap_or("/ZaDb 0 Tf 0 g"));
+        let (font, _) = self.get_form_font(b"ZaDb")?;
+        let size = width.min(height) * 0.8;
+
+        let mut operations = vec![Operation::new("q", vec![]), Operation::new("BT", vec![])];
+        operations.extend(appearance.operations);
+        operations.push(Operation::new("Tf", vec![Object::Name(b"ZaDb".to_vec()), size.into()]));
+        // The check mark glyph of ZapfDingbats is 0.846 em wide and 0.692 em high.
+        let (x, y) = ((width - 0.846 * size) / 2.0, (height - 0.692 * size) / 2.0);
+        operations.push(Operation::new("Tm", reals(&[1.0, 0.0, 0.0, 1.0, x, y])));
+        operations.push(Operation::new("Tj", vec![Object::string_literal("4")]));
+        operations.push(Operation::new("ET", vec![]));
+        operations.push(Operation::new("Q", vec![]));
+
+        let content = Content { operations }.encode()?;
+        let on_id = self.add_appearance_stream(width, height, b"ZaDb", font.clone(), content);
+        let off_id = self.add_appearance_stream(width, height, b"ZaDb", font, Vec::new());
+        self.get_object_mut(widget.id).and_then(Object::as_dict_mut)?.set(
+            "AP",
+            dictionary! { "N" => dictionary! { "Yes" => on_id, "Off" => off_id } },
+        );
+        Ok(b"Yes".to_vec())
+    }
+
+    /// Generate the appearance of a radio button widget, a bullet in its on state and nothing when off.
+    fn generate_radio_appearance(&mut self, field: &Field, widget: &Widget, on_state: &[u8]) -> Result<()> {
+        let rect = widget.rect.unwrap_or_else(|| Rectangle::new(0.0, 0.0, 0.0, 0.0));
+        let (width, height) = (rect.width(), rect.height());
+        let appearance = DefaultAppearance::parse(field.default_appearance.as_deref().unwrap_or("/ZaDb 0 Tf 0 g"));
+        let (font, _) = self.get_form_font(b"ZaDb")?;
+        let size = width.min(height) * 0.8;
+
+        let mut operations = vec![Operation::new("q", vec![]), Operation::new("BT", vec![])];
+        operations.extend(appearance.operations);
+        operations.push(Operation::new("Tf", vec![Object::Name(b"ZaDb".to_vec()), size.into()]));
+        // The bullet glyph of ZapfDingbats is 0.791 em wide and 0.705 em high.
+        let (x, y) = ((width - 0.791 * size) / 2.0, (height - 0.705 * size) / 2.0);
+        operations.push(Operation::new("Tm", reals(&[1.0, 0.0, 0.0, 1.0, x, y])));
+        operations.push(Operation::new("Tj", vec![Object::string_literal("l")]));
+        operations.push(Operation::new("ET", vec![]));
+        operations.push(Operation::new("Q", vec![]));
+
+        let content = Content { operations }.encode()?;
+        let on_id = self.add_appearance_stream(width, height, b"ZaDb", font.clone(), content);
+        let off_id = self.add_appearance_stream(width, height, b"ZaDb", font, Vec::new());
+        let mut states = Dictionary::new();
+        states.set(on_state.to_vec(), on_id);
+        states.set("Off", off_id);
+        self.get_object_mut(widget.id)
+            .and_then(Object::as_dict_mut)?
+            .set("AP", dictionary! { "N" => states });
+        Ok(())
+    }
+
+    fn add_appearance_stream(
+        &mut self, width: f64, height: f64, font_name: &[u8], font: Object, content: Vec<u8>,
+    ) -> ObjectId {
+        let mut stream = xobject::form(
+            vec![0.0, 0.0, width, height],
+            vec![1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+            content,
+        );
+        let mut fonts = Dictionary::new();
+        fonts.set(font_name.to_vec(), font);
+        stream.dict.set("Resources", dictionary! { "Font" => fonts });
+        self.add_object(stream)
+    }
+}
+
+/// Annotation flag of annotations which are neither displayed nor printed.
+const ANNOTATION_HIDDEN: i64 = 1 << 1;
+
+/// Padding between the border of a widget and its text.
+const PADDING: f64 = 2.0;
+const LINE_HEIGHT: f64 = 1.15;
+const MIN_FONT_SIZE: f64 = 4.0;
+/// Approximate descent of Latin fonts, as a fraction of the font size.
+const DESCENT: f64 = 0.22;
+const HIGHLIGHT_COLOR: [f64; 3] = [0.6, 0.75, 0.87];
+
+/// Glyph widths of Helvetica for the printable ASCII characters, used when a font has no /Widths.
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556, 556, 556, 556, 556,
+    556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833,
+    722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556,
+    556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334,
+    260, 334, 584,
+];
+
+/// Width of encoded text in text space units of a font size of one.
+///
+/// Fonts without /Widths are measured as the standard fonts Courier or otherwise Helvetica.
+pub(crate) fn text_width(font: &Dictionary, text: &[u8]) -> f64 {
+    let first_char = font.get(b"FirstChar").and_then(Object::as_i64).unwrap_or(0);
+    let widths = font.get(b"Widths").and_then(Object::as_array).ok();
+    let monospaced = font
+        .get(b"BaseFont")
+        .and_then(Object::as_name)
+        .is_ok_and(|name| name.starts_with(b"Courier"));
+    text.iter()
+        .map(|&code| {
+            let width = match widths {
+                None if monospaced => Some(600.0),
+                Some(widths) => usize::try_from(i64::from(code) - first_char)
+                    .ok()
+                    .and_then(|index| widths.get(index))
+                    .and_then(|width| width.as_float().ok()),
+                None => usize::from(code)
+                    .checked_sub(32)
+                    .and_then(|index| HELVETICA_WIDTHS.get(index))
+                    .map(|&width| f64::from(width)),
+            };
+            width.unwrap_or(556.0) / 1000.0
+        })
+        .sum()
+}
+
+fn reals(values: &[f64]) -> Vec<Object> {
+    values.iter().map(|&value| value.into()).collect()
+}
+
+/// Default appearance string of a variable text field, split into its font and other operations.
+pub(crate) struct DefaultAppearance {
+    pub(crate) font: Vec<u8>,
+    pub(crate) size: f64,
+    pub(crate) operations: Vec<Operation>,
+}
+
+impl DefaultAppearance {
+    pub(crate) fn parse(text: &str) -> DefaultAppearance {
+        let mut appearance = DefaultAppearance {
+            font: b"Helv".to_vec(),
+            size: 0.0,
+            operations: Vec::new(),
+        };
+        let mut operands = Vec::new();
+        for token in text.split_whitespace() {
+            if let Some(name) = token.strip_prefix('/') {
+                operands.push(Object::Name(name.as_bytes().to_vec()));
+            } else if let Ok(number) = token.parse::<f64>() {
+                operands.push(Object::Real(number));
+            } else if token == "Tf" {
+                if let [Object::Name(font), Object::Real(size)] = &operands[operands.len().saturating_sub(2)..] {
+                    appearance.font = font.clone();
+                    appearance.size = *size;
+                }
+                operands.clear();
+            } else {
+                appearance
+                    .operations
+                    .push(Operation::new(token, std::mem::take(&mut operands)));
+            }
+        }
+        appearance
+    }
+}
+
+fn read_widget(id: ObjectId, dict: &Dictionary) -> Widget {
+    Widget {
+        id,
+        rect: dict.get(b"Rect").and_then(Rectangle::from_object).ok(),
+        page: dict.get(b"P").and_then(Object::as_reference).ok(),
+    }
+}
+
+#[test]
+fn read_form_fields() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    let name_id = doc.add_object(dictionary! {
+        "T" => Object::string_literal("name"),
+        "V" => Object::string_literal("Jane"),
+        "Type" => "Annot",
+        "Subtype" => "Widget",
+        "Rect" => vec![100.into(), 700.into(), 300.into(), 720.into()],
+        "P" => page_id,
+    });
+    let check_widget_id = doc.add_object(dictionary! {
+        "Type" => "Annot",
+        "Subtype" => "Widget",
+        "Rect" => vec![100.into(), 650.into(), 115.into(), 665.into()],
+    });
+    let check_id = doc.add_object(dictionary! {
+        "FT" => "Btn",
+        "T" => Object::string_literal("subscribe"),
+        "V" => "Yes",
+        "Kids" => vec![check_widget_id.into()],
+    });
+    let person_id = doc.new_object_id();
+    doc.objects.insert(
+        person_id,
+        Object::Dictionary(dictionary! {
+            "FT" => "Tx",
+            "T" => Object::string_literal("person"),
+            "Kids" => vec![name_id.into(), check_id.into()],
+        }),
+    );
+    let catalog_id = doc.trailer.get(b"Root").and_then(Object::as_reference).unwrap();
+    doc.get_object_mut(catalog_id)
+        .and_then(Object::as_dict_mut)
+        .unwrap()
+        .set(
+            "AcroForm",
+            dictionary! {
+                "Fields" => vec![person_id.into()],
+                "DA" => Object::string_literal("/Helv 0 Tf 0 g"),
+            },
+        );
+
+    let form = doc.get_form().unwrap();
+    assert_eq!(form.iter().count(), 3);
+    let name = form.get_field("person.name").unwrap();
+    assert_eq!(name.field_type, FieldType::Text);
+    assert_eq!(name.text_value().unwrap(), "Jane");
+    assert_eq!(name.default_appearance.as_ref().unwrap(), "/Helv 0 Tf 0 g");
+    assert_eq!(name.widgets[0].rect.unwrap().width(), 200.0);
+    assert_eq!(name.widgets[0].page, Some(page_id));
+    let check = form.get_field("person.subscribe").unwrap();
+    assert_eq!(check.field_type, FieldType::CheckBox);
+    assert_eq!(check.text_value().unwrap(), "Yes");
+    assert_eq!(check.widgets[0].id, check_widget_id);
+}
+
+#[test]
+fn fill_form_fields() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    let name_id = doc.add_object(dictionary! {
+        "FT" => "Tx",
+        "T" => Object::string_literal("name"),
+        "Q" => 1,
+        "Type" => "Annot",
+        "Subtype" => "Widget",
+        "Rect" => vec![100.into(), 700.into(), 300.into(), 720.into()],
+    });
+    let check_id = doc.add_object(dictionary! {
+        "FT" => "Btn",
+        "T" => Object::string_literal("subscribe"),
+        "Type" => "Annot",
+        "Subtype" => "Widget",
+        "Rect" => vec![100.into(), 650.into(), 115.into(), 665.into()],
+    });
+    let color_id = doc.add_object(dictionary! {
+        "FT" => "Ch",
+        "Ff" => Field::COMBO as i64,
+        "T" => Object::string_literal("color"),
+        "Opt" => vec![Object::string_literal("Red"), Object::string_literal("Blue")],
+        "Type" => "Annot",
+        "Subtype" => "Widget",
+        "Rect" => vec![100.into(), 600.into(), 200.into(), 620.into()],
+    });
+    let catalog_id = doc.trailer.get(b"Root").and_then(Object::as_reference).unwrap();
+    doc.get_object_mut(catalog_id)
+        .and_then(Object::as_dict_mut)
+        .unwrap()
+        .set(
+            "AcroForm",
+            dictionary! {
+                "Fields" => vec![name_id.into(), check_id.into(), color_id.into()],
+                "DA" => Object::string_literal("/Helv 0 Tf 0 g"),
+            },
+        );
+
+    let mut form = doc.get_form().unwrap();
+    form.set_text_value(&mut doc, "name", "Jane Doe").unwrap();
+    form.set_checkbox(&mut doc, "subscribe", true).unwrap();
+    form.set_choice(&mut doc, "color", "Blue").unwrap();
+    assert!(form.set_choice(&mut doc, "color", "Green").is_err());
+    assert!(form.set_checkbox(&mut doc, "name", true).is_err());
+    assert_eq!(form.get_field("name").unwrap().text_value().unwrap(), "Jane Doe");
+
+    let appearance = |doc: &Document, widget_id: ObjectId| {
+        let appearances = doc.get_dictionary(widget_id).unwrap().get(b"AP").unwrap();
+        let normal_id = appearances
+            .as_dict()
+            .unwrap()
+            .get(b"N")
+            .and_then(Object::as_reference)
+            .unwrap();
+        let stream = doc.get_object(normal_id).and_then(Object::as_stream).unwrap();
+        String::from_utf8(stream.decompressed_content().unwrap_or_else(|_| stream.content.clone())).unwrap()
+    };
+    let content = appearance(&doc, name_id);
+    assert!(content.contains("/Helv 12.0 Tf"));
+    assert!(content.contains("(Jane Doe) Tj"));
+    assert!(appearance(&doc, color_id).contains("(Blue) Tj"));
+
+    let form = doc.get_form().unwrap();
+    assert_eq!(form.get_field("name").unwrap().text_value().unwrap(), "Jane Doe");
+    assert_eq!(form.get_field("subscribe").unwrap().text_value().unwrap(), "Yes");
+    assert_eq!(form.get_field("color").unwrap().text_value().unwrap(), "Blue");
+    let check = doc.get_dictionary(check_id).unwrap();
+    assert_eq!(check.get(b"AS").and_then(Object::as_name).unwrap(), b"Yes");
+    let fonts = doc.find_form_font(b"Helv").unwrap();
+    assert_eq!(
+        fonts.1.get(b"BaseFont").and_then(Object::as_name).unwrap(),
+        b"Helvetica"
+    );
+}
+
+#[test]
+fn flatten_form_fields() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    let name_id = doc.add_object(dictionary! {
+        "FT" => "Tx",
+        "T" => Object::string_literal("name"),
+        "Type" => "Annot",
+        "Subtype" => "Widget",
+        "Rect" => vec![100.into(), 700.into(), 300.into(), 720.into()],
+    });
+    let annotations_id = doc.add_object(vec![name_id.into()]);
+    doc.get_object_mut(page_id)
+        .and_then(Object::as_dict_mut)
+        .unwrap()
+        .set("Annots", annotations_id);
+    let catalog_id = doc.trailer.get(b"Root").and_then(Object::as_reference).unwrap();
+    doc.get_object_mut(catalog_id)
+        .and_then(Object::as_dict_mut)
+        .unwrap()
+        .set("AcroForm", dictionary! { "Fields" => vec![name_id.into()] });
+
+    let mut form = doc.get_form().unwrap();
+    form.set_text_value(&mut doc, "name", "Jane Doe").unwrap();
+    doc.flatten_forms().unwrap();
+
+    assert!(doc.get_form().is_err());
+    assert!(doc.get_object(name_id).is_err());
+    let annotations = doc.get_object(annotations_id).and_then(Object::as_array).unwrap();
+    assert!(annotations.is_empty());
+    let content = doc.get_page_content(page_id).unwrap();
+    let content = String::from_utf8_lossy(&content);
+    assert!(content.starts_with("q\n"));
+    assert!(content.contains("1.0 0.0 0.0 1.0 100.0 700.0 cm"));
+    assert_eq!(doc.get_page_fonts(page_id).len(), 1);
+    let mut buffer = Vec::new();
+    doc.save_to(&mut buffer).unwrap();
+}
+
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+#[test]
+fn create_form_fields() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    let rect = |y: f64| Rectangle::new(100.0, y, 300.0, y + 20.0);
+    doc.add_field(
+        &FieldBuilder::text("name", page_id, rect(700.0))
+            .with_value("Jane Doe")
+            .with_max_length(40)
+            .with_tooltip("Full name"),
+    )
+    .unwrap();
+    doc.add_field(
+        &FieldBuilder::checkbox("agree", page_id, Rectangle::new(100.0, 670.0, 115.0, 685.0)).with_value("Yes"),
+    )
+    .unwrap();
+    let radio_id = doc
+        .add_field(
+            &FieldBuilder::radio_group(
+                "size",
+                &[
+                    (page_id, Rectangle::new(100.0, 640.0, 115.0, 655.0), "Small"),
+                    (page_id, Rectangle::new(130.0, 640.0, 145.0, 655.0), "Large"),
+                ],
+            )
+            .with_value("Large"),
+        )
+        .unwrap();
+    doc.add_field(&FieldBuilder::combo_box("color", page_id, rect(600.0), &["Red", "Green"]).with_value("Green"))
+        .unwrap();
+    doc.add_field(&FieldBuilder::list_box(
+        "fruit",
+        page_id,
+        rect(540.0),
+        &["Apple", "Pear"],
+    ))
+    .unwrap();
+    doc.add_field(&FieldBuilder::push_button("submit", page_id, rect(500.0), "Submit"))
+        .unwrap();
+    assert!(doc
+        .add_field(&FieldBuilder::text("name", page_id, rect(400.0)))
+        .is_err());
+
+    let mut output = Vec::new();
+    doc.save_to(&mut output).unwrap();
+    let doc = Document::load_mem(&output).unwrap();
+    let form = doc.get_form().unwrap();
+    let types: Vec<FieldType> = form.fields.iter().map(|field| field.field_type).collect();
+    assert_eq!(
+        types,
+        vec![
+            FieldType::Text,
+            FieldType::CheckBox,
+            FieldType::RadioButton,
+            FieldType::ComboBox,
+            FieldType::ListBox,
+            FieldType::PushButton
+        ]
+    );
+    assert_eq!(form.get_field("name").unwrap().text_value().unwrap(), "Jane Doe");
+    assert_eq!(form.get_field("agree").unwrap().text_value().unwrap(), "Yes");
+    assert_eq!(form.get_field("color").unwrap().text_value().unwrap(), "Green");
+    assert_eq!(form.get_field("fruit").unwrap().options, vec!["Apple", "Pear"]);
+    let size = form.get_field("size").unwrap();
+    assert_eq!(size.id, radio_id);
+    assert_eq!(size.text_value().unwrap(), "Large");
+    assert_eq!(size.widgets.len(), 2);
+    assert_eq!(
+        doc.appearance_states(size.widgets[0].id),
+        vec![b"Small".to_vec(), b"Off".to_vec()]
+    );
+    for field in form.iter() {
+        for widget in &field.widgets {
+            assert!(doc.get_dictionary(widget.id).unwrap().has(b"AP"));
+        }
+    }
+    let page = doc.get_dictionary(page_id).unwrap();
+    assert_eq!(page.get(b"Annots").and_then(Object::as_array).unwrap().len(), 7);
+    assert!(doc.find_form_font(b"Helv").is_ok());
+    assert!(doc.find_form_font(b"ZaDb").is_ok());
+}
+
+#[test]
+fn extract_and_remove_xfa() {
+    use crate::test_support::create_document;
+    use crate::Stream;
+
+    let mut doc = create_document();
+    assert!(!doc.has_xfa());
+    assert!(!doc.remove_xfa().unwrap());
+    let template_id = doc.add_object(Stream::new(dictionary! {}, b"<template/>".to_vec()));
+    let mut datasets = Stream::new(dictionary! {}, b"<xfa:datasets/>".to_vec());
+    datasets.compress().unwrap();
+    let datasets_id = doc.add_object(datasets);
+    let catalog_id = doc.trailer.get(b"Root").and_then(Object::as_reference).unwrap();
+    let catalog = doc.get_object_mut(catalog_id).and_then(Object::as_dict_mut).unwrap();
+    catalog.set("NeedsRendering", true);
+    catalog.set(
+        "AcroForm",
+        dictionary! {
+            "Fields" => Vec::<Object>::new(),
+            "XFA" => vec![
+                Object::string_literal("template"),
+                template_id.into(),
+                Object::string_literal("datasets"),
+                datasets_id.into(),
+            ],
+        },
+    );
+
+    assert!(doc.has_xfa());
+    let packets = doc.get_xfa_packets().unwrap();
+    assert_eq!(packets[0], ("template".to_owned(), b"<template/>".to_vec()));
+    assert_eq!(packets[1].0, "datasets");
+    assert_eq!(doc.get_xfa().unwrap(), b"<template/><xfa:datasets/>");
+
+    assert!(doc.remove_xfa().unwrap());
+    assert!(!doc.has_xfa());
+    assert!(doc.get_object(template_id).is_err());
+    assert!(doc.get_object(datasets_id).is_err());
+    assert!(!doc.catalog().unwrap().has(b"NeedsRendering"));
+    assert!(doc.get_form().is_ok());
+}
@@ -0,0 +1,337 @@
+use crate::{Error, Object, Result};
+use std::ops::Mul;
+
+/// Rectangle given by its lower-left and upper-right corners, as used for page boxes and annotations.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Rectangle {
+    pub llx: f64,
+    pub lly: f64,
+    pub urx: f64,
+    pub ury: f64,
+}
+
+impl Rectangle {
+    pub fn new(llx: f64, lly: f64, urx: f64, ury: f64) -> Rectangle {
+        Rectangle { llx, lly, urx, ury }
+    }
+
+    /// Get a rectangle from an array of four numbers, which may give any two opposite corners.
+    pub fn from_object(object: &Object) -> Result<Rectangle> {
+        let array = object.as_array()?;
+        if array.len() != 4 {
+            return Err(Error::Type);
+        }
+        let numbers = array.iter().map(Object::as_float).collect::<Result<Vec<f64>>>()?;
+        Ok(Rectangle {
+            llx: numbers[0].min(numbers[2]),
+            lly: numbers[1].min(numbers[3]),
+            urx: numbers[0].max(numbers[2]),
+            ury: numbers[1].max(numbers[3]),
+        })
+    }
+
+    pub fn width(&self) -> f64 {
+        self.urx - self.llx
+    }
+
+    pub fn height(&self) -> f64 {
+        self.ury - self.lly
+    }
+
+    /// Whether the other rectangle lies entirely within this one.
+    pub fn contains(&self, other: &Rectangle) -> bool {
+        self.llx <= other.llx && self.lly <= other.lly && self.urx >= other.urx && self.ury >= other.ury
+    }
+
+    /// Get the rectangle with two opposite corners, in any order.
+    pub fn from_points(a: Point, b: Point) -> Rectangle {
+        Rectangle::new(a.x.min(b.x), a.y.min(b.y), a.x.max(b.x), a.y.max(b.y))
+    }
+
+    /// Get the rectangle with corners swapped as needed so that the lower-left corner is below and left of the
+    /// upper-right one.
+    pub fn normalized(&self) -> Rectangle {
+        Rectangle::from_points(Point::new(self.llx, self.lly), Point::new(self.urx, self.ury))
+    }
+
+    /// Get the smallest rectangle covering both rectangles.
+    pub fn union(&self, other: &Rectangle) -> Rectangle {
+        Rectangle {
+            llx: self.llx.min(other.llx),
+            lly: self.lly.min(other.lly),
+            urx: self.urx.max(other.urx),
+            ury: self.ury.max(other.ury),
+        }
+    }
+
+    /// Get the area covered by both rectangles, if they overlap.
+    pub fn intersection(&self, other: &Rectangle) -> Option<Rectangle> {
+        let rect = Rectangle {
+            llx: self.llx.max(other.llx),
+            lly: self.lly.max(other.lly),
+            urx: self.urx.min(other.urx),
+            ury: self.ury.min(other.ury),
+        };
+        if rect.llx <= rect.urx && rect.lly <= rect.ury {
+            Some(rect)
+        } else {
+            None
+        }
+    }
+}
+
+impl From<Rectangle> for Object {
+    fn from(rect: Rectangle) -> Self {
+        Object::Array(vec![
+            Object::Real(rect.llx),
+            Object::Real(rect.lly),
+            Object::Real(rect.urx),
+            Object::Real(rect.ury),
+        ])
+    }
+}
+
+/// Point given by its coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Point {
+    pub fn new(x: f64, y: f64) -> Point {
+        Point { x, y }
+    }
+}
+
+/// Transformation matrix `[a b c d e f]`, which maps a point (x, y) to (a x + c y + e, b x + d y + f), as used
+/// by the `cm` operator and the /Matrix of forms.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Matrix {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    pub d: f64,
+    pub e: f64,
+    pub f: f64,
+}
+
+impl Matrix {
+    pub const IDENTITY: Matrix = Matrix::new(1.0, 0.0, 0.0, 1.0, 0.0, 0.0);
+
+    pub const fn new(a: f64, b: f64, c: f64, d: f64, e: f64, f: f64) -> Matrix {
+        Matrix { a, b, c, d, e, f }
+    }
+
+    pub fn translate(tx: f64, ty: f64) -> Matrix {
+        Matrix::new(1.0, 0.0, 0.0, 1.0, tx, ty)
+    }
+
+    pub fn scale(sx: f64, sy: f64) -> Matrix {
+        Matrix::new(sx, 0.0, 0.0, sy, 0.0, 0.0)
+    }
+
+    /// Counterclockwise rotation by an angle in degrees.
+    pub fn rotate(degrees: f64) -> Matrix {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Matrix::new(cos, sin, -sin, cos, 0.0, 0.0)
+    }
+
+    /// Skew by angles in degrees, of the x axis towards the y axis and of the y axis towards the x axis.
+    pub fn skew(x_degrees: f64, y_degrees: f64) -> Matrix {
+        Matrix::new(
+            1.0,
+            x_degrees.to_radians().tan(),
+            y_degrees.to_radians().tan(),
+            1.0,
+            0.0,
+            0.0,
+        )
+    }
+
+    /// Get the matrix which scales, then skews the y axis towards the x axis, then rotates and then translates, as
+    /// given by a decomposition.
+    pub fn compose(decomposition: &Decomposition) -> Matrix {
+        Matrix::scale(decomposition.scale_x, decomposition.scale_y)
+            .then(&Matrix::skew(0.0, decomposition.skew))
+            .then(&Matrix::rotate(decomposition.rotation))
+            .then(&Matrix::translate(decomposition.translate_x, decomposition.translate_y))
+    }
+
+    /// Split the matrix into a scale, a skew, a rotation and a translation, unless it's degenerate. A reflection
+    /// is given by a negative vertical scale.
+    pub fn decompose(&self) -> Option<Decomposition> {
+        let determinant = self.a * self.d - self.b * self.c;
+        if determinant.abs() < f64::EPSILON {
+            return None;
+        }
+        let scale_x = self.a.hypot(self.b);
+        Some(Decomposition {
+            translate_x: self.e,
+            translate_y: self.f,
+            scale_x,
+            scale_y: determinant / scale_x,
+            rotation: self.b.atan2(self.a).to_degrees(),
+            skew: ((self.a * self.c + self.b * self.d) / determinant).atan().to_degrees(),
+        })
+    }
+
+    /// Get a matrix from an array of six numbers.
+    pub fn from_object(object: &Object) -> Result<Matrix> {
+        Matrix::from_operands(object.as_array()?)
+    }
+
+    /// Get a matrix from six numbers, such as the operands of the `cm` and `Tm` operators.
+    pub fn from_operands(operands: &[Object]) -> Result<Matrix> {
+        match operands
+            .iter()
+            .map(Object::as_float)
+            .collect::<Result<Vec<f64>>>()?
+            .as_slice()
+        {
+            &[a, b, c, d, e, f] => Ok(Matrix::new(a, b, c, d, e, f)),
+            _ => Err(Error::Type),
+        }
+    }
+
+    pub fn to_array(&self) -> [f64; 6] {
+        [self.a, self.b, self.c, self.d, self.e, self.f]
+    }
+
+    /// Get the six numbers as operands, such as for the `cm` operator.
+    pub fn to_operands(&self) -> Vec<Object> {
+        self.to_array().iter().map(|&number| Object::Real(number)).collect()
+    }
+
+    /// Get the matrix which applies this matrix and then the other one, as the other matrix is the current
+    /// transformation matrix when a `cm` operator concatenates this one.
+    pub fn then(&self, other: &Matrix) -> Matrix {
+        Matrix {
+            a: self.a * other.a + self.b * other.c,
+            b: self.a * other.b + self.b * other.d,
+            c: self.c * other.a + self.d * other.c,
+            d: self.c * other.b + self.d * other.d,
+            e: self.e * other.a + self.f * other.c + other.e,
+            f: self.e * other.b + self.f * other.d + other.f,
+        }
+    }
+
+    /// Get the matrix which undoes this one, unless it's degenerate.
+    pub fn invert(&self) -> Option<Matrix> {
+        let determinant = self.a * self.d - self.b * self.c;
+        if determinant.abs() < f64::EPSILON {
+            return None;
+        }
+        Some(Matrix {
+            a: self.d / determinant,
+            b: -self.b / determinant,
+            c: -self.c / determinant,
+            d: self.a / determinant,
+            e: (self.c * self.f - self.d * self.e) / determinant,
+            f: (self.b * self.e - self.a * self.f) / determinant,
+        })
+    }
+
+    pub fn transform_point(&self, point: Point) -> Point {
+        Point {
+            x: self.a * point.x + self.c * point.y + self.e,
+            y: self.b * point.x + self.d * point.y + self.f,
+        }
+    }
+
+    /// Get the bounding box of a transformed rectangle.
+    pub fn transform_rect(&self, rect: &Rectangle) -> Rectangle {
+        let corners = [
+            self.transform_point(Point::new(rect.llx, rect.lly)),
+            self.transform_point(Point::new(rect.urx, rect.lly)),
+            self.transform_point(Point::new(rect.llx, rect.ury)),
+            self.transform_point(Point::new(rect.urx, rect.ury)),
+        ];
+        corners[1..]
+            .iter()
+            .fold(Rectangle::from_points(corners[0], corners[0]), |bbox, &corner| {
+                bbox.union(&Rectangle::from_points(corner, corner))
+            })
+    }
+}
+
+/// Product of matrices, which applies the left matrix and then the right one, like [`Matrix::then`].
+impl Mul for Matrix {
+    type Output = Matrix;
+
+    fn mul(self, other: Matrix) -> Matrix {
+        self.then(&other)
+    }
+}
+
+impl From<[f64; 6]> for Matrix {
+    fn from([a, b, c, d, e, f]: [f64; 6]) -> Self {
+        Matrix::new(a, b, c, d, e, f)
+    }
+}
+
+impl Default for Matrix {
+    fn default() -> Matrix {
+        Matrix::IDENTITY
+    }
+}
+
+impl From<Matrix> for Object {
+    fn from(matrix: Matrix) -> Self {
+        Object::Array(matrix.to_operands())
+    }
+}
+
+/// Parts of a transformation matrix, which is composed by scaling, skewing, rotating and translating in this order.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decomposition {
+    pub translate_x: f64,
+    pub translate_y: f64,
+    pub scale_x: f64,
+    pub scale_y: f64,
+    /// Counterclockwise rotation in degrees.
+    pub rotation: f64,
+    /// Angle in degrees by which the y axis is skewed towards the x axis.
+    pub skew: f64,
+}
+
+#[test]
+fn transform_geometry() {
+    let rect = Rectangle::new(10.0, 20.0, 0.0, 0.0).normalized();
+    assert_eq!(rect, Rectangle::new(0.0, 0.0, 10.0, 20.0));
+    assert_eq!(
+        rect.union(&Rectangle::new(5.0, -5.0, 15.0, 5.0)),
+        Rectangle::new(0.0, -5.0, 15.0, 20.0)
+    );
+
+    let matrix = Matrix::scale(2.0, 2.0).then(&Matrix::translate(100.0, 50.0));
+    assert_eq!(matrix.transform_point(Point::new(1.0, 1.0)), Point::new(102.0, 52.0));
+    let inverse = matrix.invert().unwrap();
+    assert_eq!(inverse.transform_point(Point::new(102.0, 52.0)), Point::new(1.0, 1.0));
+    assert!(Matrix::scale(0.0, 1.0).invert().is_none());
+
+    let rotated = Matrix::rotate(90.0).transform_rect(&rect);
+    assert!((rotated.llx + 20.0).abs() < 1e-9 && (rotated.ury - 10.0).abs() < 1e-9);
+
+    assert_eq!(Matrix::scale(2.0, 2.0) * Matrix::translate(100.0, 50.0), matrix);
+    assert_eq!(Matrix::from(matrix.to_array()), matrix);
+    let composed = Matrix::compose(&Decomposition {
+        translate_x: 10.0,
+        translate_y: -20.0,
+        scale_x: 2.0,
+        scale_y: -0.5,
+        rotation: 30.0,
+        skew: 15.0,
+    });
+    let decomposition = composed.decompose().unwrap();
+    assert!((decomposition.scale_y + 0.5).abs() < 1e-9 && (decomposition.skew - 15.0).abs() < 1e-9);
+    let recomposed = Matrix::compose(&decomposition).to_array();
+    for (number, other) in recomposed.iter().zip(composed.to_array().iter()) {
+        assert!((number - other).abs() < 1e-9);
+    }
+    assert!(Matrix::scale(1.0, 0.0).decompose().is_none());
+
+    let object: Object = matrix.into();
+    assert_eq!(Matrix::from_object(&object).unwrap(), matrix);
+    assert!(Matrix::from_object(&Object::Array(vec![1.into()])).is_err());
+}
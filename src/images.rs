@@ -0,0 +1,957 @@
+#![cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+
+use crate::color::ColorSpace;
+use crate::content::{Content, Operation};
+#[cfg(feature = "ccitt")]
+use crate::filters::ccitt::CcittParams;
+use crate::text::MAX_FORM_DEPTH;
+use crate::{Dictionary, Document, Error, Matrix, Object, ObjectId, Rectangle, Result, Stream};
+use log::warn;
+use std::collections::BTreeMap;
+
+/// Layout of the pixels of an extracted image, which have 8 bits per component.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorType {
+    Gray,
+    GrayAlpha,
+    Rgb,
+    Rgba,
+    Cmyk,
+    CmykAlpha,
+}
+
+impl ColorType {
+    fn new(components: usize, alpha: bool) -> Option<ColorType> {
+        match (components, alpha) {
+            (1, false) => Some(ColorType::Gray),
+            (1, true) => Some(ColorType::GrayAlpha),
+            (3, false) => Some(ColorType::Rgb),
+            (3, true) => Some(ColorType::Rgba),
+            (4, false) => Some(ColorType::Cmyk),
+            (4, true) => Some(ColorType::CmykAlpha),
+            _ => None,
+        }
+    }
+
+    /// Number of components of a pixel, including alpha.
+    pub fn components(self) -> usize {
+        match self {
+            ColorType::Gray => 1,
+            ColorType::GrayAlpha => 2,
+            ColorType::Rgb => 3,
+            ColorType::Rgba | ColorType::Cmyk => 4,
+            ColorType::CmykAlpha => 5,
+        }
+    }
+}
+
+/// Place where a page draws an image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImagePlacement {
+    /// Bounding box in the default user space of the page.
+    pub bbox: Rectangle,
+    /// Horizontal and vertical resolution in pixels per inch.
+    pub dpi: (f64, f64),
+}
+
+/// Image XObject of a page, with its decoded pixels.
+#[derive(Debug, Clone)]
+pub struct ExtractedImage {
+    pub id: ObjectId,
+    pub width: u32,
+    pub height: u32,
+    /// Family of the color space of the image, such as DeviceRGB, ICCBased or Indexed.
+    pub color_space: String,
+    /// Bits per component of the image data, before decoding it to 8 bits per component.
+    pub bits_per_component: u8,
+    pub color_type: ColorType,
+    /// Pixels row by row from the top, with indexed colors looked up and the soft mask or mask as alpha.
+    /// Components of color spaces other than the device, calibrated, ICC based and indexed ones
+    /// are scaled to bytes without conversion.
+    pub pixels: Vec<u8>,
+    /// Places where the page draws the image, which may be more than one or none.
+    pub placements: Vec<ImagePlacement>,
+}
+
+/// Image to insert with `Document::insert_image`.
+#[derive(Debug, Clone)]
+pub enum ImageSource<'a> {
+    /// JPEG file, which is embedded as it is with the DCTDecode filter.
+    Jpeg(&'a [u8]),
+    /// Pixels row by row from the top with 8 bits per component, which are compressed with the Flate filter.
+    /// Alpha is stored as a soft mask.
+    Pixels {
+        width: u32,
+        height: u32,
+        color_type: ColorType,
+        data: &'a [u8],
+    },
+    /// Image XObject, such as one created by `xobject::image`.
+    XObject(Stream),
+}
+
+/// Options of `Document::insert_image`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ImageOptions {
+    /// Scale the image uniformly to fit the rectangle and center it, rather than stretching it to fill it.
+    pub keep_aspect_ratio: bool,
+    /// Whether viewers should smooth the image when scaling it up.
+    pub interpolate: bool,
+}
+
+/// Image samples decoded to 8 bits per component, before applying masks.
+struct Samples {
+    width: usize,
+    height: usize,
+    color_space: String,
+    bits_per_component: u8,
+    components: usize,
+    data: Vec<u8>,
+    /// Samples as stored in the image data, which color key masks apply to.
+    raw: Vec<u16>,
+}
+
+/// Image data after applying its filters.
+enum ImageData {
+    /// Samples as described by the image dictionary.
+    Samples(Vec<u8>),
+    /// Pixels with 8 bits per component decoded from JPEG or JPEG 2000 data, with the color space given by
+    /// JPEG 2000 data.
+    Pixels(Vec<u8>, Option<(String, ColorSpace)>),
+}
+
+impl Document {
+    /// Extract the images of a page, including those of its form XObjects, decoding image data
+    /// compressed with the Flate, LZW, run-length, ASCII, DCT, JPX, CCITT fax or JBIG2 filters. Decoding DCT data
+    /// needs the `images` feature, decoding CCITT fax data the `ccitt` feature, decoding JBIG2 data the `jbig2`
+    /// feature and decoding JPX data a decoder registered with `filters::jpx::set_decoder`.
+    ///
+    /// Images which cannot be decoded are skipped with a warning.
+    pub fn extract_images(&self, page_number: u32) -> Result<Vec<ExtractedImage>> {
+        let page_id = *self
+            .get_pages()
+            .get(&page_number)
+            .ok_or(Error::PageNumberNotFound(page_number))?;
+        let resources = self
+            .get_page_attribute(page_id, b"Resources")
+            .and_then(|resources| resources.as_dict().ok());
+        let mut placements = BTreeMap::new();
+        self.collect_image_placements(
+            &self.get_page_content(page_id)?,
+            resources,
+            Matrix::IDENTITY,
+            0,
+            &mut placements,
+        )?;
+
+        let mut images = Vec::new();
+        for (id, matrices) in placements {
+            let samples = match self.get_object(id).and_then(Object::as_stream) {
+                Ok(image) => self.decode_image(image),
+                Err(error) => Err(error),
+            };
+            match samples {
+                Ok((samples, alpha, color_type)) => {
+                    let placements = matrices
+                        .iter()
+                        .map(|matrix| image_placement(matrix, samples.width, samples.height))
+                        .collect();
+                    images.push(ExtractedImage {
+                        id,
+                        width: samples.width as u32,
+                        height: samples.height as u32,
+                        color_space: samples.color_space,
+                        bits_per_component: samples.bits_per_component,
+                        color_type,
+                        pixels: match alpha {
+                            Some(alpha) => samples
+                                .data
+                                .chunks(samples.components.max(1))
+                                .zip(alpha)
+                                .flat_map(|(pixel, alpha)| pixel.iter().copied().chain(Some(alpha)))
+                                .collect(),
+                            None => samples.data,
+                        },
+                        placements,
+                    });
+                }
+                Err(error) => warn!("Could not decode image {:?}: {}", id, error),
+            }
+        }
+        Ok(images)
+    }
+
+    /// Draw an image in a rectangle of a page, over the existing content, and get the id of its image XObject.
+    pub fn insert_image(
+        &mut self, page_id: ObjectId, image: ImageSource, rect: Rectangle, options: &ImageOptions,
+    ) -> Result<ObjectId> {
+        let mut image = match image {
+            ImageSource::Jpeg(data) => jpeg_image(data)?,
+            ImageSource::Pixels {
+                width,
+                height,
+                color_type,
+                data,
+            } => {
+                let components = color_type.components();
+                if data.len() != width as usize * height as usize * components {
+                    return Err(Error::Type);
+                }
+                let (color_space, color_components) = match color_type {
+                    ColorType::Gray | ColorType::GrayAlpha => ("DeviceGray", 1),
+                    ColorType::Rgb | ColorType::Rgba => ("DeviceRGB", 3),
+                    ColorType::Cmyk | ColorType::CmykAlpha => ("DeviceCMYK", 4),
+                };
+                let image_dict = |color_space: &str| {
+                    dictionary! {
+                        "Type" => "XObject",
+                        "Subtype" => "Image",
+                        "Width" => width,
+                        "Height" => height,
+                        "ColorSpace" => color_space,
+                        "BitsPerComponent" => 8,
+                    }
+                };
+                let mut image = if components == color_components {
+                    Stream::new(image_dict(color_space), data.to_vec())
+                } else {
+                    let pixels = data.chunks(components);
+                    let colors = pixels
+                        .clone()
+                        .flat_map(|pixel| pixel[..color_components].to_vec())
+                        .collect();
+                    let mut mask = Stream::new(
+                        image_dict("DeviceGray"),
+                        pixels.map(|pixel| pixel[color_components]).collect(),
+                    );
+                    // Ignore any compression error.
+                    let _ = mask.compress();
+                    let mut image = Stream::new(image_dict(color_space), colors);
+                    image.dict.set("SMask", self.add_object(mask));
+                    image
+                };
+                // Ignore any compression error.
+                let _ = image.compress();
+                image
+            }
+            ImageSource::XObject(image) => image,
+        };
+        if options.interpolate {
+            image.dict.set("Interpolate", true);
+        }
+        let (width, height) = (
+            image.dict.get(b"Width").and_then(Object::as_float)?,
+            image.dict.get(b"Height").and_then(Object::as_float)?,
+        );
+        let image_id = self.add_object(image);
+        let image_name = format!("Im{}", image_id.0);
+        self.add_xobject(page_id, image_name.as_bytes(), image_id)?;
+
+        let (mut scale_x, mut scale_y) = (rect.width(), rect.height());
+        let (mut x, mut y) = (rect.llx, rect.lly);
+        if options.keep_aspect_ratio && width > 0.0 && height > 0.0 {
+            let scale = (rect.width() / width).min(rect.height() / height);
+            scale_x = width * scale;
+            scale_y = height * scale;
+            x += (rect.width() - scale_x) / 2.0;
+            y += (rect.height() - scale_y) / 2.0;
+        }
+        let content = Content {
+            operations: vec![
+                Operation::new("q", vec![]),
+                Operation::new(
+                    "cm",
+                    (Matrix::scale(scale_x, scale_y) * Matrix::translate(x, y)).to_operands(),
+                ),
+                Operation::new("Do", vec![Object::Name(image_name.into_bytes())]),
+                Operation::new("Q", vec![]),
+            ],
+        };
+        self.append_page_content(page_id, content.encode()?)?;
+        Ok(image_id)
+    }
+
+    /// Collect the image XObjects of resources and the transformation matrices with which content draws them.
+    fn collect_image_placements(
+        &self, content: &[u8], resources: Option<&Dictionary>, ctm: Matrix, depth: usize,
+        placements: &mut BTreeMap<ObjectId, Vec<Matrix>>,
+    ) -> Result<()> {
+        let xobjects = resources.and_then(|resources| self.resource_xobjects(resources));
+        let get_xobject = |name: &[u8]| {
+            let reference = xobjects?.get(name).ok()?.as_reference().ok()?;
+            let xobject = self.get_object(reference).and_then(Object::as_stream).ok()?;
+            Some((
+                reference,
+                xobject,
+                xobject.dict.get(b"Subtype").and_then(Object::as_name).ok()?,
+            ))
+        };
+        if let Some(xobjects) = xobjects {
+            for (name, _) in xobjects.iter() {
+                if let Some((id, _, b"Image")) = get_xobject(name) {
+                    placements.entry(id).or_default();
+                }
+            }
+        }
+
+        let content = Content::decode(content)?;
+        let mut ctm = ctm;
+        let mut stack = Vec::new();
+        for operation in &content.operations {
+            match operation.operator.as_str() {
+                "q" => stack.push(ctm),
+                "Q" => ctm = stack.pop().unwrap_or(ctm),
+                "cm" => {
+                    if let Ok(matrix) = Matrix::from_operands(&operation.operands) {
+                        ctm = matrix.then(&ctm);
+                    }
+                }
+                "Do" => {
+                    let name = operation.operands.first().and_then(|name| name.as_name().ok());
+                    match name.and_then(&get_xobject) {
+                        Some((id, _, b"Image")) => placements.entry(id).or_default().push(ctm),
+                        Some((_, form, b"Form")) if depth < MAX_FORM_DEPTH => {
+                            let matrix = form
+                                .dict
+                                .get(b"Matrix")
+                                .and_then(Matrix::from_object)
+                                .unwrap_or(Matrix::IDENTITY);
+                            let form_resources = form
+                                .dict
+                                .get(b"Resources")
+                                .and_then(|resources| self.dereference(resources))
+                                .and_then(|(_, resources)| resources.as_dict())
+                                .ok()
+                                .or(resources);
+                            let data = form.decompressed_content().unwrap_or_else(|_| form.content.clone());
+                            let form_ctm = matrix.then(&ctm);
+                            self.collect_image_placements(&data, form_resources, form_ctm, depth + 1, placements)?;
+                        }
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Create a copy of an image with the pixels in areas of the unit square of image space painted black, which
+    /// removes them rather than covering them. The copy has 8 bits per component and is compressed with the Flate
+    /// filter. The soft mask or mask of the image is turned into a soft mask which is opaque in these areas,
+    /// returned separately to be added as an indirect object.
+    ///
+    /// Image masks aren't supported.
+    pub(crate) fn redact_image(&self, image: &Stream, areas: &[Rectangle]) -> Result<(Stream, Option<Stream>)> {
+        use flate2::write::ZlibEncoder;
+        use flate2::Compression;
+        use std::io::Write;
+
+        let (mut samples, mut alpha, _) = self.decode_image(image)?;
+        if samples.color_space == "ImageMask" {
+            return Err(Error::Type);
+        }
+        let (width, height, components) = (samples.width, samples.height, samples.components);
+        let black: Vec<u8> = match components {
+            4 => vec![0, 0, 0, 255],
+            _ => vec![0; components],
+        };
+        for area in areas {
+            // Rows of image data start from the top of the image.
+            let columns =
+                (area.llx * width as f64).floor().max(0.0) as usize..(area.urx * width as f64).ceil() as usize;
+            let rows = ((1.0 - area.ury) * height as f64).floor().max(0.0) as usize
+                ..((1.0 - area.lly) * height as f64).ceil() as usize;
+            for row in rows.start..rows.end.min(height) {
+                for column in columns.start..columns.end.min(width) {
+                    let pixel = row * width + column;
+                    if let Some(data) = samples.data.get_mut(pixel * components..(pixel + 1) * components) {
+                        data.copy_from_slice(&black);
+                    }
+                    if let Some(value) = alpha.as_mut().and_then(|alpha| alpha.get_mut(pixel)) {
+                        *value = 255;
+                    }
+                }
+            }
+        }
+
+        let compress = |data: &[u8]| -> Result<Vec<u8>> {
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(data)?;
+            Ok(encoder.finish()?)
+        };
+        // Samples of indexed images are the colors of the base color space.
+        let color_space = match image.dict.get(b"ColorSpace") {
+            Ok(color_space) if samples.color_space != "Indexed" => color_space.clone(),
+            _ => match components {
+                1 => "DeviceGray".into(),
+                4 => "DeviceCMYK".into(),
+                _ => "DeviceRGB".into(),
+            },
+        };
+        let redacted = Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Image",
+                "Width" => width as i64,
+                "Height" => height as i64,
+                "ColorSpace" => color_space,
+                "BitsPerComponent" => 8,
+                "Filter" => "FlateDecode",
+            },
+            compress(&samples.data)?,
+        );
+        let mask = match alpha {
+            Some(alpha) => Some(Stream::new(
+                dictionary! {
+                    "Type" => "XObject",
+                    "Subtype" => "Image",
+                    "Width" => width as i64,
+                    "Height" => height as i64,
+                    "ColorSpace" => "DeviceGray",
+                    "BitsPerComponent" => 8,
+                    "Filter" => "FlateDecode",
+                },
+                compress(&alpha)?,
+            )),
+            None => None,
+        };
+        Ok((redacted, mask))
+    }
+
+    /// Decode the samples of an image, and the alpha values of its soft mask or mask, if any.
+    fn decode_image(&self, image: &Stream) -> Result<(Samples, Option<Vec<u8>>, ColorType)> {
+        let samples = self.decode_samples(image)?;
+        let resolve = |key: &[u8]| {
+            image
+                .dict
+                .get(key)
+                .and_then(|object| self.dereference(object))
+                .map(|(_, object)| object)
+        };
+        let alpha = if let Ok(Object::Stream(ref mask)) = resolve(b"SMask") {
+            Some(resample_alpha(
+                &self.decode_samples(mask)?,
+                samples.width,
+                samples.height,
+                false,
+            ))
+        } else if let Ok(Object::Stream(ref mask)) = resolve(b"Mask") {
+            Some(resample_alpha(
+                &self.decode_samples(mask)?,
+                samples.width,
+                samples.height,
+                true,
+            ))
+        } else if let Ok(Object::Array(ref ranges)) = resolve(b"Mask") {
+            // Color key masking, which masks out pixels with all components in the given ranges.
+            let ranges: Vec<i64> = ranges.iter().filter_map(|range| range.as_i64().ok()).collect();
+            let alpha = samples
+                .raw
+                .chunks(samples.components)
+                .map(|pixel| {
+                    let masked = pixel.iter().enumerate().all(|(index, &value)| {
+                        let (min, max) = (ranges.get(2 * index), ranges.get(2 * index + 1));
+                        matches!((min, max), (Some(&min), Some(&max)) if (min..=max).contains(&i64::from(value)))
+                    });
+                    if masked {
+                        0
+                    } else {
+                        255
+                    }
+                })
+                .collect();
+            Some(alpha)
+        } else {
+            None
+        };
+        let color_type = ColorType::new(samples.components, alpha.is_some()).ok_or(Error::Type)?;
+        Ok((samples, alpha, color_type))
+    }
+
+    /// Decode the image data and color space of an image into samples with 8 bits per component.
+    fn decode_samples(&self, image: &Stream) -> Result<Samples> {
+        let dict = &image.dict;
+        let resolve = |key: &[u8]| {
+            dict.get(key)
+                .and_then(|object| self.dereference(object))
+                .map(|(_, o)| o)
+        };
+        let integer = |key: &[u8]| resolve(key).and_then(Object::as_i64);
+        let width = integer(b"Width")?.max(0) as usize;
+        let height = integer(b"Height")?.max(0) as usize;
+        let image_mask = matches!(resolve(b"ImageMask"), Ok(Object::Boolean(true)));
+
+        let (data, pixels, data_color_space) = match self.decode_image_data(image, height)? {
+            ImageData::Samples(data) => (data, false, None),
+            ImageData::Pixels(data, color_space) => (data, true, color_space),
+        };
+        let (color_space, space) = match (resolve(b"ColorSpace"), data_color_space) {
+            _ if image_mask => ("ImageMask".to_owned(), ColorSpace::DeviceGray),
+            (Ok(color_space), _) => self.image_color_space(color_space)?,
+            // JPEG 2000 data gives the color space when the image dictionary doesn't.
+            (Err(_), Some(color_space)) => color_space,
+            (Err(_), None) => return Err(Error::Type),
+        };
+        let bits_per_component = if image_mask {
+            1
+        } else if pixels {
+            8
+        } else {
+            integer(b"BitsPerComponent")?
+        };
+        if ![1, 2, 4, 8, 16].contains(&bits_per_component) {
+            return Err(Error::Type);
+        }
+
+        let stored_components = space.components();
+        if stored_components == 0 {
+            return Err(Error::Type);
+        }
+        let raw = unpack_samples(&data, width, height, stored_components, bits_per_component as usize);
+        let max = ((1u32 << bits_per_component) - 1) as f64;
+        let decode: Vec<f64> = resolve(b"Decode")
+            .and_then(Object::as_array)
+            .map(|decode| decode.iter().filter_map(|number| number.as_float().ok()).collect())
+            .unwrap_or_default();
+        let range =
+            |component: usize, default_max: f64| match (decode.get(2 * component), decode.get(2 * component + 1)) {
+                (Some(&min), Some(&max)) => (min, max),
+                _ => (0.0, default_max),
+            };
+
+        let (components, data) = match space {
+            ColorSpace::Indexed { base, hival, lookup } => {
+                let components = base.components();
+                let (min, max_index) = range(0, max);
+                let data = raw
+                    .iter()
+                    .flat_map(|&value| {
+                        let index = (min + f64::from(value) * (max_index - min) / max).round();
+                        let index = index.clamp(0.0, hival as f64) as usize;
+                        (0..components).map(move |component| index * components + component)
+                    })
+                    .map(|offset| lookup.get(offset).copied().unwrap_or(0))
+                    .collect();
+                (components, data)
+            }
+            _ => {
+                let components = stored_components;
+                let ranges: Vec<(f64, f64)> = (0..components).map(|component| range(component, 1.0)).collect();
+                let data = raw
+                    .iter()
+                    .enumerate()
+                    .map(|(index, &value)| {
+                        let (min, max_value) = ranges[index % components];
+                        let value = min + f64::from(value) * (max_value - min) / max;
+                        (value * 255.0).round().clamp(0.0, 255.0) as u8
+                    })
+                    .collect();
+                (components, data)
+            }
+        };
+        Ok(Samples {
+            width,
+            height,
+            color_space,
+            bits_per_component: bits_per_component as u8,
+            components,
+            data,
+            raw,
+        })
+    }
+
+    /// Apply the filters of an image.
+    fn decode_image_data(
+        &self, image: &Stream, #[cfg_attr(not(feature = "ccitt"), allow(unused_variables))] height: usize,
+    ) -> Result<ImageData> {
+        let filters = match image.dict.get(b"Filter").and_then(|filter| self.dereference(filter)) {
+            Ok((_, Object::Name(filter))) => vec![filter.clone()],
+            Ok((_, Object::Array(filters))) => filters
+                .iter()
+                .filter_map(|filter| filter.as_name().ok().map(<[u8]>::to_vec))
+                .collect(),
+            _ => vec![],
+        };
+        let params = image
+            .dict
+            .get(b"DecodeParms")
+            .and_then(|params| self.dereference(params))
+            .map(|(_, params)| params);
+        let mut data = image.content.clone();
+        for (index, filter) in filters.iter().enumerate() {
+            let params = match params {
+                Ok(Object::Array(params)) => params
+                    .get(index)
+                    .and_then(|params| self.dereference(params).ok())
+                    .and_then(|(_, params)| params.as_dict().ok()),
+                Ok(params) => params.as_dict().ok(),
+                Err(_) => None,
+            };
+            data = match filter.as_slice() {
+                b"FlateDecode" => Stream::decompress_zlib(&data, params)?,
+                b"LZWDecode" => Stream::decompress_lzw(&data, params)?,
+                b"RunLengthDecode" => crate::filters::run_length::decode(&data),
+                b"ASCIIHexDecode" => crate::filters::ascii_hex::decode(&data),
+                b"ASCII85Decode" => crate::filters::ascii85::decode(&data),
+                #[cfg(feature = "images")]
+                b"DCTDecode" => return Ok(ImageData::Pixels(crate::filters::dct::decode(&data)?, None)),
+                b"JPXDecode" => {
+                    let info = crate::filters::jpx::info(&data)?;
+                    let decoded = crate::filters::jpx::decode(&data)?;
+                    let space = info.color_space.or(match decoded.components {
+                        1 => Some(ColorSpace::DeviceGray),
+                        3 => Some(ColorSpace::DeviceRGB),
+                        4 => Some(ColorSpace::DeviceCMYK),
+                        _ => None,
+                    });
+                    let color_space = space.map(|space| {
+                        let family = match space {
+                            ColorSpace::DeviceGray => "DeviceGray",
+                            ColorSpace::DeviceCMYK => "DeviceCMYK",
+                            ColorSpace::ICCBased { .. } => "ICCBased",
+                            _ => "DeviceRGB",
+                        };
+                        (family.to_owned(), space)
+                    });
+                    return Ok(ImageData::Pixels(decoded.pixels, color_space));
+                }
+                #[cfg(feature = "jbig2")]
+                b"JBIG2Decode" => {
+                    let globals = params
+                        .and_then(|params| params.get(b"JBIG2Globals").ok())
+                        .and_then(|globals| self.dereference(globals).ok())
+                        .and_then(|(_, globals)| globals.as_stream().ok())
+                        .map(|globals| {
+                            globals
+                                .decompressed_content()
+                                .unwrap_or_else(|_| globals.content.clone())
+                        });
+                    crate::filters::jbig2::decode(&data, globals.as_deref())?
+                }
+                #[cfg(feature = "ccitt")]
+                b"CCITTFaxDecode" => crate::filters::ccitt::decode(&data, &CcittParams::new(params, Some(height)))?,
+                _ => return Err(Error::Type),
+            };
+        }
+        Ok(ImageData::Samples(data))
+    }
+
+    /// Get the family name and the color space of an image.
+    fn image_color_space(&self, color_space: &Object) -> Result<(String, ColorSpace)> {
+        let family = match color_space {
+            Object::Name(name) => name,
+            Object::Array(array) if !array.is_empty() => array[0].as_name()?,
+            _ => return Err(Error::Type),
+        };
+        Ok((
+            String::from_utf8_lossy(family).into_owned(),
+            ColorSpace::from_object(self, color_space)?,
+        ))
+    }
+}
+
+/// Create an image XObject embedding a JPEG file.
+fn jpeg_image(data: &[u8]) -> Result<Stream> {
+    let info = crate::filters::dct::info(data)?;
+    let color_space = match info.components {
+        1 => "DeviceGray",
+        3 => "DeviceRGB",
+        4 => "DeviceCMYK",
+        _ => return Err(Error::Type),
+    };
+    let mut dict = dictionary! {
+        "Type" => "XObject",
+        "Subtype" => "Image",
+        "Width" => info.width,
+        "Height" => info.height,
+        "ColorSpace" => color_space,
+        "BitsPerComponent" => info.bits_per_component,
+        "Filter" => "DCTDecode",
+    };
+    // CMYK files written by Adobe applications store inverted components.
+    if info.components == 4 && info.adobe_transform.is_some() {
+        dict.set(
+            "Decode",
+            vec![
+                1.into(),
+                0.into(),
+                1.into(),
+                0.into(),
+                1.into(),
+                0.into(),
+                1.into(),
+                0.into(),
+            ],
+        );
+    }
+    let mut image = Stream::new(dict, data.to_vec());
+    image.allows_compression = false;
+    Ok(image)
+}
+
+/// Bounding box and resolution of an image drawn with the given transformation matrix,
+/// which maps the unit square to the image.
+fn image_placement(matrix: &Matrix, width: usize, height: usize) -> ImagePlacement {
+    // Lengths of the image sides in inches.
+    let horizontal = matrix.a.hypot(matrix.b) / 72.0;
+    let vertical = matrix.c.hypot(matrix.d) / 72.0;
+    let dpi = |pixels: usize, inches: f64| if inches > 0.0 { pixels as f64 / inches } else { 0.0 };
+    ImagePlacement {
+        bbox: matrix.transform_rect(&Rectangle::new(0.0, 0.0, 1.0, 1.0)),
+        dpi: (dpi(width, horizontal), dpi(height, vertical)),
+    }
+}
+
+/// Split image data into samples, each row of which starts at a byte boundary.
+fn unpack_samples(data: &[u8], width: usize, height: usize, components: usize, bits: usize) -> Vec<u16> {
+    let row_bytes = (width * components * bits).div_ceil(8);
+    let mut samples = Vec::with_capacity(width * height * components);
+    for row in 0..height {
+        let row = data.get(row * row_bytes..).unwrap_or(&[]);
+        let byte = |index: usize| u16::from(row.get(index).copied().unwrap_or(0));
+        for index in 0..width * components {
+            let sample = match bits {
+                16 => byte(2 * index) << 8 | byte(2 * index + 1),
+                8 => byte(index),
+                _ => {
+                    let bit = index * bits;
+                    byte(bit / 8) >> (8 - bits - bit % 8) & ((1 << bits) - 1)
+                }
+            };
+            samples.push(sample);
+        }
+    }
+    samples
+}
+
+/// Get alpha values from the samples of a soft mask, or of a mask in which samples of 1 mask out pixels,
+/// scaling it to the size of the image.
+fn resample_alpha(mask: &Samples, width: usize, height: usize, invert: bool) -> Vec<u8> {
+    let mut alpha = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            let (mask_x, mask_y) = (x * mask.width / width.max(1), y * mask.height / height.max(1));
+            let value = mask
+                .data
+                .get((mask_y * mask.width + mask_x) * mask.components)
+                .copied()
+                .unwrap_or(255);
+            alpha.push(if invert { 255 - value } else { value });
+        }
+    }
+    alpha
+}
+
+#[cfg(feature = "ccitt")]
+#[test]
+fn extract_page_images() {
+    use crate::content::Operation;
+    use crate::test_support::create_document;
+    use fax::encoder::Encoder;
+    use fax::{Color, VecWriter};
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    let mask_id = doc.add_object(Stream::new(
+        dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Image",
+            "Width" => 2,
+            "Height" => 1,
+            "ColorSpace" => "DeviceGray",
+            "BitsPerComponent" => 8,
+        },
+        vec![255, 128],
+    ));
+    let mut indexed = Stream::new(
+        dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Image",
+            "Width" => 2,
+            "Height" => 1,
+            "ColorSpace" => vec![
+                "Indexed".into(),
+                "DeviceRGB".into(),
+                1.into(),
+                Object::String(vec![255, 0, 0, 0, 0, 255], crate::StringFormat::Hexadecimal),
+            ],
+            "BitsPerComponent" => 1,
+            "SMask" => mask_id,
+        },
+        vec![0b0100_0000],
+    );
+    indexed.compress().unwrap();
+    let indexed_id = doc.add_object(indexed);
+
+    let mut encoder = Encoder::new(VecWriter::new());
+    for _ in 0..2 {
+        let line = (0..8).map(|x| if x < 4 { Color::Black } else { Color::White });
+        encoder.encode_line(line, 8).unwrap();
+    }
+    let fax_data = encoder.finish().unwrap().finish();
+    let fax_id = doc.add_object(Stream::new(
+        dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Image",
+            "Width" => 8,
+            "Height" => 2,
+            "ColorSpace" => "DeviceGray",
+            "BitsPerComponent" => 1,
+            "Filter" => "CCITTFaxDecode",
+            "DecodeParms" => dictionary! { "K" => -1, "Columns" => 8, "Rows" => 2 },
+        },
+        fax_data,
+    ));
+    let jpeg_id = doc.add_object(Stream::new(
+        dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Image",
+            "Width" => 800,
+            "Height" => 450,
+            "ColorSpace" => "DeviceRGB",
+            "BitsPerComponent" => 8,
+            "Filter" => "DCTDecode",
+        },
+        std::fs::read("assets/pdf_icon.jpg").unwrap(),
+    ));
+    doc.add_xobject(page_id, "Im1", indexed_id).unwrap();
+    doc.add_xobject(page_id, "Im2", fax_id).unwrap();
+    doc.add_xobject(page_id, "Im3", jpeg_id).unwrap();
+    let content = Content {
+        operations: vec![
+            Operation::new("q", vec![]),
+            Operation::new(
+                "cm",
+                vec![144.into(), 0.into(), 0.into(), 72.into(), 100.into(), 100.into()],
+            ),
+            Operation::new("Do", vec!["Im1".into()]),
+            Operation::new("Q", vec![]),
+            Operation::new(
+                "cm",
+                vec![0.into(), 36.into(), (-36).into(), 0.into(), 300.into(), 300.into()],
+            ),
+            Operation::new("Do", vec!["Im2".into()]),
+        ],
+    };
+    doc.append_page_content(page_id, content.encode().unwrap()).unwrap();
+
+    let images = doc.extract_images(1).unwrap();
+    assert_eq!(images.len(), if cfg!(feature = "images") { 3 } else { 2 });
+    let indexed = &images[0];
+    assert_eq!((indexed.id, indexed.width, indexed.height), (indexed_id, 2, 1));
+    assert_eq!(indexed.color_space, "Indexed");
+    assert_eq!(indexed.color_type, ColorType::Rgba);
+    assert_eq!(indexed.pixels, [255, 0, 0, 255, 0, 0, 255, 128]);
+    assert_eq!(indexed.placements.len(), 1);
+    assert_eq!(indexed.placements[0].bbox, Rectangle::new(100.0, 100.0, 244.0, 172.0));
+    assert_eq!(indexed.placements[0].dpi, (1.0, 1.0));
+
+    let fax = &images[1];
+    assert_eq!(fax.color_type, ColorType::Gray);
+    assert_eq!(&fax.pixels[..8], [0, 0, 0, 0, 255, 255, 255, 255]);
+    assert_eq!(fax.placements[0].bbox, Rectangle::new(264.0, 300.0, 300.0, 336.0));
+    assert_eq!(fax.placements[0].dpi, (16.0, 4.0));
+
+    #[cfg(feature = "images")]
+    {
+        let jpeg = &images[2];
+        assert_eq!(jpeg.color_type, ColorType::Rgb);
+        assert_eq!(jpeg.pixels.len(), 800 * 450 * 3);
+        assert!(jpeg.placements.is_empty());
+    }
+}
+
+#[test]
+fn extract_jpx_images() {
+    use crate::filters::jpx::{self, JpxImage};
+    use crate::test_support::create_document;
+
+    let jp2_box = |kind: &[u8], contents: &[u8]| {
+        let mut jp2_box = (contents.len() as u32 + 8).to_be_bytes().to_vec();
+        jp2_box.extend_from_slice(kind);
+        jp2_box.extend_from_slice(contents);
+        jp2_box
+    };
+    let ihdr = [0, 0, 0, 1, 0, 0, 0, 2, 0, 3, 7, 7, 0, 0];
+    let header = [jp2_box(b"ihdr", &ihdr), jp2_box(b"colr", &[1, 0, 0, 0, 0, 0, 16])].concat();
+    let jp2 = [jp2_box(b"jP  ", &[0x0D, 0x0A, 0x87, 0x0A]), jp2_box(b"jp2h", &header)].concat();
+    jpx::set_decoder(|data: &[u8]| {
+        let info = jpx::info(data)?;
+        Ok(JpxImage {
+            width: info.width,
+            height: info.height,
+            components: usize::from(info.components),
+            pixels: vec![255, 0, 0, 0, 0, 255],
+        })
+    });
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    let image_id = doc.add_object(Stream::new(
+        dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Image",
+            "Width" => 2,
+            "Height" => 1,
+            "Filter" => "JPXDecode",
+        },
+        jp2,
+    ));
+    doc.add_xobject(page_id, "Im1", image_id).unwrap();
+    let images = doc.extract_images(1).unwrap();
+    assert_eq!(images.len(), 1);
+    assert_eq!(images[0].color_space, "DeviceRGB");
+    assert_eq!(images[0].color_type, ColorType::Rgb);
+    assert_eq!(images[0].pixels, [255, 0, 0, 0, 0, 255]);
+}
+
+#[test]
+fn insert_page_images() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    let jpeg = std::fs::read("assets/pdf_icon.jpg").unwrap();
+    let options = ImageOptions {
+        keep_aspect_ratio: true,
+        ..ImageOptions::default()
+    };
+    let rect = Rectangle::new(100.0, 100.0, 500.0, 500.0);
+    let jpeg_id = doc
+        .insert_image(page_id, ImageSource::Jpeg(&jpeg), rect, &options)
+        .unwrap();
+    let pixels = ImageSource::Pixels {
+        width: 2,
+        height: 1,
+        color_type: ColorType::GrayAlpha,
+        data: &[0, 255, 255, 128],
+    };
+    assert!(doc
+        .insert_image(page_id, pixels.clone(), rect, &ImageOptions::default())
+        .is_ok());
+    assert_eq!(
+        doc.get_object(jpeg_id).and_then(Object::as_stream).unwrap().content,
+        jpeg
+    );
+
+    let images = doc.extract_images(1).unwrap();
+    #[cfg(feature = "images")]
+    {
+        assert_eq!(images.len(), 2);
+        assert_eq!((images[0].width, images[0].height), (800, 450));
+        assert_eq!(images[0].placements[0].bbox, Rectangle::new(100.0, 187.5, 500.0, 412.5));
+    }
+    let gray = images.last().unwrap();
+    assert_eq!(gray.color_type, ColorType::GrayAlpha);
+    assert_eq!(gray.pixels, [0, 255, 255, 128]);
+    assert_eq!(gray.placements[0].bbox, rect);
+
+    let pixels = ImageSource::Pixels {
+        width: 2,
+        height: 2,
+        color_type: ColorType::Rgb,
+        data: &[0; 6],
+    };
+    assert!(doc.insert_image(page_id, pixels, rect, &options).is_err());
+}
@@ -0,0 +1,373 @@
+use crate::content::{Content, Operation};
+use crate::{Dictionary, Document, Error, Matrix, Object, ObjectId, Rectangle, Result, Stream};
+
+/// Order in which pages fill the cells of a sheet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NupOrder {
+    /// Rows from top to bottom, each filled from left to right.
+    LeftToRight,
+    /// Rows from top to bottom, each filled from right to left, for right-to-left scripts.
+    RightToLeft,
+    /// Columns from left to right, each filled from top to bottom.
+    TopToBottom,
+}
+
+/// Layout of pages on the sheets made by `Document::impose_nup`.
+#[derive(Debug, Clone)]
+pub struct NupOptions {
+    /// Width and height of the sheets, or the size of the first page in the orientation fitting the grid.
+    pub sheet_size: Option<(f64, f64)>,
+    /// Space around the grid of pages.
+    pub margin: f64,
+    /// Space between pages.
+    pub gutter: f64,
+    /// Whether pages are scaled to fit their cells, keeping their aspect ratio. Otherwise pages are drawn at
+    /// their size and clipped to their cells.
+    pub scale_to_fit: bool,
+    pub order: NupOrder,
+}
+
+impl Default for NupOptions {
+    fn default() -> Self {
+        NupOptions {
+            sheet_size: None,
+            margin: 0.0,
+            gutter: 0.0,
+            scale_to_fit: true,
+            order: NupOrder::LeftToRight,
+        }
+    }
+}
+
+/// Layout of the sheets made by `Document::impose_booklet`, with two pages side by side.
+#[derive(Debug, Clone)]
+pub struct BookletOptions {
+    /// Width and height of the sheets, or twice the width of the first page by its height.
+    pub sheet_size: Option<(f64, f64)>,
+    /// Space around the pages.
+    pub margin: f64,
+    /// Space between the two pages, around the fold.
+    pub gutter: f64,
+    /// Whether pages are scaled to fit their half of the sheet, keeping their aspect ratio.
+    pub scale_to_fit: bool,
+    /// Whether blank pages are added at the end to reach a multiple of four pages, so that the sheets can be
+    /// printed on both sides and folded. Otherwise only a blank page to reach an even number is added.
+    pub add_blank_pages: bool,
+}
+
+impl Default for BookletOptions {
+    fn default() -> Self {
+        BookletOptions {
+            sheet_size: None,
+            margin: 0.0,
+            gutter: 0.0,
+            scale_to_fit: true,
+            add_blank_pages: true,
+        }
+    }
+}
+
+/// Page drawn as a form XObject, with its size as displayed.
+#[derive(Debug, Clone, Copy)]
+struct PageForm {
+    id: ObjectId,
+    width: f64,
+    height: f64,
+}
+
+impl Document {
+    /// Replace the pages with sheets showing `n` pages each, in a grid of two columns for 2-up and 4-up, three
+    /// columns for 6-up and 9-up, and four columns for 8-up and 16-up.
+    ///
+    /// Each page becomes a form XObject drawing its content at the size of its crop box, rotated by /Rotate.
+    /// Annotations of the pages aren't kept. Objects only used by the original pages remain until
+    /// `prune_objects` is called.
+    pub fn impose_nup(&mut self, n: u32, options: &NupOptions) -> Result<()> {
+        if n == 0 {
+            return Err(Error::Syntax("Number of pages per sheet must be positive".to_string()));
+        }
+        let (columns, rows) = match n {
+            2 => (2, 1),
+            6 => (3, 2),
+            8 => (4, 2),
+            _ => {
+                let columns = (f64::from(n).sqrt().ceil() as u32).max(1);
+                (columns, n.div_ceil(columns))
+            }
+        };
+        let forms = self.page_forms()?;
+        let first = match forms.first() {
+            Some(first) => *first,
+            None => return Ok(()),
+        };
+        let (width, height) = options.sheet_size.unwrap_or_else(|| {
+            let (short, long) = (first.width.min(first.height), first.width.max(first.height));
+            if columns > rows {
+                (long, short)
+            } else {
+                (short, long)
+            }
+        });
+
+        let cell_width = (width - 2.0 * options.margin - f64::from(columns - 1) * options.gutter) / f64::from(columns);
+        let cell_height = (height - 2.0 * options.margin - f64::from(rows - 1) * options.gutter) / f64::from(rows);
+        let cells: Vec<Rectangle> = (0..n)
+            .map(|index| {
+                let (column, row) = match options.order {
+                    NupOrder::LeftToRight => (index % columns, index / columns),
+                    NupOrder::RightToLeft => (columns - 1 - index % columns, index / columns),
+                    NupOrder::TopToBottom => (index / rows, index % rows),
+                };
+                let llx = options.margin + f64::from(column) * (cell_width + options.gutter);
+                let ury = height - options.margin - f64::from(row) * (cell_height + options.gutter);
+                Rectangle::new(llx, ury - cell_height, llx + cell_width, ury)
+            })
+            .collect();
+
+        let mut sheets = Vec::new();
+        for pages in forms.chunks(n as usize) {
+            let placements: Vec<(PageForm, Rectangle)> = pages.iter().copied().zip(cells.iter().copied()).collect();
+            sheets.push(self.add_sheet(width, height, &placements, options.scale_to_fit)?);
+        }
+        self.replace_pages(&sheets)
+    }
+
+    /// Replace the pages with sheets for a saddle-stitched booklet, which show pages in the order of folding:
+    /// the last and first pages on the front of the first sheet, the second and second-to-last pages on its
+    /// back, and so on. Blank pages are left empty.
+    ///
+    /// Pages become form XObjects as for `impose_nup`.
+    pub fn impose_booklet(&mut self, options: &BookletOptions) -> Result<()> {
+        let forms = self.page_forms()?;
+        let first = match forms.first() {
+            Some(first) => *first,
+            None => return Ok(()),
+        };
+        let (width, height) = options.sheet_size.unwrap_or((2.0 * first.width, first.height));
+        let cell_width = (width - 2.0 * options.margin - options.gutter) / 2.0;
+        let left = Rectangle::new(
+            options.margin,
+            options.margin,
+            options.margin + cell_width,
+            height - options.margin,
+        );
+        let right = Rectangle::new(
+            width - options.margin - cell_width,
+            options.margin,
+            width - options.margin,
+            height - options.margin,
+        );
+
+        let count = if options.add_blank_pages {
+            forms.len().div_ceil(4) * 4
+        } else {
+            forms.len().div_ceil(2) * 2
+        };
+        let mut sheets = Vec::new();
+        for index in 0..count / 2 {
+            // Even sides have a page of the second half on the left, odd sides one of the first half.
+            let (left_index, right_index) = if index % 2 == 0 {
+                (count - 1 - index, index)
+            } else {
+                (index, count - 1 - index)
+            };
+            let placements: Vec<(PageForm, Rectangle)> = [(left_index, left), (right_index, right)]
+                .iter()
+                .filter_map(|&(index, cell)| Some((*forms.get(index)?, cell)))
+                .collect();
+            sheets.push(self.add_sheet(width, height, &placements, options.scale_to_fit)?);
+        }
+        self.replace_pages(&sheets)
+    }
+
+    /// Create form XObjects of the pages, in page order.
+    fn page_forms(&mut self) -> Result<Vec<PageForm>> {
+        let page_ids: Vec<ObjectId> = self.page_iter().collect();
+        let mut forms = Vec::new();
+        for page_id in page_ids {
+            let id = self.page_to_xobject(page_id)?;
+            let form = self.get_object(id).and_then(Object::as_stream)?;
+            let bbox = form.dict.get(b"BBox").and_then(Rectangle::from_object)?;
+            let size = form
+                .dict
+                .get(b"Matrix")
+                .and_then(Matrix::from_object)?
+                .transform_rect(&bbox);
+            forms.push(PageForm {
+                id,
+                width: size.width(),
+                height: size.height(),
+            });
+        }
+        Ok(forms)
+    }
+
+    /// Create a page drawing forms of pages in cells, centered and clipped to them.
+    fn add_sheet(
+        &mut self, width: f64, height: f64, placements: &[(PageForm, Rectangle)], scale_to_fit: bool,
+    ) -> Result<ObjectId> {
+        let mut operations = Vec::new();
+        let mut xobjects = Dictionary::new();
+        for (index, (form, cell)) in placements.iter().enumerate() {
+            let name = format!("P{}", index);
+            xobjects.set(name.as_bytes(), form.id);
+            let scale = if scale_to_fit && form.width > 0.0 && form.height > 0.0 {
+                (cell.width() / form.width).min(cell.height() / form.height)
+            } else {
+                1.0
+            };
+            let x = cell.llx + (cell.width() - form.width * scale) / 2.0;
+            let y = cell.lly + (cell.height() - form.height * scale) / 2.0;
+            operations.extend(vec![
+                Operation::new("q", vec![]),
+                Operation::new(
+                    "re",
+                    vec![
+                        cell.llx.into(),
+                        cell.lly.into(),
+                        cell.width().into(),
+                        cell.height().into(),
+                    ],
+                ),
+                Operation::new("W", vec![]),
+                Operation::new("n", vec![]),
+                Operation::new(
+                    "cm",
+                    (Matrix::scale(scale, scale) * Matrix::translate(x, y)).to_operands(),
+                ),
+                Operation::new("Do", vec![Object::Name(name.into_bytes())]),
+                Operation::new("Q", vec![]),
+            ]);
+        }
+        let content_id = self.add_object(Stream::new(dictionary! {}, Content { operations }.encode()?));
+        Ok(self.add_object(dictionary! {
+            "Type" => "Page",
+            "MediaBox" => vec![0.into(), 0.into(), width.into(), height.into()],
+            "Resources" => dictionary! { "XObject" => xobjects },
+            "Contents" => content_id,
+        }))
+    }
+
+    /// Make the pages of the page tree root the given pages.
+    fn replace_pages(&mut self, page_ids: &[ObjectId]) -> Result<()> {
+        let pages_id = self.catalog()?.get(b"Pages").and_then(Object::as_reference)?;
+        for &page_id in page_ids {
+            self.get_object_mut(page_id)
+                .and_then(Object::as_dict_mut)?
+                .set("Parent", pages_id);
+        }
+        let pages = self.get_object_mut(pages_id).and_then(Object::as_dict_mut)?;
+        pages.set("Kids", page_ids.iter().map(|&id| id.into()).collect::<Vec<Object>>());
+        pages.set("Count", page_ids.len() as i64);
+        // Attributes of the root would be inherited by the new pages.
+        for key in [b"Resources".as_ref(), b"MediaBox", b"CropBox", b"Rotate"] {
+            pages.remove(key);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+#[test]
+fn impose_pages_four_up() {
+    use crate::test_support::{append_page, create_document};
+    use crate::PageBox;
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    for rotation in [0, 90, 0, 0] {
+        let mut page = doc.get_dictionary(page_id).unwrap().clone();
+        page.set("Rotate", rotation);
+        append_page(&mut doc, page);
+    }
+
+    let options = NupOptions {
+        margin: 10.0,
+        gutter: 5.0,
+        ..NupOptions::default()
+    };
+    doc.impose_nup(4, &options).unwrap();
+
+    let pages = doc.get_pages();
+    assert_eq!(pages.len(), 2);
+    let sheet_id = pages[&1];
+    assert_eq!(
+        doc.get_page_box(sheet_id, PageBox::MediaBox).unwrap(),
+        Rectangle::new(0.0, 0.0, 595.0, 842.0)
+    );
+    assert_eq!(doc.extract_text_fragments(1).unwrap().len(), 4);
+    assert_eq!(doc.extract_text_fragments(2).unwrap()[0].text, "Hello World!");
+
+    let content = doc.get_and_decode_page_content(sheet_id).unwrap();
+    let matrices: Vec<Vec<f64>> = content
+        .operations
+        .iter()
+        .filter(|operation| operation.operator == "cm")
+        .map(|operation| {
+            operation
+                .operands
+                .iter()
+                .map(|operand| operand.as_float().unwrap())
+                .collect()
+        })
+        .collect();
+    // Cells are 285 by 408.5, filled from the top left, and reals are written with six decimals. The third page
+    // is rotated to landscape.
+    let scale = 285.0 / 595.0;
+    assert!((matrices[0][0] - scale).abs() < 1e-6);
+    assert!((matrices[0][4] - 10.0).abs() < 1e-6);
+    assert!((matrices[0][5] - (832.0 - 408.5 / 2.0 - 842.0 * scale / 2.0)).abs() < 1e-6);
+    assert!((matrices[1][4] - 300.0).abs() < 1e-6);
+    let scale = 285.0 / 842.0;
+    assert!((matrices[2][0] - scale).abs() < 1e-6);
+    assert!((matrices[2][5] - (10.0 + 408.5 / 2.0 - 595.0 * scale / 2.0)).abs() < 1e-6);
+}
+
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+#[test]
+fn impose_pages_booklet() {
+    use crate::test_support::create_document_with_pages;
+    use crate::PageBox;
+
+    let mut doc = create_document_with_pages(5);
+
+    // Five pages are padded to eight, on two sheets printed on both sides. Forms are created in page order.
+    let first_form = doc.max_id + 1;
+    doc.impose_booklet(&BookletOptions::default()).unwrap();
+    let pages = doc.get_pages();
+    assert_eq!(pages.len(), 4);
+    assert_eq!(
+        doc.get_page_box(pages[&1], PageBox::MediaBox).unwrap(),
+        Rectangle::new(0.0, 0.0, 1190.0, 842.0)
+    );
+    let placed = |page_number: u32| -> Vec<(u32, f64)> {
+        let sheet_id = pages[&page_number];
+        let xobjects = doc
+            .get_dictionary(sheet_id)
+            .unwrap()
+            .get(b"Resources")
+            .and_then(Object::as_dict)
+            .and_then(|resources| resources.get(b"XObject"))
+            .and_then(Object::as_dict)
+            .unwrap();
+        let content = doc.get_and_decode_page_content(sheet_id).unwrap();
+        let mut offsets = content
+            .operations
+            .iter()
+            .filter(|operation| operation.operator == "cm")
+            .map(|operation| operation.operands[4].as_float().unwrap());
+        content
+            .operations
+            .iter()
+            .filter(|operation| operation.operator == "Do")
+            .map(|operation| {
+                let id = xobjects.get(operation.operands[0].as_name().unwrap()).unwrap();
+                (id.as_reference().unwrap().0 - first_form, offsets.next().unwrap())
+            })
+            .collect()
+    };
+    assert_eq!(placed(1), vec![(0, 595.0)]);
+    assert_eq!(placed(2), vec![(1, 0.0)]);
+    assert_eq!(placed(3), vec![(2, 595.0)]);
+    assert_eq!(placed(4), vec![(3, 0.0), (4, 595.0)]);
+}
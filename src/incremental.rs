@@ -0,0 +1,408 @@
+use crate::writer::{CountingWrite, Writer, XrefRow};
+use crate::xref::{Xref, XrefEntry};
+use crate::{Dictionary, Document, Error, Object, ObjectId, Result};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet};
+use std::hash::Hasher;
+use std::io::{self, Write};
+use std::ops::Range;
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// State of a document as it was loaded or last saved incrementally.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct LoadedState {
+    /// Size of the file in bytes.
+    pub file_size: usize,
+    /// Hashes of the serialized objects.
+    pub object_hashes: BTreeMap<ObjectId, u64>,
+}
+
+impl LoadedState {
+    pub fn new(document: &Document, file_size: usize) -> LoadedState {
+        let hash_entry = |(id, object): (&ObjectId, &Object)| (*id, hash_object(object));
+        #[cfg(feature = "rayon")]
+        let object_hashes = document.objects.par_iter().map(hash_entry).collect();
+        #[cfg(not(feature = "rayon"))]
+        let object_hashes = document.objects.iter().map(hash_entry).collect();
+
+        LoadedState {
+            file_size,
+            object_hashes,
+        }
+    }
+}
+
+/// Revision of the file a document was loaded from, which is the original file or an incremental update.
+#[derive(Debug, Clone)]
+pub struct Revision {
+    /// Bytes of the file written by the revision, ending after its `%%EOF` marker. The file as it was after the
+    /// revision is the bytes up to the end of the range.
+    pub byte_range: Range<usize>,
+    /// Byte offset of the cross-reference section of the revision.
+    pub xref_start: usize,
+    /// Trailer dictionary of the revision, or the dictionary of its cross-reference stream.
+    pub trailer: Dictionary,
+    /// Objects which didn't exist before the revision.
+    pub added: BTreeSet<ObjectId>,
+    /// Objects replaced by the revision, with their new ids.
+    pub changed: BTreeSet<ObjectId>,
+    /// Objects which the revision marks as free.
+    pub deleted: BTreeSet<ObjectId>,
+    /// Objects as they were before the revision changed or deleted them.
+    pub(crate) replaced: BTreeMap<ObjectId, Object>,
+}
+
+fn hash_object(object: &Object) -> u64 {
+    let mut hasher = HashWrite(DefaultHasher::new());
+    // Writing into a hasher can't fail.
+    let _ = Writer::write_object(&mut hasher, object);
+    hasher.0.finish()
+}
+
+struct HashWrite(DefaultHasher);
+
+impl Write for HashWrite {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        self.0.write(buffer);
+        Ok(buffer.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Keys of a cross-reference stream dictionary which don't belong into a trailer dictionary.
+const XREF_STREAM_KEYS: [&[u8]; 7] = [
+    b"Type",
+    b"Filter",
+    b"DecodeParms",
+    b"Length",
+    b"W",
+    b"Index",
+    b"XRefStm",
+];
+
+impl Document {
+    /// Get ids of objects which were added or changed since the document was loaded or last saved incrementally.
+    pub fn changed_objects(&self) -> Vec<ObjectId> {
+        let hashes = self.loaded_state.as_ref().map(|state| &state.object_hashes);
+        self.objects
+            .iter()
+            .filter(|(id, object)| hashes.and_then(|hashes| hashes.get(id)) != Some(&hash_object(object)))
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    /// Save changes made since the document was loaded as an incremental update.
+    ///
+    /// Only added and changed objects, a cross-reference section for them and deleted objects, and a new trailer
+    /// are written. They must be appended to the original file, so `target` is expected to be positioned at its
+    /// end. Earlier revisions, including digital signatures over them, are left intact.
+    pub fn save_incremental<W: Write>(&mut self, target: &mut W) -> Result<()> {
+        let state = self.loaded_state.as_ref().ok_or(Error::NoRevision)?;
+        let changed_objects = self.changed_objects_since(state);
+        let deleted_objects: Vec<ObjectId> = state
+            .object_hashes
+            .keys()
+            .filter(|id| !self.objects.contains_key(id))
+            .cloned()
+            .collect();
+        let size = state
+            .object_hashes
+            .keys()
+            .map(|id| id.0 + 1)
+            .max()
+            .unwrap_or(0)
+            .max(self.max_id + 1);
+
+        let mut target = CountingWrite {
+            inner: target,
+            bytes_written: state.file_size,
+        };
+        // The original file doesn't necessarily end with an end-of-line marker.
+        target.write_all(b"\n")?;
+        let mut xref = Xref::new(size);
+        for &(id, generation) in changed_objects.keys() {
+            let object = &self.objects[&(id, generation)];
+            Writer::write_indirect_object(&mut target, id, generation, object, &mut xref)?;
+        }
+
+        let xref_start = target.bytes_written;
+        let mut rows: BTreeMap<u32, XrefRow> = xref
+            .entries
+            .iter()
+            .filter_map(|(id, entry)| match *entry {
+                XrefEntry::Normal { offset, generation } => Some((*id, (1, offset, generation))),
+                _ => None,
+            })
+            .collect();
+        for &(id, generation) in &deleted_objects {
+            rows.insert(id, (0, 0, generation.saturating_add(1)));
+        }
+
+        let mut trailer = self.trailer.clone();
+        trailer.set("Prev", self.xref_start as i64);
+        if self.trailer.type_is(b"XRef") {
+            // Keep using cross-reference streams when the file already does.
+            trailer.set("Size", i64::from(size + 1));
+            Writer::write_xref_stream(&mut target, size, rows, &trailer)?;
+        } else {
+            for key in XREF_STREAM_KEYS.iter() {
+                trailer.remove(key);
+            }
+            trailer.set("Size", i64::from(size));
+            Writer::write_xref_section(&mut target, &rows)?;
+            target.write_all(b"trailer\n")?;
+            Writer::write_dictionary(&mut target, &trailer)?;
+        }
+        write!(target, "\nstartxref\n{}\n%%EOF", xref_start)?;
+
+        let file_size = target.bytes_written;
+        self.xref_start = xref_start;
+        if let Some(state) = self.loaded_state.as_mut() {
+            state.file_size = file_size;
+            state.object_hashes.extend(changed_objects);
+            for id in deleted_objects {
+                state.object_hashes.remove(&id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Treat an object as unchanged since the document was loaded or last saved incrementally, after it was
+    /// changed in the file as well.
+    pub(crate) fn mark_unchanged(&mut self, id: ObjectId) {
+        if let (Some(state), Some(object)) = (self.loaded_state.as_mut(), self.objects.get(&id)) {
+            state.object_hashes.insert(id, hash_object(object));
+        }
+    }
+
+    /// Get the revisions of the file the document was loaded from, starting with the original file.
+    ///
+    /// The cross-reference sections of linearized files which precede the main one in the file are part of the
+    /// same revision. Documents which weren't loaded from a file have no revisions.
+    pub fn revisions(&self) -> &[Revision] {
+        &self.revisions
+    }
+
+    /// Get the document as it was after a revision, counting from zero for the original file, by undoing the
+    /// changes and deletions of the later revisions.
+    ///
+    /// Changes made since loading the document are kept, unless they are to objects which the later revisions
+    /// changed. Incremental updates of the result are meant to be appended to the bytes of the file up to the
+    /// end of the revision.
+    pub fn rollback_to(&self, revision: usize) -> Result<Document> {
+        let target = self.revisions.get(revision).ok_or(Error::NoRevision)?;
+        let mut document = self.clone();
+        for later in self.revisions[revision + 1..].iter().rev() {
+            for id in later.added.iter().chain(&later.changed) {
+                document.objects.remove(id);
+            }
+            document
+                .objects
+                .extend(later.replaced.iter().map(|(&id, object)| (id, object.clone())));
+        }
+
+        let mut trailer = target.trailer.clone();
+        trailer.remove(b"Prev");
+        document.trailer = trailer;
+        document.xref_start = target.xref_start;
+        document.max_id = document.objects.keys().map(|id| id.0).max().unwrap_or(0);
+        document.revisions.truncate(revision + 1);
+        if self.loaded_state.is_some() {
+            document.loaded_state = Some(LoadedState::new(&document, target.byte_range.end));
+        }
+        Ok(document)
+    }
+
+    /// Discard the cross-reference data read from the file the document was loaded from, and regenerate a
+    /// consistent cross-reference table and trailer from the objects in memory.
+    ///
+    /// Of objects with the same number, the one with the highest generation is kept. Object streams,
+    /// cross-reference streams and linearization parameters are dropped, and the trailer loses its links to earlier
+    /// cross-reference sections. Afterwards the entries of `reference_table` are the offsets at which `save` writes
+    /// the objects. Since the cross-reference data of the original file can't be trusted, the document can only be
+    /// saved in full, not incrementally.
+    pub fn rebuild_xref(&mut self) -> Result<()> {
+        let mut previous: Option<ObjectId> = None;
+        let mut duplicates = Vec::new();
+        for &id in self.objects.keys() {
+            if previous.map(|previous| previous.0) == Some(id.0) {
+                duplicates.extend(previous);
+            }
+            previous = Some(id);
+        }
+        for id in duplicates {
+            self.objects.remove(&id);
+        }
+        self.objects.retain(|_, object| {
+            object
+                .type_name()
+                .map(|name| ["ObjStm", "XRef", "Linearized"].contains(&name))
+                .ok()
+                != Some(true)
+        });
+        self.max_id = self.objects.keys().map(|id| id.0).max().unwrap_or(0);
+
+        for key in XREF_STREAM_KEYS.iter().chain(&[&b"Prev"[..]]) {
+            self.trailer.remove(key);
+        }
+        let is_catalog = |object: &Object| object.as_dict().map(|dict| dict.type_is(b"Catalog")).unwrap_or(false);
+        let root = self.trailer.get(b"Root").and_then(Object::as_reference).ok();
+        if !root.and_then(|id| self.objects.get(&id)).is_some_and(is_catalog) {
+            match self.objects.iter().find(|(_, object)| is_catalog(object)) {
+                Some((&id, _)) => self.trailer.set("Root", id),
+                None => return Err(Error::Type),
+            }
+        }
+        let info = self.trailer.get(b"Info").and_then(Object::as_reference).ok();
+        if info.is_some_and(|id| !self.objects.contains_key(&id)) {
+            self.trailer.remove(b"Info");
+        }
+
+        let (xref, xref_start) = self.save_internal(&mut io::sink(), &Default::default())?;
+        self.reference_table = xref;
+        self.xref_start = xref_start;
+        self.loaded_state = None;
+        self.revisions.clear();
+        Ok(())
+    }
+
+    fn changed_objects_since(&self, state: &LoadedState) -> BTreeMap<ObjectId, u64> {
+        self.objects
+            .iter()
+            .filter(|(_, object)| {
+                object
+                    .type_name()
+                    .map(|name| ["ObjStm", "XRef", "Linearized"].contains(&name))
+                    .ok()
+                    != Some(true)
+            })
+            .map(|(id, object)| (*id, hash_object(object)))
+            .filter(|(id, hash)| state.object_hashes.get(id) != Some(hash))
+            .collect()
+    }
+}
+
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+#[test]
+fn save_incremental_update() {
+    let original = std::fs::read("assets/example.pdf").unwrap();
+    let mut doc = Document::load_mem(&original).unwrap();
+    assert!(doc.changed_objects().is_empty());
+
+    doc.replace_text(1, "Hello World!", "Incremental update!").unwrap();
+    assert_eq!(doc.changed_objects().len(), 1);
+
+    let mut output = original.clone();
+    doc.save_incremental(&mut output).unwrap();
+    assert_eq!(&output[..original.len()], &original[..]);
+    assert!(doc.changed_objects().is_empty());
+
+    let updated = Document::load_mem(&output).unwrap();
+    assert_eq!(updated.extract_text(&[1]).unwrap(), "Incremental update!\n");
+    assert_eq!(updated.xref_start, doc.xref_start);
+}
+
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+#[test]
+fn inspect_and_roll_back_revisions() {
+    let original = std::fs::read("assets/example.pdf").unwrap();
+    let mut doc = Document::load_mem(&original).unwrap();
+    assert_eq!(doc.revisions().len(), 1);
+    assert_eq!(doc.revisions()[0].byte_range, 0..original.len());
+    assert_eq!(doc.revisions()[0].added.len(), doc.objects.len());
+
+    let content_id = doc.get_page_contents(doc.page_iter().next().unwrap())[0];
+    let deleted_id = doc.add_object(Object::string_literal("deleted"));
+    doc.replace_text(1, "Hello World!", "First update!").unwrap();
+    let mut output = original.clone();
+    doc.save_incremental(&mut output).unwrap();
+    let first_update = output.len();
+    doc.replace_text(1, "First update!", "Second update!").unwrap();
+    doc.objects.remove(&deleted_id);
+    doc.save_incremental(&mut output).unwrap();
+
+    let updated = Document::load_mem(&output).unwrap();
+    let revisions = updated.revisions();
+    assert_eq!(revisions.len(), 3);
+    assert_eq!(revisions[1].byte_range, original.len()..first_update);
+    assert_eq!(revisions[2].byte_range, first_update..output.len());
+    assert_eq!(revisions[1].added, vec![deleted_id].into_iter().collect());
+    assert_eq!(revisions[1].changed, vec![content_id].into_iter().collect());
+    assert_eq!(revisions[2].changed, revisions[1].changed);
+    assert_eq!(revisions[2].deleted, vec![deleted_id].into_iter().collect());
+    assert!(revisions[1].trailer.has(b"Prev"));
+
+    let rolled_back = updated.rollback_to(1).unwrap();
+    assert_eq!(rolled_back.extract_text(&[1]).unwrap(), "First update!\n");
+    assert!(rolled_back.objects.contains_key(&deleted_id));
+    let rolled_back = updated.rollback_to(0).unwrap();
+    assert_eq!(rolled_back.extract_text(&[1]).unwrap(), "Hello World!\n");
+    assert!(!rolled_back.objects.contains_key(&deleted_id));
+    assert_eq!(rolled_back.revisions().len(), 1);
+
+    // The rolled back document can be updated on top of its revision.
+    let mut rolled_back = rolled_back;
+    rolled_back.replace_text(1, "Hello World!", "Another update!").unwrap();
+    let mut output = original.clone();
+    rolled_back.save_incremental(&mut output).unwrap();
+    let reloaded = Document::load_mem(&output).unwrap();
+    assert_eq!(reloaded.extract_text(&[1]).unwrap(), "Another update!\n");
+    assert!(updated.rollback_to(3).is_err());
+}
+
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+#[test]
+fn rebuild_broken_xref() {
+    let original = std::fs::read("assets/example.pdf").unwrap();
+    let mut doc = Document::load_mem(&original).unwrap();
+    let catalog_id = doc.trailer.get(b"Root").and_then(Object::as_reference).unwrap();
+    let (duplicate, _) = *doc.objects.keys().next().unwrap();
+    doc.objects.insert((duplicate, 1), Object::string_literal("newer"));
+    doc.trailer.set("Prev", 12345);
+    doc.trailer.set("XRefStm", 678);
+    doc.trailer.set("Root", (999, 0));
+    doc.trailer.set("Size", 999);
+    doc.max_id = 998;
+
+    doc.rebuild_xref().unwrap();
+    assert!(!doc.objects.contains_key(&(duplicate, 0)));
+    assert!(!doc.trailer.has(b"Prev"));
+    assert!(!doc.trailer.has(b"XRefStm"));
+    assert_eq!(
+        doc.trailer.get(b"Root").and_then(Object::as_reference).unwrap(),
+        catalog_id
+    );
+    assert_eq!(doc.max_id, doc.objects.keys().map(|id| id.0).max().unwrap());
+    assert!(doc.revisions().is_empty());
+    assert!(doc.save_incremental(&mut Vec::new()).is_err());
+
+    let mut output = Vec::new();
+    doc.save_to(&mut output).unwrap();
+    assert_eq!(
+        doc.xref_start,
+        output.windows(6).position(|bytes| bytes == b"\nxref\n").unwrap() + 1
+    );
+    for (&id, entry) in &doc.reference_table.entries {
+        if let XrefEntry::Normal { offset, generation } = *entry {
+            let header = format!("{} {} obj", id, generation);
+            assert!(output[offset as usize..].starts_with(header.as_bytes()));
+        }
+    }
+    assert_eq!(doc.reference_table.entries.len(), doc.objects.len());
+
+    let reloaded = Document::load_mem(&output).unwrap();
+    assert_eq!(
+        reloaded.get_object((duplicate, 1)).unwrap(),
+        &Object::string_literal("newer")
+    );
+    assert_eq!(
+        reloaded.trailer.get(b"Size").unwrap(),
+        &Object::Integer(i64::from(doc.max_id) + 1)
+    );
+    assert!(reloaded.load_warnings().is_empty());
+}
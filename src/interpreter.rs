@@ -0,0 +1,545 @@
+#![cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+
+use crate::content::Content;
+use crate::text::{Font, Glyph, TextFragment, MAX_FORM_DEPTH};
+use crate::{Dictionary, Document, Matrix, Object, ObjectId, Point, Rectangle, Result};
+use std::collections::BTreeMap;
+
+/// Graphics state parameters maintained by `ContentInterpreter`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphicsState {
+    /// Current transformation matrix, which maps user space to device space.
+    pub ctm: Matrix,
+    pub fill_color: Vec<f64>,
+    pub stroke_color: Vec<f64>,
+    /// Line width in user space.
+    pub line_width: f64,
+    /// Bounding box of the clipping path in device space, or `None` if nothing is clipped.
+    pub clip: Option<Rectangle>,
+    /// Resource name of the current font.
+    pub font: Option<Vec<u8>>,
+    pub font_size: f64,
+    pub character_spacing: f64,
+    pub word_spacing: f64,
+    /// Horizontal scaling of text, as a fraction rather than the percentage of the `Tz` operator.
+    pub horizontal_scaling: f64,
+    pub leading: f64,
+    pub rise: f64,
+}
+
+impl Default for GraphicsState {
+    fn default() -> GraphicsState {
+        GraphicsState {
+            ctm: Matrix::IDENTITY,
+            fill_color: vec![0.0],
+            stroke_color: vec![0.0],
+            line_width: 1.0,
+            clip: None,
+            font: None,
+            font_size: 0.0,
+            character_spacing: 0.0,
+            word_spacing: 0.0,
+            horizontal_scaling: 1.0,
+            leading: 0.0,
+            rise: 0.0,
+        }
+    }
+}
+
+/// Segment of a path, with coordinates in device space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathSegment {
+    MoveTo(Point),
+    LineTo(Point),
+    /// Cubic Bézier curve with two control points and its end point.
+    CurveTo(Point, Point, Point),
+    ClosePath,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FillRule {
+    NonZeroWinding,
+    EvenOdd,
+}
+
+/// Path painted by a path painting operator.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaintedPath {
+    pub segments: Vec<PathSegment>,
+    /// Rule by which the path is filled, or `None` if it isn't filled.
+    pub fill: Option<FillRule>,
+    pub stroke: bool,
+}
+
+impl PaintedPath {
+    /// Get the bounding box of the points of the path, including the control points of curves.
+    pub fn bbox(&self) -> Option<Rectangle> {
+        path_bbox(&self.segments)
+    }
+}
+
+/// Image XObject painted by the `Do` operator.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlacedImage {
+    pub id: ObjectId,
+    /// Resource name of the image.
+    pub name: Vec<u8>,
+    /// Bounding box of the image in device space, which is the unit square transformed by the CTM.
+    pub bbox: Rectangle,
+}
+
+/// Callbacks of `ContentInterpreter`, which do nothing unless implemented.
+pub trait ContentHandler {
+    /// Called before each operation, with its index in the content of the page, or in the content of a form
+    /// XObject for depths above zero.
+    fn operation_started(&mut self, _index: usize, _depth: usize) {}
+
+    /// Called for the text shown by each text showing operator.
+    fn text_shown(&mut self, _fragment: &TextFragment, _state: &GraphicsState) {}
+
+    /// Called for each path which is filled or stroked, but not for paths which are only used for clipping.
+    fn path_painted(&mut self, _path: &PaintedPath, _state: &GraphicsState) {}
+
+    fn image_placed(&mut self, _image: &PlacedImage, _state: &GraphicsState) {}
+}
+
+/// Interpreter of content streams, which keeps track of the graphics state and text matrices, and calls
+/// a handler with the text, paths and images painted, including those of form XObjects.
+pub struct ContentInterpreter<'a, H: ContentHandler> {
+    document: &'a Document,
+    handler: H,
+    font_cache: BTreeMap<(Option<ObjectId>, Vec<u8>), Font<'a>>,
+}
+
+type Fonts<'a> = BTreeMap<Vec<u8>, (Option<ObjectId>, &'a Dictionary)>;
+
+impl<'a, H: ContentHandler> ContentInterpreter<'a, H> {
+    pub fn new(document: &'a Document, handler: H) -> ContentInterpreter<'a, H> {
+        ContentInterpreter {
+            document,
+            handler,
+            font_cache: BTreeMap::new(),
+        }
+    }
+
+    pub fn handler(&self) -> &H {
+        &self.handler
+    }
+
+    pub fn handler_mut(&mut self) -> &mut H {
+        &mut self.handler
+    }
+
+    pub fn into_handler(self) -> H {
+        self.handler
+    }
+
+    /// Interpret the content of a page, starting from the default graphics state.
+    pub fn run_page(&mut self, page_id: ObjectId) -> Result<()> {
+        let document = self.document;
+        let fonts = document
+            .get_page_fonts(page_id)
+            .into_iter()
+            .map(|(name, font)| (name, (None, font)))
+            .collect();
+        let xobjects = document
+            .get_page_attribute(page_id, b"Resources")
+            .and_then(|resources| resources.as_dict().ok())
+            .and_then(|resources| document.resource_xobjects(resources));
+        let content = document.get_page_content(page_id)?;
+        self.run(&content, &fonts, xobjects, GraphicsState::default(), 0)
+    }
+
+    fn run(
+        &mut self, content: &[u8], fonts: &Fonts<'a>, xobjects: Option<&'a Dictionary>, state: GraphicsState,
+        depth: usize,
+    ) -> Result<()> {
+        let content = Content::decode(content)?;
+        let mut state = state;
+        let mut stack = Vec::new();
+        let mut text_matrix = Matrix::IDENTITY;
+        let mut line_matrix = Matrix::IDENTITY;
+        let mut path = Vec::new();
+        let mut current_point = Point::default();
+        let mut pending_clip = false;
+
+        for (index, operation) in content.operations.iter().enumerate() {
+            self.handler.operation_started(index, depth);
+            let operands = &operation.operands;
+            let number = |index: usize| operands.get(index).and_then(|n| n.as_float().ok()).unwrap_or(0.0);
+            let point = |index: usize| state.ctm.transform_point(Point::new(number(index), number(index + 1)));
+            let numbers = || operands.iter().filter_map(|n| n.as_float().ok()).collect();
+            match operation.operator.as_str() {
+                "q" => stack.push(state.clone()),
+                "Q" => {
+                    if let Some(saved) = stack.pop() {
+                        state = saved;
+                    }
+                }
+                "cm" => {
+                    if let Ok(matrix) = Matrix::from_operands(operands) {
+                        state.ctm = matrix.then(&state.ctm);
+                    }
+                }
+                "w" => state.line_width = number(0),
+                "g" | "rg" | "k" | "sc" | "scn" => state.fill_color = numbers(),
+                "G" | "RG" | "K" | "SC" | "SCN" => state.stroke_color = numbers(),
+                "cs" => state.fill_color = vec![0.0],
+                "CS" => state.stroke_color = vec![0.0],
+
+                "m" => {
+                    current_point = point(0);
+                    path.push(PathSegment::MoveTo(current_point));
+                }
+                "l" => {
+                    current_point = point(0);
+                    path.push(PathSegment::LineTo(current_point));
+                }
+                "c" => {
+                    let end = point(4);
+                    path.push(PathSegment::CurveTo(point(0), point(2), end));
+                    current_point = end;
+                }
+                "v" => {
+                    let end = point(2);
+                    path.push(PathSegment::CurveTo(current_point, point(0), end));
+                    current_point = end;
+                }
+                "y" => {
+                    let end = point(2);
+                    path.push(PathSegment::CurveTo(point(0), end, end));
+                    current_point = end;
+                }
+                "re" => {
+                    let (x, y, width, height) = (number(0), number(1), number(2), number(3));
+                    let corner = |x: f64, y: f64| state.ctm.transform_point(Point::new(x, y));
+                    current_point = corner(x, y);
+                    path.extend_from_slice(&[
+                        PathSegment::MoveTo(current_point),
+                        PathSegment::LineTo(corner(x + width, y)),
+                        PathSegment::LineTo(corner(x + width, y + height)),
+                        PathSegment::LineTo(corner(x, y + height)),
+                        PathSegment::ClosePath,
+                    ]);
+                }
+                "h" => path.push(PathSegment::ClosePath),
+                "W" | "W*" => pending_clip = true,
+                operator @ ("S" | "s" | "f" | "F" | "f*" | "B" | "B*" | "b" | "b*" | "n") => {
+                    if matches!(operator, "s" | "b" | "b*") {
+                        path.push(PathSegment::ClosePath);
+                    }
+                    let fill = match operator {
+                        "f" | "F" | "B" | "b" => Some(FillRule::NonZeroWinding),
+                        "f*" | "B*" | "b*" => Some(FillRule::EvenOdd),
+                        _ => None,
+                    };
+                    let stroke = matches!(operator, "S" | "s" | "B" | "B*" | "b" | "b*");
+                    let segments = std::mem::take(&mut path);
+                    if fill.is_some() || stroke {
+                        let painted = PaintedPath { segments, fill, stroke };
+                        self.handler.path_painted(&painted, &state);
+                        if pending_clip {
+                            state.clip = clip(state.clip, painted.bbox());
+                        }
+                    } else if pending_clip {
+                        state.clip = clip(state.clip, path_bbox(&segments));
+                    }
+                    pending_clip = false;
+                }
+
+                "BT" => {
+                    text_matrix = Matrix::IDENTITY;
+                    line_matrix = Matrix::IDENTITY;
+                }
+                "Tf" => {
+                    state.font = operands
+                        .first()
+                        .and_then(|name| name.as_name().ok())
+                        .map(<[u8]>::to_vec);
+                    state.font_size = number(1);
+                }
+                "Tc" => state.character_spacing = number(0),
+                "Tw" => state.word_spacing = number(0),
+                "Tz" => state.horizontal_scaling = number(0) / 100.0,
+                "TL" => state.leading = number(0),
+                "Ts" => state.rise = number(0),
+                "Td" | "TD" => {
+                    if operation.operator == "TD" {
+                        state.leading = -number(1);
+                    }
+                    line_matrix = Matrix::translate(number(0), number(1)).then(&line_matrix);
+                    text_matrix = line_matrix;
+                }
+                "Tm" => {
+                    if let Ok(matrix) = Matrix::from_operands(operands) {
+                        line_matrix = matrix;
+                        text_matrix = matrix;
+                    }
+                }
+                "T*" | "'" | "\"" => {
+                    if operation.operator == "\"" {
+                        state.word_spacing = number(0);
+                        state.character_spacing = number(1);
+                    }
+                    line_matrix = Matrix::translate(0.0, -state.leading).then(&line_matrix);
+                    text_matrix = line_matrix;
+                    if operation.operator != "T*" {
+                        if let Some(text) = operands.last() {
+                            self.show_text(std::slice::from_ref(text), fonts, &state, &mut text_matrix);
+                        }
+                    }
+                }
+                "Tj" => self.show_text(operands, fonts, &state, &mut text_matrix),
+                "TJ" => {
+                    if let Some(Object::Array(items)) = operands.first() {
+                        self.show_text(items, fonts, &state, &mut text_matrix);
+                    }
+                }
+
+                "Do" => {
+                    let name = match operands.first().and_then(|name| name.as_name().ok()) {
+                        Some(name) => name,
+                        None => continue,
+                    };
+                    let xobject = xobjects
+                        .and_then(|xobjects| xobjects.get(name).ok())
+                        .and_then(|xobject| self.document.dereference(xobject).ok())
+                        .and_then(|(id, xobject)| Some((id, xobject.as_stream().ok()?)));
+                    let (xobject_id, xobject) = match xobject {
+                        Some(xobject) => xobject,
+                        None => continue,
+                    };
+                    match xobject.dict.get(b"Subtype").and_then(Object::as_name) {
+                        Ok(b"Image") => {
+                            if let Some(id) = xobject_id {
+                                let image = PlacedImage {
+                                    id,
+                                    name: name.to_vec(),
+                                    bbox: state.ctm.transform_rect(&Rectangle::new(0.0, 0.0, 1.0, 1.0)),
+                                };
+                                self.handler.image_placed(&image, &state);
+                            }
+                        }
+                        Ok(b"Form") if depth < MAX_FORM_DEPTH => {
+                            let mut form_state = state.clone();
+                            if let Ok(matrix) = xobject.dict.get(b"Matrix").and_then(Matrix::from_object) {
+                                form_state.ctm = matrix.then(&state.ctm);
+                            }
+                            if let Ok(bbox) = xobject.dict.get(b"BBox").and_then(Rectangle::from_object) {
+                                form_state.clip = clip(form_state.clip, Some(form_state.ctm.transform_rect(&bbox)));
+                            }
+                            let resources = xobject
+                                .dict
+                                .get(b"Resources")
+                                .and_then(|resources| self.document.dereference(resources))
+                                .and_then(|(_, resources)| resources.as_dict());
+                            let (form_fonts, form_xobjects) = match resources {
+                                Ok(resources) => (
+                                    self.document.resource_fonts(resources, xobject_id),
+                                    self.document.resource_xobjects(resources),
+                                ),
+                                Err(_) => (fonts.clone(), xobjects),
+                            };
+                            let data = xobject
+                                .decompressed_content()
+                                .unwrap_or_else(|_| xobject.content.clone());
+                            self.run(&data, &form_fonts, form_xobjects, form_state, depth + 1)?;
+                        }
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn show_text(&mut self, items: &[Object], fonts: &Fonts<'a>, state: &GraphicsState, text_matrix: &mut Matrix) {
+        let (font_owner, font_dict) = match state.font.as_ref().and_then(|name| fonts.get(name)) {
+            Some(&font) => font,
+            None => return,
+        };
+        let font_name = state.font.clone().unwrap_or_default();
+        let document = self.document;
+        let font = self
+            .font_cache
+            .entry((font_owner, font_name.clone()))
+            .or_insert_with(|| Font::new(document, &font_name, font_dict));
+
+        let mut glyphs = Vec::new();
+        for item in items {
+            match *item {
+                Object::String(ref bytes, _) => {
+                    for code in font.codes(bytes) {
+                        let width = font.width(code) / 1000.0;
+                        // Vertical glyphs are centered below their origin.
+                        let (x, y) = if font.vertical {
+                            (-width / 2.0, -font.vertical_metrics.0 / 1000.0)
+                        } else {
+                            (0.0, 0.0)
+                        };
+                        let rendering_matrix = Matrix::new(
+                            state.font_size * state.horizontal_scaling,
+                            0.0,
+                            0.0,
+                            state.font_size,
+                            0.0,
+                            state.rise,
+                        )
+                        .then(&text_matrix.then(&state.ctm));
+                        let bbox = rendering_matrix.transform_rect(&Rectangle::from_points(
+                            Point::new(x, y + font.descent),
+                            Point::new(x + width, y + font.ascent),
+                        ));
+                        let word_spacing = if code.is_space() { state.word_spacing } else { 0.0 };
+                        let advance =
+                            font.advance(code) / 1000.0 * state.font_size + state.character_spacing + word_spacing;
+                        glyphs.push(Glyph {
+                            text: font.decode(code),
+                            bbox,
+                            code: code.bytes(),
+                            advance,
+                        });
+                        *text_matrix = if font.vertical {
+                            Matrix::translate(0.0, -advance).then(text_matrix)
+                        } else {
+                            let advance = advance * state.horizontal_scaling;
+                            Matrix::translate(advance, 0.0).then(text_matrix)
+                        };
+                    }
+                }
+                ref number => {
+                    if let Ok(adjustment) = number.as_float() {
+                        let advance = -adjustment / 1000.0 * state.font_size;
+                        *text_matrix = if font.vertical {
+                            Matrix::translate(0.0, -advance).then(text_matrix)
+                        } else {
+                            let advance = advance * state.horizontal_scaling;
+                            Matrix::translate(advance, 0.0).then(text_matrix)
+                        };
+                    }
+                }
+            }
+        }
+
+        if glyphs.is_empty() {
+            return;
+        }
+        let bbox = glyphs
+            .iter()
+            .skip(1)
+            .fold(glyphs[0].bbox, |bbox, glyph| bbox.union(&glyph.bbox));
+        let matrix = text_matrix.then(&state.ctm);
+        let fragment = TextFragment {
+            text: glyphs.iter().map(|glyph| glyph.text.as_str()).collect(),
+            bbox,
+            font: font.name.clone(),
+            font_size: state.font_size * matrix.c.hypot(matrix.d),
+            color: state.fill_color.clone(),
+            glyphs,
+        };
+        self.handler.text_shown(&fragment, state);
+    }
+}
+
+fn path_bbox(segments: &[PathSegment]) -> Option<Rectangle> {
+    segments
+        .iter()
+        .flat_map(|segment| match *segment {
+            PathSegment::MoveTo(point) | PathSegment::LineTo(point) => vec![point],
+            PathSegment::CurveTo(first, second, end) => vec![first, second, end],
+            PathSegment::ClosePath => vec![],
+        })
+        .map(|point| Rectangle::from_points(point, point))
+        .reduce(|bbox, point| bbox.union(&point))
+}
+
+/// Intersect a clipping path with a path, which clips everything if they don't overlap.
+fn clip(clip: Option<Rectangle>, path: Option<Rectangle>) -> Option<Rectangle> {
+    let path = path.unwrap_or_default();
+    match clip {
+        Some(clip) => Some(clip.intersection(&path).unwrap_or_default()),
+        None => Some(path),
+    }
+}
+
+#[test]
+fn interpret_content() {
+    use crate::content::ContentBuilder;
+    use crate::test_support::create_document;
+
+    #[derive(Default)]
+    struct Recorder {
+        texts: Vec<(String, Rectangle)>,
+        paths: Vec<(PaintedPath, Option<Rectangle>, Vec<f64>)>,
+        images: Vec<PlacedImage>,
+    }
+
+    impl ContentHandler for Recorder {
+        fn text_shown(&mut self, fragment: &TextFragment, _state: &GraphicsState) {
+            self.texts.push((fragment.text.clone(), fragment.bbox));
+        }
+
+        fn path_painted(&mut self, path: &PaintedPath, state: &GraphicsState) {
+            self.paths.push((path.clone(), state.clip, state.stroke_color.clone()));
+        }
+
+        fn image_placed(&mut self, image: &PlacedImage, _state: &GraphicsState) {
+            self.images.push(image.clone());
+        }
+    }
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    let image_id = doc.add_object(crate::Stream::new(
+        dictionary! { "Type" => "XObject", "Subtype" => "Image", "Width" => 1, "Height" => 1 },
+        vec![0],
+    ));
+    doc.add_xobject(page_id, "Im1", image_id).unwrap();
+    let content = ContentBuilder::new()
+        .save_state()
+        .transform(Matrix::translate(100.0, 100.0))
+        .rectangle(0.0, 0.0, 50.0, 50.0)
+        .clip()
+        .end_path()
+        .set_stroke_color(&[1.0, 0.0, 0.0])
+        .move_to(10.0, 10.0)
+        .line_to(90.0, 10.0)
+        .stroke()
+        .transform(Matrix::scale(20.0, 10.0))
+        .draw_xobject("Im1")
+        .restore_state()
+        .encode()
+        .unwrap();
+    doc.append_page_content(page_id, content).unwrap();
+
+    let mut interpreter = ContentInterpreter::new(&doc, Recorder::default());
+    interpreter.run_page(page_id).unwrap();
+    let recorder = interpreter.into_handler();
+
+    assert_eq!(recorder.texts.len(), 1);
+    assert_eq!(recorder.texts[0].0, "Hello World!");
+    assert!((recorder.texts[0].1.llx - 100.0).abs() < 1e-6);
+
+    assert_eq!(recorder.paths.len(), 1);
+    let (path, clip, color) = &recorder.paths[0];
+    assert_eq!(
+        path.segments,
+        [
+            PathSegment::MoveTo(Point::new(110.0, 110.0)),
+            PathSegment::LineTo(Point::new(190.0, 110.0))
+        ]
+    );
+    assert!(path.stroke && path.fill.is_none());
+    assert_eq!(*clip, Some(Rectangle::new(100.0, 100.0, 150.0, 150.0)));
+    assert_eq!(*color, [1.0, 0.0, 0.0]);
+
+    assert_eq!(
+        recorder.images,
+        [PlacedImage {
+            id: image_id,
+            name: b"Im1".to_vec(),
+            bbox: Rectangle::new(100.0, 100.0, 120.0, 110.0),
+        }]
+    );
+}
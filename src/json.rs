@@ -0,0 +1,138 @@
+use crate::{Dictionary, Document, Error, Object, ObjectId, Result};
+use serde_json::{Map, Value};
+use std::path::{Component, Path, PathBuf};
+
+/// Options of the JSON tree of a document.
+#[derive(Debug, Clone, Default)]
+pub struct JsonTreeOptions {
+    /// Directory in which the data of streams is kept in files named by their object ids, such as `12_0.bin`,
+    /// instead of inline in base64.
+    pub stream_directory: Option<PathBuf>,
+}
+
+impl Document {
+    /// Dump the document as a JSON tree of the form `{"version": "1.5", "trailer": {...}, "objects": {"1 0": ...}}`,
+    /// with objects mapped as described for `Object`.
+    ///
+    /// Streams which are kept in files are `{"stream": {...}, "file": "12_0.bin"}`.
+    pub fn to_json_tree(&self, options: &JsonTreeOptions) -> Result<Value> {
+        if let Some(ref directory) = options.stream_directory {
+            std::fs::create_dir_all(directory)?;
+        }
+        let mut objects = Map::new();
+        for (&(number, generation), object) in &self.objects {
+            let mut value = serde_json::to_value(object)?;
+            if let (Object::Stream(stream), Some(directory)) = (object, &options.stream_directory) {
+                let file_name = format!("{}_{}.bin", number, generation);
+                std::fs::write(directory.join(&file_name), &stream.content)?;
+                if let Value::Object(ref mut map) = value {
+                    map.remove("data");
+                    map.insert("file".to_string(), Value::String(file_name));
+                }
+            }
+            objects.insert(format!("{} {}", number, generation), value);
+        }
+
+        let mut tree = Map::new();
+        tree.insert("version".to_string(), Value::String(self.version.clone()));
+        tree.insert("trailer".to_string(), serde_json::to_value(&self.trailer)?);
+        tree.insert("objects".to_string(), Value::Object(objects));
+        Ok(Value::Object(tree))
+    }
+
+    /// Reconstruct a document from a JSON tree made by `to_json_tree`, reading the data of streams kept in files
+    /// from the stream directory.
+    pub fn from_json_tree(tree: &Value, options: &JsonTreeOptions) -> Result<Document> {
+        let invalid = |message: &str| Error::Syntax(format!("invalid JSON tree: {}", message));
+        let mut document = Document::new();
+        if let Some(version) = tree.get("version").and_then(Value::as_str) {
+            document.version = version.to_string();
+        }
+        document.trailer = match tree.get("trailer") {
+            Some(trailer) => serde_json::from_value::<Dictionary>(trailer.clone())?,
+            None => return Err(invalid("missing trailer")),
+        };
+        let objects = tree
+            .get("objects")
+            .and_then(Value::as_object)
+            .ok_or_else(|| invalid("missing objects"))?;
+        for (key, value) in objects {
+            let mut parts = key.split(' ');
+            let id: ObjectId = match (
+                parts.next().map(str::parse::<u32>),
+                parts.next().map(str::parse::<u16>),
+                parts.next(),
+            ) {
+                (Some(Ok(number)), Some(Ok(generation)), None) => (number, generation),
+                _ => return Err(invalid(&format!("object id {:?}", key))),
+            };
+            let mut object: Object = serde_json::from_value(value.clone())?;
+            if let Some(file_name) = value.get("file").and_then(Value::as_str) {
+                let directory = options
+                    .stream_directory
+                    .as_ref()
+                    .ok_or_else(|| invalid("stream in a file without stream directory"))?;
+                // Only files directly in the stream directory are read.
+                let mut components = Path::new(file_name).components();
+                if !matches!(
+                    (components.next(), components.next()),
+                    (Some(Component::Normal(_)), None)
+                ) {
+                    return Err(invalid(&format!("stream file {:?}", file_name)));
+                }
+                object
+                    .as_stream_mut()?
+                    .set_content(std::fs::read(directory.join(file_name))?);
+            }
+            document.objects.insert(id, object);
+        }
+        document.max_id = document.objects.keys().map(|id| id.0).max().unwrap_or(0);
+        Ok(document)
+    }
+}
+
+#[test]
+fn json_tree_round_trip() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    doc.compress();
+    let tree = doc.to_json_tree(&JsonTreeOptions::default()).unwrap();
+    assert_eq!(
+        tree["objects"]["1 0"]["/Title"]["string"],
+        "Create PDF document example"
+    );
+    let loaded = Document::from_json_tree(&tree, &JsonTreeOptions::default()).unwrap();
+    assert_eq!(loaded.objects, doc.objects);
+    assert_eq!(loaded.trailer, doc.trailer);
+    assert_eq!(loaded.max_id, doc.max_id);
+
+    let options = JsonTreeOptions {
+        stream_directory: Some(std::env::temp_dir().join(format!("lopdf_json_tree_{}", std::process::id()))),
+    };
+    let tree = doc.to_json_tree(&options).unwrap();
+    let content_id = doc.get_page_contents(doc.page_iter().next().unwrap())[0];
+    let key = format!("{} {}", content_id.0, content_id.1);
+    assert_eq!(
+        tree["objects"][&key]["file"],
+        format!("{}_{}.bin", content_id.0, content_id.1)
+    );
+    assert!(tree["objects"][&key].get("data").is_none());
+    let text = serde_json::to_string_pretty(&tree).unwrap();
+    let loaded = Document::from_json_tree(&serde_json::from_str(&text).unwrap(), &options).unwrap();
+    assert_eq!(loaded.objects, doc.objects);
+    assert_eq!(loaded.extract_text(&[1]).unwrap(), "Hello World!\n");
+
+    // Stream files outside the stream directory and generations out of range are rejected.
+    let mut tree = tree;
+    let is_invalid = |result: Result<Document>| matches!(result, Err(Error::Syntax(_)));
+    tree["objects"][&key]["file"] = "../secret.bin".into();
+    assert!(is_invalid(Document::from_json_tree(&tree, &options)));
+    tree["objects"][&key]["file"] = "/etc/passwd".into();
+    assert!(is_invalid(Document::from_json_tree(&tree, &options)));
+    let mut tree = doc.to_json_tree(&JsonTreeOptions::default()).unwrap();
+    let object = tree["objects"].as_object_mut().unwrap().remove("1 0").unwrap();
+    tree["objects"]["1 65536"] = object;
+    assert!(is_invalid(Document::from_json_tree(&tree, &JsonTreeOptions::default())));
+    std::fs::remove_dir_all(options.stream_directory.unwrap()).unwrap();
+}
@@ -0,0 +1,543 @@
+#[cfg(feature = "crypto")]
+use crate::encryption::SecurityHandler;
+use crate::error::{ErrorContext, XrefError};
+use crate::object_stream::ObjectStream;
+use crate::parser;
+use crate::reader::Reader;
+use crate::xref::{Xref, XrefEntry};
+use crate::{Dictionary, Document, Error, Object, ObjectId, Result};
+use log::warn;
+#[cfg(feature = "mmap")]
+use memmap2::Mmap;
+use std::collections::BTreeMap;
+#[cfg(feature = "mmap")]
+use std::convert::TryFrom;
+use std::fs::File;
+#[cfg(feature = "mmap")]
+use std::io::Cursor;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Size of the first part of the file read to parse an object, which is doubled until the object fits.
+const WINDOW_SIZE: usize = 64 * 1024;
+
+/// Page attributes which pages inherit from their ancestors in the page tree.
+const INHERITED_ATTRIBUTES: [&[u8]; 4] = [b"Resources", b"MediaBox", b"CropBox", b"Rotate"];
+
+/// PDF document of which only the cross-reference table and the trailer are read when it's opened, and objects
+/// are read from the source on first access and then kept.
+///
+/// This allows reading some pages of large documents without loading all their objects into memory.
+/// Encrypted documents are decrypted with an empty password, which needs the `crypto` feature.
+pub struct LazyDocument<R> {
+    /// The version of the PDF specification to which the file conforms.
+    pub version: String,
+    /// The trailer of the last cross-reference section, with the entries of previous sections it lacks.
+    pub trailer: Dictionary,
+    reference_table: Xref,
+    source: R,
+    objects: BTreeMap<ObjectId, Object>,
+    #[cfg(feature = "crypto")]
+    security_handler: Option<(SecurityHandler, Option<ObjectId>)>,
+}
+
+impl LazyDocument<File> {
+    /// Open a PDF document from a file path, keeping the file open to read objects.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<LazyDocument<File>> {
+        LazyDocument::new(File::open(path)?)
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl LazyDocument<Cursor<Mmap>> {
+    /// Open a PDF document from a memory-mapped file. The file must not be modified while it's open.
+    pub fn open_mmap<P: AsRef<Path>>(path: P) -> Result<LazyDocument<Cursor<Mmap>>> {
+        let file = File::open(path)?;
+        // Safety: the map is only modified if the file is, which the caller must avoid.
+        let map = unsafe { Mmap::map(&file)? };
+        LazyDocument::new(Cursor::new(map))
+    }
+
+    /// Get the dictionary of a stream and its encoded data, borrowed from the map without copying it.
+    ///
+    /// Streams of encrypted documents and streams in object streams can't be borrowed. Objects which aren't
+    /// streams and streams of which the data doesn't end at their length are errors.
+    pub fn get_mapped_stream(&mut self, id: ObjectId) -> Result<(Dictionary, &[u8])> {
+        if self.trailer.has(b"Encrypt") {
+            return Err(Error::Syntax(
+                "streams of encrypted documents are not mapped".to_string(),
+            ));
+        }
+        let offset = match self.reference_table.get(id.0) {
+            Some(&XrefEntry::Normal { offset, generation }) if generation == id.1 => offset as usize,
+            _ => return Err(Error::ObjectNotFound),
+        };
+        let (dict, start) = parser::stream_header(self.source.get_ref(), offset, id)
+            .map_err(|e| e.with_context(ErrorContext::in_object(id).with_offset(offset)))?;
+        let length = match dict.get(b"Length")? {
+            Object::Reference(length_id) => self.get_object(*length_id)?.as_i64()?,
+            length => length.as_i64()?,
+        };
+        let buffer: &[u8] = self.source.get_ref();
+        let wrong_length = || {
+            Error::Syntax("stream data doesn't end at its length".to_string())
+                .with_context(ErrorContext::in_object(id).with_key(b"Length"))
+        };
+        let end = usize::try_from(length)
+            .ok()
+            .and_then(|length| start.checked_add(length))
+            .filter(|&end| end <= buffer.len())
+            .ok_or_else(wrong_length)?;
+        let mut rest = &buffer[end..];
+        for eol in [&b"\r\n"[..], b"\n", b"\r"] {
+            if let Some(after) = rest.strip_prefix(eol) {
+                rest = after;
+                break;
+            }
+        }
+        if !rest.starts_with(b"endstream") {
+            return Err(wrong_length());
+        }
+        Ok((dict, &buffer[start..end]))
+    }
+}
+
+impl<R: Read + Seek> LazyDocument<R> {
+    /// Read the header, cross-reference sections and trailers of a PDF document.
+    pub fn new(source: R) -> Result<LazyDocument<R>> {
+        let mut document = LazyDocument {
+            version: String::new(),
+            trailer: Dictionary::new(),
+            reference_table: Xref::new(0),
+            source,
+            objects: BTreeMap::new(),
+            #[cfg(feature = "crypto")]
+            security_handler: None,
+        };
+        let header = document.read_at(0, 1024)?;
+        document.version = parser::header(&header).ok_or(Error::Header)?;
+
+        let length = document.source.seek(SeekFrom::End(0))?;
+        let tail_start = length.saturating_sub(1024);
+        let tail = document.read_at(tail_start, 1024)?;
+        let xref_start = Reader::get_xref_start(&tail)? as u64;
+        if xref_start > length {
+            return Err(Error::Xref(XrefError::Start));
+        }
+
+        // Earlier sections of incremental updates don't replace entries of later ones.
+        let mut xref = Xref::new(0);
+        let mut next_start = Some(xref_start);
+        let mut is_last = true;
+        while let Some(start) = next_start.take() {
+            let (mut section, mut trailer) = document.read_xref_section(start)?;
+            if let Some(stream_start) = trailer.remove(b"XRefStm").and_then(|offset| offset.as_i64().ok()) {
+                let (stream_section, _) = document.read_xref_section(stream_start as u64)?;
+                section.merge_hybrid_stream(stream_section);
+            }
+            xref.extend(section);
+            next_start = trailer
+                .remove(b"Prev")
+                .and_then(|offset| offset.as_i64().ok())
+                .map(|offset| offset as u64)
+                .filter(|&offset| offset <= length);
+            if is_last {
+                document.trailer = trailer;
+                is_last = false;
+            }
+        }
+        xref.size = xref.max_id() + 1;
+        document.reference_table = xref;
+
+        #[cfg(feature = "crypto")]
+        {
+            let encrypt_id = document.trailer.get(b"Encrypt").and_then(Object::as_reference).ok();
+            let encrypt = match document.trailer.get(b"Encrypt").cloned() {
+                Ok(Object::Reference(id)) => Some(document.get_object(id)?.as_dict()?.clone()),
+                Ok(Object::Dictionary(dict)) => Some(dict),
+                _ => None,
+            };
+            if let Some(encrypt) = encrypt {
+                let file_id = document
+                    .trailer
+                    .get(b"ID")
+                    .and_then(Object::as_array)
+                    .ok()
+                    .and_then(|id| id.first())
+                    .and_then(|id| id.as_str().ok())
+                    .unwrap_or(&[]);
+                let handler = SecurityHandler::new(&encrypt, file_id, b"")?;
+                document.objects.clear();
+                document.security_handler = Some((handler, encrypt_id));
+            }
+        }
+        Ok(document)
+    }
+
+    /// Get an object, reading it from the source if it hasn't been read yet.
+    pub fn get_object(&mut self, id: ObjectId) -> Result<&Object> {
+        if !self.objects.contains_key(&id) {
+            let object = self.read_object(id)?;
+            self.objects.insert(id, object);
+        }
+        Ok(&self.objects[&id])
+    }
+
+    /// Get a dictionary object, reading it from the source if it hasn't been read yet.
+    pub fn get_dictionary(&mut self, id: ObjectId) -> Result<&Dictionary> {
+        self.get_object(id).and_then(Object::as_dict)
+    }
+
+    /// Check whether an object has been read from the source.
+    pub fn is_loaded(&self, id: ObjectId) -> bool {
+        self.objects.contains_key(&id)
+    }
+
+    /// Get the catalog dictionary.
+    pub fn catalog(&mut self) -> Result<&Dictionary> {
+        let catalog_id = self.trailer.get(b"Root").and_then(Object::as_reference)?;
+        self.get_dictionary(catalog_id)
+    }
+
+    /// Get the number of pages, from the /Count of the root of the page tree.
+    pub fn page_count(&mut self) -> Result<u32> {
+        let root_id = self.catalog()?.get(b"Pages").and_then(Object::as_reference)?;
+        Ok(self.get_dictionary(root_id)?.get(b"Count").and_then(Object::as_i64)? as u32)
+    }
+
+    /// Get the object id of a page, counting from one, reading only the page tree nodes leading to it.
+    pub fn page_id(&mut self, page_number: u32) -> Result<ObjectId> {
+        let not_found = Error::PageNumberNotFound(page_number);
+        let mut remaining = page_number
+            .checked_sub(1)
+            .ok_or(Error::PageNumberNotFound(page_number))?;
+        let mut node_id = self.catalog()?.get(b"Pages").and_then(Object::as_reference)?;
+        // Page tree nodes are limited by the number of objects, in case the tree has a cycle.
+        for _ in 0..self.reference_table.size {
+            let node = self.get_dictionary(node_id)?;
+            if node.type_is(b"Page") {
+                return if remaining == 0 { Ok(node_id) } else { Err(not_found) };
+            }
+            let kids: Vec<ObjectId> = node
+                .get(b"Kids")
+                .and_then(Object::as_array)?
+                .iter()
+                .filter_map(|kid| kid.as_reference().ok())
+                .collect();
+            let mut next_id = None;
+            for kid_id in kids {
+                let kid = self.get_dictionary(kid_id)?;
+                let count = if kid.type_is(b"Pages") {
+                    kid.get(b"Count").and_then(Object::as_i64).unwrap_or(0) as u32
+                } else {
+                    1
+                };
+                if remaining < count {
+                    next_id = Some(kid_id);
+                    break;
+                }
+                remaining -= count;
+            }
+            node_id = next_id.ok_or(Error::PageNumberNotFound(page_number))?;
+        }
+        Err(not_found)
+    }
+
+    /// Create a document of some pages, reading only the objects they use. Pages get the attributes they
+    /// inherit, and references to other pages, such as destinations of links, become null.
+    pub fn extract_pages(&mut self, page_numbers: &[u32]) -> Result<Document> {
+        let page_ids = page_numbers
+            .iter()
+            .map(|&page_number| self.page_id(page_number))
+            .collect::<Result<Vec<ObjectId>>>()?;
+        let mut document = Document::with_version(self.version.as_str());
+        let mut queue = Vec::new();
+        for &page_id in &page_ids {
+            let mut page = self.get_dictionary(page_id)?.clone();
+            let mut parent_id = page.remove(b"Parent").and_then(|parent| parent.as_reference().ok());
+            while let Some(id) = parent_id {
+                let parent = self.get_dictionary(id)?;
+                for &key in &INHERITED_ATTRIBUTES {
+                    if let (false, Ok(value)) = (page.has(key), parent.get(key)) {
+                        page.set(key.to_vec(), value.clone());
+                    }
+                }
+                parent_id = parent.get(b"Parent").and_then(Object::as_reference).ok();
+            }
+            let mut page = Object::Dictionary(page);
+            self.collect_references(&mut page, &page_ids, &mut queue)?;
+            document.objects.insert(page_id, page);
+        }
+        while let Some(id) = queue.pop() {
+            if document.objects.contains_key(&id) {
+                continue;
+            }
+            let mut object = self.get_object(id)?.clone();
+            self.collect_references(&mut object, &page_ids, &mut queue)?;
+            document.objects.insert(id, object);
+        }
+
+        document.max_id = document.objects.keys().map(|id| id.0).max().unwrap_or(0);
+        let pages_id = document.new_object_id();
+        for page_id in &page_ids {
+            if let Ok(page) = document.get_object_mut(*page_id).and_then(Object::as_dict_mut) {
+                page.set("Parent", pages_id);
+            }
+        }
+        let pages = dictionary! {
+            "Type" => "Pages",
+            "Kids" => page_ids.iter().map(|&id| id.into()).collect::<Vec<Object>>(),
+            "Count" => page_ids.len() as i64,
+        };
+        document.objects.insert(pages_id, Object::Dictionary(pages));
+        let catalog_id = document.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        document.trailer.set("Root", catalog_id);
+        Ok(document)
+    }
+
+    /// Read all objects into a document.
+    pub fn into_document(mut self) -> Result<Document> {
+        let ids: Vec<ObjectId> = self
+            .reference_table
+            .entries
+            .iter()
+            .filter_map(|(&number, entry)| match *entry {
+                XrefEntry::Normal { generation, .. } => Some((number, generation)),
+                XrefEntry::Compressed { .. } => Some((number, 0)),
+                XrefEntry::Free => None,
+            })
+            .collect();
+        for id in ids {
+            if let Err(err) = self.get_object(id) {
+                warn!("Object {:?} could not be read: {}", id, err);
+            }
+        }
+        let mut document = Document::new();
+        document.version = self.version;
+        document.trailer = self.trailer;
+        #[cfg(feature = "crypto")]
+        if let Some((_, Some(encrypt_id))) = self.security_handler {
+            document.trailer.remove(b"Encrypt");
+            self.objects.remove(&encrypt_id);
+        }
+        document.max_id = self.reference_table.size.saturating_sub(1);
+        document.reference_table = self.reference_table;
+        document.objects = self
+            .objects
+            .into_iter()
+            .filter(|(_, object)| !matches!(object, Object::Stream(stream) if stream.dict.type_is(b"XRef")))
+            .collect();
+        Ok(document)
+    }
+
+    /// Replace references to page tree nodes other than the pages, and to missing objects, by null, and queue
+    /// the other referenced objects.
+    fn collect_references(
+        &mut self, object: &mut Object, page_ids: &[ObjectId], queue: &mut Vec<ObjectId>,
+    ) -> Result<()> {
+        match object {
+            Object::Reference(id) => {
+                if page_ids.contains(id) {
+                    return Ok(());
+                }
+                let is_page_node = match self.get_object(*id) {
+                    Ok(Object::Dictionary(dict)) => dict.type_is(b"Page") || dict.type_is(b"Pages"),
+                    Ok(_) => false,
+                    Err(_) => true,
+                };
+                if is_page_node {
+                    *object = Object::Null;
+                } else {
+                    queue.push(*id);
+                }
+            }
+            Object::Array(array) => {
+                for item in array {
+                    self.collect_references(item, page_ids, queue)?;
+                }
+            }
+            Object::Dictionary(dict) => {
+                for (_, value) in dict.iter_mut() {
+                    self.collect_references(value, page_ids, queue)?;
+                }
+            }
+            Object::Stream(stream) => {
+                for (_, value) in stream.dict.iter_mut() {
+                    self.collect_references(value, page_ids, queue)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn read_object(&mut self, id: ObjectId) -> Result<Object> {
+        match self.reference_table.get(id.0).cloned() {
+            Some(XrefEntry::Normal { offset, generation }) if generation == id.1 => {
+                let offset = u64::from(offset);
+                let mut object = self
+                    .parse_at(offset, |window| {
+                        // Objects cut off by the end of the window could parse as other objects.
+                        if !window.windows(6).any(|keyword| keyword == b"endobj") {
+                            return Err(Error::Parse { offset: 0 });
+                        }
+                        parser::indirect_object(window, 0, Some(id), &Reader::new(window)).map(|(_, object)| object)
+                    })
+                    .map_err(|e| e.with_context(ErrorContext::in_object(id).with_offset(offset as usize)))?;
+                if let Object::Stream(ref mut stream) = object {
+                    if let Some(position) = stream.start_position {
+                        let length_id = stream.dict.get(b"Length").and_then(Object::as_reference)?;
+                        let length = self.get_object(length_id)?.as_i64()?;
+                        let content = self.read_at(offset + position as u64, length.max(0) as usize)?;
+                        stream.set_content(content);
+                        stream.start_position = None;
+                    }
+                }
+                #[cfg(feature = "crypto")]
+                if let Some((ref handler, encrypt_id)) = self.security_handler {
+                    if encrypt_id != Some(id) {
+                        handler.decrypt_object(id, &mut object);
+                    }
+                }
+                Ok(object)
+            }
+            Some(XrefEntry::Compressed { container, .. }) if id.1 == 0 => {
+                let mut stream = self.get_object((container, 0))?.as_stream()?.clone();
+                let mut result = None;
+                for (other_id, object) in ObjectStream::new(&mut stream)?.objects {
+                    if other_id == id {
+                        result = Some(object);
+                    } else if let Some(XrefEntry::Compressed {
+                        container: other_container,
+                        ..
+                    }) = self.reference_table.get(other_id.0)
+                    {
+                        // Other objects of the stream are kept, unless they were replaced by an update.
+                        if *other_container == container && other_id.1 == 0 {
+                            self.objects.entry(other_id).or_insert(object);
+                        }
+                    }
+                }
+                result.ok_or(Error::ObjectNotFound)
+            }
+            _ => Err(Error::ObjectNotFound),
+        }
+    }
+
+    fn read_xref_section(&mut self, start: u64) -> Result<(Xref, Dictionary)> {
+        self.parse_at(start, |window| parser::xref_and_trailer(window, &Reader::new(window)))
+    }
+
+    /// Parse the source from an offset, reading more of it while parsing fails.
+    fn parse_at<T, F: Fn(&[u8]) -> Result<T>>(&mut self, offset: u64, parse: F) -> Result<T> {
+        let mut size = WINDOW_SIZE;
+        loop {
+            let window = self.read_at(offset, size)?;
+            match parse(&window) {
+                Ok(result) => return Ok(result),
+                Err(err) if window.len() < size => return Err(err),
+                Err(_) => size *= 2,
+            }
+        }
+    }
+
+    fn read_at(&mut self, offset: u64, size: usize) -> Result<Vec<u8>> {
+        self.source.seek(SeekFrom::Start(offset))?;
+        let mut buffer = Vec::with_capacity(size);
+        (&mut self.source).take(size as u64).read_to_end(&mut buffer)?;
+        Ok(buffer)
+    }
+}
+
+#[test]
+fn read_objects_lazily() {
+    use crate::test_support::{append_page, create_document};
+    use crate::SaveOptions;
+    use std::io::Cursor;
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    for text in ["Second", "Third"] {
+        let content = format!("BT /F1 48 Tf 100 600 Td ({}) Tj ET", text);
+        let content_id = doc.add_object(crate::Stream::new(dictionary! {}, content.into_bytes()));
+        let mut page = doc.get_dictionary(page_id).unwrap().clone();
+        page.set("Contents", content_id);
+        append_page(&mut doc, page);
+    }
+    let mut buffer = Vec::new();
+    let options = SaveOptions {
+        use_object_streams: true,
+        ..SaveOptions::default()
+    };
+    doc.save_to_with_options(&mut buffer, &options).unwrap();
+
+    let mut lazy = LazyDocument::new(Cursor::new(buffer.clone())).unwrap();
+    assert_eq!(lazy.version, "1.5");
+    assert_eq!(lazy.page_count().unwrap(), 3);
+    let third_id = lazy.page_id(3).unwrap();
+    assert!(lazy.page_id(4).is_err());
+    // Objects in object streams are read with their siblings, but streams are only read when they are used.
+    let content_id = doc.get_page_contents(page_id)[0];
+    assert!(!lazy.is_loaded(content_id));
+
+    let extracted = lazy.extract_pages(&[3]).unwrap();
+    assert_eq!(extracted.page_iter().collect::<Vec<_>>(), vec![third_id]);
+    assert_eq!(extracted.extract_text(&[1]).unwrap(), "Third\n");
+    assert!(!lazy.is_loaded(content_id));
+
+    let loaded = Document::load_mem(&buffer).unwrap();
+    let document = lazy.into_document().unwrap();
+    assert_eq!(document.get_pages(), loaded.get_pages());
+    assert_eq!(document.extract_text(&[2]).unwrap(), "Second\n");
+
+    let mut lazy = LazyDocument::open("assets/example.pdf").unwrap();
+    let loaded = Document::load("assets/example.pdf").unwrap();
+    assert_eq!(lazy.page_count().unwrap() as usize, loaded.get_pages().len());
+    assert_eq!(lazy.page_id(1).unwrap(), loaded.get_pages()[&1]);
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn read_mapped_streams() {
+    let loaded = Document::load_mmap("assets/example.pdf").unwrap();
+    assert_eq!(
+        loaded.get_pages(),
+        Document::load("assets/example.pdf").unwrap().get_pages()
+    );
+
+    let mut lazy = LazyDocument::open_mmap("assets/example.pdf").unwrap();
+    let content_id = loaded.get_page_contents(lazy.page_id(1).unwrap())[0];
+    let (dict, data) = lazy.get_mapped_stream(content_id).unwrap();
+    assert_eq!(dict.get(b"Length").unwrap(), &Object::Integer(data.len() as i64));
+    let stream = loaded.get_object(content_id).and_then(Object::as_stream).unwrap();
+    assert_eq!(data, stream.content.as_slice());
+    assert!(!lazy.is_loaded(content_id));
+    let page_id = lazy.page_id(1).unwrap();
+    assert!(lazy.get_mapped_stream(page_id).is_err());
+
+    // Data which doesn't end at the length of the stream, the last byte of which is an end of line.
+    let mut doc = crate::test_support::create_document();
+    let content_id = doc.get_page_contents(doc.page_iter().next().unwrap())[0];
+    let length = doc
+        .get_object(content_id)
+        .and_then(Object::as_stream)
+        .unwrap()
+        .content
+        .len();
+    let mut buffer = Vec::new();
+    doc.save_to(&mut buffer).unwrap();
+    let entry = format!("/Length {}", length);
+    let position = Reader::find(&buffer, entry.as_bytes(), 0).unwrap();
+    buffer.splice(
+        position..position + entry.len(),
+        format!("/Length {}", length - 2).into_bytes(),
+    );
+    let path = std::env::temp_dir().join("lopdf_mapped_stream_length.pdf");
+    std::fs::write(&path, &buffer).unwrap();
+    let mut lazy = LazyDocument::open_mmap(&path).unwrap();
+    assert!(lazy.get_mapped_stream(content_id).is_err());
+    std::fs::remove_file(&path).unwrap();
+}
@@ -1,17 +1,70 @@
 #[macro_use]
 mod object;
 mod datetime;
+pub use crate::datetime::PdfDate;
+mod destinations;
 pub use crate::object::{Dictionary, Object, ObjectId, Stream, StringFormat};
 
 mod document;
+#[cfg(feature = "crypto")]
+mod encryption;
+mod incremental;
+mod linearize;
+mod merge;
+mod name_tree;
+mod split;
 mod object_stream;
+mod page;
 mod xref;
 pub use crate::document::Document;
+#[cfg(feature = "crypto")]
+pub use crate::encryption::{EncryptionAlgorithm, EncryptionOptions, Permissions};
+pub use crate::incremental::Revision;
 
+pub mod annotations;
+pub mod attachments;
+pub mod collection;
+pub mod color;
 pub mod content;
 mod creator;
 mod encodings;
+mod fdf;
 pub mod filters;
+#[cfg(feature = "fonts")]
+pub mod fonts;
+pub mod forms;
+mod geometry;
+pub mod images;
+pub mod impose;
+pub mod interpreter;
+#[cfg(feature = "serde")]
+pub mod json;
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+pub mod lazy;
+pub mod ocg;
+pub mod outlines;
+pub mod page_labels;
+pub mod paths;
+#[cfg(feature = "xmp")]
+pub mod pdfa;
+#[cfg(feature = "xmp")]
+pub mod preflight;
+#[cfg(feature = "pubsec")]
+mod pubsec;
+#[cfg(feature = "pubsec")]
+pub use crate::pubsec::{PublicKeyEncryptionOptions, Recipient};
+pub mod redact;
+pub mod references;
+pub mod signatures;
+pub mod stamp;
+pub mod structure;
+#[cfg(test)]
+mod test_support;
+pub mod text;
+pub mod transitions;
+pub mod units;
+pub use crate::geometry::{Decomposition, Matrix, Point, Rectangle};
+pub use crate::page::{FitMode, Page, PageBox};
 #[cfg(not(feature = "nom_parser"))]
 #[cfg(feature = "pom_parser")]
 mod parser;
@@ -21,8 +74,18 @@ mod parser;
 mod parser_aux;
 mod processor;
 mod reader;
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+pub use crate::reader::{LoadOptions, ScannedObject};
+#[cfg(feature = "serde")]
+mod serialization;
+pub mod viewer;
 mod writer;
+pub use crate::writer::SaveOptions;
+#[cfg(feature = "xmp")]
+mod xfdf;
+#[cfg(feature = "xmp")]
+pub mod xmp;
 pub mod xobject;
 
 mod error;
-pub use error::{Error, Result};
+pub use error::{DecryptionError, Error, ErrorContext, LimitError, LoadWarning, LoadWarningKind, Result};
@@ -0,0 +1,584 @@
+use crate::writer::{CountingWrite, Writer, XrefRow};
+use crate::xref::Xref;
+use crate::{Dictionary, Document, Error, Object, ObjectId, Result, Stream};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Catalog entries whose objects belong to the document level part at the start of a linearized file.
+const DOCUMENT_LEVEL_KEYS: [&[u8]; 4] = [b"ViewerPreferences", b"OpenAction", b"AcroForm", b"Threads"];
+
+/// Trailer entries carried over into the trailer of a linearized file.
+const TRAILER_KEYS: [&[u8]; 4] = [b"Root", b"Info", b"ID", b"Encrypt"];
+
+impl Document {
+    /// Save PDF document linearized for fast web view to specified file path.
+    pub fn save_linearized<P: AsRef<Path>>(&self, path: P) -> Result<File> {
+        let mut file = BufWriter::new(File::create(path)?);
+        self.save_linearized_to(&mut file)?;
+        Ok(file.into_inner().map_err(io::Error::from)?)
+    }
+
+    /// Save linearized PDF to arbitrary target.
+    ///
+    /// Objects are reordered and renumbered, so that the first page can be displayed before the rest of the file
+    /// has arrived, and hint tables telling where to find the other pages are added. Only objects reachable
+    /// from the trailer are written.
+    pub fn save_linearized_to<W: Write>(&self, target: &mut W) -> Result<()> {
+        Linearizer::new(self)?.write(target)
+    }
+}
+
+/// Values of the linearization parameter dictionary and of the first page trailer, which depend on the final layout.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Parameters {
+    file_length: usize,
+    hint_offset: usize,
+    hint_length: usize,
+    first_page_end: usize,
+    main_xref_entries: usize,
+    main_xref_offset: usize,
+}
+
+struct Linearizer<'a> {
+    document: &'a Document,
+    /// Catalog and other document level objects.
+    document_level: Vec<ObjectId>,
+    /// Objects of the first page, starting with the page object, including objects shared with other pages.
+    first_page: Vec<ObjectId>,
+    /// Objects used only by one of the other pages, starting with the page object.
+    pages: Vec<Vec<ObjectId>>,
+    /// Objects used by several pages, but not by the first one.
+    shared: Vec<ObjectId>,
+    /// Page tree nodes, outlines, the info dictionary and everything else.
+    other: Vec<ObjectId>,
+    /// Entries of the shared object hint table: objects of the first page used by other pages, then `shared`.
+    shared_entries: Vec<ObjectId>,
+    /// Indices into `shared_entries` of the shared objects used by each of the other pages.
+    shared_references: Vec<Vec<usize>>,
+    /// New object numbers.
+    ids: BTreeMap<ObjectId, u32>,
+    linearization_id: u32,
+    hint_id: u32,
+    /// Number of objects written after the first page section, they are numbered before it.
+    main_count: u32,
+}
+
+impl Linearizer<'_> {
+    fn new(document: &Document) -> Result<Linearizer<'_>> {
+        let catalog_id = document.trailer.get(b"Root").and_then(Object::as_reference)?;
+        let catalog = document.get_dictionary(catalog_id)?;
+        let page_ids: Vec<ObjectId> = document.page_iter().collect();
+        if page_ids.is_empty() {
+            return Err(Error::PageNumberNotFound(1));
+        }
+
+        // Don't follow references from one page to another one or up the page tree.
+        let page_tree: BTreeSet<ObjectId> = document
+            .objects
+            .iter()
+            .filter(|(_, object)| {
+                object
+                    .type_name()
+                    .map(|name| ["Page", "Pages", "Catalog"].contains(&name))
+                    .unwrap_or(false)
+            })
+            .map(|(id, _)| *id)
+            .chain(page_ids.iter().cloned())
+            .collect();
+        let page_objects: Vec<Vec<ObjectId>> = page_ids
+            .iter()
+            .map(|page_id| collect_objects(document, vec![*page_id], &page_tree))
+            .collect();
+        let mut use_count = BTreeMap::new();
+        for objects in &page_objects {
+            for id in objects {
+                *use_count.entry(*id).or_insert(0) += 1;
+            }
+        }
+
+        let first_page = page_objects[0].clone();
+        let mut assigned: BTreeSet<ObjectId> = first_page.iter().cloned().collect();
+        let shared_with_first_page: Vec<ObjectId> =
+            first_page.iter().filter(|id| use_count[*id] > 1).cloned().collect();
+        let mut pages = Vec::new();
+        let mut shared = Vec::new();
+        for objects in &page_objects[1..] {
+            let mut page = Vec::new();
+            for id in objects {
+                if use_count[id] == 1 {
+                    page.push(*id);
+                } else if !assigned.contains(id) && !shared.contains(id) {
+                    shared.push(*id);
+                }
+            }
+            assigned.extend(page.iter().cloned());
+            pages.push(page);
+        }
+        assigned.extend(shared.iter().cloned());
+
+        let mut starts = vec![catalog_id];
+        starts.extend(document.trailer.get(b"Encrypt").and_then(Object::as_reference));
+        for key in DOCUMENT_LEVEL_KEYS.iter() {
+            starts.extend(catalog.get(key).and_then(Object::as_reference));
+        }
+        let mut stop = page_tree.clone();
+        stop.extend(assigned.iter().cloned());
+        let document_level = collect_objects(document, starts, &stop);
+        assigned.extend(document_level.iter().cloned());
+
+        let mut trailer_references = Vec::new();
+        collect_references(&Object::Dictionary(document.trailer.clone()), &mut trailer_references);
+        let other: Vec<ObjectId> = collect_objects(document, trailer_references, &BTreeSet::new())
+            .into_iter()
+            .filter(|id| !assigned.contains(id))
+            .collect();
+
+        let shared_entries: Vec<ObjectId> = shared_with_first_page.iter().chain(shared.iter()).cloned().collect();
+        let shared_references = page_objects[1..]
+            .iter()
+            .map(|objects| {
+                objects
+                    .iter()
+                    .filter_map(|id| shared_entries.iter().position(|entry| entry == id))
+                    .collect()
+            })
+            .collect();
+
+        let mut ids = BTreeMap::new();
+        for id in pages.iter().flatten().chain(shared.iter()).chain(other.iter()) {
+            let new_id = ids.len() as u32 + 1;
+            ids.insert(*id, new_id);
+        }
+        let main_count = ids.len() as u32;
+        let linearization_id = main_count + 1;
+        for id in document_level.iter().chain(first_page.iter()) {
+            let new_id = ids.len() as u32 + 2;
+            ids.insert(*id, new_id);
+        }
+        let hint_id = ids.len() as u32 + 2;
+
+        Ok(Linearizer {
+            document,
+            document_level,
+            first_page,
+            pages,
+            shared,
+            other,
+            shared_entries,
+            shared_references,
+            ids,
+            linearization_id,
+            hint_id,
+            main_count,
+        })
+    }
+
+    fn write<W: Write>(&self, target: &mut W) -> Result<()> {
+        let mut objects = BTreeMap::new();
+        for (&id, &new_id) in &self.ids {
+            let mut object = self.document.objects[&id].clone();
+            self.renumber(&mut object);
+            objects.insert(id, serialize(new_id, &object)?);
+        }
+        let length = |ids: &[ObjectId]| ids.iter().map(|id| objects[id].len()).sum::<usize>();
+
+        let header = format!("%PDF-{}\n%\u{e2}\u{e3}\u{cf}\u{d3}\n", self.document.version).into_bytes();
+        let mut trailer = Dictionary::new();
+        for key in TRAILER_KEYS.iter() {
+            if let Ok(value) = self.document.trailer.get(key) {
+                trailer.set(key.to_vec(), value.clone());
+            }
+        }
+        let mut trailer = Object::Dictionary(trailer);
+        self.renumber(&mut trailer);
+        let mut trailer = match trailer {
+            Object::Dictionary(trailer) => trailer,
+            _ => unreachable!(),
+        };
+        trailer.set("Size", i64::from(self.hint_id + 1));
+        let first_xref_count = (self.hint_id - self.main_count) as usize;
+        let main_trailer = dictionary! { "Size" => i64::from(self.main_count + 1) };
+
+        // Offsets and lengths only grow with the number of digits needed for them, so this converges quickly.
+        let mut parameters = Parameters::default();
+        loop {
+            let linearization = serialize(self.linearization_id, &self.linearization_dictionary(&parameters))?;
+            trailer.set("Prev", parameters.main_xref_offset as i64);
+            let mut first_trailer = b"trailer\n".to_vec();
+            Writer::write_dictionary(&mut first_trailer, &trailer)?;
+            first_trailer.extend_from_slice(b"\nstartxref\n0\n%%EOF\n");
+            let first_xref_length =
+                format!("xref\n{} {}\n", self.linearization_id, first_xref_count).len() + 20 * first_xref_count;
+
+            // Offsets in the hint tables are computed as if the hint stream wasn't there.
+            let first_xref_offset = header.len() + linearization.len();
+            let hint_offset =
+                first_xref_offset + first_xref_length + first_trailer.len() + length(&self.document_level);
+            let hint_stream = self.hint_stream(hint_offset, &objects);
+            let hint_stream = serialize(self.hint_id, &Object::Stream(hint_stream))?;
+            let first_page_end = hint_offset + hint_stream.len() + length(&self.first_page);
+            let main_xref_offset = first_page_end
+                + self.pages.iter().map(|page| length(page)).sum::<usize>()
+                + length(&self.shared)
+                + length(&self.other);
+            let main_xref_header = format!("xref\n0 {}\n", self.main_count + 1);
+            let mut main_trailer_bytes = b"trailer\n".to_vec();
+            Writer::write_dictionary(&mut main_trailer_bytes, &main_trailer)?;
+            write!(main_trailer_bytes, "\nstartxref\n{}\n%%EOF", first_xref_offset)?;
+            let file_length = main_xref_offset
+                + main_xref_header.len()
+                + 20 * (self.main_count as usize + 1)
+                + main_trailer_bytes.len();
+
+            let next = Parameters {
+                file_length,
+                hint_offset,
+                hint_length: hint_stream.len(),
+                first_page_end,
+                main_xref_entries: main_xref_offset + main_xref_header.len() - 1,
+                main_xref_offset,
+            };
+            if next != parameters {
+                parameters = next;
+                continue;
+            }
+
+            let mut target = CountingWrite {
+                inner: target,
+                bytes_written: 0,
+            };
+            target.write_all(&header)?;
+            target.write_all(&linearization)?;
+
+            let mut first_rows = BTreeMap::new();
+            first_rows.insert(self.linearization_id, (1, header.len() as u32, 0));
+            first_rows.insert(self.hint_id, (1, hint_offset as u32, 0));
+            let mut main_rows: BTreeMap<u32, XrefRow> = BTreeMap::new();
+            main_rows.insert(0, (0, 0, 65535));
+            let mut offset = first_xref_offset + first_xref_length + first_trailer.len();
+            for id in self.document_level.iter() {
+                first_rows.insert(self.ids[id], (1, offset as u32, 0));
+                offset += objects[id].len();
+            }
+            offset += hint_stream.len();
+            for id in self.first_page.iter() {
+                first_rows.insert(self.ids[id], (1, offset as u32, 0));
+                offset += objects[id].len();
+            }
+            for id in self.main_objects() {
+                main_rows.insert(self.ids[id], (1, offset as u32, 0));
+                offset += objects[id].len();
+            }
+
+            Writer::write_xref_section(&mut target, &first_rows)?;
+            target.write_all(&first_trailer)?;
+            for id in self.document_level.iter() {
+                target.write_all(&objects[id])?;
+            }
+            target.write_all(&hint_stream)?;
+            for id in self.first_page.iter().chain(self.main_objects()) {
+                target.write_all(&objects[id])?;
+            }
+            Writer::write_xref_section(&mut target, &main_rows)?;
+            target.write_all(&main_trailer_bytes)?;
+            debug_assert_eq!(target.bytes_written, parameters.file_length);
+            return Ok(());
+        }
+    }
+
+    /// Objects written after the first page section, in file order.
+    fn main_objects(&self) -> impl Iterator<Item = &ObjectId> {
+        self.pages
+            .iter()
+            .flatten()
+            .chain(self.shared.iter())
+            .chain(self.other.iter())
+    }
+
+    fn linearization_dictionary(&self, parameters: &Parameters) -> Object {
+        Object::Dictionary(dictionary! {
+            "Linearized" => 1,
+            "L" => parameters.file_length as i64,
+            "H" => vec![
+                Object::Integer(parameters.hint_offset as i64),
+                Object::Integer(parameters.hint_length as i64),
+            ],
+            "O" => i64::from(self.ids[&self.first_page[0]]),
+            "E" => parameters.first_page_end as i64,
+            "N" => self.pages.len() as i64 + 1,
+            "T" => parameters.main_xref_entries as i64,
+        })
+    }
+
+    /// Build the primary hint stream with the page offset and shared object hint tables.
+    /// `first_page_offset` is the offset of the first page object, ignoring the hint stream.
+    fn hint_stream(&self, first_page_offset: usize, objects: &BTreeMap<ObjectId, Vec<u8>>) -> Stream {
+        let length = |ids: &[ObjectId]| ids.iter().map(|id| objects[id].len() as u32).sum::<u32>();
+        let object_counts: Vec<u32> = std::iter::once(self.first_page.len() as u32)
+            .chain(self.pages.iter().map(|page| page.len() as u32))
+            .collect();
+        let page_lengths: Vec<u32> = std::iter::once(length(&self.first_page))
+            .chain(self.pages.iter().map(|page| length(page)))
+            .collect();
+        // The first page uses no objects of the shared objects section.
+        let shared_references: Vec<&[usize]> = std::iter::once(&[][..])
+            .chain(self.shared_references.iter().map(Vec::as_slice))
+            .collect();
+
+        let least_objects = object_counts.iter().cloned().min().unwrap_or(0);
+        let object_bits = bits_needed(
+            object_counts
+                .iter()
+                .map(|count| count - least_objects)
+                .max()
+                .unwrap_or(0),
+        );
+        let least_length = page_lengths.iter().cloned().min().unwrap_or(0);
+        let length_bits = bits_needed(
+            page_lengths
+                .iter()
+                .map(|length| length - least_length)
+                .max()
+                .unwrap_or(0),
+        );
+        let shared_count_bits = bits_needed(
+            shared_references
+                .iter()
+                .map(|refs| refs.len() as u32)
+                .max()
+                .unwrap_or(0),
+        );
+        let shared_id_bits = bits_needed(
+            shared_references
+                .iter()
+                .flat_map(|refs| refs.iter())
+                .map(|index| *index as u32)
+                .max()
+                .unwrap_or(0),
+        );
+
+        let mut bits = BitWriter::default();
+        // Page offset hint table header.
+        bits.write(least_objects, 32);
+        bits.write(first_page_offset as u32, 32);
+        bits.write(object_bits, 16);
+        bits.write(least_length, 32);
+        bits.write(length_bits, 16);
+        // Content stream offsets and lengths are left out, like most writers do.
+        bits.write(0, 32);
+        bits.write(0, 16);
+        bits.write(0, 32);
+        bits.write(0, 16);
+        bits.write(shared_count_bits, 16);
+        bits.write(shared_id_bits, 16);
+        bits.write(0, 16);
+        bits.write(1, 16);
+        // Page offset hint table entries, each item for all pages in turn.
+        for count in &object_counts {
+            bits.write(count - least_objects, object_bits);
+        }
+        bits.align();
+        for length in &page_lengths {
+            bits.write(length - least_length, length_bits);
+        }
+        bits.align();
+        for refs in &shared_references {
+            bits.write(refs.len() as u32, shared_count_bits);
+        }
+        bits.align();
+        for refs in &shared_references {
+            for index in refs.iter() {
+                bits.write(*index as u32, shared_id_bits);
+            }
+        }
+        bits.align();
+        let shared_table_offset = bits.bytes.len();
+
+        // Shared object hint table, every shared object is a group of its own.
+        let group_lengths: Vec<u32> = self.shared_entries.iter().map(|id| objects[id].len() as u32).collect();
+        let least_group_length = group_lengths.iter().cloned().min().unwrap_or(0);
+        let group_length_bits = bits_needed(
+            group_lengths
+                .iter()
+                .map(|length| length - least_group_length)
+                .max()
+                .unwrap_or(0),
+        );
+        let (first_shared_id, first_shared_offset) = match self.shared.first() {
+            Some(id) => {
+                let preceding = length(&self.first_page) + self.pages.iter().map(|page| length(page)).sum::<u32>();
+                (self.ids[id], first_page_offset as u32 + preceding)
+            }
+            None => (0, 0),
+        };
+        bits.write(first_shared_id, 32);
+        bits.write(first_shared_offset, 32);
+        bits.write((self.shared_entries.len() - self.shared.len()) as u32, 32);
+        bits.write(self.shared_entries.len() as u32, 32);
+        bits.write(0, 16);
+        bits.write(least_group_length, 32);
+        bits.write(group_length_bits, 16);
+        for length in &group_lengths {
+            bits.write(length - least_group_length, group_length_bits);
+        }
+        bits.align();
+        // No MD5 signatures.
+        for _ in &group_lengths {
+            bits.write(0, 1);
+        }
+        bits.align();
+
+        let mut stream = Stream::new(dictionary! { "S" => shared_table_offset as i64 }, bits.bytes);
+        // Ignore any compression error.
+        let _ = stream.compress();
+        stream
+    }
+
+    /// Replace references by the new object numbers, references to objects which aren't written become null.
+    fn renumber(&self, object: &mut Object) {
+        match *object {
+            Object::Array(ref mut array) => {
+                for item in array.iter_mut() {
+                    self.renumber(item);
+                }
+            }
+            Object::Dictionary(ref mut dict) => {
+                for (_, value) in dict.iter_mut() {
+                    self.renumber(value);
+                }
+            }
+            Object::Stream(ref mut stream) => {
+                for (_, value) in stream.dict.iter_mut() {
+                    self.renumber(value);
+                }
+            }
+            Object::Reference(id) => {
+                *object = match self.ids.get(&id) {
+                    Some(new_id) => Object::Reference((*new_id, 0)),
+                    None => Object::Null,
+                };
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Collect ids of objects reachable from `starts` in depth-first order, without following references into `stop`.
+fn collect_objects(document: &Document, starts: Vec<ObjectId>, stop: &BTreeSet<ObjectId>) -> Vec<ObjectId> {
+    let mut result = Vec::new();
+    let mut visited = BTreeSet::new();
+    let mut stack: Vec<ObjectId> = starts.into_iter().rev().collect();
+    while let Some(id) = stack.pop() {
+        if !visited.insert(id) {
+            continue;
+        }
+        let object = match document.objects.get(&id) {
+            Some(object) => object,
+            None => continue,
+        };
+        result.push(id);
+        let mut references = Vec::new();
+        collect_references(object, &mut references);
+        stack.extend(references.into_iter().rev().filter(|id| !stop.contains(id)));
+    }
+    result
+}
+
+/// Collect the references made by an object, without following them.
+pub(crate) fn collect_references(object: &Object, references: &mut Vec<ObjectId>) {
+    match *object {
+        Object::Array(ref array) => {
+            for item in array {
+                collect_references(item, references);
+            }
+        }
+        Object::Dictionary(ref dict) => {
+            for (_, value) in dict.iter() {
+                collect_references(value, references);
+            }
+        }
+        Object::Stream(ref stream) => {
+            for (_, value) in stream.dict.iter() {
+                collect_references(value, references);
+            }
+        }
+        Object::Reference(id) => references.push(id),
+        _ => {}
+    }
+}
+
+fn serialize(id: u32, object: &Object) -> io::Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    let mut target = CountingWrite {
+        inner: &mut buffer,
+        bytes_written: 0,
+    };
+    Writer::write_indirect_object(&mut target, id, 0, object, &mut Xref::new(0))?;
+    Ok(buffer)
+}
+
+fn bits_needed(value: u32) -> u32 {
+    32 - value.leading_zeros()
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    /// Number of bits used in the last byte.
+    used: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, bits: u32) {
+        for bit in (0..bits).rev() {
+            if self.used == 0 {
+                self.bytes.push(0);
+            }
+            if (value >> bit) & 1 == 1 {
+                *self.bytes.last_mut().unwrap() |= 0x80 >> self.used;
+            }
+            self.used = (self.used + 1) % 8;
+        }
+    }
+
+    fn align(&mut self) {
+        self.used = 0;
+    }
+}
+
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+#[test]
+fn save_linearized_document() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    let mut other = create_document();
+    other.replace_text(1, "Hello World!", "Second page").unwrap();
+    doc.merge(other).unwrap();
+
+    let mut buffer = Vec::new();
+    doc.save_linearized_to(&mut buffer).unwrap();
+    let linearized = Document::load_mem(&buffer).unwrap();
+    assert_eq!(linearized.extract_text(&[1, 2]).unwrap(), "Hello World!\nSecond page\n");
+
+    let parameters = linearized
+        .objects
+        .values()
+        .filter_map(|object| object.as_dict().ok())
+        .find(|dict| dict.has(b"Linearized"))
+        .unwrap();
+    assert_eq!(
+        parameters.get(b"L").and_then(Object::as_i64).unwrap(),
+        buffer.len() as i64
+    );
+    assert_eq!(parameters.get(b"N").and_then(Object::as_i64).unwrap(), 2);
+    let first_page = parameters.get(b"O").and_then(Object::as_i64).unwrap() as u32;
+    assert_eq!(linearized.get_pages()[&1].0, first_page);
+    let first_page_end = parameters.get(b"E").and_then(Object::as_i64).unwrap() as usize;
+    let first_page_object = format!("{} 0 obj", first_page).into_bytes();
+    assert!(buffer[..first_page_end]
+        .windows(first_page_object.len())
+        .any(|window| window == first_page_object.as_slice()));
+}
@@ -0,0 +1,599 @@
+use crate::page_labels::{PageLabel, PageLabelStyle};
+use crate::{Dictionary, Document, Error, Object, ObjectId, Result};
+use log::warn;
+use std::collections::{BTreeMap, BTreeSet};
+
+impl Document {
+    /// Merge another document into this one.
+    ///
+    /// Objects of `other` are renumbered after the objects of this document, its page tree is appended
+    /// to this document's page tree, and its outline items, `/Dests` entries, name trees (named
+    /// destinations, embedded files, ...) and form fields are appended to the ones of this document.
+    /// Names of `other` which this document already uses are made unique with a number suffix, and
+    /// references of `other` to renamed destinations are updated.
+    /// When this document has no catalog yet, `other` is adopted as a whole.
+    pub fn merge(&mut self, mut other: Document) -> Result<()> {
+        other.catalog()?;
+        let start_id = self.objects.keys().map(|id| id.0).max().unwrap_or(0).max(self.max_id) + 1;
+        other.renumber_objects_with(start_id);
+        if other.version > self.version {
+            self.version = other.version.clone();
+        }
+
+        if self.catalog().is_err() {
+            self.objects.append(&mut other.objects);
+            self.trailer = other.trailer;
+            self.max_id = other.max_id;
+            return Ok(());
+        }
+
+        other.rename_colliding_names(self)?;
+        let page_count = other.page_iter().count() as i64;
+        let other_labels = other.get_page_labels()?;
+        let mut other_label_nodes = Vec::new();
+        if let Ok(root) = other.catalog()?.get(b"PageLabels") {
+            other.collect_number_tree(root, &mut Vec::new(), &mut other_label_nodes);
+        }
+        let other_catalog_id = other.trailer.get(b"Root").and_then(Object::as_reference)?;
+        let other_catalog = match other.objects.remove(&other_catalog_id) {
+            Some(Object::Dictionary(dict)) => dict,
+            _ => return Err(Error::ObjectNotFound),
+        };
+        let other_pages_id = other_catalog.get(b"Pages").and_then(Object::as_reference)?;
+        let other_dests = other_catalog
+            .get(b"Dests")
+            .and_then(|dests| other.dereference(dests))
+            .and_then(|(_, dests)| dests.as_dict())
+            .cloned()
+            .ok();
+        let other_names = other_catalog
+            .get(b"Names")
+            .and_then(|names| other.dereference(names))
+            .and_then(|(_, names)| names.as_dict())
+            .cloned()
+            .ok();
+        let other_outlines_id = other_catalog.get(b"Outlines").and_then(Object::as_reference).ok();
+        let other_acro_form = other_catalog
+            .get(b"AcroForm")
+            .and_then(|acro_form| other.dereference(acro_form))
+            .and_then(|(_, acro_form)| acro_form.as_dict())
+            .cloned()
+            .ok();
+
+        self.objects.append(&mut other.objects);
+        self.max_id = self.max_id.max(other.max_id);
+
+        for node in other_label_nodes {
+            self.objects.remove(&node);
+        }
+
+        let catalog_id = self.trailer.get(b"Root").and_then(Object::as_reference)?;
+        let own_page_count = self.page_iter().count() as u32;
+        self.merge_page_tree(catalog_id, other_pages_id, page_count)?;
+        self.merge_page_labels(own_page_count, other_labels)?;
+        if let Some(outlines_id) = other_outlines_id {
+            self.merge_outlines(catalog_id, outlines_id)?;
+        }
+        if let Some(dests) = other_dests {
+            self.merge_dests(catalog_id, dests)?;
+        }
+        if let Some(names) = other_names {
+            self.merge_names(names)?;
+        }
+        if let Some(acro_form) = other_acro_form {
+            self.merge_acro_form(catalog_id, acro_form)?;
+        }
+        Ok(())
+    }
+
+    /// Rename the entries of the /Dests dictionary and of the name trees which another document also has, and
+    /// update the references to renamed destinations, so that this document can be merged into the other one.
+    fn rename_colliding_names(&mut self, used: &Document) -> Result<()> {
+        let catalog_id = self.trailer.get(b"Root").and_then(Object::as_reference)?;
+
+        // Destinations given by names are looked up in the /Dests dictionary, and those given by strings in the
+        // /Dests name tree.
+        let mut renamed_names = BTreeMap::new();
+        let used_dests: BTreeSet<Vec<u8>> = used
+            .catalog()?
+            .get(b"Dests")
+            .and_then(|dests| used.dereference(dests))
+            .and_then(|(_, dests)| dests.as_dict())
+            .map(|dests| dests.iter().map(|(name, _)| name.clone()).collect())
+            .unwrap_or_default();
+        let dests = self
+            .catalog()?
+            .get(b"Dests")
+            .and_then(|dests| self.dereference(dests))
+            .and_then(|(id, dests)| Ok((id, dests.as_dict()?.clone())));
+        if let Ok((dests_id, dests)) = dests {
+            let mut taken: BTreeSet<Vec<u8>> = dests.iter().map(|(name, _)| name.clone()).collect();
+            taken.extend(used_dests.iter().cloned());
+            let mut renamed = Dictionary::new();
+            for (name, dest) in dests.iter() {
+                if used_dests.contains(name) {
+                    let new_name = unique_name(name, &mut taken);
+                    renamed_names.insert(name.clone(), new_name.clone());
+                    renamed.set(new_name, dest.clone());
+                } else {
+                    renamed.set(name.clone(), dest.clone());
+                }
+            }
+            if !renamed_names.is_empty() {
+                match dests_id {
+                    Some(dests_id) => *self.get_object_mut(dests_id)? = Object::Dictionary(renamed),
+                    None => self
+                        .get_object_mut(catalog_id)
+                        .and_then(Object::as_dict_mut)?
+                        .set("Dests", renamed),
+                }
+            }
+        }
+
+        let mut renamed_strings = BTreeMap::new();
+        let keys: Vec<Vec<u8>> = self
+            .catalog()?
+            .get(b"Names")
+            .and_then(|names| self.dereference(names))
+            .and_then(|(_, names)| names.as_dict())
+            .map(|names| names.iter().map(|(key, _)| key.clone()).collect())
+            .unwrap_or_default();
+        for key in keys {
+            let used_names: BTreeSet<Vec<u8>> = used.get_name_tree(&key)?.into_iter().map(|(name, _)| name).collect();
+            let mut entries = self.get_name_tree(&key)?;
+            let mut taken: BTreeSet<Vec<u8>> = entries.iter().map(|(name, _)| name.clone()).collect();
+            taken.extend(used_names.iter().cloned());
+            let mut is_renamed = false;
+            for (name, _) in entries.iter_mut() {
+                if used_names.contains(name) {
+                    let new_name = unique_name(name, &mut taken);
+                    if key == b"Dests" {
+                        renamed_strings.insert(name.clone(), new_name.clone());
+                    }
+                    *name = new_name;
+                    is_renamed = true;
+                }
+            }
+            if is_renamed {
+                self.set_name_tree(&key, entries)?;
+            }
+        }
+
+        if !renamed_names.is_empty() || !renamed_strings.is_empty() {
+            for object in self.objects.values_mut() {
+                rename_destinations(object, &renamed_names, &renamed_strings);
+            }
+        }
+        Ok(())
+    }
+
+    fn merge_page_tree(&mut self, catalog_id: ObjectId, other_pages_id: ObjectId, page_count: i64) -> Result<()> {
+        let pages_id = self
+            .get_dictionary(catalog_id)?
+            .get(b"Pages")
+            .and_then(Object::as_reference)?;
+        let pages = self.get_dictionary(pages_id)?.clone();
+
+        // Attributes of our page tree root must not be inherited by pages of the other document.
+        let other_pages = self.get_object_mut(other_pages_id).and_then(Object::as_dict_mut)?;
+        if pages.has(b"Rotate") && !other_pages.has(b"Rotate") {
+            other_pages.set("Rotate", 0);
+        }
+        if pages.has(b"Resources") && !other_pages.has(b"Resources") {
+            other_pages.set("Resources", Dictionary::new());
+        }
+        if pages.has(b"CropBox") && !other_pages.has(b"CropBox") {
+            if let Ok(media_box) = other_pages.get(b"MediaBox").cloned() {
+                other_pages.set("CropBox", media_box);
+            }
+        }
+        other_pages.set("Parent", pages_id);
+
+        let pages = self.get_object_mut(pages_id).and_then(Object::as_dict_mut)?;
+        let count = pages.get(b"Count").and_then(Object::as_i64).unwrap_or(0);
+        pages.set("Count", count + page_count);
+        if let Ok(kids) = pages.get_mut(b"Kids").and_then(Object::as_array_mut) {
+            kids.push(other_pages_id.into());
+        } else {
+            pages.set("Kids", vec![other_pages_id.into()]);
+        }
+        Ok(())
+    }
+
+    fn merge_page_labels(&mut self, own_page_count: u32, other_labels: BTreeMap<u32, PageLabel>) -> Result<()> {
+        let mut labels = self.get_page_labels()?;
+        if labels.is_empty() && other_labels.is_empty() {
+            return Ok(());
+        }
+        // Pages of a document without page labels are labeled with their page numbers.
+        if labels.is_empty() && own_page_count > 0 {
+            labels.insert(0, PageLabel::new(Some(PageLabelStyle::Decimal)));
+        }
+        if other_labels.is_empty() {
+            labels.insert(own_page_count, PageLabel::new(Some(PageLabelStyle::Decimal)));
+        }
+        for (index, label) in other_labels {
+            labels.insert(own_page_count + index, label);
+        }
+        self.set_page_labels(&labels)
+    }
+
+    fn merge_outlines(&mut self, catalog_id: ObjectId, other_outlines_id: ObjectId) -> Result<()> {
+        let outlines_id = match self.get_dictionary(catalog_id)?.get(b"Outlines") {
+            Ok(Object::Reference(id)) => *id,
+            _ => {
+                let catalog = self.get_object_mut(catalog_id).and_then(Object::as_dict_mut)?;
+                catalog.set("Outlines", other_outlines_id);
+                return Ok(());
+            }
+        };
+
+        let other_outlines = self.objects.remove(&other_outlines_id).ok_or(Error::ObjectNotFound)?;
+        let other_outlines = other_outlines.as_dict()?;
+        let first = match other_outlines.get(b"First").and_then(Object::as_reference) {
+            Ok(first) => first,
+            Err(_) => return Ok(()),
+        };
+        let last = other_outlines.get(b"Last").and_then(Object::as_reference)?;
+        let other_count = other_outlines.get(b"Count").and_then(Object::as_i64).unwrap_or(0);
+
+        let mut visited = BTreeSet::new();
+        let mut next = Some(first);
+        while let Some(id) = next.filter(|id| visited.insert(*id)) {
+            let item = self.get_object_mut(id).and_then(Object::as_dict_mut)?;
+            item.set("Parent", outlines_id);
+            next = item.get(b"Next").and_then(Object::as_reference).ok();
+        }
+
+        let outlines = self.get_object_mut(outlines_id).and_then(Object::as_dict_mut)?;
+        let previous_last = outlines.get(b"Last").and_then(Object::as_reference).ok();
+        let count = outlines.get(b"Count").and_then(Object::as_i64).unwrap_or(0);
+        outlines.set("Count", count.abs() + other_count.abs());
+        outlines.set("Last", last);
+        match previous_last {
+            Some(previous_last) => {
+                if let Ok(item) = self.get_object_mut(previous_last).and_then(Object::as_dict_mut) {
+                    item.set("Next", first);
+                }
+                if let Ok(item) = self.get_object_mut(first).and_then(Object::as_dict_mut) {
+                    item.set("Prev", previous_last);
+                }
+            }
+            None => outlines.set("First", first),
+        }
+        Ok(())
+    }
+
+    fn merge_dests(&mut self, catalog_id: ObjectId, other_dests: Dictionary) -> Result<()> {
+        let catalog = self.get_dictionary(catalog_id)?;
+        let dests = match catalog.get(b"Dests") {
+            Ok(Object::Reference(id)) => self.get_object_mut(*id).and_then(Object::as_dict_mut)?,
+            Ok(Object::Dictionary(_)) => self
+                .get_object_mut(catalog_id)
+                .and_then(Object::as_dict_mut)?
+                .get_mut(b"Dests")
+                .and_then(Object::as_dict_mut)?,
+            _ => {
+                let catalog = self.get_object_mut(catalog_id).and_then(Object::as_dict_mut)?;
+                catalog.set("Dests", other_dests);
+                return Ok(());
+            }
+        };
+        for (name, dest) in other_dests.iter() {
+            if dests.has(name) {
+                warn!("Destination {} already exists.", String::from_utf8_lossy(name));
+            } else {
+                dests.set(name.clone(), dest.clone());
+            }
+        }
+        Ok(())
+    }
+
+    fn merge_names(&mut self, other_names: Dictionary) -> Result<()> {
+        for (key, other_root) in other_names.iter() {
+            let mut entries = self.get_name_tree(key)?;
+            let own_names = entries.iter().map(|(name, _)| name.clone()).collect::<BTreeSet<_>>();
+            let mut other_entries = Vec::new();
+            let mut other_nodes = Vec::new();
+            self.collect_name_tree(other_root, &mut other_entries, &mut other_nodes);
+            for (name, value) in other_entries {
+                if own_names.contains(&name) {
+                    warn!("Name {} already exists.", String::from_utf8_lossy(&name));
+                } else {
+                    entries.push((name, value));
+                }
+            }
+            for node in other_nodes {
+                self.objects.remove(&node);
+            }
+            self.set_name_tree(key, entries)?;
+        }
+        Ok(())
+    }
+
+    /// Add the fields of another interactive form to the one of this document, renaming root fields whose names
+    /// are already used. Default resources are added to the ones of this form.
+    fn merge_acro_form(&mut self, catalog_id: ObjectId, other_acro_form: Dictionary) -> Result<()> {
+        let (acro_form_id, mut acro_form) = match self.get_dictionary(catalog_id)?.get(b"AcroForm") {
+            Ok(acro_form) => {
+                let (id, acro_form) = self.dereference(acro_form)?;
+                (id, acro_form.as_dict()?.clone())
+            }
+            Err(_) => {
+                let catalog = self.get_object_mut(catalog_id).and_then(Object::as_dict_mut)?;
+                catalog.set("AcroForm", other_acro_form);
+                return Ok(());
+            }
+        };
+
+        let field_name = |doc: &Document, field: &Object| {
+            doc.dereference(field)
+                .and_then(|(_, field)| field.as_dict())
+                .and_then(|field| field.get(b"T"))
+                .and_then(Object::as_str)
+                .map(<[u8]>::to_vec)
+        };
+        let mut fields = acro_form
+            .get(b"Fields")
+            .and_then(|fields| self.dereference(fields))
+            .and_then(|(_, fields)| fields.as_array())
+            .cloned()
+            .unwrap_or_default();
+        let used_names: BTreeSet<Vec<u8>> = fields.iter().filter_map(|field| field_name(self, field).ok()).collect();
+        let other_fields = other_acro_form
+            .get(b"Fields")
+            .and_then(|fields| self.dereference(fields))
+            .and_then(|(_, fields)| fields.as_array())
+            .cloned()
+            .unwrap_or_default();
+        let mut taken = used_names.clone();
+        taken.extend(other_fields.iter().filter_map(|field| field_name(self, field).ok()));
+        // Fields of the other form inherit its default appearance rather than the one of this form.
+        let other_appearance = other_acro_form
+            .get(b"DA")
+            .ok()
+            .filter(|&appearance| acro_form.get(b"DA").ok() != Some(appearance))
+            .cloned();
+        for field in &other_fields {
+            let name = field_name(self, field).ok();
+            let field = match field
+                .as_reference()
+                .and_then(|id| self.get_object_mut(id))
+                .and_then(Object::as_dict_mut)
+            {
+                Ok(field) => field,
+                Err(_) => continue,
+            };
+            if let Some(name) = name.filter(|name| used_names.contains(name)) {
+                field.set("T", Object::string_literal(unique_name(&name, &mut taken)));
+            }
+            if let Some(ref appearance) = other_appearance {
+                if !field.has(b"DA") {
+                    field.set("DA", appearance.clone());
+                }
+            }
+        }
+        fields.extend(other_fields);
+        acro_form.set("Fields", fields);
+
+        if let Ok((_, other_resources)) = other_acro_form
+            .get(b"DR")
+            .and_then(|resources| self.dereference(resources))
+        {
+            let other_resources = other_resources.as_dict()?.clone();
+            let mut resources = acro_form
+                .get(b"DR")
+                .and_then(|resources| self.dereference(resources))
+                .and_then(|(_, resources)| resources.as_dict())
+                .cloned()
+                .unwrap_or_default();
+            for (category, other_entries) in other_resources.iter() {
+                let other_entries = match self
+                    .dereference(other_entries)
+                    .and_then(|(_, entries)| entries.as_dict())
+                {
+                    Ok(other_entries) => other_entries,
+                    Err(_) => continue,
+                };
+                let mut entries = resources
+                    .get(category)
+                    .and_then(|entries| self.dereference(entries))
+                    .and_then(|(_, entries)| entries.as_dict())
+                    .cloned()
+                    .unwrap_or_default();
+                for (name, entry) in other_entries.iter() {
+                    if !entries.has(name) {
+                        entries.set(name.clone(), entry.clone());
+                    }
+                }
+                resources.set(category.clone(), entries);
+            }
+            acro_form.set("DR", resources);
+        }
+        if let Ok(Object::Boolean(true)) = other_acro_form.get(b"NeedAppearances") {
+            acro_form.set("NeedAppearances", true);
+        }
+        if let Ok(other_flags) = other_acro_form.get(b"SigFlags").and_then(Object::as_i64) {
+            let flags = acro_form.get(b"SigFlags").and_then(Object::as_i64).unwrap_or(0);
+            acro_form.set("SigFlags", flags | other_flags);
+        }
+
+        match acro_form_id {
+            Some(acro_form_id) => *self.get_object_mut(acro_form_id)? = Object::Dictionary(acro_form),
+            None => self
+                .get_object_mut(catalog_id)
+                .and_then(Object::as_dict_mut)?
+                .set("AcroForm", acro_form),
+        }
+        Ok(())
+    }
+}
+
+/// Make a name unique by appending a number, and add it to the names which are taken. Text strings encoded in
+/// UTF-16 get the number in the same encoding.
+fn unique_name(name: &[u8], taken: &mut BTreeSet<Vec<u8>>) -> Vec<u8> {
+    let mut number = 2;
+    loop {
+        let suffix = format!("-{}", number);
+        let suffix: Vec<u8> = if name.starts_with(b"\xFE\xFF") {
+            suffix.encode_utf16().flat_map(u16::to_be_bytes).collect()
+        } else {
+            suffix.into_bytes()
+        };
+        let candidate = [name, &suffix].concat();
+        if taken.insert(candidate.clone()) {
+            return candidate;
+        }
+        number += 1;
+    }
+}
+
+/// Rename the destinations of outline items, link annotations and go-to actions in an object, with names
+/// renamed as given by `names` and strings as given by `strings`.
+fn rename_destinations(object: &mut Object, names: &BTreeMap<Vec<u8>, Vec<u8>>, strings: &BTreeMap<Vec<u8>, Vec<u8>>) {
+    match object {
+        Object::Array(array) => {
+            for item in array {
+                rename_destinations(item, names, strings);
+            }
+        }
+        Object::Dictionary(dict) | Object::Stream(crate::Stream { dict, .. }) => {
+            let is_go_to = dict.get(b"S").and_then(Object::as_name).ok() == Some(b"GoTo");
+            for (key, value) in dict.iter_mut() {
+                if key == b"Dest" || (is_go_to && key == b"D") {
+                    match value {
+                        Object::Name(name) => {
+                            if let Some(new_name) = names.get(name) {
+                                *name = new_name.clone();
+                            }
+                        }
+                        Object::String(string, _) => {
+                            if let Some(new_name) = strings.get(string) {
+                                *string = new_name.clone();
+                            }
+                        }
+                        _ => {}
+                    }
+                } else {
+                    rename_destinations(value, names, strings);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+#[test]
+fn merge_documents() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    let mut other = create_document();
+    let other_catalog_id = other.trailer.get(b"Root").and_then(Object::as_reference).unwrap();
+    let dest_page = other.page_iter().next().unwrap();
+    other
+        .get_object_mut(other_catalog_id)
+        .and_then(Object::as_dict_mut)
+        .unwrap()
+        .set(
+            "Dests",
+            dictionary! { "Second" => vec![dest_page.into(), "Fit".into()] },
+        );
+
+    doc.merge(other).unwrap();
+
+    let pages = doc.get_pages();
+    assert_eq!(pages.len(), 2);
+    assert_eq!(doc.extract_text(&[1, 2]).unwrap(), "Hello World!\nHello World!\n");
+    let dests = doc
+        .catalog()
+        .and_then(|catalog| catalog.get(b"Dests"))
+        .and_then(Object::as_dict)
+        .unwrap();
+    let dest = dests.get(b"Second").and_then(Object::as_array).unwrap();
+    assert_eq!(dest[0].as_reference().unwrap(), pages[&2]);
+}
+
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+#[test]
+fn merge_outlines_names_and_forms() {
+    use crate::attachments::AttachmentOptions;
+    use crate::forms::FieldBuilder;
+    use crate::outlines::{OutlineItem, OutlineTarget, OutlineTree};
+    use crate::test_support::create_document;
+    use crate::Rectangle;
+
+    // Both documents use the same names for destinations, attachments and fields.
+    let with_names = |title: &str| {
+        let mut doc = create_document();
+        let page_id = doc.page_iter().next().unwrap();
+        let catalog_id = doc.trailer.get(b"Root").and_then(Object::as_reference).unwrap();
+        doc.get_object_mut(catalog_id)
+            .and_then(Object::as_dict_mut)
+            .unwrap()
+            .set("Dests", dictionary! { "Start" => vec![page_id.into(), "Fit".into()] });
+        doc.add_named_destination("Intro", vec![page_id.into(), "Fit".into()].into())
+            .unwrap();
+        let outline = OutlineTree::new().with_item(
+            OutlineItem::new(title)
+                .with_destination(Object::Name(b"Start".to_vec()))
+                .with_kid(OutlineItem::new("Intro").with_destination(Object::string_literal("Intro"))),
+        );
+        doc.set_outline(&outline).unwrap();
+        doc.add_attachment("notes.txt", title.as_bytes().to_vec(), &AttachmentOptions::default())
+            .unwrap();
+        let rect = Rectangle::new(100.0, 700.0, 300.0, 720.0);
+        doc.add_field(&FieldBuilder::text("name", page_id, rect)).unwrap();
+        doc
+    };
+    let mut doc = with_names("First");
+    doc.merge(with_names("Second")).unwrap();
+
+    let pages = doc.get_pages();
+    let destinations: Vec<(Vec<u8>, Object)> = doc.named_destinations().unwrap().collect();
+    let names: Vec<&[u8]> = destinations.iter().map(|(name, _)| name.as_slice()).collect();
+    assert_eq!(names, [b"Intro".as_ref(), b"Intro-2", b"Start", b"Start-2"]);
+    for (index, page) in [(0, 1), (1, 2), (2, 1), (3, 2)].iter() {
+        let dest = destinations[*index].1.as_array().unwrap();
+        assert_eq!(dest[0].as_reference().unwrap(), pages[page]);
+    }
+
+    // Outline items of the second document refer to the renamed destinations.
+    let outline = doc.get_outline().unwrap();
+    let items: Vec<(&str, Option<&OutlineTarget>)> = outline
+        .iter()
+        .map(|item| (item.title.as_str(), item.target.as_ref()))
+        .collect();
+    assert_eq!(items.len(), 4);
+    let destination = |index: usize| match items[index].1 {
+        Some(OutlineTarget::Destination(dest)) => dest.clone(),
+        _ => panic!("outline item without destination"),
+    };
+    assert_eq!((items[0].0, destination(0)), ("First", Object::Name(b"Start".to_vec())));
+    assert_eq!(
+        (items[2].0, destination(2)),
+        ("Second", Object::Name(b"Start-2".to_vec()))
+    );
+    assert_eq!(destination(3).as_str().unwrap(), b"Intro-2");
+
+    let attachments = doc.attachments().unwrap();
+    let attachments: Vec<(&str, Vec<u8>)> = attachments
+        .iter()
+        .map(|attachment| (attachment.name.as_str(), attachment.data(&doc).unwrap()))
+        .collect();
+    assert_eq!(
+        attachments,
+        [("notes.txt", b"First".to_vec()), ("notes.txt-2", b"Second".to_vec())]
+    );
+
+    let form = doc.get_form().unwrap();
+    let fields: Vec<&str> = form.iter().map(|field| field.name.as_str()).collect();
+    assert_eq!(fields, ["name", "name-2"]);
+
+    let mut buffer = Vec::new();
+    doc.save_to(&mut buffer).unwrap();
+    let doc = Document::load_mem(&buffer).unwrap();
+    assert_eq!(doc.named_destinations().unwrap().count(), 4);
+    assert_eq!(doc.get_form().unwrap().iter().count(), 2);
+}
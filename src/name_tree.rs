@@ -0,0 +1,175 @@
+use crate::{Dictionary, Document, Object, ObjectId, Result};
+
+/// Maximum number of entries of a leaf node, and of kids of an intermediate node, of written name and number
+/// trees.
+const NODE_SIZE: usize = 32;
+
+impl Document {
+    /// Collect key-value pairs of a name tree, together with the ids of its indirect nodes.
+    pub(crate) fn collect_name_tree(
+        &self, root: &Object, entries: &mut Vec<(Vec<u8>, Object)>, nodes: &mut Vec<ObjectId>,
+    ) {
+        let mut pairs = Vec::new();
+        self.collect_tree(root, b"Names", &mut pairs, nodes);
+        entries.extend(
+            pairs
+                .into_iter()
+                .filter_map(|(name, value)| Some((name.as_str().ok()?.to_vec(), value))),
+        );
+    }
+
+    /// Collect key-value pairs of a number tree, such as /PageLabels, together with the ids of its indirect nodes.
+    pub(crate) fn collect_number_tree(
+        &self, root: &Object, entries: &mut Vec<(i64, Object)>, nodes: &mut Vec<ObjectId>,
+    ) {
+        let mut pairs = Vec::new();
+        self.collect_tree(root, b"Nums", &mut pairs, nodes);
+        entries.extend(
+            pairs
+                .into_iter()
+                .filter_map(|(number, value)| Some((number.as_i64().ok()?, value))),
+        );
+    }
+
+    /// Collect the key-value pairs of the /Names or /Nums arrays of the nodes of a tree.
+    fn collect_tree(&self, root: &Object, key: &[u8], entries: &mut Vec<(Object, Object)>, nodes: &mut Vec<ObjectId>) {
+        let mut stack = vec![root];
+        while let Some(node) = stack.pop() {
+            let (id, node) = match self.dereference(node) {
+                Ok(result) => result,
+                Err(_) => continue,
+            };
+            if let Some(id) = id {
+                if nodes.contains(&id) {
+                    continue;
+                }
+                nodes.push(id);
+            }
+            let node = match node.as_dict() {
+                Ok(node) => node,
+                Err(_) => continue,
+            };
+            if let Ok(pairs) = node.get(key).and_then(Object::as_array) {
+                for pair in pairs.chunks(2) {
+                    if let [key, value] = pair {
+                        entries.push((key.clone(), value.clone()));
+                    }
+                }
+            }
+            if let Ok(kids) = node.get(b"Kids").and_then(Object::as_array) {
+                stack.extend(kids.iter().rev());
+            }
+        }
+    }
+
+    /// Get the entries of a name tree of the /Names dictionary of the catalog, such as /Dests.
+    pub(crate) fn get_name_tree(&self, key: &[u8]) -> Result<Vec<(Vec<u8>, Object)>> {
+        let mut entries = Vec::new();
+        if let Some(root) = self.names_dictionary()?.and_then(|names| names.get(key).ok()) {
+            self.collect_name_tree(root, &mut entries, &mut Vec::new());
+        }
+        Ok(entries)
+    }
+
+    /// Replace a name tree of the /Names dictionary of the catalog with a balanced tree of the entries, sorted
+    /// by name. The nodes of the previous tree are removed, and the /Names dictionary is created if needed.
+    pub(crate) fn set_name_tree(&mut self, key: &[u8], mut entries: Vec<(Vec<u8>, Object)>) -> Result<()> {
+        let catalog_id = self.trailer.get(b"Root").and_then(Object::as_reference)?;
+        let mut nodes = Vec::new();
+        if let Some(root) = self.names_dictionary()?.and_then(|names| names.get(key).ok()) {
+            self.collect_name_tree(root, &mut Vec::new(), &mut nodes);
+        }
+        for node in nodes {
+            self.objects.remove(&node);
+        }
+
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        entries.dedup_by(|a, b| a.0 == b.0);
+        let entries = entries
+            .into_iter()
+            .map(|(name, value)| (Object::string_literal(name), value))
+            .collect();
+        let root_id = self.write_tree("Names", entries);
+
+        let catalog = self.get_object_mut(catalog_id).and_then(Object::as_dict_mut)?;
+        let names_id = match catalog.get(b"Names") {
+            Ok(&Object::Reference(id)) => Some(id),
+            Ok(&Object::Dictionary(_)) => None,
+            _ => {
+                catalog.set("Names", Dictionary::new());
+                None
+            }
+        };
+        let names = match names_id {
+            Some(id) => self.get_object_mut(id).and_then(Object::as_dict_mut)?,
+            None => self
+                .get_object_mut(catalog_id)
+                .and_then(Object::as_dict_mut)?
+                .get_mut(b"Names")
+                .and_then(Object::as_dict_mut)?,
+        };
+        names.set(key.to_vec(), root_id);
+        Ok(())
+    }
+
+    /// Write a balanced number tree of the entries, returning the id of its root.
+    pub(crate) fn write_number_tree(&mut self, mut entries: Vec<(i64, Object)>) -> ObjectId {
+        entries.sort_by_key(|entry| entry.0);
+        entries.dedup_by_key(|entry| entry.0);
+        let entries = entries
+            .into_iter()
+            .map(|(number, value)| (Object::Integer(number), value))
+            .collect();
+        self.write_tree("Nums", entries)
+    }
+
+    /// Write a balanced tree of sorted entries, with leaves listing them in /Names or /Nums arrays.
+    fn write_tree(&mut self, key: &str, entries: Vec<(Object, Object)>) -> ObjectId {
+        // Nodes have the first and last keys they contain as /Limits, except for the root.
+        let mut level = Vec::new();
+        for leaf in entries.chunks(NODE_SIZE) {
+            let pairs = leaf
+                .iter()
+                .flat_map(|(key, value)| vec![key.clone(), value.clone()])
+                .collect::<Vec<_>>();
+            let (first, last) = (leaf[0].0.clone(), leaf[leaf.len() - 1].0.clone());
+            let mut node = Dictionary::new();
+            node.set(key, pairs);
+            node.set("Limits", vec![first.clone(), last.clone()]);
+            level.push((self.add_object(node), first, last));
+        }
+        while level.len() > 1 {
+            let mut parents = Vec::new();
+            for kids in level.chunks(NODE_SIZE) {
+                let kid_ids = kids.iter().map(|(id, _, _)| Object::Reference(*id)).collect::<Vec<_>>();
+                let (first, last) = (kids[0].1.clone(), kids[kids.len() - 1].2.clone());
+                let node = dictionary! {
+                    "Kids" => kid_ids,
+                    "Limits" => vec![first.clone(), last.clone()],
+                };
+                parents.push((self.add_object(node), first, last));
+            }
+            level = parents;
+        }
+        match level.first() {
+            Some(&(root_id, _, _)) => {
+                if let Ok(root) = self.get_object_mut(root_id).and_then(Object::as_dict_mut) {
+                    root.remove(b"Limits");
+                }
+                root_id
+            }
+            None => {
+                let mut root = Dictionary::new();
+                root.set(key, Vec::<Object>::new());
+                self.add_object(root)
+            }
+        }
+    }
+
+    fn names_dictionary(&self) -> Result<Option<&Dictionary>> {
+        Ok(match self.catalog()?.get(b"Names") {
+            Ok(names) => self.dereference(names)?.1.as_dict().ok(),
+            Err(_) => None,
+        })
+    }
+}
@@ -10,7 +10,7 @@ use nom::branch::alt;
 use nom::bytes::complete::{tag, take, take_while, take_while1, take_while_m_n};
 use nom::character::complete::{digit0, digit1, one_of};
 use nom::character::{is_hex_digit, is_oct_digit};
-use nom::combinator::{map, map_opt, map_res, opt, verify};
+use nom::combinator::{cut, map, map_opt, map_res, opt, verify};
 use nom::error::{ErrorKind, ParseError};
 use nom::multi::{fold_many0, fold_many1, many0, many0_count};
 use nom::sequence::{delimited, pair, preceded, separated_pair, terminated, tuple};
@@ -278,7 +278,8 @@ fn stream<'a>(input: &'a [u8], reader: &Reader) -> NomResult<'a, Object> {
             value.as_i64()
         }
     }) {
-        let (i, data) = terminated(take(length as usize), pair(opt(eol), tag(b"endstream")))(i)?;
+        // A wrong length is an error rather than a reason to try the other kinds of objects.
+        let (i, data) = cut(terminated(take(length as usize), pair(opt(eol), tag(b"endstream"))))(i)?;
         Ok((i, Object::Stream(Stream::new(dict, data.to_vec()))))
     } else {
         // Return position relative to the start of the stream dictionary.
@@ -354,6 +355,21 @@ fn _indirect_object<'a>(
     Ok((object_id, object))
 }
 
+/// Parse an indirect stream object up to its data, returning its dictionary and the position of the data.
+#[cfg(feature = "mmap")]
+pub fn stream_header(input: &[u8], offset: usize, expected_id: ObjectId) -> crate::Result<(Dictionary, usize)> {
+    let header = input.get(offset..).ok_or(Error::Parse { offset })?;
+    let (i, (object_id, dict)) = pair(
+        terminated(object_id, pair(tag(b"obj"), space)),
+        terminated(dictionary, tuple((space, tag(b"stream"), eol))),
+    )(header)
+    .map_err(|_| Error::Parse { offset })?;
+    if object_id != expected_id {
+        return Err(Error::ObjectIdMismatch);
+    }
+    Ok((dict, offset + header.len() - i.len()))
+}
+
 pub fn header(input: &[u8]) -> Option<String> {
     strip_nom(map_res(
         delimited(
@@ -383,6 +399,8 @@ fn xref(input: &[u8]) -> NomResult<Xref> {
             for (index, ((offset, generation), is_normal)) in entries.into_iter().enumerate() {
                 if is_normal {
                     xref.insert((start + index) as u32, XrefEntry::Normal { offset, generation });
+                } else {
+                    xref.insert((start + index) as u32, XrefEntry::Free);
                 }
             }
             xref
@@ -417,7 +435,7 @@ pub fn xref_and_trailer(input: &[u8], reader: &Reader) -> crate::Result<(Xref, D
         }),
     ))(input)
     .map(|(_, o)| o)
-    .unwrap_or(Err(Error::Trailer))
+    .unwrap_or(Err(Error::Xref(XrefError::Parse)))
 }
 
 pub fn xref_start(input: &[u8]) -> Option<i64> {
@@ -465,10 +483,31 @@ fn operation(input: &[u8]) -> NomResult<Operation> {
     )(input)
 }
 
+fn inline_image(input: &[u8]) -> NomResult<Operation> {
+    let (input, parameters) = delimited(
+        pair(tag(b"BI"), content_space),
+        fold_many0(
+            pair(terminated(name, content_space), operand),
+            Dictionary::new(),
+            |mut dict, (key, value)| {
+                dict.set(key, value);
+                dict
+            },
+        ),
+        pair(tag(b"ID"), one_of(" \t\r\n\x0C\0")),
+    )(input)?;
+    let (length, end) = inline_image_end(input, &parameters).ok_or_else(|| nom::Err::Error(()))?;
+    let operation = Operation::inline_image(parameters, input[..length].to_vec());
+    let (input, _) = content_space(&input[end..])?;
+    Ok((input, operation))
+}
+
 fn _content(input: &[u8]) -> NomResult<Content<Vec<Operation>>> {
     preceded(
         content_space,
-        map(many0(operation), |operations| Content { operations }),
+        map(many0(alt((inline_image, operation))), |operations| Content {
+            operations,
+        }),
     )(input)
 }
 
@@ -539,6 +578,22 @@ T* (encoded streams.) Tj
         assert!(content.is_some());
     }
 
+    #[test]
+    fn parse_stream_with_wrong_length() {
+        let buffer = b"5 0 obj<</Length 4>>stream\nabcd\nendstream endobj\n";
+        let reader = Reader::new(buffer);
+        let (_, object) = indirect_object(buffer, 0, None, &reader).unwrap();
+        assert_eq!(object.as_stream().unwrap().content, b"abcd");
+
+        // The object isn't read as the dictionary alone, so that it's reported as unreadable.
+        let buffer = b"5 0 obj<</Length 9>>stream\nabcd\nendstream endobj\n";
+        let reader = Reader::new(buffer);
+        assert!(matches!(
+            indirect_object(buffer, 0, None, &reader),
+            Err(Error::Parse { offset: 0 })
+        ));
+    }
+
     #[test]
     fn hex_partial() {
         // Example from PDF specification.
@@ -1,4 +1,4 @@
-use crate::{Document, Error, Result};
+use crate::{Document, Error, PdfDate, Rectangle, Result};
 use linked_hash_map::{self, Iter, IterMut, LinkedHashMap};
 use log::warn;
 use std::fmt;
@@ -8,13 +8,13 @@ use std::str;
 pub type ObjectId = (u32, u16);
 
 /// Dictionary object.
-#[derive(Clone, Default)]
+#[derive(Clone, Default, PartialEq)]
 pub struct Dictionary(LinkedHashMap<Vec<u8>, Object>);
 
 /// Stream object
 /// Warning - all streams must be indirect objects, while
 /// the stream dictionary may be a direct object
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Stream {
     /// Associated stream dictionary
     pub dict: Dictionary,
@@ -28,7 +28,18 @@ pub struct Stream {
 }
 
 /// Basic PDF object types defined in an enum.
-#[derive(Clone)]
+///
+/// With the `serde` feature, objects, dictionaries and streams can be serialized and deserialized. In JSON:
+///
+/// - Null, booleans, integers, reals and arrays map to JSON values of the same kind.
+/// - Names are strings starting with a slash, such as `"/Type"`. Bytes other than printable ASCII characters and
+///   `#` are escaped as `#xx`, as in PDF files.
+/// - Dictionaries are objects with keys which are names, such as `{"/Type": "/Page"}`.
+/// - Strings are `{"string": "text", "format": "literal"}` if they are text strings in PDFDocEncoding or UTF-16BE,
+///   and `{"base64": "6Q==", "format": "hexadecimal"}` otherwise, with the format of the string in the file.
+/// - References are `{"ref": [12, 0]}`, with the object number and the generation.
+/// - Streams are `{"stream": {"/Length": 4}, "data": "AAEC/w=="}`, with the encoded data in base64.
+#[derive(Clone, PartialEq)]
 pub enum Object {
     Null,
     Boolean(bool),
@@ -43,7 +54,7 @@ pub enum Object {
 }
 
 /// String objects can be written in two formats.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum StringFormat {
     Literal,
     Hexadecimal,
@@ -158,6 +169,15 @@ impl Object {
         }
     }
 
+    /// Get a number, which may be an integer or a real.
+    pub fn as_float(&self) -> Result<f64> {
+        match *self {
+            Object::Integer(ref value) => Ok(*value as f64),
+            Object::Real(ref value) => Ok(*value),
+            _ => Err(Error::Type),
+        }
+    }
+
     pub fn as_name(&self) -> Result<&[u8]> {
         match *self {
             Object::Name(ref name) => Ok(name),
@@ -286,6 +306,35 @@ impl Dictionary {
         doc.dereference(self.get(key)?).map(|(_, object)| object)
     }
 
+    /// Get a number, which may be an integer or a real, dereferencing it if needed.
+    pub fn get_f32(&self, key: &[u8], doc: &Document) -> Result<f32> {
+        self.get_deref(key, doc)?.as_float().map(|number| number as f32)
+    }
+
+    /// Get a rectangle from an array of four numbers, dereferencing the array if needed.
+    pub fn get_rect(&self, key: &[u8], doc: &Document) -> Result<Rectangle> {
+        Rectangle::from_object(self.get_deref(key, doc)?)
+    }
+
+    /// Get a name as a string, dereferencing it if needed.
+    pub fn get_name_str<'a>(&'a self, key: &[u8], doc: &'a Document) -> Result<&'a str> {
+        self.get_deref(key, doc)?.as_name_str()
+    }
+
+    /// Get a date from a string in the `D:YYYYMMDDHHmmSSOHH'mm'` format, dereferencing it if needed.
+    pub fn get_date(&self, key: &[u8], doc: &Document) -> Result<PdfDate> {
+        PdfDate::from_object(self.get_deref(key, doc)?)
+    }
+
+    /// Get the object ids of an array of references, dereferencing the array if needed.
+    pub fn get_ref_array(&self, key: &[u8], doc: &Document) -> Result<Vec<ObjectId>> {
+        self.get_deref(key, doc)?
+            .as_array()?
+            .iter()
+            .map(Object::as_reference)
+            .collect()
+    }
+
     pub fn get_mut(&mut self, key: &[u8]) -> Result<&mut Object> {
         self.0.get_mut(key).ok_or(Error::DictKey)
     }
@@ -560,8 +609,69 @@ impl Stream {
         Ok(())
     }
 
+    /// Encode the content with a FlateDecode, LZWDecode, RunLengthDecode, ASCIIHexDecode, ASCII85Decode or
+    /// CCITTFaxDecode filter, which is added in front of the existing filters.
+    ///
+    /// The parameters are stored as the decode parameters of the filter, but predictors aren't applied.
+    /// CCITT fax data is always encoded with Group 4, from rows of one bit per pixel as wide as `Columns`, and
+    /// needs the `ccitt` feature.
+    pub fn add_filter(
+        &mut self, filter: &str, #[cfg_attr(not(feature = "ccitt"), allow(unused_mut))] mut params: Option<Dictionary>,
+    ) -> Result<()> {
+        use flate2::write::ZlibEncoder;
+        use flate2::Compression;
+        use std::io::prelude::*;
+
+        let content = match filter {
+            "FlateDecode" => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+                encoder.write_all(self.content.as_slice())?;
+                encoder.finish()?
+            }
+            "LZWDecode" => crate::filters::lzw::encode(&self.content, Self::lzw_early_change(params.as_ref())),
+            "RunLengthDecode" => crate::filters::run_length::encode(&self.content),
+            "ASCIIHexDecode" => crate::filters::ascii_hex::encode(&self.content),
+            "ASCII85Decode" => crate::filters::ascii85::encode(&self.content),
+            #[cfg(feature = "ccitt")]
+            "CCITTFaxDecode" => {
+                let params = params.get_or_insert_with(Dictionary::new);
+                params.set("K", -1);
+                let ccitt_params = crate::filters::ccitt::CcittParams::new(Some(params), None);
+                crate::filters::ccitt::encode(&self.content, &ccitt_params)?
+            }
+            _ => return Err(Error::Type),
+        };
+
+        let mut filters = match self.dict.get(b"Filter") {
+            Ok(Object::Array(filters)) => filters.clone(),
+            Ok(filter) => vec![filter.clone()],
+            Err(_) => vec![],
+        };
+        let mut decode_params = match self.dict.get(b"DecodeParms") {
+            Ok(Object::Array(decode_params)) => decode_params.clone(),
+            Ok(decode_params) => vec![decode_params.clone()],
+            Err(_) => vec![Object::Null; filters.len()],
+        };
+        filters.insert(0, Object::Name(filter.as_bytes().to_vec()));
+        decode_params.insert(0, params.map_or(Object::Null, Object::Dictionary));
+
+        if filters.len() == 1 {
+            self.dict.set("Filter", filters.remove(0));
+        } else {
+            self.dict.set("Filter", filters);
+        }
+        if decode_params.iter().all(|params| *params == Object::Null) {
+            self.dict.remove(b"DecodeParms");
+        } else if decode_params.len() == 1 {
+            self.dict.set("DecodeParms", decode_params.remove(0));
+        } else {
+            self.dict.set("DecodeParms", decode_params);
+        }
+        self.set_content(content);
+        Ok(())
+    }
+
     pub fn decompressed_content(&self) -> Result<Vec<u8>> {
-        let params = self.dict.get(b"DecodeParms").and_then(Object::as_dict).ok();
         let filters = self.filters()?;
 
         if self.dict.get(b"Subtype").and_then(Object::as_name_str).ok() == Some("Image") {
@@ -572,10 +682,16 @@ impl Stream {
         let mut output = None;
 
         // Filters are in decoding order.
-        for filter in filters {
+        for (index, filter) in filters.iter().enumerate() {
+            let params = self.decode_params(index);
             output = Some(match filter.as_str() {
                 "FlateDecode" => Self::decompress_zlib(input, params)?,
                 "LZWDecode" => Self::decompress_lzw(input, params)?,
+                "RunLengthDecode" => crate::filters::run_length::decode(input),
+                "ASCIIHexDecode" => crate::filters::ascii_hex::decode(input),
+                "ASCII85Decode" => crate::filters::ascii85::decode(input),
+                // Streams are decrypted when the document is, which removes named crypt filters.
+                "Crypt" if Self::is_identity_crypt_filter(params) => input.to_vec(),
                 _ => {
                     return Err(Error::Type);
                 }
@@ -586,59 +702,36 @@ impl Stream {
         output.ok_or(Error::Type)
     }
 
-    fn decompress_lzw(input: &[u8], params: Option<&Dictionary>) -> Result<Vec<u8>> {
-        use lzw::{Decoder, DecoderEarlyChange, MsbReader};
-        const MIN_BITS: u8 = 9;
+    /// Get the decode parameters of a filter, from an array with an entry for each filter or from a single
+    /// dictionary.
+    fn decode_params(&self, index: usize) -> Option<&Dictionary> {
+        match self.dict.get(b"DecodeParms").ok()? {
+            Object::Array(params) => params.get(index)?.as_dict().ok(),
+            params => params.as_dict().ok(),
+        }
+    }
 
-        let early_change = params
-            .and_then(|p| p.get(b"EarlyChange").ok())
-            .and_then(|p| Object::as_i64(p).ok())
-            .map(|v| v != 0)
-            .unwrap_or(true);
-
-        let output = if early_change {
-            Self::decompress_lzw_loop(
-                input,
-                DecoderEarlyChange::new(MsbReader::new(), MIN_BITS - 1),
-                DecoderEarlyChange::decode_bytes,
-            )
-        } else {
-            Self::decompress_lzw_loop(
-                input,
-                Decoder::new(MsbReader::new(), MIN_BITS - 1),
-                Decoder::decode_bytes,
-            )
-        };
+    fn is_identity_crypt_filter(params: Option<&Dictionary>) -> bool {
+        params
+            .and_then(|params| params.get(b"Name").and_then(Object::as_name).ok())
+            .filter(|&name| name != b"Identity")
+            .is_none()
+    }
 
+    pub(crate) fn decompress_lzw(input: &[u8], params: Option<&Dictionary>) -> Result<Vec<u8>> {
+        let output = crate::filters::lzw::decode(input, Self::lzw_early_change(params));
         Self::decompress_predictor(output, params)
     }
 
-    fn decompress_lzw_loop<F, D>(mut input: &[u8], mut decoder: D, decode: F) -> Vec<u8>
-    where
-        F: for<'d> Fn(&'d mut D, &[u8]) -> std::io::Result<(usize, &'d [u8])>,
-    {
-        let mut output = Vec::with_capacity(input.len() * 2);
-
-        loop {
-            match decode(&mut decoder, input) {
-                Ok((consumed_bytes, out_bytes)) => {
-                    output.extend(out_bytes);
-                    input = &input[consumed_bytes..];
-                    if input.is_empty() || consumed_bytes == 0 {
-                        break;
-                    }
-                }
-                Err(err) => {
-                    warn!("{}", err);
-                    break;
-                }
-            }
-        }
-
-        output
+    fn lzw_early_change(params: Option<&Dictionary>) -> bool {
+        params
+            .and_then(|p| p.get(b"EarlyChange").ok())
+            .and_then(|p| Object::as_i64(p).ok())
+            .map(|v| v != 0)
+            .unwrap_or(true)
     }
 
-    fn decompress_zlib(input: &[u8], params: Option<&Dictionary>) -> Result<Vec<u8>> {
+    pub(crate) fn decompress_zlib(input: &[u8], params: Option<&Dictionary>) -> Result<Vec<u8>> {
         use flate2::read::ZlibDecoder;
         use std::io::prelude::*;
 
@@ -654,22 +747,29 @@ impl Stream {
         Self::decompress_predictor(output, params)
     }
 
+    /// Undo the TIFF predictor 2 or the PNG predictors 10 to 15 given by the decode parameters.
     fn decompress_predictor(mut data: Vec<u8>, params: Option<&Dictionary>) -> Result<Vec<u8>> {
-        use crate::filters::png;
-
-        if let Some(params) = params {
-            let predictor = params.get(b"Predictor").and_then(Object::as_i64).unwrap_or(1);
-            if predictor >= 10 && predictor <= 15 {
-                let pixels_per_row = params.get(b"Columns").and_then(Object::as_i64).unwrap_or(1) as usize;
-                let colors = params.get(b"Colors").and_then(Object::as_i64).unwrap_or(1) as usize;
-                let bits = params.get(b"BitsPerComponent").and_then(Object::as_i64).unwrap_or(8) as usize;
-                let bytes_per_pixel = colors * bits / 8;
-                data = png::decode_frame(data.as_slice(), bytes_per_pixel, pixels_per_row)?;
+        use crate::filters::{png, tiff};
+
+        let params = match params {
+            Some(params) => params,
+            None => return Ok(data),
+        };
+        let integer = |key: &[u8], default: i64| params.get(key).and_then(Object::as_i64).unwrap_or(default).max(1);
+        let predictor = integer(b"Predictor", 1);
+        let colors = integer(b"Colors", 1) as usize;
+        let bits = integer(b"BitsPerComponent", 8) as usize;
+        let columns = integer(b"Columns", 1) as usize;
+        match predictor {
+            2 => tiff::decode(&mut data, colors, bits, columns)?,
+            10..=15 => {
+                let bits_per_pixel = colors.checked_mul(bits).ok_or(Error::Type)?;
+                let bits_per_row = bits_per_pixel.checked_mul(columns).ok_or(Error::Type)?;
+                data = png::decode_frame(data.as_slice(), bits_per_pixel.div_ceil(8), bits_per_row.div_ceil(8))?;
             }
-            Ok(data)
-        } else {
-            Ok(data)
+            _ => {}
         }
+        Ok(data)
     }
 
     pub fn decompress(&mut self) {
@@ -680,3 +780,105 @@ impl Stream {
         }
     }
 }
+
+#[test]
+fn typed_dictionary_getters() {
+    let mut doc = Document::new();
+    let kids_id = doc.add_object(vec![Object::Reference((3, 0)), Object::Reference((4, 0))]);
+    let width_id = doc.add_object(Object::Real(2.5));
+    let dict = dictionary! {
+        "Type" => "Page",
+        "Width" => width_id,
+        "Count" => 3,
+        "Kids" => kids_id,
+        "MediaBox" => vec![595.into(), 842.into(), 0.into(), 0.into()],
+        "CreationDate" => Object::string_literal("D:20200102030405Z"),
+        "Items" => vec![Object::Reference((3, 0)), 1.into()],
+    };
+    assert_eq!(dict.get_f32(b"Width", &doc).unwrap(), 2.5);
+    assert_eq!(dict.get_f32(b"Count", &doc).unwrap(), 3.0);
+    assert_eq!(
+        dict.get_rect(b"MediaBox", &doc).unwrap(),
+        Rectangle::new(0.0, 0.0, 595.0, 842.0)
+    );
+    assert_eq!(dict.get_name_str(b"Type", &doc).unwrap(), "Page");
+    assert_eq!(dict.get_ref_array(b"Kids", &doc).unwrap(), vec![(3, 0), (4, 0)]);
+    assert_eq!(
+        dict.get_date(b"CreationDate", &doc).unwrap(),
+        PdfDate::new(2020, 1, 2, 3, 4, 5).with_utc_offset(0)
+    );
+
+    assert!(matches!(dict.get_f32(b"Missing", &doc), Err(Error::DictKey)));
+    assert!(matches!(dict.get_name_str(b"Count", &doc), Err(Error::Type)));
+    assert!(matches!(dict.get_ref_array(b"Items", &doc), Err(Error::Type)));
+    assert!(matches!(dict.get_date(b"Type", &doc), Err(Error::Type)));
+}
+
+#[test]
+fn add_stream_filters() {
+    let data = b"BT /F1 12 Tf (Hello) Tj ET ".repeat(50);
+    let mut stream = Stream::new(Dictionary::new(), data.clone());
+    stream.add_filter("FlateDecode", None).unwrap();
+    stream
+        .add_filter("LZWDecode", Some(dictionary! { "EarlyChange" => 0 }))
+        .unwrap();
+    assert_eq!(stream.filters().unwrap(), ["LZWDecode", "FlateDecode"]);
+    assert_eq!(
+        stream.dict.get(b"DecodeParms").unwrap(),
+        &Object::Array(vec![dictionary! { "EarlyChange" => 0 }.into(), Object::Null])
+    );
+    assert!(stream.add_filter("JBIG2Decode", None).is_err());
+    stream.add_filter("ASCII85Decode", None).unwrap();
+    assert_eq!(stream.filters().unwrap(), ["ASCII85Decode", "LZWDecode", "FlateDecode"]);
+    assert_eq!(stream.decompressed_content().unwrap(), data);
+    let mut hex = Stream::new(Dictionary::new(), b"Hello".to_vec());
+    hex.add_filter("ASCIIHexDecode", None).unwrap();
+    assert_eq!(hex.content, b"48656C6C6F>");
+    assert_eq!(hex.decompressed_content().unwrap(), b"Hello");
+    let mut run_length = Stream::new(Dictionary::new(), vec![0; 1000]);
+    run_length.add_filter("RunLengthDecode", None).unwrap();
+    assert_eq!(run_length.content.len(), 17);
+    assert_eq!(run_length.decompressed_content().unwrap(), vec![0; 1000]);
+    #[cfg(feature = "ccitt")]
+    {
+        let mut fax = Stream::new(Dictionary::new(), vec![0xFF, 0x00, 0xF0, 0x0F]);
+        fax.add_filter("CCITTFaxDecode", Some(dictionary! { "Columns" => 8 }))
+            .unwrap();
+        assert_eq!(
+            fax.dict.get(b"DecodeParms").unwrap(),
+            &Object::Dictionary(dictionary! { "Columns" => 8, "K" => -1 })
+        );
+    }
+}
+
+#[test]
+fn decode_stream_predictors() {
+    // PNG Up and Sub rows of three bytes, with a partial last row.
+    let params = dictionary! { "Predictor" => 12, "Columns" => 3 };
+    let mut png = Stream::new(Dictionary::new(), vec![2, 1, 2, 3, 2, 1, 1, 1, 1, 5, 1]);
+    png.add_filter("FlateDecode", Some(params)).unwrap();
+    assert_eq!(png.decompressed_content().unwrap(), [1, 2, 3, 2, 3, 4, 5, 6]);
+
+    // Sub with two bytes per pixel for 16 bits per component.
+    let params = dictionary! { "Predictor" => 15, "Columns" => 2, "BitsPerComponent" => 16 };
+    let mut png = Stream::new(Dictionary::new(), vec![1, 1, 0, 1, 1]);
+    png.add_filter("FlateDecode", Some(params)).unwrap();
+    assert_eq!(png.decompressed_content().unwrap(), [1, 0, 2, 1]);
+
+    let params = dictionary! { "Predictor" => 2, "Colors" => 3, "Columns" => 2 };
+    let mut tiff = Stream::new(Dictionary::new(), vec![10, 20, 30, 1, 1, 1]);
+    tiff.add_filter("LZWDecode", Some(params)).unwrap();
+    assert_eq!(tiff.decompressed_content().unwrap(), [10, 20, 30, 11, 21, 31]);
+
+    // Rows far longer than the data are decoded as one partial row, and overflowing sizes are errors.
+    let params = dictionary! { "Predictor" => 10, "Columns" => 1i64 << 40 };
+    let mut png = Stream::new(Dictionary::new(), vec![0, 1, 2]);
+    png.add_filter("FlateDecode", Some(params)).unwrap();
+    assert_eq!(png.decompressed_content().unwrap(), [1, 2]);
+    for &predictor in &[2, 10] {
+        let params = dictionary! { "Predictor" => predictor, "Colors" => 1i64 << 40, "Columns" => 1i64 << 40 };
+        let mut stream = Stream::new(Dictionary::new(), vec![0, 1, 2]);
+        stream.add_filter("FlateDecode", Some(params)).unwrap();
+        assert!(stream.decompressed_content().is_err());
+    }
+}
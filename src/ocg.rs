@@ -0,0 +1,220 @@
+use crate::content::{Content, Operation};
+use crate::encodings::{decode_text_string, encode_text_string};
+use crate::{Dictionary, Document, Object, ObjectId, Result, StringFormat};
+
+/// Optional content group, also known as a layer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layer {
+    pub id: ObjectId,
+    pub name: String,
+    /// Whether the layer is shown when the document is opened, according to the default configuration.
+    pub visible: bool,
+}
+
+impl Document {
+    /// Get the layers listed in /OCGs of the /OCProperties of the catalog.
+    pub fn get_layers(&self) -> Result<Vec<Layer>> {
+        let properties = match self.catalog()?.get(b"OCProperties") {
+            Ok(properties) => self.dereference(properties)?.1.as_dict()?,
+            Err(_) => return Ok(Vec::new()),
+        };
+        let configuration = properties
+            .get(b"D")
+            .and_then(|configuration| self.dereference(configuration))
+            .and_then(|(_, configuration)| configuration.as_dict())
+            .ok();
+        let ids = |key: &[u8]| -> Vec<ObjectId> {
+            configuration
+                .and_then(|configuration| configuration.get(key).ok())
+                .and_then(|ids| self.dereference(ids).ok())
+                .and_then(|(_, ids)| ids.as_array().ok())
+                .map(|ids| ids.iter().filter_map(|id| id.as_reference().ok()).collect())
+                .unwrap_or_default()
+        };
+        let (on, off) = (ids(b"ON"), ids(b"OFF"));
+        let base_state = configuration
+            .and_then(|configuration| configuration.get(b"BaseState").ok())
+            .and_then(|state| state.as_name_str().ok());
+
+        let groups = match properties.get(b"OCGs") {
+            Ok(groups) => self.dereference(groups)?.1.as_array()?,
+            Err(_) => return Ok(Vec::new()),
+        };
+        Ok(groups
+            .iter()
+            .filter_map(|group| {
+                let id = group.as_reference().ok()?;
+                let group = self.get_dictionary(id).ok()?;
+                let name = group
+                    .get(b"Name")
+                    .and_then(|name| self.dereference(name))
+                    .and_then(|(_, name)| name.as_str())
+                    .map(decode_text_string)
+                    .unwrap_or_default();
+                let visible = if off.contains(&id) {
+                    false
+                } else {
+                    on.contains(&id) || base_state != Some("OFF")
+                };
+                Some(Layer { id, name, visible })
+            })
+            .collect())
+    }
+
+    /// Create a layer, listing it in /OCGs and in the /Order of the default configuration, in which it's
+    /// visible or hidden.
+    pub fn add_layer(&mut self, name: &str, visible: bool) -> Result<ObjectId> {
+        let layer_id = self.add_object(dictionary! {
+            "Type" => "OCG",
+            "Name" => Object::String(encode_text_string(name), StringFormat::Literal),
+        });
+        self.update_optional_content(|properties, configuration| {
+            for (dict, key) in [(properties, "OCGs"), (configuration, "Order")] {
+                match dict.get_mut(key.as_bytes()).and_then(Object::as_array_mut) {
+                    Ok(ids) => ids.push(layer_id.into()),
+                    Err(_) => dict.set(key, vec![layer_id.into()]),
+                }
+            }
+        })?;
+        self.set_layer_visibility(layer_id, visible)?;
+        Ok(layer_id)
+    }
+
+    /// Set whether a layer is shown when the document is opened, using the /ON and /OFF arrays of the default
+    /// configuration.
+    pub fn set_layer_visibility(&mut self, layer_id: ObjectId, visible: bool) -> Result<()> {
+        self.update_optional_content(|_, configuration| {
+            for key in [b"ON".as_ref(), b"OFF".as_ref()] {
+                if let Ok(ids) = configuration.get_mut(key).and_then(Object::as_array_mut) {
+                    ids.retain(|id| id.as_reference().ok() != Some(layer_id));
+                }
+            }
+            let key = if visible { "ON" } else { "OFF" };
+            match configuration.get_mut(key.as_bytes()).and_then(Object::as_array_mut) {
+                Ok(ids) => ids.push(layer_id.into()),
+                Err(_) => configuration.set(key, vec![layer_id.into()]),
+            }
+        })
+    }
+
+    /// Append content to a page as marked content of a layer, `/OC /name BDC ... EMC`, which is only shown
+    /// while the layer is visible. The layer is added to the /Properties resources of the page.
+    pub fn add_layer_content(&mut self, page_id: ObjectId, layer_id: ObjectId, content: Vec<u8>) -> Result<()> {
+        let resource_name = format!("OC{}", layer_id.0);
+        let resources = self.get_or_create_resources(page_id).and_then(Object::as_dict_mut)?;
+        if !resources.has(b"Properties") {
+            resources.set("Properties", Dictionary::new());
+        }
+        let properties = match resources.get(b"Properties").and_then(Object::as_reference) {
+            Ok(properties_id) => self.get_object_mut(properties_id)?,
+            Err(_) => self
+                .get_or_create_resources(page_id)
+                .and_then(Object::as_dict_mut)?
+                .get_mut(b"Properties")?,
+        };
+        properties.as_dict_mut()?.set(resource_name.as_bytes(), layer_id);
+
+        let begin = Content {
+            operations: vec![Operation::new(
+                "BDC",
+                vec!["OC".into(), Object::Name(resource_name.into_bytes())],
+            )],
+        };
+        let mut marked = begin.encode()?;
+        marked.push(b'\n');
+        marked.extend(content);
+        marked.extend_from_slice(b"\nEMC");
+        self.append_page_content(page_id, marked)
+    }
+
+    /// Modify the /OCProperties dictionary of the catalog and its default configuration /D, which are created if
+    /// needed.
+    fn update_optional_content<F: FnOnce(&mut Dictionary, &mut Dictionary)>(&mut self, update: F) -> Result<()> {
+        let catalog_id = self.trailer.get(b"Root").and_then(Object::as_reference)?;
+        let properties_id = self.catalog()?.get(b"OCProperties").and_then(Object::as_reference).ok();
+        let mut properties = match properties_id {
+            Some(id) => self.get_dictionary(id)?.clone(),
+            None => self
+                .catalog()?
+                .get(b"OCProperties")
+                .and_then(Object::as_dict)
+                .cloned()
+                .unwrap_or_default(),
+        };
+        let configuration_id = properties.get(b"D").and_then(Object::as_reference).ok();
+        let mut configuration = match configuration_id {
+            Some(id) => self.get_dictionary(id)?.clone(),
+            None => properties
+                .get(b"D")
+                .and_then(Object::as_dict)
+                .cloned()
+                .unwrap_or_default(),
+        };
+
+        update(&mut properties, &mut configuration);
+
+        match configuration_id {
+            Some(id) => {
+                self.objects.insert(id, Object::Dictionary(configuration));
+            }
+            None => properties.set("D", configuration),
+        }
+        match properties_id {
+            Some(id) => {
+                self.objects.insert(id, Object::Dictionary(properties));
+            }
+            None => self
+                .get_object_mut(catalog_id)
+                .and_then(Object::as_dict_mut)?
+                .set("OCProperties", properties),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+#[test]
+fn add_and_toggle_layers() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    assert!(doc.get_layers().unwrap().is_empty());
+    let page_id = doc.page_iter().next().unwrap();
+    let walls_id = doc.add_layer("Walls", true).unwrap();
+    let wiring_id = doc.add_layer("Wiring", false).unwrap();
+    let content = Content {
+        operations: vec![
+            Operation::new("re", vec![10.into(), 10.into(), 100.into(), 100.into()]),
+            Operation::new("S", vec![]),
+        ],
+    };
+    doc.add_layer_content(page_id, wiring_id, content.encode().unwrap())
+        .unwrap();
+
+    let layers = doc.get_layers().unwrap();
+    assert_eq!(layers.len(), 2);
+    assert_eq!(layers[0].name, "Walls");
+    assert!(layers[0].visible);
+    assert_eq!(layers[1].id, wiring_id);
+    assert!(!layers[1].visible);
+
+    doc.set_layer_visibility(walls_id, false).unwrap();
+    doc.set_layer_visibility(wiring_id, true).unwrap();
+    let visible: Vec<bool> = doc.get_layers().unwrap().iter().map(|layer| layer.visible).collect();
+    assert_eq!(visible, vec![false, true]);
+
+    let content = doc.get_and_decode_page_content(page_id).unwrap();
+    let operators: Vec<&str> = content
+        .operations
+        .iter()
+        .map(|operation| operation.operator.as_str())
+        .collect();
+    assert_eq!(operators[operators.len() - 4..], ["BDC", "re", "S", "EMC"]);
+    let marked = &content.operations[operators.len() - 4];
+    let properties = doc.get_page_resources(page_id).0.unwrap().get(b"Properties").unwrap();
+    let name = marked.operands[1].as_name().unwrap();
+    assert_eq!(
+        properties.as_dict().unwrap().get(name).unwrap().as_reference().unwrap(),
+        wiring_id
+    );
+}
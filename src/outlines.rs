@@ -0,0 +1,354 @@
+use crate::encodings::{decode_text_string, encode_text_string};
+use crate::{Dictionary, Document, Object, ObjectId, Result, StringFormat};
+use std::collections::BTreeSet;
+
+/// Document outline, also known as bookmarks.
+#[derive(Debug, Clone, Default)]
+pub struct OutlineTree {
+    /// Top-level outline items.
+    pub items: Vec<OutlineItem>,
+}
+
+impl OutlineTree {
+    pub fn new() -> OutlineTree {
+        OutlineTree::default()
+    }
+
+    pub fn with_item(mut self, item: OutlineItem) -> OutlineTree {
+        self.items.push(item);
+        self
+    }
+
+    /// Iterate over all items depth-first, parents before their kids.
+    pub fn iter(&self) -> impl Iterator<Item = &OutlineItem> {
+        let mut stack: Vec<&OutlineItem> = self.items.iter().rev().collect();
+        std::iter::from_fn(move || {
+            let item = stack.pop()?;
+            stack.extend(item.kids.iter().rev());
+            Some(item)
+        })
+    }
+}
+
+/// Outline item, with its nested items as kids.
+#[derive(Debug, Clone)]
+pub struct OutlineItem {
+    pub title: String,
+    pub target: Option<OutlineTarget>,
+    /// Whether the kids are shown when the outline is first displayed.
+    pub open: bool,
+    /// Color of the title as RGB components.
+    pub color: Option<[f64; 3]>,
+    pub flags: u32,
+    pub kids: Vec<OutlineItem>,
+}
+
+/// What happens when an outline item is activated.
+#[derive(Debug, Clone)]
+pub enum OutlineTarget {
+    /// Explicit destination array or name of a named destination, given by /Dest.
+    Destination(Object),
+    /// Action dictionary, given by /A.
+    Action(Dictionary),
+}
+
+impl OutlineItem {
+    pub const ITALIC: u32 = 1;
+    pub const BOLD: u32 = 1 << 1;
+
+    pub fn new(title: &str) -> OutlineItem {
+        OutlineItem {
+            title: title.to_owned(),
+            target: None,
+            open: false,
+            color: None,
+            flags: 0,
+            kids: Vec::new(),
+        }
+    }
+
+    /// Go to a page, fitting it in the window.
+    pub fn with_page(self, page_id: ObjectId) -> OutlineItem {
+        self.with_destination(Object::Array(vec![page_id.into(), "Fit".into()]))
+    }
+
+    /// Go to a position of a page, the top of the window being at the given coordinate.
+    pub fn with_page_position(self, page_id: ObjectId, top: f64) -> OutlineItem {
+        self.with_destination(Object::Array(vec![
+            page_id.into(),
+            "XYZ".into(),
+            Object::Null,
+            top.into(),
+            Object::Null,
+        ]))
+    }
+
+    pub fn with_destination(mut self, destination: Object) -> OutlineItem {
+        self.target = Some(OutlineTarget::Destination(destination));
+        self
+    }
+
+    pub fn with_action(mut self, action: Dictionary) -> OutlineItem {
+        self.target = Some(OutlineTarget::Action(action));
+        self
+    }
+
+    /// Open a web address.
+    pub fn with_uri(self, uri: &str) -> OutlineItem {
+        self.with_action(dictionary! {
+            "S" => "URI",
+            "URI" => Object::string_literal(uri),
+        })
+    }
+
+    pub fn with_kid(mut self, kid: OutlineItem) -> OutlineItem {
+        self.kids.push(kid);
+        self
+    }
+
+    pub fn with_open(mut self, open: bool) -> OutlineItem {
+        self.open = open;
+        self
+    }
+
+    pub fn with_color(mut self, color: [f64; 3]) -> OutlineItem {
+        self.color = Some(color);
+        self
+    }
+
+    pub fn with_flags(mut self, flags: u32) -> OutlineItem {
+        self.flags = flags;
+        self
+    }
+
+    /// Number of descendants shown when this item is open, as given by /Count.
+    fn visible_descendants(&self) -> i64 {
+        self.kids
+            .iter()
+            .map(|kid| 1 + if kid.open { kid.visible_descendants() } else { 0 })
+            .sum()
+    }
+}
+
+impl Document {
+    /// Get the outline of the document, which is empty if it has none.
+    pub fn get_outline(&self) -> Result<OutlineTree> {
+        let outlines = match self.catalog()?.get(b"Outlines") {
+            Ok(outlines) => self.dereference(outlines)?.1.as_dict()?,
+            Err(_) => return Ok(OutlineTree::new()),
+        };
+        let mut visited = BTreeSet::new();
+        Ok(OutlineTree {
+            items: self.read_outline_items(outlines, &mut visited),
+        })
+    }
+
+    fn read_outline_items(&self, parent: &Dictionary, visited: &mut BTreeSet<ObjectId>) -> Vec<OutlineItem> {
+        let mut items = Vec::new();
+        let mut next = parent.get(b"First").and_then(Object::as_reference).ok();
+        while let Some(id) = next {
+            if !visited.insert(id) {
+                break;
+            }
+            let dict = match self.get_dictionary(id) {
+                Ok(dict) => dict,
+                Err(_) => break,
+            };
+            next = dict.get(b"Next").and_then(Object::as_reference).ok();
+
+            let resolve = |key: &[u8]| {
+                dict.get(key)
+                    .and_then(|object| self.dereference(object))
+                    .map(|(_, object)| object)
+            };
+            let target = match (resolve(b"Dest"), resolve(b"A")) {
+                (Ok(destination), _) => Some(OutlineTarget::Destination(destination.clone())),
+                (_, Ok(Object::Dictionary(action))) => Some(OutlineTarget::Action(action.clone())),
+                _ => None,
+            };
+            let color = resolve(b"C").and_then(Object::as_array).ok().and_then(|color| {
+                match color.iter().map(Object::as_float).collect::<Result<Vec<f64>>>() {
+                    Ok(ref color) if color.len() == 3 => Some([color[0], color[1], color[2]]),
+                    _ => None,
+                }
+            });
+            items.push(OutlineItem {
+                title: resolve(b"Title")
+                    .and_then(Object::as_str)
+                    .map(decode_text_string)
+                    .unwrap_or_default(),
+                target,
+                open: dict.get(b"Count").and_then(Object::as_i64).unwrap_or(0) > 0,
+                color,
+                flags: dict.get(b"F").and_then(Object::as_i64).unwrap_or(0) as u32,
+                kids: self.read_outline_items(dict, visited),
+            });
+        }
+        items
+    }
+
+    /// Replace the outline of the document, linking the items and computing their counts.
+    ///
+    /// The objects of the previous outline are removed. An empty tree removes the outline.
+    pub fn set_outline(&mut self, outline: &OutlineTree) -> Result<()> {
+        let catalog_id = self.trailer.get(b"Root").and_then(Object::as_reference)?;
+        if let Ok(&Object::Reference(outlines_id)) = self.catalog()?.get(b"Outlines") {
+            let mut ids = BTreeSet::new();
+            self.collect_outline_ids(outlines_id, &mut ids);
+            for id in ids {
+                self.objects.remove(&id);
+            }
+        }
+
+        let catalog = self.get_object_mut(catalog_id).and_then(Object::as_dict_mut)?;
+        if outline.items.is_empty() {
+            catalog.remove(b"Outlines");
+            return Ok(());
+        }
+        let count: i64 = outline
+            .items
+            .iter()
+            .map(|item| 1 + if item.open { item.visible_descendants() } else { 0 })
+            .sum();
+        let outlines_id = self.new_object_id();
+        let mut outlines = dictionary! {
+            "Type" => "Outlines",
+            "Count" => count,
+        };
+        self.write_outline_items(outlines_id, &mut outlines, &outline.items);
+        self.objects.insert(outlines_id, Object::Dictionary(outlines));
+        self.get_object_mut(catalog_id)
+            .and_then(Object::as_dict_mut)?
+            .set("Outlines", outlines_id);
+        Ok(())
+    }
+
+    fn collect_outline_ids(&self, id: ObjectId, ids: &mut BTreeSet<ObjectId>) {
+        if !ids.insert(id) {
+            return;
+        }
+        if let Ok(dict) = self.get_dictionary(id) {
+            for key in [b"First".as_ref(), b"Next".as_ref()].iter() {
+                if let Ok(next_id) = dict.get(key).and_then(Object::as_reference) {
+                    self.collect_outline_ids(next_id, ids);
+                }
+            }
+        }
+    }
+
+    fn write_outline_items(&mut self, parent_id: ObjectId, parent: &mut Dictionary, items: &[OutlineItem]) {
+        let ids: Vec<ObjectId> = items.iter().map(|_| self.new_object_id()).collect();
+        if let (Some(&first), Some(&last)) = (ids.first(), ids.last()) {
+            parent.set("First", first);
+            parent.set("Last", last);
+        }
+
+        for (index, item) in items.iter().enumerate() {
+            let mut dict = dictionary! {
+                "Title" => Object::String(encode_text_string(&item.title), StringFormat::Literal),
+                "Parent" => parent_id,
+            };
+            if index > 0 {
+                dict.set("Prev", ids[index - 1]);
+            }
+            if let Some(&next) = ids.get(index + 1) {
+                dict.set("Next", next);
+            }
+            if !item.kids.is_empty() {
+                let count = item.visible_descendants();
+                dict.set("Count", if item.open { count } else { -count });
+            }
+            match item.target {
+                Some(OutlineTarget::Destination(ref destination)) => dict.set("Dest", destination.clone()),
+                Some(OutlineTarget::Action(ref action)) => dict.set("A", action.clone()),
+                None => {}
+            }
+            if let Some(color) = item.color {
+                dict.set(
+                    "C",
+                    color
+                        .iter()
+                        .map(|&component| Object::Real(component))
+                        .collect::<Vec<_>>(),
+                );
+            }
+            if item.flags != 0 {
+                dict.set("F", item.flags as i64);
+            }
+            self.write_outline_items(ids[index], &mut dict, &item.kids);
+            self.objects.insert(ids[index], Object::Dictionary(dict));
+        }
+    }
+}
+
+#[test]
+fn set_and_get_outline() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    let outline = OutlineTree::new()
+        .with_item(
+            OutlineItem::new("Chapter 1")
+                .with_page(page_id)
+                .with_open(true)
+                .with_kid(OutlineItem::new("Section 1.1").with_page_position(page_id, 500.0))
+                .with_kid(
+                    OutlineItem::new("Section 1.2")
+                        .with_flags(OutlineItem::BOLD)
+                        .with_kid(OutlineItem::new("Übersicht")),
+                ),
+        )
+        .with_item(
+            OutlineItem::new("Website")
+                .with_uri("https://example.com")
+                .with_color([1.0, 0.0, 0.0]),
+        );
+    doc.set_outline(&outline).unwrap();
+    let read = doc.get_outline().unwrap();
+    let titles: Vec<&str> = read.iter().map(|item| item.title.as_str()).collect();
+    assert_eq!(
+        titles,
+        ["Chapter 1", "Section 1.1", "Section 1.2", "Übersicht", "Website"]
+    );
+    assert!(read.items[0].open);
+    assert_eq!(read.items[0].kids[1].flags, OutlineItem::BOLD);
+    assert_eq!(read.items[1].color, Some([1.0, 0.0, 0.0]));
+    match read.items[1].target {
+        Some(OutlineTarget::Action(ref action)) => {
+            assert_eq!(action.get(b"S").and_then(Object::as_name_str).unwrap(), "URI")
+        }
+        _ => panic!("expected an action"),
+    }
+    match read.items[0].kids[0].target {
+        Some(OutlineTarget::Destination(ref destination)) => {
+            assert_eq!(destination.as_array().unwrap()[0].as_reference().unwrap(), page_id)
+        }
+        _ => panic!("expected a destination"),
+    }
+
+    let outlines = doc
+        .catalog()
+        .and_then(|catalog| catalog.get(b"Outlines"))
+        .and_then(Object::as_reference)
+        .and_then(|id| doc.get_dictionary(id))
+        .unwrap();
+    assert_eq!(outlines.get(b"Count").and_then(Object::as_i64).unwrap(), 4);
+    let first = outlines.get(b"First").and_then(Object::as_reference).unwrap();
+    let first = doc.get_dictionary(first).unwrap();
+    assert_eq!(first.get(b"Count").and_then(Object::as_i64).unwrap(), 2);
+    let last = first.get(b"Last").and_then(Object::as_reference).unwrap();
+    let last = doc.get_dictionary(last).unwrap();
+    assert_eq!(last.get(b"Count").and_then(Object::as_i64).unwrap(), -1);
+    assert!(last.has(b"Prev"));
+    assert!(!last.has(b"Next"));
+
+    // Replacing the outline removes the objects of the previous one.
+    let object_count = doc.objects.len();
+    doc.set_outline(&OutlineTree::new().with_item(OutlineItem::new("Only")))
+        .unwrap();
+    assert_eq!(doc.objects.len(), object_count - 4);
+    doc.set_outline(&OutlineTree::new()).unwrap();
+    assert!(doc.get_outline().unwrap().items.is_empty());
+    assert!(doc.catalog().unwrap().get(b"Outlines").is_err());
+}
@@ -0,0 +1,663 @@
+use crate::annotations::Annotation;
+use crate::content::{Content, Operation};
+use crate::{Dictionary, Document, Error, Matrix, Object, ObjectId, Point, Rectangle, Result, Stream};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Boundary of a page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageBox {
+    /// Boundary of the physical medium, which is required and inheritable.
+    MediaBox,
+    /// Region to which the page is clipped when displayed or printed, defaulting to the media box.
+    CropBox,
+    /// Region to which the page is clipped in production, defaulting to the crop box.
+    BleedBox,
+    /// Intended dimensions of the finished page after trimming, defaulting to the crop box.
+    TrimBox,
+    /// Extent of the meaningful content, defaulting to the crop box.
+    ArtBox,
+}
+
+impl PageBox {
+    pub const ALL: [PageBox; 5] = [
+        PageBox::MediaBox,
+        PageBox::CropBox,
+        PageBox::BleedBox,
+        PageBox::TrimBox,
+        PageBox::ArtBox,
+    ];
+
+    pub fn key(self) -> &'static str {
+        match self {
+            PageBox::MediaBox => "MediaBox",
+            PageBox::CropBox => "CropBox",
+            PageBox::BleedBox => "BleedBox",
+            PageBox::TrimBox => "TrimBox",
+            PageBox::ArtBox => "ArtBox",
+        }
+    }
+}
+
+/// How `Document::resize_page` fits the content of a page to its new size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FitMode {
+    /// Scale the content uniformly to fit the new size and center it.
+    Fit,
+    /// Scale the content uniformly to cover the new size and center it, cutting off what lies outside.
+    Fill,
+    /// Scale the width and height of the content separately to match the new size.
+    Stretch,
+    /// Center the content without scaling it.
+    Center,
+}
+
+/// View of a page of a document, resolving references and the attributes it inherits from the page tree.
+#[derive(Debug, Clone, Copy)]
+pub struct Page<'a> {
+    document: &'a Document,
+    id: ObjectId,
+    dict: &'a Dictionary,
+}
+
+impl<'a> Page<'a> {
+    /// Object id of the page dictionary.
+    pub fn id(&self) -> ObjectId {
+        self.id
+    }
+
+    /// The page dictionary itself, without inherited attributes.
+    pub fn dictionary(&self) -> &'a Dictionary {
+        self.dict
+    }
+
+    /// Get an attribute of the page, dereferenced and possibly inherited from an ancestor page tree node.
+    pub fn attribute(&self, key: &[u8]) -> Option<&'a Object> {
+        self.document.get_page_attribute(self.id, key)
+    }
+
+    pub fn media_box(&self) -> Result<Rectangle> {
+        self.page_box(PageBox::MediaBox)
+    }
+
+    pub fn crop_box(&self) -> Result<Rectangle> {
+        self.page_box(PageBox::CropBox)
+    }
+
+    /// Get a page box, resolving inherited values and defaults as `Document::get_page_box`.
+    pub fn page_box(&self, page_box: PageBox) -> Result<Rectangle> {
+        self.document.get_page_box(self.id, page_box)
+    }
+
+    /// Clockwise rotation of the page when displayed, which is 0, 90, 180 or 270 degrees.
+    pub fn rotation(&self) -> i64 {
+        self.document.get_page_rotation(self.id)
+    }
+
+    /// Resource dictionary of the page, possibly inherited.
+    pub fn resources(&self) -> Option<&'a Dictionary> {
+        self.attribute(b"Resources")
+            .and_then(|resources| resources.as_dict().ok())
+    }
+
+    /// Fonts of the resources of the page, by resource name.
+    pub fn fonts(&self) -> BTreeMap<Vec<u8>, &'a Dictionary> {
+        self.document.get_page_fonts(self.id)
+    }
+
+    /// Object ids of the content streams of the page.
+    pub fn contents(&self) -> Vec<ObjectId> {
+        self.document.get_page_contents(self.id)
+    }
+
+    /// Decompressed content of the page, with its content streams concatenated.
+    pub fn content(&self) -> Result<Vec<u8>> {
+        self.document.get_page_content(self.id)
+    }
+
+    pub fn annotations(&self) -> Result<Vec<Annotation>> {
+        self.document.get_page_annotations(self.id)
+    }
+}
+
+impl Document {
+    /// Get a view of a page, counting from one.
+    pub fn get_page(&self, page_number: u32) -> Result<Page<'_>> {
+        let page_id = self
+            .page_iter()
+            .nth(
+                page_number
+                    .checked_sub(1)
+                    .ok_or(Error::PageNumberNotFound(page_number))? as usize,
+            )
+            .ok_or(Error::PageNumberNotFound(page_number))?;
+        self.get_page_by_id(page_id)
+    }
+
+    /// Get a view of a page from the id of its dictionary.
+    pub fn get_page_by_id(&self, page_id: ObjectId) -> Result<Page<'_>> {
+        let dict = self.get_dictionary(page_id)?;
+        if !dict.type_is(b"Page") {
+            return Err(Error::Type);
+        }
+        Ok(Page {
+            document: self,
+            id: page_id,
+            dict,
+        })
+    }
+
+    /// Iterate over views of the pages in page order.
+    pub fn pages(&self) -> impl Iterator<Item = Page<'_>> + '_ {
+        self.page_iter()
+            .filter_map(move |page_id| self.get_page_by_id(page_id).ok())
+    }
+
+    /// Get an attribute of a page, which may be inherited from an ancestor page tree node.
+    pub(crate) fn get_page_attribute(&self, page_id: ObjectId, key: &[u8]) -> Option<&Object> {
+        let mut visited = BTreeSet::new();
+        let mut node_id = page_id;
+        while visited.insert(node_id) {
+            let node = self.get_dictionary(node_id).ok()?;
+            if let Ok(value) = node.get(key) {
+                return self.dereference(value).map(|(_, value)| value).ok();
+            }
+            node_id = node.get(b"Parent").and_then(Object::as_reference).ok()?;
+        }
+        None
+    }
+
+    /// Get a page box, resolving inherited values and defaults.
+    ///
+    /// Boxes other than the media box are clipped to it, as they are when displaying the page.
+    pub fn get_page_box(&self, page_id: ObjectId, page_box: PageBox) -> Result<Rectangle> {
+        let media_box = self
+            .get_page_attribute(page_id, b"MediaBox")
+            .ok_or(Error::DictKey)
+            .and_then(Rectangle::from_object)?;
+        let rect = match page_box {
+            PageBox::MediaBox => return Ok(media_box),
+            // The crop box is inheritable, while the other boxes are not.
+            PageBox::CropBox => self.get_page_attribute(page_id, b"CropBox"),
+            _ => self
+                .get_dictionary(page_id)?
+                .get(page_box.key().as_bytes())
+                .and_then(|rect| self.dereference(rect))
+                .map(|(_, rect)| rect)
+                .ok(),
+        };
+        match rect.map(Rectangle::from_object) {
+            Some(rect) => Ok(rect?.intersection(&media_box).unwrap_or_default()),
+            None if page_box == PageBox::CropBox => Ok(media_box),
+            None => self.get_page_box(page_id, PageBox::CropBox),
+        }
+    }
+
+    /// Set a page box on the page itself, overriding any inherited value.
+    ///
+    /// Fails if the crop box would not be contained in the media box.
+    pub fn set_page_box(&mut self, page_id: ObjectId, page_box: PageBox, rect: Rectangle) -> Result<()> {
+        let (media_box, crop_box) = match page_box {
+            PageBox::MediaBox => match self.get_page_attribute(page_id, b"CropBox") {
+                Some(crop_box) => (rect, Rectangle::from_object(crop_box)?),
+                None => (rect, rect),
+            },
+            PageBox::CropBox => (self.get_page_box(page_id, PageBox::MediaBox)?, rect),
+            _ => (rect, rect),
+        };
+        if !media_box.contains(&crop_box) {
+            return Err(Error::PageBoxOutsideMediaBox);
+        }
+        self.get_object_mut(page_id)
+            .and_then(Object::as_dict_mut)?
+            .set(page_box.key(), rect);
+        Ok(())
+    }
+
+    /// Get the clockwise rotation of a page when displayed, which is 0, 90, 180 or 270 degrees.
+    pub fn get_page_rotation(&self, page_id: ObjectId) -> i64 {
+        let rotation = self
+            .get_page_attribute(page_id, b"Rotate")
+            .and_then(|rotation| rotation.as_i64().ok())
+            .unwrap_or(0);
+        normalize_rotation(rotation)
+    }
+
+    /// Rotate a page clockwise by a multiple of 90 degrees, adjusting its /Rotate entry.
+    pub fn rotate_page(&mut self, page_id: ObjectId, degrees: i64) -> Result<()> {
+        if degrees % 90 != 0 {
+            return Err(Error::Type);
+        }
+        let rotation = normalize_rotation(self.get_page_rotation(page_id) + degrees);
+        self.get_object_mut(page_id)
+            .and_then(Object::as_dict_mut)?
+            .set("Rotate", rotation);
+        Ok(())
+    }
+
+    /// Rotate all pages clockwise by a multiple of 90 degrees.
+    pub fn rotate_all_pages(&mut self, degrees: i64) -> Result<()> {
+        let page_ids: Vec<ObjectId> = self.page_iter().collect();
+        for page_id in page_ids {
+            self.rotate_page(page_id, degrees)?;
+        }
+        Ok(())
+    }
+
+    /// Apply the /Rotate entry of a page to its content, boxes and annotation rectangles,
+    /// so that the page looks the same in viewers which ignore /Rotate.
+    pub fn bake_page_rotation(&mut self, page_id: ObjectId) -> Result<()> {
+        let rotation = self.get_page_rotation(page_id);
+        if rotation == 0 {
+            // Set it explicitly, in case it's inherited.
+            return self.rotate_page(page_id, 0);
+        }
+        let media_box = self
+            .get_page_attribute(page_id, b"MediaBox")
+            .ok_or(Error::DictKey)
+            .and_then(Rectangle::from_object)?;
+        let matrix = rotation_matrix(rotation, &media_box);
+
+        let boxes = self.get_inherited_page_boxes(page_id);
+        self.transform_page(page_id, &matrix)?;
+
+        let page = self.get_object_mut(page_id).and_then(Object::as_dict_mut)?;
+        for (name, rect) in boxes {
+            page.set(name, matrix.transform_rect(&rect));
+        }
+        page.set("Rotate", 0);
+        Ok(())
+    }
+
+    /// Scale the content of a page, its boxes and the rectangles of its annotations by a factor, keeping the
+    /// origin in place.
+    pub fn scale_page(&mut self, page_id: ObjectId, factor: f64) -> Result<()> {
+        if !factor.is_finite() || factor <= 0.0 {
+            return Err(Error::Type);
+        }
+        let matrix = Matrix::scale(factor, factor);
+        let boxes = self.get_inherited_page_boxes(page_id);
+        self.transform_page(page_id, &matrix)?;
+
+        let page = self.get_object_mut(page_id).and_then(Object::as_dict_mut)?;
+        for (name, rect) in boxes {
+            page.set(name, matrix.transform_rect(&rect));
+        }
+        Ok(())
+    }
+
+    /// Change the media box of a page to a width and height, before applying its /Rotate entry, and transform
+    /// the content of its crop box to the new size according to the fit mode.
+    ///
+    /// The other boxes and the rectangles of annotations are transformed with the content, and the boxes are
+    /// clipped to the new media box.
+    pub fn resize_page(&mut self, page_id: ObjectId, new_size: (f64, f64), fit_mode: FitMode) -> Result<()> {
+        let (width, height) = new_size;
+        let crop_box = self.get_page_box(page_id, PageBox::CropBox)?;
+        if !(width > 0.0 && height > 0.0 && crop_box.width() > 0.0 && crop_box.height() > 0.0) {
+            return Err(Error::Type);
+        }
+        let (sx, sy) = (width / crop_box.width(), height / crop_box.height());
+        let (sx, sy) = match fit_mode {
+            FitMode::Fit => (sx.min(sy), sx.min(sy)),
+            FitMode::Fill => (sx.max(sy), sx.max(sy)),
+            FitMode::Stretch => (sx, sy),
+            FitMode::Center => (1.0, 1.0),
+        };
+        let matrix = Matrix::translate(0.0 - crop_box.llx, 0.0 - crop_box.lly)
+            .then(&Matrix::scale(sx, sy))
+            .then(&Matrix::translate(
+                (width - crop_box.width() * sx) / 2.0,
+                (height - crop_box.height() * sy) / 2.0,
+            ));
+        let media_box = Rectangle::new(0.0, 0.0, width, height);
+        let boxes = self.get_inherited_page_boxes(page_id);
+        self.transform_page(page_id, &matrix)?;
+
+        let page = self.get_object_mut(page_id).and_then(Object::as_dict_mut)?;
+        for (name, rect) in boxes {
+            let rect = matrix
+                .transform_rect(&rect)
+                .intersection(&media_box)
+                .unwrap_or_default();
+            page.set(name, rect);
+        }
+        page.set("MediaBox", media_box);
+        Ok(())
+    }
+
+    /// Get the boxes set on a page or inherited by it, without defaults.
+    fn get_inherited_page_boxes(&self, page_id: ObjectId) -> Vec<(&'static str, Rectangle)> {
+        PageBox::ALL
+            .iter()
+            .filter_map(|page_box| {
+                let rect = self.get_page_attribute(page_id, page_box.key().as_bytes())?;
+                Some((page_box.key(), Rectangle::from_object(rect).ok()?))
+            })
+            .collect()
+    }
+
+    /// Transform the content of a page and the geometry of its annotations with a matrix, wrapping the content
+    /// streams between streams saving the graphics state and concatenating the matrix, and restoring it.
+    ///
+    /// The page boxes are left unchanged. Graphics states saved and not restored by the content are restored
+    /// before the final restore, and states restored without being saved are saved with the matrix applied
+    /// beforehand, so that the whole content stays transformed and the page ends in its original state.
+    pub fn transform_page(&mut self, page_id: ObjectId, matrix: &Matrix) -> Result<()> {
+        let annotation_ids: Vec<ObjectId> = self
+            .get_dictionary(page_id)
+            .and_then(|page| page.get(b"Annots"))
+            .and_then(|annotations| self.dereference(annotations))
+            .and_then(|(_, annotations)| annotations.as_array())
+            .ok()
+            .map(|annotations| {
+                annotations
+                    .iter()
+                    .filter_map(|annotation| annotation.as_reference().ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+        for annotation_id in annotation_ids {
+            if let Ok(annotation) = self.get_object_mut(annotation_id).and_then(Object::as_dict_mut) {
+                if let Ok(rect) = annotation.get(b"Rect").and_then(Rectangle::from_object) {
+                    annotation.set("Rect", matrix.transform_rect(&rect));
+                }
+                for key in [b"QuadPoints".as_ref(), b"Vertices", b"L"].iter() {
+                    if let Ok(points) = annotation.get_mut(key).and_then(Object::as_array_mut) {
+                        transform_points(points, matrix);
+                    }
+                }
+                if let Ok(paths) = annotation.get_mut(b"InkList").and_then(Object::as_array_mut) {
+                    for path in paths.iter_mut().filter_map(|path| path.as_array_mut().ok()) {
+                        transform_points(path, matrix);
+                    }
+                }
+            }
+        }
+
+        let (unmatched_restores, unclosed_saves) = graphics_state_balance(&self.get_page_content(page_id)?);
+        let mut begin = vec![Operation::new("q", vec![]), Operation::new("cm", matrix.to_operands())];
+        begin.extend((0..unmatched_restores).map(|_| Operation::new("q", vec![])));
+        let begin_id = self.add_object(Stream::new(dictionary! {}, Content { operations: begin }.encode()?));
+        let end = b"\nQ".repeat(unclosed_saves + 1);
+        let end_id = self.add_object(Stream::new(dictionary! {}, [end.as_slice(), b"\n"].concat()));
+        let mut contents: Vec<Object> = self
+            .get_page_contents(page_id)
+            .into_iter()
+            .map(Object::Reference)
+            .collect();
+        contents.insert(0, begin_id.into());
+        contents.push(end_id.into());
+
+        self.get_object_mut(page_id)
+            .and_then(Object::as_dict_mut)?
+            .set("Contents", contents);
+        Ok(())
+    }
+}
+
+/// Transform an array of coordinates given as pairs of numbers, leaving it unchanged if it has other objects.
+fn transform_points(points: &mut Vec<Object>, matrix: &Matrix) {
+    let numbers = match points.iter().map(Object::as_float).collect::<Result<Vec<f64>>>() {
+        Ok(numbers) if numbers.len() % 2 == 0 => numbers,
+        _ => return,
+    };
+    *points = numbers
+        .chunks(2)
+        .flat_map(|pair| {
+            let point = matrix.transform_point(Point::new(pair[0], pair[1]));
+            vec![Object::Real(point.x), Object::Real(point.y)]
+        })
+        .collect();
+}
+
+/// Count the restores of graphics states which content makes without saving them first, and the saved states it
+/// leaves without restoring them.
+///
+/// The content is scanned for the operators rather than decoded, skipping comments, strings, names and inline
+/// image data, so that this also works without a parser.
+fn graphics_state_balance(content: &[u8]) -> (usize, usize) {
+    let is_regular = |byte: &u8| !byte.is_ascii_whitespace() && !b"()<>[]{}/%".contains(byte);
+    let skip_while = |mut index: usize, condition: &dyn Fn(&u8) -> bool| {
+        while content.get(index).is_some_and(condition) {
+            index += 1;
+        }
+        index
+    };
+    let (mut unmatched_restores, mut depth) = (0, 0);
+    let mut index = 0;
+    while index < content.len() {
+        index = match content[index] {
+            b'%' => skip_while(index, &|byte| !b"\r\n".contains(byte)),
+            b'/' => skip_while(index + 1, &is_regular),
+            b'<' if content.get(index + 1) != Some(&b'<') => skip_while(index, &|&byte| byte != b'>') + 1,
+            b'(' => {
+                let mut nesting = 0;
+                while index < content.len() {
+                    match content[index] {
+                        b'\\' => index += 1,
+                        b'(' => nesting += 1,
+                        b')' if nesting == 1 => break,
+                        b')' => nesting -= 1,
+                        _ => {}
+                    }
+                    index += 1;
+                }
+                index + 1
+            }
+            byte if is_regular(&byte) => {
+                let mut end = skip_while(index, &is_regular);
+                match &content[index..end] {
+                    b"q" => depth += 1,
+                    b"Q" if depth > 0 => depth -= 1,
+                    b"Q" => unmatched_restores += 1,
+                    b"ID" => {
+                        // Inline image data ends at an EI operator, preceded and followed by white space.
+                        let is_data_end = |index: usize| {
+                            content[index - 1].is_ascii_whitespace()
+                                && content[index..].starts_with(b"EI")
+                                && content.get(index + 2).is_none_or(u8::is_ascii_whitespace)
+                        };
+                        end += 1;
+                        while end < content.len() && !is_data_end(end) {
+                            end += 1;
+                        }
+                    }
+                    _ => {}
+                }
+                end
+            }
+            _ => index + 1,
+        };
+    }
+    (unmatched_restores, depth)
+}
+
+fn normalize_rotation(degrees: i64) -> i64 {
+    degrees.rem_euclid(360) / 90 * 90
+}
+
+/// Matrix which rotates the media box clockwise, moving its lower-left corner to the origin.
+/// Offsets are subtracted from zero rather than negated, so that no negative zero is written.
+pub(crate) fn rotation_matrix(rotation: i64, media_box: &Rectangle) -> Matrix {
+    let Rectangle { llx, lly, urx, ury } = *media_box;
+    match rotation {
+        90 => Matrix::new(0.0, -1.0, 1.0, 0.0, 0.0 - lly, urx),
+        180 => Matrix::new(-1.0, 0.0, 0.0, -1.0, urx, ury),
+        270 => Matrix::new(0.0, 1.0, -1.0, 0.0, ury, 0.0 - llx),
+        _ => Matrix::IDENTITY,
+    }
+}
+
+#[test]
+fn rotate_pages() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    assert_eq!(doc.get_page_rotation(page_id), 0);
+    doc.rotate_page(page_id, 90).unwrap();
+    assert_eq!(doc.get_page_rotation(page_id), 90);
+    doc.rotate_all_pages(-180).unwrap();
+    assert_eq!(doc.get_page_rotation(page_id), 270);
+    assert!(doc.rotate_page(page_id, 45).is_err());
+
+    doc.rotate_page(page_id, 180).unwrap();
+    doc.bake_page_rotation(page_id).unwrap();
+    assert_eq!(doc.get_page_rotation(page_id), 0);
+    let page = doc.get_dictionary(page_id).unwrap();
+    let media_box = page.get(b"MediaBox").and_then(Rectangle::from_object).unwrap();
+    assert_eq!(media_box, Rectangle::new(0.0, 0.0, 842.0, 595.0));
+    let content = String::from_utf8(doc.get_page_content(page_id).unwrap()).unwrap();
+    assert!(content.starts_with("q\n0.0 -1.0 1.0 0.0 0.0 595.0 cm\n"));
+    assert!(content.ends_with("Q\n"));
+}
+
+#[test]
+fn page_boxes() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    let media_box = Rectangle::new(0.0, 0.0, 595.0, 842.0);
+    assert_eq!(doc.get_page_box(page_id, PageBox::MediaBox).unwrap(), media_box);
+    assert_eq!(doc.get_page_box(page_id, PageBox::TrimBox).unwrap(), media_box);
+
+    let crop_box = Rectangle::new(10.0, 10.0, 585.0, 832.0);
+    doc.set_page_box(page_id, PageBox::CropBox, crop_box).unwrap();
+    assert_eq!(doc.get_page_box(page_id, PageBox::ArtBox).unwrap(), crop_box);
+    doc.set_page_box(page_id, PageBox::BleedBox, Rectangle::new(-5.0, 5.0, 590.0, 837.0))
+        .unwrap();
+    assert_eq!(
+        doc.get_page_box(page_id, PageBox::BleedBox).unwrap(),
+        Rectangle::new(0.0, 5.0, 590.0, 837.0)
+    );
+
+    assert!(doc
+        .set_page_box(page_id, PageBox::CropBox, Rectangle::new(0.0, 0.0, 600.0, 842.0))
+        .is_err());
+    assert!(doc
+        .set_page_box(page_id, PageBox::MediaBox, Rectangle::new(20.0, 20.0, 595.0, 842.0))
+        .is_err());
+    doc.set_page_box(page_id, PageBox::MediaBox, Rectangle::new(0.0, 0.0, 612.0, 842.0))
+        .unwrap();
+    assert_eq!(doc.get_page_box(page_id, PageBox::MediaBox).unwrap().width(), 612.0);
+}
+
+#[test]
+fn scale_and_resize_pages() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    let annotation_id = doc.add_object(dictionary! {
+        "Type" => "Annot",
+        "Subtype" => "Square",
+        "Rect" => Rectangle::new(100.0, 100.0, 200.0, 200.0),
+    });
+    doc.get_object_mut(page_id)
+        .and_then(Object::as_dict_mut)
+        .unwrap()
+        .set("Annots", vec![annotation_id.into()]);
+    let annotation_rect = |doc: &Document| {
+        doc.get_dictionary(annotation_id)
+            .and_then(|annotation| annotation.get(b"Rect"))
+            .and_then(Rectangle::from_object)
+            .unwrap()
+    };
+
+    doc.scale_page(page_id, 0.5).unwrap();
+    assert_eq!(
+        doc.get_page_box(page_id, PageBox::MediaBox).unwrap(),
+        Rectangle::new(0.0, 0.0, 297.5, 421.0)
+    );
+    assert_eq!(annotation_rect(&doc), Rectangle::new(50.0, 50.0, 100.0, 100.0));
+    let content = String::from_utf8(doc.get_page_content(page_id).unwrap()).unwrap();
+    assert!(content.starts_with("q\n0.5 0.0 0.0 0.5 0.0 0.0 cm\n"));
+    assert!(doc.scale_page(page_id, 0.0).is_err());
+
+    // A4 at half size, fitted into a square, is scaled to its height and centered horizontally.
+    doc.resize_page(page_id, (842.0, 842.0), FitMode::Fit).unwrap();
+    let media_box = doc.get_page_box(page_id, PageBox::MediaBox).unwrap();
+    assert_eq!(media_box, Rectangle::new(0.0, 0.0, 842.0, 842.0));
+    assert_eq!(annotation_rect(&doc), Rectangle::new(223.5, 100.0, 323.5, 200.0));
+
+    doc.set_page_box(page_id, PageBox::CropBox, Rectangle::new(0.0, 0.0, 421.0, 842.0))
+        .unwrap();
+    doc.resize_page(page_id, (842.0, 842.0), FitMode::Fill).unwrap();
+    assert_eq!(annotation_rect(&doc), Rectangle::new(447.0, -221.0, 647.0, -21.0));
+    assert_eq!(doc.get_page_box(page_id, PageBox::CropBox).unwrap(), media_box);
+    doc.resize_page(page_id, (421.0, 842.0), FitMode::Stretch).unwrap();
+    assert_eq!(annotation_rect(&doc), Rectangle::new(223.5, -221.0, 323.5, -21.0));
+}
+
+#[test]
+fn page_views() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    doc.rotate_page(page_id, 90).unwrap();
+    let page = doc.get_page(1).unwrap();
+    assert_eq!(page.id(), page_id);
+    assert!(page.dictionary().get(b"MediaBox").is_err());
+    assert_eq!(page.media_box().unwrap(), Rectangle::new(0.0, 0.0, 595.0, 842.0));
+    assert_eq!(page.rotation(), 90);
+    assert!(page.resources().unwrap().has(b"Font"));
+    assert_eq!(page.fonts().len(), 1);
+    assert_eq!(page.contents().len(), 1);
+    assert!(page.annotations().unwrap().is_empty());
+    assert!(doc.get_page(0).is_err());
+    assert!(doc.get_page(2).is_err());
+    assert_eq!(doc.pages().map(|page| page.id()).collect::<Vec<_>>(), vec![page_id]);
+}
+
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+#[test]
+fn transform_page_with_unbalanced_content() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    doc.change_page_content(page_id, b"Q q 1 0 0 1 5 5 cm 0 g q".to_vec())
+        .unwrap();
+    let annotation_id = doc.add_object(dictionary! {
+        "Subtype" => "Highlight",
+        "Rect" => Rectangle::new(100.0, 100.0, 200.0, 120.0),
+        "QuadPoints" => vec![100.into(), 120.into(), 200.into(), 120.into(), 100.into(), 100.into(), 200.into(), 100.into()],
+    });
+    doc.get_object_mut(page_id)
+        .and_then(Object::as_dict_mut)
+        .unwrap()
+        .set("Annots", vec![annotation_id.into()]);
+
+    doc.transform_page(page_id, &Matrix::translate(10.0, 20.0)).unwrap();
+    let annotation = doc.get_dictionary(annotation_id).unwrap();
+    assert_eq!(
+        annotation.get(b"Rect").and_then(Rectangle::from_object).unwrap(),
+        Rectangle::new(110.0, 120.0, 210.0, 140.0)
+    );
+    let quad_points = annotation.get(b"QuadPoints").and_then(Object::as_array).unwrap();
+    assert_eq!(quad_points[0].as_float().unwrap(), 110.0);
+    assert_eq!(quad_points[1].as_float().unwrap(), 140.0);
+
+    // The restore without a save restores a state with the matrix applied, and the page ends in its initial state.
+    let content = doc.get_and_decode_page_content(page_id).unwrap();
+    let operators: Vec<&str> = content
+        .operations
+        .iter()
+        .map(|operation| operation.operator.as_str())
+        .filter(|operator| ["q", "Q", "cm"].contains(operator))
+        .collect();
+    assert_eq!(operators, ["q", "cm", "q", "Q", "q", "cm", "q", "Q", "Q", "Q"]);
+    assert_eq!(graphics_state_balance(&doc.get_page_content(page_id).unwrap()), (0, 0));
+}
+
+#[test]
+fn count_unbalanced_graphics_states() {
+    assert_eq!(graphics_state_balance(b"q 1 0 0 1 5 5 cm Q"), (0, 0));
+    assert_eq!(graphics_state_balance(b"Q q\nq\rQ"), (1, 1));
+    // Operators in comments, strings, names and inline image data are ignored.
+    let content = b"q % Q Q\n(Q \\) (Q)) Tj <51> Tj /Q gs /q1 Do BI /W 1 /H 1 ID Q\nQ EI Q";
+    assert_eq!(graphics_state_balance(content), (0, 0));
+    assert_eq!(graphics_state_balance(b"q Qq BI ID q EIq EI q"), (0, 2));
+}
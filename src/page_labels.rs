@@ -0,0 +1,232 @@
+use crate::encodings::{decode_text_string, encode_text_string};
+use crate::{Dictionary, Document, Object, Result, StringFormat};
+use std::collections::BTreeMap;
+
+/// Numbering style of page labels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PageLabelStyle {
+    /// Arabic numerals: 1, 2, 3.
+    Decimal,
+    /// Uppercase Roman numerals: I, II, III.
+    UpperRoman,
+    /// Lowercase Roman numerals: i, ii, iii.
+    LowerRoman,
+    /// Uppercase letters: A to Z, then AA to ZZ.
+    UpperLetters,
+    /// Lowercase letters: a to z, then aa to zz.
+    LowerLetters,
+}
+
+/// Labeling of a range of pages, which starts at a page index and ends before the next range.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageLabel {
+    /// Numbering style, or none for labels consisting of the prefix only.
+    pub style: Option<PageLabelStyle>,
+    pub prefix: Option<String>,
+    /// Number of the first page of the range.
+    pub start: i64,
+}
+
+impl PageLabel {
+    pub fn new(style: Option<PageLabelStyle>) -> PageLabel {
+        PageLabel {
+            style,
+            prefix: None,
+            start: 1,
+        }
+    }
+
+    pub fn with_prefix(mut self, prefix: &str) -> PageLabel {
+        self.prefix = Some(prefix.to_owned());
+        self
+    }
+
+    pub fn with_start(mut self, start: i64) -> PageLabel {
+        self.start = start;
+        self
+    }
+
+    /// Get the label of a page at an offset from the start of the range.
+    pub fn label(&self, offset: u32) -> String {
+        let number = self.start + i64::from(offset);
+        let mut label = self.prefix.clone().unwrap_or_default();
+        match self.style {
+            Some(PageLabelStyle::Decimal) => label.push_str(&number.to_string()),
+            Some(PageLabelStyle::UpperRoman) => label.push_str(&roman(number)),
+            Some(PageLabelStyle::LowerRoman) => label.push_str(&roman(number).to_lowercase()),
+            Some(PageLabelStyle::UpperLetters) => label.push_str(&letters(number)),
+            Some(PageLabelStyle::LowerLetters) => label.push_str(&letters(number).to_lowercase()),
+            None => {}
+        }
+        label
+    }
+
+    fn from_dictionary(dict: &Dictionary) -> PageLabel {
+        let style = match dict.get(b"S").and_then(Object::as_name_str) {
+            Ok("D") => Some(PageLabelStyle::Decimal),
+            Ok("R") => Some(PageLabelStyle::UpperRoman),
+            Ok("r") => Some(PageLabelStyle::LowerRoman),
+            Ok("A") => Some(PageLabelStyle::UpperLetters),
+            Ok("a") => Some(PageLabelStyle::LowerLetters),
+            _ => None,
+        };
+        PageLabel {
+            style,
+            prefix: dict.get(b"P").and_then(Object::as_str).map(decode_text_string).ok(),
+            start: dict.get(b"St").and_then(Object::as_i64).unwrap_or(1),
+        }
+    }
+
+    fn to_dictionary(&self) -> Dictionary {
+        let mut dict = dictionary! { "Type" => "PageLabel" };
+        let style = match self.style {
+            Some(PageLabelStyle::Decimal) => Some("D"),
+            Some(PageLabelStyle::UpperRoman) => Some("R"),
+            Some(PageLabelStyle::LowerRoman) => Some("r"),
+            Some(PageLabelStyle::UpperLetters) => Some("A"),
+            Some(PageLabelStyle::LowerLetters) => Some("a"),
+            None => None,
+        };
+        if let Some(style) = style {
+            dict.set("S", style);
+        }
+        if let Some(ref prefix) = self.prefix {
+            dict.set("P", Object::String(encode_text_string(prefix), StringFormat::Literal));
+        }
+        if self.start != 1 {
+            dict.set("St", self.start);
+        }
+        dict
+    }
+}
+
+fn roman(mut number: i64) -> String {
+    const NUMERALS: [(i64, &str); 13] = [
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+    let mut text = String::new();
+    for &(value, numeral) in NUMERALS.iter() {
+        while number >= value {
+            text.push_str(numeral);
+            number -= value;
+        }
+    }
+    text
+}
+
+/// Letters repeat for numbers above 26, so 27 is AA and 53 is AAA.
+fn letters(number: i64) -> String {
+    if number < 1 {
+        return String::new();
+    }
+    let letter = (b'A' + ((number - 1) % 26) as u8) as char;
+    letter.to_string().repeat(((number - 1) / 26 + 1) as usize)
+}
+
+impl Document {
+    /// Get the page label ranges of the /PageLabels number tree of the catalog, by the index of their first page,
+    /// counting from zero.
+    pub fn get_page_labels(&self) -> Result<BTreeMap<u32, PageLabel>> {
+        let mut entries = Vec::new();
+        if let Ok(root) = self.catalog()?.get(b"PageLabels") {
+            self.collect_number_tree(root, &mut entries, &mut Vec::new());
+        }
+        Ok(entries
+            .into_iter()
+            .filter_map(|(index, label)| {
+                let label = self.dereference(&label).ok()?.1.as_dict().ok()?;
+                Some((index as u32, PageLabel::from_dictionary(label)))
+            })
+            .collect())
+    }
+
+    /// Replace the page labels with ranges starting at page indices, counting from zero. The first range should
+    /// start at index zero. No ranges remove the page labels.
+    pub fn set_page_labels(&mut self, labels: &BTreeMap<u32, PageLabel>) -> Result<()> {
+        let catalog_id = self.trailer.get(b"Root").and_then(Object::as_reference)?;
+        let mut nodes = Vec::new();
+        if let Ok(root) = self.catalog()?.get(b"PageLabels") {
+            self.collect_number_tree(root, &mut Vec::new(), &mut nodes);
+        }
+        for node in nodes {
+            self.objects.remove(&node);
+        }
+
+        let catalog = self.get_object_mut(catalog_id).and_then(Object::as_dict_mut)?;
+        if labels.is_empty() {
+            catalog.remove(b"PageLabels");
+            return Ok(());
+        }
+        let entries = labels
+            .iter()
+            .map(|(&index, label)| (i64::from(index), Object::Dictionary(label.to_dictionary())))
+            .collect();
+        let root_id = self.write_number_tree(entries);
+        self.get_object_mut(catalog_id)
+            .and_then(Object::as_dict_mut)?
+            .set("PageLabels", root_id);
+        Ok(())
+    }
+
+    /// Get the label of each page in page order, which is its page number for documents without page labels.
+    pub fn page_label_strings(&self) -> Result<Vec<String>> {
+        let labels = self.get_page_labels()?;
+        let page_count = self.get_pages().len() as u32;
+        Ok((0..page_count)
+            .map(|index| match labels.range(..=index).next_back() {
+                Some((&start, label)) => label.label(index - start),
+                None => (index + 1).to_string(),
+            })
+            .collect())
+    }
+}
+
+#[test]
+fn read_and_write_page_labels() {
+    use crate::test_support::{create_document, create_document_with_pages};
+
+    assert_eq!(roman(1994), "MCMXCIV");
+    assert_eq!(letters(28), "BB");
+
+    let mut doc = create_document();
+    let mut other = create_document_with_pages(6);
+    assert_eq!(other.page_label_strings().unwrap(), vec!["1", "2", "3", "4", "5", "6"]);
+
+    let mut labels = BTreeMap::new();
+    labels.insert(0, PageLabel::new(Some(PageLabelStyle::LowerRoman)));
+    labels.insert(
+        2,
+        PageLabel::new(Some(PageLabelStyle::Decimal))
+            .with_prefix("A-")
+            .with_start(3),
+    );
+    labels.insert(5, PageLabel::new(None).with_prefix("Back"));
+    other.set_page_labels(&labels).unwrap();
+    assert_eq!(other.get_page_labels().unwrap(), labels);
+    assert_eq!(
+        other.page_label_strings().unwrap(),
+        vec!["i", "ii", "A-3", "A-4", "A-5", "Back"]
+    );
+
+    // Merged documents keep the labels of both, with the pages of a document without labels numbered.
+    doc.merge(other).unwrap();
+    assert_eq!(
+        doc.page_label_strings().unwrap(),
+        vec!["1", "i", "ii", "A-3", "A-4", "A-5", "Back"]
+    );
+
+    doc.set_page_labels(&BTreeMap::new()).unwrap();
+    assert!(doc.catalog().unwrap().get(b"PageLabels").is_err());
+}
@@ -209,6 +209,19 @@ fn _indirect_object<'a>(expected_id: Option<ObjectId>, reader: &'a Reader) -> Pa
         - space()
 }
 
+/// Parse an indirect stream object up to its data, returning its dictionary and the position of the data.
+#[cfg(feature = "mmap")]
+pub fn stream_header(input: &[u8], offset: usize, expected_id: ObjectId) -> Result<(Dictionary, usize)> {
+    let header = object_id().convert(move |id| if id == expected_id { Ok(id) } else { Err(()) })
+        * (seq(b"obj") - space())
+        * (dictionary() - space() - seq(b"stream") - eol())
+        + empty().pos();
+    header
+        .parse_at(input, offset)
+        .map(|(out, _)| out)
+        .map_err(|_| Error::Parse { offset })
+}
+
 pub fn header(input: &[u8]) -> Option<String> {
     (seq(b"%PDF-") * none_of(b"\r\n").repeat(0..).convert(String::from_utf8) - eol() - comment().repeat(0..))
         .parse(input)
@@ -229,6 +242,8 @@ fn xref<'a>() -> Parser<'a, u8, Xref> {
                 for (index, ((offset, generation), is_normal)) in entries.into_iter().enumerate() {
                     if is_normal {
                         xref.insert((start + index) as u32, XrefEntry::Normal { offset, generation });
+                    } else {
+                        xref.insert((start + index) as u32, XrefEntry::Free);
                     }
                 }
                 xref
@@ -294,10 +309,37 @@ fn operation<'a>() -> Parser<'a, u8, Operation> {
     operation.map(|(operands, operator)| Operation { operator, operands })
 }
 
+fn inline_image<'a>() -> Parser<'a, u8, Operation> {
+    let entry = name() - content_space() + operand();
+    let parameters = seq(b"BI") * content_space() * entry.repeat(0..) - seq(b"ID") - one_of(b" \t\r\n\x0C\0");
+    (parameters.map(|entries| {
+        entries.into_iter().fold(Dictionary::new(), |mut dict, (key, value)| {
+            dict.set(key, value);
+            dict
+        })
+    }) >> |parameters: Dictionary| {
+        Parser::new(
+            move |input: &'a [u8], start: usize| match inline_image_end(&input[start..], &parameters) {
+                Some((length, end)) => Ok((
+                    Operation::inline_image(parameters.clone(), input[start..start + length].to_vec()),
+                    start + end,
+                )),
+                None => Err(pom::Error::Mismatch {
+                    message: "inline image without EI".to_string(),
+                    position: start,
+                }),
+            },
+        )
+    }) - content_space()
+}
+
 pub fn content(input: &[u8]) -> Option<Content<Vec<Operation>>> {
-    (content_space() * operation().repeat(0..).map(|operations| Content { operations }))
-        .parse(input)
-        .ok()
+    (content_space()
+        * (inline_image() | operation())
+            .repeat(0..)
+            .map(|operations| Content { operations }))
+    .parse(input)
+    .ok()
 }
 
 #[cfg(test)]
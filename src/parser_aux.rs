@@ -3,17 +3,13 @@
 use crate::{
     content::{Content, Operation},
     document::Document,
-    error::XrefError,
+    error::{ErrorContext, XrefError},
     object::Object::Name,
     xref::{Xref, XrefEntry},
     Error, Result,
 };
 use crate::{parser, Dictionary, Object, ObjectId, Stream};
-use log::info;
-use std::{
-    collections::BTreeMap,
-    io::{Cursor, Read},
-};
+use std::io::{Cursor, Read};
 
 impl Content<Vec<Operation>> {
     /// Decode content operations.
@@ -34,128 +30,7 @@ impl Document {
     pub fn get_and_decode_page_content(&self, page_id: ObjectId) -> Result<Content<Vec<Operation>>> {
         let content_data = self.get_page_content(page_id)?;
         Content::decode(&content_data)
-    }
-
-    pub fn extract_text(&self, page_numbers: &[u32]) -> Result<String> {
-        fn collect_text(text: &mut String, encoding: Option<&str>, operands: &[Object]) {
-            for operand in operands.iter() {
-                match *operand {
-                    Object::String(ref bytes, _) => {
-                        let decoded_text = Document::decode_text(encoding, bytes);
-                        text.push_str(&decoded_text);
-                    }
-                    Object::Array(ref arr) => {
-                        collect_text(text, encoding, arr);
-                    }
-                    _ => {}
-                }
-            }
-        }
-        let mut text = String::new();
-        let pages = self.get_pages();
-        for page_number in page_numbers {
-            let page_id = *pages.get(page_number).ok_or(Error::PageNumberNotFound(*page_number))?;
-            let fonts = self.get_page_fonts(page_id);
-            let encodings = fonts
-                .into_iter()
-                .map(|(name, font)| (name, font.get_font_encoding()))
-                .collect::<BTreeMap<Vec<u8>, &str>>();
-            let content_data = self.get_page_content(page_id)?;
-            let content = Content::decode(&content_data)?;
-            let mut current_encoding = None;
-            for operation in &content.operations {
-                match operation.operator.as_ref() {
-                    "Tf" => {
-                        let current_font = operation
-                            .operands
-                            .get(0)
-                            .ok_or(Error::Syntax("missing font operand".to_string()))?
-                            .as_name()?;
-                        current_encoding = encodings.get(current_font).cloned();
-                    }
-                    "Tj" | "TJ" => {
-                        collect_text(&mut text, current_encoding, &operation.operands);
-                    }
-                    "ET" => {
-                        if !text.ends_with('\n') {
-                            text.push('\n')
-                        }
-                    }
-                    _ => {}
-                }
-            }
-        }
-        Ok(text)
-    }
-
-    pub fn replace_text(&mut self, page_number: u32, text: &str, other_text: &str) -> Result<()> {
-        let page_id = self
-            .page_iter()
-            .nth(page_number as usize - 1)
-            .ok_or(Error::PageNumberNotFound(page_number))?;
-        let encodings = self
-            .get_page_fonts(page_id)
-            .into_iter()
-            .map(|(name, font)| (name, font.get_font_encoding().to_owned()))
-            .collect::<BTreeMap<Vec<u8>, String>>();
-        let content_data = self.get_page_content(page_id)?;
-        let mut content = Content::decode(&content_data)?;
-        let mut current_encoding = None;
-        for operation in &mut content.operations {
-            match operation.operator.as_ref() {
-                "Tf" => {
-                    let current_font = operation
-                        .operands
-                        .get(0)
-                        .ok_or(Error::Syntax("missing font operand".to_string()))?
-                        .as_name()?;
-                    current_encoding = encodings.get(current_font).map(std::string::String::as_str);
-                }
-                "Tj" => {
-                    for bytes in operation.operands.iter_mut().flat_map(Object::as_str_mut) {
-                        let decoded_text = Document::decode_text(current_encoding, bytes);
-                        info!("{}", decoded_text);
-                        if decoded_text == text {
-                            let encoded_bytes = Document::encode_text(current_encoding, other_text);
-                            *bytes = encoded_bytes;
-                        }
-                    }
-                }
-                _ => {}
-            }
-        }
-        let modified_content = content.encode()?;
-        self.change_page_content(page_id, modified_content)
-    }
-
-    pub fn insert_image(
-        &mut self, page_id: ObjectId, img_object: Stream, position: (f64, f64), size: (f64, f64),
-    ) -> Result<()> {
-        let img_id = self.add_object(img_object);
-        let img_name = format!("X{}", img_id.0);
-
-        self.add_xobject(page_id, img_name.as_bytes(), img_id)?;
-
-        let mut content = self.get_and_decode_page_content(page_id)?;
-        content.operations.push(Operation::new("q", vec![]));
-        content.operations.push(Operation::new(
-            "cm",
-            vec![
-                size.0.into(),
-                0.into(),
-                0.into(),
-                size.1.into(),
-                position.0.into(),
-                position.1.into(),
-            ],
-        ));
-        content
-            .operations
-            .push(Operation::new("Do", vec![Name(img_name.as_bytes().to_vec())]));
-        content.operations.push(Operation::new("Q", vec![]));
-        content.operations.push(Operation::new("Q", vec![]));
-
-        self.change_page_content(page_id, content.encode()?)
+            .map_err(|e| e.with_context(ErrorContext::in_object(page_id).with_key(b"Contents")))
     }
 
     pub fn insert_form_object(&mut self, page_id: ObjectId, form_obj: Stream) -> Result<()> {
@@ -219,6 +94,7 @@ pub fn decode_xref_stream(mut stream: Stream) -> Result<(Xref, Dictionary)> {
                         //free object
                         read_big_endian_integer(&mut reader, bytes2.as_mut_slice())?;
                         read_big_endian_integer(&mut reader, bytes3.as_mut_slice())?;
+                        xref.insert((start + j) as u32, XrefEntry::Free);
                     }
                     1 => {
                         //normal object
@@ -0,0 +1,118 @@
+#![cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+
+use crate::interpreter::{ContentHandler, ContentInterpreter, GraphicsState, PaintedPath, PathSegment};
+use crate::{Document, Error, Point, Rectangle, Result};
+
+/// Path painted on a page, with the graphics state parameters which affect its appearance.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VectorPath {
+    /// Segments in device space, and whether they are filled and stroked.
+    pub path: PaintedPath,
+    pub fill_color: Vec<f64>,
+    pub stroke_color: Vec<f64>,
+    /// Line width in device space, scaled by the current transformation matrix.
+    pub line_width: f64,
+    /// Bounding box of the clipping path in device space, if any.
+    pub clip: Option<Rectangle>,
+}
+
+impl VectorPath {
+    /// Get the straight lines of the path, including those which close subpaths, which is what table rulings
+    /// are usually drawn with.
+    pub fn lines(&self) -> Vec<(Point, Point)> {
+        let mut lines = Vec::new();
+        let mut start = None;
+        let mut current = None;
+        for segment in &self.path.segments {
+            match *segment {
+                PathSegment::MoveTo(point) => {
+                    start = Some(point);
+                    current = Some(point);
+                }
+                PathSegment::LineTo(point) => {
+                    if let Some(from) = current {
+                        lines.push((from, point));
+                    }
+                    current = Some(point);
+                }
+                PathSegment::CurveTo(_, _, point) => current = Some(point),
+                PathSegment::ClosePath => {
+                    if let (Some(from), Some(to)) = (current, start) {
+                        if from != to {
+                            lines.push((from, to));
+                        }
+                    }
+                    current = start;
+                }
+            }
+        }
+        lines
+    }
+}
+
+struct PathCollector(Vec<VectorPath>);
+
+impl ContentHandler for PathCollector {
+    fn path_painted(&mut self, path: &PaintedPath, state: &GraphicsState) {
+        let ctm = state.ctm;
+        self.0.push(VectorPath {
+            path: path.clone(),
+            fill_color: state.fill_color.clone(),
+            stroke_color: state.stroke_color.clone(),
+            line_width: state.line_width * (ctm.a * ctm.d - ctm.b * ctm.c).abs().sqrt(),
+            clip: state.clip,
+        });
+    }
+}
+
+impl Document {
+    /// Extract the paths which are filled or stroked on a page, including those of form XObjects,
+    /// in painting order.
+    pub fn extract_paths(&self, page_number: u32) -> Result<Vec<VectorPath>> {
+        let page_id = *self
+            .get_pages()
+            .get(&page_number)
+            .ok_or(Error::PageNumberNotFound(page_number))?;
+        let mut interpreter = ContentInterpreter::new(self, PathCollector(Vec::new()));
+        interpreter.run_page(page_id)?;
+        Ok(interpreter.into_handler().0)
+    }
+}
+
+#[test]
+fn extract_vector_paths() {
+    use crate::content::ContentBuilder;
+    use crate::test_support::create_document;
+    use crate::Matrix;
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    let content = ContentBuilder::new()
+        .transform(Matrix::scale(2.0, 2.0))
+        .set_line_width(0.5)
+        .set_stroke_color(&[0.0, 0.0, 1.0])
+        .move_to(10.0, 10.0)
+        .line_to(60.0, 10.0)
+        .stroke()
+        .set_fill_color(&[0.9])
+        .rectangle(10.0, 20.0, 50.0, 30.0)
+        .fill_and_stroke()
+        .rectangle(0.0, 0.0, 5.0, 5.0)
+        .end_path()
+        .encode()
+        .unwrap();
+    doc.append_page_content(page_id, content).unwrap();
+
+    let paths = doc.extract_paths(1).unwrap();
+    assert_eq!(paths.len(), 2);
+    assert_eq!(paths[0].lines(), [(Point::new(20.0, 20.0), Point::new(120.0, 20.0))]);
+    assert_eq!(paths[0].stroke_color, [0.0, 0.0, 1.0]);
+    assert!((paths[0].line_width - 1.0).abs() < 1e-9);
+    assert!(paths[0].path.fill.is_none());
+
+    assert!(paths[1].path.stroke && paths[1].path.fill.is_some());
+    assert_eq!(paths[1].fill_color, [0.9]);
+    assert_eq!(paths[1].lines().len(), 4);
+    assert_eq!(paths[1].path.bbox(), Some(Rectangle::new(20.0, 40.0, 120.0, 100.0)));
+    assert!(doc.extract_paths(2).is_err());
+}
@@ -0,0 +1,576 @@
+use crate::encodings::decode_text_string;
+use crate::{Dictionary, Document, Error, Object, ObjectId, Result, Stream, StringFormat};
+use std::collections::hash_map::RandomState;
+use std::collections::BTreeSet;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Part and conformance level of ISO 19005 (PDF/A) with basic conformance, which requires the visual
+/// appearance of the document to be preserved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PdfALevel {
+    /// PDF/A-1b, based on PDF 1.4, which doesn't allow transparency, optional content or embedded files.
+    A1b,
+    /// PDF/A-2b, based on PDF 1.7, which doesn't allow embedded files other than PDF/A documents.
+    A2b,
+    /// PDF/A-3b, which allows any embedded files.
+    A3b,
+}
+
+impl PdfALevel {
+    pub fn part(self) -> u32 {
+        match self {
+            PdfALevel::A1b => 1,
+            PdfALevel::A2b => 2,
+            PdfALevel::A3b => 3,
+        }
+    }
+}
+
+/// Action types which PDF/A doesn't allow, as they run code or depend on the viewer.
+const FORBIDDEN_ACTIONS: [&str; 10] = [
+    "JavaScript",
+    "Launch",
+    "Sound",
+    "Movie",
+    "ResetForm",
+    "ImportData",
+    "Hide",
+    "SetOCGState",
+    "Rendition",
+    "Trans",
+];
+
+/// Name of the output condition of the embedded ICC profile.
+const OUTPUT_CONDITION: &str = "sRGB IEC61966-2.1";
+
+/// Annotation flags Invisible, Hidden and NoView, which PDF/A doesn't allow, and Print, which it requires.
+pub(crate) const HIDING_FLAGS: i64 = 1 | 2 | 32;
+pub(crate) const PRINT_FLAG: i64 = 4;
+
+impl Document {
+    /// Convert the document to conform to a PDF/A level.
+    ///
+    /// An sRGB output intent is added unless the document has a PDF/A output intent, JavaScript and other
+    /// forbidden actions are removed, annotations are made printable, and the document information is copied to
+    /// the XMP metadata, which identifies the PDF/A level. For PDF/A-1b, transparency and optional content are
+    /// removed, and the document must be saved without cross-reference and object streams.
+    ///
+    /// Fails without changing the document if it's encrypted or uses fonts which aren't embedded, as fonts can't
+    /// be embedded without their files.
+    pub fn convert_to_pdfa(&mut self, level: PdfALevel) -> Result<()> {
+        if self.is_encrypted() {
+            return Err(Error::Syntax("Encrypted documents must be decrypted first".to_string()));
+        }
+        let referenced: BTreeSet<ObjectId> = self.traverse_objects(|_| {}).into_iter().collect();
+        if let Some(font) = self.unembedded_fonts(&referenced).into_iter().next() {
+            return Err(Error::Syntax(format!("Font {} is not embedded", font)));
+        }
+
+        let forbidden_actions: BTreeSet<ObjectId> = referenced
+            .iter()
+            .filter(|&&id| self.get_dictionary(id).is_ok_and(is_forbidden_action))
+            .copied()
+            .collect();
+        let remove_transparency = level == PdfALevel::A1b;
+        self.traverse_objects(|object| {
+            let dict = match object {
+                Object::Dictionary(dict) => dict,
+                Object::Stream(stream) => &mut stream.dict,
+                _ => return,
+            };
+            remove_forbidden_features(dict, &forbidden_actions);
+            if remove_transparency {
+                remove_transparency_entries(dict);
+            }
+        });
+
+        let catalog_id = self.trailer.get(b"Root").and_then(Object::as_reference)?;
+        let mut names_keys = vec![b"JavaScript".as_ref()];
+        if level != PdfALevel::A3b {
+            names_keys.push(b"EmbeddedFiles");
+        }
+        let names_id = self.catalog()?.get(b"Names").and_then(Object::as_reference).ok();
+        let catalog = self.get_object_mut(catalog_id).and_then(Object::as_dict_mut)?;
+        if level == PdfALevel::A1b {
+            catalog.remove(b"OCProperties");
+        }
+        let names = match names_id {
+            Some(id) => self.get_object_mut(id).and_then(Object::as_dict_mut).ok(),
+            None => catalog.get_mut(b"Names").and_then(Object::as_dict_mut).ok(),
+        };
+        if let Some(names) = names {
+            for key in names_keys {
+                names.remove(key);
+            }
+        }
+
+        self.add_pdfa_output_intent(catalog_id)?;
+        if !self.trailer.has(b"ID") {
+            let id = Object::String(file_id(), StringFormat::Hexadecimal);
+            self.trailer.set("ID", vec![id.clone(), id]);
+        }
+        match level {
+            PdfALevel::A1b => self.version = "1.4".to_string(),
+            _ if self.version.as_str() > "1.7" => self.version = "1.7".to_string(),
+            _ => {}
+        }
+        self.set_pdfa_xmp(level)
+    }
+
+    /// Get the names of fonts among the objects which aren't embedded.
+    fn unembedded_fonts(&self, ids: &BTreeSet<ObjectId>) -> Vec<String> {
+        ids.iter()
+            .filter_map(|&id| self.get_dictionary(id).ok())
+            .filter(|font| font.type_is(b"Font") && !self.is_font_embedded(font))
+            .map(|font| {
+                font.get(b"BaseFont")
+                    .and_then(Object::as_name_str)
+                    .unwrap_or("")
+                    .to_owned()
+            })
+            .collect()
+    }
+
+    /// Check whether a font has a font file, or the descendant font of a composite font. Type 3 fonts are
+    /// considered embedded.
+    pub(crate) fn is_font_embedded(&self, font: &Dictionary) -> bool {
+        fn dictionary<'a>(doc: &'a Document, object: &'a Object) -> Option<&'a Dictionary> {
+            doc.dereference(object).and_then(|(_, object)| object.as_dict()).ok()
+        }
+        let font = match font.get(b"Subtype").and_then(Object::as_name_str) {
+            Ok("Type3") => return true,
+            Ok("Type0") => {
+                let descendant = font
+                    .get(b"DescendantFonts")
+                    .and_then(|fonts| self.dereference(fonts))
+                    .and_then(|(_, fonts)| fonts.as_array())
+                    .ok()
+                    .and_then(|fonts| dictionary(self, fonts.first()?));
+                match descendant {
+                    Some(descendant) => descendant,
+                    None => return false,
+                }
+            }
+            _ => font,
+        };
+        let descriptor = font
+            .get(b"FontDescriptor")
+            .ok()
+            .and_then(|descriptor| dictionary(self, descriptor));
+        descriptor.is_some_and(|descriptor| {
+            [b"FontFile".as_ref(), b"FontFile2", b"FontFile3"]
+                .iter()
+                .any(|key| descriptor.has(key))
+        })
+    }
+
+    /// Get the first output intent of the catalog with the /GTS_PDFA1 subtype.
+    pub(crate) fn pdfa_output_intent(&self) -> Option<&Dictionary> {
+        let intents = self.catalog().ok()?.get(b"OutputIntents").ok()?;
+        self.dereference(intents)
+            .ok()?
+            .1
+            .as_array()
+            .ok()?
+            .iter()
+            .filter_map(|intent| self.dereference(intent).ok()?.1.as_dict().ok())
+            .find(|intent| intent.get(b"S").and_then(Object::as_name_str).ok() == Some("GTS_PDFA1"))
+    }
+
+    /// Add an output intent with an sRGB profile, unless the catalog has a PDF/A output intent.
+    fn add_pdfa_output_intent(&mut self, catalog_id: ObjectId) -> Result<()> {
+        if self.pdfa_output_intent().is_some() {
+            return Ok(());
+        }
+        let mut intents = match self.catalog()?.get(b"OutputIntents") {
+            Ok(intents) => self.dereference(intents)?.1.as_array()?.clone(),
+            Err(_) => Vec::new(),
+        };
+
+        let profile_id = self.add_object(Stream::new(dictionary! { "N" => 3 }, srgb_profile()));
+        let intent_id = self.add_object(dictionary! {
+            "Type" => "OutputIntent",
+            "S" => "GTS_PDFA1",
+            "OutputConditionIdentifier" => Object::string_literal(OUTPUT_CONDITION),
+            "Info" => Object::string_literal(OUTPUT_CONDITION),
+            "DestOutputProfile" => profile_id,
+        });
+        intents.push(intent_id.into());
+        self.get_object_mut(catalog_id)
+            .and_then(Object::as_dict_mut)?
+            .set("OutputIntents", intents);
+        Ok(())
+    }
+
+    /// Write XMP metadata identifying the PDF/A level, with the entries of the document information dictionary.
+    /// Dates which can't be parsed are removed from the dictionary, as they must be equal to the metadata.
+    fn set_pdfa_xmp(&mut self, level: PdfALevel) -> Result<()> {
+        let mut xmp = self.get_xmp()?.unwrap_or_default();
+        xmp.pdfa_part = Some(level.part());
+        xmp.pdfa_conformance = Some("B".to_string());
+        xmp.format = Some("application/pdf".to_string());
+
+        let info_id = self.trailer.get(b"Info").and_then(Object::as_reference).ok();
+        let info = match info_id {
+            Some(id) => self.get_object_mut(id).and_then(Object::as_dict_mut).ok(),
+            None => self.trailer.get_mut(b"Info").and_then(Object::as_dict_mut).ok(),
+        };
+        if let Some(info) = info {
+            let text = |key: &[u8]| info.get(key).and_then(Object::as_str).map(decode_text_string).ok();
+            if let Some(title) = text(b"Title") {
+                xmp.title = Some(title);
+            }
+            if let Some(author) = text(b"Author") {
+                xmp.creators = vec![author];
+            }
+            if let Some(subject) = text(b"Subject") {
+                xmp.description = Some(subject);
+            }
+            if let Some(keywords) = text(b"Keywords") {
+                xmp.keywords = Some(keywords);
+            }
+            if let Some(creator) = text(b"Creator") {
+                xmp.creator_tool = Some(creator);
+            }
+            if let Some(producer) = text(b"Producer") {
+                xmp.producer = Some(producer);
+            }
+            for (key, date) in [
+                (b"CreationDate".as_ref(), &mut xmp.create_date),
+                (b"ModDate", &mut xmp.modify_date),
+            ] {
+                if let Ok(value) = info.get(key).and_then(Object::as_str) {
+                    match iso_date(value) {
+                        Some(iso) => *date = Some(iso),
+                        None => {
+                            info.remove(key);
+                        }
+                    }
+                }
+            }
+        }
+        self.set_xmp(&xmp)?;
+        Ok(())
+    }
+}
+
+pub(crate) fn is_forbidden_action(dict: &Dictionary) -> bool {
+    dict.get(b"S")
+        .and_then(Object::as_name_str)
+        .is_ok_and(|action| FORBIDDEN_ACTIONS.contains(&action))
+}
+
+/// Generate a file identifier from the current time, hashed with randomly keyed hashers. It needs to be unique,
+/// not unpredictable.
+fn file_id() -> Vec<u8> {
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_nanos());
+    (0..2)
+        .flat_map(|_| {
+            let mut hasher = RandomState::new().build_hasher();
+            hasher.write_u128(time);
+            hasher.finish().to_be_bytes()
+        })
+        .collect()
+}
+
+/// Remove additional actions, forbidden actions, and interpolation of images, and make annotations printable.
+fn remove_forbidden_features(dict: &mut Dictionary, forbidden_actions: &BTreeSet<ObjectId>) {
+    let is_forbidden = |action: &Object| match action {
+        Object::Reference(id) => forbidden_actions.contains(id),
+        Object::Dictionary(action) => is_forbidden_action(action),
+        _ => false,
+    };
+    dict.remove(b"AA");
+    for key in [b"A".as_ref(), b"OpenAction"] {
+        if dict.get(key).is_ok_and(is_forbidden) {
+            dict.remove(key);
+        }
+    }
+    match dict.get_mut(b"Next") {
+        Ok(Object::Array(actions)) => actions.retain(|action| !is_forbidden(action)),
+        Ok(action) if is_forbidden(action) => {
+            dict.remove(b"Next");
+        }
+        _ => {}
+    }
+    if let Ok(Object::Boolean(true)) = dict.get(b"Interpolate") {
+        dict.set("Interpolate", false);
+    }
+
+    let is_annotation = dict.has(b"Rect") && dict.has(b"Subtype") && !dict.type_is(b"Page");
+    if is_annotation && dict.get(b"Subtype").and_then(Object::as_name_str).ok() != Some("Popup") {
+        let flags = dict.get(b"F").and_then(Object::as_i64).unwrap_or(0);
+        dict.set("F", (flags & !HIDING_FLAGS) | PRINT_FLAG);
+    }
+}
+
+/// Remove soft masks, constant alpha, blend modes and transparency groups, which PDF/A-1 doesn't allow.
+fn remove_transparency_entries(dict: &mut Dictionary) {
+    if dict.has(b"SMask") {
+        if dict.get(b"Subtype").and_then(Object::as_name_str).ok() == Some("Image") {
+            dict.remove(b"SMask");
+        } else {
+            dict.set("SMask", "None");
+        }
+    }
+    dict.remove(b"SMaskInData");
+    for key in [b"CA".as_ref(), b"ca"] {
+        if dict.get(key).and_then(Object::as_float).is_ok_and(|alpha| alpha != 1.0) {
+            dict.set(key.to_vec(), 1.0);
+        }
+    }
+    let blend_mode = dict.get(b"BM").and_then(Object::as_name_str).ok();
+    if blend_mode.is_some_and(|mode| mode != "Normal" && mode != "Compatible") {
+        dict.set("BM", "Normal");
+    }
+    if dict.type_is(b"Page") || dict.get(b"Subtype").and_then(Object::as_name_str).ok() == Some("Form") {
+        dict.remove(b"Group");
+    }
+}
+
+/// Convert a date of the form `D:YYYYMMDDHHmmSSOHH'mm'`, of which all parts after the year are optional, to
+/// ISO 8601.
+fn iso_date(date: &[u8]) -> Option<String> {
+    let date = std::str::from_utf8(date).ok()?;
+    let date = date.strip_prefix("D:").unwrap_or(date);
+    let digits = date.bytes().take_while(u8::is_ascii_digit).count();
+    if digits < 4 || digits % 2 != 0 || digits > 14 {
+        return None;
+    }
+    let part = |index: usize, default: &'static str| {
+        if index < digits {
+            &date[index..index + 2]
+        } else {
+            default
+        }
+    };
+    let mut iso = format!(
+        "{}-{}-{}T{}:{}:{}",
+        &date[..4],
+        part(4, "01"),
+        part(6, "01"),
+        part(8, "00"),
+        part(10, "00"),
+        part(12, "00")
+    );
+    let offset = &date[digits..];
+    match offset.as_bytes().first() {
+        None | Some(b'Z') => iso.push('Z'),
+        Some(b'+') | Some(b'-') => {
+            let numbers: String = offset[1..].chars().filter(char::is_ascii_digit).collect();
+            if numbers.len() != 2 && numbers.len() != 4 {
+                return None;
+            }
+            let minutes = numbers.get(2..4).unwrap_or("00");
+            iso.push_str(&format!("{}{}:{}", &offset[..1], &numbers[..2], minutes));
+        }
+        _ => return None,
+    }
+    Some(iso)
+}
+
+/// Create an ICC version 2 display profile of the sRGB color space, with its primaries adapted to the D50
+/// illuminant of the profile connection space and its tone curve sampled at 1024 points.
+fn srgb_profile() -> Vec<u8> {
+    fn s15_fixed16(value: f64) -> [u8; 4] {
+        ((value * 65536.0).round() as i32).to_be_bytes()
+    }
+    fn xyz(x: f64, y: f64, z: f64) -> Vec<u8> {
+        let mut data = b"XYZ \0\0\0\0".to_vec();
+        for value in [x, y, z] {
+            data.extend_from_slice(&s15_fixed16(value));
+        }
+        data
+    }
+    let description = {
+        let mut data = b"desc\0\0\0\0".to_vec();
+        data.extend_from_slice(&(OUTPUT_CONDITION.len() as u32 + 1).to_be_bytes());
+        data.extend_from_slice(OUTPUT_CONDITION.as_bytes());
+        // Terminating zero, empty Unicode and ScriptCode descriptions.
+        data.extend_from_slice(&[0; 1 + 8 + 3 + 67]);
+        data
+    };
+    let copyright = {
+        let mut data = b"text\0\0\0\0".to_vec();
+        data.extend_from_slice(b"No copyright, use freely\0");
+        data
+    };
+    let curve = {
+        let mut data = b"curv\0\0\0\0".to_vec();
+        data.extend_from_slice(&1024u32.to_be_bytes());
+        for index in 0..1024 {
+            let value = f64::from(index) / 1023.0;
+            let linear = if value <= 0.04045 {
+                value / 12.92
+            } else {
+                ((value + 0.055) / 1.055).powf(2.4)
+            };
+            data.extend_from_slice(&((linear * 65535.0).round() as u16).to_be_bytes());
+        }
+        data
+    };
+    let tags: [(&[u8; 4], Vec<u8>); 9] = [
+        (b"desc", description),
+        (b"cprt", copyright),
+        (b"wtpt", xyz(0.9642, 1.0, 0.8249)),
+        (b"rXYZ", xyz(0.4361, 0.2225, 0.0139)),
+        (b"gXYZ", xyz(0.3851, 0.7169, 0.0971)),
+        (b"bXYZ", xyz(0.1431, 0.0606, 0.7141)),
+        (b"rTRC", curve.clone()),
+        (b"gTRC", curve.clone()),
+        (b"bTRC", curve),
+    ];
+
+    let mut table = (tags.len() as u32).to_be_bytes().to_vec();
+    let mut data = Vec::new();
+    let data_start = 128 + 4 + 12 * tags.len();
+    for (signature, tag) in &tags {
+        table.extend_from_slice(*signature);
+        table.extend_from_slice(&((data_start + data.len()) as u32).to_be_bytes());
+        table.extend_from_slice(&(tag.len() as u32).to_be_bytes());
+        data.extend_from_slice(tag);
+        data.resize(data.len().div_ceil(4) * 4, 0);
+    }
+
+    let mut profile = vec![0; 128];
+    profile[8] = 2;
+    profile[9] = 0x10;
+    profile[12..16].copy_from_slice(b"mntr");
+    profile[16..20].copy_from_slice(b"RGB ");
+    profile[20..24].copy_from_slice(b"XYZ ");
+    // Creation date of 2000-01-01.
+    profile[24..26].copy_from_slice(&2000u16.to_be_bytes());
+    profile[27] = 1;
+    profile[29] = 1;
+    profile[36..40].copy_from_slice(b"acsp");
+    profile[68..80].copy_from_slice(&xyz(0.9642, 1.0, 0.8249)[8..]);
+    profile.extend(table);
+    profile.extend(data);
+    let size = (profile.len() as u32).to_be_bytes();
+    profile[..4].copy_from_slice(&size);
+    profile
+}
+
+#[cfg(feature = "fonts")]
+#[test]
+fn convert_document_to_pdfa() {
+    use crate::fonts::EmbeddedFont;
+    use crate::test_support::create_document;
+
+    assert_eq!(
+        iso_date(b"D:20240131235900+05'30'").unwrap(),
+        "2024-01-31T23:59:00+05:30"
+    );
+    assert_eq!(iso_date(b"D:2024").unwrap(), "2024-01-01T00:00:00Z");
+    assert_eq!(iso_date(b"yesterday"), None);
+
+    let mut doc = create_document();
+    match doc.convert_to_pdfa(PdfALevel::A1b) {
+        Err(Error::Syntax(message)) => assert_eq!(message, "Font Courier is not embedded"),
+        result => panic!("unexpected result {:?}", result),
+    }
+    assert!(doc.catalog().unwrap().get(b"OutputIntents").is_err());
+
+    // Replace Courier by an embedded font, and add JavaScript and transparency.
+    let mut font = EmbeddedFont::new(&mut doc, std::fs::read("assets/DejaVuSans-ASCII.ttf").unwrap()).unwrap();
+    font.encode("Hello World!");
+    let font_id = font.embed(&mut doc).unwrap();
+    let page_id = doc.page_iter().next().unwrap();
+    let pages_id = doc
+        .catalog()
+        .unwrap()
+        .get(b"Pages")
+        .and_then(Object::as_reference)
+        .unwrap();
+    let resources_id = doc
+        .get_dictionary(pages_id)
+        .and_then(|pages| pages.get(b"Resources"))
+        .and_then(Object::as_reference)
+        .unwrap();
+    let resources = doc.get_object_mut(resources_id).and_then(Object::as_dict_mut).unwrap();
+    resources.set("Font", dictionary! { "F1" => font_id });
+    resources.set(
+        "ExtGState",
+        dictionary! { "GS1" => dictionary! { "ca" => 0.5, "BM" => "Multiply" } },
+    );
+    let script_id = doc.add_object(dictionary! {
+        "S" => "JavaScript",
+        "JS" => Object::string_literal("app.alert('Hello');"),
+    });
+    let catalog_id = doc.trailer.get(b"Root").and_then(Object::as_reference).unwrap();
+    let catalog = doc.get_object_mut(catalog_id).and_then(Object::as_dict_mut).unwrap();
+    catalog.set("OpenAction", script_id);
+    catalog.set(
+        "Names",
+        dictionary! { "JavaScript" => dictionary! { "Names" => Vec::<Object>::new() } },
+    );
+    let page = doc.get_object_mut(page_id).and_then(Object::as_dict_mut).unwrap();
+    page.set("AA", dictionary! { "O" => script_id });
+    page.set("Group", dictionary! { "S" => "Transparency" });
+
+    doc.convert_to_pdfa(PdfALevel::A1b).unwrap();
+    assert_eq!(doc.version, "1.4");
+    let id = doc.trailer.get(b"ID").and_then(Object::as_array).unwrap();
+    assert_eq!(id[0].as_str().unwrap().len(), 16);
+    assert_ne!(file_id(), file_id());
+    let catalog = doc.catalog().unwrap();
+    assert!(!catalog.has(b"OpenAction"));
+    assert!(!catalog
+        .get(b"Names")
+        .and_then(Object::as_dict)
+        .unwrap()
+        .has(b"JavaScript"));
+    let page = doc.get_dictionary(page_id).unwrap();
+    assert!(!page.has(b"AA"));
+    assert!(!page.has(b"Group"));
+    let state = doc
+        .get_dictionary(resources_id)
+        .and_then(|resources| resources.get(b"ExtGState"))
+        .and_then(Object::as_dict)
+        .and_then(|states| states.get(b"GS1"))
+        .and_then(Object::as_dict)
+        .unwrap();
+    assert_eq!(state.get(b"ca").and_then(Object::as_float).unwrap(), 1.0);
+    assert_eq!(state.get(b"BM").and_then(Object::as_name_str).unwrap(), "Normal");
+
+    let intents = catalog.get(b"OutputIntents").and_then(Object::as_array).unwrap();
+    assert_eq!(intents.len(), 1);
+    let intent = doc.get_dictionary(intents[0].as_reference().unwrap()).unwrap();
+    let profile = doc
+        .get_object(intent.get(b"DestOutputProfile").and_then(Object::as_reference).unwrap())
+        .and_then(Object::as_stream)
+        .unwrap();
+    assert_eq!(&profile.content[36..40], b"acsp");
+    assert_eq!(
+        u32::from_be_bytes([
+            profile.content[0],
+            profile.content[1],
+            profile.content[2],
+            profile.content[3]
+        ]) as usize,
+        profile.content.len()
+    );
+
+    let xmp = doc.get_xmp().unwrap().unwrap();
+    assert_eq!(xmp.pdfa_part, Some(1));
+    assert_eq!(xmp.pdfa_conformance.as_deref(), Some("B"));
+    assert_eq!(xmp.title.as_deref(), Some("Create PDF document example"));
+    assert_eq!(xmp.creator_tool.as_deref(), Some("https://crates.io/crates/lopdf"));
+    assert!(xmp.create_date.is_some());
+
+    // Converting again keeps the output intent.
+    doc.convert_to_pdfa(PdfALevel::A2b).unwrap();
+    assert_eq!(
+        doc.catalog()
+            .unwrap()
+            .get(b"OutputIntents")
+            .and_then(Object::as_array)
+            .unwrap()
+            .len(),
+        1
+    );
+    assert_eq!(doc.get_xmp().unwrap().unwrap().pdfa_part, Some(2));
+}
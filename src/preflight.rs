@@ -0,0 +1,346 @@
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+use crate::content::Content;
+use crate::pdfa::{is_forbidden_action, PdfALevel, HIDING_FLAGS, PRINT_FLAG};
+use crate::{Dictionary, Document, Object, ObjectId};
+use std::fmt;
+
+/// Requirement of PDF/A which a document doesn't meet.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rule {
+    /// The document is encrypted.
+    Encrypted,
+    /// The trailer has no file identifier.
+    MissingFileIdentifier,
+    /// The catalog has no XMP metadata identifying the PDF/A part and conformance level.
+    MissingIdentification,
+    /// A font, given by its base font name, isn't embedded.
+    FontNotEmbedded(String),
+    /// A device color space is used without a PDF/A output intent with the same number of components.
+    DeviceColor(String),
+    /// An action type which runs code or depends on the viewer is used.
+    ForbiddenAction(String),
+    /// Additional actions, /AA, are triggered by events.
+    AdditionalActions,
+    /// The /Names dictionary has JavaScript.
+    JavaScript,
+    /// A soft mask, constant alpha, blend mode or transparency group is used, which PDF/A-1 doesn't allow.
+    Transparency,
+    /// Optional content, which PDF/A-1 doesn't allow.
+    OptionalContent,
+    /// An embedded file, which PDF/A-1 doesn't allow, and PDF/A-2 only allows for PDF files.
+    EmbeddedFile,
+    /// An image is interpolated.
+    Interpolation,
+    /// A stream is compressed with LZW.
+    LzwCompression,
+    /// An annotation is hidden or isn't printed.
+    HiddenAnnotation,
+    /// A content stream couldn't be decoded, so the colors it uses weren't checked.
+    UncheckedContent,
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Rule::Encrypted => write!(f, "The document is encrypted"),
+            Rule::MissingFileIdentifier => write!(f, "The trailer has no file identifier"),
+            Rule::MissingIdentification => write!(f, "The metadata doesn't identify the PDF/A level"),
+            Rule::FontNotEmbedded(name) => write!(f, "Font {} is not embedded", name),
+            Rule::DeviceColor(space) => write!(f, "{} is used without a matching output intent", space),
+            Rule::ForbiddenAction(action) => write!(f, "{} actions are not allowed", action),
+            Rule::AdditionalActions => write!(f, "Additional actions are not allowed"),
+            Rule::JavaScript => write!(f, "JavaScript is not allowed"),
+            Rule::Transparency => write!(f, "Transparency is not allowed"),
+            Rule::OptionalContent => write!(f, "Optional content is not allowed"),
+            Rule::EmbeddedFile => write!(f, "The embedded file is not allowed"),
+            Rule::Interpolation => write!(f, "Images must not be interpolated"),
+            Rule::LzwCompression => write!(f, "LZW compression is not allowed"),
+            Rule::HiddenAnnotation => write!(f, "Annotations must be printed and visible"),
+            Rule::UncheckedContent => write!(f, "The content stream could not be checked"),
+        }
+    }
+}
+
+/// Violation of a PDF/A rule, found by `Document::preflight`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    pub rule: Rule,
+    /// Indirect object which violates the rule, or which contains the direct object violating it.
+    pub object_id: Option<ObjectId>,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.object_id {
+            Some((number, generation)) => write!(f, "{} (object {} {})", self.rule, number, generation),
+            None => self.rule.fmt(f),
+        }
+    }
+}
+
+impl Document {
+    /// Check the document against the rules of a PDF/A level, listing the violations found in object order.
+    ///
+    /// The checks cover features which `convert_to_pdfa` changes, as well as fonts, device colors and LZW
+    /// compression. Passing them doesn't guarantee conformance, as syntax and font programs aren't checked.
+    /// Content streams which can't be decoded, as when no parser feature is enabled, are reported as unchecked.
+    pub fn preflight(&self, level: PdfALevel) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        let mut violation = |rule: Rule, object_id: Option<ObjectId>| {
+            let violation = Violation { rule, object_id };
+            if !violations.contains(&violation) {
+                violations.push(violation);
+            }
+        };
+
+        if self.is_encrypted() {
+            violation(
+                Rule::Encrypted,
+                self.trailer.get(b"Encrypt").and_then(Object::as_reference).ok(),
+            );
+        }
+        if !self.trailer.has(b"ID") {
+            violation(Rule::MissingFileIdentifier, None);
+        }
+        let identified = self
+            .get_xmp()
+            .ok()
+            .flatten()
+            .is_some_and(|xmp| xmp.pdfa_part == Some(level.part()) && xmp.pdfa_conformance.is_some());
+        if !identified {
+            violation(Rule::MissingIdentification, None);
+        }
+        if let Ok(catalog) = self.catalog() {
+            let catalog_id = self.trailer.get(b"Root").and_then(Object::as_reference).ok();
+            if level == PdfALevel::A1b && catalog.has(b"OCProperties") {
+                violation(Rule::OptionalContent, catalog_id);
+            }
+            if let Ok((names_id, names)) = catalog
+                .get(b"Names")
+                .and_then(|names| self.dereference(names))
+                .and_then(|(id, names)| Ok((id.or(catalog_id), names.as_dict()?)))
+            {
+                if names.has(b"JavaScript") {
+                    violation(Rule::JavaScript, names_id);
+                }
+            }
+        }
+
+        let intent_components = self
+            .pdfa_output_intent()
+            .and_then(|intent| intent.get(b"DestOutputProfile").ok())
+            .and_then(|profile| self.dereference(profile).ok())
+            .and_then(|(_, profile)| profile.as_stream().ok())
+            .and_then(|profile| profile.dict.get(b"N").and_then(Object::as_i64).ok());
+        let allowed = |space: &str| match (space, intent_components) {
+            ("DeviceGray", Some(_)) | ("DeviceRGB", Some(3)) | ("DeviceCMYK", Some(4)) => true,
+            ("DeviceGray", None) | ("DeviceRGB", _) | ("DeviceCMYK", _) => false,
+            _ => true,
+        };
+
+        for (&id, object) in &self.objects {
+            let id = Some(id);
+            if let Object::Stream(stream) = object {
+                let filters = stream.filters().unwrap_or_default();
+                if filters.iter().any(|filter| filter == "LZWDecode") {
+                    violation(Rule::LzwCompression, id);
+                }
+                if stream.dict.get(b"Subtype").and_then(Object::as_name_str).ok() == Some("Form") {
+                    let content = stream.decompressed_content().unwrap_or_else(|_| stream.content.clone());
+                    match content_color_spaces(&content) {
+                        Some(spaces) => {
+                            for space in spaces {
+                                if !allowed(space) {
+                                    violation(Rule::DeviceColor(space.to_owned()), id);
+                                }
+                            }
+                        }
+                        None => violation(Rule::UncheckedContent, id),
+                    }
+                }
+            }
+            visit_dictionaries(object, &mut |dict| {
+                if dict.type_is(b"Font") && !self.is_font_embedded(dict) {
+                    let name = dict.get(b"BaseFont").and_then(Object::as_name_str).unwrap_or("");
+                    violation(Rule::FontNotEmbedded(name.to_owned()), id);
+                }
+                if is_forbidden_action(dict) {
+                    let action = dict.get(b"S").and_then(Object::as_name_str).unwrap_or("");
+                    violation(Rule::ForbiddenAction(action.to_owned()), id);
+                }
+                if dict.has(b"AA") {
+                    violation(Rule::AdditionalActions, id);
+                }
+                if let Ok(Object::Boolean(true)) = dict.get(b"Interpolate") {
+                    violation(Rule::Interpolation, id);
+                }
+                for key in [b"ColorSpace".as_ref(), b"CS"] {
+                    if let Ok(space) = dict.get(key).and_then(Object::as_name_str) {
+                        if !allowed(space) {
+                            violation(Rule::DeviceColor(space.to_owned()), id);
+                        }
+                    }
+                }
+
+                let subtype = dict.get(b"Subtype").and_then(Object::as_name_str).ok();
+                if dict.has(b"Rect") && subtype.is_some() && subtype != Some("Popup") && !dict.type_is(b"Page") {
+                    let flags = dict.get(b"F").and_then(Object::as_i64).unwrap_or(0);
+                    if flags & HIDING_FLAGS != 0 || flags & PRINT_FLAG == 0 {
+                        violation(Rule::HiddenAnnotation, id);
+                    }
+                }
+                if dict.has(b"EF") && (level == PdfALevel::A1b || level == PdfALevel::A2b) {
+                    let is_pdf = dict
+                        .get(b"EF")
+                        .and_then(Object::as_dict)
+                        .and_then(|files| files.get(b"F"))
+                        .and_then(|file| self.dereference(file))
+                        .and_then(|(_, file)| file.as_stream())
+                        .and_then(|file| file.dict.get(b"Subtype"))
+                        .and_then(Object::as_name_str)
+                        .is_ok_and(|subtype| subtype == "application/pdf");
+                    if level == PdfALevel::A1b || !is_pdf {
+                        violation(Rule::EmbeddedFile, id);
+                    }
+                }
+                if level == PdfALevel::A1b && has_transparency(dict) {
+                    violation(Rule::Transparency, id);
+                }
+            });
+        }
+
+        for page_id in self.page_iter() {
+            for content_id in self.get_page_contents(page_id) {
+                if let Ok(stream) = self.get_object(content_id).and_then(Object::as_stream) {
+                    let content = stream.decompressed_content().unwrap_or_else(|_| stream.content.clone());
+                    match content_color_spaces(&content) {
+                        Some(spaces) => {
+                            for space in spaces {
+                                if !allowed(space) {
+                                    violation(Rule::DeviceColor(space.to_owned()), Some(content_id));
+                                }
+                            }
+                        }
+                        None => violation(Rule::UncheckedContent, Some(content_id)),
+                    }
+                }
+            }
+        }
+        violations
+    }
+}
+
+/// Call a function on a dictionary and the dictionaries nested in it, including stream dictionaries.
+fn visit_dictionaries<F: FnMut(&Dictionary)>(object: &Object, visit: &mut F) {
+    match object {
+        Object::Array(array) => {
+            for item in array {
+                visit_dictionaries(item, visit);
+            }
+        }
+        Object::Dictionary(dict) | Object::Stream(crate::Stream { dict, .. }) => {
+            visit(dict);
+            for (_, value) in dict.iter() {
+                visit_dictionaries(value, visit);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn has_transparency(dict: &Dictionary) -> bool {
+    let soft_mask = match dict.get(b"SMask") {
+        Ok(Object::Name(name)) => name != b"None",
+        Ok(_) => true,
+        Err(_) => false,
+    };
+    let alpha = [b"CA".as_ref(), b"ca"]
+        .iter()
+        .any(|key| dict.get(key).and_then(Object::as_float).is_ok_and(|alpha| alpha != 1.0));
+    let blend_mode = dict
+        .get(b"BM")
+        .and_then(Object::as_name_str)
+        .is_ok_and(|mode| mode != "Normal" && mode != "Compatible");
+    let group = dict
+        .get(b"Group")
+        .and_then(Object::as_dict)
+        .and_then(|group| group.get(b"S"))
+        .and_then(Object::as_name_str)
+        .is_ok_and(|subtype| subtype == "Transparency");
+    soft_mask || alpha || blend_mode || group
+}
+
+/// Get the device color spaces set by operators of a content stream, or `None` if it can't be decoded.
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+fn content_color_spaces(content: &[u8]) -> Option<Vec<&'static str>> {
+    let content = Content::decode(content).ok()?;
+    let mut spaces = Vec::new();
+    for operation in &content.operations {
+        let space = match operation.operator.as_str() {
+            "g" | "G" => "DeviceGray",
+            "rg" | "RG" => "DeviceRGB",
+            "k" | "K" => "DeviceCMYK",
+            "cs" | "CS" => match operation.operands.first().and_then(|space| space.as_name_str().ok()) {
+                Some("DeviceGray") => "DeviceGray",
+                Some("DeviceRGB") => "DeviceRGB",
+                Some("DeviceCMYK") => "DeviceCMYK",
+                _ => continue,
+            },
+            _ => continue,
+        };
+        if !spaces.contains(&space) {
+            spaces.push(space);
+        }
+    }
+    Some(spaces)
+}
+
+/// Content streams can't be decoded without a parser.
+#[cfg(not(any(feature = "pom_parser", feature = "nom_parser")))]
+fn content_color_spaces(_content: &[u8]) -> Option<Vec<&'static str>> {
+    None
+}
+
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+#[test]
+fn preflight_document() {
+    use crate::content::Operation;
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    let (&font_id, _) = doc
+        .objects
+        .iter()
+        .find(|(_, object)| object.as_dict().is_ok_and(|dict| dict.type_is(b"Font")))
+        .unwrap();
+    let content = Content {
+        operations: vec![
+            Operation::new("rg", vec![1.into(), 0.into(), 0.into()]),
+            Operation::new("re", vec![10.into(), 10.into(), 100.into(), 100.into()]),
+            Operation::new("f", vec![]),
+        ],
+    };
+    doc.append_page_content(page_id, content.encode().unwrap()).unwrap();
+    let content_id = *doc.get_page_contents(page_id).last().unwrap();
+    let state_id = doc.add_object(dictionary! { "Type" => "ExtGState", "ca" => 0.5 });
+
+    let violations = doc.preflight(PdfALevel::A1b);
+    let rules: Vec<(Rule, Option<ObjectId>)> = violations
+        .iter()
+        .map(|violation| (violation.rule.clone(), violation.object_id))
+        .collect();
+    assert!(rules.contains(&(Rule::MissingFileIdentifier, None)));
+    assert!(rules.contains(&(Rule::MissingIdentification, None)));
+    assert!(rules.contains(&(Rule::FontNotEmbedded("Courier".to_string()), Some(font_id))));
+    assert!(rules.contains(&(Rule::DeviceColor("DeviceRGB".to_string()), Some(content_id))));
+    assert!(rules.contains(&(Rule::Transparency, Some(state_id))));
+    assert!(!rules
+        .iter()
+        .any(|(rule, _)| *rule == Rule::Encrypted || *rule == Rule::UncheckedContent));
+    assert_eq!(violations[0].to_string(), "The trailer has no file identifier");
+
+    // Transparency is allowed by PDF/A-2.
+    let violations = doc.preflight(PdfALevel::A2b);
+    assert!(!violations.iter().any(|violation| violation.rule == Rule::Transparency));
+}
@@ -1,9 +1,16 @@
+use crate::linearize::collect_references;
+use crate::references::visit_references;
 use crate::Result;
-use crate::{Document, Object, ObjectId};
-use std::collections::BTreeMap;
+use crate::{Dictionary, Document, Object, ObjectId, Stream};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs::File;
+use std::hash::Hasher;
 use std::io::Write;
 
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
 impl Document {
     /// Change producer of document information dictionary.
     pub fn change_producer(&mut self, producer: &str) {
@@ -18,16 +25,20 @@ impl Document {
         }
     }
 
-    /// Compress PDF stream objects.
+    /// Compress PDF stream objects, in parallel with the `rayon` feature.
     pub fn compress(&mut self) {
-        for object in self.objects.values_mut() {
+        let compress = |object: &mut Object| {
             if let Object::Stream(ref mut stream) = *object {
                 if stream.allows_compression {
                     // Ignore any error and continue to compress other streams.
                     let _ = stream.compress();
                 }
             }
-        }
+        };
+        #[cfg(feature = "rayon")]
+        self.objects.par_iter_mut().for_each(|(_, object)| compress(object));
+        #[cfg(not(feature = "rayon"))]
+        self.objects.values_mut().for_each(compress);
     }
 
     /// Decompress PDF stream objects.
@@ -79,6 +90,176 @@ impl Document {
         ids
     }
 
+    /// Collapse identical objects which can be shared into a single object, rewriting the references to them,
+    /// and get the ids of the deleted duplicates.
+    ///
+    /// Streams, arrays and dictionaries of resources, such as fonts, font descriptors, encodings, graphics
+    /// states, patterns, shadings and functions, and resource dictionaries, are compared with the objects they
+    /// refer to, so that a font copied by merging documents is deduplicated together with its descriptor and
+    /// font file.
+    pub fn dedupe_resources(&mut self) -> Vec<ObjectId> {
+        let resources: BTreeSet<ObjectId> = self
+            .objects
+            .values()
+            .filter_map(|object| match *object {
+                Object::Dictionary(ref dict) => Some(dict),
+                Object::Stream(ref stream) => Some(&stream.dict),
+                _ => None,
+            })
+            .filter_map(|dict| dict.get(b"Resources").and_then(Object::as_reference).ok())
+            .collect();
+        let mut ids = vec![];
+        loop {
+            // Keep the duplicate with the lowest id, objects being compared after replacing the duplicates
+            // they refer to, until none is left.
+            let mut kept: HashMap<u64, Vec<ObjectId>> = HashMap::new();
+            let mut replace = BTreeMap::new();
+            for (&id, object) in &self.objects {
+                if is_shareable(object) || resources.contains(&id) {
+                    let mut hasher = DefaultHasher::new();
+                    hash_object(object, &mut hasher);
+                    let candidates = kept.entry(hasher.finish()).or_default();
+                    match candidates
+                        .iter()
+                        .find(|kept_id| same_object(&self.objects[kept_id], object))
+                    {
+                        Some(&kept_id) => {
+                            replace.insert(id, kept_id);
+                        }
+                        None => candidates.push(id),
+                    }
+                }
+            }
+            if replace.is_empty() {
+                return ids;
+            }
+
+            for id in replace.keys() {
+                self.objects.remove(id);
+            }
+            let action = |object: &mut Object| {
+                if let Object::Reference(ref mut id) = *object {
+                    if let Some(&kept_id) = replace.get(id) {
+                        *id = kept_id;
+                    }
+                }
+            };
+            self.traverse_objects(action);
+            ids.extend(replace.keys());
+        }
+    }
+
+    /// Copy an object of another document into this one, with the objects it references, and get its id.
+    ///
+    /// References are remapped to the copies, and objects referenced several times or in cycles are copied
+    /// once. The /Parent entries of pages are left out, so that importing a page doesn't import the page tree.
+    pub fn import_object(&mut self, source: &Document, id: ObjectId) -> ObjectId {
+        self.import_objects(source, &[id])[0]
+    }
+
+    /// Copy objects of another document into this one as `import_object` does, sharing the copies of the
+    /// objects they reference.
+    pub fn import_objects(&mut self, source: &Document, ids: &[ObjectId]) -> Vec<ObjectId> {
+        let mut copied = BTreeMap::new();
+        ids.iter()
+            .map(|&id| {
+                let copy = self.copy_foreign_object(source, &Object::Reference(id), &mut copied);
+                copy.as_reference().unwrap_or(id)
+            })
+            .collect()
+    }
+
+    /// Copy an object of another document, adding copies of the objects it references to this document.
+    pub(crate) fn copy_foreign_object(
+        &mut self, source: &Document, object: &Object, copied: &mut BTreeMap<ObjectId, ObjectId>,
+    ) -> Object {
+        match *object {
+            Object::Reference(id) => {
+                if let Some(&copy_id) = copied.get(&id) {
+                    return copy_id.into();
+                }
+                let copy_id = self.new_object_id();
+                copied.insert(id, copy_id);
+                let copy = match source.get_object(id) {
+                    Ok(object) => self.copy_foreign_object(source, object, copied),
+                    Err(_) => Object::Null,
+                };
+                self.objects.insert(copy_id, copy);
+                copy_id.into()
+            }
+            Object::Array(ref array) => Object::Array(
+                array
+                    .iter()
+                    .map(|item| self.copy_foreign_object(source, item, copied))
+                    .collect(),
+            ),
+            Object::Dictionary(ref dict) => Object::Dictionary(self.copy_foreign_dictionary(source, dict, copied)),
+            Object::Stream(ref stream) => Object::Stream(Stream {
+                dict: self.copy_foreign_dictionary(source, &stream.dict, copied),
+                ..stream.clone()
+            }),
+            _ => object.clone(),
+        }
+    }
+
+    fn copy_foreign_dictionary(
+        &mut self, source: &Document, dict: &Dictionary, copied: &mut BTreeMap<ObjectId, ObjectId>,
+    ) -> Dictionary {
+        let mut copy = Dictionary::new();
+        for (key, value) in dict.iter() {
+            // Parent entries of pages lead back into the page tree of the source document.
+            if key != b"Parent" || !(dict.type_is(b"Page") || dict.type_is(b"Pages")) {
+                copy.set(key.clone(), self.copy_foreign_object(source, value, copied));
+            }
+        }
+        copy
+    }
+
+    /// Delete the objects which can't be reached from the catalog, the document information dictionary or the
+    /// encryption dictionary of the trailer, and get their ids.
+    ///
+    /// Objects are deleted even when they refer to each other. Unlike `prune_objects`, references from other
+    /// trailer entries don't keep objects, such as ones left by tools which track objects in custom entries.
+    pub fn collect_garbage(&mut self) -> Vec<ObjectId> {
+        let mut pending = Vec::new();
+        for key in [&b"Root"[..], b"Info", b"Encrypt"] {
+            if let Ok(root) = self.trailer.get(key) {
+                collect_references(root, &mut pending);
+            }
+        }
+        let mut reachable = BTreeSet::new();
+        while let Some(id) = pending.pop() {
+            if reachable.insert(id) {
+                if let Some(object) = self.objects.get(&id) {
+                    collect_references(object, &mut pending);
+                }
+            }
+        }
+
+        let ids: Vec<ObjectId> = self
+            .objects
+            .keys()
+            .filter(|id| !reachable.contains(id))
+            .copied()
+            .collect();
+        for id in &ids {
+            self.objects.remove(id);
+        }
+        ids
+    }
+
+    /// Count the references to an object from other objects and the trailer.
+    pub(crate) fn reference_count(&self, id: ObjectId) -> usize {
+        let mut count = 0;
+        let trailer = Object::Dictionary(self.trailer.clone());
+        for object in std::iter::once(&trailer).chain(self.objects.values()) {
+            visit_references(object, &mut Vec::new(), &mut |target, _| {
+                count += usize::from(target == id)
+            });
+        }
+        count
+    }
+
     /// Delete object by object ID.
     pub fn delete_object(&mut self, id: ObjectId) -> Option<Object> {
         let action = |object: &mut Object| match *object {
@@ -212,6 +393,41 @@ impl Document {
         Ok(())
     }
 
+    /// Append content to a page, after wrapping its existing content in a saved graphics state
+    /// so that the appended content is drawn with the default one.
+    pub(crate) fn append_page_content(&mut self, page_id: ObjectId, content: Vec<u8>) -> Result<()> {
+        let mut contents: Vec<Object> = self
+            .get_page_contents(page_id)
+            .into_iter()
+            .map(Object::Reference)
+            .collect();
+        let mut appended = b"\nQ\n".to_vec();
+        appended.extend(content);
+        let begin_id = self.add_object(super::Stream::new(dictionary! {}, b"q\n".to_vec()));
+        let end_id = self.add_object(super::Stream::new(dictionary! {}, appended));
+        contents.insert(0, begin_id.into());
+        contents.push(end_id.into());
+        self.get_object_mut(page_id)
+            .and_then(Object::as_dict_mut)?
+            .set("Contents", contents);
+        Ok(())
+    }
+
+    /// Insert content before the existing content of a page. The content must restore the graphics state it changes.
+    pub(crate) fn prepend_page_content(&mut self, page_id: ObjectId, content: Vec<u8>) -> Result<()> {
+        let mut contents: Vec<Object> = self
+            .get_page_contents(page_id)
+            .into_iter()
+            .map(Object::Reference)
+            .collect();
+        let stream_id = self.add_object(super::Stream::new(dictionary! {}, content));
+        contents.insert(0, stream_id.into());
+        self.get_object_mut(page_id)
+            .and_then(Object::as_dict_mut)?
+            .set("Contents", contents);
+        Ok(())
+    }
+
     pub fn extract_stream(&self, stream_id: ObjectId, decompress: bool) -> Result<()> {
         let mut file = File::create(format!("{:?}.bin", stream_id))?;
         if let Ok(stream_obj) = self.get_object(stream_id) {
@@ -230,3 +446,182 @@ impl Document {
         Ok(())
     }
 }
+
+/// Whether an object can be referred to from several places in place of identical copies.
+fn is_shareable(object: &Object) -> bool {
+    match *object {
+        Object::Stream(_) | Object::Array(_) => true,
+        Object::Dictionary(ref dict) => {
+            matches!(
+                dict.type_name(),
+                Ok("Font") | Ok("FontDescriptor") | Ok("Encoding") | Ok("ExtGState") | Ok("Pattern")
+            ) || dict.has(b"ShadingType")
+                || dict.has(b"FunctionType")
+                || dict.has(b"Registry")
+        }
+        _ => false,
+    }
+}
+
+/// Hash an object unambiguously, with the entries of dictionaries in sorted order.
+fn hash_object(object: &Object, hasher: &mut DefaultHasher) {
+    fn hash_dictionary(dict: &Dictionary, hasher: &mut DefaultHasher) {
+        let mut entries: Vec<(&Vec<u8>, &Object)> = dict.iter().collect();
+        entries.sort_by_key(|&(key, _)| key);
+        hasher.write(&(entries.len() as u64).to_be_bytes());
+        for (key, value) in entries {
+            hasher.write(&(key.len() as u64).to_be_bytes());
+            hasher.write(key);
+            hash_object(value, hasher);
+        }
+    }
+    let hash_bytes = |tag: u8, bytes: &[u8], hasher: &mut DefaultHasher| {
+        hasher.write(&[tag]);
+        hasher.write(&(bytes.len() as u64).to_be_bytes());
+        hasher.write(bytes);
+    };
+    match *object {
+        Object::Null => hasher.write(&[0]),
+        Object::Boolean(value) => hasher.write(&[1, u8::from(value)]),
+        Object::Integer(value) => hash_bytes(2, &value.to_be_bytes(), hasher),
+        Object::Real(value) => hash_bytes(3, &value.to_bits().to_be_bytes(), hasher),
+        Object::Name(ref name) => hash_bytes(4, name, hasher),
+        Object::String(ref bytes, _) => hash_bytes(5, bytes, hasher),
+        Object::Array(ref array) => {
+            hasher.write(&[6]);
+            hasher.write(&(array.len() as u64).to_be_bytes());
+            for item in array {
+                hash_object(item, hasher);
+            }
+        }
+        Object::Dictionary(ref dict) => {
+            hasher.write(&[7]);
+            hash_dictionary(dict, hasher);
+        }
+        Object::Stream(ref stream) => {
+            hasher.write(&[8]);
+            hash_dictionary(&stream.dict, hasher);
+            hash_bytes(8, &stream.content, hasher);
+        }
+        Object::Reference((number, generation)) => {
+            hasher.write(&[9]);
+            hasher.write(&number.to_be_bytes());
+            hasher.write(&generation.to_be_bytes());
+        }
+    }
+}
+
+/// Whether two objects are identical as `hash_object` sees them, regardless of the order of dictionary entries
+/// and of the format of strings.
+fn same_object(object: &Object, other: &Object) -> bool {
+    fn same_dictionary(dict: &Dictionary, other: &Dictionary) -> bool {
+        dict.len() == other.len()
+            && dict
+                .iter()
+                .all(|(key, value)| other.get(key).is_ok_and(|other| same_object(value, other)))
+    }
+    match (object, other) {
+        (Object::Real(value), Object::Real(other)) => value.to_bits() == other.to_bits(),
+        (Object::String(bytes, _), Object::String(other, _)) => bytes == other,
+        (Object::Array(array), Object::Array(other)) => {
+            array.len() == other.len() && array.iter().zip(other).all(|(item, other)| same_object(item, other))
+        }
+        (Object::Dictionary(dict), Object::Dictionary(other)) => same_dictionary(dict, other),
+        (Object::Stream(stream), Object::Stream(other)) => {
+            same_dictionary(&stream.dict, &other.dict) && stream.content == other.content
+        }
+        _ => object == other,
+    }
+}
+
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+#[test]
+fn dedupe_merged_resources() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    let font = dictionary! {
+        "Type" => "Font",
+        "Subtype" => "TrueType",
+        "BaseFont" => "Test",
+        "Widths" => vec![500.into(), 600.into()],
+    };
+    let descriptor_id = doc.add_object(dictionary! { "Type" => "FontDescriptor", "FontName" => "Test" });
+    let other_descriptor_id = doc.add_object(dictionary! { "FontName" => "Test", "Type" => "FontDescriptor" });
+    let mut font_with_descriptor = font.clone();
+    font_with_descriptor.set("FontDescriptor", descriptor_id);
+    let font_id = doc.add_object(font_with_descriptor.clone());
+    font_with_descriptor.set("FontDescriptor", other_descriptor_id);
+    let other_font_id = doc.add_object(font_with_descriptor);
+    let page_id = doc.page_iter().next().unwrap();
+    let page = doc.get_object_mut(page_id).and_then(Object::as_dict_mut).unwrap();
+    page.set("Fonts", vec![font_id.into(), other_font_id.into()]);
+
+    let mut other = create_document();
+    other.add_object(dictionary! { "Type" => "Annot", "Subtype" => "Text" });
+    doc.merge(other).unwrap();
+    doc.add_object(dictionary! { "Type" => "Annot", "Subtype" => "Text" });
+    let count = doc.objects.len();
+
+    let removed = doc.dedupe_resources();
+    // The descriptor and the font using it, and the font, resources and content stream of the merged document.
+    assert_eq!(removed.len(), 5);
+    assert_eq!(doc.objects.len(), count - 5);
+    assert!(removed.contains(&other_descriptor_id) && removed.contains(&other_font_id));
+    let page = doc.get_dictionary(page_id).unwrap();
+    assert_eq!(
+        page.get(b"Fonts").unwrap(),
+        &Object::Array(vec![font_id.into(), font_id.into()])
+    );
+    // Pages and annotations are kept.
+    assert_eq!(doc.get_pages().len(), 2);
+    assert_eq!(doc.extract_text(&[1, 2]).unwrap(), "Hello World!\nHello World!\n");
+    assert!(doc.dedupe_resources().is_empty());
+}
+
+#[test]
+fn import_objects() {
+    use crate::test_support::create_document;
+
+    let source = create_document();
+    let page_id = source.page_iter().next().unwrap();
+    let source_contents = source.get_page_contents(page_id)[0];
+    let mut doc = Document::with_version("1.5");
+
+    let page_copy = doc.import_object(&source, page_id);
+    let page = doc.get_dictionary(page_copy).unwrap();
+    assert!(page.type_is(b"Page") && !page.has(b"Parent"));
+    let contents = page.get(b"Contents").and_then(Object::as_reference).unwrap();
+    assert_eq!(
+        doc.get_object(contents).and_then(Object::as_stream).unwrap().content,
+        source.get_page_content(page_id).unwrap()
+    );
+    // The resources are inherited from the page tree, which isn't imported.
+    assert_eq!(doc.objects.len(), 2);
+
+    let copies = doc.import_objects(&source, &[source_contents, source_contents]);
+    assert_eq!(copies[0], copies[1]);
+    assert_ne!(copies[0], contents);
+}
+
+#[test]
+fn collect_garbage() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    let count = doc.objects.len();
+    let first = doc.new_object_id();
+    let second = doc.add_object(dictionary! { "Next" => first });
+    doc.objects.insert(first, dictionary! { "Next" => second }.into());
+    let orphan = doc.add_object(Object::string_literal("orphan"));
+    doc.trailer.set("Orphan", orphan);
+
+    let mut pruned = doc.clone().prune_objects();
+    pruned.sort();
+    assert_eq!(pruned, vec![first, second]);
+    let mut ids = doc.collect_garbage();
+    ids.sort();
+    assert_eq!(ids, vec![first, second, orphan]);
+    assert_eq!(doc.objects.len(), count);
+    assert_eq!(doc.get_pages().len(), 1);
+}
@@ -0,0 +1,334 @@
+//! Public-key security handler (/Adobe.PubSec), which encrypts documents for the certificates of their recipients
+//! instead of with passwords.
+//!
+//! The file encryption key is derived from a seed, which is sealed for each recipient in a PKCS #7 enveloped data
+//! structure that only the private key of the recipient opens.
+
+use crate::encryption::{EncryptionAlgorithm, Permissions, SecurityHandler};
+use crate::error::DecryptionError;
+use crate::{Dictionary, Document, Error, Object, Result, StringFormat};
+use aes::cipher::block_padding::Pkcs7;
+use aes::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use aes::{Aes128, Aes192, Aes256};
+use cms::cert::IssuerAndSerialNumber;
+use cms::content_info::{CmsVersion, ContentInfo};
+use cms::enveloped_data::{
+    EncryptedContentInfo, EnvelopedData, KeyTransRecipientInfo, RecipientIdentifier, RecipientInfo, RecipientInfos,
+};
+use des::TdesEde3;
+use rsa::pkcs1::DecodeRsaPrivateKey;
+use rsa::pkcs8::{DecodePrivateKey, DecodePublicKey};
+use rsa::{Pkcs1v15Encrypt, RsaPrivateKey, RsaPublicKey};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::convert::TryFrom;
+use x509_cert::der::asn1::{ObjectIdentifier, OctetString};
+use x509_cert::der::{Any, Decode, Encode, SliceReader};
+use x509_cert::spki::AlgorithmIdentifierOwned;
+use x509_cert::Certificate;
+
+const ID_DATA: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.7.1");
+const ID_ENVELOPED_DATA: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.7.3");
+const RSA_ENCRYPTION: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.1.1");
+const DES_EDE3_CBC: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.3.7");
+const AES128_CBC: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.16.840.1.101.3.4.1.2");
+const AES192_CBC: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.16.840.1.101.3.4.1.22");
+const AES256_CBC: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.16.840.1.101.3.4.1.42");
+
+/// Recipient of a document encrypted with the public-key security handler.
+#[derive(Debug, Clone)]
+pub struct Recipient {
+    /// DER encoded X.509 certificate with the RSA public key of the recipient.
+    pub certificate: Vec<u8>,
+    /// Permissions granted to the recipient.
+    pub permissions: Permissions,
+}
+
+/// Options for encrypting a document for the certificates of its recipients when saving.
+#[derive(Debug, Clone)]
+pub struct PublicKeyEncryptionOptions {
+    pub recipients: Vec<Recipient>,
+    pub algorithm: EncryptionAlgorithm,
+}
+
+impl Document {
+    /// Decrypt a document encrypted with the public-key security handler, using the certificate and the private
+    /// key of one of its recipients.
+    ///
+    /// The certificate is DER encoded X.509, the RSA private key DER encoded PKCS #8 or PKCS #1.
+    pub fn decrypt_with_certificate(&mut self, certificate: &[u8], private_key: &[u8]) -> Result<()> {
+        let invalid = || Error::Decryption(DecryptionError::InvalidCertificate);
+        let certificate = Certificate::from_der(certificate).map_err(|_| invalid())?;
+        let private_key = RsaPrivateKey::from_pkcs8_der(private_key)
+            .or_else(|_| RsaPrivateKey::from_pkcs1_der(private_key))
+            .map_err(|_| invalid())?;
+        self.decrypt_with(|encrypt, _| security_handler(encrypt, &certificate, &private_key))
+    }
+}
+
+/// Get the security handler of a document encrypted for a recipient.
+fn security_handler(
+    encrypt: &Dictionary, certificate: &Certificate, private_key: &RsaPrivateKey,
+) -> Result<SecurityHandler> {
+    let invalid = || Error::Decryption(DecryptionError::InvalidEncryptDictionary);
+    if encrypt
+        .get(b"Filter")
+        .and_then(Object::as_name)
+        .map_err(|_| invalid())?
+        != b"Adobe.PubSec"
+    {
+        return Err(Error::Decryption(DecryptionError::UnsupportedEncryption));
+    }
+    // The recipients are listed by the encryption dictionary (adbe.pkcs7.s4), or by the crypt filter of the
+    // streams (adbe.pkcs7.s5).
+    let filter = match encrypt.get(b"Recipients") {
+        Ok(_) => encrypt,
+        Err(_) => encrypt
+            .get(b"StmF")
+            .and_then(Object::as_name)
+            .and_then(|name| encrypt.get(b"CF").and_then(Object::as_dict)?.get(name))
+            .and_then(Object::as_dict)
+            .map_err(|_| invalid())?,
+    };
+    let recipients = match filter.get(b"Recipients").map_err(|_| invalid())? {
+        Object::Array(recipients) => recipients
+            .iter()
+            .map(Object::as_str)
+            .collect::<Result<Vec<_>>>()
+            .map_err(|_| invalid())?,
+        Object::String(recipient, _) => vec![recipient.as_slice()],
+        _ => return Err(invalid()),
+    };
+    let encrypt_metadata = match filter.get(b"EncryptMetadata") {
+        Ok(Object::Boolean(value)) => *value,
+        _ => true,
+    };
+
+    let content = recipients
+        .iter()
+        .find_map(|recipient| open_envelope(recipient, certificate, private_key))
+        .ok_or(Error::Decryption(DecryptionError::NotARecipient))?;
+    if content.len() < 20 {
+        return Err(invalid());
+    }
+    let version = encrypt.get(b"V").and_then(Object::as_i64).unwrap_or(0);
+    SecurityHandler::from_key(encrypt, encrypt_metadata, |key_length| {
+        file_key(version, &content[..20], &recipients, encrypt_metadata, key_length)
+    })
+}
+
+/// Create a security handler with a new file encryption key, together with the encryption dictionary.
+pub(crate) fn security_handler_for_recipients(
+    options: &PublicKeyEncryptionOptions,
+) -> Result<(SecurityHandler, Dictionary)> {
+    let seed: [u8; 20] = rand::random();
+    let recipients = options
+        .recipients
+        .iter()
+        .map(|recipient| {
+            let mut content = seed.to_vec();
+            content.extend_from_slice(&recipient.permissions.to_p_value().to_be_bytes());
+            seal_envelope(&content, &recipient.certificate)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let (version, method, length) = match options.algorithm {
+        EncryptionAlgorithm::Rc4 => (4, "V2", 128),
+        EncryptionAlgorithm::Aes128 => (4, "AESV2", 128),
+        EncryptionAlgorithm::Aes256 => (5, "AESV3", 256),
+    };
+    let encrypt = dictionary! {
+        "Filter" => "Adobe.PubSec",
+        "SubFilter" => "adbe.pkcs7.s5",
+        "V" => version,
+        "Length" => length,
+        "CF" => dictionary! {
+            "DefaultCryptFilter" => dictionary! {
+                "CFM" => method,
+                "Recipients" => recipients
+                    .iter()
+                    .map(|recipient| Object::String(recipient.clone(), StringFormat::Hexadecimal))
+                    .collect::<Vec<_>>(),
+            },
+        },
+        "StmF" => "DefaultCryptFilter",
+        "StrF" => "DefaultCryptFilter",
+    };
+    let recipients: Vec<&[u8]> = recipients.iter().map(Vec::as_slice).collect();
+    let handler = SecurityHandler::from_key(&encrypt, true, |key_length| {
+        file_key(version, &seed, &recipients, true, key_length)
+    })?;
+    Ok((handler, encrypt))
+}
+
+/// Compute the file encryption key from the seed and the enveloped data of all recipients.
+fn file_key(version: i64, seed: &[u8], recipients: &[&[u8]], encrypt_metadata: bool, key_length: usize) -> Vec<u8> {
+    let mut input = seed.to_vec();
+    for recipient in recipients {
+        input.extend_from_slice(recipient);
+    }
+    if !encrypt_metadata {
+        input.extend_from_slice(&[0xFF; 4]);
+    }
+    let mut key = if version == 5 {
+        Sha256::digest(&input).to_vec()
+    } else {
+        Sha1::digest(&input).to_vec()
+    };
+    key.truncate(key_length);
+    key
+}
+
+/// Get the content of enveloped data with the private key of a recipient, if it's sealed for the certificate.
+fn open_envelope(data: &[u8], certificate: &Certificate, private_key: &RsaPrivateKey) -> Option<Vec<u8>> {
+    // Enveloped data in strings may be padded with zeros.
+    let info = ContentInfo::decode(&mut SliceReader::new(data).ok()?).ok()?;
+    if info.content_type != ID_ENVELOPED_DATA {
+        return None;
+    }
+    let enveloped: EnvelopedData = info.content.decode_as().ok()?;
+    let tbs_certificate = &certificate.tbs_certificate;
+    enveloped.recip_infos.0.iter().find_map(|recipient| match recipient {
+        RecipientInfo::Ktri(recipient) => {
+            let is_recipient = match recipient.rid {
+                RecipientIdentifier::IssuerAndSerialNumber(ref id) => {
+                    id.issuer == tbs_certificate.issuer && id.serial_number == tbs_certificate.serial_number
+                }
+                // Whether the key identifier is the one of the certificate is checked by decrypting.
+                RecipientIdentifier::SubjectKeyIdentifier(_) => true,
+            };
+            if !is_recipient {
+                return None;
+            }
+            let key = private_key
+                .decrypt(Pkcs1v15Encrypt, recipient.enc_key.as_bytes())
+                .ok()?;
+            decrypt_content(&enveloped.encrypted_content, &key)
+        }
+        _ => None,
+    })
+}
+
+fn decrypt_content(content: &EncryptedContentInfo, key: &[u8]) -> Option<Vec<u8>> {
+    let iv: OctetString = content.content_enc_alg.parameters.as_ref()?.decode_as().ok()?;
+    let iv = iv.as_bytes();
+    let data = content.encrypted_content.as_ref()?.as_bytes();
+    let algorithm = content.content_enc_alg.oid;
+    if algorithm == AES128_CBC {
+        cbc::Decryptor::<Aes128>::new_from_slices(key, iv)
+            .ok()?
+            .decrypt_padded_vec_mut::<Pkcs7>(data)
+            .ok()
+    } else if algorithm == AES192_CBC {
+        cbc::Decryptor::<Aes192>::new_from_slices(key, iv)
+            .ok()?
+            .decrypt_padded_vec_mut::<Pkcs7>(data)
+            .ok()
+    } else if algorithm == AES256_CBC {
+        cbc::Decryptor::<Aes256>::new_from_slices(key, iv)
+            .ok()?
+            .decrypt_padded_vec_mut::<Pkcs7>(data)
+            .ok()
+    } else if algorithm == DES_EDE3_CBC {
+        cbc::Decryptor::<TdesEde3>::new_from_slices(key, iv)
+            .ok()?
+            .decrypt_padded_vec_mut::<Pkcs7>(data)
+            .ok()
+    } else {
+        None
+    }
+}
+
+/// Seal content for the RSA public key of a certificate as enveloped data, encrypted with AES-256.
+fn seal_envelope(content: &[u8], certificate: &[u8]) -> Result<Vec<u8>> {
+    let invalid = |_| Error::Decryption(DecryptionError::InvalidCertificate);
+    let certificate = Certificate::from_der(certificate).map_err(invalid)?;
+    let tbs_certificate = certificate.tbs_certificate;
+    let public_key_info = tbs_certificate.subject_public_key_info.to_der().map_err(invalid)?;
+    let public_key = RsaPublicKey::from_public_key_der(&public_key_info)
+        .map_err(|_| Error::Decryption(DecryptionError::InvalidCertificate))?;
+
+    let key: [u8; 32] = rand::random();
+    let iv: [u8; 16] = rand::random();
+    let encrypted_content = cbc::Encryptor::<Aes256>::new_from_slices(&key, &iv)
+        .expect("key and iv have a valid length")
+        .encrypt_padded_vec_mut::<Pkcs7>(content);
+    let encrypted_key = public_key
+        .encrypt(&mut rand::thread_rng(), Pkcs1v15Encrypt, &key)
+        .map_err(|_| Error::Decryption(DecryptionError::InvalidCertificate))?;
+
+    let recipient = KeyTransRecipientInfo {
+        version: CmsVersion::V0,
+        rid: RecipientIdentifier::IssuerAndSerialNumber(IssuerAndSerialNumber {
+            issuer: tbs_certificate.issuer,
+            serial_number: tbs_certificate.serial_number,
+        }),
+        key_enc_alg: AlgorithmIdentifierOwned {
+            oid: RSA_ENCRYPTION,
+            parameters: Some(Any::null()),
+        },
+        enc_key: OctetString::new(encrypted_key).map_err(invalid)?,
+    };
+    let enveloped = EnvelopedData {
+        version: CmsVersion::V0,
+        originator_info: None,
+        recip_infos: RecipientInfos::try_from(vec![RecipientInfo::Ktri(recipient)]).map_err(invalid)?,
+        encrypted_content: EncryptedContentInfo {
+            content_type: ID_DATA,
+            content_enc_alg: AlgorithmIdentifierOwned {
+                oid: AES256_CBC,
+                parameters: Some(Any::encode_from(&OctetString::new(iv.to_vec()).map_err(invalid)?).map_err(invalid)?),
+            },
+            encrypted_content: Some(OctetString::new(encrypted_content).map_err(invalid)?),
+        },
+        unprotected_attrs: None,
+    };
+    ContentInfo {
+        content_type: ID_ENVELOPED_DATA,
+        content: Any::encode_from(&enveloped).map_err(invalid)?,
+    }
+    .to_der()
+    .map_err(invalid)
+}
+
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+#[test]
+fn encrypt_for_recipients() {
+    use crate::test_support::create_document;
+    use crate::SaveOptions;
+
+    let certificate = std::fs::read("assets/recipient.cer").unwrap();
+    let private_key = std::fs::read("assets/recipient.key").unwrap();
+    for &algorithm in [
+        EncryptionAlgorithm::Rc4,
+        EncryptionAlgorithm::Aes128,
+        EncryptionAlgorithm::Aes256,
+    ]
+    .iter()
+    {
+        let options = SaveOptions {
+            public_key_encryption: Some(PublicKeyEncryptionOptions {
+                recipients: vec![Recipient {
+                    certificate: certificate.clone(),
+                    permissions: Permissions::PRINT,
+                }],
+                algorithm,
+            }),
+            ..SaveOptions::default()
+        };
+        let mut buffer = Vec::new();
+        create_document().save_to_with_options(&mut buffer, &options).unwrap();
+        assert!(!buffer.windows(12).any(|window| window == b"Hello World!"));
+
+        let mut doc = Document::load_mem(&buffer).unwrap();
+        assert!(doc.is_encrypted());
+        assert!(matches!(
+            doc.clone().decrypt("").unwrap_err().without_context(),
+            Error::Decryption(DecryptionError::UnsupportedEncryption)
+        ));
+        assert!(doc.clone().decrypt_with_certificate(&certificate, b"invalid").is_err());
+        doc.decrypt_with_certificate(&certificate, &private_key).unwrap();
+        assert!(!doc.is_encrypted());
+        assert_eq!(doc.extract_text(&[1]).unwrap(), "Hello World!\n");
+    }
+}
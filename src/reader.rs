@@ -4,7 +4,7 @@ use log::{error, warn};
 use std::cmp;
 use std::convert::TryInto;
 use std::fs::File;
-use std::io::Read;
+use std::io::{Read, Seek, SeekFrom};
 use std::path::Path;
 use std::sync::Mutex;
 
@@ -13,40 +13,290 @@ use rayon::prelude::*;
 
 use super::parser;
 use super::{Document, Object, ObjectId};
-use crate::error::XrefError;
+use crate::error::{ErrorContext, LimitError, XrefError};
+use crate::incremental::{LoadedState, Revision};
 use crate::object_stream::ObjectStream;
-use crate::xref::XrefEntry;
-use crate::{Error, Result};
+use crate::xref::{Xref, XrefEntry};
+use crate::{Dictionary, Error, LoadWarning, LoadWarningKind, Result, Stream};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Limits on the resources used to load a document, so that malicious files can't exhaust the stack, memory or
+/// time of the process. Loading fails with `Error::Limit` when a limit is exceeded.
+#[derive(Debug, Clone, Copy)]
+pub struct LoadOptions {
+    /// Maximum nesting of arrays and dictionaries in an object, which are parsed recursively.
+    pub max_depth: usize,
+    /// Maximum number of objects, including the objects of object streams.
+    pub max_objects: usize,
+    /// Maximum length of the data of a stream, and of the decompressed data of object streams, in bytes.
+    pub max_stream_length: usize,
+    /// Maximum number of cross-reference sections, which are chained by the /Prev entries of their trailers.
+    pub max_xref_chain: usize,
+}
+
+impl Default for LoadOptions {
+    fn default() -> Self {
+        LoadOptions {
+            max_depth: 256,
+            // Limit on the number of indirect objects given by the PDF specification.
+            max_objects: 8_388_607,
+            max_stream_length: usize::MAX,
+            max_xref_chain: 1024,
+        }
+    }
+}
 
 impl Document {
     /// Load a PDF document from a specified file path.
     #[inline]
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Document> {
+        Self::load_with_options(path, &LoadOptions::default())
+    }
+
+    /// Load a PDF document from a specified file path with limits on the resources used.
+    pub fn load_with_options<P: AsRef<Path>>(path: P, options: &LoadOptions) -> Result<Document> {
         let file = File::open(path)?;
         let capacity = Some(file.metadata()?.len() as usize);
-        Self::load_internal(file, capacity)
+        Self::load_internal(file, capacity, options)
     }
 
     /// Load a PDF document from an arbitrary source.
     #[inline]
     pub fn load_from<R: Read>(source: R) -> Result<Document> {
-        Self::load_internal(source, None)
+        Self::load_internal(source, None, &LoadOptions::default())
     }
 
-    fn load_internal<R: Read>(mut source: R, capacity: Option<usize>) -> Result<Document> {
+    /// Load a PDF document from a seekable source, starting at its current position, such as a file inside an
+    /// archive or a cursor over a buffer. Its length is known in advance, so it's read in a single allocation.
+    pub fn load_from_seekable<R: Read + Seek>(mut source: R) -> Result<Document> {
+        let start = source.stream_position()?;
+        let end = source.seek(SeekFrom::End(0))?;
+        source.seek(SeekFrom::Start(start))?;
+        Self::load_internal(
+            source,
+            Some(end.saturating_sub(start) as usize),
+            &LoadOptions::default(),
+        )
+    }
+
+    fn load_internal<R: Read>(mut source: R, capacity: Option<usize>, options: &LoadOptions) -> Result<Document> {
         let mut buffer = capacity.map(Vec::with_capacity).unwrap_or_else(Vec::new);
         source.read_to_end(&mut buffer)?;
+        Self::load_mem_with_options(&buffer, options)
+    }
+
+    /// Load a PDF document from an asynchronous source, which is read to its end into memory before it's parsed.
+    #[cfg(feature = "async")]
+    pub async fn load_async<R: tokio::io::AsyncRead + Unpin>(mut source: R) -> Result<Document> {
+        use tokio::io::AsyncReadExt;
+
+        let mut buffer = Vec::new();
+        source.read_to_end(&mut buffer).await?;
+        Self::load_mem(&buffer)
+    }
+
+    /// Load a PDF document from a memory-mapped file, so that the file isn't read into a buffer before parsing.
+    ///
+    /// The objects of the document own their data, so stream data is still copied from the map. To borrow
+    /// stream data from the map instead, open the file with `LazyDocument::open_mmap` and read streams with
+    /// `LazyDocument::get_mapped_stream`. The file must not be modified while it's loaded.
+    #[cfg(feature = "mmap")]
+    pub fn load_mmap<P: AsRef<Path>>(path: P) -> Result<Document> {
+        let file = File::open(path)?;
+        // Safety: the map is only read during loading, and nothing keeps references into it afterwards.
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+        Self::load_mem(&map)
+    }
+
+    /// Load a PDF document from a memory slice.
+    pub fn load_mem(buffer: &[u8]) -> Result<Document> {
+        buffer.try_into()
+    }
 
+    /// Load a PDF document from a memory slice with limits on the resources used.
+    pub fn load_mem_with_options(buffer: &[u8], options: &LoadOptions) -> Result<Document> {
         Reader {
-            buffer: &buffer,
+            buffer,
             document: Document::new(),
+            options: *options,
         }
         .read()
     }
 
-    /// Load a PDF document from a memory slice.
-    pub fn load_mem(buffer: &[u8]) -> Result<Document> {
-        buffer.try_into()
+    /// Load a PDF document from a specified file path, recovering it if it's damaged.
+    pub fn load_with_recovery<P: AsRef<Path>>(path: P) -> Result<Document> {
+        let buffer = std::fs::read(path)?;
+        Self::load_mem_with_recovery(&buffer)
+    }
+
+    /// Load a PDF document from a memory slice, recovering it if its cross-reference table or trailer is broken
+    /// or missing, or if objects can't be read at the offsets it gives.
+    ///
+    /// Recovery scans the whole file for `obj ... endobj` patterns, keeping the last object found for each object
+    /// number, and reads streams up to `endstream` if their length is wrong. The trailer is rebuilt from the
+    /// trailers and cross-reference streams found, and the catalog and page tree are recreated from the pages
+    /// found if needed. Recovered documents can't be saved incrementally.
+    pub fn load_mem_with_recovery(buffer: &[u8]) -> Result<Document> {
+        let reader = Reader {
+            buffer,
+            document: Document::new(),
+            options: LoadOptions::default(),
+        };
+        let message = match reader.read() {
+            Ok(document) if !document.is_damaged() => return Ok(document),
+            Ok(_) => "objects of the document are missing".to_string(),
+            Err(err) => err.to_string(),
+        };
+        warn!("Could not load the document ({}), recovering it", message);
+        let mut document = Reader {
+            buffer,
+            document: Document::new(),
+            options: LoadOptions::default(),
+        }
+        .recover()?;
+        document.load_warnings.insert(
+            0,
+            LoadWarning {
+                kind: LoadWarningKind::Recovered,
+                offset: None,
+                object_id: None,
+                message,
+            },
+        );
+        Ok(document)
+    }
+
+    /// Check whether the catalog or objects listed in the cross-reference table are missing.
+    fn is_damaged(&self) -> bool {
+        self.catalog().is_err()
+            || self
+                .reference_table
+                .entries
+                .iter()
+                .any(|(&number, entry)| match *entry {
+                    XrefEntry::Normal { generation, .. } => !self.objects.contains_key(&(number, generation)),
+                    _ => false,
+                })
+    }
+
+    /// Make the page tree list the pages found if it has no pages, creating a catalog if needed.
+    fn recover_page_tree(&mut self) {
+        let has_catalog = self.catalog().is_ok();
+        if has_catalog && self.page_iter().next().is_some() {
+            return;
+        }
+        let is_type = |object: &Object, type_name: &[u8]| object.as_dict().is_ok_and(|dict| dict.type_is(type_name));
+        let root_id = self
+            .objects
+            .iter()
+            .rev()
+            .find(|(_, object)| is_type(object, b"Pages") && !object.as_dict().is_ok_and(|dict| dict.has(b"Parent")))
+            .map(|(&id, _)| id);
+        let catalog_id = match self.trailer.get(b"Root").and_then(Object::as_reference) {
+            Ok(catalog_id) if has_catalog => catalog_id,
+            _ => {
+                let catalog_id = self.add_object(dictionary! { "Type" => "Catalog" });
+                self.trailer.set("Root", catalog_id);
+                catalog_id
+            }
+        };
+        if let Some(root_id) = root_id {
+            if let Ok(catalog) = self.get_object_mut(catalog_id).and_then(Object::as_dict_mut) {
+                catalog.set("Pages", root_id);
+            }
+            if self.page_iter().next().is_some() {
+                return;
+            }
+        }
+
+        // Pages keep the attributes they inherited, as they get a new parent.
+        let page_ids: Vec<ObjectId> = self
+            .objects
+            .iter()
+            .filter(|(_, object)| is_type(object, b"Page"))
+            .map(|(&id, _)| id)
+            .collect();
+        let pages_id = self.new_object_id();
+        for &page_id in &page_ids {
+            let inherited: Vec<(&[u8], Object)> = [b"Resources".as_ref(), b"MediaBox", b"CropBox", b"Rotate"]
+                .iter()
+                .filter_map(|&key| Some((key, self.get_page_attribute(page_id, key)?.clone())))
+                .collect();
+            if let Ok(page) = self.get_object_mut(page_id).and_then(Object::as_dict_mut) {
+                for (key, value) in inherited {
+                    if !page.has(key) {
+                        page.set(key.to_vec(), value);
+                    }
+                }
+                page.set("Parent", pages_id);
+            }
+        }
+        let pages = dictionary! {
+            "Type" => "Pages",
+            "Kids" => page_ids.iter().map(|&id| id.into()).collect::<Vec<Object>>(),
+            "Count" => page_ids.len() as i64,
+        };
+        self.objects.insert(pages_id, Object::Dictionary(pages));
+        if let Ok(catalog) = self.get_object_mut(catalog_id).and_then(Object::as_dict_mut) {
+            catalog.set("Pages", pages_id);
+        }
+    }
+}
+
+/// Indirect object found by `Document::scan_objects`.
+#[derive(Debug, Clone)]
+pub struct ScannedObject {
+    pub id: ObjectId,
+    /// Byte offset of the object in the file, or of the object stream containing it.
+    pub offset: usize,
+    /// Object stream containing the object, for objects of object streams.
+    pub container: Option<ObjectId>,
+    pub object: Object,
+}
+
+impl Document {
+    /// Find the indirect objects of a file by scanning all of its bytes for `obj ... endobj` patterns, regardless
+    /// of its cross-reference table, such as to carve objects which were deleted or replaced by incremental
+    /// updates.
+    ///
+    /// All versions of objects are returned in file order, with the objects of object streams following their
+    /// stream. Objects are returned as they are in the file, without decrypting them.
+    pub fn scan_objects<R: Read>(mut source: R) -> Result<Vec<ScannedObject>> {
+        let mut buffer = Vec::new();
+        source.read_to_end(&mut buffer)?;
+        let mut reader = Reader {
+            buffer: &buffer,
+            document: Document::new(),
+            options: LoadOptions::default(),
+        };
+        let mut scanned = Vec::new();
+        for (id, offset, mut object) in reader.scan() {
+            let mut compressed = Vec::new();
+            if let Object::Stream(ref mut stream) = object {
+                if let Some(data) = reader.unread_stream_data(stream) {
+                    stream.set_content(data);
+                    stream.start_position = None;
+                }
+                if stream.dict.type_is(b"ObjStm") {
+                    if let Ok(object_stream) = ObjectStream::new(&mut stream.clone()) {
+                        compressed.extend(object_stream.objects);
+                    }
+                }
+            }
+            scanned.push(ScannedObject {
+                id,
+                offset,
+                container: None,
+                object,
+            });
+            scanned.extend(compressed.into_iter().map(|(compressed_id, object)| ScannedObject {
+                id: compressed_id,
+                offset,
+                container: Some(id),
+                object,
+            }));
+        }
+        Ok(scanned)
     }
 }
 
@@ -57,6 +307,7 @@ impl TryInto<Document> for &[u8] {
         Reader {
             buffer: self,
             document: Document::new(),
+            options: LoadOptions::default(),
         }
         .read()
     }
@@ -65,12 +316,22 @@ impl TryInto<Document> for &[u8] {
 pub struct Reader<'a> {
     buffer: &'a [u8],
     document: Document,
+    options: LoadOptions,
 }
 
 /// Maximum allowed embedding of literal strings.
 pub const MAX_BRACKET: usize = 100;
 
 impl<'a> Reader<'a> {
+    /// Create a reader of a part of a file, which reads streams with indirect lengths without their content.
+    pub(crate) fn new(buffer: &'a [u8]) -> Reader<'a> {
+        Reader {
+            buffer,
+            document: Document::new(),
+            options: LoadOptions::default(),
+        }
+    }
+
     /// Read whole document.
     fn read(mut self) -> Result<Document> {
         // The document structure can be expressed in PEG as:
@@ -82,63 +343,115 @@ impl<'a> Reader<'a> {
             return Err(Error::Xref(XrefError::Start));
         }
 
-        let (mut xref, mut trailer) = parser::xref_and_trailer(&self.buffer[xref_start..], &self)?;
+        let (mut xref, mut trailer) = self.read_xref_section(xref_start)?;
+        // Cross-reference sections with their offset and trailer, from the last one.
+        let mut sections = vec![(xref_start, xref.clone(), trailer.clone())];
+        trailer.remove(b"XRefStm");
 
         // Read previous Xrefs of linearized or incremental updated document.
         let mut prev_xref_start = trailer.remove(b"Prev");
         while let Some(prev) = prev_xref_start.and_then(|offset| offset.as_i64().ok()) {
+            if sections.len() >= self.options.max_xref_chain {
+                return Err(Error::Limit(LimitError::XrefChain));
+            }
             let prev = prev as usize;
             if prev > self.buffer.len() {
                 return Err(Error::Xref(XrefError::PrevStart));
             }
-            let (prev_xref, mut prev_trailer) = parser::xref_and_trailer(&self.buffer[prev..], &self)?;
+            let (prev_xref, mut prev_trailer) = self.read_xref_section(prev)?;
+            sections.push((prev, prev_xref.clone(), prev_trailer.clone()));
             xref.extend(prev_xref);
-
-            // Read xref stream in hybrid-reference file
-            let prev_xref_stream_start = trailer.remove(b"XRefStm");
-            if let Some(prev) = prev_xref_stream_start.and_then(|offset| offset.as_i64().ok()) {
-                let prev = prev as usize;
-                if prev > self.buffer.len() {
-                    return Err(Error::Xref(XrefError::StreamStart));
-                }
-                let (prev_xref, _) = parser::xref_and_trailer(&self.buffer[prev..], &self)?;
-                xref.extend(prev_xref);
-            }
-
             prev_xref_start = prev_trailer.remove(b"Prev");
         }
 
+        let warnings = Mutex::new(vec![]);
         let xref_entry_count = xref.max_id() + 1;
         if xref.size != xref_entry_count {
-            warn!(
+            let message = format!(
                 "Size entry of trailer dictionary is {}, correct value is {}.",
                 xref.size, xref_entry_count
             );
+            warn!("{}", message);
+            warnings.lock().unwrap().push(LoadWarning {
+                kind: LoadWarningKind::XrefSize,
+                offset: Some(xref_start),
+                object_id: None,
+                message,
+            });
             xref.size = xref_entry_count;
         }
 
+        if xref.entries.len() > self.options.max_objects {
+            return Err(Error::Limit(LimitError::ObjectCount));
+        }
+
         self.document.version = version;
         self.document.max_id = xref.size - 1;
+        self.document.xref_start = xref_start;
         self.document.trailer = trailer;
         self.document.reference_table = xref;
 
         let zero_length_streams = Mutex::new(vec![]);
         let object_streams = Mutex::new(vec![]);
+        let limit_error = Mutex::new(None);
+        // Object streams of encrypted documents can only be read after decryption.
+        let is_encrypted = self.document.is_encrypted();
 
-        let entries_filter_map = |(_, entry): (&_, &_)| {
-            if let XrefEntry::Normal { offset, .. } = *entry {
+        let warn = |kind, offset: usize, object_id: ObjectId, message: String| {
+            warnings.lock().unwrap().push(LoadWarning {
+                kind,
+                offset: Some(offset),
+                object_id: Some(object_id),
+                message,
+            });
+        };
+        let entries_filter_map = |(&number, entry): (&u32, &_)| {
+            if let XrefEntry::Normal { offset, generation } = *entry {
                 let (object_id, mut object) = self
                     .read_object(offset as usize, None)
-                    .map_err(|e| error!("Object load error: {:?}", e))
+                    .map_err(|e| match e.without_context() {
+                        Error::Limit(_) => *limit_error.lock().unwrap() = Some(e),
+                        _ => {
+                            error!("Object load error: {:?}", e);
+                            warn(
+                                LoadWarningKind::UnreadableObject,
+                                offset as usize,
+                                (number, generation),
+                                e.to_string(),
+                            );
+                        }
+                    })
                     .ok()?;
+                if object_id != (number, generation) {
+                    let message = format!(
+                        "Object {} {} is listed as {} {}",
+                        object_id.0, object_id.1, number, generation
+                    );
+                    warn(LoadWarningKind::ObjectIdMismatch, offset as usize, object_id, message);
+                }
                 if let Ok(ref mut stream) = object.as_stream_mut() {
-                    if stream.dict.type_is(b"ObjStm") {
-                        let obj_stream = ObjectStream::new(stream).ok()?;
+                    if stream.dict.type_is(b"ObjStm") && !is_encrypted {
+                        if let Err(e) = self.check_object_stream(stream) {
+                            *limit_error.lock().unwrap() = Some(e.with_context(ErrorContext::in_object(object_id)));
+                            return None;
+                        }
+                        let obj_stream = match ObjectStream::new(stream) {
+                            Ok(obj_stream) => obj_stream,
+                            Err(e) => {
+                                warn(
+                                    LoadWarningKind::UnreadableObjectStream,
+                                    offset as usize,
+                                    object_id,
+                                    e.to_string(),
+                                );
+                                return None;
+                            }
+                        };
                         let mut object_streams = object_streams.lock().unwrap();
                         object_streams.extend(obj_stream.objects);
                     } else if stream.content.is_empty() {
                         let mut zero_length_streams = zero_length_streams.lock().unwrap();
-                        zero_length_streams.push(object_id);
+                        zero_length_streams.push((object_id, offset as usize, stream.start_position.is_some()));
                     }
                 }
                 Some((object_id, object))
@@ -166,17 +479,405 @@ impl<'a> Reader<'a> {
                 .filter_map(entries_filter_map)
                 .collect();
         }
+        if let Some(e) = limit_error.into_inner().unwrap() {
+            return Err(e);
+        }
         self.document.objects.extend(object_streams.into_inner().unwrap());
+        if self.document.objects.len() > self.options.max_objects {
+            return Err(Error::Limit(LimitError::ObjectCount));
+        }
+
+        let mut warnings = warnings.into_inner().unwrap();
+        for (object_id, offset, is_unread) in zero_length_streams.into_inner().unwrap() {
+            match self.set_stream_content(object_id) {
+                Err(e) if matches!(e.without_context(), Error::Limit(_)) => return Err(e),
+                Err(e) if is_unread => warnings.push(LoadWarning {
+                    kind: LoadWarningKind::StreamLength,
+                    offset: Some(offset),
+                    object_id: Some(object_id),
+                    message: e.to_string(),
+                }),
+                _ => {}
+            }
+        }
+        warnings.sort_by_key(|warning| warning.offset);
+        self.document.load_warnings = warnings;
+        self.document.revisions = self.read_revisions(sections);
+
+        if is_encrypted {
+            // Documents without a user password open with an empty one. Other documents are left encrypted,
+            // so they can be decrypted with the password later on, as are all documents without the `crypto`
+            // feature.
+            #[cfg(feature = "crypto")]
+            if let Err(err) = self.document.decrypt("") {
+                warn!("Could not decrypt the document: {}", err);
+            }
+        } else {
+            self.document.loaded_state = Some(LoadedState::new(&self.document, self.buffer.len()));
+        }
 
-        for object_id in zero_length_streams.into_inner().unwrap() {
-            let _ = self.set_stream_content(object_id);
+        Ok(self.document)
+    }
+
+    /// Read the objects found by scanning the whole file for `obj ... endobj` patterns, in file order, with their
+    /// offsets. Patterns in stream data are skipped.
+    fn scan(&mut self) -> Vec<(ObjectId, usize, Object)> {
+        let candidates = Self::object_headers(self.buffer);
+        // Indirect stream lengths are read through the cross-reference table.
+        for &((number, generation), offset) in &candidates {
+            let entry = XrefEntry::Normal {
+                offset: offset as u32,
+                generation,
+            };
+            self.document.reference_table.insert(number, entry);
+        }
+
+        let mut objects = Vec::new();
+        let mut stream_end = 0;
+        for (id, offset) in candidates {
+            if offset < stream_end {
+                continue;
+            }
+            let object = match self
+                .read_object(offset, Some(id))
+                .map(|(_, object)| object)
+                .or_else(|_| self.read_stream_object(offset))
+            {
+                Ok(object) => object,
+                Err(_) => continue,
+            };
+            if let Object::Stream(_) = object {
+                if let Some(end) = Self::find(self.buffer, b"endstream", offset) {
+                    stream_end = end;
+                }
+            }
+            objects.push((id, offset, object));
+        }
+        objects
+    }
+
+    /// Rebuild the document from the objects found by scanning the whole file.
+    fn recover(mut self) -> Result<Document> {
+        let version = Self::find(self.buffer, b"%PDF-", 0)
+            .and_then(|start| parser::header(&self.buffer[start..]))
+            .unwrap_or_else(|| "1.4".to_string());
+
+        // Later objects replace earlier ones, as in incremental updates.
+        let mut xref_streams = Vec::new();
+        for ((number, generation), offset, object) in self.scan() {
+            if let Object::Stream(ref stream) = object {
+                if stream.dict.type_is(b"XRef") {
+                    xref_streams.push((offset, stream.dict.clone()));
+                }
+            }
+            let entry = XrefEntry::Normal {
+                offset: offset as u32,
+                generation,
+            };
+            self.document.reference_table.insert(number, entry);
+            let versions: Vec<ObjectId> = self
+                .document
+                .objects
+                .range((number, 0)..=(number, u16::MAX))
+                .map(|(&id, _)| id)
+                .collect();
+            for id in versions {
+                self.document.objects.remove(&id);
+            }
+            self.document.objects.insert((number, generation), object);
+        }
+
+        // Trailer entries are taken from the trailers and cross-reference streams in file order.
+        let mut trailers = xref_streams;
+        let mut start = 0;
+        while let Some(position) = Self::find(self.buffer, b"trailer", start) {
+            start = position + 1;
+            let dict = Self::skip_whitespace(self.buffer, position + b"trailer".len())
+                .and_then(|start| parser::direct_object(&self.buffer[start..]))
+                .and_then(|dict| dict.as_dict().ok().cloned());
+            if let Some(dict) = dict {
+                trailers.push((position, dict));
+            }
+        }
+        trailers.sort_by_key(|(position, _)| *position);
+        let mut trailer = Dictionary::new();
+        for (_, dict) in trailers {
+            for key in [b"Root".as_ref(), b"Info", b"ID", b"Encrypt"] {
+                if let Ok(value) = dict.get(key) {
+                    trailer.set(key.to_vec(), value.clone());
+                }
+            }
+        }
+        let is_encrypted = trailer.has(b"Encrypt");
+        let mut compressed = Vec::new();
+        let mut unread_streams = Vec::new();
+        for (&id, object) in self.document.objects.iter_mut() {
+            if let Object::Stream(ref mut stream) = *object {
+                if stream.dict.type_is(b"ObjStm") && !is_encrypted {
+                    if let Ok(object_stream) = ObjectStream::new(stream) {
+                        compressed.extend(object_stream.objects);
+                    }
+                } else if stream.start_position.is_some() {
+                    unread_streams.push(id);
+                }
+            }
+        }
+        for (id, object) in compressed {
+            self.document.objects.entry(id).or_insert(object);
+        }
+        for id in unread_streams {
+            if self.set_stream_content(id).is_err() {
+                let data = self.document.objects.get(&id).and_then(|object| match object {
+                    Object::Stream(stream) => self.stream_data(stream.start_position?),
+                    _ => None,
+                });
+                if let (Some(data), Ok(stream)) =
+                    (data, self.document.get_object_mut(id).and_then(Object::as_stream_mut))
+                {
+                    stream.set_content(data.to_vec());
+                }
+            }
+        }
+
+        let is_catalog = |id: ObjectId| {
+            self.document
+                .get_dictionary(id)
+                .is_ok_and(|catalog| catalog.type_is(b"Catalog"))
+        };
+        if !trailer
+            .get(b"Root")
+            .and_then(Object::as_reference)
+            .is_ok_and(is_catalog)
+        {
+            trailer.remove(b"Root");
+            if let Some(&catalog_id) = self.document.objects.keys().rev().find(|&&id| is_catalog(id)) {
+                trailer.set("Root", catalog_id);
+            }
         }
 
+        self.document.version = version;
+        self.document.trailer = trailer;
+        self.document.max_id = self.document.objects.keys().map(|id| id.0).max().unwrap_or(0);
+        self.document.reference_table.size = self.document.max_id + 1;
+        self.document.recover_page_tree();
+
+        #[cfg(feature = "crypto")]
+        if self.document.is_encrypted() {
+            if let Err(err) = self.document.decrypt("") {
+                warn!("Could not decrypt the document: {}", err);
+            }
+        }
         Ok(self.document)
     }
 
+    /// Get the revisions of the file from its cross-reference sections, given from the last one, reading the
+    /// objects which each revision replaces as they were before.
+    fn read_revisions(&self, mut sections: Vec<(usize, Xref, Dictionary)>) -> Vec<Revision> {
+        sections.reverse();
+        // Sections preceding an earlier one in the file are merged into it, such as the section for the first
+        // page of linearized files, which the main section follows.
+        let mut merged: Vec<(usize, usize, Xref, Dictionary)> = Vec::new();
+        for (start, mut xref, trailer) in sections {
+            match merged.last_mut() {
+                Some(last) if start < last.0 => {
+                    xref.extend(std::mem::replace(&mut last.2, Xref::new(0)));
+                    *last = (last.0, start, xref, trailer);
+                }
+                _ => merged.push((start, start, xref, trailer)),
+            }
+        }
+
+        let mut revisions = Vec::new();
+        let mut visible: BTreeMap<u32, XrefEntry> = BTreeMap::new();
+        let mut revision_start = 0;
+        let count = merged.len();
+        for (index, (end_of_sections, xref_start, xref, trailer)) in merged.into_iter().enumerate() {
+            // Revisions end after their end-of-file marker, and the last one at the end of the file.
+            let end = match Self::find(self.buffer, b"%%EOF", end_of_sections) {
+                Some(end) if index + 1 < count => end + 5,
+                _ => self.buffer.len(),
+            };
+            let mut revision = Revision {
+                byte_range: revision_start..end,
+                xref_start,
+                trailer,
+                added: BTreeSet::new(),
+                changed: BTreeSet::new(),
+                deleted: BTreeSet::new(),
+                replaced: BTreeMap::new(),
+            };
+            for (&id, entry) in &xref.entries {
+                if id == 0 {
+                    continue;
+                }
+                let previous = visible.get(&id).map(|previous| (id, entry_generation(previous)));
+                match (entry, previous) {
+                    (XrefEntry::Free, Some(previous)) => {
+                        revision.deleted.insert(previous);
+                    }
+                    (XrefEntry::Free, None) => continue,
+                    (_, Some(_)) => {
+                        revision.changed.insert((id, entry_generation(entry)));
+                    }
+                    (_, None) => {
+                        revision.added.insert((id, entry_generation(entry)));
+                    }
+                }
+                if let Some(previous) = previous {
+                    if let Some(object) = self.read_previous_object(&visible, previous) {
+                        revision.replaced.insert(previous, object);
+                    }
+                }
+            }
+            for (&id, entry) in &xref.entries {
+                match *entry {
+                    XrefEntry::Free => visible.remove(&id),
+                    _ => visible.insert(id, entry.clone()),
+                };
+            }
+            revision_start = end;
+            revisions.push(revision);
+        }
+        revisions
+    }
+
+    /// Read the cross-reference section starting at an offset, with its trailer.
+    ///
+    /// In hybrid-reference files the trailer of a cross-reference table refers to a cross-reference stream by
+    /// /XRefStm, whose entries take precedence over those of the table.
+    fn read_xref_section(&self, start: usize) -> Result<(Xref, Dictionary)> {
+        let (mut xref, trailer) = parser::xref_and_trailer(&self.buffer[start..], self)
+            .map_err(|e| e.with_context(ErrorContext::at_offset(start)))?;
+        let stream_start = match trailer.get(b"XRefStm").and_then(Object::as_i64) {
+            Ok(stream_start) if !trailer.type_is(b"XRef") => stream_start as usize,
+            _ => return Ok((xref, trailer)),
+        };
+        if stream_start > self.buffer.len() {
+            return Err(Error::Xref(XrefError::StreamStart));
+        }
+        let (stream_xref, _) = parser::xref_and_trailer(&self.buffer[stream_start..], self)
+            .map_err(|e| e.with_context(ErrorContext::at_offset(stream_start)))?;
+        xref.merge_hybrid_stream(stream_xref);
+        Ok((xref, trailer))
+    }
+
+    /// Read an object as given by earlier cross-reference entries.
+    fn read_previous_object(&self, entries: &BTreeMap<u32, XrefEntry>, id: ObjectId) -> Option<Object> {
+        match *entries.get(&id.0)? {
+            XrefEntry::Normal { offset, .. } => self
+                .read_object(offset as usize, Some(id))
+                .ok()
+                .map(|(_, object)| object),
+            XrefEntry::Compressed { container, .. } => {
+                let container = (container, 0);
+                let mut container = self.read_previous_object(entries, container)?;
+                let mut objects = ObjectStream::new(container.as_stream_mut().ok()?).ok()?.objects;
+                objects.remove(&id)
+            }
+            XrefEntry::Free => None,
+        }
+    }
+
+    /// Read a stream object up to the `endstream` keyword, for streams with a wrong length.
+    fn read_stream_object(&self, offset: usize) -> Result<Object> {
+        let header_end = Self::find(self.buffer, b"obj", offset).ok_or(Error::Parse { offset })? + 3;
+        let dict = Self::skip_whitespace(self.buffer, header_end)
+            .and_then(|start| parser::direct_object(&self.buffer[start..]))
+            .and_then(|dict| dict.as_dict().ok().cloned())
+            .ok_or(Error::Parse { offset })?;
+        let mut start = Self::find(self.buffer, b"stream", header_end).ok_or(Error::Parse { offset })? + 6;
+        if self.buffer.get(start) == Some(&b'\r') {
+            start += 1;
+        }
+        if self.buffer.get(start) == Some(&b'\n') {
+            start += 1;
+        }
+        let data = self.stream_data(start).ok_or(Error::Parse { offset })?;
+        Ok(Object::Stream(Stream::new(dict, data.to_vec())))
+    }
+
+    /// Read the data of a stream which wasn't read with the object, as its length is an indirect object, or up to
+    /// the `endstream` keyword if the length is wrong.
+    fn unread_stream_data(&self, stream: &Stream) -> Option<Vec<u8>> {
+        let start = stream.start_position?;
+        let length = stream
+            .dict
+            .get(b"Length")
+            .and_then(Object::as_reference)
+            .and_then(|id| self.get_object(id))
+            .and_then(|length| length.as_i64());
+        let data = match length {
+            Ok(length) if length >= 0 => self.buffer.get(start..start.checked_add(length as usize)?),
+            _ => None,
+        };
+        data.or_else(|| self.stream_data(start)).map(<[u8]>::to_vec)
+    }
+
+    /// Get the data of a stream from its start up to the end of line before the `endstream` keyword.
+    fn stream_data(&self, start: usize) -> Option<&'a [u8]> {
+        let end = Self::find(self.buffer, b"endstream", start)?;
+        let mut data = &self.buffer[start..end];
+        for eol in [b'\n', b'\r'] {
+            if data.last() == Some(&eol) {
+                data = &data[..data.len() - 1];
+            }
+        }
+        Some(data)
+    }
+
+    /// Find the first occurrence of a pattern from a position.
+    pub(crate) fn find(buffer: &[u8], pattern: &[u8], start: usize) -> Option<usize> {
+        buffer
+            .get(start..)?
+            .windows(pattern.len())
+            .position(|window| window == pattern)
+            .map(|position| start + position)
+    }
+
+    /// Find the `number generation obj` headers of indirect objects, with their offsets.
+    fn object_headers(buffer: &[u8]) -> Vec<(ObjectId, usize)> {
+        let is_space = |c: u8| c.is_ascii_whitespace() || c == 0;
+        // Walk back over a run of bytes, returning where it starts.
+        let skip_back = |end: usize, matches: &dyn Fn(u8) -> bool| {
+            let start = buffer[..end]
+                .iter()
+                .rposition(|&c| !matches(c))
+                .map_or(0, |index| index + 1);
+            Some(start).filter(|&start| start < end)
+        };
+
+        let mut headers = Vec::new();
+        let mut position = 0;
+        while let Some(keyword) = Self::find(buffer, b"obj", position) {
+            position = keyword + 3;
+            // The keyword must end at a word boundary.
+            if buffer
+                .get(position)
+                .is_some_and(|&c| c.is_ascii_alphanumeric() || c == b'_')
+            {
+                continue;
+            }
+            let header = || {
+                let generation_end = skip_back(keyword, &is_space)?;
+                let generation_start = skip_back(generation_end, &|c| c.is_ascii_digit())?;
+                let number_end = skip_back(generation_start, &is_space)?;
+                let number_start = skip_back(number_end, &|c| c.is_ascii_digit())?;
+                let number = std::str::from_utf8(&buffer[number_start..number_end]).ok()?;
+                let generation = std::str::from_utf8(&buffer[generation_start..generation_end]).ok()?;
+                Some(((number.parse().ok()?, generation.parse().ok()?), number_start))
+            };
+            headers.extend(header());
+        }
+        headers
+    }
+
+    pub(crate) fn skip_whitespace(buffer: &[u8], start: usize) -> Option<usize> {
+        (start..buffer.len()).find(|&index| !buffer[index].is_ascii_whitespace())
+    }
+
     fn set_stream_content(&mut self, object_id: ObjectId) -> Result<()> {
-        let length = self.get_stream_length(object_id)?;
+        let length_error = |e: Error| e.with_context(ErrorContext::in_object(object_id).with_key(b"Length"));
+        let length = self.get_stream_length(object_id).map_err(length_error)?;
         let stream = self
             .document
             .get_object_mut(object_id)
@@ -184,13 +885,18 @@ impl<'a> Reader<'a> {
         let start = stream.start_position.ok_or(Error::ObjectNotFound)?;
 
         if length < 0 {
-            return Err(Error::Syntax("Negative stream length.".to_string()));
+            return Err(length_error(Error::Syntax("Negative stream length.".to_string())));
         }
 
+        if length as u64 > self.options.max_stream_length as u64 {
+            return Err(length_error(Error::Limit(LimitError::StreamLength)));
+        }
         let end = start + length as usize;
 
         if end > self.buffer.len() {
-            return Err(Error::Syntax("Stream extends after document end.".to_string()));
+            return Err(length_error(Error::Syntax(
+                "Stream extends after document end.".to_string(),
+            )));
         }
 
         stream.set_content(self.buffer[start..end].to_vec());
@@ -236,10 +942,39 @@ impl<'a> Reader<'a> {
             return Err(Error::Offset(offset));
         }
 
-        parser::indirect_object(&self.buffer, offset, expected_id, self)
+        // Parse errors already give the offset.
+        let context = |e: Error| {
+            let offset = match e {
+                Error::Parse { .. } => None,
+                _ => Some(offset),
+            };
+            e.with_context(ErrorContext {
+                offset,
+                object_id: expected_id,
+                path: Vec::new(),
+            })
+        };
+        check_depth(&self.buffer[offset..], self.options.max_depth).map_err(context)?;
+        let (id, object) = parser::indirect_object(&self.buffer, offset, expected_id, self).map_err(context)?;
+        if let Object::Stream(ref stream) = object {
+            if stream.content.len() > self.options.max_stream_length {
+                let error = Error::Limit(LimitError::StreamLength);
+                return Err(error.with_context(ErrorContext::in_object(id).with_offset(offset)));
+            }
+        }
+        Ok((id, object))
+    }
+
+    /// Decompress an object stream, checking its length and the nesting of its objects before they're parsed.
+    fn check_object_stream(&self, stream: &mut Stream) -> Result<()> {
+        stream.decompress();
+        if stream.content.len() > self.options.max_stream_length {
+            return Err(Error::Limit(LimitError::StreamLength));
+        }
+        check_depth(&stream.content, self.options.max_depth)
     }
 
-    fn get_xref_start(buffer: &[u8]) -> Result<usize> {
+    pub(crate) fn get_xref_start(buffer: &[u8]) -> Result<usize> {
         let seek_pos = buffer.len() - cmp::min(buffer.len(), 512);
         Self::search_substring(buffer, b"%%EOF", seek_pos)
             .and_then(|eof_pos| if eof_pos > 25 { Some(eof_pos) } else { None })
@@ -280,6 +1015,68 @@ impl<'a> Reader<'a> {
     }
 }
 
+/// Generation of the object of a cross-reference entry, which is zero for objects in object streams.
+fn entry_generation(entry: &XrefEntry) -> u16 {
+    match *entry {
+        XrefEntry::Normal { generation, .. } => generation,
+        _ => 0,
+    }
+}
+
+/// Check that arrays and dictionaries aren't nested deeper than a limit, before they're parsed recursively. Objects
+/// are scanned up to their `stream` or `endobj` keyword, skipping strings and comments.
+fn check_depth(bytes: &[u8], max_depth: usize) -> Result<()> {
+    let mut depth = 0usize;
+    let mut index = 0;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'[' => depth += 1,
+            b']' => depth = depth.saturating_sub(1),
+            b'<' if bytes.get(index + 1) == Some(&b'<') => {
+                depth += 1;
+                index += 1;
+            }
+            b'>' if bytes.get(index + 1) == Some(&b'>') => {
+                depth = depth.saturating_sub(1);
+                index += 1;
+            }
+            b'(' => {
+                let mut level = 0;
+                while index < bytes.len() {
+                    match bytes[index] {
+                        b'\\' => index += 1,
+                        b'(' => level += 1,
+                        b')' => {
+                            level -= 1;
+                            if level == 0 {
+                                break;
+                            }
+                        }
+                        _ => {}
+                    }
+                    index += 1;
+                }
+            }
+            b'%' => {
+                while index < bytes.len() && !matches!(bytes[index], b'\r' | b'\n') {
+                    index += 1;
+                }
+            }
+            b's' | b'e'
+                if depth == 0 && (bytes[index..].starts_with(b"stream") || bytes[index..].starts_with(b"endobj")) =>
+            {
+                return Ok(());
+            }
+            _ => {}
+        }
+        if depth > max_depth {
+            return Err(Error::Limit(LimitError::Depth));
+        }
+        index += 1;
+    }
+    Ok(())
+}
+
 #[test]
 fn load_document() {
     let mut doc = Document::load("assets/example.pdf").unwrap();
@@ -287,6 +1084,34 @@ fn load_document() {
     doc.save("test_2_load.pdf").unwrap();
 }
 
+#[test]
+fn load_document_from_sources() {
+    use std::io::Cursor;
+
+    let buffer = std::fs::read("assets/example.pdf").unwrap();
+    let pages = Document::load_mem(&buffer).unwrap().get_pages();
+    // Documents can start after other data, as in archives.
+    let mut archive = b"archive header".to_vec();
+    archive.extend_from_slice(&buffer);
+    let mut source = Cursor::new(archive);
+    source.set_position(14);
+    assert_eq!(Document::load_from_seekable(source).unwrap().get_pages(), pages);
+    assert_eq!(Document::load_from(buffer.as_slice()).unwrap().get_pages(), pages);
+}
+
+#[cfg(feature = "async")]
+#[tokio::test(flavor = "current_thread")]
+async fn load_and_save_document_asynchronously() {
+    let buffer = std::fs::read("assets/example.pdf").unwrap();
+    let mut doc = Document::load_async(buffer.as_slice()).await.unwrap();
+    let mut saved = Vec::new();
+    doc.save_async(&mut saved, &crate::SaveOptions::default())
+        .await
+        .unwrap();
+    let loaded = Document::load_async(saved.as_slice()).await.unwrap();
+    assert_eq!(loaded.get_pages(), doc.get_pages());
+}
+
 #[test]
 #[should_panic(expected = "Xref(Start)")]
 fn load_short_document() {
@@ -393,3 +1218,230 @@ startxref
     let pages = doc.get_pages().keys().map(|r| *r).collect::<Vec<_>>();
     assert_eq!("Hello World!\n", doc.extract_text(&pages).unwrap());
 }
+
+#[test]
+fn load_damaged_document() {
+    use crate::test_support::create_document;
+
+    let mut buffer = Vec::new();
+    create_document().save_to(&mut buffer).unwrap();
+    let text = String::from_utf8(buffer).unwrap();
+
+    // Objects are moved by a comment and the stream has a wrong length, so the offsets are wrong.
+    let moved = text
+        .replacen("\n", "\n% Damaged by a text editor\n", 1)
+        .replace("/Length 45", "/Length 99");
+    let doc = Document::load_mem_with_recovery(moved.as_bytes()).unwrap();
+    assert_eq!(doc.trailer.get(b"Root").and_then(Object::as_reference).unwrap(), (7, 0));
+    assert_eq!(doc.trailer.get(b"Info").and_then(Object::as_reference).unwrap(), (1, 0));
+    assert_eq!(doc.extract_text(&[1]).unwrap(), "Hello World!\n");
+
+    // Without the cross-reference table, trailer and catalog, a catalog is created for the page tree.
+    let truncated = text[..text.find("xref").unwrap()].replace("/Catalog", "/Broken ");
+    assert!(Document::load_mem(truncated.as_bytes()).is_err());
+    let doc = Document::load_mem_with_recovery(truncated.as_bytes()).unwrap();
+    assert_eq!(
+        doc.catalog()
+            .unwrap()
+            .get(b"Pages")
+            .and_then(Object::as_reference)
+            .unwrap(),
+        (2, 0)
+    );
+    assert_eq!(doc.extract_text(&[1]).unwrap(), "Hello World!\n");
+}
+
+#[test]
+fn load_with_limits() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    let mut nested = Object::Array(vec![]);
+    for _ in 0..300 {
+        nested = Object::Array(vec![nested]);
+    }
+    let nested_id = doc.add_object(nested);
+    let mut buffer = Vec::new();
+    doc.save_to(&mut buffer).unwrap();
+    let is_limit = |result: Result<Document>, limit: LimitError| match result {
+        Err(error) => matches!(error.without_context(), Error::Limit(error) if *error == limit),
+        _ => false,
+    };
+    assert!(is_limit(Document::load_mem(&buffer), LimitError::Depth));
+    let options = LoadOptions {
+        max_depth: 400,
+        ..LoadOptions::default()
+    };
+    let loaded = Document::load_mem_with_options(&buffer, &options).unwrap();
+    assert!(loaded.get_object(nested_id).is_ok());
+
+    let mut buffer = Vec::new();
+    create_document().save_to(&mut buffer).unwrap();
+    let object_count = Document::load_mem(&buffer).unwrap().objects.len();
+    let options = LoadOptions {
+        max_objects: object_count - 1,
+        ..LoadOptions::default()
+    };
+    assert!(is_limit(
+        Document::load_mem_with_options(&buffer, &options),
+        LimitError::ObjectCount
+    ));
+    let options = LoadOptions {
+        max_stream_length: 44,
+        ..LoadOptions::default()
+    };
+    assert!(is_limit(
+        Document::load_mem_with_options(&buffer, &options),
+        LimitError::StreamLength
+    ));
+
+    // A trailer whose /Prev refers to its own cross-reference table would be read forever.
+    let text = String::from_utf8(buffer).unwrap();
+    let xref_start = text.rfind("xref").unwrap() - "start".len();
+    let xref_start = text[xref_start..].split_whitespace().nth(1).unwrap().to_owned();
+    let looped = text.replacen("trailer\n<<", &format!("trailer\n<</Prev {}", xref_start), 1);
+    assert!(is_limit(Document::load_mem(looped.as_bytes()), LimitError::XrefChain));
+}
+
+#[test]
+fn collect_load_warnings() {
+    use crate::test_support::create_document;
+
+    let mut buffer = Vec::new();
+    create_document().save_to(&mut buffer).unwrap();
+    assert!(Document::load_mem(&buffer).unwrap().load_warnings().is_empty());
+    let text = String::from_utf8(buffer).unwrap();
+
+    // The offsets stay the same, as the replacements are as long as the text they replace.
+    let damaged = text
+        .replace("3 0 obj<</Type/Font", "9 0 obj<</Type/Font")
+        .replace("/Length 45", "/Length 99")
+        .replace("/Size 8", "/Size 9");
+    let doc = Document::load_mem(damaged.as_bytes()).unwrap();
+    let warnings: Vec<(LoadWarningKind, Option<ObjectId>)> = doc
+        .load_warnings()
+        .iter()
+        .map(|warning| (warning.kind, warning.object_id))
+        .collect();
+    assert_eq!(
+        warnings,
+        vec![
+            (LoadWarningKind::ObjectIdMismatch, Some((9, 0))),
+            (LoadWarningKind::UnreadableObject, Some((5, 0))),
+            (LoadWarningKind::XrefSize, None),
+        ]
+    );
+    let xref_start = doc.load_warnings()[2].offset.unwrap();
+    assert!(damaged[xref_start..].starts_with("xref"));
+    assert!(doc.load_warnings()[0]
+        .to_string()
+        .starts_with("ObjectIdMismatch in object 9 0 at byte"));
+
+    let doc = Document::load_mem_with_recovery(damaged.as_bytes()).unwrap();
+    assert_eq!(doc.load_warnings()[0].kind, LoadWarningKind::Recovered);
+}
+
+#[test]
+fn errors_give_context() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    #[cfg(feature = "crypto")]
+    {
+        use crate::references::PathStep;
+
+        let encrypt_id = doc.add_object(dictionary! { "Filter" => "Standard", "V" => 9, "R" => 9 });
+        doc.trailer.set("Encrypt", encrypt_id);
+        let error = doc.decrypt("").unwrap_err();
+        assert!(matches!(error.without_context(), Error::Decryption(_)));
+        assert_eq!(error.context().unwrap().object_id, Some(encrypt_id));
+        assert!(error
+            .to_string()
+            .ends_with(&format!("(in object {} {})", encrypt_id.0, encrypt_id.1)));
+        doc.trailer
+            .set("Encrypt", dictionary! { "Filter" => "Standard", "V" => 9, "R" => 9 });
+        let context = doc.decrypt("").unwrap_err().context().cloned().unwrap();
+        assert_eq!(context.path, vec![PathStep::Key(b"Encrypt".to_vec())]);
+        doc.trailer.remove(b"Encrypt");
+    }
+
+    let mut buffer = Vec::new();
+    doc.save_to(&mut buffer).unwrap();
+    let text = String::from_utf8(buffer).unwrap();
+    let xref_start = text.rfind("\nxref").unwrap() + 1;
+    let broken = text.replace("\nxref\n0 ", "\nxref\nA ");
+    let error = Document::load_mem(broken.as_bytes()).unwrap_err();
+    assert!(matches!(error.without_context(), Error::Xref(XrefError::Parse)));
+    assert_eq!(error.context().unwrap().offset, Some(xref_start));
+}
+
+#[test]
+fn find_object_headers() {
+    let buffer = b"12 0 obj\n3\x00\x001 obj 7 0 objx 4 0 obj<<>> 5 0 objects a67 2\r\nobj 99999999999 0 obj";
+    assert_eq!(
+        Reader::object_headers(buffer),
+        vec![((12, 0), 0), ((3, 1), 9), ((4, 0), 27), ((67, 2), 52)]
+    );
+}
+
+#[test]
+fn scan_objects() {
+    use crate::test_support::create_document;
+    use crate::SaveOptions;
+
+    let mut buffer = Vec::new();
+    create_document().save_to(&mut buffer).unwrap();
+    let text = String::from_utf8(buffer).unwrap();
+    // Objects which aren't listed in the cross-reference table, such as deleted or replaced objects.
+    let header_end = text.find('\n').unwrap() + 1;
+    let carved = format!(
+        "{}9 0 obj(carved)endobj\n1 0 obj<</Title(Old)>>endobj\n{}",
+        &text[..header_end],
+        &text[header_end..]
+    );
+    let scanned = Document::scan_objects(carved.as_bytes()).unwrap();
+    for object in &scanned {
+        assert!(carved[object.offset..].starts_with(&format!("{} {} obj", object.id.0, object.id.1)));
+    }
+    assert_eq!(scanned[0].id, (9, 0));
+    assert_eq!(scanned[0].object.as_str().unwrap(), b"carved");
+    let titles: Vec<&[u8]> = scanned
+        .iter()
+        .filter(|object| object.id == (1, 0))
+        .map(|object| {
+            object
+                .object
+                .as_dict()
+                .unwrap()
+                .get(b"Title")
+                .unwrap()
+                .as_str()
+                .unwrap()
+        })
+        .collect();
+    assert_eq!(titles, vec![b"Old".as_ref(), b"Create PDF document example"]);
+    let content = scanned.iter().find(|object| object.id == (5, 0)).unwrap();
+    assert!(content.object.as_stream().unwrap().content.starts_with(b"BT"));
+
+    let mut buffer = Vec::new();
+    let options = SaveOptions {
+        use_object_streams: true,
+        ..SaveOptions::default()
+    };
+    create_document().save_to_with_options(&mut buffer, &options).unwrap();
+    let scanned = Document::scan_objects(buffer.as_slice()).unwrap();
+    let catalog = scanned
+        .iter()
+        .find(|object| {
+            object
+                .object
+                .as_dict()
+                .map(|dict| dict.type_is(b"Catalog"))
+                .unwrap_or(false)
+        })
+        .unwrap();
+    let container = catalog.container.unwrap();
+    assert!(scanned
+        .iter()
+        .any(|object| object.id == container && object.offset == catalog.offset));
+}
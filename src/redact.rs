@@ -0,0 +1,498 @@
+#![cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+
+use crate::content::{Content, Operation};
+use crate::interpreter::{ContentHandler, ContentInterpreter, GraphicsState, PlacedImage};
+use crate::text::{Glyph, TextFragment};
+use crate::{Document, Error, Object, ObjectId, Rectangle, Result, Stream, StringFormat};
+use std::collections::BTreeMap;
+
+/// Options of `Document::redact`.
+#[derive(Debug, Clone)]
+pub struct RedactionOptions {
+    /// RGB color of the boxes drawn over the redacted areas, or `None` to leave them blank.
+    pub fill: Option<[f64; 3]>,
+}
+
+impl Default for RedactionOptions {
+    fn default() -> Self {
+        RedactionOptions {
+            fill: Some([0.0, 0.0, 0.0]),
+        }
+    }
+}
+
+/// What `Document::redact` removed from a page.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RedactionReport {
+    /// Number of glyphs removed from text showing operations.
+    pub glyphs: usize,
+    /// Number of images whose pixels in the areas were painted black, or which were removed when they couldn't
+    /// be decoded.
+    pub images: usize,
+    /// Number of form XObjects removed because they draw text or images in the areas.
+    pub forms: usize,
+    pub annotations: usize,
+}
+
+/// Change to an operation of the page content.
+enum Redaction {
+    /// Text showing operation, with its glyphs and whether each is removed.
+    Text { glyphs: Vec<(Glyph, bool)>, font_size: f64 },
+    /// Image drawn by a `Do` operation, with the areas to paint black in the unit square of image space.
+    Image { id: ObjectId, areas: Vec<Rectangle> },
+    /// Form drawn by a `Do` operation.
+    Form,
+}
+
+struct Redactor<'r> {
+    regions: &'r [Rectangle],
+    /// Index of the current operation of the page content.
+    index: usize,
+    depth: usize,
+    redactions: BTreeMap<usize, Redaction>,
+}
+
+impl<'r> Redactor<'r> {
+    fn overlaps(&self, rect: &Rectangle) -> bool {
+        self.regions.iter().any(|region| overlap(region, rect))
+    }
+}
+
+/// Name of the XObject drawn by a `Do` operation.
+fn xobject_name(operation: &Operation) -> Option<Vec<u8>> {
+    match operation.operands.first() {
+        Some(Object::Name(name)) if operation.operator == "Do" => Some(name.clone()),
+        _ => None,
+    }
+}
+
+/// Whether two rectangles share some area, or one contains the other if it's empty.
+fn overlap(region: &Rectangle, rect: &Rectangle) -> bool {
+    (region.llx < rect.urx && rect.llx < region.urx && region.lly < rect.ury && rect.lly < region.ury)
+        || region.contains(rect)
+}
+
+impl<'r> ContentHandler for Redactor<'r> {
+    fn operation_started(&mut self, index: usize, depth: usize) {
+        self.depth = depth;
+        if depth == 0 {
+            self.index = index;
+        }
+    }
+
+    fn text_shown(&mut self, fragment: &TextFragment, state: &GraphicsState) {
+        if !self.overlaps(&fragment.bbox) {
+            return;
+        }
+        if self.depth > 0 {
+            self.redactions.insert(self.index, Redaction::Form);
+            return;
+        }
+        let glyphs: Vec<(Glyph, bool)> = fragment
+            .glyphs
+            .iter()
+            .map(|glyph| (glyph.clone(), self.overlaps(&glyph.bbox)))
+            .collect();
+        if glyphs.iter().any(|&(_, removed)| removed) {
+            let font_size = state.font_size;
+            self.redactions
+                .insert(self.index, Redaction::Text { glyphs, font_size });
+        }
+    }
+
+    fn image_placed(&mut self, image: &PlacedImage, state: &GraphicsState) {
+        if !self.overlaps(&image.bbox) {
+            return;
+        }
+        let inverse = match state.ctm.invert() {
+            Some(inverse) if self.depth == 0 => inverse,
+            _ => {
+                self.redactions.insert(self.index, Redaction::Form);
+                return;
+            }
+        };
+        let areas = self
+            .regions
+            .iter()
+            .filter(|region| overlap(region, &image.bbox))
+            .map(|region| inverse.transform_rect(region))
+            .collect();
+        self.redactions
+            .insert(self.index, Redaction::Image { id: image.id, areas });
+    }
+}
+
+/// Rewrite a text showing operation as a `TJ` operation in which removed glyphs are replaced by adjustments
+/// of the same width, so that the remaining text keeps its position.
+fn redact_text(operation: &Operation, glyphs: &[(Glyph, bool)], font_size: f64) -> Vec<Operation> {
+    let operands = &operation.operands;
+    let mut operations = Vec::new();
+    let items = match operation.operator.as_str() {
+        "TJ" => match operands.first() {
+            Some(Object::Array(items)) => items.clone(),
+            _ => vec![],
+        },
+        "'" | "\"" => {
+            if operation.operator == "\"" && operands.len() == 3 {
+                operations.push(Operation::new("Tw", vec![operands[0].clone()]));
+                operations.push(Operation::new("Tc", vec![operands[1].clone()]));
+            }
+            operations.push(Operation::new("T*", vec![]));
+            operands.last().cloned().into_iter().collect()
+        }
+        _ => operands.first().cloned().into_iter().collect(),
+    };
+
+    let mut glyphs = glyphs.iter();
+    let mut array = Vec::new();
+    let mut text = Vec::new();
+    let flush = |text: &mut Vec<u8>, array: &mut Vec<Object>| {
+        if !text.is_empty() {
+            array.push(Object::String(std::mem::take(text), StringFormat::Literal));
+        }
+    };
+    for item in items {
+        match item {
+            Object::String(bytes, _) => {
+                let mut consumed = 0;
+                while consumed < bytes.len() {
+                    let (glyph, removed) = match glyphs.next() {
+                        Some(glyph) => glyph,
+                        None => break,
+                    };
+                    consumed += glyph.code.len().max(1);
+                    if *removed {
+                        flush(&mut text, &mut array);
+                        if font_size != 0.0 {
+                            array.push(Object::Real(-glyph.advance * 1000.0 / font_size));
+                        }
+                    } else {
+                        text.extend_from_slice(&glyph.code);
+                    }
+                }
+            }
+            number => {
+                flush(&mut text, &mut array);
+                array.push(number);
+            }
+        }
+    }
+    flush(&mut text, &mut array);
+    operations.push(Operation::new("TJ", vec![Object::Array(array)]));
+    operations
+}
+
+impl Document {
+    /// Remove the content of a page in the regions, given in default user space, rather than just covering it.
+    ///
+    /// Glyphs of text showing operations which overlap the regions are removed, keeping the position of the
+    /// remaining text, and the pixels of images in the regions are painted black. Form XObjects drawing text or
+    /// images in the regions are removed as a whole, and so are annotations overlapping the regions. The page
+    /// content is replaced, deleting the previous content streams and images unless other objects still refer
+    /// to them. Vector graphics and inline images are kept.
+    pub fn redact(
+        &mut self, page_number: u32, regions: &[Rectangle], options: &RedactionOptions,
+    ) -> Result<RedactionReport> {
+        let page_id = *self
+            .get_pages()
+            .get(&page_number)
+            .ok_or(Error::PageNumberNotFound(page_number))?;
+        let redactor = Redactor {
+            regions,
+            index: 0,
+            depth: 0,
+            redactions: BTreeMap::new(),
+        };
+        let mut interpreter = ContentInterpreter::new(self, redactor);
+        interpreter.run_page(page_id)?;
+        let mut redactions = interpreter.into_handler().redactions;
+        let content = Content::decode(&self.get_page_content(page_id)?)?;
+
+        let mut report = RedactionReport::default();
+        let mut image_uses = BTreeMap::new();
+        for redaction in redactions.values() {
+            if let Redaction::Image { id, .. } = *redaction {
+                *image_uses.entry(id).or_insert(0) += 1;
+            }
+        }
+        // Names of XObjects no longer drawn, whose resources are removed.
+        let mut dropped = Vec::new();
+        let mut operations = vec![Operation::new("q", vec![])];
+        for (index, mut operation) in content.operations.into_iter().enumerate() {
+            match redactions.remove(&index) {
+                None => operations.push(operation),
+                Some(Redaction::Text { glyphs, font_size }) => {
+                    report.glyphs += glyphs.iter().filter(|&&(_, removed)| removed).count();
+                    operations.extend(redact_text(&operation, &glyphs, font_size));
+                }
+                Some(Redaction::Form) => {
+                    report.forms += 1;
+                    dropped.extend(xobject_name(&operation));
+                }
+                Some(Redaction::Image { id, areas }) => {
+                    report.images += 1;
+                    let image = self.get_object(id).and_then(Object::as_stream)?;
+                    let (mut image, mask) = match self.redact_image(image, &areas) {
+                        Ok(redacted) => redacted,
+                        Err(_) => {
+                            dropped.extend(xobject_name(&operation));
+                            continue;
+                        }
+                    };
+                    if let Some(mask) = mask {
+                        image.dict.set("SMask", self.add_object(mask));
+                    }
+                    // Images only drawn here are replaced, so that their previous data is gone.
+                    if image_uses[&id] == 1 && self.reference_count(id) == 1 {
+                        let previous = self.objects.insert(id, Object::Stream(image));
+                        if let Some(Object::Stream(previous)) = previous {
+                            self.delete_unreferenced(previous.dict.get(b"SMask"));
+                        }
+                    } else {
+                        let image_id = self.add_object(image);
+                        let name = format!("Redacted{}", image_id.0);
+                        self.add_xobject(page_id, name.as_bytes(), image_id)?;
+                        operation.operands = vec![Object::Name(name.into_bytes())];
+                    }
+                    operations.push(operation);
+                }
+            }
+        }
+        operations.push(Operation::new("Q", vec![]));
+        dropped.retain(|name| {
+            !operations
+                .iter()
+                .any(|operation| xobject_name(operation).as_ref() == Some(name))
+        });
+        if let Some([red, green, blue]) = options.fill {
+            operations.push(Operation::new("q", vec![]));
+            operations.push(Operation::new("rg", vec![red.into(), green.into(), blue.into()]));
+            for region in regions {
+                let operands = vec![region.llx, region.lly, region.width(), region.height()];
+                operations.push(Operation::new("re", operands.into_iter().map(Object::Real).collect()));
+            }
+            operations.push(Operation::new("f", vec![]));
+            operations.push(Operation::new("Q", vec![]));
+        }
+
+        let previous_contents = self.get_page_contents(page_id);
+        let mut stream = Stream::new(dictionary! {}, Content { operations }.encode()?);
+        stream.compress()?;
+        let content_id = self.add_object(stream);
+        self.get_object_mut(page_id)
+            .and_then(Object::as_dict_mut)?
+            .set("Contents", content_id);
+        for id in previous_contents {
+            self.delete_unreferenced(Ok(&Object::Reference(id)));
+        }
+        for name in dropped {
+            let xobject = self.remove_page_xobject(page_id, &name)?;
+            self.delete_unreferenced(xobject.as_ref().ok_or(Error::DictKey));
+        }
+
+        report.annotations = self.redact_annotations(page_id, regions)?;
+        Ok(report)
+    }
+
+    /// Remove the annotations of a page which overlap the regions, with their pop-up annotations.
+    fn redact_annotations(&mut self, page_id: ObjectId, regions: &[Rectangle]) -> Result<usize> {
+        let annotations = match self.get_dictionary(page_id)?.get(b"Annots") {
+            Ok(annotations) => self.dereference(annotations)?,
+            Err(_) => return Ok(0),
+        };
+        let (array_id, annotations) = match annotations {
+            (id, Object::Array(annotations)) => (id, annotations.clone()),
+            _ => return Ok(0),
+        };
+        let mut removed = Vec::new();
+        for (index, annotation) in annotations.iter().enumerate() {
+            let dict = match self.dereference(annotation).and_then(|(_, dict)| dict.as_dict()) {
+                Ok(dict) => dict,
+                Err(_) => continue,
+            };
+            let rect = dict.get(b"Rect").and_then(Rectangle::from_object);
+            if rect
+                .map(|rect| regions.iter().any(|region| overlap(region, &rect)))
+                .unwrap_or(false)
+            {
+                removed.push(index);
+                if let Ok(popup_id) = dict.get(b"Popup").and_then(Object::as_reference) {
+                    if let Some(popup) = annotations.iter().position(|item| *item == Object::Reference(popup_id)) {
+                        removed.push(popup);
+                    }
+                }
+            }
+        }
+        removed.sort_unstable();
+        removed.dedup();
+        let count = removed.len();
+
+        let ids: Vec<ObjectId> = removed
+            .iter()
+            .filter_map(|&index| annotations[index].as_reference().ok())
+            .collect();
+        let remaining: Vec<Object> = annotations
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _)| !removed.contains(index))
+            .map(|(_, annotation)| annotation)
+            .collect();
+        match array_id {
+            Some(array_id) => *self.get_object_mut(array_id)? = Object::Array(remaining),
+            None => self
+                .get_object_mut(page_id)
+                .and_then(Object::as_dict_mut)?
+                .set("Annots", remaining),
+        }
+        // Deleting the annotations also removes them from the fields of interactive forms.
+        for id in ids {
+            self.delete_object(id);
+        }
+        Ok(count)
+    }
+
+    /// Remove an XObject from the resources of a page, returning the entry. Resources inherited or shared with
+    /// other pages are copied to the page first.
+    fn remove_page_xobject(&mut self, page_id: ObjectId, name: &[u8]) -> Result<Option<Object>> {
+        let xobjects = match self.get_or_create_resources(page_id)?.as_dict()?.get(b"XObject") {
+            Ok(xobjects) => xobjects.clone(),
+            Err(_) => return Ok(None),
+        };
+        let mut xobjects = self.dereference(&xobjects)?.1.as_dict()?.clone();
+        let removed = xobjects.remove(name);
+        self.get_or_create_resources(page_id)?
+            .as_dict_mut()?
+            .set("XObject", xobjects);
+        Ok(removed)
+    }
+
+    /// Delete the object an entry refers to, unless other objects still refer to it.
+    fn delete_unreferenced(&mut self, object: Result<&Object>) {
+        if let Ok(&Object::Reference(id)) = object {
+            if self.reference_count(id) == 0 {
+                self.objects.remove(&id);
+            }
+        }
+    }
+}
+
+#[test]
+fn redact_page() {
+    use crate::test_support::create_document;
+    use std::io::Read;
+
+    #[derive(Default)]
+    struct Texts(Vec<(String, Rectangle)>);
+
+    impl ContentHandler for Texts {
+        fn text_shown(&mut self, fragment: &TextFragment, _state: &GraphicsState) {
+            self.0
+                .push((fragment.text.clone(), fragment.glyphs.last().unwrap().bbox));
+        }
+    }
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    let image_id = doc.add_object(Stream::new(
+        dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Image",
+            "Width" => 2,
+            "Height" => 1,
+            "ColorSpace" => "DeviceGray",
+            "BitsPerComponent" => 8,
+        },
+        vec![200, 200],
+    ));
+    doc.add_xobject(page_id, "Im1", image_id).unwrap();
+    let content = Content {
+        operations: vec![
+            Operation::new(
+                "cm",
+                vec![100.into(), 0.into(), 0.into(), 50.into(), 0.into(), 0.into()],
+            ),
+            Operation::new("Do", vec!["Im1".into()]),
+        ],
+    };
+    doc.append_page_content(page_id, content.encode().unwrap()).unwrap();
+    let inside = doc
+        .add_object(dictionary! { "Subtype" => "Square", "Rect" => vec![30.into(), 10.into(), 40.into(), 20.into()] });
+    let outside = doc.add_object(
+        dictionary! { "Subtype" => "Square", "Rect" => vec![300.into(), 10.into(), 310.into(), 20.into()] },
+    );
+    doc.get_object_mut(page_id)
+        .and_then(Object::as_dict_mut)
+        .unwrap()
+        .set("Annots", vec![inside.into(), outside.into()]);
+    let previous_contents = doc.get_page_contents(page_id);
+
+    // Courier glyphs of 48 points are 28.8 points wide, so "World" goes from 272.8 to 416.8.
+    let regions = [
+        Rectangle::new(275.0, 590.0, 410.0, 660.0),
+        Rectangle::new(0.0, 0.0, 45.0, 45.0),
+    ];
+    let report = doc.redact(1, &regions, &RedactionOptions::default()).unwrap();
+    assert_eq!(
+        report,
+        RedactionReport {
+            glyphs: 5,
+            images: 1,
+            forms: 0,
+            annotations: 1,
+        }
+    );
+
+    let mut interpreter = ContentInterpreter::new(&doc, Texts::default());
+    interpreter.run_page(page_id).unwrap();
+    let texts = interpreter.into_handler().0;
+    assert_eq!(texts[0].0, "Hello !");
+    assert!((texts[0].1.llx - 416.8).abs() < 1e-6);
+    let content = doc.get_page_content(page_id).unwrap();
+    assert!(!content.windows(5).any(|window| window == b"World"));
+    assert!(previous_contents.iter().all(|id| !doc.objects.contains_key(id)));
+
+    let image = doc.get_object(image_id).and_then(Object::as_stream).unwrap();
+    let mut pixels = Vec::new();
+    flate2::read::ZlibDecoder::new(image.content.as_slice())
+        .read_to_end(&mut pixels)
+        .unwrap();
+    assert_eq!(pixels, [0, 200]);
+    assert!(!doc.objects.contains_key(&inside));
+    assert_eq!(
+        doc.get_dictionary(page_id).unwrap().get(b"Annots").unwrap(),
+        &Object::Array(vec![outside.into()])
+    );
+}
+
+#[test]
+fn redact_form_xobjects() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    let font_id = doc.get_page_fonts(page_id)[b"F1".as_ref()].clone();
+    let form = Stream::new(
+        dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Form",
+            "BBox" => vec![0.into(), 0.into(), 200.into(), 100.into()],
+            "Resources" => dictionary! { "Font" => dictionary! { "F1" => font_id } },
+        },
+        b"BT /F1 12 Tf 10 10 Td (Secret) Tj ET".to_vec(),
+    );
+    let form_id = doc.add_object(form);
+    doc.add_xobject(page_id, "Fm1", form_id).unwrap();
+    doc.append_page_content(page_id, b"/Fm1 Do".to_vec()).unwrap();
+
+    let regions = [Rectangle::new(0.0, 0.0, 100.0, 50.0)];
+    let report = doc.redact(1, &regions, &RedactionOptions::default()).unwrap();
+    assert_eq!(report.forms, 1);
+    let resources = doc.get_page_resources(page_id).0.unwrap();
+    assert!(!resources.get(b"XObject").and_then(Object::as_dict).unwrap().has(b"Fm1"));
+    assert!(!doc.objects.contains_key(&form_id));
+
+    let mut saved = Vec::new();
+    doc.save_to(&mut saved).unwrap();
+    assert!(!saved.windows(6).any(|window| window == b"Secret"));
+    assert!(saved.windows(5).any(|window| window == b"Hello"));
+}
@@ -0,0 +1,137 @@
+//! Reverse lookup of the references between objects, to find which objects use an object before deleting or
+//! replacing it.
+
+use crate::{Document, Object, ObjectId, Stream};
+use std::collections::BTreeMap;
+
+/// Step from an array or dictionary to one of its items. Entries of stream dictionaries are reached by their
+/// keys, as entries of dictionaries.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PathStep {
+    Key(Vec<u8>),
+    Index(usize),
+}
+
+/// Location of a reference within an object, as the steps from the object to the reference.
+pub type Path = Vec<PathStep>;
+
+/// Index of the objects which refer to each object, with the locations of the references.
+///
+/// References from the trailer aren't included.
+#[derive(Debug, Clone, Default)]
+pub struct ReferenceIndex {
+    referrers: BTreeMap<ObjectId, Vec<(ObjectId, Path)>>,
+}
+
+impl ReferenceIndex {
+    /// Index the references made by all objects of a document.
+    pub fn new(document: &Document) -> ReferenceIndex {
+        let mut referrers: BTreeMap<ObjectId, Vec<(ObjectId, Path)>> = BTreeMap::new();
+        for (&id, object) in &document.objects {
+            visit_references(object, &mut Vec::new(), &mut |target, path| {
+                referrers.entry(target).or_default().push((id, path.clone()));
+            });
+        }
+        ReferenceIndex { referrers }
+    }
+
+    /// Get the objects referring to an object, with the location of each reference, in order of object id.
+    pub fn references_to(&self, id: ObjectId) -> &[(ObjectId, Path)] {
+        self.referrers.get(&id).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    pub fn is_referenced(&self, id: ObjectId) -> bool {
+        self.referrers.contains_key(&id)
+    }
+
+    /// Iterate over the referenced objects, with the objects referring to them.
+    pub fn iter(&self) -> impl Iterator<Item = (ObjectId, &[(ObjectId, Path)])> {
+        self.referrers.iter().map(|(&id, referrers)| (id, referrers.as_slice()))
+    }
+}
+
+/// Call a function with each reference made by an object and its location, without following them.
+pub(crate) fn visit_references<F: FnMut(ObjectId, &Path)>(object: &Object, path: &mut Path, visit: &mut F) {
+    match *object {
+        Object::Reference(id) => visit(id, path),
+        Object::Array(ref array) => {
+            for (index, item) in array.iter().enumerate() {
+                path.push(PathStep::Index(index));
+                visit_references(item, path, visit);
+                path.pop();
+            }
+        }
+        Object::Dictionary(ref dict) | Object::Stream(Stream { ref dict, .. }) => {
+            for (key, value) in dict.iter() {
+                path.push(PathStep::Key(key.clone()));
+                visit_references(value, path, visit);
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+impl Document {
+    /// Get the objects referring to an object, with the location of each reference, in order of object id.
+    ///
+    /// This walks all objects. Use a `ReferenceIndex` to look up the references to many objects.
+    pub fn references_to(&self, id: ObjectId) -> Vec<(ObjectId, Path)> {
+        let mut references = Vec::new();
+        for (&referrer, object) in &self.objects {
+            visit_references(object, &mut Vec::new(), &mut |target, path| {
+                if target == id {
+                    references.push((referrer, path.clone()));
+                }
+            });
+        }
+        references
+    }
+
+    /// Index the references between all objects.
+    pub fn reference_index(&self) -> ReferenceIndex {
+        ReferenceIndex::new(self)
+    }
+}
+
+#[test]
+fn find_references() {
+    use crate::test_support::create_document;
+
+    let doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    let catalog_id = doc.trailer.get(b"Root").and_then(Object::as_reference).unwrap();
+    let pages_id = doc
+        .catalog()
+        .and_then(|catalog| catalog.get(b"Pages"))
+        .and_then(Object::as_reference)
+        .unwrap();
+    let font_id = doc
+        .objects
+        .iter()
+        .find(|(_, object)| object.as_dict().map(|dict| dict.type_is(b"Font")).unwrap_or(false))
+        .map(|(&id, _)| id)
+        .unwrap();
+
+    assert_eq!(
+        doc.references_to(pages_id),
+        vec![
+            (page_id, vec![PathStep::Key(b"Parent".to_vec())]),
+            (catalog_id, vec![PathStep::Key(b"Pages".to_vec())]),
+        ]
+    );
+    let index = doc.reference_index();
+    assert_eq!(index.references_to(pages_id), doc.references_to(pages_id).as_slice());
+    assert_eq!(
+        index.references_to(page_id),
+        &[(pages_id, vec![PathStep::Key(b"Kids".to_vec()), PathStep::Index(0)])]
+    );
+    let (referrer, path) = &index.references_to(font_id)[0];
+    assert_eq!(
+        path,
+        &vec![PathStep::Key(b"Font".to_vec()), PathStep::Key(b"F1".to_vec())]
+    );
+    assert!(doc.get_dictionary(*referrer).unwrap().has(b"Font"));
+    assert!(!index.is_referenced(catalog_id));
+    assert!(index.references_to(catalog_id).is_empty());
+}
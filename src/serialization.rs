@@ -0,0 +1,265 @@
+use crate::encodings::{decode_text_string, encode_text_string};
+use crate::{Dictionary, Object, Stream, StringFormat};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use std::fmt;
+
+/// Write a name as in PDF files, with bytes other than printable ASCII characters and `#` as `#xx`.
+fn escape_name(name: &[u8]) -> String {
+    let mut text = String::from("/");
+    for &byte in name {
+        if (0x21..=0x7E).contains(&byte) && byte != b'#' {
+            text.push(char::from(byte));
+        } else {
+            text.push_str(&format!("#{:02X}", byte));
+        }
+    }
+    text
+}
+
+fn unescape_name<E: de::Error>(text: &str) -> Result<Vec<u8>, E> {
+    let text = text
+        .strip_prefix('/')
+        .ok_or_else(|| E::custom(format!("name {:?} doesn't start with a slash", text)))?;
+    let mut name = Vec::with_capacity(text.len());
+    let mut bytes = text.bytes();
+    while let Some(byte) = bytes.next() {
+        if byte == b'#' {
+            let hex: Vec<u8> = bytes.by_ref().take(2).collect();
+            let code = std::str::from_utf8(&hex)
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| E::custom(format!("invalid escape in name {:?}", text)))?;
+            name.push(code);
+        } else {
+            name.push(byte);
+        }
+    }
+    Ok(name)
+}
+
+impl Serialize for Object {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Object::Null => serializer.serialize_unit(),
+            Object::Boolean(value) => serializer.serialize_bool(*value),
+            Object::Integer(value) => serializer.serialize_i64(*value),
+            Object::Real(value) => serializer.serialize_f64(*value),
+            Object::Name(name) => serializer.serialize_str(&escape_name(name)),
+            Object::String(bytes, format) => {
+                let mut map = serializer.serialize_map(Some(2))?;
+                let text = decode_text_string(bytes);
+                if encode_text_string(&text) == *bytes {
+                    map.serialize_entry("string", &text)?;
+                } else {
+                    map.serialize_entry("base64", &STANDARD.encode(bytes))?;
+                }
+                let format = match format {
+                    StringFormat::Literal => "literal",
+                    StringFormat::Hexadecimal => "hexadecimal",
+                };
+                map.serialize_entry("format", format)?;
+                map.end()
+            }
+            Object::Array(array) => array.serialize(serializer),
+            Object::Dictionary(dict) => dict.serialize(serializer),
+            Object::Stream(stream) => stream.serialize(serializer),
+            Object::Reference(id) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry("ref", &[id.0, u32::from(id.1)])?;
+                map.end()
+            }
+        }
+    }
+}
+
+impl Serialize for Dictionary {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.len()))?;
+        for (key, value) in self {
+            map.serialize_entry(&escape_name(key), value)?;
+        }
+        map.end()
+    }
+}
+
+impl Serialize for Stream {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(2))?;
+        map.serialize_entry("stream", &self.dict)?;
+        map.serialize_entry("data", &STANDARD.encode(&self.content))?;
+        map.end()
+    }
+}
+
+struct ObjectVisitor;
+
+impl<'de> Visitor<'de> for ObjectVisitor {
+    type Value = Object;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a PDF object")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Object, E> {
+        Ok(Object::Null)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Object, E> {
+        Ok(Object::Null)
+    }
+
+    fn visit_bool<E: de::Error>(self, value: bool) -> Result<Object, E> {
+        Ok(Object::Boolean(value))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Object, E> {
+        Ok(Object::Integer(value))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Object, E> {
+        Ok(Object::Integer(value as i64))
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<Object, E> {
+        Ok(Object::Real(value))
+    }
+
+    /// Strings without a leading slash are read as text strings, which is how the values of tagged objects are read.
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Object, E> {
+        if value.starts_with('/') {
+            unescape_name(value).map(Object::Name)
+        } else {
+            Ok(Object::String(encode_text_string(value), StringFormat::Literal))
+        }
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Object, A::Error> {
+        let mut array = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(item) = seq.next_element()? {
+            array.push(item);
+        }
+        Ok(Object::Array(array))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Object, A::Error> {
+        let mut dict = Dictionary::new();
+        let mut tags = Vec::new();
+        while let Some(key) = map.next_key::<String>()? {
+            let value: Object = map.next_value()?;
+            if key.starts_with('/') {
+                dict.set(unescape_name(&key)?, value);
+            } else {
+                tags.push((key, value));
+            }
+        }
+        if tags.is_empty() {
+            return Ok(Object::Dictionary(dict));
+        }
+        if !dict.is_empty() {
+            return Err(de::Error::custom("dictionary keys mixed with tags of other objects"));
+        }
+
+        let text = |value: &Object| match value {
+            Object::String(bytes, _) => Ok(decode_text_string(bytes)),
+            _ => Err(de::Error::custom("expected a string")),
+        };
+        let base64 =
+            |value: &Object| -> Result<Vec<u8>, A::Error> { STANDARD.decode(text(value)?).map_err(de::Error::custom) };
+        let tag = |name: &str| tags.iter().find(|(key, _)| key == name).map(|(_, value)| value);
+        if let Some(id) = tag("ref") {
+            let id = match id {
+                Object::Array(id) => match id.as_slice() {
+                    [Object::Integer(number), Object::Integer(generation)] => (*number as u32, *generation as u16),
+                    _ => return Err(de::Error::custom("expected an object number and generation")),
+                },
+                _ => return Err(de::Error::custom("expected an object number and generation")),
+            };
+            Ok(Object::Reference(id))
+        } else if let Some(dict) = tag("stream") {
+            let dict = match dict {
+                Object::Dictionary(dict) => dict.clone(),
+                _ => return Err(de::Error::custom("expected a stream dictionary")),
+            };
+            let content = match tag("data") {
+                Some(data) => base64(data)?,
+                None => Vec::new(),
+            };
+            Ok(Object::Stream(Stream::new(dict, content)))
+        } else {
+            let bytes = match (tag("string"), tag("base64")) {
+                (Some(string), _) => encode_text_string(&text(string)?),
+                (None, Some(data)) => base64(data)?,
+                (None, None) => return Err(de::Error::custom("unknown tags of object")),
+            };
+            let format = match tag("format").map(text).transpose()?.as_deref() {
+                None | Some("literal") => StringFormat::Literal,
+                Some("hexadecimal") => StringFormat::Hexadecimal,
+                Some(format) => return Err(de::Error::custom(format!("unknown string format {:?}", format))),
+            };
+            Ok(Object::String(bytes, format))
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Object {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Object, D::Error> {
+        deserializer.deserialize_any(ObjectVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for Dictionary {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Dictionary, D::Error> {
+        match Object::deserialize(deserializer)? {
+            Object::Dictionary(dict) => Ok(dict),
+            _ => Err(de::Error::custom("expected a dictionary")),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Stream {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Stream, D::Error> {
+        match Object::deserialize(deserializer)? {
+            Object::Stream(stream) => Ok(stream),
+            _ => Err(de::Error::custom("expected a stream")),
+        }
+    }
+}
+
+#[test]
+fn serialize_objects_to_json() {
+    let stream = Stream::new(dictionary! { "Type" => "XObject" }, vec![0, 1, 2, 255]);
+    let object = Object::Array(vec![
+        Object::Null,
+        true.into(),
+        42.into(),
+        Object::Real(1.5),
+        Object::Name(b"A B#\xFF".to_vec()),
+        Object::string_literal("Hello"),
+        Object::String(encode_text_string("\u{4E2D}\u{6587}"), StringFormat::Hexadecimal),
+        Object::String(vec![0xE9], StringFormat::Literal),
+        Object::Reference((12, 0)),
+        Object::Dictionary(dictionary! { "Kids" => vec![Object::Reference((3, 1))] }),
+        Object::Stream(stream),
+    ]);
+    let json = serde_json::to_string(&object).unwrap();
+    assert_eq!(
+        json,
+        concat!(
+            r#"[null,true,42,1.5,"/A#20B#23#FF","#,
+            r#"{"string":"Hello","format":"literal"},"#,
+            r#"{"string":"中文","format":"hexadecimal"},"#,
+            r#"{"base64":"6Q==","format":"literal"},"#,
+            r#"{"ref":[12,0]},{"/Kids":[{"ref":[3,1]}]},"#,
+            r#"{"stream":{"/Type":"/XObject","/Length":4},"data":"AAEC/w=="}]"#
+        )
+    );
+    assert_eq!(serde_json::from_str::<Object>(&json).unwrap(), object);
+
+    let dict: Dictionary = serde_json::from_str(r#"{"/Type": "/Page", "/Rotate": 90}"#).unwrap();
+    assert_eq!(dict, dictionary! { "Type" => "Page", "Rotate" => 90 });
+    assert!(serde_json::from_str::<Dictionary>(r#"{"/Type": "/Page", "ref": [1, 0]}"#).is_err());
+    assert!(serde_json::from_str::<Stream>(r#"{"ref": [1, 0]}"#).is_err());
+}
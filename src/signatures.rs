@@ -0,0 +1,1153 @@
+//! Digital signatures and document timestamps, which are added in incremental updates so that the earlier revisions
+//! of the file stay intact.
+//!
+//! The update is written with placeholders for the byte range and the contents of the signature dictionary, which
+//! are filled in once the signed bytes are known. The signature itself, a detached CMS structure or an RFC 3161
+//! timestamp token, is created by a callback, so any cryptographic library, hardware token or timestamp authority
+//! can be used.
+
+use crate::content::ContentBuilder;
+use crate::encodings::encode_text_string;
+use crate::error::Error;
+use crate::forms::text_width;
+use crate::{
+    xobject, Dictionary, Document, Matrix, Object, ObjectId, PdfDate, Rectangle, Result, Stream, StringFormat,
+};
+#[cfg(feature = "crypto")]
+use sha1::{Digest, Sha1};
+#[cfg(feature = "crypto")]
+use sha2::Sha256;
+use std::collections::BTreeSet;
+
+/// Value of the byte range entries until the offsets are known, wide enough for any offset written over it.
+const BYTE_RANGE_PLACEHOLDER: i64 = 9_999_999_999;
+
+/// Signature field and signature dictionary entries of a signature.
+#[derive(Debug, Clone)]
+pub struct SignatureOptions {
+    /// Partial name of the signature field.
+    pub field_name: String,
+    /// Number of the page the widget annotation of the signature field is placed on.
+    pub page: u32,
+    /// Visible appearance of the widget annotation, which is invisible if none.
+    pub appearance: Option<SignatureAppearance>,
+    /// Number of bytes reserved for the signature. Signing fails if the signature is larger.
+    pub contents_size: usize,
+    /// Name of the signer.
+    pub name: Option<String>,
+    pub reason: Option<String>,
+    pub location: Option<String>,
+    pub contact_info: Option<String>,
+    /// Time of signing, which is only informational since it isn't signed.
+    pub signing_time: Option<PdfDate>,
+    /// Changes allowed after a certification signature, which is signed with the DocMDP transform method. Only the
+    /// first signature of a document can certify it. Document timestamps ignore it.
+    pub certification: Option<MdpPermissions>,
+}
+
+/// Changes which later incremental updates may make to a document certified by a signature, each level allowing
+/// those of the previous ones. Adding validation data and document timestamps is always allowed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MdpPermissions {
+    NoChanges = 1,
+    /// Filling in forms, instantiating page templates and signing.
+    FormFilling = 2,
+    /// Creating, deleting and changing annotations as well.
+    Annotations = 3,
+}
+
+impl MdpPermissions {
+    fn from_i64(value: i64) -> Option<MdpPermissions> {
+        match value {
+            1 => Some(MdpPermissions::NoChanges),
+            2 => Some(MdpPermissions::FormFilling),
+            3 => Some(MdpPermissions::Annotations),
+            _ => None,
+        }
+    }
+}
+
+/// Object which an incremental update added, changed or deleted without the permission of the certification
+/// signature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MdpViolation {
+    /// Index of the revision in `Document::revisions`.
+    pub revision: usize,
+    pub id: ObjectId,
+}
+
+impl Default for SignatureOptions {
+    fn default() -> Self {
+        SignatureOptions {
+            field_name: "Signature1".to_string(),
+            page: 1,
+            appearance: None,
+            contents_size: 8192,
+            name: None,
+            reason: None,
+            location: None,
+            contact_info: None,
+            signing_time: None,
+            certification: None,
+        }
+    }
+}
+
+/// Visible appearance of a signature, a stamp showing the name of the signer, the reason, the location and the
+/// time of signing, as far as they're given, next to an optional logo.
+#[derive(Debug, Clone)]
+pub struct SignatureAppearance {
+    /// Rectangle of the widget annotation on the page.
+    pub rect: Rectangle,
+    /// Image XObject drawn at the left of the text, such as one created by `xobject::image`.
+    pub logo: Option<Stream>,
+}
+
+impl SignatureAppearance {
+    pub fn new(rect: Rectangle) -> SignatureAppearance {
+        SignatureAppearance { rect, logo: None }
+    }
+
+    pub fn with_logo(mut self, logo: Stream) -> SignatureAppearance {
+        self.logo = Some(logo);
+        self
+    }
+}
+
+/// Padding between the border of a signature appearance and its content.
+const PADDING: f64 = 2.0;
+const LINE_HEIGHT: f64 = 1.15;
+/// Fraction of the width of a signature appearance taken by the logo, if there is text as well.
+const LOGO_WIDTH: f64 = 0.4;
+
+/// Certificates and revocation information for validating signatures, as kept in the document security store.
+/// The store needs the `crypto` feature, as VRI entries are keyed by SHA-1 digests.
+#[cfg(feature = "crypto")]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ValidationData {
+    /// DER encoded certificates.
+    pub certificates: Vec<Vec<u8>>,
+    /// DER encoded OCSP responses.
+    pub ocsp_responses: Vec<Vec<u8>>,
+    /// DER encoded certificate revocation lists.
+    pub crls: Vec<Vec<u8>>,
+}
+
+/// Keys of the document security store and of its VRI entries for the kinds of validation data.
+#[cfg(feature = "crypto")]
+const DSS_KEYS: [(&str, &str); 3] = [("Certs", "Cert"), ("OCSPs", "OCSP"), ("CRLs", "CRL")];
+
+#[cfg(feature = "crypto")]
+impl ValidationData {
+    fn kinds(&self) -> [&Vec<Vec<u8>>; 3] {
+        [&self.certificates, &self.ocsp_responses, &self.crls]
+    }
+
+    fn kinds_mut(&mut self) -> [&mut Vec<Vec<u8>>; 3] {
+        [&mut self.certificates, &mut self.ocsp_responses, &mut self.crls]
+    }
+}
+
+impl Document {
+    /// Add validation data to the document security store, creating it if missing, which makes signatures
+    /// verifiable long after their certificates expired or were revoked, as required by PAdES-LTV.
+    ///
+    /// The data is also listed in the VRI entry of the signature with the id of its signature dictionary, if any.
+    /// Data already in the store isn't added again. The store is written by the next incremental update, which
+    /// keeps earlier signatures valid.
+    #[cfg(feature = "crypto")]
+    pub fn add_validation_data(&mut self, signature_id: Option<ObjectId>, data: &ValidationData) -> Result<()> {
+        let vri_key = match signature_id {
+            Some(signature_id) => Some(vri_key(self.get_dictionary(signature_id)?)?),
+            None => None,
+        };
+        let catalog_id = self.trailer.get(b"Root").and_then(Object::as_reference)?;
+        let (dss_id, mut dss) = match self.catalog()?.get(b"DSS") {
+            Ok(&Object::Reference(dss_id)) => (Some(dss_id), self.get_dictionary(dss_id)?.clone()),
+            Ok(dss) => (None, dss.as_dict()?.clone()),
+            Err(_) => (None, Dictionary::new()),
+        };
+
+        let mut vri_entry = Dictionary::new();
+        for (&(dss_key, vri_entry_key), blobs) in DSS_KEYS.iter().zip(data.kinds().iter()) {
+            if blobs.is_empty() {
+                continue;
+            }
+            let mut stored = match dss.get(dss_key.as_bytes()) {
+                Ok(array) => self.dereference(array)?.1.as_array()?.clone(),
+                Err(_) => Vec::new(),
+            };
+            let mut ids = Vec::new();
+            for blob in blobs.iter() {
+                let existing = stored
+                    .iter()
+                    .filter_map(|object| object.as_reference().ok())
+                    .find(|&id| {
+                        self.get_object(id)
+                            .and_then(Object::as_stream)
+                            .map(stream_data)
+                            .ok()
+                            .as_ref()
+                            == Some(blob)
+                    });
+                let id = match existing {
+                    Some(id) => id,
+                    None => {
+                        let mut stream = Stream::new(Dictionary::new(), blob.clone());
+                        stream.compress()?;
+                        let id = self.add_object(stream);
+                        stored.push(id.into());
+                        id
+                    }
+                };
+                if !ids.contains(&id) {
+                    ids.push(id);
+                }
+            }
+            dss.set(dss_key, stored);
+            vri_entry.set(
+                vri_entry_key,
+                ids.into_iter().map(Object::Reference).collect::<Vec<_>>(),
+            );
+        }
+
+        if let Some(vri_key) = vri_key {
+            let mut vri = match dss.get(b"VRI") {
+                Ok(vri) => self.dereference(vri)?.1.as_dict()?.clone(),
+                Err(_) => Dictionary::new(),
+            };
+            // Data added for a signature before is kept.
+            if let Ok(existing) = vri
+                .get(&vri_key)
+                .and_then(|entry| self.dereference(entry))
+                .and_then(|(_, entry)| entry.as_dict())
+            {
+                for &(_, key) in DSS_KEYS.iter() {
+                    if let Ok(array) = existing.get(key.as_bytes()).and_then(Object::as_array) {
+                        let mut ids = array.clone();
+                        if let Ok(added) = vri_entry.get(key.as_bytes()).and_then(Object::as_array) {
+                            ids.extend(added.iter().filter(|&id| !array.contains(id)).cloned());
+                        }
+                        vri_entry.set(key, ids);
+                    }
+                }
+            }
+            vri.set(vri_key, vri_entry);
+            dss.set("VRI", vri);
+        }
+
+        match dss_id {
+            Some(dss_id) => {
+                self.objects.insert(dss_id, Object::Dictionary(dss));
+            }
+            None => {
+                let dss_id = self.add_object(dss);
+                self.get_object_mut(catalog_id)
+                    .and_then(Object::as_dict_mut)?
+                    .set("DSS", dss_id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the validation data of the document security store, or only the data listed in the VRI entry of the
+    /// signature with the id of its signature dictionary.
+    #[cfg(feature = "crypto")]
+    pub fn get_validation_data(&self, signature_id: Option<ObjectId>) -> Result<ValidationData> {
+        let (_, dss) = self.dereference(self.catalog()?.get(b"DSS")?)?;
+        let mut dictionary = dss.as_dict()?;
+        if let Some(signature_id) = signature_id {
+            let vri = self.dereference(dictionary.get(b"VRI")?)?.1.as_dict()?;
+            let key = vri_key(self.get_dictionary(signature_id)?)?;
+            dictionary = self.dereference(vri.get(&key)?)?.1.as_dict()?;
+        }
+        let mut data = ValidationData::default();
+        for (&(dss_key, vri_entry_key), blobs) in DSS_KEYS.iter().zip(data.kinds_mut().iter_mut()) {
+            let key = if signature_id.is_some() { vri_entry_key } else { dss_key };
+            if let Ok(array) = dictionary.get(key.as_bytes()) {
+                for object in self.dereference(array)?.1.as_array()? {
+                    let (_, stream) = self.dereference(object)?;
+                    blobs.push(stream_data(stream.as_stream()?));
+                }
+            }
+        }
+        Ok(data)
+    }
+
+    /// Sign the document in an incremental update of the file it was loaded from or last saved incrementally to,
+    /// returning the signed file.
+    ///
+    /// `sign` is given the signed bytes of the file, which are all bytes but the signature itself, and returns
+    /// the DER encoded detached CMS signature of them, as required by the ETSI.CAdES.detached sub filter.
+    pub fn sign_incremental<F>(&mut self, file: &[u8], options: &SignatureOptions, sign: F) -> Result<Vec<u8>>
+    where
+        F: FnOnce(&[u8]) -> Result<Vec<u8>>,
+    {
+        let mut signature = dictionary! {
+            "Type" => "Sig",
+            "Filter" => "Adobe.PPKLite",
+            "SubFilter" => "ETSI.CAdES.detached",
+        };
+        let entries = [
+            ("Name", &options.name),
+            ("Reason", &options.reason),
+            ("Location", &options.location),
+            ("ContactInfo", &options.contact_info),
+        ];
+        for &(key, value) in entries.iter() {
+            if let Some(value) = value {
+                signature.set(key, Object::String(encode_text_string(value), StringFormat::Literal));
+            }
+        }
+        if let Some(ref signing_time) = options.signing_time {
+            signature.set("M", *signing_time);
+        }
+        self.write_signature(file, options, signature, options.certification, sign)
+    }
+
+    /// Add a document timestamp signature in an incremental update of the file it was loaded from or last saved
+    /// incrementally to, returning the timestamped file. Document timestamps extend the validity of earlier
+    /// signatures, as required by PAdES-LTA.
+    ///
+    /// `timestamp` is given the SHA-256 digest of the signed bytes of the file, and returns the DER encoded
+    /// RFC 3161 timestamp token which a timestamp authority issued for it. Digesting the file needs the `crypto`
+    /// feature.
+    #[cfg(feature = "crypto")]
+    pub fn add_document_timestamp<F>(
+        &mut self, file: &[u8], options: &SignatureOptions, timestamp: F,
+    ) -> Result<Vec<u8>>
+    where
+        F: FnOnce(&[u8]) -> Result<Vec<u8>>,
+    {
+        let signature = dictionary! {
+            "Type" => "DocTimeStamp",
+            "Filter" => "Adobe.PPKLite",
+            "SubFilter" => "ETSI.RFC3161",
+        };
+        self.write_signature(file, options, signature, None, |data| timestamp(&Sha256::digest(data)))
+    }
+
+    /// Write an incremental update with a signature field of the signature dictionary, and fill in the signature.
+    fn write_signature<F>(
+        &mut self, file: &[u8], options: &SignatureOptions, mut signature: Dictionary,
+        certification: Option<MdpPermissions>, sign: F,
+    ) -> Result<Vec<u8>>
+    where
+        F: FnOnce(&[u8]) -> Result<Vec<u8>>,
+    {
+        match self.loaded_state {
+            Some(ref state) if state.file_size == file.len() => {}
+            _ => return Err(Error::NoRevision),
+        }
+        let page_id = *self
+            .get_pages()
+            .get(&options.page)
+            .ok_or(Error::PageNumberNotFound(options.page))?;
+        signature.set(
+            "ByteRange",
+            vec![
+                0.into(),
+                BYTE_RANGE_PLACEHOLDER.into(),
+                BYTE_RANGE_PLACEHOLDER.into(),
+                BYTE_RANGE_PLACEHOLDER.into(),
+            ],
+        );
+        signature.set(
+            "Contents",
+            Object::String(vec![0; options.contents_size], StringFormat::Hexadecimal),
+        );
+        if let Some(permissions) = certification {
+            if self.is_signed() {
+                return Err(Error::Certification);
+            }
+            signature.set(
+                "Reference",
+                vec![Object::Dictionary(dictionary! {
+                    "Type" => "SigRef",
+                    "TransformMethod" => "DocMDP",
+                    "TransformParams" => dictionary! {
+                        "Type" => "TransformParams",
+                        "P" => permissions as i64,
+                        "V" => "1.2",
+                    },
+                })],
+            );
+        }
+        let signature_id = self.add_object(signature);
+        if certification.is_some() {
+            let catalog_id = self.trailer.get(b"Root").and_then(Object::as_reference)?;
+            self.get_object_mut(catalog_id)
+                .and_then(Object::as_dict_mut)?
+                .set("Perms", dictionary! { "DocMDP" => signature_id });
+        }
+        let field_id = self.add_signature_field(page_id, &options.field_name, signature_id)?;
+        if let Some(ref appearance) = options.appearance {
+            let appearance_id = self.create_signature_appearance(options, appearance)?;
+            let field = self.get_object_mut(field_id).and_then(Object::as_dict_mut)?;
+            let rect = appearance.rect.normalized();
+            field.set(
+                "Rect",
+                vec![rect.llx.into(), rect.lly.into(), rect.urx.into(), rect.ury.into()],
+            );
+            field.set("AP", dictionary! { "N" => appearance_id });
+        }
+
+        let mut output = file.to_vec();
+        self.save_incremental(&mut output)?;
+
+        // The placeholders are in the update, with the byte range before the contents.
+        let placeholder = format!("/ByteRange[0 {0} {0} {0}]", BYTE_RANGE_PLACEHOLDER);
+        let byte_range_start = find(&output, placeholder.as_bytes(), file.len()).ok_or(Error::ObjectNotFound)?;
+        let contents_start = find(&output, b"/Contents<", byte_range_start).ok_or(Error::ObjectNotFound)? + 9;
+        let contents_end = contents_start + 2 * options.contents_size + 2;
+        let byte_range = [0, contents_start, contents_end, output.len() - contents_end];
+        let mut text = format!("/ByteRange[0 {} {} {}", byte_range[1], byte_range[2], byte_range[3]);
+        while text.len() < placeholder.len() - 1 {
+            text.push(' ');
+        }
+        text.push(']');
+        output[byte_range_start..byte_range_start + text.len()].copy_from_slice(text.as_bytes());
+
+        let mut signed = output[..contents_start].to_vec();
+        signed.extend_from_slice(&output[contents_end..]);
+        let contents = sign(&signed)?;
+        if contents.len() > options.contents_size {
+            return Err(Error::SignatureSize(contents.len()));
+        }
+        let hex: String = contents.iter().map(|byte| format!("{:02X}", byte)).collect();
+        output[contents_start + 1..contents_start + 1 + hex.len()].copy_from_slice(hex.as_bytes());
+
+        // The signature dictionary in memory matches the file, so it isn't written again by later updates.
+        let mut padded = contents;
+        padded.resize(options.contents_size, 0);
+        let signature = self.get_object_mut(signature_id).and_then(Object::as_dict_mut)?;
+        signature.set(
+            "ByteRange",
+            byte_range
+                .iter()
+                .map(|&offset| Object::Integer(offset as i64))
+                .collect::<Vec<_>>(),
+        );
+        signature.set("Contents", Object::String(padded, StringFormat::Hexadecimal));
+        self.mark_unchanged(signature_id);
+        Ok(output)
+    }
+
+    /// Get the certification signature of the document, as the id of its signature dictionary and the changes it
+    /// allows.
+    pub fn get_certification(&self) -> Result<(ObjectId, MdpPermissions)> {
+        let perms = self.dereference(self.catalog()?.get(b"Perms")?)?.1.as_dict()?;
+        let signature_id = perms.get(b"DocMDP").and_then(Object::as_reference)?;
+        let signature = self.get_dictionary(signature_id)?;
+        let references = signature.get(b"Reference").and_then(Object::as_array)?;
+        for reference in references {
+            let reference = self.dereference(reference)?.1.as_dict()?;
+            if reference.get(b"TransformMethod").and_then(Object::as_name_str).ok() != Some("DocMDP") {
+                continue;
+            }
+            let permissions = match reference.get(b"TransformParams") {
+                Ok(params) => self
+                    .dereference(params)?
+                    .1
+                    .as_dict()?
+                    .get(b"P")
+                    .and_then(Object::as_i64)
+                    .ok(),
+                Err(_) => None,
+            };
+            // The default is to allow form filling.
+            let permissions = MdpPermissions::from_i64(permissions.unwrap_or(2)).ok_or(Error::Type)?;
+            return Ok((signature_id, permissions));
+        }
+        Err(Error::DictKey)
+    }
+
+    /// Check the incremental updates after the certification signature against the changes it allows, returning
+    /// the objects changed without permission. Documents without a certification signature have no violations.
+    ///
+    /// Changes are judged per object, by the kind of object: validation data, signatures, form fields and their
+    /// appearances, annotations, and the entries of the catalog, pages and interactive form which refer to them.
+    /// Arrays are judged by the entries which refer to them, and form XObjects and fonts may only change when
+    /// nothing but widget appearances and the default resources of the interactive form use them. Changes to any
+    /// other object violate the permissions.
+    pub fn check_mdp_permissions(&self) -> Result<Vec<MdpViolation>> {
+        let (signature_id, permissions) = match self.get_certification() {
+            Ok(certification) => certification,
+            Err(_) => return Ok(Vec::new()),
+        };
+        let byte_range = self
+            .get_dictionary(signature_id)?
+            .get(b"ByteRange")
+            .and_then(Object::as_array)?;
+        let signed_end = match (byte_range.get(2), byte_range.get(3)) {
+            (Some(start), Some(length)) => (start.as_i64()? + length.as_i64()?) as usize,
+            _ => return Err(Error::Type),
+        };
+        let signed_revision = self
+            .revisions
+            .iter()
+            .rposition(|revision| revision.byte_range.start < signed_end)
+            .ok_or(Error::NoRevision)?;
+
+        let mut violations = Vec::new();
+        let mut before = self.rollback_to(signed_revision)?;
+        for (index, revision) in self.revisions.iter().enumerate().skip(signed_revision + 1) {
+            let after = self.rollback_to(index)?;
+            let validation = after.validation_objects();
+            let ids = revision.added.iter().chain(&revision.changed).chain(&revision.deleted);
+            for &id in ids {
+                if !validation.contains(&id) && !change_allowed(&before, &after, id, permissions) {
+                    violations.push(MdpViolation { revision: index, id });
+                }
+            }
+            before = after;
+        }
+        Ok(violations)
+    }
+
+    /// Whether a signature field of the document is signed.
+    fn is_signed(&self) -> bool {
+        self.catalog().and_then(|catalog| catalog.get(b"Perms")).is_ok()
+            || self.objects.values().any(|object| {
+                object.as_dict().is_ok_and(|dict| {
+                    dict.get(b"FT").and_then(Object::as_name_str).ok() == Some("Sig") && dict.has(b"V")
+                })
+            })
+    }
+
+    /// Get the ids of the document security store and the objects it refers to.
+    fn validation_objects(&self) -> BTreeSet<ObjectId> {
+        let mut ids = BTreeSet::new();
+        let mut pending: Vec<&Object> = self
+            .catalog()
+            .and_then(|catalog| catalog.get(b"DSS"))
+            .into_iter()
+            .collect();
+        while let Some(object) = pending.pop() {
+            match *object {
+                Object::Reference(id) if ids.insert(id) => pending.extend(self.objects.get(&id)),
+                Object::Array(ref array) => pending.extend(array),
+                Object::Dictionary(ref dict) => pending.extend(dict.iter().map(|(_, value)| value)),
+                _ => {}
+            }
+        }
+        ids
+    }
+
+    /// Create the form XObject of the visible appearance of a signature.
+    fn create_signature_appearance(
+        &mut self, options: &SignatureOptions, appearance: &SignatureAppearance,
+    ) -> Result<ObjectId> {
+        let rect = appearance.rect.normalized();
+        let (width, height) = (rect.width(), rect.height());
+        let mut lines = Vec::new();
+        if let Some(ref name) = options.name {
+            lines.push(format!("Digitally signed by {}", name));
+        }
+        if let Some(ref reason) = options.reason {
+            lines.push(format!("Reason: {}", reason));
+        }
+        if let Some(ref location) = options.location {
+            lines.push(format!("Location: {}", location));
+        }
+        if let Some(ref date) = options.signing_time {
+            lines.push(format!("Date: {}", format_date(date)));
+        }
+
+        let mut content = ContentBuilder::new();
+        let mut resources = Dictionary::new();
+        let mut text_left = 0.0;
+        if let Some(ref logo) = appearance.logo {
+            let logo_width = if lines.is_empty() { width } else { width * LOGO_WIDTH };
+            text_left = logo_width;
+            let image_width = logo.dict.get(b"Width").and_then(Object::as_float).unwrap_or(1.0);
+            let image_height = logo.dict.get(b"Height").and_then(Object::as_float).unwrap_or(1.0);
+            // The logo keeps its aspect ratio, centered in its part of the appearance.
+            let scale = ((logo_width - 2.0 * PADDING) / image_width).min((height - 2.0 * PADDING) / image_height);
+            if scale > 0.0 {
+                let (logo_width, logo_height) = (image_width * scale, image_height * scale);
+                let logo_id = self.add_object(logo.clone());
+                resources.set("XObject", dictionary! { "Im1" => logo_id });
+                content = content
+                    .save_state()
+                    .transform(Matrix::new(
+                        logo_width,
+                        0.0,
+                        0.0,
+                        logo_height,
+                        (text_left - logo_width) / 2.0,
+                        (height - logo_height) / 2.0,
+                    ))
+                    .draw_xobject("Im1")
+                    .restore_state();
+            }
+        }
+
+        if !lines.is_empty() {
+            let font = dictionary! {
+                "Type" => "Font",
+                "Subtype" => "Type1",
+                "BaseFont" => "Helvetica",
+                "Encoding" => "WinAnsiEncoding",
+            };
+            let texts: Vec<Vec<u8>> = lines
+                .iter()
+                .map(|line| Document::encode_text(Some("WinAnsiEncoding"), line))
+                .collect();
+            // The text is as large as fits, but not larger than 12 points.
+            let text_width_available = width - text_left - 2.0 * PADDING;
+            let widest = texts.iter().map(|text| text_width(&font, text)).fold(0.0, f64::max);
+            let mut size = ((height - 2.0 * PADDING) / (LINE_HEIGHT * texts.len() as f64)).min(12.0);
+            if widest * size > text_width_available && widest > 0.0 {
+                size = text_width_available / widest;
+            }
+            let leading = size * LINE_HEIGHT;
+            content = content.begin_text().set_font("F1", size).set_fill_color(&[0.0]);
+            for (index, text) in texts.into_iter().enumerate() {
+                // Leave room for descenders below the baseline.
+                let y = height - PADDING - leading * (index + 1) as f64 + (leading - size) / 2.0 + 0.22 * size;
+                content = content
+                    .set_text_matrix(Matrix::translate(text_left + PADDING, y))
+                    .show_text(text);
+            }
+            content = content.end_text();
+            resources.set("Font", dictionary! { "F1" => font });
+        }
+
+        let mut form = xobject::form(
+            vec![0.0, 0.0, width, height],
+            vec![1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+            content.encode()?,
+        );
+        form.dict.set("Resources", resources);
+        Ok(self.add_object(form))
+    }
+
+    /// Add a signature field with an invisible widget annotation to a page and the interactive form.
+    fn add_signature_field(&mut self, page_id: ObjectId, name: &str, signature_id: ObjectId) -> Result<ObjectId> {
+        let field_id = self.add_object(dictionary! {
+            "Type" => "Annot",
+            "Subtype" => "Widget",
+            "FT" => "Sig",
+            "T" => Object::String(encode_text_string(name), StringFormat::Literal),
+            "V" => signature_id,
+            "Rect" => vec![0.into(), 0.into(), 0.into(), 0.into()],
+            // Print and Locked.
+            "F" => 132,
+            "P" => page_id,
+        });
+
+        self.append_annotation(page_id, field_id)?;
+        // Signatures exist, and the document is to be updated incrementally only.
+        self.get_or_create_acro_form()?.set("SigFlags", 3);
+        self.append_root_field(field_id)?;
+        Ok(field_id)
+    }
+}
+
+fn name<'a>(dict: &'a Dictionary, key: &[u8]) -> Option<&'a str> {
+    dict.get(key).and_then(Object::as_name_str).ok()
+}
+
+fn is_annotation(dict: &Dictionary) -> bool {
+    name(dict, b"Type") == Some("Annot") || (dict.has(b"Rect") && dict.has(b"Subtype") && !dict.has(b"Type"))
+}
+
+fn is_widget(dict: &Dictionary) -> bool {
+    name(dict, b"Subtype") == Some("Widget")
+}
+
+/// Whether certification permissions allow an incremental update to add, change or delete an object.
+fn change_allowed(before: &Document, after: &Document, id: ObjectId, permissions: MdpPermissions) -> bool {
+    let dict = match after.objects.get(&id) {
+        Some(Object::Dictionary(dict)) => dict,
+        // Arrays are judged by the entries which refer to them.
+        Some(Object::Array(_)) => {
+            let referrers = referring_entries(after, id);
+            return !referrers.is_empty()
+                && referrers.into_iter().all(|(referrer_id, referrer, key)| {
+                    kind_allowed(after, referrer, permissions)
+                        .unwrap_or_else(|| entry_allowed(after, referrer_id, referrer, key, permissions))
+                });
+        }
+        Some(Object::Stream(stream)) => {
+            return permissions >= MdpPermissions::FormFilling
+                && name(&stream.dict, b"Subtype") == Some("Form")
+                && appearance_only(before, after, id)
+        }
+        Some(_) => return false,
+        None => {
+            return permissions >= MdpPermissions::Annotations
+                && before
+                    .get_dictionary(id)
+                    .is_ok_and(|dict| is_annotation(dict) && !is_widget(dict))
+        }
+    };
+    if name(dict, b"Type") == Some("Font") {
+        return permissions >= MdpPermissions::FormFilling && appearance_only(before, after, id);
+    }
+    if let Some(allowed) = kind_allowed(after, dict, permissions) {
+        return allowed;
+    }
+
+    // Containers may change the entries which refer to the objects judged above.
+    let old = match before.get_dictionary(id) {
+        Ok(old) => old,
+        Err(_) => return false,
+    };
+    old.iter()
+        .chain(dict.iter())
+        .all(|(key, _)| entry_allowed(after, id, dict, key, permissions) || old.get(key).ok() == dict.get(key).ok())
+}
+
+/// Whether a dictionary may change as a whole by its kind: document timestamps, form fields, widget and other
+/// annotations and signatures. Other dictionaries are containers, judged by their entries.
+fn kind_allowed(after: &Document, dict: &Dictionary, permissions: MdpPermissions) -> Option<bool> {
+    let is_document_timestamp = |dict: &Dictionary| name(dict, b"Type") == Some("DocTimeStamp");
+    if is_document_timestamp(dict) {
+        return Some(true);
+    }
+    if dict.has(b"FT") || (dict.has(b"Parent") && dict.has(b"T")) || is_widget(dict) {
+        let timestamp = dict
+            .get(b"V")
+            .and_then(Object::as_reference)
+            .and_then(|id| after.get_dictionary(id))
+            .is_ok_and(is_document_timestamp);
+        return Some(timestamp || permissions >= MdpPermissions::FormFilling);
+    }
+    if is_annotation(dict) {
+        return Some(permissions >= MdpPermissions::Annotations);
+    }
+    if name(dict, b"Type") == Some("Sig") {
+        return Some(permissions >= MdpPermissions::FormFilling);
+    }
+    None
+}
+
+/// Whether an entry of a container may change: the entries of the catalog, pages and interactive form which
+/// refer to fields, annotations and validation data.
+fn entry_allowed(after: &Document, id: ObjectId, dict: &Dictionary, key: &[u8], permissions: MdpPermissions) -> bool {
+    let acro_form_id = after
+        .catalog()
+        .and_then(|catalog| catalog.get(b"AcroForm"))
+        .and_then(Object::as_reference)
+        .ok();
+    let keys: &[&[u8]] = match name(dict, b"Type") {
+        Some("Catalog") => &[b"AcroForm", b"DSS", b"Extensions"],
+        Some("Page") => &[b"Annots"],
+        _ if Some(id) == acro_form_id => {
+            if permissions >= MdpPermissions::FormFilling {
+                return true;
+            }
+            &[b"Fields", b"SigFlags"]
+        }
+        _ => &[],
+    };
+    keys.contains(&key)
+}
+
+/// Get the dictionaries, with their ids, and the keys of their entries which refer to an object.
+fn referring_entries(doc: &Document, id: ObjectId) -> Vec<(ObjectId, &Dictionary, &[u8])> {
+    let mut entries = Vec::new();
+    for (&referrer_id, object) in &doc.objects {
+        let dict = match *object {
+            Object::Dictionary(ref dict) => dict,
+            Object::Stream(ref stream) => &stream.dict,
+            _ => continue,
+        };
+        for (key, value) in dict.iter() {
+            if value.as_reference().ok() == Some(id) {
+                entries.push((referrer_id, dict, key.as_slice()));
+            }
+        }
+    }
+    entries
+}
+
+/// Whether an object is new or only used by the appearances of widget annotations and the default resources of
+/// the interactive form, so that changing it can't change the content of pages.
+fn appearance_only(before: &Document, after: &Document, id: ObjectId) -> bool {
+    let mut ids = BTreeSet::new();
+    let mut pending: Vec<&Object> = after.trailer.get(b"Root").into_iter().collect();
+    while let Some(object) = pending.pop() {
+        match *object {
+            Object::Reference(id) if ids.insert(id) => pending.extend(after.objects.get(&id)),
+            Object::Array(ref array) => pending.extend(array),
+            Object::Dictionary(ref dict) | Object::Stream(Stream { ref dict, .. }) => {
+                let skipped: &[u8] = if is_widget(dict) {
+                    b"AP"
+                } else if dict.has(b"Fields") {
+                    b"DR"
+                } else {
+                    b""
+                };
+                pending.extend(
+                    dict.iter()
+                        .filter(|(key, _)| key.as_slice() != skipped)
+                        .map(|(_, value)| value),
+                );
+            }
+            _ => {}
+        }
+    }
+    !ids.contains(&id) || !before.objects.contains_key(&id)
+}
+
+/// Format a date for display, as `YYYY-MM-DD HH:mm:ss` followed by the offset from UTC if known.
+fn format_date(date: &PdfDate) -> String {
+    let mut text = format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        date.year, date.month, date.day, date.hour, date.minute, date.second
+    );
+    match date.utc_offset {
+        None => {}
+        Some(0) => text.push_str(" UTC"),
+        Some(offset) => {
+            let sign = if offset < 0 { '-' } else { '+' };
+            let offset = offset.unsigned_abs();
+            text.push_str(&format!(" {}{:02}:{:02}", sign, offset / 60, offset % 60));
+        }
+    }
+    text
+}
+
+/// Key of the VRI entry of a signature, the uppercase hexadecimal SHA-1 digest of its contents.
+#[cfg(feature = "crypto")]
+fn vri_key(signature: &Dictionary) -> Result<Vec<u8>> {
+    let contents = signature.get(b"Contents").and_then(Object::as_str)?;
+    Ok(Sha1::digest(contents)
+        .iter()
+        .flat_map(|byte| format!("{:02X}", byte).into_bytes())
+        .collect())
+}
+
+#[cfg(any(feature = "crypto", test))]
+fn stream_data(stream: &Stream) -> Vec<u8> {
+    stream.decompressed_content().unwrap_or_else(|_| stream.content.clone())
+}
+
+fn find(data: &[u8], pattern: &[u8], start: usize) -> Option<usize> {
+    data.get(start..)?
+        .windows(pattern.len())
+        .position(|window| window == pattern)
+        .map(|position| start + position)
+}
+
+#[cfg(feature = "crypto")]
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+#[test]
+fn add_document_timestamp() {
+    use crate::forms::FieldType;
+
+    let original = std::fs::read("assets/example.pdf").unwrap();
+    let mut doc = Document::load_mem(&original).unwrap();
+    let options = SignatureOptions {
+        contents_size: 64,
+        ..SignatureOptions::default()
+    };
+    let mut digest = Vec::new();
+    let output = doc
+        .add_document_timestamp(&original, &options, |data| {
+            digest = data.to_vec();
+            Ok(b"timestamp token".to_vec())
+        })
+        .unwrap();
+    assert_eq!(&output[..original.len()], &original[..]);
+    assert_eq!(digest.len(), 32);
+
+    let doc = Document::load_mem(&output).unwrap();
+    assert_eq!(doc.revisions().len(), 2);
+    let form = doc.get_form().unwrap();
+    let field = form.fields.iter().find(|field| field.name == "Signature1").unwrap();
+    assert_eq!(field.field_type, FieldType::Signature);
+    let signature_id = doc
+        .get_dictionary(field.id)
+        .and_then(|field| field.get(b"V"))
+        .and_then(Object::as_reference)
+        .unwrap();
+    let signature = doc.get_dictionary(signature_id).unwrap();
+    assert_eq!(
+        signature.get(b"Type").and_then(Object::as_name_str).unwrap(),
+        "DocTimeStamp"
+    );
+    let byte_range: Vec<usize> = signature
+        .get(b"ByteRange")
+        .and_then(Object::as_array)
+        .unwrap()
+        .iter()
+        .map(|offset| offset.as_i64().unwrap() as usize)
+        .collect();
+    assert_eq!(byte_range[0], 0);
+    assert_eq!(byte_range[2] + byte_range[3], output.len());
+    let mut signed = output[..byte_range[1]].to_vec();
+    signed.extend_from_slice(&output[byte_range[2]..]);
+    assert_eq!(&Sha256::digest(&signed)[..], &digest[..]);
+    let contents = signature.get(b"Contents").and_then(Object::as_str).unwrap();
+    assert!(contents.starts_with(b"timestamp token\0"));
+
+    let mut doc = Document::load_mem(&original).unwrap();
+    let result = doc.add_document_timestamp(&original, &options, |_| Ok(vec![0; 65]));
+    assert!(matches!(result, Err(Error::SignatureSize(65))));
+}
+
+#[cfg(feature = "crypto")]
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+#[test]
+fn add_validation_data() {
+    let original = std::fs::read("assets/example.pdf").unwrap();
+    let mut doc = Document::load_mem(&original).unwrap();
+    let signed = doc
+        .sign_incremental(&original, &SignatureOptions::default(), |_| Ok(b"signature".to_vec()))
+        .unwrap();
+
+    let mut doc = Document::load_mem(&signed).unwrap();
+    let signature_id = doc
+        .objects
+        .iter()
+        .find(|(_, object)| {
+            object
+                .as_dict()
+                .and_then(|dict| dict.get(b"Type"))
+                .and_then(Object::as_name_str)
+                .ok()
+                == Some("Sig")
+        })
+        .map(|(&id, _)| id)
+        .unwrap();
+    let chain = ValidationData {
+        certificates: vec![b"signer".to_vec(), b"root".to_vec()],
+        ocsp_responses: vec![b"ocsp".to_vec()],
+        crls: vec![],
+    };
+    doc.add_validation_data(Some(signature_id), &chain).unwrap();
+    let root_crl = ValidationData {
+        certificates: vec![b"root".to_vec()],
+        crls: vec![b"crl".to_vec()],
+        ..ValidationData::default()
+    };
+    doc.add_validation_data(None, &root_crl).unwrap();
+    let mut output = signed.clone();
+    doc.save_incremental(&mut output).unwrap();
+    assert_eq!(&output[..signed.len()], &signed[..]);
+
+    let doc = Document::load_mem(&output).unwrap();
+    assert_eq!(doc.get_validation_data(Some(signature_id)).unwrap(), chain);
+    assert_eq!(
+        doc.get_validation_data(None).unwrap(),
+        ValidationData {
+            certificates: vec![b"signer".to_vec(), b"root".to_vec()],
+            ocsp_responses: vec![b"ocsp".to_vec()],
+            crls: vec![b"crl".to_vec()],
+        }
+    );
+}
+
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+#[test]
+fn sign_with_visible_appearance() {
+    use crate::content::Content;
+
+    let original = std::fs::read("assets/example.pdf").unwrap();
+    let mut doc = Document::load_mem(&original).unwrap();
+    let logo = Stream::new(
+        dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Image",
+            "Width" => 2,
+            "Height" => 1,
+            "ColorSpace" => "DeviceGray",
+            "BitsPerComponent" => 8,
+        },
+        vec![0, 255],
+    );
+    let options = SignatureOptions {
+        name: Some("Jane Doe".to_string()),
+        reason: Some("Approval".to_string()),
+        signing_time: Some(PdfDate::new(2024, 5, 17, 9, 30, 0).with_utc_offset(120)),
+        appearance: Some(SignatureAppearance::new(Rectangle::new(300.0, 100.0, 500.0, 150.0)).with_logo(logo)),
+        ..SignatureOptions::default()
+    };
+    let signed = doc
+        .sign_incremental(&original, &options, |_| Ok(vec![1, 2, 3]))
+        .unwrap();
+
+    let doc = Document::load_mem(&signed).unwrap();
+    let form = doc.get_form().unwrap();
+    let widget = &form.fields[0].widgets[0];
+    assert_eq!(widget.rect, Some(Rectangle::new(300.0, 100.0, 500.0, 150.0)));
+    let appearance = doc
+        .get_dictionary(widget.id)
+        .and_then(|widget| widget.get(b"AP"))
+        .and_then(Object::as_dict)
+        .and_then(|ap| ap.get(b"N"))
+        .and_then(Object::as_reference)
+        .and_then(|id| doc.get_object(id))
+        .and_then(Object::as_stream)
+        .unwrap();
+    let resources = appearance.dict.get(b"Resources").and_then(Object::as_dict).unwrap();
+    assert!(resources.get(b"XObject").and_then(Object::as_dict).unwrap().has(b"Im1"));
+    let content = Content::decode(&stream_data(appearance)).unwrap();
+    let texts: Vec<&[u8]> = content
+        .operations
+        .iter()
+        .filter(|operation| operation.operator == "Tj")
+        .map(|operation| operation.operands[0].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        texts,
+        vec![
+            &b"Digitally signed by Jane Doe"[..],
+            b"Reason: Approval",
+            b"Date: 2024-05-17 09:30:00 +02:00"
+        ]
+    );
+}
+
+#[cfg(feature = "crypto")]
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+#[test]
+fn certify_and_check_later_revisions() {
+    let original = std::fs::read("assets/example.pdf").unwrap();
+    let mut doc = Document::load_mem(&original).unwrap();
+    let certification = SignatureOptions {
+        field_name: "Author".to_string(),
+        certification: Some(MdpPermissions::NoChanges),
+        ..SignatureOptions::default()
+    };
+    let certified = doc
+        .sign_incremental(&original, &certification, |_| Ok(vec![1]))
+        .unwrap();
+
+    let mut doc = Document::load_mem(&certified).unwrap();
+    let (signature_id, permissions) = doc.get_certification().unwrap();
+    assert_eq!(permissions, MdpPermissions::NoChanges);
+    assert!(doc.check_mdp_permissions().unwrap().is_empty());
+    let result = doc.sign_incremental(&certified, &certification, |_| Ok(vec![1]));
+    assert!(matches!(result, Err(Error::Certification)));
+
+    // Validation data and document timestamps are always allowed.
+    let mut doc = Document::load_mem(&certified).unwrap();
+    let data = ValidationData {
+        certificates: vec![b"certificate".to_vec()],
+        ..ValidationData::default()
+    };
+    doc.add_validation_data(Some(signature_id), &data).unwrap();
+    let mut validated = certified.clone();
+    doc.save_incremental(&mut validated).unwrap();
+    let options = SignatureOptions {
+        field_name: "Timestamp".to_string(),
+        ..SignatureOptions::default()
+    };
+    let timestamped = doc
+        .add_document_timestamp(&validated, &options, |_| Ok(vec![2]))
+        .unwrap();
+    let doc = Document::load_mem(&timestamped).unwrap();
+    assert_eq!(doc.revisions().len(), 4);
+    assert!(doc.check_mdp_permissions().unwrap().is_empty());
+
+    // Signing is only allowed from form filling on, and changing the page content never.
+    let options = SignatureOptions {
+        field_name: "Approval".to_string(),
+        ..SignatureOptions::default()
+    };
+    let mut doc = Document::load_mem(&timestamped).unwrap();
+    let mut approved = doc.sign_incremental(&timestamped, &options, |_| Ok(vec![3])).unwrap();
+    let content_id = doc.get_page_contents(doc.page_iter().next().unwrap())[0];
+    doc.replace_text(1, "Hello World!", "Changed!").unwrap();
+    doc.save_incremental(&mut approved).unwrap();
+
+    let doc = Document::load_mem(&approved).unwrap();
+    let violations = doc.check_mdp_permissions().unwrap();
+    assert!(violations.iter().any(|violation| violation.revision == 4));
+    assert_eq!(
+        violations
+            .iter()
+            .filter(|violation| violation.revision == 5)
+            .collect::<Vec<_>>(),
+        vec![&MdpViolation {
+            revision: 5,
+            id: content_id
+        }]
+    );
+
+    let mut doc = Document::load_mem(&approved).unwrap();
+    // With form filling allowed, only the changed page content remains.
+    let mut signature = doc.get_dictionary(signature_id).unwrap().clone();
+    signature.set(
+        "Reference",
+        vec![Object::Dictionary(dictionary! {
+            "TransformMethod" => "DocMDP",
+            "TransformParams" => dictionary! { "P" => 2 },
+        })],
+    );
+    doc.objects.insert(signature_id, Object::Dictionary(signature));
+    let violations = doc.check_mdp_permissions().unwrap();
+    assert_eq!(
+        violations,
+        vec![MdpViolation {
+            revision: 5,
+            id: content_id
+        }]
+    );
+}
+
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+#[test]
+fn check_page_content_changes_with_form_filling() {
+    let mut doc = crate::test_support::create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    let content_id = doc.get_page_contents(page_id)[0];
+    let form_id = doc.add_object(xobject::form(
+        vec![0.0, 0.0, 10.0, 10.0],
+        vec![1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+        b"0 0 10 10 re f".to_vec(),
+    ));
+    let draw_id = doc.add_object(Stream::new(dictionary! {}, b"q /X1 Do Q".to_vec()));
+    let contents_id = doc.add_object(vec![content_id.into(), draw_id.into()]);
+    let page = doc.get_object_mut(page_id).and_then(Object::as_dict_mut).unwrap();
+    page.set("Contents", contents_id);
+    page.set(
+        "Resources",
+        dictionary! { "XObject" => dictionary! { "X1" => form_id } },
+    );
+    let mut original = Vec::new();
+    doc.save_to(&mut original).unwrap();
+
+    let mut doc = Document::load_mem(&original).unwrap();
+    let certification = SignatureOptions {
+        certification: Some(MdpPermissions::FormFilling),
+        ..SignatureOptions::default()
+    };
+    let certified = doc
+        .sign_incremental(&original, &certification, |_| Ok(vec![1]))
+        .unwrap();
+
+    // Dropping a content stream through the indirect array of the page contents.
+    let mut doc = Document::load_mem(&certified).unwrap();
+    doc.objects.insert(contents_id, Object::Array(vec![content_id.into()]));
+    let mut changed = certified.clone();
+    doc.save_incremental(&mut changed).unwrap();
+    let violations = Document::load_mem(&changed).unwrap().check_mdp_permissions().unwrap();
+    assert_eq!(
+        violations,
+        vec![MdpViolation {
+            revision: 2,
+            id: contents_id
+        }]
+    );
+
+    // Redrawing a form XObject of the page resources.
+    let mut doc = Document::load_mem(&certified).unwrap();
+    let form = doc.get_object_mut(form_id).and_then(Object::as_stream_mut).unwrap();
+    form.set_plain_content(b"0 0 5 5 re f".to_vec());
+    let mut changed = certified.clone();
+    doc.save_incremental(&mut changed).unwrap();
+    let violations = Document::load_mem(&changed).unwrap().check_mdp_permissions().unwrap();
+    assert_eq!(
+        violations,
+        vec![MdpViolation {
+            revision: 2,
+            id: form_id
+        }]
+    );
+}
@@ -0,0 +1,163 @@
+use crate::{Dictionary, Document, Error, Object, ObjectId, Result};
+use std::collections::{BTreeMap, BTreeSet};
+
+impl Document {
+    /// Extract pages into a new standalone document.
+    ///
+    /// Objects reachable from the selected pages are copied into the new document and inherited page attributes
+    /// are set on the copied pages. References to other pages are replaced by null objects, so nothing else of
+    /// this document is carried over.
+    pub fn extract_pages<I: IntoIterator<Item = u32>>(&self, page_numbers: I) -> Result<Document> {
+        let pages = self.get_pages();
+        let mut document = Document::with_version(self.version.clone());
+        let pages_id = document.new_object_id();
+
+        let mut copier = ObjectCopier {
+            source: self,
+            ids: BTreeMap::new(),
+            pages: BTreeSet::new(),
+            queue: Vec::new(),
+        };
+        let mut kids = Vec::new();
+        for page_number in page_numbers {
+            let page_id = *pages.get(&page_number).ok_or(Error::PageNumberNotFound(page_number))?;
+            if copier.pages.insert(page_id) {
+                let new_id = document.new_object_id();
+                copier.ids.insert(page_id, new_id);
+                copier.queue.push(page_id);
+                kids.push(Object::Reference(new_id));
+            }
+        }
+
+        if let Ok(info) = self.trailer.get(b"Info") {
+            let mut info = info.clone();
+            copier.copy_references(&mut info, &mut document);
+            document.trailer.set("Info", info);
+        }
+        while let Some(id) = copier.queue.pop() {
+            let mut object = self.objects.get(&id).cloned().unwrap_or(Object::Null);
+            let is_page = copier.pages.contains(&id);
+            if is_page {
+                if let Ok(page) = object.as_dict_mut() {
+                    self.set_inherited_page_attributes(page);
+                    page.remove(b"Parent");
+                }
+            }
+            copier.copy_references(&mut object, &mut document);
+            if is_page {
+                if let Ok(page) = object.as_dict_mut() {
+                    page.set("Parent", pages_id);
+                }
+            }
+            document.objects.insert(copier.ids[&id], object);
+        }
+
+        document.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Count" => kids.len() as i64,
+                "Kids" => kids,
+            }),
+        );
+        let catalog_id = document.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        document.trailer.set("Root", catalog_id);
+        Ok(document)
+    }
+
+    /// Copy attributes a page inherits from its ancestors in the page tree onto the page itself.
+    fn set_inherited_page_attributes(&self, page: &mut Dictionary) {
+        const INHERITABLE: [&[u8]; 4] = [b"Resources", b"MediaBox", b"CropBox", b"Rotate"];
+        let mut visited = BTreeSet::new();
+        let mut parent = page.get(b"Parent").and_then(Object::as_reference).ok();
+        while let Some(parent_id) = parent.filter(|id| visited.insert(*id)) {
+            let node = match self.get_dictionary(parent_id) {
+                Ok(node) => node,
+                Err(_) => break,
+            };
+            for key in INHERITABLE.iter() {
+                if !page.has(key) {
+                    if let Ok(value) = node.get(key) {
+                        page.set(key.to_vec(), value.clone());
+                    }
+                }
+            }
+            parent = node.get(b"Parent").and_then(Object::as_reference).ok();
+        }
+    }
+}
+
+struct ObjectCopier<'a> {
+    source: &'a Document,
+    /// Map from source object ids to object ids in the target document.
+    ids: BTreeMap<ObjectId, ObjectId>,
+    /// Source ids of the pages being copied.
+    pages: BTreeSet<ObjectId>,
+    /// Source ids of objects still to be copied.
+    queue: Vec<ObjectId>,
+}
+
+impl ObjectCopier<'_> {
+    fn copy_references(&mut self, object: &mut Object, target: &mut Document) {
+        match *object {
+            Object::Array(ref mut array) => {
+                for item in array.iter_mut() {
+                    self.copy_references(item, target);
+                }
+            }
+            Object::Dictionary(ref mut dict) => {
+                for (_, value) in dict.iter_mut() {
+                    self.copy_references(value, target);
+                }
+            }
+            Object::Stream(ref mut stream) => {
+                for (_, value) in stream.dict.iter_mut() {
+                    self.copy_references(value, target);
+                }
+            }
+            Object::Reference(ref mut id) => {
+                if let Some(new_id) = self.ids.get(id) {
+                    *id = *new_id;
+                    return;
+                }
+                let is_page_tree = self
+                    .source
+                    .get_object(*id)
+                    .and_then(Object::type_name)
+                    .map(|name| ["Page", "Pages", "Catalog"].contains(&name));
+                match is_page_tree {
+                    // Other pages, page tree nodes and the catalog are not copied.
+                    Ok(true) | Err(Error::ObjectNotFound) => *object = Object::Null,
+                    _ => {
+                        let new_id = target.new_object_id();
+                        self.ids.insert(*id, new_id);
+                        self.queue.push(*id);
+                        *id = new_id;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+#[test]
+fn extract_pages() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    let mut other = create_document();
+    other.replace_text(1, "Hello World!", "Second page").unwrap();
+    doc.merge(other).unwrap();
+
+    let extracted = doc.extract_pages(vec![2]).unwrap();
+    assert_eq!(extracted.get_pages().len(), 1);
+    assert_eq!(extracted.extract_text(&[1]).unwrap(), "Second page\n");
+    // Catalog, page tree, page, content, resources, font and info dictionary.
+    assert_eq!(extracted.objects.len(), 7);
+    assert!(extracted.extract_pages(vec![2]).is_err());
+}
@@ -0,0 +1,803 @@
+use crate::content::{Content, ContentBuilder, Operation};
+#[cfg(feature = "fonts")]
+use crate::fonts::EmbeddedFont;
+use crate::forms::text_width;
+use crate::page::rotation_matrix;
+use crate::{
+    xobject, Dictionary, Document, Error, Matrix, Object, ObjectId, PageBox, PdfDate, Rectangle, Result, Stream,
+    StringFormat,
+};
+use std::collections::BTreeMap;
+
+/// Content drawn on pages by `Document::stamp_pages`, such as a watermark.
+#[derive(Debug, Clone)]
+pub enum Overlay<'a> {
+    /// Single line of text in one of the standard fonts, such as Helvetica or Times-Bold.
+    Text {
+        text: String,
+        font: String,
+        size: f64,
+        /// RGB color components.
+        color: [f64; 3],
+    },
+    /// Image XObject, drawn with the given width and height.
+    Image { image: Stream, width: f64, height: f64 },
+    /// Page of another document, drawn at the size of its crop box.
+    Page { document: &'a Document, page_id: ObjectId },
+}
+
+impl<'a> Overlay<'a> {
+    /// Gray text in Helvetica of the given size.
+    pub fn text(text: &str, size: f64) -> Overlay<'a> {
+        Overlay::Text {
+            text: text.to_owned(),
+            font: "Helvetica".to_owned(),
+            size,
+            color: [0.5, 0.5, 0.5],
+        }
+    }
+}
+
+/// Whether an overlay is drawn under or over the existing content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layer {
+    Under,
+    Over,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Position {
+    /// Center of the overlay at the center of the crop box.
+    Center,
+    /// Lower-left corner of the overlay at the given coordinates, rotating around it.
+    At(f64, f64),
+}
+
+/// Where and how an overlay is drawn.
+#[derive(Debug, Clone)]
+pub struct Placement {
+    pub layer: Layer,
+    pub position: Position,
+    pub scale: f64,
+    /// Counterclockwise rotation in degrees.
+    pub rotation: f64,
+    /// Opacity between 0 and 1.
+    pub opacity: f64,
+    /// Numbers of the pages to stamp, or all pages if empty.
+    pub pages: Vec<u32>,
+}
+
+impl Default for Placement {
+    fn default() -> Self {
+        Placement {
+            layer: Layer::Over,
+            position: Position::Center,
+            scale: 1.0,
+            rotation: 0.0,
+            opacity: 1.0,
+            pages: Vec::new(),
+        }
+    }
+}
+
+/// Position of text along the edges of pages, as displayed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgePosition {
+    TopLeft,
+    TopCenter,
+    TopRight,
+    BottomLeft,
+    BottomCenter,
+    BottomRight,
+}
+
+/// Font of text drawn along the edges of pages.
+#[derive(Debug, Clone)]
+pub enum StampFont {
+    /// One of the standard fonts, such as Helvetica or Times-Bold.
+    Standard(String),
+    /// Data of a TrueType font, or OpenType font with TrueType outlines, embedded with the glyphs used.
+    #[cfg(feature = "fonts")]
+    Embedded(Vec<u8>),
+}
+
+/// Appearance of text drawn along the edges of pages.
+#[derive(Debug, Clone)]
+pub struct TextStyle {
+    pub font: StampFont,
+    pub size: f64,
+    /// RGB color components.
+    pub color: [f64; 3],
+    /// Distance between the text and the edges of the crop box.
+    pub margin: f64,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        TextStyle {
+            font: StampFont::Standard("Helvetica".to_owned()),
+            size: 10.0,
+            color: [0.0, 0.0, 0.0],
+            margin: 36.0,
+        }
+    }
+}
+
+/// Headers and footers drawn by `Document::stamp_headers_footers`.
+///
+/// Texts may contain the tokens `{page}`, `{pages}` and `{date}`, which are replaced by the number of the page,
+/// the number of pages of the document and the date as YYYY-MM-DD.
+#[derive(Debug, Clone, Default)]
+pub struct HeaderFooter {
+    /// Texts at the left, center and right of the top of pages, which are empty for none.
+    pub header: [String; 3],
+    /// Texts at the left, center and right of the bottom of pages, which are empty for none.
+    pub footer: [String; 3],
+    pub style: TextStyle,
+    /// Date replacing `{date}`, or the current date.
+    pub date: Option<PdfDate>,
+    /// Numbers of the pages to stamp, or all pages if empty.
+    pub pages: Vec<u32>,
+}
+
+/// Bates numbers drawn by `Document::stamp_bates` and `stamp_bates`, which are a prefix, a number padded with
+/// zeros and a suffix, incremented for each page.
+#[derive(Debug, Clone)]
+pub struct BatesOptions {
+    pub prefix: String,
+    pub suffix: String,
+    /// Minimum number of digits.
+    pub digits: usize,
+    /// Number of the first page.
+    pub start: u64,
+    pub position: EdgePosition,
+    pub style: TextStyle,
+}
+
+impl Default for BatesOptions {
+    fn default() -> Self {
+        BatesOptions {
+            prefix: String::new(),
+            suffix: String::new(),
+            digits: 6,
+            start: 1,
+            position: EdgePosition::BottomRight,
+            style: TextStyle::default(),
+        }
+    }
+}
+
+impl BatesOptions {
+    /// Get the Bates number of a page.
+    pub fn label(&self, number: u64) -> String {
+        format!(
+            "{}{:0digits$}{}",
+            self.prefix,
+            number,
+            self.suffix,
+            digits = self.digits
+        )
+    }
+}
+
+/// Bates numbers assigned to the pages of a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatesRange {
+    pub first: u64,
+    pub last: u64,
+    pub first_label: String,
+    pub last_label: String,
+}
+
+/// Stamp Bates numbers on the pages of documents, continuing the numbering from one document to the next,
+/// and get the range assigned to each document.
+pub fn stamp_bates<'d, I>(documents: I, options: &BatesOptions) -> Result<Vec<BatesRange>>
+where
+    I: IntoIterator<Item = &'d mut Document>,
+{
+    let mut options = options.clone();
+    let mut ranges = Vec::new();
+    for document in documents {
+        let range = document.stamp_bates(&options)?;
+        options.start = range.last + 1;
+        ranges.push(range);
+    }
+    Ok(ranges)
+}
+
+/// Font used to draw text along the edges of pages, with the object id of its font dictionary.
+enum EdgeFont {
+    Standard(Dictionary, ObjectId),
+    #[cfg(feature = "fonts")]
+    Embedded(Box<EmbeddedFont>),
+}
+
+impl EdgeFont {
+    fn id(&self) -> ObjectId {
+        match *self {
+            EdgeFont::Standard(_, id) => id,
+            #[cfg(feature = "fonts")]
+            EdgeFont::Embedded(ref font) => font.id(),
+        }
+    }
+
+    /// Encode text, getting its width in thousandths of the font size.
+    fn encode(&mut self, text: &str) -> (Vec<u8>, f64) {
+        match *self {
+            EdgeFont::Standard(ref dict, _) => {
+                let encoded = Document::encode_text(Some("WinAnsiEncoding"), text);
+                let width = text_width(dict, &encoded) * 1000.0;
+                (encoded, width)
+            }
+            #[cfg(feature = "fonts")]
+            EdgeFont::Embedded(ref mut font) => (font.encode(text), font.width(text)),
+        }
+    }
+}
+
+impl Document {
+    /// Draw an overlay under or over the existing content of pages.
+    ///
+    /// The overlay is added once as a form XObject. Drawing it over the content wraps the existing
+    /// content in a saved graphics state, so that state left over by the page doesn't affect it.
+    pub fn stamp_pages(&mut self, overlay: &Overlay, placement: &Placement) -> Result<()> {
+        let pages = self.get_pages();
+        let page_ids = if placement.pages.is_empty() {
+            pages.values().copied().collect()
+        } else {
+            placement
+                .pages
+                .iter()
+                .map(|number| pages.get(number).copied().ok_or(Error::PageNumberNotFound(*number)))
+                .collect::<Result<Vec<ObjectId>>>()?
+        };
+
+        let (form, width, height) = self.create_overlay_form(overlay)?;
+        let form_id = self.add_object(form);
+        let form_name = format!("Stamp{}", form_id.0);
+        let graphics_state = if placement.opacity < 1.0 {
+            let state_id = self.add_object(dictionary! {
+                "Type" => "ExtGState",
+                "CA" => placement.opacity,
+                "ca" => placement.opacity,
+            });
+            Some((format!("GStamp{}", state_id.0), state_id))
+        } else {
+            None
+        };
+
+        for page_id in page_ids {
+            let crop_box = self.get_page_box(page_id, PageBox::CropBox)?;
+            self.add_xobject(page_id, form_name.as_bytes(), form_id)?;
+            let mut operations = vec![Operation::new("q", vec![])];
+            if let Some((ref state_name, state_id)) = graphics_state {
+                self.add_graphics_state(page_id, state_name.as_bytes(), state_id)?;
+                operations.push(Operation::new("gs", vec![Object::Name(state_name.as_bytes().to_vec())]));
+            }
+            let matrix = placement_matrix(placement, &crop_box, width, height);
+            operations.push(Operation::new("cm", matrix.to_operands()));
+            operations.push(Operation::new("Do", vec![Object::Name(form_name.as_bytes().to_vec())]));
+            operations.push(Operation::new("Q", vec![]));
+
+            let content = Content { operations }.encode()?;
+            match placement.layer {
+                Layer::Over => self.append_page_content(page_id, content)?,
+                Layer::Under => self.prepend_page_content(page_id, content)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Draw a page of another document under or over the existing content of a page, as for letterheads,
+    /// backgrounds and printing on templates. Returns the id of the form XObject of the imported page.
+    ///
+    /// The imported page is drawn with the lower-left corner of its crop box at the origin, transformed by the
+    /// matrix. Its resources are copied into this document.
+    pub fn overlay_page(
+        &mut self, page_id: ObjectId, source: &Document, source_page_id: ObjectId, matrix: &Matrix, layer: Layer,
+    ) -> Result<ObjectId> {
+        self.get_dictionary(page_id)?;
+        let overlay = Overlay::Page {
+            document: source,
+            page_id: source_page_id,
+        };
+        let (form, _, _) = self.create_overlay_form(&overlay)?;
+        let form_id = self.add_object(form);
+        let form_name = format!("Overlay{}", form_id.0);
+        self.add_xobject(page_id, form_name.as_bytes(), form_id)?;
+        let content = Content {
+            operations: vec![
+                Operation::new("q", vec![]),
+                Operation::new("cm", matrix.to_operands()),
+                Operation::new("Do", vec![Object::Name(form_name.into_bytes())]),
+                Operation::new("Q", vec![]),
+            ],
+        }
+        .encode()?;
+        match layer {
+            Layer::Over => self.append_page_content(page_id, content)?,
+            Layer::Under => self.prepend_page_content(page_id, content)?,
+        }
+        Ok(form_id)
+    }
+
+    /// Create a form XObject drawing the overlay with its lower-left corner at the origin, and get its size.
+    fn create_overlay_form(&mut self, overlay: &Overlay) -> Result<(Stream, f64, f64)> {
+        let identity = vec![1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+        match *overlay {
+            Overlay::Text {
+                ref text,
+                ref font,
+                size,
+                color,
+            } => {
+                let mut font_dict = dictionary! {
+                    "Type" => "Font",
+                    "Subtype" => "Type1",
+                    "BaseFont" => font.as_str(),
+                };
+                if font != "Symbol" && font != "ZapfDingbats" {
+                    font_dict.set("Encoding", "WinAnsiEncoding");
+                }
+                let encoded = Document::encode_text(Some("WinAnsiEncoding"), text);
+                let (width, height) = (text_width(&font_dict, &encoded) * size, size);
+                let content = ContentBuilder::new()
+                    .begin_text()
+                    .set_font("F1", size)
+                    .set_fill_color(&color)
+                    // Leave room for descenders below the baseline.
+                    .move_text(0.0, 0.2 * size)
+                    .show_text(encoded)
+                    .end_text();
+                let mut form = xobject::form(vec![0.0, 0.0, width, height], identity, content.encode()?);
+                form.dict
+                    .set("Resources", dictionary! { "Font" => dictionary! { "F1" => font_dict } });
+                Ok((form, width, height))
+            }
+            Overlay::Image {
+                ref image,
+                width,
+                height,
+            } => {
+                let image_id = self.add_object(image.clone());
+                let content = ContentBuilder::new()
+                    .save_state()
+                    .transform(Matrix::scale(width, height))
+                    .draw_xobject("Im1")
+                    .restore_state();
+                let mut form = xobject::form(vec![0.0, 0.0, width, height], identity, content.encode()?);
+                form.dict.set(
+                    "Resources",
+                    dictionary! { "XObject" => dictionary! { "Im1" => image_id } },
+                );
+                Ok((form, width, height))
+            }
+            Overlay::Page { document, page_id } => {
+                let crop_box = document.get_page_box(page_id, PageBox::CropBox)?;
+                let content = document.get_page_content(page_id)?;
+                let matrix = vec![1.0, 0.0, 0.0, 1.0, 0.0 - crop_box.llx, 0.0 - crop_box.lly];
+                let bbox = vec![crop_box.llx, crop_box.lly, crop_box.urx, crop_box.ury];
+                let mut form = xobject::form(bbox, matrix, content);
+                if let Some(resources) = document.get_page_attribute(page_id, b"Resources") {
+                    let resources = self.copy_foreign_object(document, resources, &mut BTreeMap::new());
+                    form.dict.set("Resources", resources);
+                }
+                Ok((form, crop_box.width(), crop_box.height()))
+            }
+        }
+    }
+
+    /// Draw headers and footers over the content of pages, in the orientation in which the pages are displayed.
+    pub fn stamp_headers_footers(&mut self, header_footer: &HeaderFooter) -> Result<()> {
+        let pages = self.get_pages();
+        let numbers: Vec<u32> = if header_footer.pages.is_empty() {
+            pages.keys().copied().collect()
+        } else {
+            header_footer.pages.clone()
+        };
+        let date = header_footer
+            .date
+            .unwrap_or_else(|| time::OffsetDateTime::now_utc().into());
+        let date = format!("{:04}-{:02}-{:02}", date.year, date.month, date.day);
+        let count = pages.len().to_string();
+        let positions = [
+            EdgePosition::TopLeft,
+            EdgePosition::TopCenter,
+            EdgePosition::TopRight,
+            EdgePosition::BottomLeft,
+            EdgePosition::BottomCenter,
+            EdgePosition::BottomRight,
+        ];
+
+        let mut texts = Vec::new();
+        for number in numbers {
+            let page_id = *pages.get(&number).ok_or(Error::PageNumberNotFound(number))?;
+            let page_texts = header_footer
+                .header
+                .iter()
+                .chain(header_footer.footer.iter())
+                .zip(positions.iter())
+                .filter(|(text, _)| !text.is_empty())
+                .map(|(text, &position)| {
+                    let text = text
+                        .replace("{page}", &number.to_string())
+                        .replace("{pages}", &count)
+                        .replace("{date}", &date);
+                    (position, text)
+                })
+                .collect();
+            texts.push((page_id, page_texts));
+        }
+        self.stamp_edge_texts(texts, &header_footer.style)
+    }
+
+    /// Stamp Bates numbers on all pages, starting from the number of the options, and get the assigned range.
+    ///
+    /// Fails for documents without pages.
+    pub fn stamp_bates(&mut self, options: &BatesOptions) -> Result<BatesRange> {
+        let page_ids: Vec<ObjectId> = self.page_iter().collect();
+        if page_ids.is_empty() {
+            return Err(Error::PageNumberNotFound(1));
+        }
+        let first = options.start;
+        let last = first + page_ids.len() as u64 - 1;
+        let texts = page_ids
+            .into_iter()
+            .zip(first..)
+            .map(|(page_id, number)| (page_id, vec![(options.position, options.label(number))]))
+            .collect();
+        self.stamp_edge_texts(texts, &options.style)?;
+        Ok(BatesRange {
+            first,
+            last,
+            first_label: options.label(first),
+            last_label: options.label(last),
+        })
+    }
+
+    /// Draw lines of text along the edges of pages, each page in a form XObject of its own.
+    fn stamp_edge_texts(
+        &mut self, texts: Vec<(ObjectId, Vec<(EdgePosition, String)>)>, style: &TextStyle,
+    ) -> Result<()> {
+        let mut font = match style.font {
+            StampFont::Standard(ref name) => {
+                let mut dict = dictionary! {
+                    "Type" => "Font",
+                    "Subtype" => "Type1",
+                    "BaseFont" => name.as_str(),
+                };
+                if name != "Symbol" && name != "ZapfDingbats" {
+                    dict.set("Encoding", "WinAnsiEncoding");
+                }
+                let id = self.add_object(dict.clone());
+                EdgeFont::Standard(dict, id)
+            }
+            #[cfg(feature = "fonts")]
+            StampFont::Embedded(ref data) => EdgeFont::Embedded(Box::new(EmbeddedFont::new(self, data.clone())?)),
+        };
+        let (size, margin) = (style.size, style.margin);
+
+        for (page_id, page_texts) in texts {
+            if page_texts.is_empty() {
+                continue;
+            }
+            // Lay out the text in the crop box as displayed, rotated back to the page by the matrix of the form.
+            let crop_box = self.get_page_box(page_id, PageBox::CropBox)?;
+            let rotation = self.get_page_rotation(page_id);
+            let to_displayed = rotation_matrix(rotation, &crop_box);
+            let frame = to_displayed.transform_rect(&crop_box);
+            let matrix = to_displayed.invert().unwrap_or(Matrix::IDENTITY);
+
+            let mut content = ContentBuilder::new()
+                .begin_text()
+                .set_font("F1", size)
+                .set_fill_color(&style.color);
+            for (position, text) in page_texts {
+                let (encoded, width) = font.encode(&text);
+                let width = width * size / 1000.0;
+                let x = match position {
+                    EdgePosition::TopLeft | EdgePosition::BottomLeft => frame.llx + margin,
+                    EdgePosition::TopCenter | EdgePosition::BottomCenter => (frame.llx + frame.urx - width) / 2.0,
+                    EdgePosition::TopRight | EdgePosition::BottomRight => frame.urx - margin - width,
+                };
+                // Leave room for ascenders above the header and descenders below the footer.
+                let y = match position {
+                    EdgePosition::TopLeft | EdgePosition::TopCenter | EdgePosition::TopRight => {
+                        frame.ury - margin - 0.8 * size
+                    }
+                    _ => frame.lly + margin + 0.2 * size,
+                };
+                content = content
+                    .set_text_matrix(Matrix::translate(x, y))
+                    .operation("Tj", vec![Object::String(encoded, StringFormat::Hexadecimal)]);
+            }
+            let bbox = vec![frame.llx, frame.lly, frame.urx, frame.ury];
+            let mut form = xobject::form(bbox, matrix.to_array().to_vec(), content.encode()?);
+            form.dict
+                .set("Resources", dictionary! { "Font" => dictionary! { "F1" => font.id() } });
+            let form_id = self.add_object(form);
+            let form_name = format!("Stamp{}", form_id.0);
+            self.add_xobject(page_id, form_name.as_bytes(), form_id)?;
+            let content = ContentBuilder::new()
+                .save_state()
+                .draw_xobject(&form_name)
+                .restore_state();
+            self.append_page_content(page_id, content.encode()?)?;
+        }
+
+        #[cfg(feature = "fonts")]
+        if let EdgeFont::Embedded(font) = font {
+            font.embed(self)?;
+        }
+        Ok(())
+    }
+}
+
+/// Matrix which scales and rotates an overlay of the given size and moves it to its position.
+fn placement_matrix(placement: &Placement, crop_box: &Rectangle, width: f64, height: f64) -> Matrix {
+    let (sin, cos) = placement.rotation.to_radians().sin_cos();
+    let (a, b, c, d) = (
+        placement.scale * cos,
+        placement.scale * sin,
+        0.0 - placement.scale * sin,
+        placement.scale * cos,
+    );
+    let (x, y) = match placement.position {
+        Position::At(x, y) => (x, y),
+        Position::Center => {
+            let center_x = (crop_box.llx + crop_box.urx) / 2.0;
+            let center_y = (crop_box.lly + crop_box.ury) / 2.0;
+            (
+                center_x - (a * width + c * height) / 2.0,
+                center_y - (b * width + d * height) / 2.0,
+            )
+        }
+    };
+    Matrix::new(a, b, c, d, x, y)
+}
+
+#[test]
+fn stamp_text_and_page() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    let placement = Placement {
+        rotation: 45.0,
+        opacity: 0.3,
+        ..Placement::default()
+    };
+    doc.stamp_pages(&Overlay::text("DRAFT", 72.0), &placement).unwrap();
+
+    let other = create_document();
+    let other_page_id = other.page_iter().next().unwrap();
+    let overlay = Overlay::Page {
+        document: &other,
+        page_id: other_page_id,
+    };
+    let placement = Placement {
+        layer: Layer::Under,
+        position: Position::At(10.0, 10.0),
+        scale: 0.25,
+        pages: vec![1],
+        ..Placement::default()
+    };
+    doc.stamp_pages(&overlay, &placement).unwrap();
+    assert!(doc
+        .stamp_pages(
+            &overlay,
+            &Placement {
+                pages: vec![2],
+                ..Placement::default()
+            }
+        )
+        .is_err());
+
+    let content = String::from_utf8(doc.get_page_content(page_id).unwrap()).unwrap();
+    let under = content.find("0.25 0.0 0.0 0.25 10.0 10.0 cm").unwrap();
+    let original = content.find("(Hello World!) Tj").unwrap();
+    let over = content.find("/GStamp").unwrap();
+    assert!(under < original && original < over);
+
+    // Inherited resources are kept when adding the stamps to the page.
+    let resources = doc.get_page_resources(page_id).0.unwrap();
+    assert!(resources.has(b"Font"));
+    assert_eq!(resources.get(b"XObject").and_then(Object::as_dict).unwrap().len(), 2);
+    let mut buffer = Vec::new();
+    doc.save_to(&mut buffer).unwrap();
+}
+
+#[test]
+fn overlay_pages() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    let mut letterhead = create_document();
+    let letterhead_page_id = letterhead.page_iter().next().unwrap();
+    letterhead
+        .set_page_box(
+            letterhead_page_id,
+            PageBox::CropBox,
+            Rectangle::new(0.0, 742.0, 595.0, 842.0),
+        )
+        .unwrap();
+
+    let form_id = doc
+        .overlay_page(
+            page_id,
+            &letterhead,
+            letterhead_page_id,
+            &Matrix::translate(0.0, 742.0),
+            Layer::Under,
+        )
+        .unwrap();
+    let form = doc.get_object(form_id).and_then(Object::as_stream).unwrap();
+    assert_eq!(
+        form.dict.get(b"BBox").and_then(Rectangle::from_object).unwrap(),
+        Rectangle::new(0.0, 742.0, 595.0, 842.0)
+    );
+    assert!(form
+        .dict
+        .get(b"Resources")
+        .and_then(Object::as_dict)
+        .unwrap()
+        .has(b"Font"));
+    let background = create_document();
+    let background_page_id = background.page_iter().next().unwrap();
+    doc.overlay_page(page_id, &background, background_page_id, &Matrix::IDENTITY, Layer::Over)
+        .unwrap();
+
+    let content = String::from_utf8(doc.get_page_content(page_id).unwrap()).unwrap();
+    let under = content.find(&format!("/Overlay{} Do", form_id.0)).unwrap();
+    let original = content.find("(Hello World!) Tj").unwrap();
+    let over = content.rfind("/Overlay").unwrap();
+    assert!(under < original && original < over);
+    assert_eq!(
+        doc.get_page_resources(page_id)
+            .0
+            .unwrap()
+            .get(b"XObject")
+            .and_then(Object::as_dict)
+            .unwrap()
+            .len(),
+        2
+    );
+    assert!(doc
+        .overlay_page(
+            (999, 0),
+            &background,
+            background_page_id,
+            &Matrix::IDENTITY,
+            Layer::Over
+        )
+        .is_err());
+}
+
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+#[test]
+fn stamp_headers_and_footers() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    doc.rotate_page(page_id, 90).unwrap();
+    let header_footer = HeaderFooter {
+        header: [String::new(), "Report of {date}".to_owned(), String::new()],
+        footer: [String::new(), String::new(), "Page {page} of {pages}".to_owned()],
+        date: Some(PdfDate::new(2024, 3, 9, 0, 0, 0)),
+        ..HeaderFooter::default()
+    };
+    doc.stamp_headers_footers(&header_footer).unwrap();
+
+    let forms: Vec<&Stream> = doc
+        .objects
+        .values()
+        .filter_map(|object| object.as_stream().ok())
+        .filter(|stream| stream.dict.has(b"BBox"))
+        .collect();
+    assert_eq!(forms.len(), 1);
+    // The page is displayed in landscape, with the rotation undone by the matrix of the form.
+    let bbox = forms[0].dict.get(b"BBox").and_then(Rectangle::from_object).unwrap();
+    assert_eq!(bbox, Rectangle::new(0.0, 0.0, 842.0, 595.0));
+    let matrix = forms[0].dict.get(b"Matrix").and_then(Matrix::from_object).unwrap();
+    assert_eq!(
+        matrix.transform_point(crate::Point::new(0.0, 0.0)),
+        crate::Point::new(595.0, 0.0)
+    );
+    let content = Content::decode(&forms[0].decompressed_content().unwrap()).unwrap();
+    let texts: Vec<&[u8]> = content
+        .operations
+        .iter()
+        .filter(|operation| operation.operator == "Tj")
+        .map(|operation| operation.operands[0].as_str().unwrap())
+        .collect();
+    assert_eq!(texts, [&b"Report of 2024-03-09"[..], b"Page 1 of 1"]);
+    let tm = content
+        .operations
+        .iter()
+        .find(|operation| operation.operator == "Tm")
+        .unwrap();
+    assert_eq!(tm.operands[5].as_float().unwrap(), 595.0 - 36.0 - 8.0);
+
+    #[cfg(feature = "fonts")]
+    {
+        let mut doc = create_document();
+        let style = TextStyle {
+            font: StampFont::Embedded(std::fs::read("assets/DejaVuSans-ASCII.ttf").unwrap()),
+            ..TextStyle::default()
+        };
+        let header_footer = HeaderFooter {
+            footer: [String::new(), "{page}".to_owned(), String::new()],
+            style,
+            pages: vec![1],
+            ..HeaderFooter::default()
+        };
+        doc.stamp_headers_footers(&header_footer).unwrap();
+        assert!(doc.objects.values().any(|object| object
+            .as_dict()
+            .map(|dict| dict.get(b"Subtype").and_then(Object::as_name_str).ok() == Some("Type0"))
+            .unwrap_or(false)));
+        assert!(doc
+            .stamp_headers_footers(&HeaderFooter {
+                pages: vec![2],
+                ..header_footer
+            })
+            .is_err());
+    }
+}
+
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+#[test]
+fn stamp_bates_numbers() {
+    use crate::test_support::create_document;
+
+    let mut first = create_document();
+    let mut second = create_document();
+    let options = BatesOptions {
+        prefix: "ABC".to_owned(),
+        start: 98,
+        ..BatesOptions::default()
+    };
+    let ranges = stamp_bates(vec![&mut first, &mut second], &options).unwrap();
+    assert_eq!(
+        ranges,
+        vec![
+            BatesRange {
+                first: 98,
+                last: 98,
+                first_label: "ABC000098".to_owned(),
+                last_label: "ABC000098".to_owned(),
+            },
+            BatesRange {
+                first: 99,
+                last: 99,
+                first_label: "ABC000099".to_owned(),
+                last_label: "ABC000099".to_owned(),
+            },
+        ]
+    );
+    assert_eq!(
+        BatesOptions {
+            digits: 2,
+            suffix: "-C".to_owned(),
+            ..options
+        }
+        .label(1234),
+        "ABC1234-C"
+    );
+
+    let form = second
+        .objects
+        .values()
+        .filter_map(|object| object.as_stream().ok())
+        .find(|stream| stream.dict.has(b"BBox"))
+        .unwrap();
+    // Small streams are left uncompressed.
+    let content = form.decompressed_content().unwrap_or_else(|_| form.content.clone());
+    let content = Content::decode(&content).unwrap();
+    assert!(content
+        .operations
+        .iter()
+        .any(|operation| operation.operator == "Tj" && operation.operands[0].as_str().unwrap() == b"ABC000099"));
+}
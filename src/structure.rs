@@ -0,0 +1,966 @@
+#![cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+
+//! Logical structure of tagged documents: the tree of structure elements of the /StructTreeRoot of the catalog,
+//! and the marked content of pages which they refer to by marked-content identifiers (MCIDs), and the creation of
+//! tagged content.
+
+use crate::content::{Content, Operation};
+use crate::encodings::{decode_text_string, encode_text_string};
+use crate::interpreter::{ContentHandler, ContentInterpreter, GraphicsState};
+use crate::text::TextFragment;
+use crate::{Dictionary, Document, Error, Object, ObjectId, Result, StringFormat};
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+
+/// Structure tree of a tagged document.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StructureTree {
+    /// Mapping of the structure types used in the document to standard structure types, from /RoleMap.
+    pub role_map: BTreeMap<String, String>,
+    pub kids: Vec<StructureKid>,
+}
+
+impl StructureTree {
+    /// Iterate over the structure elements of the tree, depth first in document order.
+    pub fn elements(&self) -> impl Iterator<Item = &StructureElement> {
+        let mut stack: Vec<&StructureKid> = self.kids.iter().rev().collect();
+        std::iter::from_fn(move || loop {
+            if let StructureKid::Element(element) = stack.pop()? {
+                stack.extend(element.kids.iter().rev());
+                return Some(element);
+            }
+        })
+    }
+
+    /// Map a structure type to a standard structure type through the role map, following chains of mappings.
+    pub fn resolve_role<'a>(&'a self, structure_type: &'a str) -> &'a str {
+        let mut role = structure_type;
+        let mut seen = BTreeSet::new();
+        while let Some(mapped) = self.role_map.get(role) {
+            if !seen.insert(role) {
+                break;
+            }
+            role = mapped;
+        }
+        role
+    }
+}
+
+/// Element of the structure tree, a /StructElem dictionary.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructureElement {
+    pub id: Option<ObjectId>,
+    /// Structure type /S, as used in the document.
+    pub structure_type: String,
+    /// Standard structure type which the structure type is mapped to by the role map.
+    pub role: String,
+    pub title: Option<String>,
+    /// Alternate description /Alt, such as of figures.
+    pub alt: Option<String>,
+    /// Replacement text /ActualText of the content.
+    pub actual_text: Option<String>,
+    pub lang: Option<String>,
+    /// Page containing the content of the element, unless given by its kids.
+    pub page: Option<ObjectId>,
+    pub kids: Vec<StructureKid>,
+}
+
+/// Kid of a structure element.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StructureKid {
+    Element(StructureElement),
+    /// Sequence of marked content, identified by its MCID in the content of a page, or of a stream such as a form
+    /// XObject drawn on the page.
+    MarkedContent {
+        page: Option<ObjectId>,
+        stream: Option<ObjectId>,
+        mcid: i64,
+    },
+    /// Whole object, such as an annotation, referred to by an object reference dictionary.
+    Object {
+        page: Option<ObjectId>,
+        object: ObjectId,
+    },
+}
+
+/// Sequence of marked content of a page, between a BMC or BDC operator and the matching EMC operator.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarkedContent {
+    pub tag: String,
+    /// Marked-content identifier, which links the content to the structure tree.
+    pub mcid: Option<i64>,
+    /// Text shown within the sequence, including that of nested sequences.
+    pub text: String,
+}
+
+/// Optional entries of a structure element created by `Document::add_structure_element`.
+#[derive(Debug, Clone, Default)]
+pub struct StructureAttributes {
+    pub title: Option<String>,
+    /// Alternate description /Alt, which is required for figures of accessible documents.
+    pub alt: Option<String>,
+    pub actual_text: Option<String>,
+    pub lang: Option<String>,
+}
+
+impl Document {
+    /// Read the structure tree of /StructTreeRoot of the catalog, or `None` if the document isn't tagged.
+    pub fn structure_tree(&self) -> Result<Option<StructureTree>> {
+        let root = match self.catalog()?.get(b"StructTreeRoot") {
+            Ok(root) => self.dereference(root)?.1.as_dict()?,
+            Err(_) => return Ok(None),
+        };
+        let role_map = root
+            .get(b"RoleMap")
+            .and_then(|role_map| self.dereference(role_map))
+            .and_then(|(_, role_map)| role_map.as_dict())
+            .map(|role_map| {
+                role_map
+                    .iter()
+                    .filter_map(|(key, role)| {
+                        let role = role.as_name_str().ok()?;
+                        Some((String::from_utf8_lossy(key).into_owned(), role.to_owned()))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let mut tree = StructureTree {
+            role_map,
+            kids: Vec::new(),
+        };
+        let mut visited = BTreeSet::new();
+        if let Ok(kids) = root.get(b"K") {
+            tree.kids = self.structure_kids(kids, None, &tree, &mut visited);
+        }
+        Ok(Some(tree))
+    }
+
+    /// Get the figures of the structure tree, elements whose role is Figure or Formula, which have neither
+    /// alternate description nor replacement text.
+    pub fn figures_without_alt_text(&self) -> Result<Vec<StructureElement>> {
+        let tree = match self.structure_tree()? {
+            Some(tree) => tree,
+            None => return Ok(Vec::new()),
+        };
+        let is_blank = |text: &Option<String>| text.as_deref().unwrap_or_default().trim().is_empty();
+        Ok(tree
+            .elements()
+            .filter(|element| matches!(element.role.as_str(), "Figure" | "Formula"))
+            .filter(|element| is_blank(&element.alt) && is_blank(&element.actual_text))
+            .cloned()
+            .collect())
+    }
+
+    /// Set the alternate description /Alt of a structure element, or remove it for an empty text.
+    pub fn set_alt_text(&mut self, element_id: ObjectId, alt: &str) -> Result<()> {
+        let element = self.get_object_mut(element_id).and_then(Object::as_dict_mut)?;
+        if !element.has(b"S") {
+            return Err(Error::Type);
+        }
+        if alt.is_empty() {
+            element.remove(b"Alt");
+        } else {
+            element.set("Alt", Object::String(encode_text_string(alt), StringFormat::Literal));
+        }
+        Ok(())
+    }
+
+    /// Get the structure element which contains the marked content with an MCID on a page, using the
+    /// /ParentTree of the structure tree root and the /StructParents entry of the page.
+    pub fn structure_parent(&self, page_id: ObjectId, mcid: i64) -> Result<Option<ObjectId>> {
+        let key = match self.get_dictionary(page_id)?.get(b"StructParents") {
+            Ok(key) => key.as_i64()?,
+            Err(_) => return Ok(None),
+        };
+        let parents = match self.parent_tree_entry(key)? {
+            Some(parents) => parents,
+            None => return Ok(None),
+        };
+        let parents = self.dereference(&parents)?.1.as_array()?;
+        Ok(usize::try_from(mcid)
+            .ok()
+            .and_then(|mcid| parents.get(mcid))
+            .and_then(|parent| parent.as_reference().ok()))
+    }
+
+    /// Get the sequences of marked content of a page in the order they begin, with the text shown within them.
+    /// The properties of BDC operators are either inline or named in the /Properties resources of the page.
+    pub fn marked_content(&self, page_id: ObjectId) -> Result<Vec<MarkedContent>> {
+        let content = Content::decode(&self.get_page_content(page_id)?)?;
+        let properties = self
+            .get_page_resources(page_id)
+            .0
+            .and_then(|resources| resources.get(b"Properties").ok())
+            .and_then(|properties| self.dereference(properties).ok())
+            .and_then(|(_, properties)| properties.as_dict().ok());
+
+        // Find the sequences enclosing each operation, innermost last.
+        let mut sequences = Vec::new();
+        let mut enclosing = Vec::with_capacity(content.operations.len());
+        let mut open = Vec::new();
+        for operation in &content.operations {
+            match operation.operator.as_str() {
+                operator @ ("BMC" | "BDC") => {
+                    let tag = operation
+                        .operands
+                        .first()
+                        .and_then(|tag| tag.as_name_str().ok())
+                        .unwrap_or_default();
+                    let mcid = if operator == "BDC" {
+                        operation
+                            .operands
+                            .get(1)
+                            .and_then(|dict| match dict {
+                                Object::Name(name) => properties?.get(name).ok(),
+                                dict => Some(dict),
+                            })
+                            .and_then(|dict| self.dereference(dict).ok())
+                            .and_then(|(_, dict)| dict.as_dict().ok())
+                            .and_then(|dict| dict.get(b"MCID").and_then(Object::as_i64).ok())
+                    } else {
+                        None
+                    };
+                    open.push(sequences.len());
+                    sequences.push(MarkedContent {
+                        tag: tag.to_owned(),
+                        mcid,
+                        text: String::new(),
+                    });
+                }
+                "EMC" => {
+                    open.pop();
+                }
+                _ => {}
+            }
+            enclosing.push(open.clone());
+        }
+
+        let mut interpreter = ContentInterpreter::new(
+            self,
+            MarkedText {
+                enclosing,
+                current: Vec::new(),
+                sequences,
+            },
+        );
+        interpreter.run_page(page_id)?;
+        Ok(interpreter.into_handler().sequences)
+    }
+
+    /// Extract the text of pages in the logical order of the structure tree rather than the order of their
+    /// content, which differs for layouts such as multiple columns. Paragraphs, headings, list items and table
+    /// rows end with a line break, and table cells are separated by tabs. Replacement text /ActualText is used
+    /// instead of the text of the content of an element.
+    ///
+    /// Untagged documents fall back to `extract_text`. Content which isn't part of the structure tree, such as
+    /// artifacts, is left out.
+    pub fn extract_text_in_reading_order(&self, page_numbers: &[u32]) -> Result<String> {
+        let tree = match self.structure_tree()? {
+            Some(tree) => tree,
+            None => return self.extract_text(page_numbers),
+        };
+        let pages = self.get_pages();
+        let mut texts = BTreeMap::new();
+        for page_number in page_numbers {
+            let page_id = *pages.get(page_number).ok_or(Error::PageNumberNotFound(*page_number))?;
+            for marked in self.marked_content(page_id)? {
+                if let Some(mcid) = marked.mcid {
+                    texts
+                        .entry((page_id, mcid))
+                        .or_insert_with(String::new)
+                        .push_str(&marked.text);
+                }
+            }
+        }
+        let mut text = String::new();
+        for kid in &tree.kids {
+            write_reading_order(kid, &texts, &mut text);
+        }
+        Ok(text)
+    }
+
+    /// Look up the value of a key of the /ParentTree number tree of the structure tree root.
+    fn parent_tree_entry(&self, key: i64) -> Result<Option<Object>> {
+        let root = match self.catalog()?.get(b"StructTreeRoot") {
+            Ok(root) => self.dereference(root)?.1.as_dict()?,
+            Err(_) => return Ok(None),
+        };
+        let mut entries = Vec::new();
+        if let Ok(parent_tree) = root.get(b"ParentTree") {
+            self.collect_number_tree(parent_tree, &mut entries, &mut Vec::new());
+        }
+        Ok(entries.into_iter().find(|entry| entry.0 == key).map(|entry| entry.1))
+    }
+
+    /// Read the kids /K of a structure element, which are either a single kid or an array of them.
+    fn structure_kids(
+        &self, kids: &Object, page: Option<ObjectId>, tree: &StructureTree, visited: &mut BTreeSet<ObjectId>,
+    ) -> Vec<StructureKid> {
+        match self.dereference(kids) {
+            Ok((None, Object::Array(kids))) => kids
+                .iter()
+                .filter_map(|kid| self.structure_kid(kid, page, tree, visited))
+                .collect(),
+            Ok(_) => self.structure_kid(kids, page, tree, visited).into_iter().collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    fn structure_kid(
+        &self, kid: &Object, page: Option<ObjectId>, tree: &StructureTree, visited: &mut BTreeSet<ObjectId>,
+    ) -> Option<StructureKid> {
+        let (id, kid) = self.dereference(kid).ok()?;
+        let dict = match kid {
+            Object::Integer(mcid) => {
+                return Some(StructureKid::MarkedContent {
+                    page,
+                    stream: None,
+                    mcid: *mcid,
+                })
+            }
+            Object::Dictionary(dict) => dict,
+            _ => return None,
+        };
+        let reference = |key: &[u8]| dict.get(key).and_then(Object::as_reference).ok();
+        let kid_page = reference(b"Pg").or(page);
+        match dict.get(b"Type").and_then(Object::as_name_str) {
+            Ok("MCR") => Some(StructureKid::MarkedContent {
+                page: kid_page,
+                stream: reference(b"Stm"),
+                mcid: dict.get(b"MCID").and_then(Object::as_i64).ok()?,
+            }),
+            Ok("OBJR") => Some(StructureKid::Object {
+                page: kid_page,
+                object: reference(b"Obj")?,
+            }),
+            _ => {
+                if let Some(id) = id {
+                    if !visited.insert(id) {
+                        return None;
+                    }
+                }
+                Some(StructureKid::Element(
+                    self.structure_element(id, dict, page, tree, visited),
+                ))
+            }
+        }
+    }
+
+    fn structure_element(
+        &self, id: Option<ObjectId>, dict: &Dictionary, page: Option<ObjectId>, tree: &StructureTree,
+        visited: &mut BTreeSet<ObjectId>,
+    ) -> StructureElement {
+        let text = |key: &[u8]| {
+            dict.get(key)
+                .and_then(|text| self.dereference(text))
+                .and_then(|(_, text)| text.as_str())
+                .map(decode_text_string)
+                .ok()
+        };
+        let structure_type = dict
+            .get(b"S")
+            .and_then(Object::as_name_str)
+            .unwrap_or_default()
+            .to_owned();
+        let page = dict.get(b"Pg").and_then(Object::as_reference).ok().or(page);
+        let kids = match dict.get(b"K") {
+            Ok(kids) => self.structure_kids(kids, page, tree, visited),
+            Err(_) => Vec::new(),
+        };
+        StructureElement {
+            id,
+            role: tree.resolve_role(&structure_type).to_owned(),
+            structure_type,
+            title: text(b"T"),
+            alt: text(b"Alt"),
+            actual_text: text(b"ActualText"),
+            lang: text(b"Lang"),
+            page,
+            kids,
+        }
+    }
+
+    /// Get the structure tree root of the catalog, creating an empty one if the document isn't tagged yet, and
+    /// mark the document as tagged in /MarkInfo.
+    pub fn structure_root(&mut self) -> Result<ObjectId> {
+        let catalog_id = self.trailer.get(b"Root").and_then(Object::as_reference)?;
+        let root_id = match self.catalog()?.get(b"StructTreeRoot").ok().cloned() {
+            Some(Object::Reference(root_id)) => root_id,
+            Some(Object::Dictionary(root)) => self.add_object(root),
+            _ => self.add_object(dictionary! { "Type" => "StructTreeRoot" }),
+        };
+        let catalog = self.get_object_mut(catalog_id).and_then(Object::as_dict_mut)?;
+        catalog.set("StructTreeRoot", root_id);
+        match catalog.get_mut(b"MarkInfo").and_then(Object::as_dict_mut) {
+            Ok(mark_info) => mark_info.set("Marked", true),
+            Err(_) => catalog.set("MarkInfo", dictionary! { "Marked" => true }),
+        }
+        Ok(root_id)
+    }
+
+    /// Create a structure element of a structure type, and add it to the kids of a parent element or of the
+    /// structure tree root.
+    pub fn add_structure_element(
+        &mut self, parent_id: ObjectId, structure_type: &str, attributes: &StructureAttributes,
+    ) -> Result<ObjectId> {
+        let mut element = dictionary! {
+            "Type" => "StructElem",
+            "S" => Object::Name(structure_type.as_bytes().to_vec()),
+            "P" => parent_id,
+        };
+        let entries = [
+            ("T", &attributes.title),
+            ("Alt", &attributes.alt),
+            ("ActualText", &attributes.actual_text),
+            ("Lang", &attributes.lang),
+        ];
+        for (key, text) in entries {
+            if let Some(text) = text {
+                element.set(key, Object::String(encode_text_string(text), StringFormat::Literal));
+            }
+        }
+        let element_id = self.add_object(element);
+        self.add_structure_kid(parent_id, element_id.into())?;
+        Ok(element_id)
+    }
+
+    /// Map a structure type used in the document to a standard structure type in the /RoleMap of the structure
+    /// tree root.
+    pub fn add_role_mapping(&mut self, structure_type: &str, role: &str) -> Result<()> {
+        let root_id = self.structure_root()?;
+        let root = self.get_object_mut(root_id).and_then(Object::as_dict_mut)?;
+        let role_map = match root.get(b"RoleMap").and_then(Object::as_reference) {
+            Ok(role_map_id) => self.get_object_mut(role_map_id)?,
+            Err(_) => {
+                if root.get(b"RoleMap").and_then(Object::as_dict).is_err() {
+                    root.set("RoleMap", Dictionary::new());
+                }
+                root.get_mut(b"RoleMap")?
+            }
+        };
+        role_map
+            .as_dict_mut()?
+            .set(structure_type, Object::Name(role.as_bytes().to_vec()));
+        Ok(())
+    }
+
+    /// Append content to a page as marked content of a structure element, `/tag <</MCID n>> BDC ... EMC`,
+    /// returning the MCID, which is the next one of the page. The content is added to the kids of the element and
+    /// the element to the parent tree.
+    pub fn add_tagged_content(
+        &mut self, page_id: ObjectId, element_id: ObjectId, tag: &str, content: Vec<u8>,
+    ) -> Result<i64> {
+        let (mcid, mut marked) = self.begin_tagged_content(page_id, element_id, tag)?;
+        marked.push(b'\n');
+        marked.extend(content);
+        marked.extend_from_slice(b"\nEMC");
+        self.append_page_content(page_id, marked)?;
+        Ok(mcid)
+    }
+
+    /// Mark the existing content of a page as content of a structure element, wrapping it in BDC and EMC
+    /// operators, like `add_tagged_content`.
+    pub fn tag_page_content(&mut self, page_id: ObjectId, element_id: ObjectId, tag: &str) -> Result<i64> {
+        let (mcid, begin) = self.begin_tagged_content(page_id, element_id, tag)?;
+        self.prepend_page_content(page_id, begin)?;
+        self.append_page_content(page_id, b"EMC".to_vec())?;
+        Ok(mcid)
+    }
+
+    /// Add a whole object of a page, such as a link annotation, to the kids of a structure element with an object
+    /// reference, and the element to the parent tree with the /StructParent key of the object.
+    pub fn add_structure_object(&mut self, element_id: ObjectId, page_id: ObjectId, object_id: ObjectId) -> Result<()> {
+        let key = self.parent_tree_key(object_id, b"StructParent")?;
+        self.update_parent_tree(key, |parent| *parent = element_id.into())?;
+        self.add_structure_kid(
+            element_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "OBJR",
+                "Pg" => page_id,
+                "Obj" => object_id,
+            }),
+        )
+    }
+
+    /// Allocate the next MCID of a page for marked content of a structure element, and get the BDC operation
+    /// beginning it.
+    fn begin_tagged_content(&mut self, page_id: ObjectId, element_id: ObjectId, tag: &str) -> Result<(i64, Vec<u8>)> {
+        let key = self.parent_tree_key(page_id, b"StructParents")?;
+        let mcid = self.update_parent_tree(key, |parents| {
+            let mut elements = match std::mem::replace(parents, Object::Null) {
+                Object::Array(elements) => elements,
+                _ => Vec::new(),
+            };
+            elements.push(element_id.into());
+            let mcid = elements.len() as i64 - 1;
+            *parents = Object::Array(elements);
+            mcid
+        })?;
+
+        // Kids on the page of the element are given by their MCID only.
+        let element = self.get_object_mut(element_id).and_then(Object::as_dict_mut)?;
+        if !element.has(b"Pg") {
+            element.set("Pg", page_id);
+        }
+        let kid = if element.get(b"Pg").and_then(Object::as_reference).ok() == Some(page_id) {
+            Object::Integer(mcid)
+        } else {
+            Object::Dictionary(dictionary! {
+                "Type" => "MCR",
+                "Pg" => page_id,
+                "MCID" => mcid,
+            })
+        };
+        self.add_structure_kid(element_id, kid)?;
+
+        let begin = Content {
+            operations: vec![Operation::new(
+                "BDC",
+                vec![
+                    Object::Name(tag.as_bytes().to_vec()),
+                    Object::Dictionary(dictionary! { "MCID" => mcid }),
+                ],
+            )],
+        };
+        Ok((mcid, begin.encode()?))
+    }
+
+    /// Add a kid to the /K entry of a structure element or of the structure tree root.
+    fn add_structure_kid(&mut self, parent_id: ObjectId, kid: Object) -> Result<()> {
+        let parent = self.get_object_mut(parent_id).and_then(Object::as_dict_mut)?;
+        match parent.get_mut(b"K") {
+            Ok(Object::Array(kids)) => kids.push(kid),
+            Ok(single) => {
+                let first = std::mem::replace(single, Object::Null);
+                *single = Object::Array(vec![first, kid]);
+            }
+            Err(_) => parent.set("K", kid),
+        }
+        Ok(())
+    }
+
+    /// Get the key of the parent tree entry of a page or object, allocating the next key of the structure tree root
+    /// if it has none.
+    fn parent_tree_key(&mut self, object_id: ObjectId, key: &[u8]) -> Result<i64> {
+        if let Ok(existing) = self.get_dictionary(object_id)?.get(key).and_then(Object::as_i64) {
+            return Ok(existing);
+        }
+        let root_id = self.structure_root()?;
+        let root = self.get_dictionary(root_id)?;
+        let next = match root.get(b"ParentTreeNextKey").and_then(Object::as_i64) {
+            Ok(next) => next,
+            Err(_) => {
+                let mut entries = Vec::new();
+                if let Ok(parent_tree) = root.get(b"ParentTree") {
+                    self.collect_number_tree(parent_tree, &mut entries, &mut Vec::new());
+                }
+                entries.iter().map(|entry| entry.0 + 1).max().unwrap_or(0)
+            }
+        };
+        self.get_object_mut(root_id)
+            .and_then(Object::as_dict_mut)?
+            .set("ParentTreeNextKey", next + 1);
+        self.get_object_mut(object_id)
+            .and_then(Object::as_dict_mut)?
+            .set(key, next);
+        Ok(next)
+    }
+
+    /// Modify the value of a key of the /ParentTree of the structure tree root, which is null for new keys. The
+    /// tree is written anew.
+    fn update_parent_tree<T, F: FnOnce(&mut Object) -> T>(&mut self, key: i64, update: F) -> Result<T> {
+        let root_id = self.structure_root()?;
+        let mut entries = Vec::new();
+        let mut nodes = Vec::new();
+        if let Ok(parent_tree) = self.get_dictionary(root_id)?.get(b"ParentTree") {
+            self.collect_number_tree(parent_tree, &mut entries, &mut nodes);
+        }
+        for node in nodes {
+            self.objects.remove(&node);
+        }
+        let index = match entries.iter().position(|entry| entry.0 == key) {
+            Some(index) => index,
+            None => {
+                entries.push((key, Object::Null));
+                entries.len() - 1
+            }
+        };
+        let result = match entries[index].1 {
+            Object::Reference(id) => update(self.get_object_mut(id)?),
+            ref mut value => update(value),
+        };
+        let parent_tree_id = self.write_number_tree(entries);
+        self.get_object_mut(root_id)
+            .and_then(Object::as_dict_mut)?
+            .set("ParentTree", parent_tree_id);
+        Ok(result)
+    }
+}
+
+/// Structure types of block elements, whose text ends with a line break.
+const BLOCK_ROLES: &[&str] = &[
+    "P",
+    "H",
+    "H1",
+    "H2",
+    "H3",
+    "H4",
+    "H5",
+    "H6",
+    "LI",
+    "TOCI",
+    "Caption",
+    "BlockQuote",
+    "Note",
+    "Title",
+    "Table",
+];
+
+/// Append the text of the marked content of a structure kid, found by page and MCID, in tree order.
+fn write_reading_order(kid: &StructureKid, texts: &BTreeMap<(ObjectId, i64), String>, text: &mut String) {
+    let element = match kid {
+        StructureKid::MarkedContent {
+            page: Some(page),
+            stream: None,
+            mcid,
+        } => {
+            if let Some(marked) = texts.get(&(*page, *mcid)) {
+                text.push_str(marked);
+            }
+            return;
+        }
+        StructureKid::Element(element) => element,
+        _ => return,
+    };
+    let start = text.len();
+    if element.role == "TR" {
+        let cells: Vec<String> = element
+            .kids
+            .iter()
+            .map(|cell| {
+                let mut cell_text = String::new();
+                write_reading_order(cell, texts, &mut cell_text);
+                cell_text.split_whitespace().collect::<Vec<_>>().join(" ")
+            })
+            .collect();
+        if cells.iter().any(|cell| !cell.is_empty()) {
+            text.push_str(&cells.join("\t"));
+            text.push('\n');
+        }
+        return;
+    }
+    for kid in &element.kids {
+        write_reading_order(kid, texts, text);
+    }
+    if text.len() > start {
+        if let Some(actual_text) = &element.actual_text {
+            text.truncate(start);
+            text.push_str(actual_text);
+        }
+        if BLOCK_ROLES.contains(&element.role.as_str()) && !text.ends_with('\n') {
+            text.push('\n');
+        }
+    }
+}
+
+/// Collector of the text shown within sequences of marked content.
+struct MarkedText {
+    /// Indices of the sequences enclosing each operation of the page content.
+    enclosing: Vec<Vec<usize>>,
+    current: Vec<usize>,
+    sequences: Vec<MarkedContent>,
+}
+
+impl ContentHandler for MarkedText {
+    fn operation_started(&mut self, index: usize, depth: usize) {
+        // Text of form XObjects belongs to the sequences enclosing the Do operation.
+        if depth == 0 {
+            self.current = self.enclosing.get(index).cloned().unwrap_or_default();
+        }
+    }
+
+    fn text_shown(&mut self, fragment: &TextFragment, _state: &GraphicsState) {
+        for &sequence in &self.current {
+            self.sequences[sequence].text.push_str(&fragment.text);
+        }
+    }
+}
+
+#[test]
+fn read_structure_tree() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    let mut content = doc.get_and_decode_page_content(page_id).unwrap();
+    let text_index = content
+        .operations
+        .iter()
+        .position(|operation| operation.operator == "Tj")
+        .unwrap();
+    content.operations.insert(text_index + 1, Operation::new("EMC", vec![]));
+    content.operations.insert(
+        text_index,
+        Operation::new("BDC", vec!["Head".into(), dictionary! { "MCID" => 0 }.into()]),
+    );
+    let content_id = doc.get_page_contents(page_id)[0];
+    doc.get_object_mut(content_id)
+        .and_then(Object::as_stream_mut)
+        .unwrap()
+        .set_content(content.encode().unwrap());
+
+    let heading_id = doc.new_object_id();
+    let document_id = doc.add_object(dictionary! {
+        "Type" => "StructElem",
+        "S" => "Document",
+        "Lang" => Object::String(b"en".to_vec(), StringFormat::Literal),
+        "K" => vec![heading_id.into()],
+    });
+    let root_id = doc.add_object(dictionary! {
+        "Type" => "StructTreeRoot",
+        "K" => document_id,
+        "RoleMap" => dictionary! { "Head" => "H1" },
+        "ParentTree" => dictionary! { "Nums" => vec![0.into(), vec![heading_id.into()].into()] },
+    });
+    doc.objects.insert(
+        heading_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "StructElem",
+            "S" => "Head",
+            "P" => document_id,
+            "Pg" => page_id,
+            "Alt" => Object::String(b"Greeting".to_vec(), StringFormat::Literal),
+            "K" => vec![0.into(), heading_id.into()],
+        }),
+    );
+    doc.get_object_mut(document_id)
+        .and_then(Object::as_dict_mut)
+        .unwrap()
+        .set("P", root_id);
+    doc.get_object_mut(page_id)
+        .and_then(Object::as_dict_mut)
+        .unwrap()
+        .set("StructParents", 0);
+    let catalog_id = doc.trailer.get(b"Root").and_then(Object::as_reference).unwrap();
+    doc.get_object_mut(catalog_id)
+        .and_then(Object::as_dict_mut)
+        .unwrap()
+        .set("StructTreeRoot", root_id);
+
+    let tree = doc.structure_tree().unwrap().unwrap();
+    assert_eq!(tree.resolve_role("Head"), "H1");
+    let elements: Vec<&StructureElement> = tree.elements().collect();
+    assert_eq!(elements.len(), 2);
+    assert_eq!(elements[0].id, Some(document_id));
+    assert_eq!(elements[0].lang.as_deref(), Some("en"));
+    let heading = elements[1];
+    assert_eq!(heading.structure_type, "Head");
+    assert_eq!(heading.role, "H1");
+    assert_eq!(heading.alt.as_deref(), Some("Greeting"));
+    // The reference of the heading to itself is skipped.
+    assert_eq!(
+        heading.kids,
+        vec![StructureKid::MarkedContent {
+            page: Some(page_id),
+            stream: None,
+            mcid: 0,
+        }]
+    );
+
+    assert_eq!(doc.structure_parent(page_id, 0).unwrap(), Some(heading_id));
+    assert_eq!(doc.structure_parent(page_id, 1).unwrap(), None);
+    assert_eq!(
+        doc.marked_content(page_id).unwrap(),
+        vec![MarkedContent {
+            tag: "Head".to_owned(),
+            mcid: Some(0),
+            text: "Hello World!".to_owned(),
+        }]
+    );
+    assert!(create_document().structure_tree().unwrap().is_none());
+}
+
+#[test]
+fn write_structure_tree() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    let root_id = doc.structure_root().unwrap();
+    assert_eq!(doc.structure_root().unwrap(), root_id);
+    let attributes = StructureAttributes {
+        lang: Some("en".to_owned()),
+        ..StructureAttributes::default()
+    };
+    let document_id = doc.add_structure_element(root_id, "Document", &attributes).unwrap();
+    doc.add_role_mapping("Greeting", "H1").unwrap();
+    let heading_id = doc
+        .add_structure_element(document_id, "Greeting", &StructureAttributes::default())
+        .unwrap();
+    let attributes = StructureAttributes {
+        alt: Some("Black square".to_owned()),
+        ..StructureAttributes::default()
+    };
+    let figure_id = doc.add_structure_element(document_id, "Figure", &attributes).unwrap();
+    assert_eq!(doc.tag_page_content(page_id, heading_id, "H1").unwrap(), 0);
+    assert_eq!(
+        doc.add_tagged_content(page_id, figure_id, "Figure", b"0 0 10 10 re f".to_vec())
+            .unwrap(),
+        1
+    );
+    let link_id = doc.add_object(dictionary! { "Type" => "Annot", "Subtype" => "Link" });
+    let link_element_id = doc
+        .add_structure_element(document_id, "Link", &StructureAttributes::default())
+        .unwrap();
+    doc.add_structure_object(link_element_id, page_id, link_id).unwrap();
+
+    let catalog = doc.catalog().unwrap();
+    assert_eq!(catalog.get(b"StructTreeRoot").unwrap().as_reference().unwrap(), root_id);
+    assert!(catalog
+        .get(b"MarkInfo")
+        .and_then(Object::as_dict)
+        .and_then(|mark_info| mark_info.get(b"Marked"))
+        .map(|marked| *marked == Object::Boolean(true))
+        .unwrap());
+
+    let tree = doc.structure_tree().unwrap().unwrap();
+    let elements: Vec<&StructureElement> = tree.elements().collect();
+    assert_eq!(elements.len(), 4);
+    assert_eq!(elements[0].lang.as_deref(), Some("en"));
+    assert_eq!(elements[1].role, "H1");
+    assert_eq!(
+        elements[1].kids,
+        vec![StructureKid::MarkedContent {
+            page: Some(page_id),
+            stream: None,
+            mcid: 0,
+        }]
+    );
+    assert_eq!(elements[2].alt.as_deref(), Some("Black square"));
+    assert_eq!(
+        elements[3].kids,
+        vec![StructureKid::Object {
+            page: Some(page_id),
+            object: link_id,
+        }]
+    );
+    let element_ids: Vec<ObjectId> = elements.iter().filter_map(|element| element.id).collect();
+    assert_eq!(element_ids, vec![document_id, heading_id, figure_id, link_element_id]);
+    assert_eq!(
+        doc.get_dictionary(heading_id)
+            .unwrap()
+            .get(b"P")
+            .unwrap()
+            .as_reference()
+            .unwrap(),
+        document_id
+    );
+
+    assert_eq!(doc.structure_parent(page_id, 0).unwrap(), Some(heading_id));
+    assert_eq!(doc.structure_parent(page_id, 1).unwrap(), Some(figure_id));
+    let key = doc
+        .get_dictionary(link_id)
+        .unwrap()
+        .get(b"StructParent")
+        .unwrap()
+        .as_i64()
+        .unwrap();
+    assert_eq!(key, 1);
+    assert_eq!(
+        doc.parent_tree_entry(key).unwrap(),
+        Some(Object::Reference(link_element_id))
+    );
+
+    let marked: Vec<(String, Option<i64>, String)> = doc
+        .marked_content(page_id)
+        .unwrap()
+        .into_iter()
+        .map(|marked| (marked.tag, marked.mcid, marked.text))
+        .collect();
+    assert_eq!(
+        marked,
+        vec![
+            ("H1".to_owned(), Some(0), "Hello World!".to_owned()),
+            ("Figure".to_owned(), Some(1), String::new()),
+        ]
+    );
+}
+
+#[test]
+fn set_alt_text_of_figures() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    assert!(doc.figures_without_alt_text().unwrap().is_empty());
+    let root_id = doc.structure_root().unwrap();
+    doc.add_role_mapping("Chart", "Figure").unwrap();
+    let chart_id = doc
+        .add_structure_element(root_id, "Chart", &StructureAttributes::default())
+        .unwrap();
+    let attributes = StructureAttributes {
+        alt: Some("Logo".to_owned()),
+        ..StructureAttributes::default()
+    };
+    doc.add_structure_element(root_id, "Figure", &attributes).unwrap();
+    let paragraph_id = doc
+        .add_structure_element(root_id, "P", &StructureAttributes::default())
+        .unwrap();
+
+    let missing: Vec<Option<ObjectId>> = doc
+        .figures_without_alt_text()
+        .unwrap()
+        .iter()
+        .map(|element| element.id)
+        .collect();
+    assert_eq!(missing, vec![Some(chart_id)]);
+    doc.set_alt_text(chart_id, "Sales by quarter").unwrap();
+    assert!(doc.figures_without_alt_text().unwrap().is_empty());
+    doc.set_alt_text(chart_id, "").unwrap();
+    assert_eq!(doc.figures_without_alt_text().unwrap().len(), 1);
+    assert!(doc.set_alt_text(root_id, "Not an element").is_err());
+    assert!(doc.get_dictionary(paragraph_id).unwrap().get(b"Alt").is_err());
+}
+
+#[test]
+fn extract_text_in_reading_order() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    let root_id = doc.structure_root().unwrap();
+    let attributes = StructureAttributes::default();
+    let heading_id = doc.add_structure_element(root_id, "H1", &attributes).unwrap();
+    let paragraph_id = doc.add_structure_element(root_id, "P", &attributes).unwrap();
+    let table_id = doc.add_structure_element(root_id, "Table", &attributes).unwrap();
+    let row_id = doc.add_structure_element(table_id, "TR", &attributes).unwrap();
+    doc.tag_page_content(page_id, paragraph_id, "P").unwrap();
+    doc.add_tagged_content(
+        page_id,
+        heading_id,
+        "H1",
+        b"BT /F1 24 Tf 100 700 Td (Title) Tj ET".to_vec(),
+    )
+    .unwrap();
+    for (x, cell) in [(100, "A"), (200, "B")] {
+        let cell_id = doc.add_structure_element(row_id, "TD", &attributes).unwrap();
+        let content = format!("BT /F1 12 Tf {} 100 Td ({}) Tj ET", x, cell);
+        doc.add_tagged_content(page_id, cell_id, "TD", content.into_bytes())
+            .unwrap();
+    }
+    doc.append_page_content(page_id, b"/Artifact BMC BT /F1 8 Tf 10 10 Td (1) Tj ET EMC".to_vec())
+        .unwrap();
+    assert_eq!(doc.extract_text(&[1]).unwrap(), "Hello World!\nTitle\nA\nB\n1\n");
+    assert_eq!(
+        doc.extract_text_in_reading_order(&[1]).unwrap(),
+        "Title\nHello World!\nA\tB\n"
+    );
+    doc.get_object_mut(heading_id)
+        .and_then(Object::as_dict_mut)
+        .unwrap()
+        .set("ActualText", Object::String(b"Heading".to_vec(), StringFormat::Literal));
+    assert!(doc
+        .extract_text_in_reading_order(&[1])
+        .unwrap()
+        .starts_with("Heading\n"));
+    assert!(doc.extract_text_in_reading_order(&[2]).is_err());
+}
@@ -0,0 +1,86 @@
+//! Documents shared by the tests of several modules.
+
+use crate::content::*;
+use crate::{Dictionary, Document, Object, ObjectId, Stream};
+
+/// Create a one page document showing "Hello World!".
+pub fn create_document() -> Document {
+    let mut doc = Document::with_version("1.5");
+    let info_id = doc.add_object(dictionary! {
+        "Title" => Object::string_literal("Create PDF document example"),
+        "Creator" => Object::string_literal("https://crates.io/crates/lopdf"),
+        "CreationDate" => time::OffsetDateTime::now_utc(),
+    });
+    let pages_id = doc.new_object_id();
+    let font_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Courier",
+    });
+    let resources_id = doc.add_object(dictionary! {
+        "Font" => dictionary! {
+            "F1" => font_id,
+        },
+    });
+    let content = Content {
+        operations: vec![
+            Operation::new("BT", vec![]),
+            Operation::new("Tf", vec!["F1".into(), 48.into()]),
+            Operation::new("Td", vec![100.into(), 600.into()]),
+            Operation::new("Tj", vec![Object::string_literal("Hello World!")]),
+            Operation::new("ET", vec![]),
+        ],
+    };
+    let content_id = doc.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+    let page_id = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "Contents" => content_id,
+    });
+    let pages = dictionary! {
+        "Type" => "Pages",
+        "Kids" => vec![page_id.into()],
+        "Count" => 1,
+        "Resources" => resources_id,
+        "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+    };
+    doc.objects.insert(pages_id, Object::Dictionary(pages));
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+    doc.trailer.set("Info", info_id);
+    doc
+}
+
+/// Create a document like `create_document`, with copies of its page until it has `page_count` pages.
+pub fn create_document_with_pages(page_count: usize) -> Document {
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    for _ in 1..page_count {
+        let page = doc.get_dictionary(page_id).unwrap().clone();
+        append_page(&mut doc, page);
+    }
+    doc
+}
+
+/// Add a page to the end of the root of the page tree, returning its id.
+pub fn append_page(doc: &mut Document, page: Dictionary) -> ObjectId {
+    let page_id = doc.add_object(page);
+    let pages_id = doc
+        .catalog()
+        .unwrap()
+        .get(b"Pages")
+        .and_then(Object::as_reference)
+        .unwrap();
+    let pages = doc.get_object_mut(pages_id).and_then(Object::as_dict_mut).unwrap();
+    pages
+        .get_mut(b"Kids")
+        .and_then(Object::as_array_mut)
+        .unwrap()
+        .push(page_id.into());
+    let count = pages.get(b"Count").and_then(Object::as_i64).unwrap();
+    pages.set("Count", count + 1);
+    page_id
+}
@@ -0,0 +1,1394 @@
+#![cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+
+use crate::content::Operation;
+use crate::forms::text_width;
+use crate::interpreter::{ContentHandler, ContentInterpreter, GraphicsState};
+use crate::{Dictionary, Document, Error, Object, ObjectId, Rectangle, Result, StringFormat};
+#[cfg(feature = "search")]
+use regex::RegexBuilder;
+use std::collections::BTreeMap;
+
+/// Text shown by one text showing operator, with its position and appearance.
+#[derive(Debug, Clone)]
+pub struct TextFragment {
+    pub text: String,
+    /// Bounding box in the default user space of the page.
+    pub bbox: Rectangle,
+    /// Base font name, or the resource name of fonts without one.
+    pub font: String,
+    /// Font size in user space, after applying the text and current transformation matrices.
+    pub font_size: f64,
+    /// Fill color components, one for gray, three for RGB and four for CMYK.
+    pub color: Vec<f64>,
+    pub glyphs: Vec<Glyph>,
+}
+
+/// Text of a single character code, with its bounding box in default user space.
+#[derive(Debug, Clone)]
+pub struct Glyph {
+    pub text: String,
+    pub bbox: Rectangle,
+    /// Bytes of the character code in the shown string.
+    pub code: Vec<u8>,
+    /// Distance by which the glyph moves the text position in unscaled text space, including character and word
+    /// spacing.
+    pub advance: f64,
+}
+
+impl TextFragment {
+    /// Split the fragment into words separated by whitespace, with their bounding boxes.
+    pub fn words(&self) -> Vec<(String, Rectangle)> {
+        let mut words = Vec::new();
+        let mut current: Option<(String, Rectangle)> = None;
+        for glyph in &self.glyphs {
+            if glyph.text.chars().all(char::is_whitespace) {
+                words.extend(current.take());
+                continue;
+            }
+            current = Some(match current {
+                Some((mut text, bbox)) => {
+                    text.push_str(&glyph.text);
+                    (text, bbox.union(&glyph.bbox))
+                }
+                None => (glyph.text.clone(), glyph.bbox),
+            });
+        }
+        words.extend(current);
+        words
+    }
+}
+
+/// Options of `Document::search`.
+#[cfg(feature = "search")]
+#[derive(Debug, Clone, Default)]
+pub struct SearchOptions {
+    pub case_insensitive: bool,
+    /// Whether the pattern is a regular expression rather than plain text.
+    pub regex: bool,
+    /// Numbers of the pages to search, or all pages if empty.
+    pub pages: Vec<u32>,
+}
+
+/// Occurrence of a search pattern.
+#[cfg(feature = "search")]
+#[derive(Debug, Clone)]
+pub struct TextMatch {
+    pub page_number: u32,
+    pub text: String,
+    /// Quadrilaterals covering the matched text, one for each line, as eight coordinates
+    /// in the order used by the /QuadPoints of highlight annotations.
+    pub quads: Vec<[f64; 8]>,
+}
+
+/// Nesting limit of form XObjects, which guards against cycles.
+pub(crate) const MAX_FORM_DEPTH: usize = 8;
+
+/// Character code of a font, with its length in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct Code {
+    value: u32,
+    length: u8,
+}
+
+impl Code {
+    fn new(bytes: &[u8]) -> Code {
+        Code {
+            value: bytes.iter().fold(0u32, |value, &byte| value << 8 | u32::from(byte)),
+            length: bytes.len() as u8,
+        }
+    }
+
+    pub(crate) fn bytes(self) -> Vec<u8> {
+        self.value.to_be_bytes()[4 - usize::from(self.length.min(4))..].to_vec()
+    }
+
+    /// Whether word spacing applies to the code, which is the case for the single byte code 32.
+    pub(crate) fn is_space(self) -> bool {
+        self.length == 1 && self.value == 32
+    }
+}
+
+/// Encoding form of the Unicode characters of predefined CMaps.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum UnicodeForm {
+    Ucs2,
+    Utf16,
+    Utf8,
+    Utf32,
+}
+
+/// Mapping of the character codes of a font to character ids.
+enum CMap {
+    /// Single byte codes of simple fonts.
+    Simple,
+    /// Two byte codes which are character ids, as for the Identity-H and Identity-V CMaps.
+    Identity,
+    /// Unicode characters, as for the UniGB-UCS2-H CMap. Their character ids are defined by CMap files which
+    /// aren't available, so glyphs get the default width.
+    Unicode(UnicodeForm),
+    /// Other predefined CMaps, read as two byte codes without known character ids.
+    Predefined,
+    /// Embedded CMap, with its code space ranges and the first character id of each range of codes.
+    Embedded {
+        code_space: Vec<(Vec<u8>, Vec<u8>)>,
+        ranges: Vec<(Code, Code, u32)>,
+    },
+}
+
+impl CMap {
+    fn predefined(name: &str) -> CMap {
+        if name.starts_with("Identity") {
+            CMap::Identity
+        } else if !name.starts_with("Uni") {
+            CMap::Predefined
+        } else if name.contains("UCS2") {
+            CMap::Unicode(UnicodeForm::Ucs2)
+        } else if name.contains("UTF16") {
+            CMap::Unicode(UnicodeForm::Utf16)
+        } else if name.contains("UTF8") {
+            CMap::Unicode(UnicodeForm::Utf8)
+        } else if name.contains("UTF32") {
+            CMap::Unicode(UnicodeForm::Utf32)
+        } else {
+            CMap::Predefined
+        }
+    }
+
+    /// Get the length of the code starting a string.
+    fn code_length(&self, bytes: &[u8]) -> usize {
+        let length = match *self {
+            CMap::Simple => 1,
+            CMap::Identity | CMap::Predefined | CMap::Unicode(UnicodeForm::Ucs2) => 2,
+            CMap::Unicode(UnicodeForm::Utf16) => match bytes[0] {
+                0xD8..=0xDB => 4,
+                _ => 2,
+            },
+            CMap::Unicode(UnicodeForm::Utf8) => match bytes[0] {
+                0xF0..=0xFF => 4,
+                0xE0..=0xEF => 3,
+                0xC0..=0xDF => 2,
+                _ => 1,
+            },
+            CMap::Unicode(UnicodeForm::Utf32) => 4,
+            CMap::Embedded { ref code_space, .. } => {
+                let matching = code_space.iter().find(|(low, high)| {
+                    low.len() <= bytes.len()
+                        && (0..low.len()).all(|index| low[index] <= bytes[index] && bytes[index] <= high[index])
+                });
+                // Codes outside of the code space are read with the shortest code length.
+                matching
+                    .or_else(|| code_space.iter().min_by_key(|(low, _)| low.len()))
+                    .map_or(1, |(low, _)| low.len())
+            }
+        };
+        length.min(bytes.len())
+    }
+
+    /// Get the code for a value, with the length of a code space range containing it.
+    fn code(&self, value: u32) -> Code {
+        let length = match *self {
+            CMap::Simple => 1,
+            CMap::Unicode(UnicodeForm::Utf32) => 4,
+            CMap::Unicode(UnicodeForm::Utf8) if value <= 0xFF => 1,
+            CMap::Unicode(UnicodeForm::Utf16) | CMap::Unicode(UnicodeForm::Utf8) => match value {
+                0..=0xFFFF => 2,
+                0x10000..=0xFF_FFFF => 3,
+                _ => 4,
+            },
+            CMap::Embedded { ref code_space, .. } => (1..=4)
+                .find(|&length| {
+                    let code = Code { value, length };
+                    (length == 4 || value >> (8 * u32::from(length)) == 0)
+                        && code_space.iter().any(|(low, high)| {
+                            low.len() == usize::from(length) && Code::new(low) <= code && code <= Code::new(high)
+                        })
+                })
+                .unwrap_or(2),
+            _ => 2,
+        };
+        Code { value, length }
+    }
+
+    fn cid(&self, code: Code) -> Option<u32> {
+        match *self {
+            CMap::Identity => Some(code.value),
+            CMap::Embedded { ref ranges, .. } => ranges
+                .iter()
+                .find(|(low, high, _)| low.length == code.length && low.value <= code.value && code.value <= high.value)
+                .map(|(low, _, cid)| cid + (code.value - low.value)),
+            _ => None,
+        }
+    }
+
+    /// Decode the code of a Unicode character.
+    fn unicode(form: UnicodeForm, code: Code) -> String {
+        match form {
+            UnicodeForm::Ucs2 | UnicodeForm::Utf16 => {
+                let units: Vec<u16> = code
+                    .bytes()
+                    .chunks(2)
+                    .map(|pair| u16::from(pair[0]) << 8 | u16::from(*pair.get(1).unwrap_or(&0)))
+                    .collect();
+                String::from_utf16_lossy(&units)
+            }
+            UnicodeForm::Utf8 => String::from_utf8_lossy(&code.bytes()).into_owned(),
+            UnicodeForm::Utf32 => std::char::from_u32(code.value).unwrap_or('\u{FFFD}').to_string(),
+        }
+    }
+}
+
+/// Font metrics and character mapping needed to position and decode text.
+pub(crate) struct Font<'a> {
+    pub(crate) name: String,
+    dict: &'a Dictionary,
+    encoding: Option<&'a str>,
+    cmap: CMap,
+    /// Whether glyphs are positioned vertically, as for the Identity-V CMap.
+    pub(crate) vertical: bool,
+    /// Vertical position of the glyph origin and vertical advance, from /DW2 of composite fonts.
+    pub(crate) vertical_metrics: (f64, f64),
+    first_char: i64,
+    widths: Vec<f64>,
+    /// Widths of composite fonts by character id.
+    cid_widths: BTreeMap<u32, f64>,
+    default_width: f64,
+    pub(crate) ascent: f64,
+    pub(crate) descent: f64,
+    to_unicode: BTreeMap<u32, String>,
+}
+
+impl<'a> Font<'a> {
+    pub(crate) fn new(document: &'a Document, resource_name: &[u8], dict: &'a Dictionary) -> Font<'a> {
+        let resolve = |dict: &'a Dictionary, key: &[u8]| -> Option<&'a Object> {
+            document.dereference(dict.get(key).ok()?).map(|(_, object)| object).ok()
+        };
+        let numbers = |object: Option<&Object>| -> Vec<f64> {
+            object
+                .and_then(|array| array.as_array().ok())
+                .map(|array| array.iter().filter_map(|number| number.as_float().ok()).collect())
+                .unwrap_or_default()
+        };
+
+        let composite = dict.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"Type0");
+        let (cmap, vertical) = match resolve(dict, b"Encoding") {
+            _ if !composite => (CMap::Simple, false),
+            Some(Object::Name(name)) => {
+                let name = String::from_utf8_lossy(name);
+                (CMap::predefined(&name), name.ends_with("-V"))
+            }
+            Some(Object::Stream(stream)) => {
+                let vertical = stream.dict.get(b"WMode").and_then(Object::as_i64).ok() == Some(1);
+                let (cmap, wmode) =
+                    parse_cmap(&stream.decompressed_content().unwrap_or_else(|_| stream.content.clone()));
+                (cmap, vertical || wmode == Some(1))
+            }
+            _ => (CMap::Identity, false),
+        };
+        let descendant = resolve(dict, b"DescendantFonts")
+            .and_then(|fonts| fonts.as_array().ok())
+            .and_then(|fonts| fonts.first())
+            .and_then(|font| document.dereference(font).ok())
+            .and_then(|(_, font)| font.as_dict().ok());
+        let descriptor = resolve(descendant.unwrap_or(dict), b"FontDescriptor").and_then(|d| d.as_dict().ok());
+        let metric = |key: &[u8], default: f64| {
+            descriptor
+                .and_then(|descriptor| resolve(descriptor, key))
+                .and_then(|value| value.as_float().ok())
+                .filter(|value| *value != 0.0)
+                .map_or(default, |value| value / 1000.0)
+        };
+
+        let mut cid_widths = BTreeMap::new();
+        let mut default_width = metric(b"MissingWidth", 0.0) * 1000.0;
+        let mut vertical_metrics = (880.0, -1000.0);
+        if let Some(descendant) = descendant {
+            default_width = resolve(descendant, b"DW")
+                .and_then(|width| width.as_float().ok())
+                .unwrap_or(1000.0);
+            if let [position, advance] = numbers(resolve(descendant, b"DW2")).as_slice() {
+                vertical_metrics = (*position, *advance);
+            }
+            if let Some(Object::Array(entries)) = resolve(descendant, b"W") {
+                let mut index = 0;
+                while index + 1 < entries.len() {
+                    let first = entries[index].as_i64().unwrap_or(0) as u32;
+                    match document.dereference(&entries[index + 1]) {
+                        Ok((_, Object::Array(widths))) => {
+                            let widths = widths.iter().filter_map(|width| width.as_float().ok());
+                            for (offset, width) in widths.enumerate() {
+                                cid_widths.insert(first + offset as u32, width);
+                            }
+                            index += 2;
+                        }
+                        _ => {
+                            let last = entries[index + 1].as_i64().unwrap_or(0) as u32;
+                            let width = entries.get(index + 2).and_then(|w| w.as_float().ok()).unwrap_or(0.0);
+                            for cid in first..=last.min(first.saturating_add(0xFFFF)) {
+                                cid_widths.insert(cid, width);
+                            }
+                            index += 3;
+                        }
+                    }
+                }
+            }
+        }
+
+        let to_unicode = resolve(dict, b"ToUnicode")
+            .and_then(|cmap| cmap.as_stream().ok())
+            .map(|cmap| parse_to_unicode(&cmap.decompressed_content().unwrap_or_else(|_| cmap.content.clone())))
+            .unwrap_or_default();
+        let name = dict.get(b"BaseFont").and_then(Object::as_name).unwrap_or(resource_name);
+
+        Font {
+            name: String::from_utf8_lossy(name).into_owned(),
+            dict,
+            encoding: dict.get(b"Encoding").and_then(Object::as_name_str).ok(),
+            cmap,
+            vertical,
+            vertical_metrics,
+            first_char: dict.get(b"FirstChar").and_then(Object::as_i64).unwrap_or(0),
+            widths: numbers(resolve(dict, b"Widths")),
+            cid_widths,
+            default_width,
+            ascent: metric(b"Ascent", 0.8),
+            descent: metric(b"Descent", -0.2),
+            to_unicode,
+        }
+    }
+
+    /// Split a string into character codes.
+    pub(crate) fn codes(&self, bytes: &[u8]) -> Vec<Code> {
+        let mut codes = Vec::new();
+        let mut index = 0;
+        while index < bytes.len() {
+            let length = self.cmap.code_length(&bytes[index..]);
+            codes.push(Code::new(&bytes[index..index + length]));
+            index += length;
+        }
+        codes
+    }
+
+    /// Get the width of a character code in thousandths of text space units.
+    pub(crate) fn width(&self, code: Code) -> f64 {
+        if !matches!(self.cmap, CMap::Simple) {
+            return self
+                .cmap
+                .cid(code)
+                .and_then(|cid| self.cid_widths.get(&cid))
+                .copied()
+                .unwrap_or(self.default_width);
+        }
+        let index = i64::from(code.value) - self.first_char;
+        if self.widths.is_empty() {
+            text_width(self.dict, &[code.value as u8]) * 1000.0
+        } else if index >= 0 && (index as usize) < self.widths.len() {
+            self.widths[index as usize]
+        } else {
+            self.default_width
+        }
+    }
+
+    /// Get the distance the text position moves after a glyph in thousandths of text space units, which is
+    /// downwards for vertical fonts.
+    pub(crate) fn advance(&self, code: Code) -> f64 {
+        if self.vertical {
+            -self.vertical_metrics.1
+        } else {
+            self.width(code)
+        }
+    }
+
+    pub(crate) fn decode(&self, code: Code) -> String {
+        if let Some(text) = self.to_unicode.get(&code.value) {
+            return text.clone();
+        }
+        match self.cmap {
+            CMap::Simple => Document::decode_text(self.encoding, &code.bytes()),
+            CMap::Unicode(form) => CMap::unicode(form, code),
+            _ => '\u{FFFD}'.to_string(),
+        }
+    }
+
+    /// Get the character codes showing a text, if the font can show all of its characters.
+    fn encode(&self, text: &str) -> Option<Vec<Code>> {
+        let mut reverse = BTreeMap::new();
+        for (&code, text) in &self.to_unicode {
+            reverse.entry(text.as_str()).or_insert(code);
+        }
+        let mut codes = Vec::new();
+        for character in text.chars() {
+            let string = character.to_string();
+            let code = match (reverse.get(string.as_str()), &self.cmap) {
+                (Some(&value), _) => self.cmap.code(value),
+                (None, CMap::Simple) => {
+                    let encoded = Document::encode_text(self.encoding, &string);
+                    match self.codes(&encoded).as_slice() {
+                        &[code] if self.decode(code) == string => code,
+                        _ => return None,
+                    }
+                }
+                (None, &CMap::Unicode(form)) => {
+                    let bytes = match form {
+                        UnicodeForm::Ucs2 if u32::from(character) > 0xFFFF => return None,
+                        UnicodeForm::Ucs2 | UnicodeForm::Utf16 => character
+                            .encode_utf16(&mut [0; 2])
+                            .iter()
+                            .flat_map(|unit| unit.to_be_bytes().to_vec())
+                            .collect(),
+                        UnicodeForm::Utf8 => string.clone().into_bytes(),
+                        UnicodeForm::Utf32 => u32::from(character).to_be_bytes().to_vec(),
+                    };
+                    Code::new(&bytes)
+                }
+                (None, _) => return None,
+            };
+            if matches!(self.cmap, CMap::Simple) && code.value > 0xFF {
+                return None;
+            }
+            codes.push(code);
+        }
+        Some(codes)
+    }
+
+    fn code_bytes(&self, codes: &[Code]) -> Vec<u8> {
+        codes.iter().flat_map(|code| code.bytes()).collect()
+    }
+}
+
+#[derive(PartialEq)]
+enum CMapToken {
+    Hex(Vec<u8>),
+    Word(Vec<u8>),
+    ArrayStart,
+    ArrayEnd,
+}
+
+/// Split a CMap into hexadecimal strings, arrays and other words.
+fn cmap_tokens(data: &[u8]) -> Vec<CMapToken> {
+    let mut tokens = Vec::new();
+    let mut index = 0;
+    while index < data.len() {
+        match data[index] {
+            b'<' if data.get(index + 1) != Some(&b'<') => {
+                let end = data[index..]
+                    .iter()
+                    .position(|&b| b == b'>')
+                    .map_or(data.len(), |end| index + end);
+                let digits: Vec<u8> = data[index + 1..end]
+                    .iter()
+                    .filter_map(|&b| (b as char).to_digit(16).map(|digit| digit as u8))
+                    .collect();
+                let bytes = digits
+                    .chunks(2)
+                    .map(|pair| pair[0] << 4 | pair.get(1).copied().unwrap_or(0))
+                    .collect();
+                tokens.push(CMapToken::Hex(bytes));
+                index = end + 1;
+            }
+            b'[' => {
+                tokens.push(CMapToken::ArrayStart);
+                index += 1;
+            }
+            b']' => {
+                tokens.push(CMapToken::ArrayEnd);
+                index += 1;
+            }
+            b'%' => {
+                index = data[index..]
+                    .iter()
+                    .position(|&b| b == b'\r' || b == b'\n')
+                    .map_or(data.len(), |end| index + end);
+            }
+            b if b.is_ascii_whitespace() => index += 1,
+            _ => {
+                let end = data[index..]
+                    .iter()
+                    .position(|&b| b.is_ascii_whitespace() || b"<[]%".contains(&b))
+                    .map_or(data.len(), |end| index + end);
+                tokens.push(CMapToken::Word(data[index..end].to_vec()));
+                index = end.max(index + 1);
+            }
+        }
+    }
+    tokens
+}
+
+/// Parse the code space ranges, character id mappings and writing mode of an embedded CMap.
+fn parse_cmap(data: &[u8]) -> (CMap, Option<i64>) {
+    let tokens = cmap_tokens(data);
+    let number = |token: Option<&CMapToken>| match token {
+        Some(CMapToken::Word(word)) => std::str::from_utf8(word).ok()?.parse::<i64>().ok(),
+        _ => None,
+    };
+    let mut code_space = Vec::new();
+    let mut ranges = Vec::new();
+    let mut wmode = None;
+    let mut section: &[u8] = b"";
+    let mut index = 0;
+    while index < tokens.len() {
+        match (&tokens[index], section) {
+            (CMapToken::Word(word), _) => {
+                section = match word.as_slice() {
+                    b"begincodespacerange" => b"codespacerange",
+                    b"begincidrange" => b"cidrange",
+                    b"begincidchar" => b"cidchar",
+                    b"/WMode" => {
+                        wmode = number(tokens.get(index + 1));
+                        b""
+                    }
+                    _ => b"",
+                };
+                index += 1;
+            }
+            (CMapToken::Hex(low), b"codespacerange") => {
+                if let Some(CMapToken::Hex(high)) = tokens.get(index + 1) {
+                    if low.len() == high.len() && !low.is_empty() {
+                        code_space.push((low.clone(), high.clone()));
+                    }
+                }
+                index += 2;
+            }
+            (CMapToken::Hex(low), b"cidrange") => {
+                if let (Some(CMapToken::Hex(high)), Some(cid)) = (tokens.get(index + 1), number(tokens.get(index + 2)))
+                {
+                    ranges.push((Code::new(low), Code::new(high), cid as u32));
+                }
+                index += 3;
+            }
+            (CMapToken::Hex(code), b"cidchar") => {
+                if let Some(cid) = number(tokens.get(index + 1)) {
+                    ranges.push((Code::new(code), Code::new(code), cid as u32));
+                }
+                index += 2;
+            }
+            _ => index += 1,
+        }
+    }
+    (CMap::Embedded { code_space, ranges }, wmode)
+}
+
+/// Parse the bfchar and bfrange mappings of a ToUnicode CMap.
+fn parse_to_unicode(data: &[u8]) -> BTreeMap<u32, String> {
+    let tokens = cmap_tokens(data);
+    let code = |bytes: &[u8]| Code::new(bytes).value;
+    let text = |bytes: &[u8]| {
+        let units: Vec<u16> = bytes
+            .chunks(2)
+            .map(|pair| u16::from(pair[0]) << 8 | u16::from(*pair.get(1).unwrap_or(&0)))
+            .collect();
+        String::from_utf16_lossy(&units)
+    };
+    let mut map = BTreeMap::new();
+    let mut section: &[u8] = b"";
+    let mut index = 0;
+    while index < tokens.len() {
+        match (&tokens[index], section) {
+            (CMapToken::Word(word), _) => {
+                section = match word.as_slice() {
+                    b"beginbfchar" => b"bfchar",
+                    b"beginbfrange" => b"bfrange",
+                    _ => b"",
+                };
+                index += 1;
+            }
+            (CMapToken::Hex(source), b"bfchar") => {
+                if let Some(CMapToken::Hex(destination)) = tokens.get(index + 1) {
+                    map.insert(code(source), text(destination));
+                }
+                index += 2;
+            }
+            (CMapToken::Hex(low), b"bfrange") => {
+                let (low, high) = match tokens.get(index + 1) {
+                    Some(CMapToken::Hex(high)) => (code(low), code(high)),
+                    _ => break,
+                };
+                match tokens.get(index + 2) {
+                    Some(CMapToken::Hex(destination)) => {
+                        let mut destination = destination.clone();
+                        for source in low..=high.min(low.saturating_add(0xFFFF)) {
+                            map.insert(source, text(&destination));
+                            // Increment the last byte of the destination for each following code.
+                            if let Some(last) = destination.last_mut() {
+                                *last = last.wrapping_add(1);
+                            }
+                        }
+                        index += 3;
+                    }
+                    Some(CMapToken::ArrayStart) => {
+                        index += 3;
+                        let mut source = low;
+                        while let Some(CMapToken::Hex(destination)) = tokens.get(index) {
+                            if source <= high {
+                                map.insert(source, text(destination));
+                            }
+                            source += 1;
+                            index += 1;
+                        }
+                        index += 1;
+                    }
+                    _ => break,
+                }
+            }
+            _ => index += 1,
+        }
+    }
+    map
+}
+
+struct FragmentCollector(Vec<TextFragment>);
+
+impl ContentHandler for FragmentCollector {
+    fn text_shown(&mut self, fragment: &TextFragment, _state: &GraphicsState) {
+        self.0.push(fragment.clone());
+    }
+}
+
+impl Document {
+    /// Extract the text of pages, with a line break after each text object.
+    ///
+    /// Character codes are decoded with the /ToUnicode CMaps of fonts, or else their encodings.
+    pub fn extract_text(&self, page_numbers: &[u32]) -> Result<String> {
+        let mut text = String::new();
+        let pages = self.get_pages();
+        for page_number in page_numbers {
+            let page_id = *pages.get(page_number).ok_or(Error::PageNumberNotFound(*page_number))?;
+            self.extract_page_text(page_id, &mut text)?;
+        }
+        Ok(text)
+    }
+
+    /// Iterate over the text of each page with its page number, extracted as by `extract_text` when the iterator
+    /// advances, so that the decoded contents and fonts of only one page are kept at a time.
+    pub fn pages_text_iter(&self) -> impl Iterator<Item = Result<(u32, String)>> + '_ {
+        self.page_iter().zip(1..).map(move |(page_id, page_number)| {
+            let mut text = String::new();
+            self.extract_page_text(page_id, &mut text)?;
+            Ok((page_number, text))
+        })
+    }
+
+    fn extract_page_text(&self, page_id: ObjectId, text: &mut String) -> Result<()> {
+        let fonts: BTreeMap<Vec<u8>, Font> = self
+            .get_page_fonts(page_id)
+            .into_iter()
+            .map(|(name, font)| (name.clone(), Font::new(self, &name, font)))
+            .collect();
+        let content = self.get_and_decode_page_content(page_id)?;
+        let mut current_font = None;
+        for operation in &content.operations {
+            let items = match operation.operator.as_str() {
+                "Tf" => {
+                    let font = operation
+                        .operands
+                        .first()
+                        .ok_or_else(|| Error::Syntax("missing font operand".to_string()))?
+                        .as_name()?;
+                    current_font = fonts.get(font);
+                    continue;
+                }
+                "Tj" | "'" | "\"" => operation.operands.last().map(std::slice::from_ref),
+                "TJ" => operation
+                    .operands
+                    .first()
+                    .and_then(|items| items.as_array().ok())
+                    .map(Vec::as_slice),
+                "ET" => {
+                    if !text.ends_with('\n') {
+                        text.push('\n')
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+            for bytes in items.unwrap_or_default().iter().filter_map(|item| item.as_str().ok()) {
+                match current_font {
+                    Some(font) => text.extend(font.codes(bytes).into_iter().map(|code| font.decode(code))),
+                    None => text.push_str(&Document::decode_text(None, bytes)),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Extract the text of a page as fragments with their positions, fonts and colors,
+    /// including text drawn by form XObjects.
+    pub fn extract_text_fragments(&self, page_number: u32) -> Result<Vec<TextFragment>> {
+        let page_id = *self
+            .get_pages()
+            .get(&page_number)
+            .ok_or(Error::PageNumberNotFound(page_number))?;
+        let mut interpreter = ContentInterpreter::new(self, FragmentCollector(Vec::new()));
+        interpreter.run_page(page_id)?;
+        Ok(interpreter.into_handler().0)
+    }
+
+    /// Search the text of pages for a pattern.
+    ///
+    /// Text is matched in content stream order. Glyphs that are shown apart, without a space character
+    /// between them, are separated by a space, while glyphs shown next to each other are joined even if
+    /// they are split by kerning or separate operators. Any whitespace in a plain text pattern matches
+    /// any whitespace in the text, including line breaks.
+    #[cfg(feature = "search")]
+    pub fn search(&self, pattern: &str, options: &SearchOptions) -> Result<Vec<TextMatch>> {
+        let pattern = if options.regex {
+            pattern.to_owned()
+        } else {
+            pattern
+                .split_whitespace()
+                .map(regex::escape)
+                .collect::<Vec<_>>()
+                .join(r"\s+")
+        };
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(options.case_insensitive)
+            .build()
+            .map_err(|error| Error::Syntax(error.to_string()))?;
+        let page_numbers: Vec<u32> = if options.pages.is_empty() {
+            self.get_pages().keys().copied().collect()
+        } else {
+            options.pages.clone()
+        };
+
+        let mut matches = Vec::new();
+        for page_number in page_numbers {
+            let fragments = self.extract_text_fragments(page_number)?;
+            let (text, glyphs) = search_text(&fragments);
+            for found in regex.find_iter(&text) {
+                if found.as_str().is_empty() {
+                    continue;
+                }
+                // Union of the glyph boxes of each line of the match.
+                let mut lines: Vec<(usize, Rectangle)> = Vec::new();
+                for &(_, bbox, line) in glyphs
+                    .iter()
+                    .filter(|(offset, _, _)| *offset >= found.start() && *offset < found.end())
+                {
+                    let bbox = match bbox {
+                        Some(bbox) => bbox,
+                        None => continue,
+                    };
+                    match lines.last_mut() {
+                        Some((last_line, last_bbox)) if *last_line == line => *last_bbox = last_bbox.union(&bbox),
+                        _ => lines.push((line, bbox)),
+                    }
+                }
+                matches.push(TextMatch {
+                    page_number,
+                    text: found.as_str().to_owned(),
+                    quads: lines
+                        .into_iter()
+                        .map(|(_, r)| [r.llx, r.ury, r.urx, r.ury, r.llx, r.lly, r.urx, r.lly])
+                        .collect(),
+                });
+            }
+        }
+        Ok(matches)
+    }
+
+    /// Replace text shown by the text showing operators of a page, re-encoding the replacement with the font
+    /// in effect.
+    ///
+    /// Occurrences may span strings and kerning adjustments of a TJ array, but not separate operators.
+    /// A kerning adjustment is added after each replacement which makes up for the difference in width,
+    /// so that the text following it stays in place. Fails if the font cannot show the replacement.
+    pub fn replace_text(&mut self, page_number: u32, text: &str, other_text: &str) -> Result<()> {
+        let page_id = *self
+            .get_pages()
+            .get(&page_number)
+            .ok_or(Error::PageNumberNotFound(page_number))?;
+        if text.is_empty() {
+            return Ok(());
+        }
+        let mut content = self.get_and_decode_page_content(page_id)?;
+        let fonts: BTreeMap<Vec<u8>, Font> = self
+            .get_page_fonts(page_id)
+            .into_iter()
+            .map(|(name, font)| (name.clone(), Font::new(self, &name, font)))
+            .collect();
+
+        let mut state = GraphicsState::default();
+        let mut stack = Vec::new();
+        let mut operations = Vec::with_capacity(content.operations.len());
+        for operation in content.operations {
+            let operands = &operation.operands;
+            let number = |index: usize| operands.get(index).and_then(|n| n.as_float().ok()).unwrap_or(0.0);
+            match operation.operator.as_str() {
+                "q" => stack.push(state.clone()),
+                "Q" => state = stack.pop().unwrap_or(state),
+                "Tf" => {
+                    state.font = operands
+                        .first()
+                        .and_then(|name| name.as_name().ok())
+                        .map(<[u8]>::to_vec);
+                    state.font_size = number(1);
+                }
+                "Tc" => state.character_spacing = number(0),
+                "Tw" => state.word_spacing = number(0),
+                "\"" => {
+                    state.word_spacing = number(0);
+                    state.character_spacing = number(1);
+                }
+                _ => {}
+            }
+            let font = match state.font.as_ref().and_then(|name| fonts.get(name)) {
+                Some(font) => font,
+                None => {
+                    operations.push(operation);
+                    continue;
+                }
+            };
+            let items = match operation.operator.as_str() {
+                "Tj" | "'" | "\"" => operands.last().map(std::slice::from_ref),
+                "TJ" => operands
+                    .first()
+                    .and_then(|items| items.as_array().ok())
+                    .map(Vec::as_slice),
+                _ => None,
+            };
+            let replaced = match items {
+                Some(items) => replace_in_text(items, font, &state, text, other_text)?,
+                None => None,
+            };
+            match replaced {
+                Some(items) => {
+                    // The line movement of ' and " is kept in a separate operator, as they take a single string.
+                    if operation.operator == "\"" {
+                        operations.push(Operation::new("Tw", vec![operands[0].clone()]));
+                        operations.push(Operation::new("Tc", vec![operands[1].clone()]));
+                    }
+                    if operation.operator != "Tj" && operation.operator != "TJ" {
+                        operations.push(Operation::new("T*", vec![]));
+                    }
+                    match items.as_slice() {
+                        [Object::String(..)] => operations.push(Operation::new("Tj", items)),
+                        _ => operations.push(Operation::new("TJ", vec![Object::Array(items)])),
+                    }
+                }
+                None => operations.push(operation),
+            }
+        }
+        content.operations = operations;
+        self.change_page_content(page_id, content.encode()?)
+    }
+
+    /// Get the fonts of a resource dictionary, keyed by their resource names.
+    pub(crate) fn resource_fonts<'a>(
+        &'a self, resources: &'a Dictionary, owner: Option<ObjectId>,
+    ) -> BTreeMap<Vec<u8>, (Option<ObjectId>, &'a Dictionary)> {
+        let fonts = resources
+            .get(b"Font")
+            .and_then(|fonts| self.dereference(fonts))
+            .and_then(|(_, fonts)| fonts.as_dict());
+        let mut map = BTreeMap::new();
+        if let Ok(fonts) = fonts {
+            for (name, font) in fonts.iter() {
+                if let Ok((_, Object::Dictionary(font))) = self.dereference(font) {
+                    // Fonts are cached by owner, as forms may use the same resource names for other fonts.
+                    map.insert(name.clone(), (owner, font));
+                }
+            }
+        }
+        map
+    }
+
+    pub(crate) fn resource_xobjects<'a>(&'a self, resources: &'a Dictionary) -> Option<&'a Dictionary> {
+        let xobjects = resources.get(b"XObject").ok()?;
+        self.dereference(xobjects).ok()?.1.as_dict().ok()
+    }
+}
+
+/// Replace text in the strings and kerning adjustments of a text showing operator, returning the new items
+/// if the text was found.
+fn replace_in_text(
+    items: &[Object], font: &Font, state: &GraphicsState, text: &str, other_text: &str,
+) -> Result<Option<Vec<Object>>> {
+    enum Piece {
+        Glyph(Code),
+        Adjustment(f64),
+    }
+    let mut pieces = Vec::new();
+    let mut format = StringFormat::Literal;
+    for item in items {
+        match *item {
+            Object::String(ref bytes, ref string_format) => {
+                format = string_format.clone();
+                pieces.extend(font.codes(bytes).into_iter().map(Piece::Glyph));
+            }
+            ref number => pieces.extend(number.as_float().ok().map(Piece::Adjustment)),
+        }
+    }
+
+    // Decoded text, with the index of the piece of each glyph by byte offset.
+    let mut decoded = String::new();
+    let mut starts = BTreeMap::new();
+    for (index, piece) in pieces.iter().enumerate() {
+        if let Piece::Glyph(code) = *piece {
+            starts.insert(decoded.len(), index);
+            decoded.push_str(&font.decode(code));
+        }
+    }
+    starts.insert(decoded.len(), pieces.len());
+    let found: Vec<(usize, usize)> = decoded
+        .match_indices(text)
+        .filter_map(|(offset, _)| Some((*starts.get(&offset)?, *starts.get(&(offset + text.len()))?)))
+        .collect();
+    if found.is_empty() {
+        return Ok(None);
+    }
+    let replacement = font.encode(other_text).ok_or(Error::CharacterEncoding)?;
+
+    // Advance of a glyph in thousandths of text space units, as used by kerning adjustments.
+    let advance = |code: Code| {
+        let spacing = if code.is_space() {
+            state.character_spacing + state.word_spacing
+        } else {
+            state.character_spacing
+        };
+        if state.font_size == 0.0 {
+            font.advance(code)
+        } else {
+            font.advance(code) + spacing * 1000.0 / state.font_size
+        }
+    };
+    let mut result = Vec::new();
+    let mut codes = Vec::new();
+    let flush = |codes: &mut Vec<Code>, result: &mut Vec<Object>| {
+        if !codes.is_empty() {
+            result.push(Object::String(font.code_bytes(codes), format.clone()));
+            codes.clear();
+        }
+    };
+    let mut index = 0;
+    let mut found = found.into_iter().peekable();
+    while index < pieces.len() {
+        if let Some(&(start, end)) = found.peek() {
+            if start == index {
+                let old_width: f64 = pieces[start..end]
+                    .iter()
+                    .map(|piece| match *piece {
+                        Piece::Glyph(code) => advance(code),
+                        Piece::Adjustment(adjustment) => -adjustment,
+                    })
+                    .sum();
+                let new_width: f64 = replacement.iter().map(|&code| advance(code)).sum();
+                codes.extend(&replacement);
+                if (new_width - old_width).abs() > 0.001 {
+                    flush(&mut codes, &mut result);
+                    result.push((new_width - old_width).into());
+                }
+                index = end;
+                found.next();
+                continue;
+            }
+        }
+        match pieces[index] {
+            Piece::Glyph(code) => codes.push(code),
+            Piece::Adjustment(adjustment) => {
+                flush(&mut codes, &mut result);
+                result.push(adjustment.into());
+            }
+        }
+        index += 1;
+    }
+    flush(&mut codes, &mut result);
+    Ok(Some(result))
+}
+
+/// Join the glyphs of text fragments into searchable text, with the byte offset, bounding box and line index
+/// of each character. Inserted spaces have no bounding box.
+#[allow(clippy::type_complexity)]
+#[cfg(feature = "search")]
+fn search_text(fragments: &[TextFragment]) -> (String, Vec<(usize, Option<Rectangle>, usize)>) {
+    let mut text = String::new();
+    let mut glyphs = Vec::new();
+    let mut previous: Option<Rectangle> = None;
+    let mut line = 0;
+    for glyph in fragments.iter().flat_map(|fragment| &fragment.glyphs) {
+        if let Some(previous) = previous {
+            let center = (glyph.bbox.lly + glyph.bbox.ury) / 2.0;
+            let height = previous.height().max(glyph.bbox.height());
+            let same_line = center >= previous.lly && center <= previous.ury && glyph.bbox.llx >= previous.llx;
+            let gap = glyph.bbox.llx - previous.urx;
+            if !same_line {
+                line += 1;
+            }
+            if (!same_line || gap > 0.15 * height) && !text.ends_with(char::is_whitespace) {
+                glyphs.push((text.len(), None, line));
+                text.push(' ');
+            }
+        }
+        for character in glyph.text.chars() {
+            glyphs.push((text.len(), Some(glyph.bbox), line));
+            text.push(character);
+        }
+        previous = Some(glyph.bbox);
+    }
+    (text, glyphs)
+}
+
+#[test]
+fn extract_positioned_text() {
+    use crate::content::{Content, Operation};
+    use crate::test_support::create_document;
+    use crate::xobject;
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    let fragments = doc.extract_text_fragments(1).unwrap();
+    assert_eq!(fragments.len(), 1);
+    assert_eq!(fragments[0].text, "Hello World!");
+    assert_eq!(fragments[0].font, "Courier");
+    assert_eq!(fragments[0].font_size, 48.0);
+    let words = fragments[0].words();
+    assert_eq!(words.len(), 2);
+    assert_eq!(words[1].0, "World!");
+    assert!(words[0].1.urx < words[1].1.llx);
+    assert!(fragments[0].bbox.llx == 100.0 && fragments[0].bbox.lly < 600.0 && fragments[0].bbox.ury > 600.0);
+
+    // Text of a scaled form XObject, in red.
+    let content = Content {
+        operations: vec![
+            Operation::new("BT", vec![]),
+            Operation::new("Tf", vec!["F1".into(), 10.into()]),
+            Operation::new("rg", vec![1.into(), 0.into(), 0.into()]),
+            Operation::new("Td", vec![5.into(), 5.into()]),
+            Operation::new(
+                "TJ",
+                vec![Object::Array(vec![
+                    Object::string_literal("A"),
+                    (-1000).into(),
+                    Object::string_literal("B"),
+                ])],
+            ),
+            Operation::new("ET", vec![]),
+        ],
+    };
+    let mut form = xobject::form(
+        vec![0.0, 0.0, 100.0, 100.0],
+        vec![2.0, 0.0, 0.0, 2.0, 0.0, 0.0],
+        content.encode().unwrap(),
+    );
+    let courier = doc.get_page_fonts(page_id)[b"F1".as_ref()].clone();
+    form.dict
+        .set("Resources", dictionary! { "Font" => dictionary! { "F1" => courier } });
+    let form_id = doc.add_object(form);
+    doc.add_xobject(page_id, "X1", form_id).unwrap();
+    let mut content = doc.get_and_decode_page_content(page_id).unwrap();
+    content.operations.push(Operation::new("q", vec![]));
+    content.operations.push(Operation::new(
+        "cm",
+        vec![1.into(), 0.into(), 0.into(), 1.into(), 50.into(), 50.into()],
+    ));
+    content.operations.push(Operation::new("Do", vec!["X1".into()]));
+    content.operations.push(Operation::new("Q", vec![]));
+    doc.change_page_content(page_id, content.encode().unwrap()).unwrap();
+
+    let fragments = doc.extract_text_fragments(1).unwrap();
+    assert_eq!(fragments.len(), 2);
+    let fragment = &fragments[1];
+    assert_eq!(fragment.text, "AB");
+    assert_eq!(fragment.font_size, 20.0);
+    assert_eq!(fragment.color, vec![1.0, 0.0, 0.0]);
+    assert_eq!(fragment.glyphs[0].bbox.llx, 60.0);
+    // Courier glyphs are 0.6 em wide, then the TJ adjustment moves one em further.
+    assert!((fragment.glyphs[1].bbox.llx - (60.0 + 20.0 * 1.6)).abs() < 1e-9);
+    assert!(doc.extract_text_fragments(2).is_err());
+}
+
+#[cfg(feature = "search")]
+#[test]
+fn search_text_across_operators() {
+    use crate::content::{Content, Operation};
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    let content = Content {
+        operations: vec![
+            Operation::new("BT", vec![]),
+            Operation::new("Tf", vec!["F1".into(), 10.into()]),
+            Operation::new("Td", vec![100.into(), 500.into()]),
+            // Kerned word, split over two operators.
+            Operation::new(
+                "TJ",
+                vec![Object::Array(vec![
+                    Object::string_literal("Lor"),
+                    20.into(),
+                    Object::string_literal("e"),
+                ])],
+            ),
+            Operation::new("Tj", vec![Object::string_literal("m")]),
+            // Words positioned apart without a space character.
+            Operation::new("Td", vec![50.into(), 0.into()]),
+            Operation::new("Tj", vec![Object::string_literal("ipsum")]),
+            Operation::new("Td", vec![(-50).into(), (-12).into()]),
+            Operation::new("Tj", vec![Object::string_literal("dolor sit")]),
+            Operation::new("ET", vec![]),
+        ],
+    };
+    let content_id = doc.add_object(crate::Stream::new(dictionary! {}, content.encode().unwrap()));
+    doc.get_object_mut(page_id)
+        .and_then(Object::as_dict_mut)
+        .unwrap()
+        .set("Contents", content_id);
+
+    let matches = doc.search(
+        "lorem IPSUM dolor",
+        &SearchOptions {
+            case_insensitive: true,
+            ..SearchOptions::default()
+        },
+    );
+    let matches = matches.unwrap();
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].page_number, 1);
+    assert_eq!(matches[0].text, "Lorem ipsum dolor");
+    assert_eq!(matches[0].quads.len(), 2);
+    assert_eq!(matches[0].quads[0][0], 100.0);
+    assert_eq!(matches[0].quads[1][1], 496.0);
+
+    assert!(doc.search("lorem", &SearchOptions::default()).unwrap().is_empty());
+    let options = SearchOptions {
+        regex: true,
+        ..SearchOptions::default()
+    };
+    let matches = doc.search(r"\b[a-z]{5}\b", &options).unwrap();
+    let words: Vec<&str> = matches.iter().map(|found| found.text.as_str()).collect();
+    assert_eq!(words, ["ipsum", "dolor"]);
+    assert!(doc.search("(", &options).is_err());
+}
+
+#[test]
+fn replace_text_in_operators() {
+    use crate::content::Content;
+    use crate::test_support::create_document;
+    use crate::Stream;
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    let to_unicode = b"1 beginbfrange\n<0001> <0003> <0041>\nendbfrange".to_vec();
+    let cid_font = dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type0",
+        "BaseFont" => "Test",
+        "Encoding" => "Identity-H",
+        "DescendantFonts" => vec![Object::Dictionary(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "CIDFontType2",
+            "DW" => 1000,
+            "W" => vec![3.into(), vec![500.into()].into()],
+        })],
+        "ToUnicode" => doc.add_object(Stream::new(dictionary! {}, to_unicode)),
+    };
+    let cid_font_id = doc.add_object(cid_font);
+    let resources = doc.get_or_create_resources(page_id).unwrap().as_dict_mut().unwrap();
+    resources
+        .get_mut(b"Font")
+        .and_then(Object::as_dict_mut)
+        .unwrap()
+        .set("F2", cid_font_id);
+    let content = Content {
+        operations: vec![
+            Operation::new("BT", vec![]),
+            Operation::new("Tf", vec!["F1".into(), 10.into()]),
+            Operation::new(
+                "TJ",
+                vec![Object::Array(vec![
+                    Object::string_literal("Dear Jo"),
+                    (-20).into(),
+                    Object::string_literal("hn Doe,"),
+                ])],
+            ),
+            Operation::new("Tj", vec![Object::string_literal("Unchanged")]),
+            Operation::new("Tf", vec!["F2".into(), 10.into()]),
+            Operation::new(
+                "Tj",
+                vec![Object::String(vec![0, 1, 0, 2, 0, 3], StringFormat::Hexadecimal)],
+            ),
+            Operation::new("ET", vec![]),
+        ],
+    };
+    let content_id = doc.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+    doc.get_object_mut(page_id)
+        .and_then(Object::as_dict_mut)
+        .unwrap()
+        .set("Contents", content_id);
+
+    doc.replace_text(1, "John Doe", "X").unwrap();
+    doc.replace_text(1, "B", "CA").unwrap();
+    assert!(matches!(doc.replace_text(1, "A", "Z"), Err(Error::CharacterEncoding)));
+
+    let content = doc.get_and_decode_page_content(page_id).unwrap();
+    let operands: Vec<&Vec<Object>> = content.operations.iter().map(|operation| &operation.operands).collect();
+    let items = operands[2][0].as_array().unwrap();
+    assert_eq!(items[0].as_str().unwrap(), b"Dear X");
+    // The replaced text was wider by seven glyphs of 600 units and a kerning adjustment of 20 units.
+    assert_eq!(items[1].as_float().unwrap(), -4220.0);
+    assert_eq!(items[2].as_str().unwrap(), b",");
+    assert_eq!(operands[3][0].as_str().unwrap(), b"Unchanged");
+    let items = operands[5][0].as_array().unwrap();
+    assert_eq!(items[0].as_str().unwrap(), [0, 1, 0, 3, 0, 1]);
+    assert_eq!(items[1].as_float().unwrap(), 500.0);
+    assert_eq!(items[2].as_str().unwrap(), [0, 3]);
+}
+
+#[test]
+fn extract_text_with_to_unicode() {
+    use crate::content::Content;
+    use crate::test_support::create_document;
+    use crate::{Stream, StringFormat};
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    let to_unicode = b"%!PS-Adobe-3.0 Resource-CMap\n\
+        /CIDInit /ProcSet findresource begin\n12 dict begin\nbegincmap\n\
+        1 begincodespacerange\n<00> <FF>\nendcodespacerange\n\
+        1 beginbfchar\n<01> <00660069> % fi ligature\nendbfchar\n\
+        2 beginbfrange\n<02> <04> <D835DC00>\n<05> <06> [<0041> <D83DDE00>]\nendbfrange\n\
+        endcmap\nCMapName currentdict /CMap defineresource pop\nend\nend\n";
+    let to_unicode_id = doc.add_object(Stream::new(dictionary! {}, to_unicode.to_vec()));
+    let font_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "TrueType",
+        "BaseFont" => "Test",
+        "ToUnicode" => to_unicode_id,
+    });
+    let resources = doc.get_or_create_resources(page_id).unwrap().as_dict_mut().unwrap();
+    resources
+        .get_mut(b"Font")
+        .and_then(Object::as_dict_mut)
+        .unwrap()
+        .set("F2", font_id);
+    let content = Content {
+        operations: vec![
+            Operation::new("BT", vec![]),
+            Operation::new("Tf", vec!["F2".into(), 10.into()]),
+            Operation::new(
+                "Tj",
+                vec![Object::String(vec![1, 2, 3, 4, 5, 6], StringFormat::Hexadecimal)],
+            ),
+            Operation::new("ET", vec![]),
+        ],
+    };
+    doc.append_page_content(page_id, content.encode().unwrap()).unwrap();
+
+    let text = "fi\u{1D400}\u{1D401}\u{1D402}A\u{1F600}";
+    assert_eq!(doc.extract_text(&[1]).unwrap(), format!("Hello World!\n{}\n", text));
+    assert_eq!(doc.extract_text_fragments(1).unwrap()[1].text, text);
+}
+
+#[test]
+fn extract_composite_font_text() {
+    use crate::content::Content;
+    use crate::test_support::create_document;
+    use crate::{Stream, StringFormat};
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    let cid_font = |doc: &mut Document, widths: Object| {
+        doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "CIDFontType0",
+            "BaseFont" => "Test",
+            "DW" => 1000,
+            "W" => widths,
+        })
+    };
+
+    // Unicode characters of a predefined CMap, positioned with the default width.
+    let descendant_id = cid_font(&mut doc, vec![].into());
+    let unicode_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type0",
+        "BaseFont" => "Unicode",
+        "Encoding" => "UniGB-UCS2-H",
+        "DescendantFonts" => vec![descendant_id.into()],
+    });
+
+    // Embedded CMap with one and two byte codes, and their character ids.
+    let cmap = b"/CIDInit /ProcSet findresource begin\n12 dict begin\nbegincmap\n/WMode 0 def\n\
+        2 begincodespacerange\n<00> <80>\n<8140> <FEFE>\nendcodespacerange\n\
+        1 begincidrange\n<20> <7E> 1\nendcidrange\n1 begincidchar\n<8140> 633\nendcidchar\n\
+        endcmap\nend\nend\n";
+    let cmap_id = doc.add_object(Stream::new(dictionary! { "Type" => "CMap" }, cmap.to_vec()));
+    let to_unicode = b"2 beginbfrange\n<20> <7E> <0020>\n<8140> <8140> <3000>\nendbfrange\n";
+    let to_unicode_id = doc.add_object(Stream::new(dictionary! {}, to_unicode.to_vec()));
+    let descendant_id = cid_font(&mut doc, vec![1.into(), 95.into(), 250.into()].into());
+    let embedded_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type0",
+        "BaseFont" => "Embedded",
+        "Encoding" => cmap_id,
+        "DescendantFonts" => vec![descendant_id.into()],
+        "ToUnicode" => to_unicode_id,
+    });
+
+    // Vertical text of the Identity-V CMap.
+    let to_unicode = b"1 beginbfrange\n<0001> <0002> <4E0A>\nendbfrange\n";
+    let to_unicode_id = doc.add_object(Stream::new(dictionary! {}, to_unicode.to_vec()));
+    let descendant_id = cid_font(&mut doc, vec![1.into(), vec![500.into(), 1000.into()].into()].into());
+    let vertical_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type0",
+        "BaseFont" => "Vertical",
+        "Encoding" => "Identity-V",
+        "DescendantFonts" => vec![descendant_id.into()],
+        "ToUnicode" => to_unicode_id,
+    });
+
+    let resources = doc.get_or_create_resources(page_id).unwrap().as_dict_mut().unwrap();
+    let fonts = resources.get_mut(b"Font").and_then(Object::as_dict_mut).unwrap();
+    fonts.set("F2", unicode_id);
+    fonts.set("F3", embedded_id);
+    fonts.set("F4", vertical_id);
+    let hex = |bytes: &[u8]| Object::String(bytes.to_vec(), StringFormat::Hexadecimal);
+    let content = Content {
+        operations: vec![
+            Operation::new("BT", vec![]),
+            Operation::new("Tf", vec!["F2".into(), 10.into()]),
+            Operation::new("Td", vec![100.into(), 500.into()]),
+            Operation::new("Tj", vec![hex(&[0x4E, 0x2D, 0x65, 0x87])]),
+            Operation::new("Tf", vec!["F3".into(), 10.into()]),
+            Operation::new("Td", vec![0.into(), (-20).into()]),
+            Operation::new("Tj", vec![hex(&[0x41, 0x81, 0x40, 0x42])]),
+            Operation::new("ET", vec![]),
+            Operation::new("BT", vec![]),
+            Operation::new("Tf", vec!["F4".into(), 10.into()]),
+            Operation::new("Td", vec![300.into(), 500.into()]),
+            Operation::new("Tj", vec![hex(&[0, 1, 0, 2])]),
+            Operation::new("ET", vec![]),
+        ],
+    };
+    doc.append_page_content(page_id, content.encode().unwrap()).unwrap();
+
+    assert_eq!(
+        doc.extract_text(&[1]).unwrap(),
+        "Hello World!\n\u{4E2D}\u{6587}A\u{3000}B\n\u{4E0A}\u{4E0B}\n"
+    );
+    let fragments = doc.extract_text_fragments(1).unwrap();
+    assert_eq!(fragments.len(), 4);
+    let left = |fragment: &TextFragment| -> Vec<f64> { fragment.glyphs.iter().map(|glyph| glyph.bbox.llx).collect() };
+    assert_eq!(left(&fragments[1]), vec![100.0, 110.0]);
+    assert_eq!(left(&fragments[2]), vec![100.0, 102.5, 112.5]);
+    // Vertical glyphs are centered on the text position and move down by one em.
+    let glyphs = &fragments[3].glyphs;
+    assert_eq!(left(&fragments[3]), vec![297.5, 295.0]);
+    assert!((glyphs[0].bbox.ury - (500.0 - 0.8)).abs() < 1e-9);
+    assert!((glyphs[1].bbox.ury - (490.0 - 0.8)).abs() < 1e-9);
+}
+
+#[test]
+fn iterate_pages_text() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    doc.merge(create_document()).unwrap();
+    doc.replace_text(2, "Hello World!", "Second page").unwrap();
+
+    let pages: Vec<(u32, String)> = doc.pages_text_iter().collect::<Result<_>>().unwrap();
+    assert_eq!(
+        pages,
+        vec![(1, "Hello World!\n".to_string()), (2, "Second page\n".to_string())]
+    );
+    assert_eq!(doc.extract_text(&[1, 2]).unwrap(), "Hello World!\nSecond page\n");
+}
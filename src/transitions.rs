@@ -0,0 +1,212 @@
+use crate::{Dictionary, Document, Object, ObjectId, Result};
+
+/// Effect of a page transition, with the direction of motion in degrees counterclockwise from left to right, which
+/// is 0, 90, 180, 270 or 315 depending on the style.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransitionStyle {
+    /// Two lines sweep across the screen, revealing the new page.
+    Split { vertical: bool, inward: bool },
+    /// Multiple lines sweep across the screen, revealing the new page.
+    Blinds { vertical: bool },
+    /// A rectangle sweeps inward from the edges or outward from the center.
+    Box { inward: bool },
+    /// A single line sweeps across the screen in a direction.
+    Wipe { direction: i64 },
+    /// The old page dissolves gradually.
+    Dissolve,
+    /// Like dissolve, with the effect sweeping across the screen in a direction.
+    Glitter { direction: i64 },
+    /// The new page replaces the old one without an effect.
+    Replace,
+    /// The new page flies in, or the old page flies out, in a direction.
+    Fly { direction: i64, inward: bool },
+    /// The new page slides on, pushing the old page off the screen.
+    Push { direction: i64 },
+    /// The new page slides on, covering the old page.
+    Cover { direction: i64 },
+    /// The old page slides off, uncovering the new page.
+    Uncover { direction: i64 },
+    /// The new page becomes visible gradually.
+    Fade,
+}
+
+/// Transition to a page during a presentation, given by /Trans of the page.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transition {
+    pub style: TransitionStyle,
+    /// Duration of the effect in seconds.
+    pub duration: f64,
+}
+
+impl Transition {
+    pub fn new(style: TransitionStyle) -> Transition {
+        Transition { style, duration: 1.0 }
+    }
+
+    pub fn with_duration(mut self, duration: f64) -> Transition {
+        self.duration = duration;
+        self
+    }
+
+    fn from_dictionary(dict: &Dictionary) -> Option<Transition> {
+        let vertical = dict.get(b"Dm").and_then(Object::as_name).ok() == Some(b"V");
+        let inward = dict.get(b"M").and_then(Object::as_name).ok() != Some(b"O");
+        // Fly transitions may have a direction of /None, which is only meaningful with a scale.
+        let direction = dict.get(b"Di").and_then(Object::as_i64).unwrap_or(0);
+        let style = match dict.get(b"S").and_then(Object::as_name).unwrap_or(b"R") {
+            b"Split" => TransitionStyle::Split { vertical, inward },
+            b"Blinds" => TransitionStyle::Blinds { vertical },
+            b"Box" => TransitionStyle::Box { inward },
+            b"Wipe" => TransitionStyle::Wipe { direction },
+            b"Dissolve" => TransitionStyle::Dissolve,
+            b"Glitter" => TransitionStyle::Glitter { direction },
+            b"R" => TransitionStyle::Replace,
+            b"Fly" => TransitionStyle::Fly { direction, inward },
+            b"Push" => TransitionStyle::Push { direction },
+            b"Cover" => TransitionStyle::Cover { direction },
+            b"Uncover" => TransitionStyle::Uncover { direction },
+            b"Fade" => TransitionStyle::Fade,
+            _ => return None,
+        };
+        Some(Transition {
+            style,
+            duration: dict.get(b"D").and_then(Object::as_float).unwrap_or(1.0),
+        })
+    }
+
+    fn to_dictionary(self) -> Dictionary {
+        let mut dict = dictionary! { "Type" => "Trans" };
+        let dimension = |vertical: bool| if vertical { "V" } else { "H" };
+        let motion = |inward: bool| if inward { "I" } else { "O" };
+        let (style, dimension, motion, direction) = match self.style {
+            TransitionStyle::Split { vertical, inward } => {
+                ("Split", Some(dimension(vertical)), Some(motion(inward)), None)
+            }
+            TransitionStyle::Blinds { vertical } => ("Blinds", Some(dimension(vertical)), None, None),
+            TransitionStyle::Box { inward } => ("Box", None, Some(motion(inward)), None),
+            TransitionStyle::Wipe { direction } => ("Wipe", None, None, Some(direction)),
+            TransitionStyle::Dissolve => ("Dissolve", None, None, None),
+            TransitionStyle::Glitter { direction } => ("Glitter", None, None, Some(direction)),
+            TransitionStyle::Replace => ("R", None, None, None),
+            TransitionStyle::Fly { direction, inward } => ("Fly", None, Some(motion(inward)), Some(direction)),
+            TransitionStyle::Push { direction } => ("Push", None, None, Some(direction)),
+            TransitionStyle::Cover { direction } => ("Cover", None, None, Some(direction)),
+            TransitionStyle::Uncover { direction } => ("Uncover", None, None, Some(direction)),
+            TransitionStyle::Fade => ("Fade", None, None, None),
+        };
+        dict.set("S", style);
+        if let Some(dimension) = dimension {
+            dict.set("Dm", dimension);
+        }
+        if let Some(motion) = motion {
+            dict.set("M", motion);
+        }
+        if let Some(direction) = direction {
+            dict.set("Di", direction);
+        }
+        if (self.duration - 1.0).abs() > f64::EPSILON {
+            dict.set("D", self.duration);
+        }
+        dict
+    }
+}
+
+impl Document {
+    /// Get the transition to a page during presentations. Transitions of unknown styles are ignored.
+    pub fn get_page_transition(&self, page_id: ObjectId) -> Result<Option<Transition>> {
+        let transition = match self.get_dictionary(page_id)?.get(b"Trans") {
+            Ok(transition) => self.dereference(transition)?.1.as_dict()?,
+            Err(_) => return Ok(None),
+        };
+        Ok(Transition::from_dictionary(transition))
+    }
+
+    /// Set the transition to a page during presentations, or remove it for no effect.
+    pub fn set_page_transition(&mut self, page_id: ObjectId, transition: Option<&Transition>) -> Result<()> {
+        let page = self.get_object_mut(page_id).and_then(Object::as_dict_mut)?;
+        match transition {
+            Some(transition) => page.set("Trans", transition.to_dictionary()),
+            None => {
+                page.remove(b"Trans");
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the number of seconds a page is shown before advancing to the next page during presentations, given by
+    /// /Dur of the page.
+    pub fn get_page_display_duration(&self, page_id: ObjectId) -> Result<Option<f64>> {
+        Ok(self
+            .get_dictionary(page_id)?
+            .get(b"Dur")
+            .and_then(Object::as_float)
+            .ok())
+    }
+
+    /// Set the number of seconds a page is shown during presentations, or remove it for advancing manually.
+    pub fn set_page_display_duration(&mut self, page_id: ObjectId, duration: Option<f64>) -> Result<()> {
+        let page = self.get_object_mut(page_id).and_then(Object::as_dict_mut)?;
+        match duration {
+            Some(duration) => page.set("Dur", duration),
+            None => {
+                page.remove(b"Dur");
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn read_and_write_page_transitions() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    assert_eq!(doc.get_page_transition(page_id).unwrap(), None);
+
+    let styles = [
+        TransitionStyle::Split {
+            vertical: true,
+            inward: false,
+        },
+        TransitionStyle::Wipe { direction: 270 },
+        TransitionStyle::Fly {
+            direction: 0,
+            inward: true,
+        },
+        TransitionStyle::Fade,
+    ];
+    for &style in styles.iter() {
+        let transition = Transition::new(style).with_duration(0.5);
+        doc.set_page_transition(page_id, Some(&transition)).unwrap();
+        assert_eq!(doc.get_page_transition(page_id).unwrap(), Some(transition));
+    }
+    let dict = doc
+        .get_dictionary(page_id)
+        .unwrap()
+        .get(b"Trans")
+        .and_then(Object::as_dict)
+        .unwrap();
+    assert_eq!(dict.get(b"S").and_then(Object::as_name_str).unwrap(), "Fade");
+    assert_eq!(dict.get(b"D").and_then(Object::as_float).unwrap(), 0.5);
+
+    // Entries which are missing have their defaults.
+    doc.get_object_mut(page_id)
+        .and_then(Object::as_dict_mut)
+        .unwrap()
+        .set("Trans", dictionary! { "S" => "Split" });
+    assert_eq!(
+        doc.get_page_transition(page_id).unwrap(),
+        Some(Transition::new(TransitionStyle::Split {
+            vertical: false,
+            inward: true,
+        }))
+    );
+
+    doc.set_page_display_duration(page_id, Some(5.0)).unwrap();
+    assert_eq!(doc.get_page_display_duration(page_id).unwrap(), Some(5.0));
+    doc.set_page_transition(page_id, None).unwrap();
+    doc.set_page_display_duration(page_id, None).unwrap();
+    assert_eq!(doc.get_page_transition(page_id).unwrap(), None);
+    assert_eq!(doc.get_page_display_duration(page_id).unwrap(), None);
+}
@@ -0,0 +1,182 @@
+//! Conversion between points, the unit of PDF coordinates, and millimeters and inches, and standard page sizes.
+
+use crate::Rectangle;
+
+pub const POINTS_PER_INCH: f64 = 72.0;
+pub const MILLIMETERS_PER_INCH: f64 = 25.4;
+const POINTS_PER_MM: f64 = POINTS_PER_INCH / MILLIMETERS_PER_INCH;
+
+/// Convert millimeters to points.
+pub fn mm(millimeters: f64) -> f64 {
+    millimeters * POINTS_PER_MM
+}
+
+/// Convert centimeters to points.
+pub fn cm(centimeters: f64) -> f64 {
+    mm(centimeters * 10.0)
+}
+
+/// Convert inches to points.
+pub fn inch(inches: f64) -> f64 {
+    inches * POINTS_PER_INCH
+}
+
+pub fn points_to_mm(points: f64) -> f64 {
+    points / POINTS_PER_MM
+}
+
+pub fn points_to_inches(points: f64) -> f64 {
+    points / POINTS_PER_INCH
+}
+
+/// Width and height of a page in points, in portrait orientation for the standard sizes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageSize {
+    pub width: f64,
+    pub height: f64,
+}
+
+impl PageSize {
+    pub const A0: PageSize = PageSize {
+        width: 841.0 * POINTS_PER_MM,
+        height: 1189.0 * POINTS_PER_MM,
+    };
+    pub const A1: PageSize = PageSize {
+        width: 594.0 * POINTS_PER_MM,
+        height: 841.0 * POINTS_PER_MM,
+    };
+    pub const A2: PageSize = PageSize {
+        width: 420.0 * POINTS_PER_MM,
+        height: 594.0 * POINTS_PER_MM,
+    };
+    pub const A3: PageSize = PageSize {
+        width: 297.0 * POINTS_PER_MM,
+        height: 420.0 * POINTS_PER_MM,
+    };
+    pub const A4: PageSize = PageSize {
+        width: 210.0 * POINTS_PER_MM,
+        height: 297.0 * POINTS_PER_MM,
+    };
+    pub const A5: PageSize = PageSize {
+        width: 148.0 * POINTS_PER_MM,
+        height: 210.0 * POINTS_PER_MM,
+    };
+    pub const A6: PageSize = PageSize {
+        width: 105.0 * POINTS_PER_MM,
+        height: 148.0 * POINTS_PER_MM,
+    };
+    pub const B4: PageSize = PageSize {
+        width: 250.0 * POINTS_PER_MM,
+        height: 353.0 * POINTS_PER_MM,
+    };
+    pub const B5: PageSize = PageSize {
+        width: 176.0 * POINTS_PER_MM,
+        height: 250.0 * POINTS_PER_MM,
+    };
+    /// US Letter, 8.5 by 11 inches.
+    pub const LETTER: PageSize = PageSize {
+        width: 612.0,
+        height: 792.0,
+    };
+    /// US Legal, 8.5 by 14 inches.
+    pub const LEGAL: PageSize = PageSize {
+        width: 612.0,
+        height: 1008.0,
+    };
+    /// Tabloid, 11 by 17 inches, which is Ledger in landscape orientation.
+    pub const TABLOID: PageSize = PageSize {
+        width: 792.0,
+        height: 1224.0,
+    };
+    /// Executive, 7.25 by 10.5 inches.
+    pub const EXECUTIVE: PageSize = PageSize {
+        width: 522.0,
+        height: 756.0,
+    };
+
+    pub fn new(width: f64, height: f64) -> PageSize {
+        PageSize { width, height }
+    }
+
+    /// Page size given in millimeters.
+    pub fn from_mm(width: f64, height: f64) -> PageSize {
+        PageSize::new(mm(width), mm(height))
+    }
+
+    /// Get a standard page size by its name, such as "A4" or "Letter", ignoring case.
+    pub fn from_name(name: &str) -> Option<PageSize> {
+        let size = match name.to_ascii_uppercase().as_str() {
+            "A0" => PageSize::A0,
+            "A1" => PageSize::A1,
+            "A2" => PageSize::A2,
+            "A3" => PageSize::A3,
+            "A4" => PageSize::A4,
+            "A5" => PageSize::A5,
+            "A6" => PageSize::A6,
+            "B4" => PageSize::B4,
+            "B5" => PageSize::B5,
+            "LETTER" => PageSize::LETTER,
+            "LEGAL" => PageSize::LEGAL,
+            "TABLOID" => PageSize::TABLOID,
+            "EXECUTIVE" => PageSize::EXECUTIVE,
+            _ => return None,
+        };
+        Some(size)
+    }
+
+    /// The same size with width and height swapped if needed so that the page is wider than high.
+    pub fn landscape(self) -> PageSize {
+        if self.width < self.height {
+            PageSize::new(self.height, self.width)
+        } else {
+            self
+        }
+    }
+
+    /// The same size with width and height swapped if needed so that the page is higher than wide.
+    pub fn portrait(self) -> PageSize {
+        if self.width > self.height {
+            PageSize::new(self.height, self.width)
+        } else {
+            self
+        }
+    }
+
+    /// Width and height, as used for the sheet sizes of imposition.
+    pub fn size(self) -> (f64, f64) {
+        (self.width, self.height)
+    }
+
+    /// Rectangle of the page size with the lower-left corner at the origin, as used for a media box.
+    pub fn rectangle(self) -> Rectangle {
+        Rectangle::new(0.0, 0.0, self.width, self.height)
+    }
+}
+
+impl From<PageSize> for Rectangle {
+    fn from(size: PageSize) -> Rectangle {
+        size.rectangle()
+    }
+}
+
+#[test]
+fn convert_units_and_page_sizes() {
+    let close = |a: f64, b: f64| (a - b).abs() < 0.001;
+    assert!(close(mm(25.4), 72.0));
+    assert!(close(cm(2.54), 72.0));
+    assert!(close(inch(8.5), 612.0));
+    assert!(close(points_to_mm(72.0), 25.4));
+    assert!(close(points_to_inches(inch(2.0)), 2.0));
+    assert_eq!(PageSize::from_mm(210.0, 297.0), PageSize::A4);
+
+    assert!(close(PageSize::A4.width, 595.276));
+    assert!(close(PageSize::A4.height, 841.89));
+    assert_eq!(PageSize::from_name("letter"), Some(PageSize::LETTER));
+    assert_eq!(PageSize::from_name("A7"), None);
+    assert_eq!(PageSize::LETTER.landscape().size(), (792.0, 612.0));
+    assert_eq!(PageSize::LETTER.landscape().portrait(), PageSize::LETTER);
+    assert_eq!(
+        Rectangle::from(PageSize::LEGAL),
+        Rectangle::new(0.0, 0.0, 612.0, 1008.0)
+    );
+}
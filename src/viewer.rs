@@ -0,0 +1,451 @@
+use crate::{Dictionary, Document, Object, ObjectId, Rectangle, Result};
+
+/// Arrangement of the pages when the document is opened, given by /PageLayout of the catalog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageLayout {
+    /// One page at a time.
+    SinglePage,
+    /// The pages in one continuous column.
+    OneColumn,
+    /// The pages in two columns, with odd pages on the left.
+    TwoColumnLeft,
+    /// The pages in two columns, with odd pages on the right.
+    TwoColumnRight,
+    /// Two pages at a time, with odd pages on the left.
+    TwoPageLeft,
+    /// Two pages at a time, with odd pages on the right.
+    TwoPageRight,
+}
+
+impl PageLayout {
+    fn from_name(name: &[u8]) -> Option<PageLayout> {
+        match name {
+            b"SinglePage" => Some(PageLayout::SinglePage),
+            b"OneColumn" => Some(PageLayout::OneColumn),
+            b"TwoColumnLeft" => Some(PageLayout::TwoColumnLeft),
+            b"TwoColumnRight" => Some(PageLayout::TwoColumnRight),
+            b"TwoPageLeft" => Some(PageLayout::TwoPageLeft),
+            b"TwoPageRight" => Some(PageLayout::TwoPageRight),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            PageLayout::SinglePage => "SinglePage",
+            PageLayout::OneColumn => "OneColumn",
+            PageLayout::TwoColumnLeft => "TwoColumnLeft",
+            PageLayout::TwoColumnRight => "TwoColumnRight",
+            PageLayout::TwoPageLeft => "TwoPageLeft",
+            PageLayout::TwoPageRight => "TwoPageRight",
+        }
+    }
+}
+
+/// What is shown besides the pages when the document is opened, given by /PageMode of the catalog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageMode {
+    /// Neither the outline nor the thumbnails.
+    UseNone,
+    UseOutlines,
+    UseThumbs,
+    /// Full screen, without menu bar, window controls or any other window.
+    FullScreen,
+    /// The panel of optional content groups.
+    UseOC,
+    /// The panel of attachments.
+    UseAttachments,
+}
+
+impl PageMode {
+    fn from_name(name: &[u8]) -> Option<PageMode> {
+        match name {
+            b"UseNone" => Some(PageMode::UseNone),
+            b"UseOutlines" => Some(PageMode::UseOutlines),
+            b"UseThumbs" => Some(PageMode::UseThumbs),
+            b"FullScreen" => Some(PageMode::FullScreen),
+            b"UseOC" => Some(PageMode::UseOC),
+            b"UseAttachments" => Some(PageMode::UseAttachments),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            PageMode::UseNone => "UseNone",
+            PageMode::UseOutlines => "UseOutlines",
+            PageMode::UseThumbs => "UseThumbs",
+            PageMode::FullScreen => "FullScreen",
+            PageMode::UseOC => "UseOC",
+            PageMode::UseAttachments => "UseAttachments",
+        }
+    }
+}
+
+/// Paper handling preset for the print dialog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Duplex {
+    /// Print single-sided.
+    Simplex,
+    /// Print double-sided, flipping on the short edge of the sheet.
+    DuplexFlipShortEdge,
+    /// Print double-sided, flipping on the long edge of the sheet.
+    DuplexFlipLongEdge,
+}
+
+/// Entries of the /ViewerPreferences dictionary of the catalog. Flags which are not set are false.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ViewerPreferences {
+    pub hide_toolbar: bool,
+    pub hide_menubar: bool,
+    /// Hide the scroll bars, navigation controls and other user interface elements of the window.
+    pub hide_window_ui: bool,
+    /// Resize the window to fit the first page.
+    pub fit_window: bool,
+    pub center_window: bool,
+    /// Show the title of the document information dictionary or metadata instead of the file name in the title bar.
+    pub display_doc_title: bool,
+    /// Page mode used when leaving full screen mode, for documents opening in full screen mode.
+    pub non_full_screen_page_mode: Option<PageMode>,
+    pub duplex: Option<Duplex>,
+}
+
+impl ViewerPreferences {
+    const FLAGS: [&'static str; 6] = [
+        "HideToolbar",
+        "HideMenubar",
+        "HideWindowUI",
+        "FitWindow",
+        "CenterWindow",
+        "DisplayDocTitle",
+    ];
+
+    fn flags(&self) -> [bool; 6] {
+        [
+            self.hide_toolbar,
+            self.hide_menubar,
+            self.hide_window_ui,
+            self.fit_window,
+            self.center_window,
+            self.display_doc_title,
+        ]
+    }
+
+    fn from_dictionary(dict: &Dictionary) -> ViewerPreferences {
+        let flag = |key: &str| matches!(dict.get(key.as_bytes()), Ok(Object::Boolean(true)));
+        let duplex = match dict.get(b"Duplex").and_then(Object::as_name_str) {
+            Ok("Simplex") => Some(Duplex::Simplex),
+            Ok("DuplexFlipShortEdge") => Some(Duplex::DuplexFlipShortEdge),
+            Ok("DuplexFlipLongEdge") => Some(Duplex::DuplexFlipLongEdge),
+            _ => None,
+        };
+        ViewerPreferences {
+            hide_toolbar: flag("HideToolbar"),
+            hide_menubar: flag("HideMenubar"),
+            hide_window_ui: flag("HideWindowUI"),
+            fit_window: flag("FitWindow"),
+            center_window: flag("CenterWindow"),
+            display_doc_title: flag("DisplayDocTitle"),
+            non_full_screen_page_mode: dict
+                .get(b"NonFullScreenPageMode")
+                .and_then(Object::as_name)
+                .ok()
+                .and_then(PageMode::from_name),
+            duplex,
+        }
+    }
+
+    /// Write the preferences into a dictionary, keeping the entries which have no field. Flags which are false and
+    /// unset fields are removed, as they are the default.
+    fn update_dictionary(&self, dict: &mut Dictionary) {
+        for (key, &value) in Self::FLAGS.iter().zip(self.flags().iter()) {
+            if value {
+                dict.set(*key, true);
+            } else {
+                dict.remove(key.as_bytes());
+            }
+        }
+        match self.non_full_screen_page_mode {
+            Some(mode) => dict.set("NonFullScreenPageMode", mode.name()),
+            None => {
+                dict.remove(b"NonFullScreenPageMode");
+            }
+        }
+        let duplex = self.duplex.map(|duplex| match duplex {
+            Duplex::Simplex => "Simplex",
+            Duplex::DuplexFlipShortEdge => "DuplexFlipShortEdge",
+            Duplex::DuplexFlipLongEdge => "DuplexFlipLongEdge",
+        });
+        match duplex {
+            Some(duplex) => dict.set("Duplex", duplex),
+            None => {
+                dict.remove(b"Duplex");
+            }
+        }
+    }
+}
+
+/// How a page of an explicit destination is shown. Coordinates which are none are left unchanged by the viewer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DestinationView {
+    /// Position the top left corner of the window at the coordinates and zoom by a factor, 1.0 being 100%, as
+    /// given by /XYZ.
+    Position {
+        left: Option<f64>,
+        top: Option<f64>,
+        zoom: Option<f64>,
+    },
+    /// Fit the whole page in the window.
+    Fit,
+    /// Fit the width of the page in the window, with the top of the window at the coordinate.
+    FitH(Option<f64>),
+    /// Fit the height of the page in the window, with the left of the window at the coordinate.
+    FitV(Option<f64>),
+    /// Fit the rectangle in the window.
+    FitR(Rectangle),
+    /// Fit the bounding box of the page content in the window.
+    FitB,
+    FitBH(Option<f64>),
+    FitBV(Option<f64>),
+}
+
+impl DestinationView {
+    /// Get the view of an explicit destination array, which starts with the page.
+    pub fn from_destination(destination: &[Object]) -> Option<DestinationView> {
+        let number = |index: usize| destination.get(index).and_then(|number| number.as_float().ok());
+        let view = match destination.get(1)?.as_name().ok()? {
+            b"XYZ" => DestinationView::Position {
+                left: number(2),
+                top: number(3),
+                // A zoom of zero also keeps the current zoom.
+                zoom: number(4).filter(|&zoom| zoom != 0.0),
+            },
+            b"Fit" => DestinationView::Fit,
+            b"FitH" => DestinationView::FitH(number(2)),
+            b"FitV" => DestinationView::FitV(number(2)),
+            b"FitR" => DestinationView::FitR(Rectangle::new(number(2)?, number(3)?, number(4)?, number(5)?)),
+            b"FitB" => DestinationView::FitB,
+            b"FitBH" => DestinationView::FitBH(number(2)),
+            b"FitBV" => DestinationView::FitBV(number(2)),
+            _ => return None,
+        };
+        Some(view)
+    }
+
+    /// Get an explicit destination array showing a page.
+    pub fn destination(&self, page_id: ObjectId) -> Vec<Object> {
+        let number = |number: Option<f64>| number.map(Object::from).unwrap_or(Object::Null);
+        let mut destination = vec![page_id.into()];
+        match *self {
+            DestinationView::Position { left, top, zoom } => {
+                destination.extend(vec!["XYZ".into(), number(left), number(top), number(zoom)])
+            }
+            DestinationView::Fit => destination.push("Fit".into()),
+            DestinationView::FitH(top) => destination.extend(vec!["FitH".into(), number(top)]),
+            DestinationView::FitV(left) => destination.extend(vec!["FitV".into(), number(left)]),
+            DestinationView::FitR(rect) => destination.extend(vec![
+                "FitR".into(),
+                rect.llx.into(),
+                rect.lly.into(),
+                rect.urx.into(),
+                rect.ury.into(),
+            ]),
+            DestinationView::FitB => destination.push("FitB".into()),
+            DestinationView::FitBH(top) => destination.extend(vec!["FitBH".into(), number(top)]),
+            DestinationView::FitBV(left) => destination.extend(vec!["FitBV".into(), number(left)]),
+        }
+        destination
+    }
+}
+
+impl Document {
+    pub fn get_page_layout(&self) -> Result<Option<PageLayout>> {
+        Ok(self
+            .catalog()?
+            .get(b"PageLayout")
+            .and_then(Object::as_name)
+            .ok()
+            .and_then(PageLayout::from_name))
+    }
+
+    /// Set the page layout, or remove it for the default of single pages.
+    pub fn set_page_layout(&mut self, layout: Option<PageLayout>) -> Result<()> {
+        let catalog = self.catalog_mut()?;
+        match layout {
+            Some(layout) => catalog.set("PageLayout", layout.name()),
+            None => {
+                catalog.remove(b"PageLayout");
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get_page_mode(&self) -> Result<Option<PageMode>> {
+        Ok(self
+            .catalog()?
+            .get(b"PageMode")
+            .and_then(Object::as_name)
+            .ok()
+            .and_then(PageMode::from_name))
+    }
+
+    /// Set the page mode, or remove it for the default of neither outline nor thumbnails.
+    pub fn set_page_mode(&mut self, mode: Option<PageMode>) -> Result<()> {
+        let catalog = self.catalog_mut()?;
+        match mode {
+            Some(mode) => catalog.set("PageMode", mode.name()),
+            None => {
+                catalog.remove(b"PageMode");
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the viewer preferences, which are all default for documents without /ViewerPreferences.
+    pub fn get_viewer_preferences(&self) -> Result<ViewerPreferences> {
+        let preferences = match self.catalog()?.get(b"ViewerPreferences") {
+            Ok(preferences) => self.dereference(preferences)?.1.as_dict()?,
+            Err(_) => return Ok(ViewerPreferences::default()),
+        };
+        Ok(ViewerPreferences::from_dictionary(preferences))
+    }
+
+    /// Set the viewer preferences, keeping the entries of /ViewerPreferences which [`ViewerPreferences`] doesn't
+    /// have, such as the print settings. The dictionary is removed when nothing is left.
+    pub fn set_viewer_preferences(&mut self, preferences: &ViewerPreferences) -> Result<()> {
+        let mut dict = match self.catalog()?.get(b"ViewerPreferences") {
+            Ok(dict) => self.dereference(dict)?.1.as_dict()?.clone(),
+            Err(_) => Dictionary::new(),
+        };
+        if let Ok(id) = self.catalog()?.get(b"ViewerPreferences").and_then(Object::as_reference) {
+            self.objects.remove(&id);
+        }
+        preferences.update_dictionary(&mut dict);
+        let catalog = self.catalog_mut()?;
+        if dict.is_empty() {
+            catalog.remove(b"ViewerPreferences");
+        } else {
+            catalog.set("ViewerPreferences", dict);
+        }
+        Ok(())
+    }
+
+    /// Get the page and view shown when the document is opened, given by /OpenAction as an explicit destination or
+    /// as a go-to action. Named destinations are resolved.
+    pub fn get_open_destination(&self) -> Result<Option<(ObjectId, DestinationView)>> {
+        let action = match self.catalog()?.get(b"OpenAction") {
+            Ok(action) => self.dereference(action)?.1,
+            Err(_) => return Ok(None),
+        };
+        let destination = match *action {
+            Object::Dictionary(ref action) if action.get(b"S").and_then(Object::as_name).ok() == Some(b"GoTo") => {
+                self.dereference(action.get(b"D")?)?.1
+            }
+            Object::Dictionary(_) => return Ok(None),
+            _ => action,
+        };
+        let destination = match *destination {
+            Object::Array(ref destination) => destination.clone(),
+            Object::Name(ref name) | Object::String(ref name, _) => {
+                match self.named_destinations()?.find(|(other, _)| other == name) {
+                    Some((_, Object::Array(destination))) => destination,
+                    _ => return Ok(None),
+                }
+            }
+            _ => return Ok(None),
+        };
+        let page_id = match destination.first().map(Object::as_reference) {
+            Some(Ok(page_id)) => page_id,
+            _ => return Ok(None),
+        };
+        Ok(DestinationView::from_destination(&destination).map(|view| (page_id, view)))
+    }
+
+    /// Show a page with a view when the document is opened, replacing the /OpenAction of the catalog.
+    pub fn set_open_destination(&mut self, page_id: ObjectId, view: DestinationView) -> Result<()> {
+        self.set_open_action(Object::Array(view.destination(page_id)))
+    }
+
+    /// Replace the /OpenAction of the catalog with an explicit destination or an action dictionary, or remove it
+    /// with null.
+    pub fn set_open_action(&mut self, action: Object) -> Result<()> {
+        let catalog = self.catalog_mut()?;
+        match action {
+            Object::Null => {
+                catalog.remove(b"OpenAction");
+            }
+            action => catalog.set("OpenAction", action),
+        }
+        Ok(())
+    }
+
+    fn catalog_mut(&mut self) -> Result<&mut Dictionary> {
+        let catalog_id = self.trailer.get(b"Root").and_then(Object::as_reference)?;
+        self.get_object_mut(catalog_id).and_then(Object::as_dict_mut)
+    }
+}
+
+#[test]
+fn read_and_write_viewer_settings() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    assert_eq!(doc.get_page_layout().unwrap(), None);
+    assert_eq!(doc.get_viewer_preferences().unwrap(), ViewerPreferences::default());
+    assert_eq!(doc.get_open_destination().unwrap(), None);
+
+    doc.set_page_layout(Some(PageLayout::TwoPageRight)).unwrap();
+    doc.set_page_mode(Some(PageMode::UseOutlines)).unwrap();
+    assert_eq!(doc.get_page_layout().unwrap(), Some(PageLayout::TwoPageRight));
+    assert_eq!(doc.get_page_mode().unwrap(), Some(PageMode::UseOutlines));
+    doc.set_page_mode(None).unwrap();
+    assert!(!doc.catalog().unwrap().has(b"PageMode"));
+
+    // Entries without a field are kept.
+    let preferences_id = doc.add_object(dictionary! { "PrintScaling" => "None", "HideMenubar" => true });
+    doc.catalog_mut().unwrap().set("ViewerPreferences", preferences_id);
+    let mut preferences = doc.get_viewer_preferences().unwrap();
+    assert!(preferences.hide_menubar);
+    preferences.hide_menubar = false;
+    preferences.hide_toolbar = true;
+    preferences.fit_window = true;
+    preferences.duplex = Some(Duplex::DuplexFlipLongEdge);
+    doc.set_viewer_preferences(&preferences).unwrap();
+    assert_eq!(doc.get_viewer_preferences().unwrap(), preferences);
+    let dict = doc
+        .catalog()
+        .unwrap()
+        .get(b"ViewerPreferences")
+        .and_then(Object::as_dict)
+        .unwrap();
+    assert_eq!(dict.get(b"PrintScaling").and_then(Object::as_name_str).unwrap(), "None");
+    assert!(!dict.has(b"HideMenubar"));
+    assert!(doc.get_object(preferences_id).is_err());
+
+    let view = DestinationView::Position {
+        left: None,
+        top: Some(700.0),
+        zoom: Some(1.5),
+    };
+    doc.set_open_destination(page_id, view).unwrap();
+    assert_eq!(doc.get_open_destination().unwrap(), Some((page_id, view)));
+
+    // Go-to actions with named destinations are resolved.
+    doc.add_named_destination(
+        "start",
+        Object::Array(DestinationView::FitH(Some(500.0)).destination(page_id)),
+    )
+    .unwrap();
+    doc.set_open_action(Object::Dictionary(dictionary! {
+        "S" => "GoTo",
+        "D" => Object::string_literal("start"),
+    }))
+    .unwrap();
+    assert_eq!(
+        doc.get_open_destination().unwrap(),
+        Some((page_id, DestinationView::FitH(Some(500.0))))
+    );
+    doc.set_open_action(Object::Null).unwrap();
+    assert_eq!(doc.get_open_destination().unwrap(), None);
+}
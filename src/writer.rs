@@ -1,55 +1,291 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::{BufWriter, Result, Write};
 use std::path::Path;
 
 use super::Object::*;
 use super::{Dictionary, Document, Object, Stream, StringFormat};
+#[cfg(feature = "crypto")]
+use crate::encryption::{EncryptionAlgorithm, EncryptionOptions, SecurityHandler};
 use crate::xref::*;
 
+/// Security handler of documents which are never encrypted, as without the `crypto` feature.
+#[cfg(not(feature = "crypto"))]
+enum SecurityHandler {}
+
+#[cfg(not(feature = "crypto"))]
+impl SecurityHandler {
+    fn encrypt_object(&self, _id: (u32, u16), _object: &mut Object) {
+        match *self {}
+    }
+}
+
+/// Options controlling how a document is written.
+#[derive(Debug, Clone)]
+pub struct SaveOptions {
+    /// Write a compressed cross-reference stream (PDF 1.5) instead of a cross-reference table.
+    pub use_xref_stream: bool,
+    /// Pack objects other than streams into compressed object streams (PDF 1.5).
+    /// Implies a cross-reference stream, since compressed objects can't be listed in a cross-reference table.
+    pub use_object_streams: bool,
+    /// Maximum number of objects packed into one object stream.
+    pub max_objects_per_stream: usize,
+    /// With object streams, write a hybrid-reference file: a cross-reference table for readers of PDF before 1.5,
+    /// with the compressed objects in a cross-reference stream which the trailer refers to by /XRefStm. Otherwise
+    /// the cross-references are written in a single style.
+    pub hybrid_xref: bool,
+    /// Encrypt strings and streams with the standard security handler.
+    #[cfg(feature = "crypto")]
+    pub encryption: Option<EncryptionOptions>,
+    /// Encrypt strings and streams for the certificates of recipients with the public-key security handler,
+    /// instead of with the standard security handler.
+    #[cfg(feature = "pubsec")]
+    pub public_key_encryption: Option<crate::PublicKeyEncryptionOptions>,
+}
+
+impl Default for SaveOptions {
+    fn default() -> Self {
+        SaveOptions {
+            use_xref_stream: false,
+            use_object_streams: false,
+            max_objects_per_stream: 100,
+            hybrid_xref: false,
+            #[cfg(feature = "crypto")]
+            encryption: None,
+            #[cfg(feature = "pubsec")]
+            public_key_encryption: None,
+        }
+    }
+}
+
+/// Cross-reference entry as written into a file: type (0 free, 1 in use, 2 compressed) and two type specific fields.
+pub(crate) type XrefRow = (u8, u32, u16);
+
 impl Document {
     /// Save PDF document to specified file path.
     #[inline]
     pub fn save<P: AsRef<Path>>(&mut self, path: P) -> Result<File> {
+        self.save_with_options(path, &SaveOptions::default())
+    }
+
+    /// Save PDF document to specified file path using the given options.
+    pub fn save_with_options<P: AsRef<Path>>(&mut self, path: P, options: &SaveOptions) -> Result<File> {
         let mut file = BufWriter::new(File::create(path)?);
-        self.save_internal(&mut file)?;
+        self.save_internal(&mut file, options)?;
         Ok(file.into_inner()?)
     }
 
     /// Save PDF to arbitrary target
     #[inline]
     pub fn save_to<W: Write>(&mut self, target: &mut W) -> Result<()> {
-        self.save_internal(target)
+        self.save_internal(target, &SaveOptions::default())?;
+        Ok(())
+    }
+
+    /// Save PDF to arbitrary target using the given options.
+    #[inline]
+    pub fn save_to_with_options<W: Write>(&mut self, target: &mut W, options: &SaveOptions) -> Result<()> {
+        self.save_internal(target, options)?;
+        Ok(())
+    }
+
+    /// Save PDF document to specified file path, encrypted as it was before it was decrypted.
+    ///
+    /// The original encryption dictionary is kept, so the document opens with the same passwords and grants the
+    /// same permissions. The file encryption key is derived again from `owner_password`; the user password is
+    /// accepted as well, as it gives the same key.
+    #[cfg(feature = "crypto")]
+    pub fn save_with_original_encryption<P: AsRef<Path>>(
+        &mut self, path: P, owner_password: &str,
+    ) -> crate::Result<File> {
+        let security = self.original_security_handler(owner_password)?;
+        let mut file = BufWriter::new(File::create(path)?);
+        self.write_document(&mut file, &SaveOptions::default(), Some(security), "")?;
+        Ok(file.into_inner().map_err(std::io::Error::from)?)
+    }
+
+    /// Save PDF to arbitrary target, encrypted as it was before it was decrypted.
+    #[cfg(feature = "crypto")]
+    pub fn save_to_with_original_encryption<W: Write>(
+        &mut self, target: &mut W, owner_password: &str,
+    ) -> crate::Result<()> {
+        let security = self.original_security_handler(owner_password)?;
+        self.write_document(target, &SaveOptions::default(), Some(security), "")?;
+        Ok(())
+    }
+
+    /// Save PDF to an asynchronous target using the given options. The document is written to memory first, and
+    /// then to the target.
+    #[cfg(feature = "async")]
+    pub async fn save_async<W: tokio::io::AsyncWrite + Unpin>(
+        &mut self, target: &mut W, options: &SaveOptions,
+    ) -> Result<()> {
+        use tokio::io::AsyncWriteExt;
+
+        let mut buffer = Vec::new();
+        self.save_internal(&mut buffer, options)?;
+        target.write_all(&buffer).await?;
+        target.flush().await
     }
 
-    fn save_internal<W: Write>(&mut self, target: &mut W) -> Result<()> {
+    /// Write the document, returning the cross-reference entries of the written objects and the byte offset of
+    /// the cross-reference section.
+    pub(crate) fn save_internal<W: Write>(&mut self, target: &mut W, options: &SaveOptions) -> Result<(Xref, usize)> {
+        #[cfg(feature = "pubsec")]
+        if let Some(ref encryption) = options.public_key_encryption {
+            let security = crate::pubsec::security_handler_for_recipients(encryption)
+                .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err.to_string()))?;
+            let required_version = match encryption.algorithm {
+                EncryptionAlgorithm::Rc4 => "1.5",
+                EncryptionAlgorithm::Aes128 => "1.6",
+                EncryptionAlgorithm::Aes256 => "2.0",
+            };
+            return self.write_document(target, options, Some(security), required_version);
+        }
+        #[cfg(not(feature = "crypto"))]
+        let (security, required_version) = (None, "");
+        #[cfg(feature = "crypto")]
+        let (security, required_version) = match options.encryption {
+            Some(ref encryption) => {
+                let file_id = match self.file_id() {
+                    Some(file_id) => file_id.to_vec(),
+                    None => {
+                        let file_id = rand::random::<[u8; 16]>().to_vec();
+                        let id = Object::String(file_id.clone(), StringFormat::Hexadecimal);
+                        self.trailer.set("ID", vec![id.clone(), id]);
+                        file_id
+                    }
+                };
+                let required_version = match encryption.algorithm {
+                    EncryptionAlgorithm::Rc4 => "1.4",
+                    EncryptionAlgorithm::Aes128 => "1.6",
+                    EncryptionAlgorithm::Aes256 => "2.0",
+                };
+                (
+                    Some(SecurityHandler::for_encryption(encryption, &file_id)),
+                    required_version,
+                )
+            }
+            None => (None, ""),
+        };
+        self.write_document(target, options, security, required_version)
+    }
+
+    /// Write the document, encrypted by a security handler with the given encryption dictionary.
+    fn write_document<W: Write>(
+        &mut self, target: &mut W, options: &SaveOptions, security: Option<(SecurityHandler, Dictionary)>,
+        required_version: &str,
+    ) -> Result<(Xref, usize)> {
         let mut target = CountingWrite {
             inner: target,
             bytes_written: 0,
         };
         let mut xref = Xref::new(self.max_id + 1);
-        writeln!(target, "%PDF-{}", self.version)?;
+        let use_hybrid_xref = options.hybrid_xref && options.use_object_streams;
+        let use_xref_stream = (options.use_xref_stream || options.use_object_streams) && !use_hybrid_xref;
+        let mut version = self.version.as_str();
+        if (use_xref_stream || use_hybrid_xref) && version < "1.5" {
+            version = "1.5";
+        }
+        writeln!(target, "%PDF-{}", version.max(required_version))?;
+
+        let mut next_id = self.max_id + 1;
+        let security_handler = match security {
+            Some((handler, encrypt)) => {
+                Writer::write_indirect_object(&mut target, next_id, 0, &Object::Dictionary(encrypt), &mut xref)?;
+                self.trailer.set("Encrypt", (next_id, 0));
+                next_id += 1;
+                Some(handler)
+            }
+            None => None,
+        };
 
+        let encrypt_id = self.trailer.get(b"Encrypt").and_then(Object::as_reference).ok();
+        let mut compressible = Vec::new();
         for (&(id, generation), object) in &self.objects {
             if object
                 .type_name()
                 .map(|name| ["ObjStm", "XRef", "Linearized"].contains(&name))
                 .ok()
-                != Some(true)
+                == Some(true)
+            {
+                continue;
+            }
+            // Streams, objects with a non-zero generation and the encryption dictionary can't be compressed.
+            if options.use_object_streams
+                && generation == 0
+                && Some((id, generation)) != encrypt_id
+                && object.as_stream().is_err()
             {
-                Writer::write_indirect_object(&mut target, id, generation, object, &mut xref)?;
+                compressible.push((id, object));
+            } else {
+                let object = Writer::encrypted(security_handler.as_ref(), (id, generation), object);
+                Writer::write_indirect_object(&mut target, id, generation, &object, &mut xref)?;
             }
         }
 
+        for objects in compressible.chunks(options.max_objects_per_stream.max(1)) {
+            Writer::write_object_stream(&mut target, next_id, objects, security_handler.as_ref(), &mut xref)?;
+            next_id += 1;
+        }
+
+        if use_hybrid_xref {
+            let rows = xref
+                .entries
+                .iter()
+                .filter_map(|(id, entry)| match *entry {
+                    XrefEntry::Compressed { container, index } => Some((*id, (2, container, index))),
+                    _ => None,
+                })
+                .collect();
+            let stream_start = target.bytes_written;
+            let stream_id = next_id;
+            let dict = dictionary! { "Size" => i64::from(stream_id + 1) };
+            Writer::write_xref_stream(&mut target, stream_id, rows, &dict)?;
+            xref.insert(
+                stream_id,
+                XrefEntry::Normal {
+                    offset: stream_start as u32,
+                    generation: 0,
+                },
+            );
+            next_id += 1;
+            self.trailer.set("XRefStm", stream_start as i64);
+        }
+
         let xref_start = target.bytes_written;
-        Writer::write_xref(&mut target, &xref)?;
-        self.write_trailer(&mut target)?;
+        if use_xref_stream {
+            let xref_id = next_id;
+            self.trailer.set("Size", i64::from(xref_id + 1));
+            let mut rows = BTreeMap::new();
+            rows.insert(0, (0, 0, 65535));
+            for id in 1..xref_id {
+                let row = match xref.get(id) {
+                    Some(XrefEntry::Normal { offset, generation }) => (1, *offset, *generation),
+                    Some(XrefEntry::Compressed { container, index }) => (2, *container, *index),
+                    _ => (0, 0, 65535),
+                };
+                rows.insert(id, row);
+            }
+            Writer::write_xref_stream(&mut target, xref_id, rows, &self.trailer)?;
+        } else {
+            xref.size = next_id;
+            Writer::write_xref(&mut target, &xref)?;
+            self.write_trailer(&mut target, xref.size)?;
+        }
         write!(target, "\nstartxref\n{}\n%%EOF", xref_start)?;
 
-        Ok(())
+        self.trailer.remove(b"XRefStm");
+        // The document itself stays unencrypted.
+        if security_handler.is_some() {
+            self.trailer.remove(b"Encrypt");
+        }
+        Ok((xref, xref_start))
     }
 
-    fn write_trailer(&mut self, file: &mut dyn Write) -> Result<()> {
-        self.trailer.set("Size", i64::from(self.max_id + 1));
+    fn write_trailer(&mut self, file: &mut dyn Write, size: u32) -> Result<()> {
+        self.trailer.set("Size", i64::from(size));
         file.write_all(b"trailer\n")?;
         Writer::write_dictionary(file, &self.trailer)?;
         Ok(())
@@ -92,10 +328,9 @@ impl Writer {
 
         let mut obj_id = 1;
         while obj_id < xref.size {
-            if let Some(entry) = xref.get(obj_id) {
-                if let XrefEntry::Normal { offset, generation } = *entry {
-                    write_xref_entry(offset, generation, 'n')?;
-                };
+            // Compressed objects are listed as free in the table of hybrid-reference files.
+            if let Some(XrefEntry::Normal { offset, generation }) = xref.get(obj_id) {
+                write_xref_entry(*offset, *generation, 'n')?;
             } else {
                 write_xref_entry(0, 65535, 'f')?;
             }
@@ -104,7 +339,113 @@ impl Writer {
         Ok(())
     }
 
-    fn write_indirect_object<W: Write>(
+    /// Write cross-reference rows as a table, grouped into subsections of consecutive object numbers.
+    pub(crate) fn write_xref_section(file: &mut dyn Write, rows: &BTreeMap<u32, XrefRow>) -> Result<()> {
+        writeln!(file, "xref")?;
+        for (start, rows) in Writer::xref_subsections(rows) {
+            writeln!(file, "{} {}", start, rows.len())?;
+            for (kind, offset, generation) in rows {
+                let kind = if kind == 1 { 'n' } else { 'f' };
+                writeln!(file, "{:>010} {:>05} {} ", offset, generation, kind)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write cross-reference rows as a cross-reference stream object with the given id, which also carries
+    /// the trailer entries. The row of the stream object itself is added.
+    pub(crate) fn write_xref_stream<W: Write>(
+        file: &mut CountingWrite<&mut W>, id: u32, mut rows: BTreeMap<u32, XrefRow>, trailer: &Dictionary,
+    ) -> Result<()> {
+        rows.insert(id, (1, file.bytes_written as u32, 0));
+
+        let mut index = Vec::new();
+        let mut content = Vec::new();
+        for (start, rows) in Writer::xref_subsections(&rows) {
+            index.push(Integer(i64::from(start)));
+            index.push(Integer(rows.len() as i64));
+            for (kind, field2, field3) in rows {
+                content.push(kind);
+                content.extend_from_slice(&field2.to_be_bytes());
+                content.extend_from_slice(&field3.to_be_bytes());
+            }
+        }
+
+        let mut dict = trailer.clone();
+        dict.set("Type", "XRef");
+        dict.set("W", vec![Integer(1), Integer(4), Integer(2)]);
+        dict.set("Index", index);
+        dict.remove(b"Filter");
+        dict.remove(b"DecodeParms");
+        let mut stream = super::Stream::new(dict, content);
+        // Ignore any compression error.
+        let _ = stream.compress();
+
+        let mut xref = Xref::new(0);
+        Writer::write_indirect_object(file, id, 0, &Object::Stream(stream), &mut xref)
+    }
+
+    /// Write objects packed into a compressed object stream with the given id.
+    fn write_object_stream<W: Write>(
+        file: &mut CountingWrite<&mut W>, id: u32, objects: &[(u32, &Object)],
+        security_handler: Option<&SecurityHandler>, xref: &mut Xref,
+    ) -> Result<()> {
+        let mut offsets = Vec::new();
+        let mut content = Vec::new();
+        for (index, &(object_id, object)) in objects.iter().enumerate() {
+            write!(offsets, "{} {} ", object_id, content.len())?;
+            Writer::write_object(&mut content, object)?;
+            content.push(b'\n');
+            xref.insert(
+                object_id,
+                XrefEntry::Compressed {
+                    container: id,
+                    index: index as u16,
+                },
+            );
+        }
+
+        let first = offsets.len();
+        offsets.append(&mut content);
+        let dict = dictionary! {
+            "Type" => "ObjStm",
+            "N" => objects.len() as i64,
+            "First" => first as i64,
+        };
+        let mut stream = super::Stream::new(dict, offsets);
+        // Ignore any compression error.
+        let _ = stream.compress();
+        let stream = Writer::encrypted(security_handler, (id, 0), &Object::Stream(stream)).into_owned();
+        Writer::write_indirect_object(file, id, 0, &stream, xref)
+    }
+
+    /// Get an encrypted copy of an object if a security handler is given.
+    fn encrypted<'a>(
+        security_handler: Option<&SecurityHandler>, id: (u32, u16), object: &'a Object,
+    ) -> Cow<'a, Object> {
+        match security_handler {
+            Some(handler) => {
+                let mut object = object.clone();
+                handler.encrypt_object(id, &mut object);
+                Cow::Owned(object)
+            }
+            None => Cow::Borrowed(object),
+        }
+    }
+
+    /// Split rows into runs of consecutive object numbers.
+    fn xref_subsections(rows: &BTreeMap<u32, XrefRow>) -> Vec<(u32, Vec<XrefRow>)> {
+        let mut subsections: Vec<(u32, Vec<XrefRow>)> = Vec::new();
+        for (&id, &row) in rows {
+            match subsections.last_mut() {
+                Some((start, rows)) if *start + rows.len() as u32 == id => rows.push(row),
+                _ => subsections.push((id, vec![row])),
+            }
+        }
+        subsections
+    }
+
+    pub(crate) fn write_indirect_object<W: Write>(
         file: &mut CountingWrite<&mut W>, id: u32, generation: u16, object: &Object, xref: &mut Xref,
     ) -> Result<()> {
         let offset = file.bytes_written as u32;
@@ -139,7 +480,7 @@ impl Writer {
                 let _ = itoa::write(file, *value);
                 Ok(())
             }
-            Real(ref value) => file.write_all(format!("{:.02?}", *value).as_bytes()),
+            Real(ref value) => Writer::write_real(file, *value),
             Name(ref name) => Writer::write_name(file, name),
             String(ref text, ref format) => Writer::write_string(file, text, format),
             Array(ref array) => Writer::write_array(file, array),
@@ -149,6 +490,17 @@ impl Writer {
         }
     }
 
+    fn write_real(file: &mut dyn Write, value: f64) -> Result<()> {
+        // Readers don't accept exponents, so reals are written with up to six decimals, keeping one so that they
+        // are read back as reals.
+        let mut text = format!("{:.6}", value);
+        text.truncate(text.trim_end_matches('0').len());
+        if text.ends_with('.') {
+            text.push('0');
+        }
+        file.write_all(text.as_bytes())
+    }
+
     fn write_name(file: &mut dyn Write, name: &[u8]) -> Result<()> {
         file.write_all(b"/")?;
         for &byte in name {
@@ -230,7 +582,7 @@ impl Writer {
         Ok(())
     }
 
-    fn write_dictionary(file: &mut dyn Write, dictionary: &Dictionary) -> Result<()> {
+    pub(crate) fn write_dictionary(file: &mut dyn Write, dictionary: &Dictionary) -> Result<()> {
         file.write_all(b"<<")?;
         for (key, value) in dictionary {
             Writer::write_name(file, key)?;
@@ -253,8 +605,8 @@ impl Writer {
 }
 
 pub struct CountingWrite<W: Write> {
-    inner: W,
-    bytes_written: usize,
+    pub(crate) inner: W,
+    pub(crate) bytes_written: usize,
 }
 
 impl<W: Write> Write for CountingWrite<W> {
@@ -309,3 +661,96 @@ fn save_document() {
 
     doc.save("test_0_save.pdf").unwrap();
 }
+
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+#[test]
+fn save_and_load_reals() {
+    let values = [0.5, 1.0, -2.25, 0.123456, 1.0 / 3.0, 841.889764, 1234567.5];
+    let mut doc = Document::with_version("1.5");
+    let array_id = doc.add_object(values.iter().map(|&value| Real(value)).collect::<Vec<_>>());
+    let mut buffer = Vec::new();
+    doc.save_to(&mut buffer).unwrap();
+    let text = std::string::String::from_utf8_lossy(&buffer);
+    assert!(text.contains("[0.5 1.0 -2.25 0.123456 0.333333 841.889764 1234567.5]"));
+
+    let doc = Document::load_mem(&buffer).unwrap();
+    let loaded = doc.get_object(array_id).and_then(Object::as_array).unwrap();
+    for (value, loaded) in values.iter().zip(loaded) {
+        assert!(matches!(loaded, Real(_)));
+        assert!((value - loaded.as_float().unwrap()).abs() < 1e-6);
+    }
+}
+
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+#[test]
+fn save_with_xref_stream() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    let mut buffer = Vec::new();
+    let options = SaveOptions {
+        use_xref_stream: true,
+        ..SaveOptions::default()
+    };
+    doc.save_to_with_options(&mut buffer, &options).unwrap();
+    assert!(!buffer.windows(7).any(|window| window == b"trailer"));
+
+    let mut doc = Document::load_mem(&buffer).unwrap();
+    assert_eq!(doc.trailer.get(b"Type").and_then(Object::as_name_str).unwrap(), "XRef");
+    assert_eq!(doc.extract_text(&[1]).unwrap(), "Hello World!\n");
+
+    // Objects other than streams can be packed into object streams.
+    let options = SaveOptions {
+        use_object_streams: true,
+        max_objects_per_stream: 2,
+        ..SaveOptions::default()
+    };
+    let mut packed = Vec::new();
+    doc.save_to_with_options(&mut packed, &options).unwrap();
+    let packed = Document::load_mem(&packed).unwrap();
+    assert_eq!(packed.extract_text(&[1]).unwrap(), "Hello World!\n");
+    assert!(packed.reference_table.entries.values().any(XrefEntry::is_compressed));
+
+    // Incremental updates keep using cross-reference streams.
+    doc.replace_text(1, "Hello World!", "Updated").unwrap();
+    doc.save_incremental(&mut buffer).unwrap();
+    assert!(!buffer.windows(7).any(|window| window == b"trailer"));
+    let doc = Document::load_mem(&buffer).unwrap();
+    assert_eq!(doc.extract_text(&[1]).unwrap(), "Updated\n");
+}
+
+#[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+#[test]
+fn save_hybrid_reference_file() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    let options = SaveOptions {
+        use_object_streams: true,
+        hybrid_xref: true,
+        ..SaveOptions::default()
+    };
+    let mut buffer = Vec::new();
+    doc.save_to_with_options(&mut buffer, &options).unwrap();
+    assert!(buffer.windows(7).any(|window| window == b"trailer"));
+    assert!(!doc.trailer.has(b"XRefStm"));
+
+    // The compressed objects are free in the table, but found through the stream.
+    let loaded = Document::load_mem(&buffer).unwrap();
+    assert_eq!(loaded.extract_text(&[1]).unwrap(), "Hello World!\n");
+    assert!(loaded.reference_table.entries.values().any(XrefEntry::is_compressed));
+    assert!(!loaded.trailer.has(b"XRefStm"));
+    assert!(loaded.revisions()[0].deleted.is_empty());
+    let mut lazy = crate::lazy::LazyDocument::new(std::io::Cursor::new(buffer)).unwrap();
+    assert_eq!(lazy.page_count().unwrap(), 1);
+
+    // Saving again writes a single style.
+    let mut loaded = loaded;
+    let mut normalized = Vec::new();
+    loaded.save_to(&mut normalized).unwrap();
+    assert!(!normalized.windows(7).any(|window| window == b"XRefStm"));
+    assert_eq!(
+        Document::load_mem(&normalized).unwrap().extract_text(&[1]).unwrap(),
+        "Hello World!\n"
+    );
+}
@@ -0,0 +1,488 @@
+//! XML Forms Data Format, the XML counterpart of FDF, for exchanging form field values and annotations with
+//! review tools.
+
+use crate::annotations::{Annotation, AnnotationKind, Border, BorderStyle};
+use crate::encodings::{decode_text_string, encode_text_string};
+use crate::forms::{Field, FieldType};
+use crate::xmp::{element_text, escape};
+use crate::{Document, Error, Object, ObjectId, Rectangle, Result, StringFormat};
+
+const XFDF_NAMESPACE: &str = "http://ns.adobe.com/xfdf/";
+
+/// Element names of the annotation types in XFDF, with their subtypes.
+const ANNOTATION_ELEMENTS: [(&str, &str); 14] = [
+    ("text", "Text"),
+    ("freetext", "FreeText"),
+    ("square", "Square"),
+    ("circle", "Circle"),
+    ("highlight", "Highlight"),
+    ("underline", "Underline"),
+    ("strikeout", "StrikeOut"),
+    ("squiggly", "Squiggly"),
+    ("ink", "Ink"),
+    ("stamp", "Stamp"),
+    ("line", "Line"),
+    ("polygon", "Polygon"),
+    ("polyline", "PolyLine"),
+    ("caret", "Caret"),
+];
+
+/// Names of the annotation flags in XFDF, from the lowest bit on.
+const FLAG_NAMES: [&str; 10] = [
+    "invisible",
+    "hidden",
+    "print",
+    "nozoom",
+    "norotate",
+    "noview",
+    "readonly",
+    "locked",
+    "togglenoview",
+    "lockedcontents",
+];
+
+impl Document {
+    /// Export the values of the form fields and the markup annotations of all pages as an XFDF document.
+    ///
+    /// Widgets, links and pop-up windows aren't exported, and neither are appearance streams, which importing
+    /// regenerates.
+    pub fn export_xfdf(&self) -> Result<String> {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str(&format!("<xfdf xmlns=\"{}\" xml:space=\"preserve\">\n", XFDF_NAMESPACE));
+        if let Ok(form) = self.get_form() {
+            xml.push_str(" <fields>\n");
+            for field in &form.fields {
+                self.write_xfdf_field(&mut xml, field, 2);
+            }
+            xml.push_str(" </fields>\n");
+        }
+
+        xml.push_str(" <annots>\n");
+        for (index, page_id) in self.page_iter().enumerate() {
+            for annotation in self.get_page_annotations(page_id)? {
+                let element = match ANNOTATION_ELEMENTS
+                    .iter()
+                    .find(|&&(_, subtype)| subtype == annotation.subtype())
+                {
+                    Some(&(element, _)) => element,
+                    None => continue,
+                };
+                let dict = match annotation.id {
+                    Some(id) => self.get_dictionary(id)?,
+                    None => continue,
+                };
+                let rect = annotation.rect;
+                let mut attributes = vec![
+                    ("page", index.to_string()),
+                    ("rect", format!("{},{},{},{}", rect.llx, rect.lly, rect.urx, rect.ury)),
+                ];
+                let text = |key: &[u8]| dict.get(key).and_then(Object::as_str).map(decode_text_string).ok();
+                if let Some(name) = text(b"NM") {
+                    attributes.push(("name", name));
+                }
+                if let Some(ref author) = annotation.author {
+                    attributes.push(("title", author.clone()));
+                }
+                if let Some(date) = text(b"M") {
+                    attributes.push(("date", date));
+                }
+                if let Some(ref color) = annotation.color {
+                    attributes.push(("color", hex_color(color)));
+                }
+                let flags: Vec<&str> = FLAG_NAMES
+                    .iter()
+                    .enumerate()
+                    .filter(|&(bit, _)| annotation.flags & (1 << bit) != 0)
+                    .map(|(_, &name)| name)
+                    .collect();
+                if !flags.is_empty() {
+                    attributes.push(("flags", flags.join(",")));
+                }
+                if let Some(ref border) = annotation.border {
+                    attributes.push(("width", border.width.to_string()));
+                }
+                if let Ok(quad_points) = dict.get(b"QuadPoints").and_then(Object::as_array) {
+                    let coords: Vec<String> = quad_points
+                        .iter()
+                        .filter_map(|number| number.as_float().ok())
+                        .map(|number| number.to_string())
+                        .collect();
+                    attributes.push(("coords", coords.join(",")));
+                }
+                let mut children = String::new();
+                match annotation.kind {
+                    AnnotationKind::Text { open, ref icon } => {
+                        attributes.push(("open", if open { "yes" } else { "no" }.to_owned()));
+                        if let Some(icon) = icon {
+                            attributes.push(("icon", icon.clone()));
+                        }
+                    }
+                    AnnotationKind::FreeText { ref default_appearance } => {
+                        children.push_str(&format!(
+                            "   <defaultappearance>{}</defaultappearance>\n",
+                            escape(default_appearance)
+                        ));
+                    }
+                    AnnotationKind::Square { ref interior_color } | AnnotationKind::Circle { ref interior_color } => {
+                        if let Some(interior_color) = interior_color {
+                            attributes.push(("interior-color", hex_color(interior_color)));
+                        }
+                    }
+                    AnnotationKind::Ink { ref ink_list } => {
+                        children.push_str("   <inklist>\n");
+                        for stroke in ink_list {
+                            let points: Vec<String> = stroke
+                                .chunks_exact(2)
+                                .map(|point| format!("{},{}", point[0], point[1]))
+                                .collect();
+                            children.push_str(&format!("    <gesture>{}</gesture>\n", points.join(";")));
+                        }
+                        children.push_str("   </inklist>\n");
+                    }
+                    AnnotationKind::Stamp { ref name } => attributes.push(("icon", name.clone())),
+                    _ => {}
+                }
+                if let Some(ref contents) = annotation.contents {
+                    children.push_str(&format!("   <contents>{}</contents>\n", escape(contents)));
+                }
+
+                xml.push_str(&format!("  <{}", element));
+                for (name, value) in attributes {
+                    xml.push_str(&format!(" {}=\"{}\"", name, escape(&value)));
+                }
+                if children.is_empty() {
+                    xml.push_str("/>\n");
+                } else {
+                    xml.push_str(&format!(">\n{}  </{}>\n", children, element));
+                }
+            }
+        }
+        xml.push_str(" </annots>\n</xfdf>\n");
+        Ok(xml)
+    }
+
+    fn write_xfdf_field(&self, xml: &mut String, field: &Field, depth: usize) {
+        if let FieldType::PushButton | FieldType::Signature = field.field_type {
+            return;
+        }
+        let partial_name = self
+            .get_dictionary(field.id)
+            .and_then(|dict| dict.get(b"T"))
+            .and_then(Object::as_str)
+            .map(decode_text_string);
+        let partial_name = match partial_name {
+            Ok(partial_name) => partial_name,
+            Err(_) => return,
+        };
+        let indent = " ".repeat(depth);
+        xml.push_str(&format!("{}<field name=\"{}\">\n", indent, escape(&partial_name)));
+        if field.field_type == FieldType::NonTerminal {
+            for kid in &field.kids {
+                self.write_xfdf_field(xml, kid, depth + 1);
+            }
+        } else {
+            let values = match field.value {
+                Some(Object::Array(ref values)) => values.iter().collect(),
+                Some(ref value) => vec![value],
+                None => vec![],
+            };
+            for value in values {
+                let text = match *value {
+                    Object::String(ref text, _) => decode_text_string(text),
+                    Object::Name(ref name) => String::from_utf8_lossy(name).into_owned(),
+                    _ => continue,
+                };
+                xml.push_str(&format!("{} <value>{}</value>\n", indent, escape(&text)));
+            }
+        }
+        xml.push_str(&format!("{}</field>\n", indent));
+    }
+
+    /// Import the form field values and annotations of an XFDF document. Returns the fully qualified names of the
+    /// fields which the form doesn't have.
+    ///
+    /// Annotations replace those of the same name on their page. Appearance streams are generated for the
+    /// imported annotations, as well as for the fields whose values are set.
+    pub fn import_xfdf(&mut self, xfdf: &str) -> Result<Vec<String>> {
+        let xml = roxmltree::Document::parse(xfdf).map_err(|e| Error::Syntax(format!("Invalid XFDF: {}", e)))?;
+        let root = xml.root_element();
+        if root.tag_name().name() != "xfdf" {
+            return Err(Error::Syntax("Invalid XFDF: no xfdf element".to_owned()));
+        }
+
+        let mut values = Vec::new();
+        let mut pending: Vec<(String, roxmltree::Node)> = root
+            .children()
+            .filter(|node| node.tag_name().name() == "fields")
+            .flat_map(|fields| fields.children())
+            .filter(|node| node.tag_name().name() == "field")
+            .map(|node| (String::new(), node))
+            .collect();
+        pending.reverse();
+        while let Some((parent, node)) = pending.pop() {
+            let partial_name = node.attribute("name").unwrap_or("");
+            let name = if parent.is_empty() {
+                partial_name.to_owned()
+            } else {
+                format!("{}.{}", parent, partial_name)
+            };
+            let value = node
+                .children()
+                .find(|child| child.tag_name().name() == "value")
+                .map(element_text);
+            if let Some(value) = value {
+                values.push((name.clone(), value));
+            }
+            let kids: Vec<_> = node
+                .children()
+                .filter(|child| child.tag_name().name() == "field")
+                .collect();
+            pending.extend(kids.into_iter().rev().map(|kid| (name.clone(), kid)));
+        }
+        let missing = if values.is_empty() {
+            Vec::new()
+        } else {
+            self.set_field_texts(values)?
+        };
+
+        let pages = self.get_pages();
+        let annotations = root
+            .children()
+            .filter(|node| node.tag_name().name() == "annots")
+            .flat_map(|annots| annots.children())
+            .filter(roxmltree::Node::is_element);
+        for node in annotations {
+            let subtype = match ANNOTATION_ELEMENTS
+                .iter()
+                .find(|&&(element, _)| element == node.tag_name().name())
+            {
+                Some(&(_, subtype)) => subtype,
+                None => continue,
+            };
+            let page_number = node
+                .attribute("page")
+                .and_then(|page| page.parse::<u32>().ok())
+                .unwrap_or(0)
+                + 1;
+            let page_id = *pages.get(&page_number).ok_or(Error::PageNumberNotFound(page_number))?;
+            self.import_xfdf_annotation(page_id, subtype, node)?;
+        }
+        Ok(missing)
+    }
+
+    fn import_xfdf_annotation(&mut self, page_id: ObjectId, subtype: &str, node: roxmltree::Node) -> Result<()> {
+        let child_text = |name: &str| {
+            node.children()
+                .find(|child| child.tag_name().name() == name)
+                .map(element_text)
+        };
+        let numbers = |name: &str| {
+            node.attribute(name).map(|text| {
+                text.split(&[',', ';'][..])
+                    .filter_map(|number| number.trim().parse().ok())
+                    .collect::<Vec<f64>>()
+            })
+        };
+        let rect = match numbers("rect").as_deref() {
+            Some(&[llx, lly, urx, ury]) => Rectangle::new(llx, lly, urx, ury),
+            _ => return Err(Error::Syntax("Invalid XFDF: annotation without rect".to_owned())),
+        };
+        let kind = match subtype {
+            "Text" => AnnotationKind::Text {
+                open: node.attribute("open") == Some("yes"),
+                icon: node.attribute("icon").map(str::to_owned),
+            },
+            "FreeText" => AnnotationKind::FreeText {
+                default_appearance: child_text("defaultappearance").unwrap_or_else(|| "/Helv 12 Tf 0 g".to_owned()),
+            },
+            "Square" => AnnotationKind::Square {
+                interior_color: node.attribute("interior-color").and_then(parse_color),
+            },
+            "Circle" => AnnotationKind::Circle {
+                interior_color: node.attribute("interior-color").and_then(parse_color),
+            },
+            "Highlight" => AnnotationKind::Highlight {
+                quad_points: numbers("coords").unwrap_or_default(),
+            },
+            "Ink" => AnnotationKind::Ink {
+                ink_list: node
+                    .descendants()
+                    .filter(|child| child.tag_name().name() == "gesture")
+                    .map(|gesture| {
+                        element_text(gesture)
+                            .split(&[',', ';'][..])
+                            .filter_map(|number| number.trim().parse().ok())
+                            .collect()
+                    })
+                    .collect(),
+            },
+            "Stamp" => AnnotationKind::Stamp {
+                name: node.attribute("icon").unwrap_or("Draft").to_owned(),
+            },
+            _ => AnnotationKind::Other(subtype.to_owned()),
+        };
+        let mut annotation = Annotation::new(kind, rect);
+        annotation.flags = node.attribute("flags").map_or(0, |flags| {
+            flags
+                .split(',')
+                .filter_map(|flag| FLAG_NAMES.iter().position(|&name| name == flag.trim()))
+                .fold(0, |flags, bit| flags | 1 << bit)
+        });
+        annotation.contents = child_text("contents");
+        annotation.color = node.attribute("color").and_then(parse_color);
+        annotation.author = node.attribute("title").map(str::to_owned);
+        annotation.border = node
+            .attribute("width")
+            .and_then(|width| width.parse().ok())
+            .map(|width| Border::new(width, BorderStyle::Solid));
+
+        if let Some(name) = node.attribute("name") {
+            self.remove_named_annotation(page_id, name)?;
+        }
+        let annotation_id = self.add_annotation(page_id, &annotation)?;
+        let dict = self.get_object_mut(annotation_id).and_then(Object::as_dict_mut)?;
+        let text = |text: &str| Object::String(encode_text_string(text), StringFormat::Literal);
+        if let Some(name) = node.attribute("name") {
+            dict.set("NM", text(name));
+        }
+        if let Some(date) = node.attribute("date") {
+            dict.set("M", text(date));
+        }
+        if !dict.has(b"QuadPoints") {
+            if let Some(coords) = numbers("coords") {
+                dict.set("QuadPoints", coords.into_iter().map(Object::Real).collect::<Vec<_>>());
+            }
+        }
+        self.generate_annotation_appearance(annotation_id)
+    }
+
+    /// Remove the annotations of a page with the given name.
+    fn remove_named_annotation(&mut self, page_id: ObjectId, name: &str) -> Result<()> {
+        let named: Vec<ObjectId> = self
+            .get_page_annotations(page_id)?
+            .iter()
+            .filter_map(|annotation| annotation.id)
+            .filter(|&id| {
+                self.get_dictionary(id)
+                    .and_then(|dict| dict.get(b"NM"))
+                    .and_then(Object::as_str)
+                    .is_ok_and(|nm| decode_text_string(nm) == name)
+            })
+            .collect();
+        if named.is_empty() {
+            return Ok(());
+        }
+        let page = self.get_dictionary(page_id)?;
+        let annotations = match page.get(b"Annots").and_then(Object::as_reference) {
+            Ok(annotations_id) => self.get_object_mut(annotations_id)?,
+            Err(_) => self
+                .get_object_mut(page_id)
+                .and_then(Object::as_dict_mut)?
+                .get_mut(b"Annots")?,
+        };
+        annotations
+            .as_array_mut()?
+            .retain(|annotation| annotation.as_reference().map_or(true, |id| !named.contains(&id)));
+        for id in named {
+            self.objects.remove(&id);
+        }
+        Ok(())
+    }
+}
+
+/// Format color components as `#RRGGBB`, converting gray and CMYK to RGB.
+fn hex_color(components: &[f64]) -> String {
+    let rgb = match *components {
+        [gray] => [gray, gray, gray],
+        [c, m, y, k] => [(1.0 - c) * (1.0 - k), (1.0 - m) * (1.0 - k), (1.0 - y) * (1.0 - k)],
+        [r, g, b] => [r, g, b],
+        _ => [0.0, 0.0, 0.0],
+    };
+    let byte = |component: f64| (component.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!("#{:02X}{:02X}{:02X}", byte(rgb[0]), byte(rgb[1]), byte(rgb[2]))
+}
+
+/// Parse a `#RRGGBB` color into RGB components.
+fn parse_color(text: &str) -> Option<Vec<f64>> {
+    let hex = text.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    (0..3)
+        .map(|index| {
+            let byte = u8::from_str_radix(hex.get(index * 2..index * 2 + 2)?, 16).ok()?;
+            Some(f64::from(byte) / 255.0)
+        })
+        .collect()
+}
+
+#[test]
+fn export_and_import_xfdf() {
+    use crate::test_support::create_document;
+    use crate::forms::FieldBuilder;
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    let mut blank = doc.clone();
+    doc.add_field(&FieldBuilder::text("name", page_id, Rectangle::new(100.0, 700.0, 300.0, 720.0)).with_value("A & B"))
+        .unwrap();
+    blank
+        .add_field(&FieldBuilder::text(
+            "name",
+            page_id,
+            Rectangle::new(100.0, 700.0, 300.0, 720.0),
+        ))
+        .unwrap();
+    let square = Annotation::square(Rectangle::new(50.0, 50.0, 150.0, 100.0))
+        .with_color(vec![1.0, 0.0, 0.0])
+        .with_author("Jane")
+        .with_contents("Look <here>");
+    let square_id = doc.add_annotation(page_id, &square).unwrap();
+    doc.get_object_mut(square_id)
+        .and_then(Object::as_dict_mut)
+        .unwrap()
+        .set("NM", Object::string_literal("square-1"));
+    doc.add_annotation(page_id, &Annotation::ink(&[vec![(10.0, 10.0), (20.0, 30.0)]]))
+        .unwrap();
+    doc.add_annotation(
+        page_id,
+        &Annotation::highlight(&[Rectangle::new(100.0, 600.0, 200.0, 612.0)]),
+    )
+    .unwrap();
+    doc.add_link(
+        page_id,
+        Rectangle::new(0.0, 0.0, 10.0, 10.0),
+        crate::annotations::LinkTarget::Uri("https://example.com".to_owned()),
+    )
+    .unwrap();
+
+    let xfdf = doc.export_xfdf().unwrap();
+    assert!(xfdf.contains("<value>A &amp; B</value>"));
+    assert!(xfdf.contains("color=\"#FF0000\""));
+    assert!(!xfdf.contains("<link"));
+
+    // Importing twice replaces the named annotation.
+    assert!(blank.import_xfdf(&xfdf).unwrap().is_empty());
+    assert!(blank.import_xfdf(&xfdf).unwrap().is_empty());
+    let form = blank.get_form().unwrap();
+    assert_eq!(form.get_field("name").unwrap().text_value().unwrap(), "A & B");
+    let annotations = blank.get_page_annotations(page_id).unwrap();
+    let subtypes: Vec<&str> = annotations.iter().map(Annotation::subtype).collect();
+    assert_eq!(
+        subtypes,
+        vec!["Widget", "Ink", "Highlight", "Square", "Ink", "Highlight"]
+    );
+    let imported = annotations
+        .iter()
+        .find(|annotation| annotation.subtype() == "Square")
+        .unwrap();
+    assert_eq!(imported.kind, square.kind);
+    assert_eq!(imported.rect, square.rect);
+    assert_eq!(imported.contents, square.contents);
+    assert_eq!(imported.author, square.author);
+    assert_eq!(imported.color, Some(vec![1.0, 0.0, 0.0]));
+    assert_eq!(imported.flags, Annotation::PRINT);
+    for annotation in &annotations {
+        assert!(blank.get_dictionary(annotation.id.unwrap()).unwrap().has(b"AP"));
+    }
+}
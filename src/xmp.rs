@@ -0,0 +1,324 @@
+use crate::{Document, Error, Object, ObjectId, Result, Stream};
+use std::collections::BTreeMap;
+
+const RDF: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+const XML: &str = "http://www.w3.org/XML/1998/namespace";
+const DC: &str = "http://purl.org/dc/elements/1.1/";
+const PDF: &str = "http://ns.adobe.com/pdf/1.3/";
+const XMP: &str = "http://ns.adobe.com/xap/1.0/";
+const XMP_MM: &str = "http://ns.adobe.com/xap/1.0/mm/";
+const PDFA_ID: &str = "http://www.aiim.org/pdfa/ns/id/";
+
+/// Namespace prefixes declared by written packets.
+const NAMESPACES: [(&str, &str); 6] = [
+    ("rdf", RDF),
+    ("dc", DC),
+    ("pdf", PDF),
+    ("xmp", XMP),
+    ("xmpMM", XMP_MM),
+    ("pdfaid", PDFA_ID),
+];
+
+/// XMP metadata of a document, stored in the /Metadata stream of the catalog.
+///
+/// Common Dublin Core, Adobe PDF, XMP basic, media management and PDF/A identification properties are typed.
+/// Other properties are kept as XML and written back unchanged.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Xmp {
+    /// dc:title in the default language.
+    pub title: Option<String>,
+    /// dc:creator, the authors in order.
+    pub creators: Vec<String>,
+    /// dc:description in the default language.
+    pub description: Option<String>,
+    /// dc:subject, unordered keywords.
+    pub subjects: Vec<String>,
+    /// dc:rights in the default language.
+    pub rights: Option<String>,
+    /// dc:format, the MIME type, which is application/pdf.
+    pub format: Option<String>,
+    /// pdf:Producer
+    pub producer: Option<String>,
+    /// pdf:Keywords
+    pub keywords: Option<String>,
+    /// pdf:PDFVersion
+    pub pdf_version: Option<String>,
+    /// xmp:CreatorTool
+    pub creator_tool: Option<String>,
+    /// xmp:CreateDate as an ISO 8601 date.
+    pub create_date: Option<String>,
+    /// xmp:ModifyDate as an ISO 8601 date.
+    pub modify_date: Option<String>,
+    /// xmp:MetadataDate as an ISO 8601 date.
+    pub metadata_date: Option<String>,
+    /// xmpMM:DocumentID
+    pub document_id: Option<String>,
+    /// xmpMM:InstanceID
+    pub instance_id: Option<String>,
+    /// pdfaid:part, the part of ISO 19005 the document conforms to.
+    pub pdfa_part: Option<u32>,
+    /// pdfaid:conformance, the conformance level such as B.
+    pub pdfa_conformance: Option<String>,
+    /// Namespace URIs by prefix, used by other properties.
+    pub namespaces: BTreeMap<String, String>,
+    /// Other properties as XML elements.
+    pub other_properties: Vec<String>,
+}
+
+impl Xmp {
+    pub fn new() -> Xmp {
+        Xmp::default()
+    }
+
+    /// Parse the properties of the rdf:Description elements of an XMP packet.
+    pub fn parse(packet: &[u8]) -> Result<Xmp> {
+        let text = std::str::from_utf8(packet).map_err(|_| Error::UTF8)?;
+        // The xpacket processing instruction may start with a byte order mark.
+        let text = text.trim_start_matches('\u{FEFF}');
+        let xml = roxmltree::Document::parse(text).map_err(|e| Error::Syntax(format!("Invalid XMP packet: {}", e)))?;
+        let mut xmp = Xmp::new();
+        let descriptions = xml.descendants().filter(|node| node.has_tag_name((RDF, "Description")));
+        for description in descriptions {
+            for attribute in description.attributes() {
+                let namespace = attribute.namespace().unwrap_or("");
+                if namespace == RDF || namespace == XML || namespace.is_empty() {
+                    continue;
+                }
+                if !xmp.set_property(namespace, attribute.name(), vec![attribute.value().to_string()]) {
+                    let prefix = description.lookup_prefix(namespace).unwrap_or("ns");
+                    xmp.namespaces.insert(prefix.to_string(), namespace.to_string());
+                    xmp.other_properties.push(format!(
+                        "<{0}:{1}>{2}</{0}:{1}>",
+                        prefix,
+                        attribute.name(),
+                        escape(attribute.value())
+                    ));
+                }
+            }
+            for property in description.children().filter(roxmltree::Node::is_element) {
+                let namespace = property.tag_name().namespace().unwrap_or("");
+                if !xmp.set_property(namespace, property.tag_name().name(), property_values(property)) {
+                    // Declarations of the namespaces used by the property, which may be on its ancestors.
+                    for node in property.descendants().filter(roxmltree::Node::is_element) {
+                        let attributes = node.attributes().filter_map(|attribute| attribute.namespace());
+                        for namespace in node.tag_name().namespace().into_iter().chain(attributes) {
+                            if let Some(prefix) = node
+                                .lookup_prefix(namespace)
+                                .filter(|prefix| !prefix.is_empty() && namespace != XML)
+                            {
+                                xmp.namespaces.insert(prefix.to_string(), namespace.to_string());
+                            }
+                        }
+                    }
+                    xmp.other_properties.push(text[property.range()].to_string());
+                }
+            }
+        }
+        Ok(xmp)
+    }
+
+    /// Set a typed property, returning false for other properties.
+    fn set_property(&mut self, namespace: &str, name: &str, values: Vec<String>) -> bool {
+        let first = values.first().cloned();
+        match (namespace, name) {
+            (DC, "title") => self.title = first,
+            (DC, "creator") => self.creators = values,
+            (DC, "description") => self.description = first,
+            (DC, "subject") => self.subjects = values,
+            (DC, "rights") => self.rights = first,
+            (DC, "format") => self.format = first,
+            (PDF, "Producer") => self.producer = first,
+            (PDF, "Keywords") => self.keywords = first,
+            (PDF, "PDFVersion") => self.pdf_version = first,
+            (XMP, "CreatorTool") => self.creator_tool = first,
+            (XMP, "CreateDate") => self.create_date = first,
+            (XMP, "ModifyDate") => self.modify_date = first,
+            (XMP, "MetadataDate") => self.metadata_date = first,
+            (XMP_MM, "DocumentID") => self.document_id = first,
+            (XMP_MM, "InstanceID") => self.instance_id = first,
+            (PDFA_ID, "part") => self.pdfa_part = first.and_then(|part| part.trim().parse().ok()),
+            (PDFA_ID, "conformance") => self.pdfa_conformance = first,
+            _ => return false,
+        }
+        true
+    }
+
+    /// Serialize the metadata as an XMP packet, padded so that it can be edited in place.
+    pub fn to_packet(&self) -> Vec<u8> {
+        let mut properties = String::new();
+        let mut simple = |name: &str, value: &Option<String>| {
+            if let Some(value) = value {
+                properties.push_str(&format!("   <{0}>{1}</{0}>\n", name, escape(value)));
+            }
+        };
+        simple("dc:format", &self.format);
+        simple("pdf:Producer", &self.producer);
+        simple("pdf:Keywords", &self.keywords);
+        simple("pdf:PDFVersion", &self.pdf_version);
+        simple("xmp:CreatorTool", &self.creator_tool);
+        simple("xmp:CreateDate", &self.create_date);
+        simple("xmp:ModifyDate", &self.modify_date);
+        simple("xmp:MetadataDate", &self.metadata_date);
+        simple("xmpMM:DocumentID", &self.document_id);
+        simple("xmpMM:InstanceID", &self.instance_id);
+        simple("pdfaid:part", &self.pdfa_part.map(|part| part.to_string()));
+        simple("pdfaid:conformance", &self.pdfa_conformance);
+        let mut array = |name: &str, kind: &str, values: &[String], language: bool| {
+            if values.is_empty() {
+                return;
+            }
+            properties.push_str(&format!("   <{}>\n    <rdf:{}>\n", name, kind));
+            for value in values {
+                let attribute = if language { " xml:lang=\"x-default\"" } else { "" };
+                properties.push_str(&format!("     <rdf:li{}>{}</rdf:li>\n", attribute, escape(value)));
+            }
+            properties.push_str(&format!("    </rdf:{}>\n   </{}>\n", kind, name));
+        };
+        let alternative = |value: &Option<String>| value.iter().cloned().collect::<Vec<_>>();
+        array("dc:title", "Alt", &alternative(&self.title), true);
+        array("dc:creator", "Seq", &self.creators, false);
+        array("dc:description", "Alt", &alternative(&self.description), true);
+        array("dc:subject", "Bag", &self.subjects, false);
+        array("dc:rights", "Alt", &alternative(&self.rights), true);
+        for property in &self.other_properties {
+            properties.push_str(&format!("   {}\n", property));
+        }
+
+        let mut declarations = String::new();
+        let known = NAMESPACES.iter().map(|&(prefix, uri)| (prefix, uri));
+        let other = self
+            .namespaces
+            .iter()
+            .filter(|(prefix, _)| NAMESPACES.iter().all(|(known, _)| known != prefix))
+            .map(|(prefix, uri)| (prefix.as_str(), uri.as_str()));
+        for (prefix, uri) in known.chain(other) {
+            declarations.push_str(&format!("\n    xmlns:{}=\"{}\"", prefix, escape(uri)));
+        }
+
+        let mut packet = String::from("<?xpacket begin=\"\u{FEFF}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n");
+        packet.push_str("<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n");
+        packet.push_str(&format!(" <rdf:RDF xmlns:rdf=\"{}\">\n", RDF));
+        packet.push_str(&format!("  <rdf:Description rdf:about=\"\"{}>\n", declarations));
+        packet.push_str(&properties);
+        packet.push_str("  </rdf:Description>\n </rdf:RDF>\n</x:xmpmeta>\n");
+        for _ in 0..20 {
+            packet.push_str(&" ".repeat(99));
+            packet.push('\n');
+        }
+        packet.push_str("<?xpacket end=\"w\"?>");
+        packet.into_bytes()
+    }
+}
+
+/// Get the values of a simple property or the items of an rdf:Alt, rdf:Bag or rdf:Seq array, with the default
+/// language first.
+fn property_values(property: roxmltree::Node) -> Vec<String> {
+    let array = property
+        .children()
+        .find(|node| node.is_element() && node.tag_name().namespace() == Some(RDF));
+    match array {
+        Some(array) => {
+            let mut items: Vec<roxmltree::Node> =
+                array.children().filter(|node| node.has_tag_name((RDF, "li"))).collect();
+            if let Some(index) = items
+                .iter()
+                .position(|item| item.attribute((XML, "lang")) == Some("x-default"))
+            {
+                let item = items.remove(index);
+                items.insert(0, item);
+            }
+            items.iter().map(|item| element_text(*item)).collect()
+        }
+        None => vec![element_text(property)],
+    }
+}
+
+pub(crate) fn element_text(node: roxmltree::Node) -> String {
+    node.descendants()
+        .filter(roxmltree::Node::is_text)
+        .filter_map(|node| node.text())
+        .collect()
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl Document {
+    /// Get the XMP metadata of the catalog, if the document has it.
+    pub fn get_xmp(&self) -> Result<Option<Xmp>> {
+        let metadata = match self.catalog()?.get(b"Metadata") {
+            Ok(metadata) => self.dereference(metadata)?.1.as_stream()?,
+            Err(_) => return Ok(None),
+        };
+        let packet = metadata
+            .decompressed_content()
+            .unwrap_or_else(|_| metadata.content.clone());
+        Xmp::parse(&packet).map(Some)
+    }
+
+    /// Write XMP metadata to the /Metadata stream of the catalog, replacing the stream if it exists.
+    ///
+    /// The stream isn't compressed, so that the metadata can be found by tools which don't read PDF files.
+    pub fn set_xmp(&mut self, xmp: &Xmp) -> Result<ObjectId> {
+        let catalog_id = self.trailer.get(b"Root").and_then(Object::as_reference)?;
+        let mut stream = Stream::new(
+            dictionary! {
+                "Type" => "Metadata",
+                "Subtype" => "XML",
+            },
+            xmp.to_packet(),
+        );
+        stream.allows_compression = false;
+        let metadata_id = match self.catalog()?.get(b"Metadata") {
+            Ok(&Object::Reference(id)) if self.get_object(id).and_then(Object::as_stream).is_ok() => id,
+            _ => self.new_object_id(),
+        };
+        self.objects.insert(metadata_id, Object::Stream(stream));
+        self.get_object_mut(catalog_id)
+            .and_then(Object::as_dict_mut)?
+            .set("Metadata", metadata_id);
+        Ok(metadata_id)
+    }
+}
+
+#[test]
+fn read_and_write_xmp() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    assert_eq!(doc.get_xmp().unwrap(), None);
+    let packet = "\u{FEFF}<?xpacket begin=\"\u{FEFF}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\
+        <x:xmpmeta xmlns:x=\"adobe:ns:meta/\"><rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\
+        <rdf:Description rdf:about=\"\" xmlns:pdf=\"http://ns.adobe.com/pdf/1.3/\" pdf:Producer=\"Test &amp; Co\" \
+        xmlns:custom=\"http://example.com/custom/\" custom:Simple=\"value\"/>\
+        <rdf:Description rdf:about=\"\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\
+        <dc:title><rdf:Alt><rdf:li xml:lang=\"de\">Titel</rdf:li><rdf:li xml:lang=\"x-default\">Title</rdf:li>\
+        </rdf:Alt></dc:title><dc:creator><rdf:Seq><rdf:li>One</rdf:li><rdf:li>Two</rdf:li></rdf:Seq></dc:creator>\
+        <custom:Nested xmlns:custom=\"http://example.com/custom/\"><rdf:Bag><rdf:li>a</rdf:li></rdf:Bag>\
+        </custom:Nested></rdf:Description></rdf:RDF></x:xmpmeta><?xpacket end=\"w\"?>";
+    let xmp = Xmp::parse(packet.as_bytes()).unwrap();
+    assert_eq!(xmp.title.as_deref(), Some("Title"));
+    assert_eq!(xmp.creators, vec!["One", "Two"]);
+    assert_eq!(xmp.producer.as_deref(), Some("Test & Co"));
+    assert_eq!(xmp.namespaces["custom"], "http://example.com/custom/");
+    assert_eq!(xmp.other_properties.len(), 2);
+    assert_eq!(xmp.other_properties[0], "<custom:Simple>value</custom:Simple>");
+
+    let mut xmp = Xmp {
+        pdfa_part: Some(2),
+        pdfa_conformance: Some("B".to_string()),
+        ..xmp
+    };
+    let metadata_id = doc.set_xmp(&xmp).unwrap();
+    assert_eq!(doc.get_xmp().unwrap().as_ref(), Some(&xmp));
+    let stream = doc.get_object(metadata_id).and_then(Object::as_stream).unwrap();
+    assert!(stream.content.starts_with(b"<?xpacket begin="));
+
+    xmp.title = Some("Other <title>".to_string());
+    assert_eq!(doc.set_xmp(&xmp).unwrap(), metadata_id);
+    assert_eq!(doc.get_xmp().unwrap().unwrap().title.as_deref(), Some("Other <title>"));
+}
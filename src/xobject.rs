@@ -1,3 +1,4 @@
+use crate::page::rotation_matrix;
 use crate::*;
 use crate::{Dictionary, Stream};
 
@@ -25,6 +26,40 @@ pub fn form(boundingbox: Vec<f64>, matrix: Vec<f64>, content: Vec<u8>) -> Stream
     xobject
 }
 
+impl Document {
+    /// Create a form XObject drawing a page as it is displayed, to be reused for imposition, thumbnails and
+    /// overlays. Returns the id of the form.
+    ///
+    /// The form bundles the content streams and the resources of the page, including inherited ones. Its
+    /// bounding box is the crop box of the page, and its matrix rotates the page by /Rotate and moves the
+    /// lower-left corner of the rotated crop box to the origin. Annotations of the page aren't included.
+    pub fn page_to_xobject(&mut self, page_id: ObjectId) -> Result<ObjectId> {
+        let crop_box = self.get_page_box(page_id, PageBox::CropBox)?;
+        let rotation = self.get_page_rotation(page_id);
+        let matrix = rotation_matrix(rotation, &crop_box);
+        let rotated = matrix.transform_rect(&crop_box);
+        let matrix = matrix.then(&Matrix::translate(0.0 - rotated.llx, 0.0 - rotated.lly));
+
+        let mut content = Vec::new();
+        for content_id in self.get_page_contents(page_id) {
+            if let Ok(stream) = self.get_object(content_id).and_then(Object::as_stream) {
+                content.extend(stream.decompressed_content().unwrap_or_else(|_| stream.content.clone()));
+                content.push(b'\n');
+            }
+        }
+        let bbox = vec![crop_box.llx, crop_box.lly, crop_box.urx, crop_box.ury];
+        let mut form = form(bbox, matrix.to_array().to_vec(), content);
+        if let Some(resources) = self.get_page_attribute(page_id, b"Resources") {
+            form.dict.set("Resources", resources.clone());
+        }
+        // The transparency group of the page applies to the form as a whole.
+        if let Ok(group) = self.get_dictionary(page_id)?.get(b"Group") {
+            form.dict.set("Group", group.clone());
+        }
+        Ok(self.add_object(form))
+    }
+}
+
 #[cfg(feature = "embed_image")]
 pub fn image<P: AsRef<Path>>(path: P) -> Result<Stream> {
     use std::fs::File;
@@ -69,13 +104,7 @@ pub fn image_from(buffer: Vec<u8>) -> Result<Stream> {
     dict.set("ColorSpace", Object::Name(color_space));
     dict.set("BitsPerComponent", bits);
 
-    let is_jpeg = match image::guess_format(buffer.as_ref()) {
-        Ok(format) => match format {
-            ImageFormat::Jpeg => true,
-            _ => false,
-        },
-        Err(_) => false,
-    };
+    let is_jpeg = matches!(image::guess_format(buffer.as_ref()), Ok(ImageFormat::Jpeg));
 
     if is_jpeg {
         dict.set("Filter", Object::Name(b"DCTDecode".to_vec()));
@@ -92,10 +121,40 @@ pub fn image_from(buffer: Vec<u8>) -> Result<Stream> {
 #[test]
 fn insert_image() {
     use super::xobject;
+    use crate::images::{ImageOptions, ImageSource};
     let mut doc = Document::load("assets/example.pdf").unwrap();
     let pages = doc.get_pages();
     let page_id = *pages.get(&1).expect(&format!("Page {} not exist.", 1));
     let img = xobject::image("assets/pdf_icon.jpg").unwrap();
-    doc.insert_image(page_id, img, (100.0, 210.0), (400.0, 225.0)).unwrap();
+    let rect = Rectangle::new(100.0, 210.0, 500.0, 435.0);
+    doc.insert_image(page_id, ImageSource::XObject(img), rect, &ImageOptions::default())
+        .unwrap();
     doc.save("test_5_image.pdf").unwrap();
 }
+
+#[test]
+fn page_to_form_xobject() {
+    use crate::test_support::create_document;
+
+    let mut doc = create_document();
+    let page_id = doc.page_iter().next().unwrap();
+    doc.set_page_box(page_id, PageBox::CropBox, Rectangle::new(10.0, 20.0, 310.0, 420.0))
+        .unwrap();
+    doc.rotate_page(page_id, 90).unwrap();
+    let form_id = doc.page_to_xobject(page_id).unwrap();
+
+    let form = doc.get_object(form_id).and_then(Object::as_stream).unwrap();
+    assert_eq!(form.dict.get(b"Subtype").and_then(Object::as_name_str).unwrap(), "Form");
+    let bbox = form.dict.get(b"BBox").and_then(Rectangle::from_object).unwrap();
+    assert_eq!(bbox, Rectangle::new(10.0, 20.0, 310.0, 420.0));
+    let matrix = form.dict.get(b"Matrix").and_then(Matrix::from_object).unwrap();
+    assert_eq!(matrix.transform_rect(&bbox), Rectangle::new(0.0, 0.0, 400.0, 300.0));
+    assert!(form
+        .dict
+        .get(b"Resources")
+        .and_then(Object::as_dict)
+        .unwrap()
+        .has(b"Font"));
+    let content = form.decompressed_content().unwrap_or_else(|_| form.content.clone());
+    assert!(content.windows(17).any(|window| window == b"(Hello World!) Tj"));
+}
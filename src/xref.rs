@@ -38,6 +38,20 @@ impl Xref {
         }
     }
 
+    /// Merge the entries of the cross-reference stream of a hybrid-reference file into its table section. The
+    /// stream lists the objects in object streams, which the table marks as free.
+    #[cfg(any(feature = "pom_parser", feature = "nom_parser"))]
+    pub(crate) fn merge_hybrid_stream(&mut self, stream: Xref) {
+        self.size = self.size.max(stream.size);
+        for (id, entry) in stream.entries {
+            if let XrefEntry::Free = entry {
+                self.entries.entry(id).or_insert(entry);
+            } else {
+                self.entries.insert(id, entry);
+            }
+        }
+    }
+
     pub fn clear(&mut self) {
         self.entries.clear()
     }